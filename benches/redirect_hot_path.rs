@@ -6,7 +6,7 @@ use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use dashmap::DashMap;
 use divan::{black_box, Bencher};
-use lynx::models::ShortenedUrl;
+use lynx::models::{CreatedVia, LinkOptions, ShortenedUrl};
 use lynx::storage::{LookupMetadata, LookupResult};
 use tokio::sync::mpsc;
 
@@ -35,8 +35,17 @@ static CACHED_PROJECTION: LazyLock<Arc<BenchmarkCachedProjection>> = LazyLock::n
             original_url: SHORT_DESTINATION.to_owned(),
             created_at: 0,
             created_by: None,
+            created_by_auth_method: None,
             clicks: 0,
             is_active: true,
+            reserved_until: None,
+            alias_of: None,
+            title: None,
+            created_via: CreatedVia::Unknown,
+            last_visited_at: None,
+            updated_at: 0,
+            options: LinkOptions::default(),
+            campaign_id: None,
         }),
         location: (*SHORT_LOCATION).clone(),
         analytics_code: Arc::clone(&*SHARED_SHORT_CODE),
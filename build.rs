@@ -49,9 +49,10 @@ fn main() {
 
     fs::write(dest_path, content).expect("failed to write required successes lookup table");
 
-    // Build and bundle frontend if it exists
+    // Build and bundle frontend if it exists and the binary embeds it
     let frontend_dir = PathBuf::from("frontend");
-    if frontend_dir.exists() && frontend_dir.join("package.json").exists() {
+    let embeds_frontend = env::var_os("CARGO_FEATURE_EMBEDDED_FRONTEND").is_some();
+    if embeds_frontend && frontend_dir.exists() && frontend_dir.join("package.json").exists() {
         println!("cargo:rerun-if-changed=frontend/src");
         println!("cargo:rerun-if-changed=frontend/package.json");
         println!("cargo:rerun-if-changed=frontend/vite.config.ts");
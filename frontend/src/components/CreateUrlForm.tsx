@@ -40,7 +40,7 @@ const CreateUrlForm: React.FC<CreateUrlFormProps> = ({ onUrlCreated }) => {
                 custom_code: customCode || undefined,
             };
             const result = await apiClient.createUrl(request);
-            const fullLink = buildShortLink(result.short_code, result.redirect_base_url);
+            const fullLink = result.short_url ?? buildShortLink(result.short_code, result.redirect_base_url);
             setCreated(result);
             setSuccessLink(fullLink);
             setUrl('');
@@ -152,6 +152,11 @@ const CreateUrlForm: React.FC<CreateUrlFormProps> = ({ onUrlCreated }) => {
                         </span>
                     )}
                 </div>
+                {created?.warnings?.map((warning) => (
+                    <Alert key={warning.code} tone="info" className="mt-3">
+                        {warning.message}
+                    </Alert>
+                ))}
             </Dialog>
         </Card>
     );
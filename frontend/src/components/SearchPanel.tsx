@@ -20,17 +20,17 @@ interface SearchPanelProps {
     isAdmin: boolean;
 }
 
-const toStartOfDayUnix = (value: string): number | undefined => {
+const toStartOfDayMillis = (value: string): number | undefined => {
     if (!value) return undefined;
     const ms = new Date(`${value}T00:00:00`).getTime();
-    return Number.isNaN(ms) ? undefined : Math.floor(ms / 1000);
+    return Number.isNaN(ms) ? undefined : ms;
 };
 
-const toEndOfDayUnix = (value: string): number | undefined => {
+const toEndOfDayMillis = (value: string): number | undefined => {
     if (!value) return undefined;
     // Exclusive upper bound: midnight of the following day.
     const ms = new Date(`${value}T00:00:00`).getTime() + 24 * 60 * 60 * 1000;
-    return Number.isNaN(ms) ? undefined : Math.floor(ms / 1000);
+    return Number.isNaN(ms) ? undefined : ms;
 };
 
 const SearchPanel: React.FC<SearchPanelProps> = ({
@@ -53,8 +53,8 @@ const SearchPanel: React.FC<SearchPanelProps> = ({
         (q: string): SearchFilters => ({
             q,
             created_by: createdBy.trim() || undefined,
-            created_from: toStartOfDayUnix(createdFrom),
-            created_to: toEndOfDayUnix(createdTo),
+            created_from: toStartOfDayMillis(createdFrom),
+            created_to: toEndOfDayMillis(createdTo),
             is_active: status === 'all' ? undefined : status === 'active',
         }),
         [createdBy, createdFrom, createdTo, status],
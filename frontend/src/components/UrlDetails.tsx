@@ -25,7 +25,7 @@ import {
 import { apiClient } from '../api';
 import type { AnalyticsAggregate, AnalyticsEntry, ShortenedUrl, UrlHistoryEntry } from '../types';
 import { buildShortLink, decodeShortCodeFromApi } from '../utils/url';
-import { formatDate } from '../utils/date';
+import { formatDate, formatDateMillis } from '../utils/date';
 import { extractErrorMessage } from '../utils/errorHandling';
 import { useTheme } from '../hooks/useTheme';
 import { AppHeader } from './layout/AppHeader';
@@ -319,7 +319,7 @@ const UrlDetails: React.FC = () => {
         const accounted = aggregateStats.reduce((sum, s) => sum + s.visit_count, 0);
         const unaccounted = totalClicks - accounted;
         if (unaccounted > 0) {
-            return [...aggregateStats, { dimension: 'Other', visit_count: unaccounted }];
+            return [...aggregateStats, { dimension: 'Other', visit_count: unaccounted, aggregated: false }];
         }
         return aggregateStats;
     }, [aggregateStats, totalClicks]);
@@ -338,7 +338,8 @@ const UrlDetails: React.FC = () => {
     );
 
     const shortLink = useMemo(
-        () => (url ? buildShortLink(url.short_code, url.redirect_base_url) : null),
+        () =>
+            url ? (url.short_url ?? buildShortLink(url.short_code, url.redirect_base_url)) : null,
         [url],
     );
 
@@ -486,7 +487,7 @@ const UrlDetails: React.FC = () => {
                                         label="Created"
                                         value={
                                             <span className="text-sm font-medium leading-snug text-fg sm:text-base">
-                                                {formatDate(url.created_at)}
+                                                {formatDateMillis(url.created_at)}
                                             </span>
                                         }
                                         icon={<CalendarDays className="h-5 w-5" />}
@@ -5,7 +5,7 @@ import { BarChart3, ExternalLink, PowerOff, RotateCcw } from 'lucide-react';
 import { apiClient } from '../api';
 import type { ShortenedUrl } from '../types';
 import { buildShortLink, encodeShortCodeForApi } from '../utils/url';
-import { formatDate } from '../utils/date';
+import { formatDateMillis } from '../utils/date';
 import { extractErrorMessage } from '../utils/errorHandling';
 import { cn } from '../lib/cn';
 import { Badge } from './ui/Badge';
@@ -67,7 +67,8 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
         }
     };
 
-    const linkFor = (item: ShortenedUrl) => buildShortLink(item.short_code, item.redirect_base_url);
+    const linkFor = (item: ShortenedUrl) =>
+        item.short_url ?? buildShortLink(item.short_code, item.redirect_base_url);
 
     return (
         <div ref={listRef} className="space-y-3 sm:space-y-4">
@@ -79,7 +80,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                     const busy = actionInProgress === url.short_code;
                     return (
                         <div
-                            key={url.id}
+                            key={url.short_code}
                             className="space-y-4 rounded-2xl border border-border bg-surface p-4 shadow-soft"
                         >
                             <div className="flex items-start justify-between gap-3">
@@ -105,7 +106,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                                     title={url.original_url}
                                     className="inline-flex max-w-full items-start gap-1.5 break-all text-sm text-fg-muted hover:text-fg hover:underline"
                                 >
-                                    <span className="break-all">{url.original_url}</span>
+                                    <span className="break-all">{url.title || url.original_url}</span>
                                     <ExternalLink className="mt-0.5 h-3.5 w-3.5 shrink-0 opacity-60" />
                                 </a>
                             </div>
@@ -124,7 +125,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                                         Created
                                     </p>
                                     <p className="text-sm font-medium text-fg-muted">
-                                        {formatDate(url.created_at)}
+                                        {formatDateMillis(url.created_at)}
                                     </p>
                                 </div>
                                 {isAdmin && (
@@ -218,7 +219,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                             const busy = actionInProgress === url.short_code;
                             return (
                                 <tr
-                                    key={url.id}
+                                    key={url.short_code}
                                     data-index={virtualRow.index}
                                     ref={rowVirtualizer.measureElement}
                                     className="border-b border-border/60 transition-colors hover:bg-surface-2/50"
@@ -239,7 +240,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                                             title={url.original_url}
                                             className="inline-flex max-w-full items-center gap-1.5 truncate text-fg-muted hover:text-fg hover:underline"
                                         >
-                                            <span className="truncate">{url.original_url}</span>
+                                            <span className="truncate">{url.title || url.original_url}</span>
                                             <ExternalLink className="h-3.5 w-3.5 shrink-0 opacity-60" />
                                         </a>
                                     </TD>
@@ -251,7 +252,7 @@ const UrlList: React.FC<UrlListProps> = ({ urls, isAdmin, onUrlsChanged }) => {
                                             {url.is_active ? 'Active' : 'Inactive'}
                                         </Badge>
                                     </TD>
-                                    <TD className="whitespace-nowrap text-fg-muted">{formatDate(url.created_at)}</TD>
+                                    <TD className="whitespace-nowrap text-fg-muted">{formatDateMillis(url.created_at)}</TD>
                                     {isAdmin && (
                                         <TD className="whitespace-nowrap text-fg-muted">{url.created_by || '—'}</TD>
                                     )}
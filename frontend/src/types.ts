@@ -1,18 +1,49 @@
 export interface ShortenedUrl {
-  id: number;
   short_code: string;
   original_url: string;
+  /** Milliseconds since the Unix epoch */
   created_at: number;
   created_by: string | null;
+  /** Auth method of the creating account, e.g. oauth or cloudflare */
+  created_by_auth_method?: string | null;
+  /** Creator's email, included in admin listings */
+  created_by_email?: string | null;
   clicks: number;
   is_active: boolean;
+  /** When an unused reservation lapses, in epoch milliseconds */
+  reserved_until: number | null;
+  /** Destination page title, filled in after creation when title fetching is enabled */
+  title?: string | null;
+  /** How the link was created: api, cli, bookmarklet, import, integration or unknown */
+  created_via?: string;
+  /** When the destination, owner or active state last changed, in epoch milliseconds */
+  updated_at?: number;
+  /** When a visit was last counted, in epoch milliseconds (precise to the click flush interval) */
+  last_visited_at?: number | null;
+  /** Hide clicks from viewers other than the owner and admins; null follows the instance default */
+  hide_stats?: boolean | null;
+  /** Redirects show a countdown page before the destination */
+  interstitial?: boolean | null;
+  /** Clicks were cleared because the link hides them from you */
+  stats_hidden?: boolean;
   redirect_base_url?: string | null;
+  short_url?: string | null;
+  /** Non-fatal notices about the request, such as a nearly used up quota */
+  warnings?: ApiWarning[];
+}
+
+export interface ApiWarning {
+  /** e.g. quota_nearly_reached */
+  code: string;
+  message: string;
+  details?: Record<string, unknown>;
 }
 
 export interface PaginatedUrlsResponse {
   urls: ShortenedUrl[];
   next_cursor?: string | null;
   has_more: boolean;
+  limit: number;
 }
 
 export interface CreateUrlRequest {
@@ -90,23 +121,33 @@ export interface AnalyticsResponse {
   entries: AnalyticsEntry[];
   total: number;
   clicks: number;
+  limit: number;
+  /** Analytics from this Unix timestamp on are at full detail; null if never pruned */
+  data_complete_since: number | null;
 }
 
 export interface AnalyticsAggregate {
   dimension: string;
   visit_count: number;
+  /** Visits whose dimension was dropped when old analytics were pruned */
+  aggregated: boolean;
 }
 
 export interface AnalyticsAggregateResponse {
   aggregates: AnalyticsAggregate[];
   total: number;
   clicks: number;
+  limit: number;
+  /** Analytics from this Unix timestamp on are at full detail; null if never pruned */
+  data_complete_since: number | null;
 }
 
 export interface SearchParams {
   q: string;
   created_by?: string;
+  /** Epoch milliseconds, inclusive */
   created_from?: number;
+  /** Epoch milliseconds, exclusive */
   created_to?: number;
   is_active?: boolean;
   limit?: number;
@@ -117,4 +158,5 @@ export interface SearchResponse {
   items: ShortenedUrl[];
   next_cursor?: string | null;
   has_more: boolean;
+  limit: number;
 }
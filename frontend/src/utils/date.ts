@@ -1,3 +1,7 @@
 /** Format a Unix timestamp (seconds) as a locale-aware date-time string. */
 export const formatDate = (timestamp: number): string =>
     new Date(timestamp * 1000).toLocaleString();
+
+/** Format an epoch timestamp in milliseconds, as link `created_at`/`updated_at` are. */
+export const formatDateMillis = (timestamp: number): string =>
+    new Date(timestamp).toLocaleString();
@@ -43,14 +43,16 @@ for rf in "${ROUTE_FILES[@]}"; do
 done
 
 # ── 2. Environment variable coverage ────────────────────────────────
-# Check that key env vars read in config/mod.rs and main.rs appear in
+# Check that key env vars read in the config module and the CLI appear in
 # README.md or .env.example.
 echo "=== Checking env var coverage in README + .env.example ==="
 
-# Collect env var names from config source and main.rs
+# Collect env var names from config source, main.rs and the subcommands
 ENV_SOURCES=(
-  "$REPO_ROOT/src/config/mod.rs"
+  "$REPO_ROOT"/src/config/*.rs
   "$REPO_ROOT/src/main.rs"
+  "$REPO_ROOT"/src/cli/*.rs
+  "$REPO_ROOT"/src/cli/*/*.rs
 )
 
 KNOWN_VARS=()
@@ -0,0 +1,342 @@
+//! Operator alerts for silent degradation.
+//!
+//! The click counter and the analytics aggregator degrade quietly under load:
+//! full queues spill into shared buffers, a closed analytics channel drops
+//! events, and failed flushes are requeued. Their flush tasks report what they
+//! see to [`OperatorAlerts`], which raises an alert once a configured threshold
+//! is crossed and then stays quiet for that condition until the cool-down ends.
+//!
+//! Alerts are POSTed as JSON to `ALERT_WEBHOOK_URL`, or logged at error level
+//! under the [`ALERT_LOG_TARGET`] target when no URL is set. Delivery happens on
+//! a small background task fed by a bounded queue; alerts that do not fit are
+//! discarded, so alerting never applies backpressure to the flush paths.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, mpsc::error::TrySendError};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::clock::Clock;
+use crate::config::AlertConfig;
+
+/// Tracing target for alerts that are logged instead of (or after failing to be) POSTed.
+pub const ALERT_LOG_TARGET: &str = "lynx::operator_alert";
+
+/// Alerts waiting for delivery; more than this are discarded.
+const ALERT_QUEUE_CAPACITY: usize = 32;
+
+/// How long a webhook POST may take before it counts as failed.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Window over which dropped analytics events are counted.
+const DROPPED_EVENTS_WINDOW: Duration = Duration::from_secs(60);
+
+/// A degradation an operator can be alerted about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Analytics events were dropped instead of being recorded
+    AnalyticsEventsDropped,
+    /// The click counter's queue is nearly full
+    ClickQueueDepth,
+    /// The analytics event queue is nearly full
+    AnalyticsQueueDepth,
+    /// Click batches keep failing to persist
+    ClickFlushFailures,
+    /// Analytics aggregates keep failing to persist
+    AnalyticsFlushFailures,
+}
+
+/// JSON body POSTed to the alert webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorAlert {
+    pub condition: AlertCondition,
+    pub message: String,
+    /// Observed value that crossed the threshold
+    pub value: u64,
+    pub threshold: u64,
+    /// When the alert was raised (Unix timestamp)
+    pub timestamp: i64,
+}
+
+/// Threshold checks shared by the flush tasks, with per-condition cool-down.
+pub struct OperatorAlerts {
+    config: AlertConfig,
+    queue: mpsc::Sender<OperatorAlert>,
+    last_raised: Mutex<HashMap<AlertCondition, Instant>>,
+    /// Start of the current dropped-events window and the drops counted in it
+    dropped_events: Mutex<(Instant, u64)>,
+    /// Dates the alerts raised
+    clock: Arc<dyn Clock>,
+}
+
+impl OperatorAlerts {
+    /// Start the delivery task and return the handle flush tasks report to,
+    /// which dates alerts by `clock`.
+    pub fn spawn(config: AlertConfig, clock: Arc<dyn Clock>) -> (Arc<Self>, JoinHandle<()>) {
+        let (queue, receiver) = mpsc::channel(ALERT_QUEUE_CAPACITY);
+        let handle = tokio::spawn(deliver_alerts(receiver, config.webhook_url.clone()));
+        (Arc::new(Self::new(config, queue, clock)), handle)
+    }
+
+    fn new(config: AlertConfig, queue: mpsc::Sender<OperatorAlert>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config,
+            queue,
+            last_raised: Mutex::new(HashMap::new()),
+            dropped_events: Mutex::new((Instant::now(), 0)),
+            clock,
+        }
+    }
+
+    /// Count `dropped` analytics events toward the current one-minute window.
+    pub fn record_dropped_events(&self, dropped: u64) {
+        let threshold = self.config.dropped_events_per_minute;
+        let in_window = {
+            let mut window = self
+                .dropped_events
+                .lock()
+                .expect("dropped events window mutex poisoned");
+            if window.0.elapsed() >= DROPPED_EVENTS_WINDOW {
+                *window = (Instant::now(), 0);
+            }
+            window.1 += dropped;
+            window.1
+        };
+
+        if threshold > 0 && dropped > 0 && in_window >= threshold {
+            self.raise(
+                AlertCondition::AnalyticsEventsDropped,
+                in_window,
+                threshold,
+                format!("{in_window} analytics events dropped within the last minute"),
+            );
+        }
+    }
+
+    /// Check how full a queue with `capacity` slots is while holding `len` messages.
+    pub fn check_queue_depth(&self, condition: AlertCondition, len: usize, capacity: usize) {
+        let threshold = u64::from(self.config.channel_depth_percent);
+        if threshold == 0 || capacity == 0 {
+            return;
+        }
+
+        let percent = (len as u64).saturating_mul(100) / capacity as u64;
+        if percent >= threshold {
+            self.raise(
+                condition,
+                percent,
+                threshold,
+                format!("queue is {percent}% full ({len} of {capacity} slots)"),
+            );
+        }
+    }
+
+    /// Check the number of consecutive failed flushes for `condition`.
+    pub fn check_flush_failures(&self, condition: AlertCondition, streak: u32) {
+        let threshold = self.config.flush_failure_streak;
+        if threshold > 0 && streak >= threshold {
+            self.raise(
+                condition,
+                u64::from(streak),
+                u64::from(threshold),
+                format!("{streak} consecutive flushes failed"),
+            );
+        }
+    }
+
+    /// Queue an alert unless `condition` already alerted within the cool-down.
+    fn raise(&self, condition: AlertCondition, value: u64, threshold: u64, message: String) {
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        let mut last_raised = self
+            .last_raised
+            .lock()
+            .expect("alert cool-down mutex poisoned");
+        if last_raised
+            .get(&condition)
+            .is_some_and(|raised| now.duration_since(*raised) < cooldown)
+        {
+            return;
+        }
+
+        let alert = OperatorAlert {
+            condition,
+            message,
+            value,
+            threshold,
+            timestamp: self.clock.now_epoch_secs(),
+        };
+        match self.queue.try_send(alert) {
+            Ok(()) => {
+                last_raised.insert(condition, now);
+            }
+            Err(TrySendError::Full(alert)) => {
+                tracing::warn!(condition = ?alert.condition, "operator alert queue full; discarding alert");
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// Deliver queued alerts to `webhook_url`, or log them when it is unset.
+async fn deliver_alerts(mut receiver: mpsc::Receiver<OperatorAlert>, webhook_url: Option<String>) {
+    let client = webhook_url.as_ref().map(|_| crate::http::client());
+
+    while let Some(alert) = receiver.recv().await {
+        let (Some(client), Some(url)) = (client.as_ref(), webhook_url.as_deref()) else {
+            log_alert(&alert, None);
+            continue;
+        };
+
+        let result = client
+            .post(url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&alert)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(error) = result {
+            // Report the failure without the URL, which may embed a token.
+            log_alert(&alert, Some(&error.without_url()));
+        }
+    }
+}
+
+fn log_alert(alert: &OperatorAlert, delivery_error: Option<&reqwest::Error>) {
+    match delivery_error {
+        None => tracing::error!(
+            target: ALERT_LOG_TARGET,
+            condition = ?alert.condition,
+            value = alert.value,
+            threshold = alert.threshold,
+            "{}",
+            alert.message
+        ),
+        Some(error) => tracing::error!(
+            target: ALERT_LOG_TARGET,
+            condition = ?alert.condition,
+            value = alert.value,
+            threshold = alert.threshold,
+            %error,
+            "{} (webhook delivery failed)",
+            alert.message
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn alerts(config: AlertConfig) -> (OperatorAlerts, mpsc::Receiver<OperatorAlert>) {
+        let (queue, receiver) = mpsc::channel(ALERT_QUEUE_CAPACITY);
+        let clock = Arc::new(FakeClock::at_epoch_ms(NOW * 1000));
+        (OperatorAlerts::new(config, queue, clock), receiver)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn conditions_alert_once_per_cooldown() {
+        let (alerts, mut receiver) = alerts(AlertConfig {
+            cooldown_secs: 60,
+            ..AlertConfig::default()
+        });
+
+        alerts.check_flush_failures(AlertCondition::ClickFlushFailures, 3);
+        alerts.check_flush_failures(AlertCondition::ClickFlushFailures, 4);
+        alerts.check_flush_failures(AlertCondition::AnalyticsFlushFailures, 3);
+
+        let first = receiver.try_recv().unwrap();
+        assert_eq!(first.condition, AlertCondition::ClickFlushFailures);
+        assert_eq!((first.value, first.threshold), (3, 3));
+        assert_eq!(first.timestamp, NOW);
+        assert_eq!(
+            receiver.try_recv().unwrap().condition,
+            AlertCondition::AnalyticsFlushFailures
+        );
+        assert!(receiver.try_recv().is_err());
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        alerts.check_flush_failures(AlertCondition::ClickFlushFailures, 5);
+        assert_eq!(receiver.try_recv().unwrap().value, 5);
+    }
+
+    #[tokio::test]
+    async fn thresholds_must_be_reached() {
+        let (alerts, mut receiver) = alerts(AlertConfig::default());
+
+        alerts.check_flush_failures(AlertCondition::ClickFlushFailures, 2);
+        alerts.check_queue_depth(AlertCondition::ClickQueueDepth, 89, 100);
+        assert!(receiver.try_recv().is_err());
+
+        alerts.check_queue_depth(AlertCondition::ClickQueueDepth, 900, 1000);
+        let alert = receiver.try_recv().unwrap();
+        assert_eq!((alert.value, alert.threshold), (90, 90));
+    }
+
+    #[tokio::test]
+    async fn zero_thresholds_disable_conditions() {
+        let (alerts, mut receiver) = alerts(AlertConfig {
+            dropped_events_per_minute: 0,
+            channel_depth_percent: 0,
+            flush_failure_streak: 0,
+            ..AlertConfig::default()
+        });
+
+        alerts.record_dropped_events(1_000);
+        alerts.check_queue_depth(AlertCondition::AnalyticsQueueDepth, 10, 10);
+        alerts.check_flush_failures(AlertCondition::AnalyticsFlushFailures, 100);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_events_are_counted_per_minute() {
+        let (alerts, mut receiver) = alerts(AlertConfig {
+            dropped_events_per_minute: 10,
+            cooldown_secs: 0,
+            ..AlertConfig::default()
+        });
+
+        alerts.record_dropped_events(6);
+        tokio::time::advance(Duration::from_secs(61)).await;
+        alerts.record_dropped_events(6);
+        assert!(receiver.try_recv().is_err());
+
+        alerts.record_dropped_events(4);
+        let alert = receiver.try_recv().unwrap();
+        assert_eq!(alert.condition, AlertCondition::AnalyticsEventsDropped);
+        assert_eq!(alert.value, 10);
+    }
+
+    #[tokio::test]
+    async fn full_queue_discards_alerts_without_blocking() {
+        let (queue, mut receiver) = mpsc::channel(1);
+        let alerts = OperatorAlerts::new(
+            AlertConfig::default(),
+            queue,
+            Arc::new(FakeClock::at_epoch_ms(NOW * 1000)),
+        );
+
+        alerts.check_flush_failures(AlertCondition::ClickFlushFailures, 3);
+        alerts.check_flush_failures(AlertCondition::AnalyticsFlushFailures, 3);
+
+        assert_eq!(
+            receiver.try_recv().unwrap().condition,
+            AlertCondition::ClickFlushFailures
+        );
+        assert!(receiver.try_recv().is_err());
+
+        // The discarded condition did not start its cool-down.
+        alerts.check_flush_failures(AlertCondition::AnalyticsFlushFailures, 3);
+        assert_eq!(
+            receiver.try_recv().unwrap().condition,
+            AlertCondition::AnalyticsFlushFailures
+        );
+    }
+}
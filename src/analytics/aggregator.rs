@@ -7,19 +7,27 @@
 //! to keep the hot path (request handling) as fast as possible.
 //!
 //! Uses actor pattern with mpsc channel to avoid lock contention on hot keys,
-//! similar to the ClickCounterActor in storage/cached.rs.
+//! similar to the ClickCounterActor in storage/cached/actor.rs.
 
 use dashmap::DashMap;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, mpsc::error::TrySendError, watch};
 use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
-use crate::analytics::models::{AnalyticsEvent, AnalyticsKey, AnalyticsRecord, AnalyticsValue};
-use crate::analytics::AnalyticsGroupBy;
+use crate::alerts::{AlertCondition, OperatorAlerts};
+use crate::analytics::models::{
+    AnalyticsEvent, AnalyticsKey, AnalyticsRecord, AnalyticsValue, IpVersion,
+};
+use crate::analytics::sampling::{AnalyticsSampler, SamplingStats};
 use crate::analytics::DROPPED_DIMENSION_MARKER;
+use crate::analytics::{AnalyticsGroupBy, GeoLocation};
+use crate::clock::Clock;
+use crate::config::{AnalyticsSamplingConfig, FlushConfig};
+use crate::flush::{FlushBackoff, FlushCoalescer, FlushReport, FlushTicker};
 
 /// Message types for the AnalyticsActor
 enum ActorMessage {
@@ -29,9 +37,32 @@ enum ActorMessage {
     Shutdown,
 }
 
+/// Events buffered for one short code, and when the oldest of them arrived.
+#[derive(Debug)]
+struct PendingEvents {
+    events: Vec<AnalyticsEvent>,
+    since: Instant,
+}
+
+impl PendingEvents {
+    fn new(event: AnalyticsEvent) -> Self {
+        Self {
+            events: vec![event],
+            since: Instant::now(),
+        }
+    }
+
+    /// Move `other`'s events into this bucket without cloning.
+    fn append(&mut self, mut other: PendingEvents) {
+        self.since = self.since.min(other.since);
+        self.events.append(&mut other.events);
+    }
+}
+
 fn enqueue_event(
     actor_tx: &mpsc::Sender<ActorMessage>,
-    shared_buffer: &DashMap<Arc<str>, Vec<AnalyticsEvent>>,
+    shared_buffer: &DashMap<Arc<str>, PendingEvents>,
+    dropped_events: &AtomicU64,
     event: AnalyticsEvent,
 ) {
     match actor_tx.try_send(ActorMessage::RecordEvent(event)) {
@@ -39,13 +70,14 @@ fn enqueue_event(
         Err(TrySendError::Full(ActorMessage::RecordEvent(event))) => {
             use dashmap::mapref::entry::Entry;
             match shared_buffer.entry(Arc::clone(&event.short_code)) {
-                Entry::Occupied(mut events) => events.get_mut().push(event),
-                Entry::Vacant(events) => {
-                    events.insert(vec![event]);
+                Entry::Occupied(mut pending) => pending.get_mut().events.push(event),
+                Entry::Vacant(pending) => {
+                    pending.insert(PendingEvents::new(event));
                 }
             }
         }
         Err(TrySendError::Closed(ActorMessage::RecordEvent(event))) => {
+            dropped_events.fetch_add(1, Ordering::Relaxed);
             warn!(short_code = %event.short_code, "analytics actor closed; dropping event");
         }
         Err(
@@ -66,9 +98,9 @@ struct AnalyticsActor {
     /// Channel receiver for incoming analytics events
     receiver: mpsc::Receiver<ActorMessage>,
     /// Layer 1: Lock-free event buffer (single-threaded access only in actor)
-    buffer: HashMap<Arc<str>, Vec<AnalyticsEvent>>,
+    buffer: HashMap<Arc<str>, PendingEvents>,
     /// Layer 2: Shared buffer for concurrent reads during flush
-    shared_buffer: Arc<DashMap<Arc<str>, Vec<AnalyticsEvent>>>,
+    shared_buffer: Arc<DashMap<Arc<str>, PendingEvents>>,
     /// Fast flush interval (Layer 1 → Layer 2)
     fast_flush_interval: Duration,
 }
@@ -86,11 +118,16 @@ impl AnalyticsActor {
                 Some(msg) = self.receiver.recv() => {
                     match msg {
                         ActorMessage::RecordEvent(event) => {
-                            // Fast local append in Layer 1 buffer (no locks!)
-                            self.buffer
-                                .entry(event.short_code.clone())
-                                .or_default()
-                                .push(event);
+                            // Fast local append in Layer 1 buffer (no locks!).
+                            // Only a new bucket reads the clock.
+                            match self.buffer.entry(event.short_code.clone()) {
+                                std::collections::hash_map::Entry::Occupied(mut pending) => {
+                                    pending.get_mut().events.push(event)
+                                }
+                                std::collections::hash_map::Entry::Vacant(pending) => {
+                                    pending.insert(PendingEvents::new(event));
+                                }
+                            }
                         }
                         ActorMessage::Shutdown => {
                             info!("Analytics actor received shutdown signal, flushing...");
@@ -121,13 +158,13 @@ impl AnalyticsActor {
             return;
         }
 
-        for (short_code, mut events) in self.buffer.drain() {
+        for (short_code, pending) in self.buffer.drain() {
             use dashmap::mapref::entry::Entry;
             match self.shared_buffer.entry(short_code) {
                 // Move the buffered events into the existing vec without cloning.
-                Entry::Occupied(mut existing) => existing.get_mut().append(&mut events),
+                Entry::Occupied(mut existing) => existing.get_mut().append(pending),
                 Entry::Vacant(slot) => {
-                    slot.insert(events);
+                    slot.insert(pending);
                 }
             }
         }
@@ -143,10 +180,36 @@ pub struct AnalyticsAggregator {
     actor_tx: mpsc::Sender<ActorMessage>,
 
     /// Shared event buffer (Layer 2) for concurrent flush access
-    shared_buffer: Arc<DashMap<Arc<str>, Vec<AnalyticsEvent>>>,
+    shared_buffer: Arc<DashMap<Arc<str>, PendingEvents>>,
 
     shutdown_tx: watch::Sender<bool>,
     actor_handle: Mutex<Option<JoinHandle<()>>>,
+
+    /// Runtime switch an admin flips through `POST /api/admin/analytics/enabled`
+    enabled: AtomicBool,
+    /// Wakes the flush tasks when `enabled` changes
+    enabled_tx: watch::Sender<bool>,
+
+    /// Jitter and coalescing applied by the background flush tasks
+    flush_config: FlushConfig,
+
+    /// Events dropped since the flush task last reported them
+    dropped_events: Arc<AtomicU64>,
+
+    /// Where flush tasks report dropped events, queue depth and failed flushes
+    alerts: Option<Arc<OperatorAlerts>>,
+
+    /// Thins out events while the actor channel is backed up
+    sampler: AnalyticsSampler,
+}
+
+/// The event queue and its sampling, for `GET /api/stats/analytics`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AnalyticsQueueStats {
+    /// Events waiting in the actor channel
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub sampling: SamplingStats,
 }
 
 impl AnalyticsAggregator {
@@ -154,6 +217,7 @@ impl AnalyticsAggregator {
     pub fn new_with_config(buffer_size: usize, fast_flush_interval_ms: u64) -> Self {
         let (actor_tx, actor_rx) = mpsc::channel(buffer_size);
         let (shutdown_tx, _) = watch::channel(false);
+        let (enabled_tx, _) = watch::channel(true);
         let shared_buffer = Arc::new(DashMap::new());
 
         // Spawn the analytics actor
@@ -174,9 +238,38 @@ impl AnalyticsAggregator {
             shared_buffer,
             shutdown_tx,
             actor_handle: Mutex::new(Some(actor_handle)),
+            enabled: AtomicBool::new(true),
+            enabled_tx,
+            flush_config: FlushConfig::default(),
+            dropped_events: Arc::new(AtomicU64::new(0)),
+            alerts: None,
+            sampler: AnalyticsSampler::disabled(),
         }
     }
 
+    /// Use `flush_config` for flush tasks started after this call.
+    pub fn with_flush_config(mut self, flush_config: FlushConfig) -> Self {
+        self.flush_config = flush_config;
+        self
+    }
+
+    /// Report degradation to `alerts` from flush tasks started after this call.
+    pub fn with_alerts(mut self, alerts: Arc<OperatorAlerts>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Sample events as `config` describes once the channel backs up,
+    /// dating sampling periods by `clock`.
+    pub fn with_sampling(
+        mut self,
+        config: &AnalyticsSamplingConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        self.sampler = AnalyticsSampler::new(config, self.actor_tx.max_capacity(), clock);
+        self
+    }
+
     /// Create a new analytics aggregator with default settings
     pub fn new() -> Self {
         Self::new_with_config(
@@ -189,9 +282,19 @@ impl AnalyticsAggregator {
     ///
     /// This is the HOT PATH method called on every request.
     /// Uses lock-free mpsc channel to avoid contention on hot keys.
-    /// The GeoIP lookups are deferred until flush time.
-    pub fn record_event(&self, event: AnalyticsEvent) {
-        enqueue_event(&self.actor_tx, &self.shared_buffer, event);
+    /// The GeoIP lookups are deferred until flush time. While the channel
+    /// is backed up, sampling may leave the event out or weight it.
+    pub fn record_event(&self, mut event: AnalyticsEvent) {
+        let Some(weight) = self.sampler.admit(self.queue_depth()) else {
+            return;
+        };
+        event.weight = weight;
+        enqueue_event(
+            &self.actor_tx,
+            &self.shared_buffer,
+            &self.dropped_events,
+            event,
+        );
     }
 
     /// Record a visit event (legacy - with GeoIP lookup already done)
@@ -204,7 +307,7 @@ impl AnalyticsAggregator {
         self.aggregates
             .entry(key)
             .and_modify(|v| v.count += 1)
-            .or_insert_with(|| AnalyticsValue { count: 1 });
+            .or_insert_with(|| AnalyticsValue::of(1));
     }
 
     /// Drain all aggregated analytics and return them
@@ -247,8 +350,8 @@ impl AnalyticsAggregator {
 
         // Remove and collect events
         for key in keys {
-            if let Some((_, mut events)) = self.shared_buffer.remove(&key) {
-                result.append(&mut events);
+            if let Some((_, mut pending)) = self.shared_buffer.remove(&key) {
+                result.append(&mut pending.events);
             }
         }
 
@@ -272,85 +375,10 @@ impl AnalyticsAggregator {
             + Send
             + 'static,
     {
-        let aggregates = Arc::clone(&self.aggregates);
-        let shared_buffer = Arc::clone(&self.shared_buffer);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
-
-        tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(flush_interval_secs));
-            let mut shutdown_requested = *shutdown_rx.borrow_and_update();
-
-            loop {
-                if !shutdown_requested {
-                    tokio::select! {
-                        _ = interval.tick() => {}
-                        result = shutdown_rx.changed() => {
-                            shutdown_requested = result.is_err() || *shutdown_rx.borrow_and_update();
-                        }
-                    }
-                }
-
-                // Without GeoIP, preserve events using the explicit unknown
-                // geography bucket before flushing aggregates.
-                let event_keys: Vec<Arc<str>> = shared_buffer
-                    .iter()
-                    .map(|entry| Arc::clone(entry.key()))
-                    .collect();
-                for key in event_keys {
-                    if let Some((_, events)) = shared_buffer.remove(&key) {
-                        for event in events {
-                            let analytics_key = AnalyticsKey::from_event(
-                                &event,
-                                &crate::analytics::GeoLocation::default(),
-                            );
-                            aggregates
-                                .entry(analytics_key)
-                                .and_modify(|value| value.count += 1)
-                                .or_insert_with(|| AnalyticsValue { count: 1 });
-                        }
-                    }
-                }
-
-                let mut flush_failed = false;
-
-                // Drain aggregates
-                let count = aggregates.len();
-                if count > 0 {
-                    debug!("Draining {} analytics aggregates", count);
-
-                    // Collect keys and values
-                    let mut entries = Vec::new();
-                    let keys: Vec<AnalyticsKey> =
-                        aggregates.iter().map(|entry| entry.key().clone()).collect();
-
-                    for key in keys {
-                        if let Some((k, v)) = aggregates.remove(&key) {
-                            entries.push((k, v));
-                        }
-                    }
-
-                    // Call flush function
-                    if !entries.is_empty() {
-                        let retry = entries.clone();
-                        if let Err(error) = flush_fn(entries).await {
-                            tracing::error!(%error, "analytics flush failed; requeueing aggregates");
-                            merge_aggregates(&aggregates, retry);
-                            flush_failed = true;
-                        }
-                    }
-                }
-
-                if shutdown_requested {
-                    if flush_failed {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                        continue;
-                    }
-                    info!("Analytics aggregator flush task shut down");
-                    break;
-                }
-            }
-        })
+        // Without GeoIP, preserve events using the explicit unknown
+        // geography bucket before flushing aggregates.
+        let task = self.flush_task("flush task", flush_interval_secs);
+        tokio::spawn(task.run(|event| GeoLocation::unresolved(event.client_ip), flush_fn))
     }
 
     /// Start the background flush task with GeoIP service (OPTIMIZED)
@@ -358,6 +386,7 @@ impl AnalyticsAggregator {
     /// This spawns a tokio task that periodically drains events,
     /// performs GeoIP lookups in batch, aggregates, and flushes to database.
     /// This is the OPTIMIZED path that keeps GeoIP lookups off the hot path.
+    #[cfg(feature = "analytics")]
     pub fn start_flush_task_with_geoip<F>(
         &self,
         flush_interval_secs: u64,
@@ -372,92 +401,39 @@ impl AnalyticsAggregator {
             + Send
             + 'static,
     {
-        let shared_buffer = Arc::clone(&self.shared_buffer);
-        let aggregates = Arc::clone(&self.aggregates);
-        let mut shutdown_rx = self.shutdown_tx.subscribe();
-
-        tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(flush_interval_secs));
-            let mut shutdown_requested = *shutdown_rx.borrow_and_update();
-
-            loop {
-                if !shutdown_requested {
-                    tokio::select! {
-                        _ = interval.tick() => {}
-                        result = shutdown_rx.changed() => {
-                            shutdown_requested = result.is_err() || *shutdown_rx.borrow_and_update();
-                        }
-                    }
-                }
-
-                // Drain events from shared buffer (Layer 2) and process with GeoIP
-                let event_count = shared_buffer.len();
-                if event_count > 0 {
-                    debug!("Processing {} analytics event buffers", event_count);
-
-                    // Collect all event buffers from shared buffer
-                    let keys: Vec<Arc<str>> = shared_buffer
-                        .iter()
-                        .map(|entry| entry.key().clone())
-                        .collect();
-
-                    // Process each buffer
-                    for key in keys {
-                        if let Some((_, events)) = shared_buffer.remove(&key) {
-                            // Process events with GeoIP lookup (off hot path)
-                            for event in events {
-                                let geo_location = geoip_service.lookup(event.client_ip);
-                                let analytics_key = AnalyticsKey::from_event(&event, &geo_location);
-
-                                // Aggregate the result
-                                aggregates
-                                    .entry(analytics_key)
-                                    .and_modify(|v| v.count += 1)
-                                    .or_insert_with(|| AnalyticsValue { count: 1 });
-                            }
-                        }
-                    }
-                }
-
-                let mut flush_failed = false;
-
-                // Now drain and flush aggregates
-                let agg_count = aggregates.len();
-                if agg_count > 0 {
-                    debug!("Flushing {} analytics aggregates", agg_count);
-
-                    let mut entries = Vec::new();
-                    let keys: Vec<AnalyticsKey> =
-                        aggregates.iter().map(|entry| entry.key().clone()).collect();
+        let task = self.flush_task("GeoIP flush task", flush_interval_secs);
+        tokio::spawn(task.run(move |event| geoip_service.lookup(event.client_ip), flush_fn))
+    }
 
-                    for key in keys {
-                        if let Some((k, v)) = aggregates.remove(&key) {
-                            entries.push((k, v));
-                        }
-                    }
+    fn flush_task(&self, name: &'static str, flush_interval_secs: u64) -> FlushTask {
+        FlushTask {
+            name,
+            interval: Duration::from_secs(flush_interval_secs),
+            aggregates: Arc::clone(&self.aggregates),
+            shared_buffer: Arc::clone(&self.shared_buffer),
+            shutdown_rx: self.shutdown_tx.subscribe(),
+            enabled_rx: self.enabled_tx.subscribe(),
+            flush_config: self.flush_config.clone(),
+            alerts: self.alerts.clone(),
+            dropped_events: Arc::clone(&self.dropped_events),
+            actor_tx: self.actor_tx.downgrade(),
+        }
+    }
 
-                    // Call flush function
-                    if !entries.is_empty() {
-                        let retry = entries.clone();
-                        if let Err(error) = flush_fn(entries).await {
-                            tracing::error!(%error, "analytics flush failed; requeueing aggregates");
-                            merge_aggregates(&aggregates, retry);
-                            flush_failed = true;
-                        }
-                    }
-                }
+    /// Whether visits are being recorded. The redirect handler checks this
+    /// before building an event.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
 
-                if shutdown_requested {
-                    if flush_failed {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                        continue;
-                    }
-                    info!("Analytics aggregator GeoIP flush task shut down");
-                    break;
-                }
-            }
-        })
+    /// Switch recording on or off at runtime and return the previous state.
+    ///
+    /// Disabling makes the flush tasks write out what is pending and stop;
+    /// enabling starts them again with a fresh flush schedule.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        let previous = self.enabled.swap(enabled, Ordering::Relaxed);
+        self.enabled_tx.send_replace(enabled);
+        previous
     }
 
     /// Get aggregated analytics from in-memory data for a specific short code
@@ -540,18 +516,67 @@ impl AnalyticsAggregator {
                     .unwrap_or_else(|| "Unknown".to_string()),
                 AnalyticsGroupBy::Hour => key.time_bucket.to_string(),
                 AnalyticsGroupBy::Day => ((key.time_bucket / 86400) * 86400).to_string(),
+                // Visits to the code itself have no alias to group by.
+                AnalyticsGroupBy::AliasUsed => match &key.alias_used {
+                    Some(alias) => alias.to_string(),
+                    None => continue,
+                },
+                AnalyticsGroupBy::IpVersion => {
+                    IpVersion::from_num(key.ip_version).label().to_string()
+                }
             };
 
             *grouped.entry(dimension).or_insert(0) += entry.value().count;
         }
 
-        // Process from shared buffer (Layer 2) - events pending GeoIP lookup
-        // These will be displayed as "Unknown" since GeoIP hasn't been resolved yet
+        // Process from shared buffer (Layer 2) - events pending GeoIP lookup.
+        // The alias is known before GeoIP, so those events group normally.
+        if group_by == AnalyticsGroupBy::AliasUsed {
+            for entry in self.shared_buffer.iter() {
+                if entry.key().as_ref() != short_code {
+                    continue;
+                }
+                for event in entry.value().events.iter() {
+                    if let Some(alias) = &event.alias_used {
+                        *grouped.entry(alias.to_string()).or_insert(0) += i64::from(event.weight);
+                    }
+                }
+            }
+            let mut result: Vec<(String, i64)> = grouped.into_iter().collect();
+            result.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+            return result;
+        }
+
+        // The IP version is known from the address itself, before GeoIP
+        if group_by == AnalyticsGroupBy::IpVersion {
+            for entry in self.shared_buffer.iter() {
+                if entry.key().as_ref() != short_code {
+                    continue;
+                }
+                for event in entry.value().events.iter() {
+                    let label = IpVersion::of(event.client_ip).label();
+                    *grouped.entry(label.to_string()).or_insert(0) += i64::from(event.weight);
+                }
+            }
+            let mut result: Vec<(String, i64)> = grouped.into_iter().collect();
+            result.sort_by_key(|entry| std::cmp::Reverse(entry.1));
+            return result;
+        }
+
+        // The remaining pending events are displayed as "Unknown" since GeoIP
+        // hasn't been resolved yet
         let unknown_count: i64 = self
             .shared_buffer
             .iter()
             .filter(|entry| entry.key().as_ref() == short_code)
-            .map(|entry| entry.value().len() as i64)
+            .map(|entry| {
+                entry
+                    .value()
+                    .events
+                    .iter()
+                    .map(|event| i64::from(event.weight))
+                    .sum::<i64>()
+            })
             .sum();
 
         if unknown_count > 0 {
@@ -564,6 +589,43 @@ impl AnalyticsAggregator {
         result
     }
 
+    /// Visits to `short_code` not yet written to storage that were
+    /// extrapolated from sampled events.
+    pub fn pending_estimated_visits(&self, short_code: &str) -> i64 {
+        let aggregated: i64 = self
+            .aggregates
+            .iter()
+            .filter(|entry| entry.key().short_code.as_ref() == short_code)
+            .map(|entry| entry.value().estimated)
+            .sum();
+        let buffered: i64 = self
+            .shared_buffer
+            .get(short_code)
+            .map(|pending| {
+                pending
+                    .events
+                    .iter()
+                    .map(|event| AnalyticsValue::of(event.weight).estimated)
+                    .sum()
+            })
+            .unwrap_or(0);
+        aggregated + buffered
+    }
+
+    /// Events waiting in the actor channel.
+    fn queue_depth(&self) -> usize {
+        self.actor_tx.max_capacity() - self.actor_tx.capacity()
+    }
+
+    /// The event queue and where sampling stands.
+    pub fn queue_stats(&self) -> AnalyticsQueueStats {
+        AnalyticsQueueStats {
+            queue_depth: self.queue_depth(),
+            queue_capacity: self.actor_tx.max_capacity(),
+            sampling: self.sampler.stats(),
+        }
+    }
+
     /// Signal shutdown to the flush task and actor
     pub async fn shutdown(&self) {
         let actor_handle = self
@@ -591,6 +653,228 @@ impl AnalyticsAggregator {
     }
 }
 
+/// What a background flush task needs from its aggregator.
+struct FlushTask {
+    /// Names the task in its log lines
+    name: &'static str,
+    interval: Duration,
+    aggregates: Arc<DashMap<AnalyticsKey, AnalyticsValue>>,
+    shared_buffer: Arc<DashMap<Arc<str>, PendingEvents>>,
+    shutdown_rx: watch::Receiver<bool>,
+    enabled_rx: watch::Receiver<bool>,
+    flush_config: FlushConfig,
+    alerts: Option<Arc<OperatorAlerts>>,
+    dropped_events: Arc<AtomicU64>,
+    actor_tx: mpsc::WeakSender<ActorMessage>,
+}
+
+impl FlushTask {
+    /// Locate pending visits with `locate` and write aggregates with
+    /// `flush_fn` until shutdown.
+    ///
+    /// While analytics is disabled the task writes out what is pending once
+    /// and then idles until it is enabled again or the server shuts down.
+    /// Aggregates that fail to write while disabled stay in memory for the
+    /// next run instead of being retried.
+    async fn run<L, F>(mut self, locate: L, flush_fn: F)
+    where
+        L: Fn(&AnalyticsEvent) -> GeoLocation,
+        F: Fn(
+            Vec<(AnalyticsKey, AnalyticsValue)>,
+        )
+            -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send>>,
+    {
+        let mut ticker = FlushTicker::new(self.interval, self.flush_config.jitter_percent);
+        let mut coalescer = FlushCoalescer::new(&self.flush_config);
+        let mut backoff = FlushBackoff::new();
+        let mut shutdown_requested = *self.shutdown_rx.borrow_and_update();
+        let mut enabled = *self.enabled_rx.borrow_and_update();
+        let mut failure_streak = 0;
+        // Arrival time of the oldest visit folded into `aggregates`
+        let mut oldest_aggregate: Option<Instant> = None;
+
+        loop {
+            if !shutdown_requested {
+                let was_enabled = enabled;
+                tokio::select! {
+                    _ = ticker.tick(), if enabled => {}
+                    result = self.shutdown_rx.changed() => {
+                        shutdown_requested = result.is_err() || *self.shutdown_rx.borrow_and_update();
+                    }
+                    result = self.enabled_rx.changed() => match result {
+                        Ok(()) => enabled = *self.enabled_rx.borrow_and_update(),
+                        Err(_) => shutdown_requested = true,
+                    }
+                }
+                if enabled && !was_enabled {
+                    info!("Analytics aggregator {} restarted", self.name);
+                    ticker = FlushTicker::new(self.interval, self.flush_config.jitter_percent);
+                    coalescer = FlushCoalescer::new(&self.flush_config);
+                    backoff = FlushBackoff::new();
+                    failure_streak = 0;
+                } else if !enabled && was_enabled {
+                    info!(
+                        "Analytics aggregator {} stopping; analytics disabled",
+                        self.name
+                    );
+                }
+            }
+            let draining = shutdown_requested || !enabled;
+
+            if let Some(since) =
+                resolve_pending_events(&self.shared_buffer, &self.aggregates, &locate)
+            {
+                oldest_aggregate = Some(oldest_aggregate.map_or(since, |oldest| oldest.min(since)));
+            }
+
+            let mut flush_failed = false;
+
+            // Drain aggregates, coalescing small batches and backing off
+            // after failures unless draining
+            let count = self.aggregates.len();
+            if count > 0
+                && (draining
+                    || (backoff.should_attempt(failure_streak) && coalescer.should_flush(count)))
+            {
+                debug!("Flushing {} analytics aggregates", count);
+
+                let mut entries = Vec::new();
+                let keys: Vec<AnalyticsKey> = self
+                    .aggregates
+                    .iter()
+                    .map(|entry| entry.key().clone())
+                    .collect();
+
+                for key in keys {
+                    if let Some((k, v)) = self.aggregates.remove(&key) {
+                        entries.push((k, v));
+                    }
+                }
+
+                // Call flush function
+                if !entries.is_empty() {
+                    let retry = entries.clone();
+                    let started = Instant::now();
+                    let mut report = aggregates_report(&entries, started, oldest_aggregate);
+                    let result = flush_fn(entries).await;
+                    report.duration = started.elapsed();
+                    report.succeeded = result.is_ok();
+                    report.log();
+                    if let Err(error) = result {
+                        tracing::error!(%error, "analytics flush failed; requeueing aggregates");
+                        merge_aggregates(&self.aggregates, retry);
+                        flush_failed = true;
+                        failure_streak += 1;
+                    } else {
+                        failure_streak = 0;
+                        oldest_aggregate = None;
+                    }
+                }
+            }
+
+            if let Some(alerts) = &self.alerts {
+                report_to_alerts(alerts, &self.dropped_events, &self.actor_tx, failure_streak);
+            }
+
+            if shutdown_requested {
+                if flush_failed {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+                info!("Analytics aggregator {} shut down", self.name);
+                break;
+            }
+        }
+    }
+}
+
+/// Drain the shared event buffer (Layer 2) into `aggregates`, locating each
+/// visit with `locate`, and log the batch as the `analytics_events` stage.
+///
+/// Returns when the oldest drained visit arrived, or `None` if nothing was
+/// pending.
+fn resolve_pending_events(
+    shared_buffer: &DashMap<Arc<str>, PendingEvents>,
+    aggregates: &DashMap<AnalyticsKey, AnalyticsValue>,
+    locate: impl Fn(&AnalyticsEvent) -> GeoLocation,
+) -> Option<Instant> {
+    if shared_buffer.is_empty() {
+        return None;
+    }
+    debug!("Processing {} analytics event buffers", shared_buffer.len());
+
+    let started = Instant::now();
+    let mut oldest: Option<Instant> = None;
+    let mut resolved = 0;
+    let keys: Vec<Arc<str>> = shared_buffer
+        .iter()
+        .map(|entry| Arc::clone(entry.key()))
+        .collect();
+    for key in keys {
+        if let Some((_, pending)) = shared_buffer.remove(&key) {
+            oldest = Some(oldest.map_or(pending.since, |oldest| oldest.min(pending.since)));
+            resolved += pending.events.len();
+            for event in pending.events {
+                let analytics_key = AnalyticsKey::from_event(&event, &locate(&event));
+                let value = AnalyticsValue::of(event.weight);
+                aggregates
+                    .entry(analytics_key)
+                    .and_modify(|current| current.add(&value))
+                    .or_insert(value);
+            }
+        }
+    }
+
+    let oldest = oldest?;
+    FlushReport {
+        stage: "analytics_events",
+        entries: resolved,
+        bytes: resolved * std::mem::size_of::<AnalyticsEvent>(),
+        duration: started.elapsed(),
+        lag: started - oldest,
+        succeeded: true,
+    }
+    .log();
+    Some(oldest)
+}
+
+/// Report for writing `entries` to storage; the caller fills in the outcome.
+fn aggregates_report(
+    entries: &[(AnalyticsKey, AnalyticsValue)],
+    started: Instant,
+    oldest: Option<Instant>,
+) -> FlushReport {
+    FlushReport {
+        stage: "analytics_aggregates",
+        entries: entries.len(),
+        bytes: entries.len()
+            * (std::mem::size_of::<AnalyticsKey>() + std::mem::size_of::<AnalyticsValue>()),
+        duration: Duration::ZERO,
+        lag: oldest.map_or(Duration::ZERO, |oldest| started - oldest),
+        succeeded: false,
+    }
+}
+
+/// Hand a flush task's view of dropped events, queue depth and failed
+/// flushes to the operator alerts.
+fn report_to_alerts(
+    alerts: &OperatorAlerts,
+    dropped_events: &AtomicU64,
+    actor_tx: &mpsc::WeakSender<ActorMessage>,
+    failure_streak: u32,
+) {
+    alerts.record_dropped_events(dropped_events.swap(0, Ordering::Relaxed));
+    if let Some(actor_tx) = actor_tx.upgrade() {
+        let capacity = actor_tx.max_capacity();
+        alerts.check_queue_depth(
+            AlertCondition::AnalyticsQueueDepth,
+            capacity - actor_tx.capacity(),
+            capacity,
+        );
+    }
+    alerts.check_flush_failures(AlertCondition::AnalyticsFlushFailures, failure_streak);
+}
+
 fn merge_aggregates(
     aggregates: &DashMap<AnalyticsKey, AnalyticsValue>,
     entries: Vec<(AnalyticsKey, AnalyticsValue)>,
@@ -598,7 +882,7 @@ fn merge_aggregates(
     for (key, value) in entries {
         aggregates
             .entry(key)
-            .and_modify(|current| current.count += value.count)
+            .and_modify(|current| current.add(&value))
             .or_insert(value);
     }
 }
@@ -670,21 +954,52 @@ mod tests {
                 short_code: "queued".into(),
                 timestamp: 1,
                 client_ip: "127.0.0.1".parse().unwrap(),
+                alias_used: None,
+                weight: 1,
             }))
             .unwrap();
         let shared_buffer = DashMap::new();
+        let dropped_events = AtomicU64::new(0);
 
         enqueue_event(
             &actor_tx,
             &shared_buffer,
+            &dropped_events,
             AnalyticsEvent {
                 short_code: "overflow".into(),
                 timestamp: 2,
                 client_ip: "127.0.0.1".parse().unwrap(),
+                alias_used: None,
+                weight: 1,
+            },
+        );
+
+        assert_eq!(shared_buffer.get("overflow").unwrap().events.len(), 1);
+        assert_eq!(dropped_events.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn closed_event_channel_counts_dropped_events() {
+        let (actor_tx, actor_rx) = mpsc::channel(1);
+        drop(actor_rx);
+        let shared_buffer = DashMap::new();
+        let dropped_events = AtomicU64::new(0);
+
+        enqueue_event(
+            &actor_tx,
+            &shared_buffer,
+            &dropped_events,
+            AnalyticsEvent {
+                short_code: "closed".into(),
+                timestamp: 1,
+                client_ip: "127.0.0.1".parse().unwrap(),
+                alias_used: None,
+                weight: 1,
             },
         );
 
-        assert_eq!(shared_buffer.get("overflow").unwrap().len(), 1);
+        assert!(shared_buffer.is_empty());
+        assert_eq!(dropped_events.load(Ordering::Relaxed), 1);
     }
 
     #[tokio::test]
@@ -696,6 +1011,8 @@ mod tests {
             short_code: "shutdown".into(),
             timestamp: 1,
             client_ip: "127.0.0.1".parse().unwrap(),
+            alias_used: None,
+            weight: 1,
         });
         aggregator.shutdown().await;
         let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
@@ -711,6 +1028,49 @@ mod tests {
         assert_eq!(persisted.load(Ordering::Relaxed), 1);
     }
 
+    #[tokio::test]
+    async fn flush_stages_report_entries_bytes_and_lag() {
+        let (events, _guard) = crate::flush::capture::flush_events();
+        let aggregator = AnalyticsAggregator::new();
+        for short_code in ["first", "second", "second"] {
+            aggregator.record_event(AnalyticsEvent {
+                short_code: short_code.into(),
+                timestamp: 1,
+                client_ip: "127.0.0.1".parse().unwrap(),
+                alias_used: None,
+                weight: 1,
+            });
+        }
+        aggregator.shutdown().await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let flush_handle =
+            aggregator.start_flush_task_with_storage(3_600, |_| Box::pin(async { Ok(()) }));
+        flush_handle.await.unwrap();
+
+        let events = events.lock().unwrap();
+        let stage = |name: &str| {
+            events
+                .iter()
+                .find(|event| event["stage"] == name)
+                .unwrap_or_else(|| panic!("missing {name} flush event"))
+        };
+        let resolved = stage("analytics_events");
+        assert_eq!(resolved["entries"], "3");
+        assert_eq!(
+            resolved["bytes"],
+            (3 * std::mem::size_of::<AnalyticsEvent>()).to_string()
+        );
+        assert!(resolved["lag_ms"].parse::<u64>().unwrap() >= 20);
+        assert!(resolved.contains_key("duration_ms"));
+
+        let written = stage("analytics_aggregates");
+        assert_eq!(written["entries"], "2");
+        assert_eq!(written["succeeded"], "true");
+        assert!(written["lag_ms"].parse::<u64>().unwrap() >= 20);
+        assert!(written.contains_key("duration_ms"));
+        assert!(written.contains_key("bytes"));
+    }
+
     #[tokio::test]
     async fn shutdown_retries_failed_final_flush() {
         let aggregator = AnalyticsAggregator::new();
@@ -722,6 +1082,8 @@ mod tests {
             short_code: "retry".into(),
             timestamp: 1,
             client_ip: "127.0.0.1".parse().unwrap(),
+            alias_used: None,
+            weight: 1,
         });
         aggregator.shutdown().await;
         let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
@@ -741,4 +1103,79 @@ mod tests {
         assert_eq!(attempts.load(Ordering::Relaxed), 2);
         assert_eq!(persisted.load(Ordering::Relaxed), 1);
     }
+
+    #[tokio::test]
+    async fn in_memory_alias_breakdown_counts_pending_and_aggregated_visits() {
+        let aggregator = AnalyticsAggregator::new();
+        let event = |alias_used: Option<&str>| AnalyticsEvent {
+            short_code: "docs".into(),
+            timestamp: 3_600,
+            client_ip: "127.0.0.1".parse().unwrap(),
+            alias_used: alias_used.map(Arc::from),
+            weight: 1,
+        };
+        for alias_used in [None, Some("manual"), Some("guide")] {
+            let key = AnalyticsKey::from_event(&event(alias_used), &GeoLocation::default());
+            aggregator.aggregates.insert(
+                key,
+                AnalyticsValue {
+                    count: 2,
+                    estimated: 0,
+                },
+            );
+        }
+        aggregator
+            .shared_buffer
+            .insert("docs".into(), PendingEvents::new(event(Some("guide"))));
+        aggregator
+            .shared_buffer
+            .get_mut("docs")
+            .unwrap()
+            .events
+            .push(event(None));
+
+        let mut by_alias = aggregator.get_in_memory_aggregate("docs", AnalyticsGroupBy::AliasUsed);
+        by_alias.sort();
+        assert_eq!(
+            by_alias,
+            vec![("guide".to_string(), 3), ("manual".to_string(), 2)]
+        );
+    }
+    #[tokio::test]
+    async fn in_memory_ip_version_split_counts_mapped_addresses_as_ipv4() {
+        let aggregator = AnalyticsAggregator::new();
+        let event = |client_ip: &str| AnalyticsEvent {
+            short_code: "docs".into(),
+            timestamp: 3_600,
+            client_ip: client_ip.parse().unwrap(),
+            alias_used: None,
+            weight: 1,
+        };
+        let ipv6 = event("2001:db8::1");
+        let geo = GeoLocation {
+            ip_version: 6,
+            ..GeoLocation::default()
+        };
+        aggregator.aggregates.insert(
+            AnalyticsKey::from_event(&ipv6, &geo),
+            AnalyticsValue {
+                count: 4,
+                estimated: 0,
+            },
+        );
+        aggregator
+            .shared_buffer
+            .insert("docs".into(), PendingEvents::new(event("::ffff:192.0.2.1")));
+        let mut pending = aggregator.shared_buffer.get_mut("docs").unwrap();
+        pending.events.push(event("192.0.2.2"));
+        pending.events.push(ipv6);
+        drop(pending);
+
+        let mut split = aggregator.get_in_memory_aggregate("docs", AnalyticsGroupBy::IpVersion);
+        split.sort();
+        assert_eq!(
+            split,
+            vec![("IPv4".to_string(), 2), ("IPv6".to_string(), 5)]
+        );
+    }
 }
@@ -0,0 +1,140 @@
+//! Daily analytics rollup.
+//!
+//! `analytics_daily` holds visits per short code, UTC day and country, summed
+//! from the hourly `analytics` rows. A nightly task (and `lynx analytics
+//! rollup-daily`) rolls up every whole day older than
+//! `ANALYTICS_DAILY_ROLLUP_AFTER_DAYS`. The rollup always covers every day
+//! before its watermark, so country and day aggregates can read whole days
+//! before the watermark from it and everything else from the hourly table.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::clock::Clock;
+use crate::storage::Storage;
+
+pub const DAY_SECS: i64 = 86_400;
+
+/// Time between rollups of the nightly task.
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(DAY_SECS as u64);
+
+/// Start of the UTC day containing `timestamp`.
+pub fn day_start(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(DAY_SECS)
+}
+
+/// Where a rollup run starts: `from` (rounded down to its day) when
+/// rebuilding, else the watermark. A `from` after the watermark is pulled
+/// back to it so the rollup stays free of gaps. `None` rolls up from the
+/// oldest hour, as when nothing was rolled up yet.
+pub fn rollup_start(from: Option<i64>, rolled_until: Option<i64>) -> Option<i64> {
+    let rolled_until = rolled_until?;
+    Some(from.map_or(rolled_until, |from| day_start(from).min(rolled_until)))
+}
+
+/// How an analytics range divides between the rollup and the hourly rows:
+/// whole days in `[daily_from, daily_until)` come from `analytics_daily`,
+/// the rest of the range from `analytics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RollupSplit {
+    pub daily_from: i64,
+    pub daily_until: i64,
+}
+
+impl RollupSplit {
+    /// The split of the inclusive range `[start, end]` (open ends unbounded)
+    /// given the rollup watermark, or `None` when no whole rolled-up day
+    /// falls inside it.
+    pub fn of(start: Option<i64>, end: Option<i64>, rolled_until: Option<i64>) -> Option<Self> {
+        let rolled_until = rolled_until?;
+        let daily_from = match start {
+            None => i64::MIN,
+            Some(start) if start == day_start(start) => start,
+            Some(start) => day_start(start).checked_add(DAY_SECS)?,
+        };
+        let daily_until = match end.and_then(|end| end.checked_add(1)) {
+            Some(after_end) => day_start(after_end).min(rolled_until),
+            None => rolled_until,
+        };
+        (daily_from < daily_until).then_some(Self {
+            daily_from,
+            daily_until,
+        })
+    }
+}
+
+/// Roll up whole days older than `after_days` in `storage` once a day,
+/// starting now.
+pub fn spawn_daily_rollup(
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    after_days: i64,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(ROLLUP_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let until = clock.now_epoch_secs() - after_days * DAY_SECS;
+            match storage.rollup_analytics_daily(None, until).await {
+                Ok(0) => {}
+                Ok(rows) => tracing::info!(rows, "Rolled up daily analytics"),
+                Err(error) => tracing::warn!(%error, "Failed to roll up daily analytics"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_3: i64 = 3 * DAY_SECS;
+
+    #[test]
+    fn rollup_continues_from_the_watermark_without_gaps() {
+        assert_eq!(rollup_start(None, None), None);
+        assert_eq!(rollup_start(Some(DAY_3), None), None);
+        assert_eq!(rollup_start(None, Some(DAY_3)), Some(DAY_3));
+        assert_eq!(
+            rollup_start(Some(DAY_SECS + 5), Some(DAY_3)),
+            Some(DAY_SECS)
+        );
+        assert_eq!(rollup_start(Some(DAY_3 * 2), Some(DAY_3)), Some(DAY_3));
+    }
+
+    #[test]
+    fn split_takes_whole_rolled_up_days_only() {
+        // Nothing rolled up
+        assert_eq!(RollupSplit::of(None, None, None), None);
+        // Open range: everything before the watermark
+        assert_eq!(
+            RollupSplit::of(None, None, Some(DAY_3)),
+            Some(RollupSplit {
+                daily_from: i64::MIN,
+                daily_until: DAY_3
+            })
+        );
+        // Partial days at both ends stay hourly
+        assert_eq!(
+            RollupSplit::of(Some(3600), Some(2 * DAY_SECS + 3600), Some(DAY_3)),
+            Some(RollupSplit {
+                daily_from: DAY_SECS,
+                daily_until: 2 * DAY_SECS
+            })
+        );
+        // An end on the last second of a day includes that day
+        assert_eq!(
+            RollupSplit::of(Some(0), Some(2 * DAY_SECS - 1), Some(DAY_3)),
+            Some(RollupSplit {
+                daily_from: 0,
+                daily_until: 2 * DAY_SECS
+            })
+        );
+        // Within one day, or after the watermark
+        assert_eq!(RollupSplit::of(Some(60), Some(DAY_SECS), Some(DAY_3)), None);
+        assert_eq!(RollupSplit::of(Some(DAY_3), None, Some(DAY_3)), None);
+        assert_eq!(RollupSplit::of(Some(i64::MAX), None, Some(DAY_3)), None);
+    }
+}
@@ -48,6 +48,20 @@ impl GeoIpService {
         })
     }
 
+    /// Build time of the City database as a Unix timestamp, when one is loaded
+    pub fn city_build_epoch(&self) -> Option<u64> {
+        self.city_reader
+            .as_ref()
+            .map(|reader| reader.metadata.build_epoch)
+    }
+
+    /// Build time of the ASN database as a Unix timestamp, when one is loaded
+    pub fn asn_build_epoch(&self) -> Option<u64> {
+        self.asn_reader
+            .as_ref()
+            .map(|reader| reader.metadata.build_epoch)
+    }
+
     /// Lookup geographic location for an IP address
     ///
     /// # Arguments
@@ -56,15 +70,8 @@ impl GeoIpService {
     /// # Returns
     /// GeoLocation information if found, or a default/unknown location
     pub fn lookup(&self, ip: IpAddr) -> GeoLocation {
-        let ip_version = match ip {
-            IpAddr::V4(_) => 4,
-            IpAddr::V6(_) => 6,
-        };
-
-        let mut geo_location = GeoLocation {
-            ip_version,
-            ..Default::default()
-        };
+        let ip = ip.to_canonical();
+        let mut geo_location = GeoLocation::unresolved(ip);
 
         // Try to lookup city information (which includes country)
         if let Some(ref reader) = self.city_reader {
@@ -149,4 +156,13 @@ mod tests {
         let result = GeoIpService::new(None, None);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_lookup_counts_mapped_addresses_as_ipv4() {
+        let service = GeoIpService::new(None, None).unwrap();
+        let version = |ip: &str| service.lookup(ip.parse().unwrap()).ip_version;
+        assert_eq!(version("203.0.113.1"), 4);
+        assert_eq!(version("::ffff:203.0.113.1"), 4);
+        assert_eq!(version("2001:db8::1"), 6);
+    }
 }
@@ -22,19 +22,43 @@ use crate::config::{AnalyticsConfig, TrustedProxyMode};
 /// * `config` - Analytics configuration with trust settings
 ///
 /// # Returns
-/// The client IP address, extracted according to the trust configuration
+/// The client IP address, extracted according to the trust configuration.
+/// IPv4-mapped IPv6 addresses (`::ffff:1.2.3.4`, as a dual-stack listener
+/// reports IPv4 peers) are returned as the IPv4 address they carry.
 pub fn extract_client_ip(
     headers: &HeaderMap,
     socket_addr: IpAddr,
     config: &AnalyticsConfig,
 ) -> IpAddr {
-    match config.trusted_proxy_mode {
+    let socket_addr = socket_addr.to_canonical();
+    let client_ip = match config.trusted_proxy_mode {
         TrustedProxyMode::Cloudflare => extract_cloudflare_ip(headers).unwrap_or_else(|| {
             warn!("CF-Connecting-IP header missing in Cloudflare mode, using socket address");
             socket_addr
         }),
         TrustedProxyMode::Standard => extract_standard_ip(headers, config).unwrap_or(socket_addr),
         TrustedProxyMode::None => socket_addr,
+    };
+    client_ip.to_canonical()
+}
+
+/// Whether `peer`, the socket remote address, is a proxy whose forwarded
+/// headers may be believed. Cloudflare mode trusts every peer, as it does for
+/// `CF-Connecting-IP`; standard mode trusts peers in `trusted_proxies`, or
+/// every peer when no ranges are configured.
+pub fn is_trusted_proxy(peer: IpAddr, config: &AnalyticsConfig) -> bool {
+    let peer = peer.to_canonical();
+    match config.trusted_proxy_mode {
+        TrustedProxyMode::None => false,
+        TrustedProxyMode::Cloudflare => true,
+        TrustedProxyMode::Standard => {
+            config.trusted_proxies.is_empty()
+                || config
+                    .trusted_proxies
+                    .iter()
+                    .filter_map(|cidr| IpNet::from_str(cidr).ok())
+                    .any(|range| range.contains(&peer))
+        }
     }
 }
 
@@ -79,7 +103,7 @@ fn extract_from_forwarded(headers: &HeaderMap, config: &AnalyticsConfig) -> Opti
                     .unwrap_or(value);
 
                 if let Ok(ip) = ip_str.parse::<IpAddr>() {
-                    ips.push(ip);
+                    ips.push(ip.to_canonical());
                 }
             }
         }
@@ -100,6 +124,7 @@ fn extract_from_x_forwarded_for(headers: &HeaderMap, config: &AnalyticsConfig) -
     let ips: Vec<IpAddr> = xff
         .split(',')
         .filter_map(|s| s.trim().parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical())
         .collect();
 
     if ips.is_empty() {
@@ -178,8 +203,10 @@ fn is_ip_in_trusted_ranges(ip: IpAddr, trusted_ranges: &[IpNet]) -> bool {
 ///
 /// - IPv4: Truncate to /24 (zero last octet)
 /// - IPv6: Truncate to /48 (zero last 80 bits)
+///
+/// IPv4-mapped IPv6 addresses are truncated as the IPv4 address they carry.
 pub fn anonymize_ip(ip: IpAddr) -> IpAddr {
-    match ip {
+    match ip.to_canonical() {
         IpAddr::V4(addr) => {
             let octets = addr.octets();
             IpAddr::V4(std::net::Ipv4Addr::new(octets[0], octets[1], octets[2], 0))
@@ -216,6 +243,8 @@ mod tests {
             trusted_proxies: vec![],
             num_trusted_proxies: None,
             flush_interval_secs: 60,
+            daily_rollup_after_days: 30,
+            sampling: Default::default(),
         }
     }
 
@@ -338,4 +367,83 @@ mod tests {
         // Should return 203.0.113.1 (first untrusted IP from right)
         assert_eq!(result, "203.0.113.1".parse::<IpAddr>().unwrap());
     }
+
+    #[test]
+    fn test_is_trusted_proxy() {
+        let inside: IpAddr = "10.0.0.5".parse().unwrap();
+        let outside: IpAddr = "203.0.113.9".parse().unwrap();
+
+        assert!(!is_trusted_proxy(
+            inside,
+            &create_config(TrustedProxyMode::None)
+        ));
+        assert!(is_trusted_proxy(
+            outside,
+            &create_config(TrustedProxyMode::Cloudflare)
+        ));
+
+        let mut config = create_config(TrustedProxyMode::Standard);
+        assert!(is_trusted_proxy(outside, &config));
+        config.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+        assert!(is_trusted_proxy(inside, &config));
+        assert!(!is_trusted_proxy(outside, &config));
+        assert!(is_trusted_proxy(
+            "::ffff:10.0.0.5".parse().unwrap(),
+            &config
+        ));
+    }
+
+    #[test]
+    fn test_mapped_socket_address_is_ipv4() {
+        let mapped: IpAddr = "::ffff:198.51.100.7".parse().unwrap();
+        let ipv4: IpAddr = "198.51.100.7".parse().unwrap();
+        let headers = HeaderMap::new();
+
+        for mode in [TrustedProxyMode::None, TrustedProxyMode::Standard] {
+            let result = extract_client_ip(&headers, mapped, &create_config(mode));
+            assert_eq!(result, ipv4);
+            assert!(result.is_ipv4());
+        }
+        // Cloudflare mode falls back to the socket address without its header
+        let config = create_config(TrustedProxyMode::Cloudflare);
+        assert_eq!(extract_client_ip(&headers, mapped, &config), ipv4);
+
+        // Real IPv6 addresses are left alone
+        let ipv6: IpAddr = "2001:db8::1".parse().unwrap();
+        let config = create_config(TrustedProxyMode::None);
+        assert_eq!(extract_client_ip(&headers, ipv6, &config), ipv6);
+    }
+
+    #[test]
+    fn test_mapped_forwarded_addresses_are_ipv4() {
+        let socket_addr: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+        let mut config = create_config(TrustedProxyMode::Standard);
+        config.trusted_proxies = vec!["10.0.0.0/8".to_string()];
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("::ffff:203.0.113.1, ::ffff:10.0.0.2"),
+        );
+        let result = extract_client_ip(&headers, socket_addr, &config);
+        assert_eq!(result, "203.0.113.1".parse::<IpAddr>().unwrap());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "cf-connecting-ip",
+            HeaderValue::from_static("::ffff:203.0.113.2"),
+        );
+        let config = create_config(TrustedProxyMode::Cloudflare);
+        let result = extract_client_ip(&headers, socket_addr, &config);
+        assert_eq!(result, "203.0.113.2".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_anonymize_mapped_address_as_ipv4() {
+        let mapped: IpAddr = "::ffff:198.51.100.7".parse().unwrap();
+        assert_eq!(
+            anonymize_ip(mapped),
+            "198.51.100.0".parse::<IpAddr>().unwrap()
+        );
+    }
 }
@@ -5,20 +5,36 @@
 //!
 //! The feature is designed to be optional (via runtime configuration)
 //! and does not affect core URL redirection performance when disabled.
+//! Builds without the `analytics` cargo feature leave out the GeoIP reader
+//! and cannot record analytics, but still read what is stored.
 
 pub mod aggregator;
+pub mod daily;
+#[cfg(feature = "analytics")]
 pub mod geoip;
 pub mod ip_extractor;
 pub mod models;
+pub mod sampling;
 pub mod storage;
 
 // Constants for analytics
 pub const DROPPED_DIMENSION_MARKER: &str = "<dropped>";
-pub const DEFAULT_IP_VERSION: i32 = 4; // IPv4
+/// `ip_version` of pruned rows whose version was dropped; reported as
+/// unknown rather than counted as either version
+pub const DROPPED_IP_VERSION: i32 = 0;
+/// `settings` key holding the runtime analytics switch, `"true"` or `"false"`
+pub const ANALYTICS_ENABLED_SETTING: &str = "analytics_enabled";
 
 // Re-export commonly used types
 pub use aggregator::AnalyticsAggregator;
+#[cfg(feature = "analytics")]
 pub use geoip::GeoIpService;
-pub use ip_extractor::extract_client_ip;
-pub use models::{AnalyticsEvent, AnalyticsRecord, AnalyticsRollup, GeoLocation, IpVersion};
-pub use storage::{AnalyticsAggregate, AnalyticsEntry, AnalyticsGroupBy, AnalyticsQuery};
+pub use ip_extractor::{extract_client_ip, is_trusted_proxy};
+pub use models::{
+    AliasRollup, AnalyticsEstimate, AnalyticsEvent, AnalyticsRecord, AnalyticsRollup, GeoLocation,
+    IpVersion, UNKNOWN_IP_VERSION_LABEL,
+};
+pub use storage::{
+    AnalyticsAggregate, AnalyticsEntry, AnalyticsExportScope, AnalyticsGroupBy, AnalyticsQuery,
+    UnknownGroupBy,
+};
@@ -1,6 +1,8 @@
 //! Data models for analytics
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
 
@@ -43,6 +45,20 @@ impl Default for GeoLocation {
     }
 }
 
+impl GeoLocation {
+    /// An unknown location for a visit from `ip`, used when no GeoIP
+    /// database is loaded. Only the IP version is known.
+    pub fn unresolved(ip: IpAddr) -> Self {
+        Self {
+            ip_version: match IpVersion::of(ip) {
+                IpVersion::V4 => 4,
+                IpVersion::V6 => 6,
+            },
+            ..Default::default()
+        }
+    }
+}
+
 /// Analytics record for a single visit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalyticsRecord {
@@ -71,6 +87,13 @@ pub struct AnalyticsEvent {
 
     /// Client IP address (for deferred GeoIP lookup)
     pub client_ip: IpAddr,
+
+    /// Alias the visit came through, when it was not the short code itself
+    pub alias_used: Option<Arc<str>>,
+
+    /// Visits the event stands for: 1, or N when load sampling kept it as
+    /// one of N (see [`crate::analytics::sampling`])
+    pub weight: u32,
 }
 
 /// Aggregated analytics key for grouping
@@ -96,6 +119,9 @@ pub struct AnalyticsKey {
 
     /// IP version
     pub ip_version: u8,
+
+    /// Alias the visits came through
+    pub alias_used: Option<Arc<str>>,
 }
 
 impl AnalyticsKey {
@@ -112,6 +138,7 @@ impl AnalyticsKey {
             city: record.geo_location.city.clone(),
             asn: record.geo_location.asn,
             ip_version: record.geo_location.ip_version,
+            alias_used: None,
         }
     }
 
@@ -128,6 +155,7 @@ impl AnalyticsKey {
             city: geo_location.city.clone(),
             asn: geo_location.asn,
             ip_version: geo_location.ip_version,
+            alias_used: event.alias_used.clone(),
         }
     }
 }
@@ -140,19 +168,49 @@ pub struct AnalyticsValue {
     /// Stored as `i64` to match the database column and avoid lossy casts on
     /// the flush path.
     pub count: i64,
+
+    /// Of `count`, visits carried by sampled events: extrapolated rather
+    /// than counted one by one.
+    pub estimated: i64,
+}
+
+impl AnalyticsValue {
+    /// The value of one event standing for `weight` visits.
+    pub fn of(weight: u32) -> Self {
+        let count = i64::from(weight);
+        Self {
+            count,
+            estimated: if weight > 1 { count } else { 0 },
+        }
+    }
+
+    /// Add `other`'s visits to this value.
+    pub fn add(&mut self, other: &AnalyticsValue) {
+        self.count += other.count;
+        self.estimated += other.estimated;
+    }
 }
 
 /// IP protocol version of a visitor, constrained to the only two valid values.
 ///
 /// The database persists this as the integer `4` or `6`; this enum keeps
 /// invalid values (e.g. `7`) unrepresentable while data is in flight.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IpVersion {
     V4,
     V6,
 }
 
 impl IpVersion {
+    /// The version of `ip`. IPv4-mapped IPv6 addresses (`::ffff:1.2.3.4`)
+    /// are IPv4 visitors seen through a dual-stack socket and count as IPv4.
+    pub fn of(ip: IpAddr) -> Self {
+        match ip.to_canonical() {
+            IpAddr::V4(_) => IpVersion::V4,
+            IpAddr::V6(_) => IpVersion::V6,
+        }
+    }
+
     /// Interpret a raw version number (as carried by [`GeoLocation`]). Any value
     /// other than `6` is treated as IPv4, matching the historical default.
     pub fn from_num(n: u8) -> Self {
@@ -169,8 +227,20 @@ impl IpVersion {
             IpVersion::V6 => 6,
         }
     }
+
+    /// The label `group_by=ip_version` reports for this version.
+    pub fn label(self) -> &'static str {
+        match self {
+            IpVersion::V4 => "IPv4",
+            IpVersion::V6 => "IPv6",
+        }
+    }
 }
 
+/// The `group_by=ip_version` label for visits whose version is not known,
+/// such as visits whose version was dropped when old analytics were pruned.
+pub const UNKNOWN_IP_VERSION_LABEL: &str = "unknown";
+
 /// A pre-aggregated analytics bucket ready to be upserted into storage.
 ///
 /// Replaces an 8-tuple whose fields were easy to mis-order. Construction from
@@ -186,6 +256,10 @@ pub struct AnalyticsRollup {
     pub asn: Option<i64>,
     pub ip_version: IpVersion,
     pub visit_count: i64,
+    /// Of `visit_count`, visits extrapolated from sampled events
+    pub estimated_visits: i64,
+    /// Alias the visits came through; `None` for visits to the code itself
+    pub alias_used: Option<String>,
 }
 
 impl AnalyticsRollup {
@@ -201,6 +275,109 @@ impl AnalyticsRollup {
             asn: key.asn.map(|a| a as i64),
             ip_version: IpVersion::from_num(key.ip_version),
             visit_count: value.count,
+            estimated_visits: value.estimated,
+            alias_used: key.alias_used.map(|alias| alias.to_string()),
         }
     }
+
+    /// Split rollups into rows for the `analytics` table and per-alias counts
+    /// for `alias_analytics`. The `analytics` table does not record the alias,
+    /// so rows that differ only by alias are merged into one.
+    pub fn split_aliases(records: Vec<Self>) -> (Vec<Self>, Vec<AliasRollup>) {
+        if records.iter().all(|record| record.alias_used.is_none()) {
+            return (records, Vec::new());
+        }
+
+        let mut merged: Vec<Self> = Vec::with_capacity(records.len());
+        let mut positions: HashMap<_, usize> = HashMap::new();
+        let mut aliases: Vec<AliasRollup> = Vec::new();
+        let mut alias_positions: HashMap<_, usize> = HashMap::new();
+        for mut record in records {
+            if let Some(alias_code) = record.alias_used.take() {
+                let key = (
+                    record.short_code.clone(),
+                    alias_code.clone(),
+                    record.time_bucket,
+                );
+                match alias_positions.entry(key) {
+                    Entry::Occupied(entry) => {
+                        aliases[*entry.get()].visit_count += record.visit_count
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(aliases.len());
+                        aliases.push(AliasRollup {
+                            short_code: record.short_code.clone(),
+                            alias_code,
+                            time_bucket: record.time_bucket,
+                            visit_count: record.visit_count,
+                        });
+                    }
+                }
+            }
+
+            let key = (
+                record.short_code.clone(),
+                record.time_bucket,
+                record.country_code.clone(),
+                record.region.clone(),
+                record.city.clone(),
+                record.asn,
+                record.ip_version,
+            );
+            match positions.entry(key) {
+                Entry::Occupied(entry) => {
+                    let row = &mut merged[*entry.get()];
+                    row.visit_count += record.visit_count;
+                    row.estimated_visits += record.estimated_visits;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(merged.len());
+                    merged.push(record);
+                }
+            }
+        }
+
+        (merged, aliases)
+    }
+
+    /// Estimated visits in `records` per short code and hour, for the
+    /// `analytics_estimates` table. Rows without estimates are left out.
+    pub fn estimates(records: &[Self]) -> Vec<AnalyticsEstimate> {
+        let mut estimates: Vec<AnalyticsEstimate> = Vec::new();
+        let mut positions: HashMap<(&str, i64), usize> = HashMap::new();
+        for record in records.iter().filter(|record| record.estimated_visits > 0) {
+            match positions.entry((&record.short_code, record.time_bucket)) {
+                Entry::Occupied(entry) => {
+                    estimates[*entry.get()].estimated_visits += record.estimated_visits
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(estimates.len());
+                    estimates.push(AnalyticsEstimate {
+                        short_code: record.short_code.clone(),
+                        time_bucket: record.time_bucket,
+                        estimated_visits: record.estimated_visits,
+                    });
+                }
+            }
+        }
+        estimates
+    }
+}
+
+/// Visits to a short code within one hour bucket that were extrapolated
+/// from sampled events rather than counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalyticsEstimate {
+    pub short_code: String,
+    pub time_bucket: i64,
+    pub estimated_visits: i64,
+}
+
+/// Visits to a short code through one of its aliases within one hour bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasRollup {
+    pub short_code: String,
+    pub alias_code: String,
+    pub time_bucket: i64,
+    pub visit_count: i64,
 }
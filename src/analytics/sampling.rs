@@ -0,0 +1,279 @@
+//! Sampling of visit events while the analytics queue is backed up.
+//!
+//! A burst of redirects can fill the analytics actor's channel faster than
+//! it drains, and events past it pile up in the shared buffer. Once the
+//! channel is `ANALYTICS_SAMPLING_HIGH_WATER_PERCENT` full, the sampler keeps
+//! one event in N, where N is `1 / ANALYTICS_SAMPLING_FLOOR_RATE` rounded,
+//! and records each kept event as N visits. Counts stay right on average
+//! while the queue sees a fraction of the traffic. Sampling stops only once
+//! the channel is back under `ANALYTICS_SAMPLING_LOW_WATER_PERCENT`, so a
+//! queue hovering at the mark does not switch on every event.
+//!
+//! Weighted visits are tracked as estimated through aggregation and into
+//! `analytics_estimates`, so the analytics API can say how much of a count
+//! was extrapolated.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::clock::{system_clock, Clock};
+use crate::config::AnalyticsSamplingConfig;
+
+/// Where the sampler stands, as reported by `GET /api/stats/analytics`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SamplingStats {
+    /// Whether a floor rate is configured
+    pub enabled: bool,
+    /// Whether events are being sampled right now
+    pub active: bool,
+    /// Share of events kept while sampling
+    pub rate: f64,
+    /// Visits each kept event stands for while sampling
+    pub one_in: u32,
+    /// Queue depth at which sampling starts
+    pub high_water: usize,
+    /// Queue depth at which sampling stops
+    pub low_water: usize,
+    /// When the current sampling period began (Unix seconds)
+    pub active_since: Option<i64>,
+    /// Times sampling started since the server did
+    pub activations: u64,
+    /// Events recorded with a weight while sampling
+    pub sampled_events: u64,
+    /// Events left out while sampling
+    pub skipped_events: u64,
+}
+
+/// Decides, per event, whether to record it and how many visits it stands
+/// for.
+#[derive(Debug)]
+pub struct AnalyticsSampler {
+    /// Keep one event in this many while sampling; 1 never samples
+    one_in: u32,
+    high_water: usize,
+    low_water: usize,
+    active: AtomicBool,
+    /// Start of the current sampling period (Unix seconds), 0 when inactive
+    active_since: AtomicI64,
+    activations: AtomicU64,
+    sampled_events: AtomicU64,
+    skipped_events: AtomicU64,
+    clock: Arc<dyn Clock>,
+}
+
+impl AnalyticsSampler {
+    /// A sampler that records every event.
+    pub fn disabled() -> Self {
+        Self::with_thresholds(1, usize::MAX, usize::MAX, system_clock())
+    }
+
+    /// A sampler for a queue of `capacity` events, as `config` describes,
+    /// dating sampling periods by `clock`.
+    pub fn new(config: &AnalyticsSamplingConfig, capacity: usize, clock: Arc<dyn Clock>) -> Self {
+        let Some(one_in) = config.one_in() else {
+            return Self::disabled();
+        };
+        let mark = |percent: u8| (capacity * usize::from(percent.min(100)) / 100).max(1);
+        let high_water = mark(config.high_water_percent);
+        let low_water = mark(config.low_water_percent).min(high_water);
+        Self::with_thresholds(one_in, high_water, low_water, clock)
+    }
+
+    fn with_thresholds(
+        one_in: u32,
+        high_water: usize,
+        low_water: usize,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            one_in,
+            high_water,
+            low_water,
+            active: AtomicBool::new(false),
+            active_since: AtomicI64::new(0),
+            activations: AtomicU64::new(0),
+            sampled_events: AtomicU64::new(0),
+            skipped_events: AtomicU64::new(0),
+            clock,
+        }
+    }
+
+    /// The number of visits to record an event as while the queue holds
+    /// `depth` events, or `None` to leave the event out.
+    pub fn admit(&self, depth: usize) -> Option<u32> {
+        self.admit_with(depth, |one_in| rand::random_ratio(1, one_in))
+    }
+
+    /// [`admit`](Self::admit) with `keep` deciding whether a sampled event
+    /// is kept, given N.
+    fn admit_with(&self, depth: usize, keep: impl FnOnce(u32) -> bool) -> Option<u32> {
+        if self.one_in <= 1 || !self.update(depth) {
+            return Some(1);
+        }
+        if keep(self.one_in) {
+            self.sampled_events.fetch_add(1, Ordering::Relaxed);
+            Some(self.one_in)
+        } else {
+            self.skipped_events.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+
+    /// Start or stop sampling for a queue of `depth` events and return
+    /// whether it is on.
+    fn update(&self, depth: usize) -> bool {
+        if depth >= self.high_water {
+            if !self.active.load(Ordering::Relaxed) && !self.active.swap(true, Ordering::Relaxed) {
+                self.active_since
+                    .store(self.clock.now_epoch_secs(), Ordering::Relaxed);
+                self.activations.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    depth,
+                    high_water = self.high_water,
+                    one_in = self.one_in,
+                    "Analytics queue backed up; sampling visit events"
+                );
+            }
+            true
+        } else if depth <= self.low_water {
+            if self.active.load(Ordering::Relaxed) && self.active.swap(false, Ordering::Relaxed) {
+                let since = self.active_since.swap(0, Ordering::Relaxed);
+                info!(
+                    depth,
+                    low_water = self.low_water,
+                    sampled_for_secs = self.clock.now_epoch_secs() - since,
+                    "Analytics queue recovered; recording every visit event"
+                );
+            }
+            false
+        } else {
+            self.active.load(Ordering::Relaxed)
+        }
+    }
+
+    pub fn stats(&self) -> SamplingStats {
+        let active = self.active.load(Ordering::Relaxed);
+        SamplingStats {
+            enabled: self.one_in > 1,
+            active,
+            rate: 1.0 / f64::from(self.one_in),
+            one_in: self.one_in,
+            high_water: self.high_water,
+            low_water: self.low_water,
+            active_since: Some(self.active_since.load(Ordering::Relaxed))
+                .filter(|since| active && *since > 0),
+            activations: self.activations.load(Ordering::Relaxed),
+            sampled_events: self.sampled_events.load(Ordering::Relaxed),
+            skipped_events: self.skipped_events.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::models::AnalyticsValue;
+    use crate::clock::FakeClock;
+    use tokio::sync::mpsc;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn sampler(capacity: usize) -> AnalyticsSampler {
+        AnalyticsSampler::new(
+            &AnalyticsSamplingConfig {
+                floor_rate: Some(0.25),
+                ..AnalyticsSamplingConfig::default()
+            },
+            capacity,
+            Arc::new(FakeClock::at_epoch_ms(NOW * 1000)),
+        )
+    }
+
+    fn depth<T>(channel: &mpsc::Sender<T>) -> usize {
+        channel.max_capacity() - channel.capacity()
+    }
+
+    #[tokio::test]
+    async fn sampling_starts_at_high_water_and_stops_at_low_water() {
+        let (sender, mut receiver) = mpsc::channel(10);
+        let sampler = sampler(sender.max_capacity());
+        assert_eq!((sampler.high_water, sampler.low_water), (8, 5));
+
+        // Saturate the queue: nothing drains it
+        let mut crossed = None;
+        while sender.try_send(()).is_ok() {
+            if sampler.admit_with(depth(&sender), |_| true) == Some(4) && crossed.is_none() {
+                crossed = Some(depth(&sender));
+            }
+        }
+        assert_eq!(crossed, Some(8));
+        assert_eq!(depth(&sender), 10);
+        assert!(sampler.stats().active);
+        assert_eq!(sampler.stats().active_since, Some(NOW));
+
+        // Draining to between the marks keeps sampling on
+        for _ in 0..3 {
+            receiver.recv().await.unwrap();
+        }
+        assert_eq!(sampler.admit_with(depth(&sender), |_| false), None);
+        receiver.recv().await.unwrap();
+        assert_eq!(sampler.admit_with(depth(&sender), |_| true), Some(4));
+
+        // Down to the low mark every event counts once again
+        receiver.recv().await.unwrap();
+        assert_eq!(depth(&sender), 5);
+        assert_eq!(sampler.admit_with(depth(&sender), |_| false), Some(1));
+        assert!(!sampler.stats().active);
+
+        // Refilling past the low mark alone does not restart it
+        sender.try_send(()).unwrap();
+        sender.try_send(()).unwrap();
+        assert_eq!(sampler.admit_with(depth(&sender), |_| false), Some(1));
+        sender.try_send(()).unwrap();
+        assert_eq!(sampler.admit_with(depth(&sender), |_| false), None);
+
+        let stats = sampler.stats();
+        assert_eq!(stats.activations, 2);
+        assert_eq!(stats.skipped_events, 2);
+        assert_eq!(stats.sampled_events, 4);
+    }
+
+    #[test]
+    fn kept_events_scale_back_to_the_visits_they_stand_for() {
+        let sampler = sampler(10);
+        let mut total = AnalyticsValue::default();
+        for seen in 1..=1_000 {
+            if let Some(weight) = sampler.admit_with(10, |one_in| seen % one_in == 0) {
+                total.add(&AnalyticsValue::of(weight));
+            }
+        }
+        assert_eq!(total.count, 1_000);
+        assert_eq!(total.estimated, 1_000);
+        assert_eq!(sampler.stats().sampled_events, 250);
+        assert_eq!(sampler.stats().skipped_events, 750);
+
+        // Unsampled visits are counted, not estimated
+        total.add(&AnalyticsValue::of(1));
+        assert_eq!((total.count, total.estimated), (1_001, 1_000));
+    }
+
+    #[test]
+    fn floor_rates_outside_the_open_unit_interval_never_sample() {
+        for floor_rate in [None, Some(0.0), Some(1.0), Some(1.5), Some(-0.5), Some(0.8)] {
+            let config = AnalyticsSamplingConfig {
+                floor_rate,
+                ..AnalyticsSamplingConfig::default()
+            };
+            let sampler = AnalyticsSampler::new(&config, 10, system_clock());
+            assert_eq!(sampler.admit_with(10, |_| false), Some(1), "{floor_rate:?}");
+            assert!(!sampler.stats().enabled);
+        }
+        let config = AnalyticsSamplingConfig {
+            floor_rate: Some(0.3),
+            ..AnalyticsSamplingConfig::default()
+        };
+        assert_eq!(config.one_in(), Some(3));
+    }
+}
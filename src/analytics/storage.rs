@@ -1,6 +1,8 @@
 //! Analytics storage models
 
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use thiserror::Error;
 
 /// Analytics record stored in database
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -43,6 +45,67 @@ pub enum AnalyticsGroupBy {
     Asn,
     Hour,
     Day,
+    /// Alias the visit came through; visits to the code itself are left out
+    #[serde(rename = "alias_used")]
+    AliasUsed,
+    /// `IPv4`, `IPv6`, or `unknown` for visits whose version was pruned
+    #[serde(rename = "ip_version")]
+    IpVersion,
+}
+
+impl AnalyticsGroupBy {
+    /// Every dimension, in the order they are documented.
+    pub const ALL: [Self; 8] = [
+        Self::Country,
+        Self::Region,
+        Self::City,
+        Self::Asn,
+        Self::Hour,
+        Self::Day,
+        Self::AliasUsed,
+        Self::IpVersion,
+    ];
+
+    /// The `group_by` query value naming this dimension.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Country => "country",
+            Self::Region => "region",
+            Self::City => "city",
+            Self::Asn => "asn",
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::AliasUsed => "alias_used",
+            Self::IpVersion => "ip_version",
+        }
+    }
+}
+
+/// A `group_by` value that names no [`AnalyticsGroupBy`] dimension.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "Unknown group_by '{0}', expected one of: country, region, city, asn, hour, day, alias_used, ip_version"
+)]
+pub struct UnknownGroupBy(pub String);
+
+impl FromStr for AnalyticsGroupBy {
+    type Err = UnknownGroupBy;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|group_by| group_by.as_str() == value)
+            .ok_or_else(|| UnknownGroupBy(value.to_string()))
+    }
+}
+
+/// Which links an analytics export covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyticsExportScope<'a> {
+    /// A single short code
+    Link(&'a str),
+    /// Every link created by this user
+    Owner(&'a str),
 }
 
 /// Aggregated analytics result
@@ -50,4 +113,36 @@ pub enum AnalyticsGroupBy {
 pub struct AnalyticsAggregate {
     pub dimension: String,
     pub visit_count: i64,
+    /// Set on rows counting visits whose dimension was dropped when old
+    /// analytics were pruned (`dimension` is the dropped-dimension marker)
+    #[sqlx(default)]
+    pub aggregated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_by_values_round_trip() {
+        for group_by in AnalyticsGroupBy::ALL {
+            assert_eq!(group_by.as_str().parse::<AnalyticsGroupBy>(), Ok(group_by));
+            let deserialized: AnalyticsGroupBy =
+                serde_json::from_value(serde_json::json!(group_by.as_str())).unwrap();
+            assert_eq!(deserialized, group_by);
+        }
+    }
+
+    #[test]
+    fn unknown_group_by_is_an_error() {
+        for value in ["regoin", "Country", "", "country_code"] {
+            assert_eq!(
+                value.parse::<AnalyticsGroupBy>(),
+                Err(UnknownGroupBy(value.to_string()))
+            );
+        }
+        assert!(UnknownGroupBy("regoin".to_string())
+            .to_string()
+            .contains("ip_version"));
+    }
 }
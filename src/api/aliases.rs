@@ -0,0 +1,103 @@
+//! Aliases: extra short codes that redirect to an existing link.
+//!
+//! `POST /api/links/{code}/aliases` attaches a new code to the link at `code`.
+//! The alias redirects to the link's destination, and its clicks and analytics
+//! count toward the link, with the alias kept as the `alias_used` analytics
+//! dimension. `DELETE /api/links/{code}/aliases/{alias}` removes one alias and
+//! leaves the link and its other aliases alone; like every link, the alias row
+//! is deactivated rather than deleted, so its code stays taken.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{
+    authorize_url_mutation, validated_short_code_max_length, ApiError, AppState,
+    ShortenedUrlResponse, SuccessResponse,
+};
+use super::public_url::PublicBaseUrl;
+use crate::auth::AuthClaims;
+use crate::models::ShortenedUrl;
+use crate::storage::StorageError;
+
+#[derive(Debug, Deserialize)]
+pub struct AddAliasRequest {
+    pub code: String,
+}
+
+/// Attach another short code to a link (owner or admin)
+pub async fn add_alias(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Json(payload): Json<AddAliasRequest>,
+) -> Result<(StatusCode, Json<ShortenedUrlResponse>), ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    let max_short_code_length = validated_short_code_max_length(state.config.short_code_max_length);
+    if payload.code.is_empty() || payload.code.len() > max_short_code_length {
+        return Err(ApiError::BadRequest(format!(
+            "Alias must be 1-{} characters",
+            max_short_code_length
+        )));
+    }
+
+    authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    let created_by = claims.as_ref().and_then(|c| c.user_id());
+    match state
+        .storage
+        .add_alias(&code, &payload.code, created_by.as_deref())
+        .await
+    {
+        Ok(Some(alias)) => Ok((
+            StatusCode::CREATED,
+            Json(ShortenedUrlResponse::with_base(
+                alias,
+                Some(public_base.as_str()),
+            )),
+        )),
+        Ok(None) => Err(ApiError::NotFound("URL not found".to_string())),
+        Err(StorageError::Conflict) => {
+            Err(ApiError::Conflict("Short code already exists".to_string()))
+        }
+        Err(e) => Err(ApiError::storage("Failed to add alias", e)),
+    }
+}
+
+/// Remove one alias of a link (owner or admin)
+pub async fn remove_alias(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path((encoded_code, encoded_alias)): Path<(String, String)>,
+) -> Result<Json<SuccessResponse>, ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    let alias_code = decode_code_path_param(&encoded_alias)?;
+
+    authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    let alias = state
+        .storage
+        .get_authoritative(&alias_code)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load alias", e))?;
+    let is_live_alias = |alias: Arc<ShortenedUrl>| {
+        alias.is_active && alias.alias_of.as_deref() == Some(code.as_str())
+    };
+    if !alias.is_some_and(is_live_alias) {
+        return Err(ApiError::NotFound("Alias not found".to_string()));
+    }
+
+    match state.storage.deactivate(&alias_code).await {
+        Ok(true) => Ok(Json(SuccessResponse {
+            message: "Alias removed".to_string(),
+        })),
+        Ok(false) => Err(ApiError::NotFound("Alias not found".to_string())),
+        Err(e) => Err(ApiError::storage("Failed to remove alias", e)),
+    }
+}
@@ -4,20 +4,33 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::analytics::{AnalyticsAggregate, AnalyticsAggregator, AnalyticsEntry, AnalyticsGroupBy};
+use crate::analytics::{
+    AnalyticsAggregate, AnalyticsAggregator, AnalyticsEntry, AnalyticsGroupBy, UnknownGroupBy,
+    ANALYTICS_ENABLED_SETTING, DROPPED_DIMENSION_MARKER, UNKNOWN_IP_VERSION_LABEL,
+};
 
 use super::code_param::decode_code_path_param;
-use crate::storage::Storage;
+use super::handlers::{is_user_admin, ApiError};
+use super::limits::{clamp_limit, ANALYTICS_DEFAULT_LIMIT};
+use super::stats_privacy::authorize_stats;
+use super::time_zone::time_zone_param;
+use crate::auth::AuthClaims;
+use crate::storage::{is_pool_timeout, Storage};
+use crate::timezone::local_day_start;
 
 /// State for analytics handlers
 pub struct AnalyticsState {
     pub storage: Arc<dyn Storage>,
     pub aggregator: Option<Arc<AnalyticsAggregator>>,
+    /// Largest `limit` accepted by the analytics endpoints
+    pub max_limit: i64,
+    /// `HIDE_STATS_BY_DEFAULT`, for links without their own `hide_stats`
+    pub hide_stats_by_default: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,16 +41,15 @@ pub struct AnalyticsQueryParams {
     /// End time (Unix timestamp)  
     pub end_time: Option<i64>,
 
-    /// Group by dimension
-    pub group_by: Option<AnalyticsGroupBy>,
+    /// Group by dimension (see [`AnalyticsGroupBy`]); parsed by the handler
+    /// so unknown values get a 422 instead of a generic rejection
+    pub group_by: Option<String>,
 
-    /// Limit results (default: 100, max: 1000)
-    #[serde(default = "default_limit")]
-    pub limit: i64,
-}
+    /// Limit results (default: 100, clamped to `PaginationConfig::analytics_max_limit`)
+    pub limit: Option<i64>,
 
-fn default_limit() -> i64 {
-    100
+    /// IANA time zone for `group_by=day` (default: UTC)
+    pub tz: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,6 +57,14 @@ pub struct AnalyticsResponse {
     pub entries: Vec<AnalyticsEntry>,
     pub total: usize,
     pub clicks: i64,
+    /// Effective row limit after clamping the requested limit
+    pub limit: i64,
+    /// Analytics from this Unix timestamp on are at full detail; earlier
+    /// visits were aggregated by pruning. `null` if nothing was pruned.
+    pub data_complete_since: Option<i64>,
+    /// Visits in range extrapolated from events sampled under load. When
+    /// not 0, the visit counts are partly estimates.
+    pub estimated_visits: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,11 +72,20 @@ pub struct AnalyticsAggregateResponse {
     pub aggregates: Vec<AnalyticsAggregate>,
     pub total: usize,
     pub clicks: i64,
+    /// Effective row limit after clamping the requested limit
+    pub limit: i64,
+    /// Analytics from this Unix timestamp on are at full detail; earlier
+    /// visits were aggregated by pruning. `null` if nothing was pruned.
+    pub data_complete_since: Option<i64>,
+    /// Visits in range extrapolated from events sampled under load. When
+    /// not 0, the visit counts are partly estimates.
+    pub estimated_visits: i64,
 }
 
 /// Get analytics for a specific short code
 pub async fn get_analytics(
     State(state): State<Arc<AnalyticsState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
     Path(encoded_code): Path<String>,
     Query(params): Query<AnalyticsQueryParams>,
 ) -> impl IntoResponse {
@@ -64,8 +93,28 @@ pub async fn get_analytics(
         Ok(value) => value,
         Err(err) => return err.into_response(),
     };
+    if let Err(err) = authorize_stats(
+        state.storage.as_ref(),
+        &claims,
+        &short_code,
+        state.hide_stats_by_default,
+    )
+    .await
+    {
+        return err.into_response();
+    }
+
+    let limit = clamp_limit(params.limit, ANALYTICS_DEFAULT_LIMIT, state.max_limit);
+
+    let data_complete_since = match data_complete_since(state.storage.as_ref()).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
 
-    let limit = params.limit.clamp(1, 1000);
+    let estimated_visits = match estimated_visits(&state, &short_code, &params, false).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
 
     // Get click count first
     let clicks = match state.storage.get_authoritative(&short_code).await {
@@ -84,23 +133,84 @@ pub async fn get_analytics(
                 entries,
                 total,
                 clicks,
+                limit,
+                data_complete_since,
+                estimated_visits,
             })
             .into_response()
         }
         Err(e) => {
             tracing::error!("Failed to get analytics: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to retrieve analytics",
-            )
-                .into_response()
+            (storage_failure_status(&e), "Failed to retrieve analytics").into_response()
         }
     }
 }
 
+/// Start of full-detail analytics, or the error response to send.
+async fn data_complete_since(
+    storage: &dyn Storage,
+) -> Result<Option<i64>, axum::response::Response> {
+    storage.analytics_complete_since().await.map_err(|e| {
+        tracing::error!("Failed to get analytics prune history: {}", e);
+        (storage_failure_status(&e), "Failed to retrieve analytics").into_response()
+    })
+}
+
+/// Estimated visits to `short_code` in the requested range, counting those
+/// still in memory when `with_pending`, or the error response to send.
+async fn estimated_visits(
+    state: &AnalyticsState,
+    short_code: &str,
+    params: &AnalyticsQueryParams,
+    with_pending: bool,
+) -> Result<i64, axum::response::Response> {
+    let stored = state
+        .storage
+        .get_estimated_visits(short_code, params.start_time, params.end_time)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get estimated visits: {}", e);
+            (storage_failure_status(&e), "Failed to retrieve analytics").into_response()
+        })?;
+    let pending = match &state.aggregator {
+        Some(aggregator) if with_pending => aggregator.pending_estimated_visits(short_code),
+        _ => 0,
+    };
+    Ok(stored + pending)
+}
+
+/// 503 when the connection pool timed out, 500 for any other storage failure.
+fn storage_failure_status(error: &anyhow::Error) -> StatusCode {
+    if is_pool_timeout(error) {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Whether an aggregate row counts visits whose dimension pruning dropped.
+/// Pruned IP versions are reported as unknown rather than the marker.
+pub(crate) fn is_aggregated(group_by: AnalyticsGroupBy, dimension: &str) -> bool {
+    match group_by {
+        AnalyticsGroupBy::IpVersion => dimension == UNKNOWN_IP_VERSION_LABEL,
+        _ => dimension == DROPPED_DIMENSION_MARKER,
+    }
+}
+
+/// Resolve an optional `group_by` value, defaulting to country.
+fn group_by_param(group_by: Option<&str>) -> Result<AnalyticsGroupBy, ApiError> {
+    match group_by {
+        None => Ok(AnalyticsGroupBy::Country),
+        Some(value) => value
+            .parse()
+            .map_err(|error: UnknownGroupBy| ApiError::UnprocessableEntity(error.to_string())),
+    }
+}
+
 /// Get aggregated analytics for a specific short code
 pub async fn get_analytics_aggregate(
     State(state): State<Arc<AnalyticsState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
     Path(encoded_code): Path<String>,
     Query(params): Query<AnalyticsQueryParams>,
 ) -> impl IntoResponse {
@@ -108,27 +218,69 @@ pub async fn get_analytics_aggregate(
         Ok(value) => value,
         Err(err) => return err.into_response(),
     };
+    if let Err(err) = authorize_stats(
+        state.storage.as_ref(),
+        &claims,
+        &short_code,
+        state.hide_stats_by_default,
+    )
+    .await
+    {
+        return err.into_response();
+    }
+
+    let time_zone = match time_zone_param(params.tz.as_deref()) {
+        Ok(value) => value,
+        Err(err) => return err.into_response(),
+    };
 
-    let limit = params.limit.clamp(1, 1000);
-    let group_by = params.group_by.unwrap_or(AnalyticsGroupBy::Country);
+    let group_by = match group_by_param(params.group_by.as_deref()) {
+        Ok(value) => value,
+        Err(err) => return err.into_response(),
+    };
+
+    let limit = clamp_limit(params.limit, ANALYTICS_DEFAULT_LIMIT, state.max_limit);
+
+    let data_complete_since = match data_complete_since(state.storage.as_ref()).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    let estimated_visits = match estimated_visits(&state, &short_code, &params, true).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
 
     // Get aggregates from database
-    let db_aggregates = match state
-        .storage
-        .get_analytics_aggregate(
-            &short_code,
-            params.start_time,
-            params.end_time,
-            group_by,
-            limit,
-        )
-        .await
-    {
+    let db_result = if group_by == AnalyticsGroupBy::Day {
+        state
+            .storage
+            .get_analytics_daily_aggregate(
+                &short_code,
+                params.start_time,
+                params.end_time,
+                time_zone,
+                limit,
+            )
+            .await
+    } else {
+        state
+            .storage
+            .get_analytics_aggregate(
+                &short_code,
+                params.start_time,
+                params.end_time,
+                group_by,
+                limit,
+            )
+            .await
+    };
+    let db_aggregates = match db_result {
         Ok(agg) => agg,
         Err(e) => {
             tracing::error!("Failed to get analytics aggregate: {}", e);
             return (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                storage_failure_status(&e),
                 "Failed to retrieve analytics aggregate",
             )
                 .into_response();
@@ -136,9 +288,21 @@ pub async fn get_analytics_aggregate(
     };
 
     // If we have an analytics aggregator, get in-memory data for near real-time display
-    let combined_aggregates = if let Some(aggregator) = &state.aggregator {
-        // Get in-memory aggregates (pending data not yet in DB)
-        let in_memory = aggregator.get_in_memory_aggregate(&short_code, group_by);
+    let mut combined_aggregates = if let Some(aggregator) = &state.aggregator {
+        // Get in-memory aggregates (pending data not yet in DB). Days are
+        // regrouped from hours so they follow the requested time zone.
+        let in_memory = if group_by == AnalyticsGroupBy::Day {
+            aggregator
+                .get_in_memory_aggregate(&short_code, AnalyticsGroupBy::Hour)
+                .into_iter()
+                .map(|(hour, count)| match hour.parse::<i64>() {
+                    Ok(hour) => (local_day_start(hour, time_zone).to_string(), count),
+                    Err(_) => (hour, count),
+                })
+                .collect()
+        } else {
+            aggregator.get_in_memory_aggregate(&short_code, group_by)
+        };
 
         // Combine database and in-memory data
         use std::collections::HashMap;
@@ -160,6 +324,7 @@ pub async fn get_analytics_aggregate(
             .map(|(dimension, visit_count)| AnalyticsAggregate {
                 dimension,
                 visit_count,
+                aggregated: false,
             })
             .collect();
 
@@ -180,11 +345,81 @@ pub async fn get_analytics_aggregate(
         _ => 0,
     };
 
+    for aggregate in &mut combined_aggregates {
+        aggregate.aggregated = is_aggregated(group_by, &aggregate.dimension);
+    }
+
     let total = combined_aggregates.len();
     Json(AnalyticsAggregateResponse {
         aggregates: combined_aggregates,
         total,
         clicks,
+        limit,
+        data_complete_since,
+        estimated_visits,
     })
     .into_response()
 }
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsSwitchRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyticsSwitchResponse {
+    pub enabled: bool,
+    /// Whether the new state was saved to survive a restart
+    pub persisted: bool,
+}
+
+/// Switch analytics recording on or off without a redeploy (admin only)
+///
+/// The switch takes effect before the state is saved, so recording stops
+/// even when the database that is being saved to is the problem.
+pub async fn set_analytics_enabled(
+    State(state): State<Arc<AnalyticsState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Json(request): Json<AnalyticsSwitchRequest>,
+) -> Result<Json<AnalyticsSwitchResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Switching analytics is restricted to admins".to_string(),
+        ));
+    }
+    let Some(aggregator) = &state.aggregator else {
+        return Err(ApiError::Conflict(
+            "Analytics is not enabled; set ANALYTICS_ENABLED to record visits".to_string(),
+        ));
+    };
+
+    let previous = aggregator.set_enabled(request.enabled);
+    if previous != request.enabled {
+        tracing::warn!(
+            enabled = request.enabled,
+            admin = claims
+                .as_ref()
+                .and_then(|claims| claims.user_id())
+                .as_deref(),
+            "analytics recording switched by an admin"
+        );
+    }
+
+    let value = if request.enabled { "true" } else { "false" };
+    let persisted = match state
+        .storage
+        .set_setting(ANALYTICS_ENABLED_SETTING, value)
+        .await
+    {
+        Ok(()) => true,
+        Err(error) => {
+            tracing::error!(%error, "failed to save the analytics switch");
+            false
+        }
+    };
+
+    Ok(Json(AnalyticsSwitchResponse {
+        enabled: request.enabled,
+        persisted,
+    }))
+}
@@ -0,0 +1,306 @@
+//! Raw analytics exports: `GET /api/links/{code}/analytics/export` for one
+//! link (owner or admin) and `GET /api/me/analytics/export` for every link the
+//! caller created.
+//!
+//! Rows are the stored hourly buckets, not individual visits. They are
+//! streamed as CSV in `(time_bucket, id)` order one keyset page at a time, so
+//! an export holds a single page in memory however large it is. Responses are
+//! gzipped when the client sends `Accept-Encoding: gzip`.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, SecondsFormat};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{authorize_url_mutation, ApiError, AppState};
+use crate::analytics::{AnalyticsEntry, AnalyticsExportScope};
+use crate::auth::AuthClaims;
+use crate::storage::Storage;
+
+/// Rows fetched from storage per page.
+pub const EXPORT_PAGE_SIZE: i64 = 1_000;
+
+/// First line of every CSV export. Columns are only ever appended.
+pub const CSV_HEADER: &str = "short_code,time_bucket,time_bucket_iso,country_code,region,city,asn,ip_version,visit_count,created_at,created_at_iso,updated_at,updated_at_iso\n";
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsExportQuery {
+    /// Earliest time bucket to include (Unix timestamp)
+    pub start: Option<i64>,
+    /// Latest time bucket to include (Unix timestamp)
+    pub end: Option<i64>,
+    /// Export format; only `csv` (the default) is supported
+    pub format: Option<String>,
+}
+
+impl AnalyticsExportQuery {
+    fn validate(&self) -> Result<(), ApiError> {
+        match self.format.as_deref() {
+            None | Some("csv") => {}
+            Some(other) => {
+                return Err(ApiError::UnprocessableEntity(format!(
+                    "Unknown format '{}', expected: csv",
+                    other
+                )))
+            }
+        }
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if start > end {
+                return Err(ApiError::UnprocessableEntity(
+                    "start must not be after end".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Owned form of [`AnalyticsExportScope`] that can move into the streaming task.
+enum ExportScope {
+    Link(String),
+    Owner(String),
+}
+
+impl ExportScope {
+    fn as_scope(&self) -> AnalyticsExportScope<'_> {
+        match self {
+            ExportScope::Link(short_code) => AnalyticsExportScope::Link(short_code),
+            ExportScope::Owner(user_id) => AnalyticsExportScope::Owner(user_id),
+        }
+    }
+}
+
+/// Export the analytics of one short code as CSV (owner or admin)
+pub async fn export_link_analytics(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Query(query): Query<AnalyticsExportQuery>,
+) -> Result<Response, ApiError> {
+    query.validate()?;
+    let code = decode_code_path_param(&encoded_code)?;
+    authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    let filename = format!("{}-analytics.csv", filename_safe(&code));
+    csv_response(
+        Arc::clone(&state.storage),
+        ExportScope::Link(code),
+        &query,
+        filename,
+    )
+    .await
+}
+
+/// Export the analytics of every link the caller created as CSV
+pub async fn export_my_analytics(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<AnalyticsExportQuery>,
+) -> Result<Response, ApiError> {
+    query.validate()?;
+    let Some(user_id) = claims.as_ref().and_then(|c| c.user_id()) else {
+        return Err(ApiError::Forbidden(
+            "Exporting your analytics requires a signed-in user".to_string(),
+        ));
+    };
+
+    csv_response(
+        Arc::clone(&state.storage),
+        ExportScope::Owner(user_id),
+        &query,
+        "analytics.csv".to_string(),
+    )
+    .await
+}
+
+/// Stream every row in `scope` as a CSV attachment.
+///
+/// The first page is loaded before responding so storage failures still get
+/// an error status. A later failure aborts the body, which clients see as a
+/// truncated download rather than a silently short export.
+async fn csv_response(
+    storage: Arc<dyn Storage>,
+    scope: ExportScope,
+    query: &AnalyticsExportQuery,
+    filename: String,
+) -> Result<Response, ApiError> {
+    let (start, end) = (query.start, query.end);
+    let mut page = storage
+        .get_analytics_export_page(scope.as_scope(), start, end, None, EXPORT_PAGE_SIZE)
+        .await
+        .map_err(|e| ApiError::storage("Failed to export analytics", e))?;
+
+    // One page in flight keeps memory flat when the client reads slowly.
+    let (tx, rx) = mpsc::channel::<Result<String, std::io::Error>>(1);
+    tokio::spawn(async move {
+        let mut chunk = CSV_HEADER.to_string();
+        loop {
+            for entry in &page {
+                write_csv_row(&mut chunk, entry);
+            }
+            let after = match page.last() {
+                Some(last) if page.len() as i64 == EXPORT_PAGE_SIZE => {
+                    Some((last.time_bucket, last.id))
+                }
+                _ => None,
+            };
+            if tx.send(Ok(std::mem::take(&mut chunk))).await.is_err() {
+                // The client went away.
+                return;
+            }
+            let Some(after) = after else {
+                return;
+            };
+            page = match storage
+                .get_analytics_export_page(
+                    scope.as_scope(),
+                    start,
+                    end,
+                    Some(after),
+                    EXPORT_PAGE_SIZE,
+                )
+                .await
+            {
+                Ok(page) => page,
+                Err(error) => {
+                    tracing::error!(%error, "analytics export failed mid-stream");
+                    let _ = tx
+                        .send(Err(std::io::Error::other("analytics export failed")))
+                        .await;
+                    return;
+                }
+            };
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+/// Append `entry` to `out` as one CSV line matching [`CSV_HEADER`].
+fn write_csv_row(out: &mut String, entry: &AnalyticsEntry) {
+    // Writing to a String cannot fail.
+    let _ = writeln!(
+        out,
+        "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        csv_field(&entry.short_code),
+        entry.time_bucket,
+        iso8601(entry.time_bucket),
+        csv_field(entry.country_code.as_deref().unwrap_or_default()),
+        csv_field(entry.region.as_deref().unwrap_or_default()),
+        csv_field(entry.city.as_deref().unwrap_or_default()),
+        entry.asn.map(|asn| asn.to_string()).unwrap_or_default(),
+        entry.ip_version,
+        entry.visit_count,
+        entry.created_at,
+        iso8601(entry.created_at),
+        entry.updated_at,
+        iso8601(entry.updated_at),
+    );
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break.
+pub(super) fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// A Unix timestamp as UTC ISO-8601, e.g. `2023-10-31T16:00:00Z`.
+pub(super) fn iso8601(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// Short codes may contain characters that break a `Content-Disposition`
+/// filename; keep only the unambiguous ones.
+fn filename_safe(code: &str) -> String {
+    code.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(city: Option<&str>) -> AnalyticsEntry {
+        AnalyticsEntry {
+            id: 1,
+            short_code: "docs".to_string(),
+            time_bucket: 1_698_768_000,
+            country_code: Some("US".to_string()),
+            region: None,
+            city: city.map(str::to_string),
+            asn: Some(15169),
+            ip_version: 4,
+            visit_count: 5,
+            created_at: 1_698_768_010,
+            updated_at: 1_698_771_600,
+        }
+    }
+
+    #[test]
+    fn rows_match_the_header_and_carry_both_timestamp_forms() {
+        let mut out = String::new();
+        write_csv_row(&mut out, &entry(Some("Mountain View")));
+        assert_eq!(
+            out,
+            "docs,1698768000,2023-10-31T16:00:00Z,US,,Mountain View,15169,4,5,1698768010,2023-10-31T16:00:10Z,1698771600,2023-10-31T17:00:00Z\n"
+        );
+        assert_eq!(
+            out.split(',').count(),
+            CSV_HEADER.split(',').count(),
+            "every header column has a value"
+        );
+    }
+
+    #[test]
+    fn fields_with_separators_are_quoted() {
+        let mut out = String::new();
+        write_csv_row(&mut out, &entry(Some("Washington, \"D.C.\"")));
+        assert!(out.contains(",\"Washington, \"\"D.C.\"\"\","));
+    }
+
+    #[test]
+    fn unsupported_formats_and_inverted_ranges_are_rejected() {
+        let query = |start, end, format: Option<&str>| AnalyticsExportQuery {
+            start,
+            end,
+            format: format.map(str::to_string),
+        };
+        assert!(query(None, None, None).validate().is_ok());
+        assert!(query(Some(1), Some(2), Some("csv")).validate().is_ok());
+        assert!(query(None, None, Some("xlsx")).validate().is_err());
+        assert!(query(Some(2), Some(1), None).validate().is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Campaigns: named groups of links reported together.
+//!
+//! `POST /api/campaigns` creates one for the caller, and links join it
+//! through `campaign_id` on `POST /api/urls` or `PATCH /api/urls/{code}`.
+//! `GET /api/campaigns/{id}/stats` sums clicks and visitor analytics across
+//! the member links, and `GET /api/urls/search?campaign_id=` lists them.
+//! Deleting a campaign takes its links out of it and leaves them otherwise
+//! as they were. Only the owner and admins see or change a campaign.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::analytics::is_aggregated;
+use super::handlers::{is_user_admin, ApiError, AppState, SuccessResponse};
+use crate::analytics::AnalyticsGroupBy;
+use crate::auth::AuthClaims;
+use crate::models::{Campaign, CampaignStats, ShortenedUrl};
+use crate::storage::Storage;
+
+/// Longest campaign name accepted, in characters.
+const MAX_CAMPAIGN_NAME_LENGTH: usize = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct CampaignRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignListResponse {
+    pub campaigns: Vec<Campaign>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CampaignStatsQuery {
+    /// Start of the analytics range (Unix timestamp)
+    pub start_time: Option<i64>,
+    /// End of the analytics range (Unix timestamp)
+    pub end_time: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CampaignStatsResponse {
+    pub campaign: Campaign,
+    #[serde(flatten)]
+    pub stats: CampaignStats,
+}
+
+/// `name` trimmed, if it is neither empty nor too long.
+fn validated_name(name: &str) -> Result<&str, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_CAMPAIGN_NAME_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Campaign name must be 1-{} characters",
+            MAX_CAMPAIGN_NAME_LENGTH
+        )));
+    }
+    Ok(name)
+}
+
+/// The campaign `id` if the caller owns it or is an admin.
+pub(crate) async fn authorize_campaign(
+    storage: &dyn Storage,
+    claims: &Option<AuthClaims>,
+    id: i64,
+) -> Result<Campaign, ApiError> {
+    let campaign = storage
+        .get_campaign(id)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load campaign", e))?
+        .ok_or_else(|| ApiError::NotFound("Campaign not found".to_string()))?;
+
+    let caller = claims.as_ref().and_then(|c| c.user_id());
+    if caller.is_some() && caller == campaign.owner {
+        return Ok(campaign);
+    }
+    if is_user_admin(storage, claims).await {
+        return Ok(campaign);
+    }
+    Err(ApiError::Forbidden(
+        "You do not have permission to use this campaign".to_string(),
+    ))
+}
+
+/// Put `url` in campaign `campaign_id`, or take it out of its campaign with
+/// `None`, and return the link as it now reads. Callers check with
+/// [`authorize_campaign`] first that the caller may use the campaign.
+pub(crate) async fn assign_campaign(
+    storage: &dyn Storage,
+    url: Arc<ShortenedUrl>,
+    campaign_id: Option<i64>,
+) -> Result<Arc<ShortenedUrl>, ApiError> {
+    if url.campaign_id == campaign_id {
+        return Ok(url);
+    }
+    storage
+        .set_link_campaign(&url.short_code, campaign_id)
+        .await
+        .map_err(|e| ApiError::storage("Failed to assign campaign", e))?;
+    Ok(Arc::new(ShortenedUrl {
+        campaign_id,
+        ..(*url).clone()
+    }))
+}
+
+/// Create a campaign owned by the caller
+pub async fn create_campaign(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Json(payload): Json<CampaignRequest>,
+) -> Result<(StatusCode, Json<Campaign>), ApiError> {
+    let name = validated_name(&payload.name)?;
+    let owner = claims.as_ref().and_then(|c| c.user_id());
+
+    let campaign = state
+        .storage
+        .create_campaign(name, owner.as_deref())
+        .await
+        .map_err(|e| ApiError::storage("Failed to create campaign", e))?;
+    Ok((StatusCode::CREATED, Json(campaign)))
+}
+
+/// List the caller's campaigns; admins see every campaign
+pub async fn list_campaigns(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<CampaignListResponse>, ApiError> {
+    let owner = if is_user_admin(state.storage.as_ref(), &claims).await {
+        None
+    } else {
+        let Some(user_id) = claims.as_ref().and_then(|c| c.user_id()) else {
+            return Ok(Json(CampaignListResponse {
+                campaigns: Vec::new(),
+            }));
+        };
+        Some(user_id)
+    };
+
+    let campaigns = state
+        .storage
+        .list_campaigns(owner.as_deref())
+        .await
+        .map_err(|e| ApiError::storage("Failed to list campaigns", e))?;
+    Ok(Json(CampaignListResponse { campaigns }))
+}
+
+/// Get one campaign (owner or admin)
+pub async fn get_campaign(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(id): Path<i64>,
+) -> Result<Json<Campaign>, ApiError> {
+    authorize_campaign(state.storage.as_ref(), &claims, id)
+        .await
+        .map(Json)
+}
+
+/// Rename a campaign (owner or admin)
+pub async fn rename_campaign(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CampaignRequest>,
+) -> Result<Json<Campaign>, ApiError> {
+    let name = validated_name(&payload.name)?;
+    authorize_campaign(state.storage.as_ref(), &claims, id).await?;
+
+    match state.storage.rename_campaign(id, name).await {
+        Ok(Some(campaign)) => Ok(Json(campaign)),
+        Ok(None) => Err(ApiError::NotFound("Campaign not found".to_string())),
+        Err(e) => Err(ApiError::storage("Failed to rename campaign", e)),
+    }
+}
+
+/// Delete a campaign, leaving its links outside any campaign (owner or admin)
+pub async fn delete_campaign(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(id): Path<i64>,
+) -> Result<Json<SuccessResponse>, ApiError> {
+    authorize_campaign(state.storage.as_ref(), &claims, id).await?;
+
+    match state.storage.delete_campaign(id).await {
+        Ok(Some(unassigned)) => Ok(Json(SuccessResponse {
+            message: format!(
+                "Campaign deleted; {} link(s) no longer in a campaign",
+                unassigned.len()
+            ),
+        })),
+        Ok(None) => Err(ApiError::NotFound("Campaign not found".to_string())),
+        Err(e) => Err(ApiError::storage("Failed to delete campaign", e)),
+    }
+}
+
+/// Clicks and visits summed over a campaign's links (owner or admin)
+pub async fn get_campaign_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(id): Path<i64>,
+    Query(query): Query<CampaignStatsQuery>,
+) -> Result<Json<CampaignStatsResponse>, ApiError> {
+    let campaign = authorize_campaign(state.storage.as_ref(), &claims, id).await?;
+
+    let mut stats = state
+        .storage
+        .campaign_stats(id, query.start_time, query.end_time)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load campaign stats", e))?;
+    for country in &mut stats.countries {
+        country.aggregated = is_aggregated(AnalyticsGroupBy::Country, &country.dimension);
+    }
+    Ok(Json(CampaignStatsResponse { campaign, stats }))
+}
@@ -0,0 +1,66 @@
+//! Captcha and proof-of-work enforcement on the create endpoints.
+//!
+//! See [`crate::challenge`] for the providers. Endpoints listed in
+//! `CREATION_CHALLENGE_ENDPOINTS` call [`require_challenge`] before they look
+//! at the request body, so unverified requests cost no validation or storage.
+
+use axum::{extract::State, http::HeaderMap, Json};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::handlers::{ApiError, AppState};
+use crate::challenge::{ChallengeError, IssuedChallenge, CHALLENGE_HEADER};
+use crate::config::ChallengeEndpoint;
+
+/// Get a challenge to solve before creating a link
+pub async fn issue_challenge(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<IssuedChallenge>, ApiError> {
+    let challenge = state
+        .creation_challenge
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("No creation challenge is configured".to_string()))?;
+    Ok(Json(challenge.issue()))
+}
+
+/// Reject the request unless `endpoint` needs no challenge or the request
+/// answers it in the `X-Lynx-Challenge` header.
+pub(crate) async fn require_challenge(
+    state: &AppState,
+    endpoint: ChallengeEndpoint,
+    headers: &HeaderMap,
+    remote_ip: Option<IpAddr>,
+) -> Result<(), ApiError> {
+    let Some(challenge) = state.creation_challenge.as_ref() else {
+        return Ok(());
+    };
+    if !state.config.creation_challenge.requires(endpoint) {
+        return Ok(());
+    }
+
+    let answer = headers
+        .get(CHALLENGE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|answer| !answer.is_empty());
+    let result = match answer {
+        Some(answer) => challenge.verify(answer, remote_ip).await,
+        None => Err(ChallengeError::Missing),
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(ChallengeError::Missing) => Err(ApiError::Forbidden(
+            "Verification required: solve the challenge and send the answer in the X-Lynx-Challenge header".to_string(),
+        )),
+        Err(ChallengeError::Failed) => Err(ApiError::Forbidden(
+            "Verification failed, solve a new challenge and try again".to_string(),
+        )),
+        Err(ChallengeError::Unavailable(error)) => {
+            tracing::warn!(%error, "creation challenge provider unavailable");
+            Err(ApiError::ServiceUnavailable(
+                "Verification is temporarily unavailable, try again shortly".to_string(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Daily click history: `GET /api/links/{code}/clicks/history?days=90&tz=UTC`.
+//!
+//! Unlike the analytics endpoints this needs no visitor data: the counts come
+//! from the click flush, so they are available even with analytics disabled.
+//! Clicks are stored per UTC hour and grouped into days of the `tz` zone.
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{authorize_url_mutation, ApiError, AppState};
+use super::time_zone::time_zone_param;
+use crate::auth::AuthClaims;
+use crate::models::ClickHistoryEntry;
+use crate::timezone::local_day_start_days_ago;
+
+/// Days returned when `days` is not given.
+pub const DEFAULT_HISTORY_DAYS: i64 = 90;
+/// Largest accepted `days` (about ten years).
+pub const MAX_HISTORY_DAYS: i64 = 3660;
+
+#[derive(Debug, Deserialize)]
+pub struct ClickHistoryQuery {
+    /// Number of days to return, including today
+    pub days: Option<i64>,
+    /// IANA time zone the days are counted in (default: UTC)
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClickHistoryResponse {
+    pub short_code: String,
+    /// Effective number of days after clamping
+    pub days: i64,
+    /// Time zone the days are counted in
+    pub tz: &'static str,
+    /// Clicks within the returned days
+    pub total: i64,
+    /// Lifetime click count
+    pub clicks: i64,
+    /// Days with clicks, oldest first
+    pub history: Vec<ClickHistoryEntry>,
+}
+
+/// Get clicks per day for a short code (owner or admin)
+pub async fn get_click_history(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Query(query): Query<ClickHistoryQuery>,
+) -> Result<Json<ClickHistoryResponse>, ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    let time_zone = time_zone_param(query.tz.as_deref())?;
+    let url = authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    let days = query
+        .days
+        .unwrap_or(DEFAULT_HISTORY_DAYS)
+        .clamp(1, MAX_HISTORY_DAYS);
+    let since =
+        local_day_start_days_ago(state.clock.now_epoch_secs(), (days - 1) as u64, time_zone);
+
+    let history = state
+        .storage
+        .get_click_history(&code, since, time_zone)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load click history", e))?;
+
+    Ok(Json(ClickHistoryResponse {
+        short_code: code,
+        days,
+        tz: time_zone.name(),
+        total: history.iter().map(|entry| entry.clicks).sum(),
+        clicks: url.clicks,
+        history,
+    }))
+}
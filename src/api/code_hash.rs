@@ -0,0 +1,298 @@
+//! Short codes derived from the destination (`code_strategy: hash`).
+//!
+//! A hash code is the SHA-256 of the instance salt, the owner and the
+//! normalized destination, in base62 and cut to `CODE_HASH_LENGTH`. The same
+//! request always asks for the same code, so a provisioning script that runs
+//! again gets its links back instead of duplicates, without looking anything
+//! up first. Cutting the hash short lets two destinations meet on one code;
+//! the later one then gets a random code and a
+//! [`WarningCode::HashCodeCollision`] warning.
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::api::code_rng::CodeRng;
+use crate::api::handlers::{create_with_random_code, MIN_SHORT_CODE_LENGTH};
+use crate::api::warnings::{Warning, WarningCode};
+use crate::config::{Config, UrlNormalizationConfig};
+use crate::destination::normalize_url;
+use crate::models::{CreatedVia, LinkOptions, ShortenedUrl};
+use crate::storage::{Storage, StorageError};
+
+/// Base62 digits one `u128` of the digest holds in full.
+const MAX_HASH_CODE_LENGTH: usize = 21;
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// How a hash code request ended up.
+#[derive(Debug)]
+pub(crate) enum HashCodeLink {
+    /// A new link under the hash code
+    Created(Arc<ShortenedUrl>),
+    /// The link an identical earlier request created
+    Existing(Arc<ShortenedUrl>),
+    /// A new link under a random code, because another link holds the hash
+    /// code
+    Fallback(Arc<ShortenedUrl>, Warning),
+}
+
+/// The hash code of `length` base62 characters for `original_url` owned by
+/// `owner`. Fields are separated by NUL so no two inputs run together.
+pub fn hash_code(salt: &str, owner: Option<&str>, normalized_url: &str, length: usize) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update([0])
+        .chain_update(owner.unwrap_or_default())
+        .chain_update([0])
+        .chain_update(normalized_url)
+        .finalize();
+    let mut value = u128::from_be_bytes(digest[..16].try_into().expect("digest is 32 bytes"));
+    (0..length.min(MAX_HASH_CODE_LENGTH))
+        .map(|_| {
+            let digit = BASE62[(value % 62) as usize] as char;
+            value /= 62;
+            digit
+        })
+        .collect()
+}
+
+/// The code `config` gives `original_url` created for `owner`.
+pub(crate) fn code_for(config: &Config, original_url: &str, owner: Option<&str>) -> String {
+    let generation = &config.code_generation;
+    let salt = generation
+        .hash_salt
+        .as_deref()
+        .unwrap_or(&config.redirect_base_url);
+    let max_length = config
+        .short_code_max_length
+        .clamp(MIN_SHORT_CODE_LENGTH, MAX_HASH_CODE_LENGTH);
+    let length = generation
+        .hash_length
+        .clamp(MIN_SHORT_CODE_LENGTH, max_length);
+    hash_code(
+        salt,
+        owner,
+        &normalized(original_url, &config.url_normalization),
+        length,
+    )
+}
+
+/// The link under `code` if it is the one `original_url` for `owner` would
+/// create: same owner, same normalized destination, and a plain link rather
+/// than an alias or reservation.
+pub(crate) async fn existing_link(
+    storage: &dyn Storage,
+    code: &str,
+    original_url: &str,
+    owner: Option<&str>,
+    normalization: &UrlNormalizationConfig,
+) -> Result<Option<Arc<ShortenedUrl>>, StorageError> {
+    let Some(link) = storage.get_authoritative(code).await? else {
+        return Ok(None);
+    };
+    let same = link.created_by.as_deref() == owner
+        && link.alias_of.is_none()
+        && !link.is_reserved()
+        && normalized(&link.original_url, normalization) == normalized(original_url, normalization);
+    Ok(same.then_some(link))
+}
+
+/// Create `original_url` under the hash `code`, returning the link an
+/// identical earlier request created if there is one. When another link
+/// holds the code the new link gets a random one instead; like
+/// [`create_with_random_code`], that returns `StorageError::Conflict` only
+/// when no free code turned up.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create_with_hash_code(
+    storage: &dyn Storage,
+    rng: &CodeRng,
+    code: &str,
+    original_url: &str,
+    created_by: Option<&str>,
+    created_by_auth_method: Option<&str>,
+    created_via: CreatedVia,
+    options: &LinkOptions,
+    max_length: usize,
+    normalization: &UrlNormalizationConfig,
+) -> Result<HashCodeLink, StorageError> {
+    match storage
+        .create_with_code_via(
+            code,
+            original_url,
+            created_by,
+            created_by_auth_method,
+            created_via,
+            options,
+        )
+        .await
+    {
+        Ok(link) => return Ok(HashCodeLink::Created(link)),
+        Err(StorageError::Conflict) => {}
+        Err(error) => return Err(error),
+    }
+    if let Some(link) =
+        existing_link(storage, code, original_url, created_by, normalization).await?
+    {
+        return Ok(HashCodeLink::Existing(link));
+    }
+
+    tracing::info!(
+        code,
+        "Hash code belongs to another link; using a random code"
+    );
+    let link = create_with_random_code(
+        storage,
+        rng,
+        original_url,
+        created_by,
+        created_by_auth_method,
+        created_via,
+        options,
+        max_length,
+    )
+    .await?;
+    let warning = Warning::new(
+        WarningCode::HashCodeCollision,
+        format!(
+            "The hash code '{code}' belongs to another link, so this one got a random code; \
+            repeating the request creates another link"
+        ),
+    )
+    .with_details(json!({ "hash_code": code }));
+    Ok(HashCodeLink::Fallback(link, warning))
+}
+
+/// `url` as compared for hash codes: its normalized form, or as given when
+/// it does not parse.
+fn normalized(url: &str, config: &UrlNormalizationConfig) -> String {
+    normalize_url(url, config).unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        storage
+    }
+
+    #[test]
+    fn hash_codes_are_stable_and_depend_on_every_input() {
+        let code = hash_code("salt", Some("alice"), "https://example.com/a", 8);
+        // Pinned so a change to the derivation, which would move every hash
+        // link a script has provisioned, fails here first
+        assert_eq!(code, "PIQAsKz2");
+        assert_eq!(code.len(), 8);
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(
+            hash_code("salt", Some("alice"), "https://example.com/a", 12)[..8],
+            code
+        );
+
+        for other in [
+            hash_code("pepper", Some("alice"), "https://example.com/a", 8),
+            hash_code("salt", Some("bob"), "https://example.com/a", 8),
+            hash_code("salt", None, "https://example.com/a", 8),
+            hash_code("salt", Some("alice"), "https://example.com/b", 8),
+            // The separator keeps fields from running into each other
+            hash_code("salt", Some("alic"), "ehttps://example.com/a", 8),
+        ] {
+            assert_ne!(other, code);
+        }
+        assert_eq!(hash_code("salt", None, "x", 50).len(), MAX_HASH_CODE_LENGTH);
+    }
+
+    #[test]
+    fn equivalent_destinations_hash_alike() {
+        let config = UrlNormalizationConfig::default();
+        assert_eq!(
+            normalized("https://Example.com/docs/?utm_source=x#top", &config),
+            normalized("https://example.com/docs", &config)
+        );
+        assert_eq!(normalized("not a url", &config), "not a url");
+    }
+
+    #[tokio::test]
+    async fn repeated_requests_return_the_existing_link() {
+        let storage = storage().await;
+        let normalization = UrlNormalizationConfig::default();
+        let rng = CodeRng::seeded(1);
+        let options = LinkOptions {
+            max_clicks: Some(5),
+            ..LinkOptions::default()
+        };
+        let create = |url: &'static str| {
+            create_with_hash_code(
+                &storage,
+                &rng,
+                "hashed1",
+                url,
+                Some("alice"),
+                None,
+                CreatedVia::Api,
+                &options,
+                8,
+                &normalization,
+            )
+        };
+
+        let HashCodeLink::Created(first) = create("https://example.com/a").await.unwrap() else {
+            panic!("the free hash code is used");
+        };
+        assert_eq!(first.short_code, "hashed1");
+        assert_eq!(first.options, options);
+        let HashCodeLink::Existing(again) = create("https://example.com/a/#intro").await.unwrap()
+        else {
+            panic!("the same destination converges on the first link");
+        };
+        assert_eq!(again.short_code, "hashed1");
+        assert_eq!(again.original_url, "https://example.com/a");
+    }
+
+    #[tokio::test]
+    async fn collisions_fall_back_to_a_random_code() {
+        let storage = storage().await;
+        let normalization = UrlNormalizationConfig::default();
+        storage
+            .create_with_code("hashed1", "https://example.com/other", Some("alice"))
+            .await
+            .unwrap();
+        storage
+            .create_with_code("hashed2", "https://example.com/a", Some("bob"))
+            .await
+            .unwrap();
+
+        let options = LinkOptions {
+            expires_at: Some(1_900_000_000_000),
+            ..LinkOptions::default()
+        };
+        for code in ["hashed1", "hashed2"] {
+            let outcome = create_with_hash_code(
+                &storage,
+                &CodeRng::seeded(1),
+                code,
+                "https://example.com/a",
+                Some("alice"),
+                None,
+                CreatedVia::Api,
+                &options,
+                8,
+                &normalization,
+            )
+            .await
+            .unwrap();
+            let HashCodeLink::Fallback(link, warning) = outcome else {
+                panic!("{code} is held by another link");
+            };
+            assert_ne!(link.short_code, code);
+            assert_eq!(link.original_url, "https://example.com/a");
+            assert_eq!(link.options, options);
+            assert_eq!(warning.code, WarningCode::HashCodeCollision);
+            assert_eq!(warning.details, Some(json!({ "hash_code": code })));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Randomness behind generated short codes.
+//!
+//! Handlers draw generated codes from the [`CodeRng`] in `AppState`. In
+//! production it uses the thread-local generator; tests seed it so they know
+//! which codes the server will try.
+
+use rand::distr::{Alphanumeric, Distribution};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::Mutex;
+
+/// Source of generated short codes.
+#[derive(Debug, Default)]
+pub struct CodeRng {
+    /// `None` draws from the thread-local generator
+    seeded: Option<Mutex<StdRng>>,
+}
+
+impl CodeRng {
+    /// Codes from the thread-local generator, as in production.
+    pub fn from_entropy() -> Self {
+        Self::default()
+    }
+
+    /// The same sequence of codes on every run for a given `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seeded: Some(Mutex::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+
+    /// A random alphanumeric code of `length` characters.
+    pub fn code(&self, length: usize) -> String {
+        match &self.seeded {
+            Some(rng) => {
+                let mut rng = rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                alphanumeric(&mut *rng, length)
+            }
+            None => alphanumeric(&mut rand::rng(), length),
+        }
+    }
+}
+
+fn alphanumeric(rng: &mut impl rand::Rng, length: usize) -> String {
+    (0..length)
+        .map(|_| Alphanumeric.sample(rng) as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_generators_repeat_their_codes() {
+        let (a, b) = (CodeRng::seeded(7), CodeRng::seeded(7));
+        let codes: Vec<String> = (0..4).map(|_| a.code(6)).collect();
+        assert_eq!(codes, (0..4).map(|_| b.code(6)).collect::<Vec<_>>());
+        assert_ne!(codes[0], codes[1]);
+        assert!(codes
+            .iter()
+            .all(|code| code.len() == 6 && code.chars().all(|c| c.is_ascii_alphanumeric())));
+        assert_eq!(CodeRng::from_entropy().code(8).len(), 8);
+    }
+}
@@ -1,24 +1,66 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-
-use anyhow::anyhow;
-use rand::distr::{Alphanumeric, Distribution};
-
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Once};
+use url::Url;
+
+use crate::analytics::AnalyticsAggregator;
+use crate::api::campaigns::{assign_campaign, authorize_campaign};
+use crate::api::challenge::require_challenge;
+use crate::api::code_hash::{self, HashCodeLink};
 use crate::api::code_param::decode_code_path_param;
+use crate::api::code_rng::CodeRng;
+use crate::api::limits::{clamp_limit, LIST_DEFAULT_LIMIT, SEARCH_DEFAULT_LIMIT};
+use crate::api::link_options::{apply_link_options, unchanged_by_create, validated};
+use crate::api::public_url::PublicBaseUrl;
+use crate::api::quick::QuickRateLimiter;
+use crate::api::quota::check_link_quota;
+use crate::api::server_info::ServerInfo;
+use crate::api::stats_privacy::{can_see_stats, without_stats};
+use crate::api::warnings::Warning;
+use crate::auth::usage::ApiUsageTracker;
 use crate::auth::AuthClaims;
-use crate::config::Config;
-use crate::models::{CreateUrlRequest, ShortenedUrl, UpdateUrlRequest, UrlHistoryEntry};
-use crate::storage::{SearchParams, Storage, StorageError};
+use crate::challenge::CreationChallenge;
+use crate::clock::Clock;
+use crate::config::{ChallengeEndpoint, CodeStrategy, Config};
+use crate::destination::{sanitize_destination, DestinationError};
+use crate::models::{
+    CreateUrlRequest, CreatedVia, LinkOptions, ShortenedUrl, UpdateUrlRequest, UrlHistoryEntry,
+};
+use crate::redirect::{LiveVisits, RedirectStats};
+use crate::storage::{is_pool_timeout, SearchParams, SearchSort, Storage, StorageError};
+use crate::title::TitleFetcher;
 
 pub struct AppState {
     pub storage: Arc<dyn Storage>,
     pub config: Arc<Config>,
+    /// Redirect outcome counters, present when `REDIRECT_STATS_ENABLED` is set
+    pub redirect_stats: Option<Arc<RedirectStats>>,
+    /// Per-user limit for links created through `GET /api/quick`
+    pub quick_limiter: QuickRateLimiter,
+    /// Per-IP limit for links created through `POST /api/public/urls`
+    pub anonymous_limiter: QuickRateLimiter,
+    /// Live visit feed shared with the redirect server, present when `LIVE_VISITS_ENABLED` is set
+    pub live_visits: Option<Arc<LiveVisits>>,
+    /// Fills in the titles of new links, present when `TITLE_FETCH_ENABLED` is set
+    pub title_fetcher: Option<TitleFetcher>,
+    /// Effective configuration summary served at `GET /api/admin/info`
+    pub server_info: Arc<ServerInfo>,
+    /// Captcha or proof of work, present when `CREATION_CHALLENGE_PROVIDER` is set
+    pub creation_challenge: Option<Arc<dyn CreationChallenge>>,
+    /// Draws the codes of links created without a custom code
+    pub code_rng: CodeRng,
+    /// Current time for request-relative cutoffs and expiries
+    pub clock: Arc<dyn Clock>,
+    /// Visit recorder, present when `ANALYTICS_ENABLED` is set
+    pub analytics: Option<Arc<AnalyticsAggregator>>,
+    /// Per-user API request counters the auth middleware fills
+    pub usage: Option<Arc<ApiUsageTracker>>,
 }
 
 use crate::cursor::{create_cursor, verify_cursor, CursorData};
@@ -37,7 +79,12 @@ pub enum ApiError {
     Forbidden(String),
     NotFound(String),
     Conflict(String),
+    UnprocessableEntity(String),
+    TooManyRequests(String),
     Internal(String),
+    ServiceUnavailable(String),
+    /// The request ran past its time budget and was abandoned
+    Timeout(String),
 }
 
 impl ApiError {
@@ -48,7 +95,56 @@ impl ApiError {
             ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
             ApiError::NotFound(_) => StatusCode::NOT_FOUND,
             ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// The message sent to the client.
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::UnprocessableEntity(m)
+            | ApiError::TooManyRequests(m)
+            | ApiError::Internal(m)
+            | ApiError::ServiceUnavailable(m)
+            | ApiError::Timeout(m) => m,
+        }
+    }
+
+    /// The one mapping from storage failures to HTTP statuses. Unavailable
+    /// databases become 503 so clients retry (pool timeouts are reported as
+    /// overload), conflicts 409, missing rows 404, rejected values 400 and
+    /// refused privileges 403; anything unclassified is a 500.
+    pub fn storage(context: &str, error: impl Into<StorageError>) -> Self {
+        match error.into() {
+            StorageError::Conflict => {
+                ApiError::Conflict(format!("{}: short code already exists", context))
+            }
+            StorageError::NotFound => ApiError::NotFound(format!("{}: not found", context)),
+            StorageError::InvalidInput(message) => {
+                ApiError::BadRequest(format!("{}: {}", context, message))
+            }
+            StorageError::PermissionDenied => {
+                ApiError::Forbidden(format!("{}: permission denied", context))
+            }
+            StorageError::Unavailable(error) if is_pool_timeout(&error) => {
+                ApiError::ServiceUnavailable(format!(
+                    "{}: database is overloaded, retry later",
+                    context
+                ))
+            }
+            StorageError::Unavailable(_) => ApiError::ServiceUnavailable(format!(
+                "{}: database is unavailable, retry later",
+                context
+            )),
+            StorageError::Other(error) => ApiError::Internal(format!("{}: {}", context, error)),
         }
     }
 }
@@ -60,7 +156,11 @@ impl IntoResponse for ApiError {
             ApiError::Forbidden(m) => (StatusCode::FORBIDDEN, m),
             ApiError::NotFound(m) => (StatusCode::NOT_FOUND, m),
             ApiError::Conflict(m) => (StatusCode::CONFLICT, m),
+            ApiError::UnprocessableEntity(m) => (StatusCode::UNPROCESSABLE_ENTITY, m),
+            ApiError::TooManyRequests(m) => (StatusCode::TOO_MANY_REQUESTS, m),
             ApiError::Internal(m) => (StatusCode::INTERNAL_SERVER_ERROR, m),
+            ApiError::ServiceUnavailable(m) => (StatusCode::SERVICE_UNAVAILABLE, m),
+            ApiError::Timeout(m) => (StatusCode::GATEWAY_TIMEOUT, m),
         };
         (status, Json(ErrorResponse { error })).into_response()
     }
@@ -77,13 +177,50 @@ pub struct ShortenedUrlResponse {
     pub inner: Arc<ShortenedUrl>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub redirect_base_url: Option<String>,
+    /// Full public link, built by [`ShortenedUrlResponse::short_url`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_url: Option<String>,
+    /// Old codes renamed to this one, which keep redirecting here (listings only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<ShortenedUrlResponse>,
+    /// Non-fatal notices about the request, such as a nearly used up quota
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    /// Email of the user who created the link (admin listings only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by_email: Option<String>,
+    /// Set when clicks were cleared because the link hides them from the caller
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stats_hidden: bool,
 }
 
 impl ShortenedUrlResponse {
-    fn with_base(url: Arc<ShortenedUrl>, base: Option<&str>) -> Self {
+    pub(crate) fn with_base(url: Arc<ShortenedUrl>, base: Option<&str>) -> Self {
         Self {
+            short_url: base.map(|base| Self::short_url(base, &url.short_code)),
             inner: url,
             redirect_base_url: base.map(|value| value.to_owned()),
+            aliases: Vec::new(),
+            warnings: Vec::new(),
+            created_by_email: None,
+            stats_hidden: false,
+        }
+    }
+
+    /// The public link for `short_code` under the redirect base URL. This is
+    /// the only place that joins the two: trailing slashes and any path on
+    /// the base are kept intact and the code is percent-encoded as one path
+    /// segment.
+    pub fn short_url(base: &str, short_code: &str) -> String {
+        let base = base.trim();
+        match Url::parse(base) {
+            Ok(mut url) if !url.cannot_be_a_base() => {
+                if let Ok(mut segments) = url.path_segments_mut() {
+                    segments.pop_if_empty().push(short_code);
+                }
+                url.into()
+            }
+            _ => format!("{}/{}", base.trim_end_matches('/'), short_code),
         }
     }
 }
@@ -94,24 +231,111 @@ pub struct PaginatedUrlsResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<String>,
     pub has_more: bool,
+    /// Effective page size after clamping the requested limit
+    pub limit: i64,
 }
 
 #[derive(Deserialize)]
 pub struct ListQuery {
-    #[serde(default = "default_limit")]
-    pub limit: i64,
+    /// Page size (default 50, clamped to `PaginationConfig::list_max_limit`)
+    pub limit: Option<i64>,
     /// Cursor for cursor-based pagination
     pub cursor: Option<String>,
+    /// `created_at` (newest first, default) or `last_visited_at` (least
+    /// recently visited first)
+    pub sort: Option<String>,
+    /// Only links not visited within this age, e.g. `90d` (see [`parse_age`]);
+    /// lists least recently visited first
+    pub unused_since: Option<String>,
 }
 
-fn default_limit() -> i64 {
-    50
+/// Parse an age such as `12h`, `90d` or `4w` into seconds.
+pub(crate) fn parse_age(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let unit = match value.chars().last()? {
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => return None,
+    };
+    let amount: i64 = value[..value.len() - 1].parse().ok()?;
+    if amount < 0 {
+        return None;
+    }
+    amount.checked_mul(unit)
+}
+
+/// The Unix timestamp `unused_since` (an age like `90d`) reaches back to
+/// from `now`.
+fn unused_since_cutoff(now: i64, unused_since: Option<&str>) -> Result<Option<i64>, ApiError> {
+    unused_since
+        .map(|value| {
+            let age = parse_age(value).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Invalid unused_since '{}': use an age such as 12h, 90d or 4w",
+                    value
+                ))
+            })?;
+            Ok(now.saturating_sub(age))
+        })
+        .transpose()
+}
+
+/// Search date bounds below this magnitude are read as Unix seconds: it is
+/// what a client written when the bounds were seconds sends (`1e11`
+/// milliseconds is in 1973, before any link), and read as milliseconds it
+/// would silently match from 1970 on.
+const MIN_MILLIS_BOUND: i64 = 100_000_000_000;
+
+/// Logs the first search bound sent in seconds, so operators learn that a
+/// client still needs updating.
+static SECONDS_BOUND_SEEN: Once = Once::new();
+
+/// The search bound `name` in epoch milliseconds. Deprecated: bounds in
+/// seconds are still accepted and converted until clients have moved over.
+fn created_bound_millis(name: &str, bound: Option<i64>) -> Option<i64> {
+    bound.map(|bound| {
+        if bound == 0 || bound.unsigned_abs() >= MIN_MILLIS_BOUND as u64 {
+            return bound;
+        }
+        SECONDS_BOUND_SEEN.call_once(|| {
+            tracing::warn!(
+                parameter = name,
+                "A search date bound was sent in seconds; it is read as seconds for now, \
+                 but clients should send milliseconds"
+            )
+        });
+        bound.saturating_mul(1000)
+    })
+}
+
+/// Validate and normalize a destination URL, mapping violations to 422.
+/// Blank input keeps its historical 400 response.
+pub(crate) fn validated_destination(raw: &str, config: &Config) -> Result<String, ApiError> {
+    sanitize_destination(raw, &config.destination).map_err(|error| match error {
+        DestinationError::Empty => ApiError::BadRequest("URL cannot be empty".to_string()),
+        violation => ApiError::UnprocessableEntity(violation.to_string()),
+    })
+}
+
+/// Fetch the title of a link created just now in the background, when title
+/// fetching is enabled.
+pub(crate) fn fill_title(state: &AppState, url: &ShortenedUrl) {
+    if let Some(fetcher) = &state.title_fetcher {
+        if url.title.is_none() && !url.is_reserved() {
+            fetcher.spawn_fill(
+                Arc::clone(&state.storage),
+                url.short_code.clone(),
+                url.original_url.clone(),
+            );
+        }
+    }
 }
 
 /// Helper to check if user is admin (combines JWT claims and manual promotion)
 /// JWT claims take precedence - if JWT says admin, they're admin regardless of manual table
 /// Manual promotion only applies when JWT doesn't grant admin status
-async fn is_user_admin(storage: &dyn Storage, claims: &Option<AuthClaims>) -> bool {
+pub(crate) async fn is_user_admin(storage: &dyn Storage, claims: &Option<AuthClaims>) -> bool {
     if let Some(c) = claims {
         // First check JWT claims - these take precedence
         if c.is_admin() {
@@ -129,7 +353,7 @@ async fn is_user_admin(storage: &dyn Storage, claims: &Option<AuthClaims>) -> bo
     false
 }
 
-const MIN_SHORT_CODE_LENGTH: usize = 3;
+pub(crate) const MIN_SHORT_CODE_LENGTH: usize = 3;
 const MIN_PROBES_BEFORE_ESCALATION: usize = 5;
 const MAX_PROBES_PER_LENGTH: usize = 64;
 /// Precomputed minimum number of successes required after each attempt
@@ -138,22 +362,28 @@ const MAX_PROBES_PER_LENGTH: usize = 64;
 const REQUIRED_SUCCESSES: [u8; MAX_PROBES_PER_LENGTH] =
     include!(concat!(env!("OUT_DIR"), "/required_successes.in"));
 
-fn random_code(length: usize) -> String {
-    let mut rng = rand::rng();
-    (0..length)
-        .map(|_| Alphanumeric.sample(&mut rng) as char)
-        .collect()
+pub(crate) fn random_code(length: usize) -> String {
+    CodeRng::from_entropy().code(length)
 }
 
 /// Ensure the configured short code max length never dips below the minimum.
-fn validated_short_code_max_length(max_length: usize) -> usize {
+pub(crate) fn validated_short_code_max_length(max_length: usize) -> usize {
     max_length.max(MIN_SHORT_CODE_LENGTH)
 }
 
-async fn create_with_random_code(
+/// Create a link under a code drawn from `rng`. A code that is already taken
+/// is never the caller's doing, so it is retried with another one, moving to
+/// longer codes as the short ones fill up. Returns `StorageError::Conflict`
+/// only when no free code turned up; see [`code_space_exhausted`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create_with_random_code(
     storage: &dyn Storage,
+    rng: &CodeRng,
     original_url: &str,
     created_by: Option<&str>,
+    created_by_auth_method: Option<&str>,
+    created_via: CreatedVia,
+    options: &LinkOptions,
     max_length: usize,
 ) -> Result<Arc<ShortenedUrl>, StorageError> {
     for length in MIN_SHORT_CODE_LENGTH..=max_length {
@@ -161,11 +391,18 @@ async fn create_with_random_code(
         let mut failures = 0usize;
 
         while attempts < MAX_PROBES_PER_LENGTH {
-            let candidate = random_code(length);
+            let candidate = rng.code(length);
             attempts += 1;
 
             match storage
-                .create_with_code(&candidate, original_url, created_by)
+                .create_with_code_via(
+                    &candidate,
+                    original_url,
+                    created_by,
+                    created_by_auth_method,
+                    created_via,
+                    options,
+                )
                 .await
             {
                 Ok(url) => return Ok(url),
@@ -179,36 +416,175 @@ async fn create_with_random_code(
                         }
                     }
                 }
-                Err(StorageError::Other(e)) => return Err(StorageError::Other(e)),
+                Err(e) => return Err(e),
             }
         }
     }
 
-    Err(StorageError::Other(anyhow!(
-        "Failed to generate unique short code"
-    )))
+    Err(StorageError::Conflict)
+}
+
+/// The error for a generated code that kept colliding with existing ones.
+/// Retrying later may succeed, and choosing a custom code avoids it.
+pub(crate) fn code_space_exhausted() -> ApiError {
+    ApiError::ServiceUnavailable(
+        "Could not find a free short code; try again or choose a custom code".to_string(),
+    )
+}
+
+/// Header admins can send instead of `created_by_override` to create or
+/// update a link on behalf of another user.
+pub const ACT_AS_USER_HEADER: &str = "x-act-as-user";
+
+/// The user an admin is acting for, from `created_by_override` or the
+/// `X-Act-As-User` header. Only admins may act for someone else, and only for
+/// a user in the users table.
+async fn on_behalf_of(
+    storage: &dyn Storage,
+    claims: &Option<AuthClaims>,
+    headers: &HeaderMap,
+    body_override: Option<String>,
+) -> Result<Option<String>, ApiError> {
+    let header_override = match headers.get(ACT_AS_USER_HEADER) {
+        Some(value) => Some(
+            value
+                .to_str()
+                .map_err(|_| ApiError::BadRequest("Invalid X-Act-As-User header".to_string()))?
+                .trim()
+                .to_string(),
+        ),
+        None => None,
+    };
+    let user = match (body_override, header_override) {
+        (Some(body), Some(header)) if body != header => {
+            return Err(ApiError::BadRequest(
+                "created_by_override and X-Act-As-User name different users".to_string(),
+            ));
+        }
+        (Some(user), _) | (None, Some(user)) => user,
+        (None, None) => return Ok(None),
+    };
+
+    if !is_user_admin(storage, claims).await {
+        return Err(ApiError::Forbidden(
+            "Only admins can act on behalf of another user".to_string(),
+        ));
+    }
+    if user.is_empty() {
+        return Err(ApiError::BadRequest("User to act for is empty".to_string()));
+    }
+    match storage.user_exists(&user).await {
+        Ok(true) => Ok(Some(user)),
+        Ok(false) => Err(ApiError::BadRequest(format!("Unknown user '{}'", user))),
+        Err(e) => Err(ApiError::storage("Failed to look up user", e)),
+    }
+}
+
+/// Record an action an admin took for `effective_user`. The action itself
+/// already happened, so a failure is logged rather than returned.
+pub(crate) async fn audit_on_behalf_of(
+    storage: &dyn Storage,
+    action: &str,
+    short_code: &str,
+    claims: &Option<AuthClaims>,
+    effective_user: &str,
+) {
+    let actor = claims.as_ref().and_then(|c| c.user_id());
+    if let Err(error) = storage
+        .record_audit_entry(action, short_code, actor.as_deref(), effective_user)
+        .await
+    {
+        tracing::error!(%error, action, short_code, "failed to record audit entry");
+    }
+}
+
+/// Reject an expiry that is not after `now`; a link created already
+/// expired could never be followed.
+fn validate_expiry(expires_at: Option<i64>, now: i64) -> Result<(), ApiError> {
+    match expires_at {
+        Some(expires_at) if expires_at <= now => Err(ApiError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        )),
+        _ => Ok(()),
+    }
 }
 
 /// Create a new shortened URL
 pub async fn create_url(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
     Extension(claims): Extension<Option<AuthClaims>>,
+    headers: HeaderMap,
     Json(payload): Json<CreateUrlRequest>,
 ) -> Result<(StatusCode, Json<ShortenedUrlResponse>), ApiError> {
-    let base = Some(state.config.redirect_base_url.as_str());
-
-    let CreateUrlRequest { url, custom_code } = payload;
+    let base = Some(public_base.as_str());
+
+    let options = validated(payload.link_options())?;
+    let CreateUrlRequest {
+        url,
+        custom_code,
+        created_by_override,
+        code_strategy,
+        campaign_id,
+        ..
+    } = payload;
     let max_short_code_length = validated_short_code_max_length(state.config.short_code_max_length);
 
-    if url.is_empty() {
-        return Err(ApiError::BadRequest("URL cannot be empty".to_string()));
+    require_challenge(&state, ChallengeEndpoint::Api, &headers, None).await?;
+    let url = validated_destination(&url, &state.config)?;
+
+    // The link belongs to the user an admin acts for, or else to the caller
+    let acting_for = on_behalf_of(
+        state.storage.as_ref(),
+        &claims,
+        &headers,
+        created_by_override,
+    )
+    .await?;
+    let created_by = acting_for
+        .clone()
+        .or_else(|| claims.as_ref().and_then(|c| c.user_id()));
+    let created_by_ref = created_by.as_deref();
+    // The caller's auth method says nothing about the user an admin acts for
+    let auth_method = if acting_for.is_some() {
+        None
+    } else {
+        claims.as_ref().and_then(|c| c.auth_method())
+    };
+    if let Some(id) = campaign_id {
+        authorize_campaign(state.storage.as_ref(), &claims, id).await?;
+    }
+    validate_expiry(options.expires_at, state.clock.now_epoch_ms())?;
+    let strategy = code_strategy.unwrap_or(state.config.code_generation.strategy);
+    let hash_code = (custom_code.is_none() && strategy == CodeStrategy::Hash)
+        .then(|| code_hash::code_for(&state.config, &url, created_by_ref));
+
+    // A repeated hash request gets its link back, even at the quota
+    if let Some(code) = &hash_code {
+        let existing = code_hash::existing_link(
+            state.storage.as_ref(),
+            code,
+            &url,
+            created_by_ref,
+            &state.config.url_normalization,
+        )
+        .await
+        .map_err(|e| ApiError::storage("Failed to look up hash code", e))?;
+        if let Some(existing) = existing {
+            let mut existing = unchanged_by_create(existing, &options)?;
+            if campaign_id.is_some() {
+                existing = assign_campaign(state.storage.as_ref(), existing, campaign_id).await?;
+            }
+            return Ok((
+                StatusCode::OK,
+                Json(ShortenedUrlResponse::with_base(existing, base)),
+            ));
+        }
     }
 
-    // Extract user ID from claims
-    let created_by = claims.as_ref().and_then(|c| c.user_id());
-    let created_by_ref = created_by.as_deref();
+    let quota = check_link_quota(&state, created_by_ref, &claims).await?;
 
-    let created = if let Some(custom) = custom_code {
+    let mut created = if let Some(custom) = custom_code {
         if custom.is_empty() || custom.len() > max_short_code_length {
             return Err(ApiError::BadRequest(format!(
                 "Custom code must be 1-{} characters",
@@ -218,7 +594,14 @@ pub async fn create_url(
 
         match state
             .storage
-            .create_with_code(&custom, &url, created_by_ref)
+            .create_with_code_via(
+                &custom,
+                &url,
+                created_by_ref,
+                auth_method.as_deref(),
+                CreatedVia::Api,
+                &options,
+            )
             .await
         {
             Ok(url) => Ok((
@@ -228,16 +611,53 @@ pub async fn create_url(
             Err(StorageError::Conflict) => {
                 Err(ApiError::Conflict("Short code already exists".to_string()))
             }
-            Err(StorageError::Other(e)) => Err(ApiError::Internal(format!(
-                "Failed to create URL with custom code: {}",
-                e
-            ))),
+            Err(e) => Err(ApiError::storage(
+                "Failed to create URL with custom code",
+                e,
+            )),
+        }
+    } else if let Some(code) = hash_code {
+        match code_hash::create_with_hash_code(
+            state.storage.as_ref(),
+            &state.code_rng,
+            &code,
+            &url,
+            created_by_ref,
+            auth_method.as_deref(),
+            CreatedVia::Api,
+            &options,
+            max_short_code_length,
+            &state.config.url_normalization,
+        )
+        .await
+        {
+            Ok(HashCodeLink::Created(url)) => Ok((
+                StatusCode::CREATED,
+                Json(ShortenedUrlResponse::with_base(url, base)),
+            )),
+            Ok(HashCodeLink::Existing(url)) => unchanged_by_create(url, &options).map(|url| {
+                (
+                    StatusCode::OK,
+                    Json(ShortenedUrlResponse::with_base(url, base)),
+                )
+            }),
+            Ok(HashCodeLink::Fallback(url, warning)) => {
+                let mut response = ShortenedUrlResponse::with_base(url, base);
+                response.warnings.push(warning);
+                Ok((StatusCode::CREATED, Json(response)))
+            }
+            Err(StorageError::Conflict) => Err(code_space_exhausted()),
+            Err(err) => Err(ApiError::storage("Failed to create URL", err)),
         }
     } else {
         match create_with_random_code(
             state.storage.as_ref(),
+            &state.code_rng,
             &url,
             created_by_ref,
+            auth_method.as_deref(),
+            CreatedVia::Api,
+            &options,
             max_short_code_length,
         )
         .await
@@ -246,35 +666,65 @@ pub async fn create_url(
                 StatusCode::CREATED,
                 Json(ShortenedUrlResponse::with_base(url, base)),
             )),
-            Err(e) => match e {
-                StorageError::Conflict => Err(ApiError::Internal(
-                    "Failed to generate unique short code after multiple attempts".to_string(),
-                )),
-                StorageError::Other(err) => {
-                    Err(ApiError::Internal(format!("Failed to create URL: {}", err)))
-                }
-            },
+            Err(StorageError::Conflict) => Err(code_space_exhausted()),
+            Err(err) => Err(ApiError::storage("Failed to create URL", err)),
         }
     };
 
+    if let Ok((_, Json(response))) = &mut created {
+        if campaign_id.is_some() {
+            response.inner = assign_campaign(
+                state.storage.as_ref(),
+                Arc::clone(&response.inner),
+                campaign_id,
+            )
+            .await?;
+        }
+        fill_title(&state, &response.inner);
+        response
+            .warnings
+            .extend(quota.and_then(|usage| usage.warning_after_create()));
+    }
+    if let (Ok((_, Json(response))), Some(user)) = (&created, acting_for.as_deref()) {
+        audit_on_behalf_of(
+            state.storage.as_ref(),
+            "create",
+            &response.inner.short_code,
+            &claims,
+            user,
+        )
+        .await;
+    }
+
     created
 }
 
-/// Get a shortened URL by code
+/// Get a shortened URL by code. Viewers other than the owner and admins get
+/// it without clicks when the link hides them.
 pub async fn get_url(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
     Path(encoded_code): Path<String>,
 ) -> Result<Json<ShortenedUrlResponse>, ApiError> {
     let code = decode_code_path_param(&encoded_code)?;
 
-    match state.storage.get_authoritative(&code).await {
-        Ok(Some(url)) => Ok(Json(ShortenedUrlResponse::with_base(
+    let url = match state.storage.get_authoritative(&code).await {
+        Ok(Some(url)) => url,
+        Ok(None) => return Err(ApiError::NotFound("URL not found".to_string())),
+        Err(e) => return Err(ApiError::storage("Failed to get URL", e)),
+    };
+    let hide_by_default = state.config.stats_privacy.hide_by_default;
+    if can_see_stats(state.storage.as_ref(), &claims, &url, hide_by_default).await {
+        return Ok(Json(ShortenedUrlResponse::with_base(
             url,
-            Some(state.config.redirect_base_url.as_str()),
-        ))),
-        Ok(None) => Err(ApiError::NotFound("URL not found".to_string())),
-        Err(e) => Err(ApiError::Internal(format!("Failed to get URL: {}", e))),
+            Some(public_base.as_str()),
+        )));
     }
+    let mut response =
+        ShortenedUrlResponse::with_base(without_stats(&url), Some(public_base.as_str()));
+    response.stats_hidden = true;
+    Ok(Json(response))
 }
 
 /// Deactivate a shortened URL (admin only)
@@ -297,10 +747,7 @@ pub async fn deactivate_url(
             message: "URL deactivated successfully".to_string(),
         })),
         Ok(false) => Err(ApiError::NotFound("URL not found".to_string())),
-        Err(e) => Err(ApiError::Internal(format!(
-            "Failed to deactivate URL: {}",
-            e
-        ))),
+        Err(e) => Err(ApiError::storage("Failed to deactivate URL", e)),
     }
 }
 
@@ -324,16 +771,13 @@ pub async fn reactivate_url(
             message: "URL reactivated successfully".to_string(),
         })),
         Ok(false) => Err(ApiError::NotFound("URL not found".to_string())),
-        Err(e) => Err(ApiError::Internal(format!(
-            "Failed to reactivate URL: {}",
-            e
-        ))),
+        Err(e) => Err(ApiError::storage("Failed to reactivate URL", e)),
     }
 }
 
 /// Ensure the caller may mutate the given URL: they must be the owner or an admin.
 /// Returns the existing URL on success so callers can reuse it without a second fetch.
-async fn authorize_url_mutation(
+pub(crate) async fn authorize_url_mutation(
     storage: &dyn Storage,
     claims: &Option<AuthClaims>,
     code: &str,
@@ -344,7 +788,7 @@ async fn authorize_url_mutation(
             return Err(ApiError::NotFound("URL not found".to_string()));
         }
         Err(e) => {
-            return Err(ApiError::Internal(format!("Failed to load URL: {}", e)));
+            return Err(ApiError::storage("Failed to load URL", e));
         }
     };
 
@@ -368,33 +812,64 @@ async fn authorize_url_mutation(
 /// The previous destination is recorded in the URL's history.
 pub async fn update_url(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
     Extension(claims): Extension<Option<AuthClaims>>,
     Path(encoded_code): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateUrlRequest>,
 ) -> Result<Json<ShortenedUrlResponse>, ApiError> {
     let code = decode_code_path_param(&encoded_code)?;
 
-    let new_url = payload.url.trim();
-    if new_url.is_empty() {
-        return Err(ApiError::BadRequest("URL cannot be empty".to_string()));
-    }
+    let new_url = validated_destination(&payload.url, &state.config)?;
+    let options = validated(payload.link_options())?;
 
     authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+    if let Some(Some(id)) = payload.campaign_id {
+        authorize_campaign(state.storage.as_ref(), &claims, id).await?;
+    }
+
+    // An admin acting for a user hands the link to that user
+    let acting_for = on_behalf_of(
+        state.storage.as_ref(),
+        &claims,
+        &headers,
+        payload.created_by_override,
+    )
+    .await?;
+    if let Some(user) = acting_for.as_deref() {
+        state
+            .storage
+            .patch_created_by(&code, user, None)
+            .await
+            .map_err(|e| ApiError::storage("Failed to transfer URL", e))?;
+    }
 
     let updated_by = claims.as_ref().and_then(|c| c.user_id());
 
-    match state
+    let updated = match state
         .storage
-        .update_url(&code, new_url, updated_by.as_deref())
+        .update_url(&code, &new_url, updated_by.as_deref())
         .await
     {
-        Ok(Some(url)) => Ok(Json(ShortenedUrlResponse::with_base(
-            url,
-            Some(state.config.redirect_base_url.as_str()),
-        ))),
+        Ok(Some(url)) => {
+            let mut url = apply_link_options(state.storage.as_ref(), url, &options).await?;
+            if let Some(campaign_id) = payload.campaign_id {
+                url = assign_campaign(state.storage.as_ref(), url, campaign_id).await?;
+            }
+            Ok(Json(ShortenedUrlResponse::with_base(
+                url,
+                Some(public_base.as_str()),
+            )))
+        }
         Ok(None) => Err(ApiError::NotFound("URL not found".to_string())),
-        Err(e) => Err(ApiError::Internal(format!("Failed to update URL: {}", e))),
+        Err(e) => Err(ApiError::storage("Failed to update URL", e)),
+    };
+
+    if let (Ok(_), Some(user)) = (&updated, acting_for.as_deref()) {
+        audit_on_behalf_of(state.storage.as_ref(), "update", &code, &claims, user).await;
     }
+
+    updated
 }
 
 /// Get the history of previous destinations for a shortened URL (owner or admin).
@@ -409,10 +884,7 @@ pub async fn get_url_history(
 
     match state.storage.get_url_history(&code).await {
         Ok(history) => Ok(Json(history)),
-        Err(e) => Err(ApiError::Internal(format!(
-            "Failed to load URL history: {}",
-            e
-        ))),
+        Err(e) => Err(ApiError::storage("Failed to load URL history", e)),
     }
 }
 
@@ -420,6 +892,7 @@ pub async fn get_url_history(
 /// The current destination is recorded in the URL's history.
 pub async fn restore_url(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
     Extension(claims): Extension<Option<AuthClaims>>,
     Path((encoded_code, history_id)): Path<(String, i64)>,
 ) -> Result<Json<ShortenedUrlResponse>, ApiError> {
@@ -436,43 +909,98 @@ pub async fn restore_url(
     {
         Ok(Some(url)) => Ok(Json(ShortenedUrlResponse::with_base(
             url,
-            Some(state.config.redirect_base_url.as_str()),
+            Some(public_base.as_str()),
         ))),
         Ok(None) => Err(ApiError::NotFound("URL not found".to_string())),
-        Err(e) => Err(ApiError::NotFound(format!("Failed to restore URL: {}", e))),
+        Err(StorageError::NotFound) => Err(ApiError::NotFound(
+            "History entry not found for this URL".to_string(),
+        )),
+        Err(e) => Err(ApiError::storage("Failed to restore URL", e)),
     }
 }
 
 /// List all shortened URLs
 pub async fn list_urls(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
     Extension(claims): Extension<Option<AuthClaims>>,
     Query(query): Query<ListQuery>,
 ) -> Result<Json<PaginatedUrlsResponse>, ApiError> {
     let is_admin = is_user_admin(state.storage.as_ref(), &claims).await;
     let user_id = claims.as_ref().and_then(|c| c.user_id());
+    let limit = clamp_limit(
+        query.limit,
+        LIST_DEFAULT_LIMIT,
+        state.config.pagination.list_max_limit,
+    );
+
+    let unused_since =
+        unused_since_cutoff(state.clock.now_epoch_secs(), query.unused_since.as_deref())?;
+    let by_last_visit = match query.sort.as_deref() {
+        None => unused_since.is_some(),
+        Some("last_visited_at") => true,
+        Some("created_at") if unused_since.is_none() => false,
+        Some("created_at") => {
+            return Err(ApiError::BadRequest(
+                "unused_since lists links by last visit; use sort=last_visited_at".to_string(),
+            ))
+        }
+        Some(other) => {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid sort '{}': use created_at or last_visited_at",
+                other
+            )))
+        }
+    };
 
     // Decode cursor if provided
     let cursor = if let Some(cursor_str) = query.cursor {
-        let cursor_data = crate::cursor::verify_cursor(&cursor_str)
-            .map_err(|e| ApiError::BadRequest(format!("Invalid cursor: {}", e)))?;
-        Some((cursor_data.created_at, cursor_data.id))
+        let cursor_data = crate::cursor::verify_cursor(
+            &cursor_str,
+            state
+                .config
+                .pagination
+                .accepts_legacy_cursors(state.clock.now_epoch_ms()),
+        )
+        .map_err(|e| ApiError::BadRequest(format!("Invalid cursor: {}", e)))?;
+        match (by_last_visit, cursor_data.last_visited_at) {
+            (false, None) => Some((cursor_data.created_at, cursor_data.id)),
+            (true, Some(last_visited_at)) => Some((last_visited_at, cursor_data.id)),
+            _ => {
+                return Err(ApiError::BadRequest(
+                    "Invalid cursor: issued for a different sort order".to_string(),
+                ))
+            }
+        }
     } else {
         None
     };
 
     // Fetch limit+1 to determine if there are more pages
-    let urls = state
-        .storage
-        .list_with_cursor(query.limit + 1, cursor, is_admin, user_id.as_deref())
-        .await;
+    let urls = if by_last_visit {
+        state
+            .storage
+            .list_by_last_visit(
+                limit + 1,
+                cursor,
+                unused_since,
+                is_admin,
+                user_id.as_deref(),
+            )
+            .await
+    } else {
+        state
+            .storage
+            .list_with_cursor(limit + 1, cursor, is_admin, user_id.as_deref())
+            .await
+    };
 
     match urls {
         Ok(mut urls) => {
-            let base = Some(state.config.redirect_base_url.as_str());
+            let base = Some(public_base.as_str());
 
             // Check if there are more pages
-            let has_more = urls.len() > query.limit as usize;
+            let has_more = urls.len() > limit as usize;
             if has_more {
                 urls.pop(); // Remove the extra item
             }
@@ -483,27 +1011,142 @@ pub async fn list_urls(
                 let cursor_data = CursorData {
                     created_at: last.created_at,
                     id: last.id,
+                    last_visited_at: by_last_visit.then(|| last.last_visited_at.unwrap_or(0)),
+                    offset: None,
                 };
                 create_cursor(&cursor_data).ok()
             } else {
                 None
             };
 
+            let mut urls = nest_aliases(state.storage.as_ref(), urls, base).await?;
+            if is_admin {
+                attach_owner_emails(state.storage.as_ref(), &mut urls).await?;
+            }
             let response = PaginatedUrlsResponse {
-                urls: urls
-                    .into_iter()
-                    .map(|url| ShortenedUrlResponse::with_base(url, base))
-                    .collect(),
+                urls,
                 next_cursor,
                 has_more,
+                limit,
             };
 
             Ok(Json(response))
         }
-        Err(e) => Err(ApiError::Internal(format!("Failed to list URLs: {}", e))),
+        Err(e) => Err(ApiError::storage("Failed to list URLs", e)),
     }
 }
 
+/// Fill in the creators' emails from the users table, which is keyed by user
+/// id and auth method. Links created before the auth method was recorded are
+/// left without one: the subject alone may belong to two different people.
+async fn attach_owner_emails(
+    storage: &dyn Storage,
+    items: &mut [ShortenedUrlResponse],
+) -> Result<(), ApiError> {
+    let owner = |item: &ShortenedUrlResponse| {
+        Some((
+            item.inner.created_by.clone()?,
+            item.inner.created_by_auth_method.clone()?,
+        ))
+    };
+    let owners: HashSet<(String, String)> = items.iter().filter_map(owner).collect();
+    if owners.is_empty() {
+        return Ok(());
+    }
+    let owners: Vec<(String, String)> = owners.into_iter().collect();
+    let emails: HashMap<(String, String), String> = storage
+        .user_emails(&owners)
+        .await
+        .map_err(|e| ApiError::storage("Failed to look up link owners", e))?
+        .into_iter()
+        .map(|(user_id, auth_method, email)| ((user_id, auth_method), email))
+        .collect();
+    for item in items.iter_mut() {
+        item.created_by_email = owner(item).and_then(|key| emails.get(&key).cloned());
+    }
+    Ok(())
+}
+
+/// Build listing entries with aliases nested under their canonical link
+/// instead of appearing on their own. Removed (inactive) aliases are left out,
+/// and each alias shows the destination it redirects to.
+async fn nest_aliases(
+    storage: &dyn Storage,
+    urls: Vec<Arc<ShortenedUrl>>,
+    base: Option<&str>,
+) -> Result<Vec<ShortenedUrlResponse>, ApiError> {
+    let canonical: Vec<Arc<ShortenedUrl>> =
+        urls.into_iter().filter(|url| !url.is_alias()).collect();
+    let codes: Vec<String> = canonical.iter().map(|url| url.short_code.clone()).collect();
+    let mut aliases: HashMap<String, Vec<Arc<ShortenedUrl>>> = HashMap::new();
+    if !codes.is_empty() {
+        let found = storage
+            .get_aliases(&codes)
+            .await
+            .map_err(|e| ApiError::storage("Failed to load aliases", e))?;
+        for alias in found.into_iter().filter(|alias| alias.is_active) {
+            if let Some(target) = alias.alias_of.clone() {
+                aliases.entry(target).or_default().push(alias);
+            }
+        }
+    }
+
+    Ok(canonical
+        .into_iter()
+        .map(|url| {
+            let mut nested = aliases.remove(&url.short_code).unwrap_or_default();
+            nested.sort_by(|a, b| a.short_code.cmp(&b.short_code));
+            let nested = nested
+                .into_iter()
+                .map(|alias| {
+                    let alias = ShortenedUrl {
+                        original_url: url.original_url.clone(),
+                        ..(*alias).clone()
+                    };
+                    ShortenedUrlResponse::with_base(Arc::new(alias), base)
+                })
+                .collect();
+            let mut response = ShortenedUrlResponse::with_base(url, base);
+            response.aliases = nested;
+            response
+        })
+        .collect())
+}
+
+/// Replace search hits on an alias with the link it points at, so searching
+/// for an alias code finds its canonical link. Each link appears once.
+async fn canonical_search_hits(
+    storage: &dyn Storage,
+    items: Vec<Arc<ShortenedUrl>>,
+) -> Result<Vec<Arc<ShortenedUrl>>, ApiError> {
+    let targets: Vec<String> = items
+        .iter()
+        .filter(|url| url.is_active)
+        .filter_map(|url| url.alias_of.clone())
+        .collect();
+    if targets.is_empty() {
+        return Ok(items);
+    }
+
+    let canonical: HashMap<String, Arc<ShortenedUrl>> = storage
+        .get_many(&targets)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load aliased links", e))?
+        .into_iter()
+        .map(|url| (url.short_code.clone(), url))
+        .collect();
+    let mut seen = HashSet::new();
+    Ok(items
+        .into_iter()
+        .filter_map(|url| match &url.alias_of {
+            None => Some(url),
+            Some(target) if url.is_active => canonical.get(target).cloned(),
+            Some(_) => None,
+        })
+        .filter(|url| seen.insert(url.short_code.clone()))
+        .collect())
+}
+
 /// Health check endpoint
 pub async fn health_check() -> Json<SuccessResponse> {
     Json(SuccessResponse {
@@ -585,7 +1228,108 @@ pub async fn get_auth_mode(State(state): State<Arc<AppState>>) -> Json<AuthModeR
 
 #[cfg(test)]
 mod tests {
-    use super::{validated_short_code_max_length, MIN_SHORT_CODE_LENGTH};
+    use super::{
+        created_bound_millis, parse_age, validated_short_code_max_length, ApiError,
+        ShortenedUrlResponse, MIN_SHORT_CODE_LENGTH,
+    };
+    use crate::storage::StorageError;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_created_bounds_accept_milliseconds_and_deprecated_seconds() {
+        let bound = |value| created_bound_millis("created_from", Some(value));
+        assert_eq!(bound(1_700_000_000_123), Some(1_700_000_000_123));
+        assert_eq!(bound(100_000_000_000), Some(100_000_000_000));
+        assert_eq!(bound(0), Some(0));
+        assert_eq!(created_bound_millis("created_from", None), None);
+
+        // Seconds still work while clients move over
+        assert_eq!(bound(1_700_000_000), Some(1_700_000_000_000));
+        assert_eq!(bound(99_999_999_999), Some(99_999_999_999_000));
+        assert_eq!(bound(-1), Some(-1000));
+    }
+
+    #[test]
+    fn test_storage_errors_map_to_statuses() {
+        let status = |error: StorageError| ApiError::storage("Failed", error).status_code();
+        assert_eq!(status(StorageError::Conflict), StatusCode::CONFLICT);
+        assert_eq!(status(StorageError::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(
+            status(StorageError::InvalidInput("bad".to_string())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(StorageError::PermissionDenied),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(StorageError::from(sqlx::Error::PoolTimedOut)),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(StorageError::from(sqlx::Error::PoolClosed)),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(StorageError::Other(anyhow::anyhow!("boom"))),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        let overloaded = ApiError::storage("Failed", sqlx::Error::PoolTimedOut);
+        assert!(overloaded.message().contains("overloaded"));
+    }
+
+    #[test]
+    fn test_short_url_joins_base_and_code_once() {
+        for base in ["https://lynx.example", "https://lynx.example/"] {
+            assert_eq!(
+                ShortenedUrlResponse::short_url(base, "abc"),
+                "https://lynx.example/abc"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("12h"), Some(12 * 3_600));
+        assert_eq!(parse_age("90d"), Some(90 * 86_400));
+        assert_eq!(parse_age(" 4w "), Some(28 * 86_400));
+        for invalid in ["", "d", "90", "-1d", "1.5d", "90x", "1ｄ"] {
+            assert_eq!(parse_age(invalid), None, "{invalid}");
+        }
+        assert_eq!(parse_age(&format!("{}w", i64::MAX)), None);
+    }
+
+    #[test]
+    fn test_short_url_keeps_ports() {
+        assert_eq!(
+            ShortenedUrlResponse::short_url("http://localhost:3000", "abc"),
+            "http://localhost:3000/abc"
+        );
+        assert_eq!(
+            ShortenedUrlResponse::short_url("http://127.0.0.1:8080/", "abc"),
+            "http://127.0.0.1:8080/abc"
+        );
+    }
+
+    #[test]
+    fn test_short_url_supports_custom_domains_with_paths() {
+        assert_eq!(
+            ShortenedUrlResponse::short_url("https://go.acme.co/l", "spring24-a"),
+            "https://go.acme.co/l/spring24-a"
+        );
+        assert_eq!(
+            ShortenedUrlResponse::short_url("https://go.acme.co/l/", "spring24-a"),
+            "https://go.acme.co/l/spring24-a"
+        );
+    }
+
+    #[test]
+    fn test_short_url_encodes_the_code_as_one_segment() {
+        assert_eq!(
+            ShortenedUrlResponse::short_url("https://lynx.example", "a b/c?"),
+            "https://lynx.example/a%20b%2Fc%3F"
+        );
+    }
 
     #[test]
     fn test_validated_short_code_max_length_uses_minimum() {
@@ -607,7 +1351,7 @@ mod tests {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "sqlite"))]
 mod authorization_tests {
     use super::*;
     use crate::storage::SqliteStorage;
@@ -682,21 +1426,26 @@ pub struct SearchQuery {
     pub q: String,
     /// Filter by creator (use "__null__" for NULL created_by)
     pub created_by: Option<String>,
-    /// Filter by created_at >= this value (inclusive)
+    /// Filter by created_at >= this value, in epoch milliseconds (inclusive);
+    /// seconds are still accepted but deprecated
     pub created_from: Option<i64>,
-    /// Filter by created_at < this value (exclusive)
+    /// Filter by created_at < this value, in epoch milliseconds (exclusive);
+    /// seconds are still accepted but deprecated
     pub created_to: Option<i64>,
     /// Filter by is_active status
     pub is_active: Option<bool>,
-    /// Maximum number of results (default 50, max 200)
-    #[serde(default = "default_search_limit")]
-    pub limit: u32,
+    /// Filter by creation source (api, cli, bookmarklet, import, integration, unknown)
+    pub created_via: Option<String>,
+    /// Only links not visited within this age, e.g. `90d`
+    pub unused_since: Option<String>,
+    /// Only links in this campaign
+    pub campaign_id: Option<i64>,
+    /// Maximum number of results (default 50, clamped to `PaginationConfig::search_max_limit`)
+    pub limit: Option<i64>,
     /// Cursor for pagination
     pub cursor: Option<String>,
-}
-
-fn default_search_limit() -> u32 {
-    50
+    /// `created_at` (newest first, default) or `relevance` (best match first)
+    pub sort: Option<String>,
 }
 
 /// Response for search endpoint
@@ -706,11 +1455,17 @@ pub struct SearchResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<String>,
     pub has_more: bool,
+    /// Whether the first item's code is exactly the query; it is pinned ahead
+    /// of the substring matches on the first page
+    pub exact_match: bool,
+    /// Effective page size after clamping the requested limit
+    pub limit: i64,
 }
 
 /// Search for URLs matching a query string
 pub async fn search_urls(
     State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
     Extension(claims): Extension<Option<AuthClaims>>,
     Query(query): Query<SearchQuery>,
 ) -> Result<Json<SearchResponse>, ApiError> {
@@ -722,16 +1477,57 @@ pub async fn search_urls(
         ));
     }
 
-    // Clamp limit to valid range
-    let limit = query.limit.clamp(1, 200) as i64;
+    let limit = clamp_limit(
+        query.limit,
+        SEARCH_DEFAULT_LIMIT,
+        state.config.pagination.search_max_limit,
+    );
+
+    let created_via = query
+        .created_via
+        .as_deref()
+        .map(|value| {
+            CreatedVia::parse(value).ok_or_else(|| {
+                ApiError::UnprocessableEntity(format!("Unknown created_via '{}'", value))
+            })
+        })
+        .transpose()?;
+    let unused_since =
+        unused_since_cutoff(state.clock.now_epoch_secs(), query.unused_since.as_deref())?;
+
+    let sort = match query.sort.as_deref() {
+        None | Some("created_at") => SearchSort::CreatedAt,
+        Some("relevance") => SearchSort::Relevance,
+        Some(other) => {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid sort '{}': use created_at or relevance",
+                other
+            )))
+        }
+    };
 
-    // Parse cursor if provided
-    let cursor = if let Some(cursor_str) = query.cursor {
-        let cursor_data = verify_cursor(&cursor_str)
-            .map_err(|e| ApiError::BadRequest(format!("Invalid cursor: {}", e)))?;
-        Some((cursor_data.created_at, cursor_data.id))
+    // Parse cursor if provided. Relevance pages continue by offset into the
+    // ranking, or by keyset where the backend ranks each page itself.
+    let (cursor, offset) = if let Some(cursor_str) = query.cursor {
+        let cursor_data = verify_cursor(
+            &cursor_str,
+            state
+                .config
+                .pagination
+                .accepts_legacy_cursors(state.clock.now_epoch_ms()),
+        )
+        .map_err(|e| ApiError::BadRequest(format!("Invalid cursor: {}", e)))?;
+        match (sort, cursor_data.last_visited_at, cursor_data.offset) {
+            (_, None, None) => (Some((cursor_data.created_at, cursor_data.id)), None),
+            (SearchSort::Relevance, None, Some(offset)) => (None, Some(offset)),
+            _ => {
+                return Err(ApiError::BadRequest(
+                    "Invalid cursor: issued for a different sort order".to_string(),
+                ))
+            }
+        }
     } else {
-        None
+        (None, None)
     };
 
     // Check if user is admin
@@ -742,11 +1538,16 @@ pub async fn search_urls(
     let params = SearchParams {
         q: q.to_string(),
         created_by: query.created_by,
-        created_from: query.created_from,
-        created_to: query.created_to,
+        created_from: created_bound_millis("created_from", query.created_from),
+        created_to: created_bound_millis("created_to", query.created_to),
         is_active: query.is_active,
+        created_via,
+        unused_since,
+        campaign_id: query.campaign_id,
         limit,
         cursor,
+        sort,
+        offset,
     };
 
     // Execute search
@@ -754,25 +1555,38 @@ pub async fn search_urls(
         .storage
         .search(&params, is_admin, user_id.as_deref())
         .await
-        .map_err(|e| ApiError::Internal(format!("Search failed: {}", e)))?;
+        .map_err(|e| ApiError::storage("Search failed", e))?;
 
     // Build response
-    let base = Some(state.config.redirect_base_url.as_str());
-
-    let next_cursor = if let Some((created_at, id)) = result.next_cursor {
-        let cursor_data = CursorData { created_at, id };
-        create_cursor(&cursor_data).ok()
-    } else {
-        None
-    };
+    let base = Some(public_base.as_str());
+
+    let next_cursor = match (result.next_offset, result.next_cursor) {
+        (Some(offset), _) => Some(CursorData {
+            created_at: 0,
+            id: 0,
+            last_visited_at: None,
+            offset: Some(offset),
+        }),
+        (None, Some((created_at, id))) => Some(CursorData {
+            created_at,
+            id,
+            last_visited_at: None,
+            offset: None,
+        }),
+        (None, None) => None,
+    }
+    .and_then(|cursor_data| create_cursor(&cursor_data).ok());
 
+    let items = canonical_search_hits(state.storage.as_ref(), result.items).await?;
+    let mut items = nest_aliases(state.storage.as_ref(), items, base).await?;
+    if is_admin {
+        attach_owner_emails(state.storage.as_ref(), &mut items).await?;
+    }
     Ok(Json(SearchResponse {
-        items: result
-            .items
-            .into_iter()
-            .map(|url| ShortenedUrlResponse::with_base(url, base))
-            .collect(),
+        items,
         next_cursor,
         has_more: result.has_more,
+        exact_match: result.exact_match,
+        limit,
     }))
 }
@@ -0,0 +1,69 @@
+//! Permanent deletion of a link, for content that must not be kept at all.
+//!
+//! Links are normally only deactivated, and the database refuses deletes
+//! from `urls`. With `ALLOW_HARD_DELETE` set, an admin can call
+//! `DELETE /api/admin/links/{code}?confirm=CODE`, repeating the short code
+//! in `confirm`, to delete the link with its analytics and history in one
+//! transaction (see `Storage::hard_delete`). Deleting a link deletes its
+//! aliases too. The delete is recorded in the audit log, which is kept, in
+//! the same transaction.
+//! The code becomes free to be created again.
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{is_user_admin, ApiError, AppState};
+use crate::auth::AuthClaims;
+use crate::storage::HardDeleteReport;
+
+#[derive(Debug, Deserialize)]
+pub struct HardDeleteQuery {
+    /// The short code again, guarding against deleting the wrong link
+    pub confirm: Option<String>,
+}
+
+/// Permanently delete a link and its analytics (admin only)
+pub async fn hard_delete_link(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Query(query): Query<HardDeleteQuery>,
+) -> Result<Json<HardDeleteReport>, ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Only administrators can delete links".to_string(),
+        ));
+    }
+    if !state.config.database.allow_hard_delete {
+        return Err(ApiError::Forbidden(
+            "Hard delete is disabled; set ALLOW_HARD_DELETE=true to enable it".to_string(),
+        ));
+    }
+    if query.confirm.as_deref() != Some(code.as_str()) {
+        return Err(ApiError::BadRequest(
+            "Repeat the short code in the confirm parameter to delete it".to_string(),
+        ));
+    }
+
+    let actor = claims.as_ref().and_then(|c| c.user_id());
+    let report = state
+        .storage
+        .hard_delete(&code, actor.as_deref())
+        .await
+        .map_err(|e| ApiError::storage("Failed to delete URL", e))?
+        .ok_or_else(|| ApiError::NotFound("URL not found".to_string()))?;
+    tracing::info!(
+        short_code = %code,
+        actor = actor.as_deref().unwrap_or("unknown"),
+        codes = report.codes.len(),
+        "link permanently deleted"
+    );
+
+    Ok(Json(report))
+}
@@ -0,0 +1,60 @@
+//! Shared handling of the `limit` query parameter.
+//!
+//! Every list-style endpoint resolves its page size through [`clamp_limit`] so
+//! a client cannot hold a database connection with an unbounded request. The
+//! per-endpoint maxima come from [`crate::config::PaginationConfig`], and the
+//! effective value is echoed back as `limit` in each response.
+
+/// Default page size for `GET /api/urls`.
+pub const LIST_DEFAULT_LIMIT: i64 = 50;
+/// Default page size for `GET /api/urls/search`.
+pub const SEARCH_DEFAULT_LIMIT: i64 = 50;
+/// Default row count for the analytics endpoints.
+pub const ANALYTICS_DEFAULT_LIMIT: i64 = 100;
+
+/// Resolve a requested limit against an endpoint's default and maximum.
+///
+/// A missing value falls back to `default`, zero and negative values are raised
+/// to 1, and anything above `max` is lowered to it.
+pub fn clamp_limit(requested: Option<i64>, default: i64, max: i64) -> i64 {
+    let max = max.max(1);
+    requested.unwrap_or(default).clamp(1, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_limit_uses_default() {
+        assert_eq!(clamp_limit(None, 50, 200), 50);
+    }
+
+    #[test]
+    fn default_above_max_is_clamped() {
+        assert_eq!(clamp_limit(None, 50, 10), 10);
+    }
+
+    #[test]
+    fn zero_and_negative_limits_become_one() {
+        assert_eq!(clamp_limit(Some(0), 50, 200), 1);
+        assert_eq!(clamp_limit(Some(-5), 50, 200), 1);
+        assert_eq!(clamp_limit(Some(i64::MIN), 50, 200), 1);
+    }
+
+    #[test]
+    fn oversized_limit_is_capped() {
+        assert_eq!(clamp_limit(Some(1_000_000), 50, 200), 200);
+        assert_eq!(clamp_limit(Some(i64::MAX), 50, 200), 200);
+    }
+
+    #[test]
+    fn in_range_limit_is_unchanged() {
+        assert_eq!(clamp_limit(Some(75), 50, 200), 75);
+    }
+
+    #[test]
+    fn non_positive_max_still_allows_one_row() {
+        assert_eq!(clamp_limit(Some(10), 50, 0), 1);
+    }
+}
@@ -0,0 +1,56 @@
+//! Per-link options set by create and update requests.
+//!
+//! Requests name options as top-level fields (such as `hide_stats`); the
+//! handlers collect them into a [`LinkOptions`] and validate it before
+//! touching storage. Creates store it with the new link; updates merge it
+//! into the options the link already has.
+
+use std::sync::Arc;
+
+use super::handlers::ApiError;
+use crate::models::{LinkOptions, ShortenedUrl};
+use crate::storage::Storage;
+
+/// `changes` if every option in it is acceptable.
+pub(crate) fn validated(changes: LinkOptions) -> Result<LinkOptions, ApiError> {
+    changes.validate().map_err(ApiError::BadRequest)?;
+    Ok(changes)
+}
+
+/// Store the options `changes` sets on `url` and return the link as it now
+/// reads. Options left out of `changes` keep their value.
+pub(crate) async fn apply_link_options(
+    storage: &dyn Storage,
+    url: Arc<ShortenedUrl>,
+    changes: &LinkOptions,
+) -> Result<Arc<ShortenedUrl>, ApiError> {
+    if changes.is_empty() {
+        return Ok(url);
+    }
+    let options = url.options.merged(changes);
+    storage
+        .set_link_options(&url.short_code, &options)
+        .await
+        .map_err(|e| ApiError::storage("Failed to update link options", e))?;
+    Ok(Arc::new(ShortenedUrl {
+        options,
+        ..(*url).clone()
+    }))
+}
+
+/// `url`, an existing link a create request is answered with, unless the
+/// request sets options to values the link does not have. A create never
+/// changes an existing link, so that is a conflict.
+pub(crate) fn unchanged_by_create(
+    url: Arc<ShortenedUrl>,
+    requested: &LinkOptions,
+) -> Result<Arc<ShortenedUrl>, ApiError> {
+    let changed = url.options.changed_by(requested);
+    if changed.is_empty() {
+        return Ok(url);
+    }
+    Err(ApiError::Conflict(format!(
+        "A link for this destination already exists with a different {}",
+        changed.join(", ")
+    )))
+}
@@ -0,0 +1,69 @@
+//! Live visit stream: `GET /api/links/{code}/analytics/live` (server-sent events).
+//!
+//! Every redirect of the code is pushed as a `visit` event. A subscriber that
+//! cannot keep up misses events instead of slowing redirects down, and is told
+//! how many it missed through a `dropped` event. Idle streams carry periodic
+//! keep-alive comments so proxies do not close them.
+
+use axum::{
+    extract::{Path, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::StreamExt;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{authorize_url_mutation, ApiError, AppState};
+use crate::auth::AuthClaims;
+
+/// Interval between keep-alive comments on an idle stream.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Stream visits of a short code as they happen (owner or admin)
+pub async fn stream_live_visits(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+) -> Result<Response, ApiError> {
+    let Some(live) = state.live_visits.as_ref() else {
+        return Err(ApiError::NotFound(
+            "Live visits are disabled; set LIVE_VISITS_ENABLED=true to enable them".to_string(),
+        ));
+    };
+    let code = decode_code_path_param(&encoded_code)?;
+
+    authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    let user = claims
+        .as_ref()
+        .and_then(|c| c.user_id())
+        .unwrap_or_default();
+    let Some(subscription) = live.subscribe(&code, &user) else {
+        return Err(ApiError::TooManyRequests(format!(
+            "At most {} live visit streams may be open per user",
+            live.max_connections_per_user()
+        )));
+    };
+
+    let guard = subscription.guard;
+    let events = BroadcastStream::new(subscription.receiver).map(move |item| {
+        // The guard lives as long as the stream and releases the slot on disconnect.
+        let _ = &guard;
+        match item {
+            Ok(visit) => Event::default().event("visit").json_data(visit),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                Ok(Event::default().event("dropped").data(skipped.to_string()))
+            }
+        }
+    });
+
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(HEARTBEAT_INTERVAL))
+        .into_response())
+}
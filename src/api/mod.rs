@@ -1,7 +1,38 @@
+pub mod aliases;
 pub mod analytics;
+pub mod analytics_export;
+pub mod campaigns;
+pub mod challenge;
+pub mod click_history;
+pub mod code_hash;
 pub mod code_param;
+pub mod code_rng;
 pub mod handlers;
+pub mod hard_delete;
+pub mod limits;
+pub mod link_options;
+pub mod live;
+pub mod moderation;
+pub mod profile;
+pub mod public_url;
+pub mod quick;
+pub mod quota;
+pub mod rename;
+pub mod reports;
+pub mod reservations;
+pub mod resolve;
 pub mod routes;
+pub mod server_info;
+pub mod slack;
 pub mod static_files;
+pub mod stats;
+pub mod stats_privacy;
+pub mod time_zone;
+pub mod timeout;
+pub mod warnings;
 
-pub use routes::create_api_router;
+pub use routes::{
+    create_api_router, create_api_router_with_creation_challenge,
+    create_api_router_with_live_visits, create_api_router_with_redirect_stats,
+    create_api_router_with_server_info, create_api_router_with_usage,
+};
@@ -0,0 +1,283 @@
+//! Link creation without signing in, and the admin queue that reviews it.
+//!
+//! With `ALLOW_ANONYMOUS_CREATE` set, `POST /api/public/urls` accepts a
+//! destination from anyone and creates a link with a generated code, owned by
+//! [`ANONYMOUS_USER`]. Requests are limited per client IP, resolved like the
+//! analytics IP (see `ANALYTICS_TRUSTED_PROXY_MODE`). With
+//! `ANONYMOUS_CREATE_REQUIRE_APPROVAL` the link is held inactive as `pending`
+//! until an admin approves it; rejecting a link deactivates it and records why.
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::challenge::require_challenge;
+use super::code_param::decode_code_path_param;
+use super::handlers::{
+    code_space_exhausted, create_with_random_code, is_user_admin, validated_destination,
+    validated_short_code_max_length, ApiError, AppState, ShortenedUrlResponse,
+};
+use super::limits::{clamp_limit, LIST_DEFAULT_LIMIT};
+use super::public_url::PublicBaseUrl;
+use crate::analytics::extract_client_ip;
+use crate::auth::AuthClaims;
+use crate::config::ChallengeEndpoint;
+use crate::models::{CreatedVia, LinkOptions, ModerationEntry, ModerationStatus};
+use crate::storage::StorageError;
+
+/// Owner recorded on links created without signing in.
+pub const ANONYMOUS_USER: &str = "anonymous";
+
+/// Longest accepted rejection reason, in characters.
+pub const MAX_REASON_LENGTH: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct AnonymousCreateRequest {
+    pub url: String,
+    /// Rejected: anonymous links always get a generated code
+    pub custom_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AnonymousCreateResponse {
+    #[serde(flatten)]
+    pub link: ShortenedUrlResponse,
+    /// `pending` while the link waits for an admin, otherwise `approved`
+    pub moderation_status: ModerationStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationQuery {
+    /// Entries to list (default `pending`)
+    pub status: Option<String>,
+    /// Page size (default 50, clamped to `PaginationConfig::list_max_limit`)
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModerationListResponse {
+    pub links: Vec<ModerationEntry>,
+    pub status: ModerationStatus,
+    /// Effective page size after clamping the requested limit
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectRequest {
+    pub reason: String,
+}
+
+/// Create a link for an unauthenticated visitor (generated codes only)
+pub async fn create_anonymous_url(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+    Json(payload): Json<AnonymousCreateRequest>,
+) -> Result<(StatusCode, Json<AnonymousCreateResponse>), ApiError> {
+    let config = &state.config.anonymous_create;
+    if !config.enabled {
+        return Err(ApiError::NotFound("Not found".to_string()));
+    }
+    if payload.custom_code.is_some() {
+        return Err(ApiError::BadRequest(
+            "Custom codes require signing in".to_string(),
+        ));
+    }
+
+    // Without a peer address (e.g. in-process callers) every request shares one window.
+    let peer = connect_info
+        .map(|Extension(ConnectInfo(addr))| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let client_ip = extract_client_ip(&headers, peer, &state.config.analytics);
+    if !state
+        .anonymous_limiter
+        .try_acquire(&client_ip.to_string(), Instant::now())
+    {
+        return Err(ApiError::TooManyRequests(format!(
+            "Anonymous link limit of {} per minute reached, try again shortly",
+            config.rate_limit_per_minute
+        )));
+    }
+
+    require_challenge(&state, ChallengeEndpoint::Public, &headers, Some(client_ip)).await?;
+    let url = validated_destination(&payload.url, &state.config)?;
+    let created = match create_with_random_code(
+        state.storage.as_ref(),
+        &state.code_rng,
+        &url,
+        Some(ANONYMOUS_USER),
+        None,
+        CreatedVia::Api,
+        &LinkOptions::default(),
+        validated_short_code_max_length(state.config.short_code_max_length),
+    )
+    .await
+    {
+        Ok(created) => created,
+        Err(StorageError::Conflict) => return Err(code_space_exhausted()),
+        Err(err) => return Err(ApiError::storage("Failed to create URL", err)),
+    };
+
+    // The code has not been handed out yet, so nobody can follow it before
+    // a pending link is deactivated here.
+    let status = if config.require_approval {
+        ModerationStatus::Pending
+    } else {
+        ModerationStatus::Approved
+    };
+    if let Err(err) = state
+        .storage
+        .submit_for_moderation(&created.short_code, status)
+        .await
+    {
+        // Never leave an unreviewed link active.
+        if let Err(error) = state.storage.deactivate(&created.short_code).await {
+            tracing::warn!(%error, short_code = %created.short_code, "Failed to deactivate unmoderated link");
+        }
+        return Err(ApiError::storage("Failed to queue URL for moderation", err));
+    }
+
+    let created = match state.storage.get_authoritative(&created.short_code).await {
+        Ok(Some(current)) => current,
+        Ok(None) => created,
+        Err(err) => return Err(ApiError::storage("Failed to load URL", err)),
+    };
+    Ok((
+        StatusCode::CREATED,
+        Json(AnonymousCreateResponse {
+            link: ShortenedUrlResponse::with_base(created, Some(public_base.as_str())),
+            moderation_status: status,
+        }),
+    ))
+}
+
+async fn require_admin(state: &AppState, claims: &Option<AuthClaims>) -> Result<(), ApiError> {
+    if is_user_admin(state.storage.as_ref(), claims).await {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden(
+            "Only administrators can moderate links".to_string(),
+        ))
+    }
+}
+
+/// List links under moderation, oldest first (admin only)
+pub async fn list_moderation(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<ModerationQuery>,
+) -> Result<Json<ModerationListResponse>, ApiError> {
+    require_admin(&state, &claims).await?;
+
+    let status = match query.status.as_deref() {
+        None => ModerationStatus::Pending,
+        Some(value) => ModerationStatus::parse(value).ok_or_else(|| {
+            ApiError::BadRequest("Status must be one of pending, approved or rejected".to_string())
+        })?,
+    };
+    let limit = clamp_limit(
+        query.limit,
+        LIST_DEFAULT_LIMIT,
+        state.config.pagination.list_max_limit,
+    );
+    let offset = query.offset.max(0);
+    if offset > state.config.pagination.max_offset {
+        return Err(ApiError::BadRequest(format!(
+            "Offset must be at most {}",
+            state.config.pagination.max_offset
+        )));
+    }
+
+    let links = state
+        .storage
+        .list_moderation(status, limit, offset)
+        .await
+        .map_err(|e| ApiError::storage("Failed to list moderated links", e))?;
+
+    Ok(Json(ModerationListResponse {
+        links,
+        status,
+        limit,
+        offset,
+    }))
+}
+
+/// Approve a pending link and activate it (admin only)
+pub async fn approve_link(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+) -> Result<Json<ModerationEntry>, ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    require_admin(&state, &claims).await?;
+
+    let decided_by = claims.as_ref().and_then(|c| c.user_id());
+    let approved = state
+        .storage
+        .approve_link(&code, decided_by.as_deref())
+        .await
+        .map_err(|e| ApiError::storage("Failed to approve URL", e))?;
+    decided_entry(
+        &state,
+        &code,
+        approved,
+        "Only pending links can be approved",
+    )
+    .await
+}
+
+/// Reject a pending or approved link, deactivating it (admin only)
+pub async fn reject_link(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Json(payload): Json<RejectRequest>,
+) -> Result<Json<ModerationEntry>, ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    require_admin(&state, &claims).await?;
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(ApiError::BadRequest(format!(
+            "Reason must be 1-{} characters",
+            MAX_REASON_LENGTH
+        )));
+    }
+
+    let decided_by = claims.as_ref().and_then(|c| c.user_id());
+    let rejected = state
+        .storage
+        .reject_link(&code, reason, decided_by.as_deref())
+        .await
+        .map_err(|e| ApiError::storage("Failed to reject URL", e))?;
+    decided_entry(&state, &code, rejected, "Link has already been rejected").await
+}
+
+/// The entry after a decision, or why no decision was made.
+async fn decided_entry(
+    state: &AppState,
+    code: &str,
+    decided: bool,
+    conflict: &str,
+) -> Result<Json<ModerationEntry>, ApiError> {
+    let entry = state
+        .storage
+        .get_moderation(code)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load moderation entry", e))?
+        .ok_or_else(|| ApiError::NotFound("Link is not under moderation".to_string()))?;
+    if !decided {
+        return Err(ApiError::Conflict(conflict.to_string()));
+    }
+    Ok(Json(entry))
+}
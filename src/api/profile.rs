@@ -0,0 +1,307 @@
+//! User profiles: `GET /api/admin/users/{user_id}` for admins and
+//! `GET /api/me` for the signed-in user, plus the admin user list
+//! `GET /api/admin/users` and per-user API usage at
+//! `GET /api/admin/users/{user_id}/usage`.
+//!
+//! A profile gathers what the per-user admin page shows in one response: the
+//! user's sign-ins, their links counted by state, total clicks, quota usage
+//! and their most recent links. The storage reads run concurrently.
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::handlers::{is_user_admin, ApiError, AppState, ShortenedUrlResponse};
+use super::limits::{clamp_limit, LIST_DEFAULT_LIMIT};
+use super::public_url::PublicBaseUrl;
+use super::quota::QuotaUsage;
+use crate::analytics::daily::DAY_SECS;
+use crate::auth::AuthClaims;
+use crate::models::{daily_usage, UsageCounts, UsageDay, UserAccount, UserLinkCounts};
+use crate::paging::fetch_page;
+
+/// Number of most recent links a profile lists.
+pub const PROFILE_RECENT_LINKS: i64 = 10;
+
+/// Days of API usage the user list sums and the usage report covers by default.
+pub const USAGE_DEFAULT_DAYS: i64 = 30;
+
+/// Longest span the usage report covers.
+pub const USAGE_MAX_DAYS: i64 = 366;
+
+#[derive(Serialize)]
+pub struct UserProfileResponse {
+    pub user_id: String,
+    /// Each auth method the user has signed in with; empty for owners who
+    /// never signed in, such as links assigned from the CLI
+    pub accounts: Vec<UserAccount>,
+    /// Whether any of the accounts was promoted with `lynx admin promote`.
+    /// Admins by claim or configuration are not known here.
+    pub is_manual_admin: bool,
+    pub links: UserLinkCounts,
+    /// Active links held against `LINK_QUOTA_PER_USER`, when a quota is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<QuotaUsage>,
+    /// The newest links, newest first
+    pub recent_links: Vec<ShortenedUrlResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserListQuery {
+    /// Page size (default 50, clamped to `PaginationConfig::list_max_limit`)
+    pub limit: Option<i64>,
+    /// Page number, starting from 1
+    #[serde(default = "first_page")]
+    pub page: i64,
+    /// Only list users not seen for this many days
+    pub inactive_days: Option<i64>,
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+#[derive(Serialize)]
+pub struct UserListEntry {
+    #[serde(flatten)]
+    pub account: UserAccount,
+    /// API requests of the user ID over the last `usage_days`, across all
+    /// of its sign-ins
+    pub api_requests: UsageCounts,
+}
+
+#[derive(Serialize)]
+pub struct UserListResponse {
+    /// Sign-ins, newest first
+    pub users: Vec<UserListEntry>,
+    pub usage_days: i64,
+    pub limit: i64,
+    pub page: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inactive_days: Option<i64>,
+}
+
+/// List users, optionally only those inactive for a number of days (admin only)
+pub async fn list_users(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<UserListQuery>,
+) -> Result<Json<UserListResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "The user list is restricted to admins".to_string(),
+        ));
+    }
+
+    let limit = clamp_limit(
+        query.limit,
+        LIST_DEFAULT_LIMIT,
+        state.config.pagination.list_max_limit,
+    );
+    if query.page < 1 {
+        return Err(ApiError::BadRequest("Page must be at least 1".to_string()));
+    }
+    if (query.page - 1).saturating_mul(limit) > state.config.pagination.max_offset {
+        return Err(ApiError::BadRequest(format!(
+            "Pages may start at most {} users in",
+            state.config.pagination.max_offset
+        )));
+    }
+    if query.inactive_days.is_some_and(|days| days < 0) {
+        return Err(ApiError::BadRequest(
+            "inactive_days must not be negative".to_string(),
+        ));
+    }
+
+    let inactive_since = query
+        .inactive_days
+        .map(|days| state.clock.now_epoch_secs() - days.saturating_mul(DAY_SECS));
+    let users = fetch_page(
+        query.page,
+        limit,
+        |limit, cursor| state.storage.list_all_users(limit, cursor, inactive_since),
+        |user| {
+            (
+                user.created_at,
+                user.user_id.clone(),
+                user.auth_method.clone(),
+            )
+        },
+    )
+    .await
+    .map_err(|e| ApiError::storage("Failed to list users", e))?;
+
+    let since = state.clock.now_epoch_secs() - USAGE_DEFAULT_DAYS * DAY_SECS;
+    let mut user_ids: Vec<String> = users.iter().map(|user| user.user_id.clone()).collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    let stored = state
+        .storage
+        .user_usage_totals(&user_ids, since)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load API usage", e))?;
+    let mut usage = state
+        .usage
+        .as_ref()
+        .map(|tracker| tracker.pending_totals(since))
+        .unwrap_or_default();
+    for total in stored {
+        usage.entry(total.user_id).or_default().add(&total.counts);
+    }
+
+    Ok(Json(UserListResponse {
+        users: users
+            .into_iter()
+            .map(|account| UserListEntry {
+                api_requests: usage.get(&account.user_id).copied().unwrap_or_default(),
+                account,
+            })
+            .collect(),
+        usage_days: USAGE_DEFAULT_DAYS,
+        limit,
+        page: query.page,
+        inactive_days: query.inactive_days,
+    }))
+}
+
+/// Get a user's profile (admin only)
+pub async fn get_user_profile(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<UserProfileResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "User profiles are restricted to admins".to_string(),
+        ));
+    }
+
+    let profile = load_profile(&state, user_id, &public_base).await?;
+    if profile.accounts.is_empty() && profile.recent_links.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "User '{}' not found",
+            profile.user_id
+        )));
+    }
+    Ok(Json(profile))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserUsageQuery {
+    /// Days to cover, counting today (default 30, at most 366)
+    pub days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct UserUsageResponse {
+    pub user_id: String,
+    pub days: i64,
+    /// Start of the first day covered (Unix seconds)
+    pub since: i64,
+    /// Requests over the whole span, by endpoint class
+    pub total: UsageCounts,
+    pub total_requests: i64,
+    /// UTC days with requests, oldest first
+    pub daily: Vec<UsageDay>,
+}
+
+/// API requests of a user per UTC day, by endpoint class (admin only)
+pub async fn get_user_usage(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(user_id): Path<String>,
+    Query(query): Query<UserUsageQuery>,
+) -> Result<Json<UserUsageResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "API usage is restricted to admins".to_string(),
+        ));
+    }
+    let days = query.days.unwrap_or(USAGE_DEFAULT_DAYS);
+    if !(1..=USAGE_MAX_DAYS).contains(&days) {
+        return Err(ApiError::BadRequest(format!(
+            "days must be between 1 and {USAGE_MAX_DAYS}"
+        )));
+    }
+
+    let now = state.clock.now_epoch_secs();
+    let since = now - now.rem_euclid(DAY_SECS) - (days - 1) * DAY_SECS;
+    let mut hours = state
+        .storage
+        .user_usage(&user_id, since)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load API usage", e))?;
+    if let Some(tracker) = &state.usage {
+        hours.extend(tracker.pending(&user_id, since));
+    }
+
+    let daily = daily_usage(&hours, DAY_SECS);
+    let mut total = UsageCounts::default();
+    for day in &daily {
+        total.add(&day.counts);
+    }
+    Ok(Json(UserUsageResponse {
+        user_id,
+        days,
+        since,
+        total_requests: total.total(),
+        total,
+        daily,
+    }))
+}
+
+/// Get the signed-in user's own profile
+pub async fn get_my_profile(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<UserProfileResponse>, ApiError> {
+    let Some(user_id) = claims.as_ref().and_then(|c| c.user_id()) else {
+        return Err(ApiError::Forbidden(
+            "Your profile requires a signed-in user".to_string(),
+        ));
+    };
+
+    Ok(Json(load_profile(&state, user_id, &public_base).await?))
+}
+
+async fn load_profile(
+    state: &AppState,
+    user_id: String,
+    public_base: &PublicBaseUrl,
+) -> Result<UserProfileResponse, ApiError> {
+    let storage = state.storage.as_ref();
+    let (accounts, links, recent_links, quota_used) = tokio::try_join!(
+        storage.user_accounts(&user_id),
+        storage.user_link_counts(&user_id),
+        storage.list_user_links(&user_id, PROFILE_RECENT_LINKS, None),
+        // Counted the way the quota check counts
+        storage.count_user_links_by_state(&user_id, true),
+    )
+    .map_err(|e| ApiError::storage("Failed to load user profile", e))?;
+
+    let quota = state
+        .config
+        .link_quota
+        .max_links_per_user
+        .map(|limit| QuotaUsage {
+            used: u64::try_from(quota_used).unwrap_or(0),
+            limit,
+        });
+    let base = Some(public_base.as_str());
+    Ok(UserProfileResponse {
+        is_manual_admin: accounts.iter().any(|account| account.is_manual_admin),
+        user_id,
+        accounts,
+        links,
+        quota,
+        recent_links: recent_links
+            .into_iter()
+            .map(|url| ShortenedUrlResponse::with_base(url, base))
+            .collect(),
+    })
+}
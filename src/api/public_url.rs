@@ -0,0 +1,208 @@
+//! Base URL of absolute links in API responses and pages.
+//!
+//! Short URLs are built on `REDIRECT_BASE_URL` plus any
+//! `REDIRECT_PATH_PREFIX`. With
+//! `PUBLIC_URL_FROM_FORWARDED_HEADERS`, a request relayed by a trusted proxy
+//! (see [`is_trusted_proxy`]) may override its scheme and host with
+//! `X-Forwarded-Proto` and `X-Forwarded-Host`, so one instance answers with
+//! the public name the caller used (`https://go.example.com`) rather than
+//! the address the proxy reached it on. Headers from untrusted peers, and
+//! values that are not a plain scheme or host, are ignored.
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use url::Url;
+
+use super::handlers::AppState;
+use crate::analytics::is_trusted_proxy;
+
+/// The redirect base URL for this request; extract it in handlers that
+/// return short URLs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicBaseUrl(pub String);
+
+impl PublicBaseUrl {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for PublicBaseUrl {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let configured = state.config.short_url_base();
+        // Without a peer address (in-process callers) nothing is trusted.
+        let trusted = state.config.public_url.from_forwarded_headers
+            && parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .is_some_and(|ConnectInfo(peer)| {
+                    is_trusted_proxy(peer.ip(), &state.config.analytics)
+                });
+        let base = if trusted {
+            forwarded_base_url(&configured, &parts.headers)
+        } else {
+            None
+        };
+        Ok(Self(base.unwrap_or(configured)))
+    }
+}
+
+/// `configured` with the scheme and host of `X-Forwarded-Proto` and
+/// `X-Forwarded-Host`, or `None` when neither header is usable. The path of
+/// the configured base is kept. A proxy that appends to these headers, on
+/// the same line or a line of its own, keeps whatever the client sent in
+/// front, so only the last entry, the one the trusted proxy added, counts.
+pub fn forwarded_base_url(configured: &str, headers: &HeaderMap) -> Option<String> {
+    let last = |name: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .next_back()?
+            .to_str()
+            .ok()?
+            .rsplit(',')
+            .next()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let proto = last("x-forwarded-proto")
+        .map(str::to_ascii_lowercase)
+        .filter(|proto| proto == "http" || proto == "https");
+    let host = last("x-forwarded-host").filter(|host| is_plain_host(host));
+    if proto.is_none() && host.is_none() {
+        return None;
+    }
+
+    let mut url = Url::parse(configured).ok()?;
+    if let Some(proto) = proto {
+        url.set_scheme(&proto).ok()?;
+    }
+    if let Some(host) = host {
+        // Parsed on its own so a port in the header replaces the configured one.
+        let forwarded = Url::parse(&format!("{}://{}", url.scheme(), host)).ok()?;
+        url.set_host(forwarded.host_str()).ok()?;
+        url.set_port(forwarded.port()).ok()?;
+    }
+    Some(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// A host name or address with an optional port, nothing that would change
+/// the URL's path or credentials.
+fn is_plain_host(host: &str) -> bool {
+    host.bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b':' | b'[' | b']'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn forwarded_scheme_and_host_replace_the_configured_ones() {
+        let configured = "http://10.0.0.5:3000";
+        assert_eq!(forwarded_base_url(configured, &headers(&[])), None);
+        assert_eq!(
+            forwarded_base_url(
+                configured,
+                &headers(&[
+                    ("x-forwarded-proto", "https"),
+                    ("x-forwarded-host", "go.example.com")
+                ])
+            )
+            .as_deref(),
+            Some("https://go.example.com")
+        );
+        assert_eq!(
+            forwarded_base_url(
+                "http://example.com/s",
+                &headers(&[
+                    ("x-forwarded-proto", "HTTPS"),
+                    ("x-forwarded-host", "go.example.com:8443")
+                ])
+            )
+            .as_deref(),
+            Some("https://go.example.com:8443/s")
+        );
+        assert_eq!(
+            forwarded_base_url(configured, &headers(&[("x-forwarded-proto", "https")])).as_deref(),
+            Some("https://10.0.0.5:3000")
+        );
+    }
+
+    #[test]
+    fn appended_headers_use_the_entry_the_proxy_added() {
+        assert_eq!(
+            forwarded_base_url(
+                "http://10.0.0.5:3000",
+                &headers(&[
+                    ("x-forwarded-proto", "http, https"),
+                    ("x-forwarded-host", "evil.example, go.example.com")
+                ])
+            )
+            .as_deref(),
+            Some("https://go.example.com")
+        );
+        assert_eq!(
+            forwarded_base_url(
+                "http://10.0.0.5:3000",
+                &headers(&[("x-forwarded-host", "go.example.com, evil.example/x")])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn separate_header_lines_use_the_one_the_proxy_added() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("x-forwarded-proto", "http"),
+            ("x-forwarded-host", "evil.example"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "other.example, go.example.com"),
+        ] {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        assert_eq!(
+            forwarded_base_url("http://10.0.0.5:3000", &headers).as_deref(),
+            Some("https://go.example.com")
+        );
+    }
+
+    #[test]
+    fn unusable_values_are_ignored() {
+        let configured = "http://10.0.0.5:3000";
+        for (name, value) in [
+            ("x-forwarded-proto", "javascript"),
+            ("x-forwarded-host", "evil.example/path"),
+            ("x-forwarded-host", "user@evil.example"),
+            ("x-forwarded-host", ""),
+        ] {
+            assert_eq!(
+                forwarded_base_url(configured, &headers(&[(name, value)])),
+                None,
+                "{}: {}",
+                name,
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Bookmarklet endpoint: `GET /api/quick?url=...` shortens a page in one click.
+//!
+//! Browsers get a small HTML page with the short URL, a copy button and the
+//! bookmarklet itself; clients sending `Accept: application/json` get the same
+//! body as `POST /api/urls`. Creating from a GET is safe to repeat because an
+//! active link the same user already made for the destination is returned
+//! instead of a new one, and every request counts against a per-user limit.
+
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{ACCEPT, CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use dashmap::DashMap;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::challenge::require_challenge;
+use super::handlers::{
+    code_space_exhausted, create_with_random_code, fill_title, random_code, validated_destination,
+    validated_short_code_max_length, ApiError, AppState, ShortenedUrlResponse,
+};
+use super::public_url::PublicBaseUrl;
+use super::quota::check_link_quota;
+use super::warnings::Warning;
+use crate::auth::AuthClaims;
+use crate::config::ChallengeEndpoint;
+use crate::models::{CreatedVia, LinkOptions, ShortenedUrl};
+use crate::redirect::interstitial::escape_html;
+use crate::storage::StorageError;
+
+/// Length of the rate limit window.
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Expired windows are swept once this many users are tracked.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// Rate limit key for requests without a user id.
+const ANONYMOUS_KEY: &str = "";
+
+/// Copy button and bookmarklet generation. The bookmarklet points back at
+/// whatever origin and path served the page, so it also works behind a proxy.
+const PAGE_SCRIPT: &str = r#"
+const copy = document.getElementById("copy");
+copy.addEventListener("click", () => {
+  navigator.clipboard.writeText(copy.dataset.url).then(() => {
+    copy.textContent = "Copied";
+  });
+});
+const bookmarklet = "javascript:location.href='" + location.origin + location.pathname +
+  "?url='+encodeURIComponent(location.href)";
+document.getElementById("bookmarklet").href = bookmarklet;
+document.getElementById("bookmarklet-source").textContent = bookmarklet;
+"#;
+
+/// Fixed one-minute windows counting quick link requests per user.
+pub struct QuickRateLimiter {
+    limit_per_minute: u32,
+    windows: DashMap<String, (Instant, u32)>,
+}
+
+impl QuickRateLimiter {
+    /// A limit of 0 allows every request.
+    pub fn new(limit_per_minute: u32) -> Self {
+        Self {
+            limit_per_minute,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Count one request for `key`, returning `false` once the window is used up.
+    pub fn try_acquire(&self, key: &str, now: Instant) -> bool {
+        if self.limit_per_minute == 0 {
+            return true;
+        }
+        if self.windows.len() >= SWEEP_THRESHOLD {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        }
+
+        let mut window = self.windows.entry(key.to_string()).or_insert((now, 0));
+        let (start, count) = window.value_mut();
+        if now.duration_since(*start) >= RATE_LIMIT_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.limit_per_minute {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickQuery {
+    /// Destination to shorten
+    pub url: Option<String>,
+}
+
+/// Shorten `url` and show the result (HTML, or JSON when requested)
+pub async fn quick_create(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    headers: HeaderMap,
+    Query(query): Query<QuickQuery>,
+) -> Response {
+    let json = wants_json(&headers);
+    let result = quick_link(
+        &state,
+        &claims,
+        &headers,
+        query.url.as_deref().unwrap_or_default(),
+    )
+    .await;
+
+    match result {
+        Ok((status, url, warnings)) => {
+            let mut response = ShortenedUrlResponse::with_base(url, Some(public_base.as_str()));
+            response.warnings = warnings;
+            if json {
+                (status, Json(response)).into_response()
+            } else {
+                link_page(status, &response)
+            }
+        }
+        Err(error) if json => error.into_response(),
+        Err(error) => error_page(&error),
+    }
+}
+
+/// Return the caller's existing link for `raw_url`, or create one.
+async fn quick_link(
+    state: &AppState,
+    claims: &Option<AuthClaims>,
+    headers: &HeaderMap,
+    raw_url: &str,
+) -> Result<(StatusCode, Arc<ShortenedUrl>, Vec<Warning>), ApiError> {
+    let created_by = claims.as_ref().and_then(|c| c.user_id());
+    let created_by_ref = created_by.as_deref();
+
+    if !state
+        .quick_limiter
+        .try_acquire(created_by_ref.unwrap_or(ANONYMOUS_KEY), Instant::now())
+    {
+        return Err(ApiError::TooManyRequests(format!(
+            "Quick link limit of {} per minute reached, try again shortly",
+            state.config.quick_link.rate_limit_per_minute
+        )));
+    }
+
+    require_challenge(state, ChallengeEndpoint::Quick, headers, None).await?;
+    let url = validated_destination(raw_url, &state.config)?;
+
+    let existing = state
+        .storage
+        .find_active_by_destination(&url, created_by_ref)
+        .await
+        .map_err(|e| ApiError::storage("Failed to look up existing links", e))?;
+    if let Some(existing) = existing {
+        return Ok((StatusCode::OK, existing, Vec::new()));
+    }
+
+    let quota = check_link_quota(state, created_by_ref, claims).await?;
+
+    match create_with_random_code(
+        state.storage.as_ref(),
+        &state.code_rng,
+        &url,
+        created_by_ref,
+        claims.as_ref().and_then(|c| c.auth_method()).as_deref(),
+        CreatedVia::Bookmarklet,
+        &LinkOptions::default(),
+        validated_short_code_max_length(state.config.short_code_max_length),
+    )
+    .await
+    {
+        Ok(url) => {
+            fill_title(state, &url);
+            let warnings = quota
+                .and_then(|usage| usage.warning_after_create())
+                .into_iter()
+                .collect();
+            Ok((StatusCode::CREATED, url, warnings))
+        }
+        Err(StorageError::Conflict) => Err(code_space_exhausted()),
+        Err(err) => Err(ApiError::storage("Failed to create URL", err)),
+    }
+}
+
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("application/json"))
+}
+
+fn link_page(status: StatusCode, response: &ShortenedUrlResponse) -> Response {
+    let nonce = random_code(24);
+    let title = if status == StatusCode::CREATED {
+        "Short link created"
+    } else {
+        "You already shortened this page"
+    };
+    let short = escape_html(response.short_url.as_deref().unwrap_or_default());
+    let destination = escape_html(&response.inner.original_url);
+    let warnings: String = response
+        .warnings
+        .iter()
+        .map(|warning| {
+            format!(
+                "<p><strong>Note:</strong> {}</p>\n",
+                escape_html(&warning.message)
+            )
+        })
+        .collect();
+    let body = format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n\
+         <p><a href=\"{short}\">{short}</a> \
+         <button type=\"button\" id=\"copy\" data-url=\"{short}\">Copy</button></p>\n\
+         <p>Destination: {destination}</p>\n{warnings}\
+         <h2>Bookmarklet</h2>\n\
+         <p>Drag this link to your bookmarks bar to shorten any page: \
+         <a id=\"bookmarklet\" href=\"#\">Shorten with Lynx</a></p>\n\
+         <pre><code id=\"bookmarklet-source\"></code></pre>\n\
+         <script nonce=\"{nonce}\">{PAGE_SCRIPT}</script>\n</body>\n</html>\n"
+    );
+
+    html_response(
+        status,
+        format!("default-src 'none'; script-src 'nonce-{nonce}'"),
+        body,
+    )
+}
+
+fn error_page(error: &ApiError) -> Response {
+    let message = escape_html(error.message());
+    let body = format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Could not shorten this page</title>\n</head>\n<body>\n\
+         <h1>Could not shorten this page</h1>\n<p>{message}</p>\n</body>\n</html>\n"
+    );
+    html_response(error.status_code(), "default-src 'none'".to_string(), body)
+}
+
+fn html_response(status: StatusCode, csp: String, body: String) -> Response {
+    (
+        status,
+        [
+            (CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (CONTENT_SECURITY_POLICY, csp),
+            (REFERRER_POLICY, "no-referrer".to_string()),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter_allows_requests_up_to_the_limit_per_window() {
+        let limiter = QuickRateLimiter::new(2);
+        let start = Instant::now();
+        assert!(limiter.try_acquire("alice", start));
+        assert!(limiter.try_acquire("alice", start));
+        assert!(!limiter.try_acquire("alice", start + Duration::from_secs(30)));
+        assert!(limiter.try_acquire("bob", start));
+        assert!(limiter.try_acquire("alice", start + RATE_LIMIT_WINDOW));
+    }
+
+    #[test]
+    fn zero_limit_disables_the_limiter() {
+        let limiter = QuickRateLimiter::new(0);
+        let now = Instant::now();
+        assert!((0..100).all(|_| limiter.try_acquire("alice", now)));
+    }
+
+    #[test]
+    fn json_is_chosen_from_the_accept_header() {
+        let mut headers = HeaderMap::new();
+        assert!(!wants_json(&headers));
+        headers.insert(ACCEPT, "text/html,*/*;q=0.8".parse().unwrap());
+        assert!(!wants_json(&headers));
+        headers.insert(ACCEPT, "application/json".parse().unwrap());
+        assert!(wants_json(&headers));
+    }
+}
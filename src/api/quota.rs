@@ -0,0 +1,99 @@
+//! Per-user link quota (`LINK_QUOTA_PER_USER`).
+//!
+//! Creation is refused once the owner has as many active links as the quota
+//! allows, and succeeds with a [`WarningCode::QuotaNearlyReached`] warning
+//! from [`QUOTA_WARNING_PERCENT`] of it. The check counts before inserting,
+//! so concurrent creations by one user can overshoot it slightly.
+
+use serde::Serialize;
+use serde_json::json;
+
+use super::handlers::{is_user_admin, ApiError, AppState};
+use super::warnings::{Warning, WarningCode};
+use crate::auth::AuthClaims;
+
+/// Share of the quota, in percent, from which new links come with a warning.
+pub const QUOTA_WARNING_PERCENT: u64 = 90;
+
+/// Links an owner holds against their quota, counted before a creation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct QuotaUsage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl QuotaUsage {
+    /// The warning for the owner's usage once one more link exists, if
+    /// that reaches [`QUOTA_WARNING_PERCENT`] of the quota.
+    pub fn warning_after_create(&self) -> Option<Warning> {
+        let used = self.used.saturating_add(1);
+        if used.saturating_mul(100) < self.limit.saturating_mul(QUOTA_WARNING_PERCENT) {
+            return None;
+        }
+        Some(
+            Warning::new(
+                WarningCode::QuotaNearlyReached,
+                format!(
+                    "You are using {} of your {} links; ask an admin to deactivate unused ones or raise the quota",
+                    used, self.limit
+                ),
+            )
+            .with_details(json!({ "used": used, "limit": self.limit })),
+        )
+    }
+}
+
+/// Refuse creating a link for `owner` once the quota is used up. Returns
+/// the usage to derive warnings from, or `None` when no quota applies
+/// (no quota configured, no owner, or an admin caller).
+pub(crate) async fn check_link_quota(
+    state: &AppState,
+    owner: Option<&str>,
+    claims: &Option<AuthClaims>,
+) -> Result<Option<QuotaUsage>, ApiError> {
+    let (Some(limit), Some(owner)) = (state.config.link_quota.max_links_per_user, owner) else {
+        return Ok(None);
+    };
+    if is_user_admin(state.storage.as_ref(), claims).await {
+        return Ok(None);
+    }
+
+    let used = state
+        .storage
+        .count_user_links_by_state(owner, true)
+        .await
+        .map_err(|e| ApiError::storage("Failed to check link quota", e))?;
+    let used = u64::try_from(used).unwrap_or(0);
+    if used >= limit {
+        return Err(ApiError::Forbidden(format!(
+            "Link quota reached: {} of {} active links in use",
+            used, limit
+        )));
+    }
+    Ok(Some(QuotaUsage { used, limit }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_from_ninety_percent_of_the_quota() {
+        let usage = |used| QuotaUsage { used, limit: 10 };
+        assert_eq!(usage(7).warning_after_create(), None);
+
+        let warning = usage(8).warning_after_create().unwrap();
+        assert_eq!(warning.code, WarningCode::QuotaNearlyReached);
+        assert_eq!(warning.details, Some(json!({ "used": 9, "limit": 10 })));
+        assert!(usage(9).warning_after_create().is_some());
+
+        // Small quotas warn on the last link only.
+        assert_eq!(
+            QuotaUsage { used: 0, limit: 2 }.warning_after_create(),
+            None
+        );
+        assert!(QuotaUsage { used: 1, limit: 2 }
+            .warning_after_create()
+            .is_some());
+    }
+}
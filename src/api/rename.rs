@@ -0,0 +1,64 @@
+//! Renaming: `POST /api/links/{code}/rename` moves a link to a new short code.
+//!
+//! The old code is kept as an alias of the new one, so links already shared
+//! keep working: the redirect server follows the alias to the new code's
+//! destination, and clicks on either code count toward the new one. Aliases
+//! always point at a canonical link, so renaming a link again re-points its
+//! earlier aliases, and an alias itself cannot be renamed.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::code_param::decode_code_path_param;
+use super::handlers::{
+    authorize_url_mutation, validated_short_code_max_length, ApiError, AppState,
+    ShortenedUrlResponse,
+};
+use super::public_url::PublicBaseUrl;
+use crate::auth::AuthClaims;
+use crate::storage::StorageError;
+
+#[derive(Debug, Deserialize)]
+pub struct RenameRequest {
+    pub new_code: String,
+}
+
+/// Move a link to `new_code`, keeping the old code as an alias (owner or admin)
+pub async fn rename_url(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Path(encoded_code): Path<String>,
+    Json(payload): Json<RenameRequest>,
+) -> Result<(StatusCode, Json<ShortenedUrlResponse>), ApiError> {
+    let code = decode_code_path_param(&encoded_code)?;
+    let max_short_code_length = validated_short_code_max_length(state.config.short_code_max_length);
+    if payload.new_code.is_empty() || payload.new_code.len() > max_short_code_length {
+        return Err(ApiError::BadRequest(format!(
+            "New code must be 1-{} characters",
+            max_short_code_length
+        )));
+    }
+
+    authorize_url_mutation(state.storage.as_ref(), &claims, &code).await?;
+
+    match state.storage.rename_code(&code, &payload.new_code).await {
+        Ok(Some(url)) => Ok((
+            StatusCode::CREATED,
+            Json(ShortenedUrlResponse::with_base(
+                url,
+                Some(public_base.as_str()),
+            )),
+        )),
+        Ok(None) => Err(ApiError::NotFound("URL not found".to_string())),
+        Err(StorageError::Conflict) => {
+            Err(ApiError::Conflict("Short code already exists".to_string()))
+        }
+        Err(e) => Err(ApiError::storage("Failed to rename URL", e)),
+    }
+}
@@ -0,0 +1,497 @@
+//! Admin reports: `GET /api/admin/reports/destinations` and
+//! `GET /api/admin/reports/activity`.
+//!
+//! With one or more `host` parameters (`?host=a.com&host=b.com`) the report
+//! lists every active link pointing at those hosts, with its owner and
+//! clicks, next to per-host totals: the list to work through when a vendor
+//! domain is shut down. Without hosts it groups all active links by
+//! destination host instead, keeping hosts with at least `min_links` links.
+//! With `url` it lists the active links whose normalized destination (see
+//! [`normalize_url`](crate::destination::normalize_url)) is the same as
+//! that URL's: the duplicates a new link to it would add.
+//!
+//! Both forms are paginated with `page` and `limit` and come as JSON, or as
+//! CSV with `format=csv`. Aliases are left out; they follow their link.
+//!
+//! The activity report covers the last `days` UTC days up to and including
+//! today: links created, clicks and analytics visits per day, with the
+//! day's top creators and most clicked links, for capacity planning and
+//! spotting abuse. It also comes as CSV, one row per day.
+
+use axum::{
+    extract::{Query, RawQuery, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::analytics_export::{csv_field, iso8601};
+use super::handlers::{is_user_admin, ApiError, AppState};
+use super::limits::{clamp_limit, LIST_DEFAULT_LIMIT};
+use crate::analytics::daily::{day_start, DAY_SECS};
+use crate::auth::AuthClaims;
+use crate::destination::{destination_host, normalize_url};
+use crate::models::{ActivityDay, DestinationHostLink, DestinationHostSummary};
+
+/// Most `host` parameters one report accepts.
+pub const MAX_REPORT_HOSTS: usize = 50;
+
+/// First line of the CSV for a report on given hosts.
+pub const LINKS_CSV_HEADER: &str =
+    "dest_host,short_code,original_url,created_by,owner_email,clicks,created_at,created_at_iso\n";
+
+/// First line of the CSV for a report grouped by host.
+pub const HOSTS_CSV_HEADER: &str = "dest_host,links,clicks,owners\n";
+
+/// First line of the CSV for the activity report. The top columns hold
+/// `name:count` pairs separated by `;`.
+pub const ACTIVITY_CSV_HEADER: &str =
+    "day,day_iso,links_created,clicks,visits,top_creators,top_links\n";
+
+const ACTIVITY_DEFAULT_DAYS: i64 = 30;
+const ACTIVITY_MAX_DAYS: i64 = 366;
+
+/// Creators and links listed per day in the activity report.
+pub const ACTIVITY_TOP: i64 = 5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Result<Self, ApiError> {
+        match value {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            other => Err(ApiError::UnprocessableEntity(format!(
+                "Unknown format '{}', expected: json, csv",
+                other
+            ))),
+        }
+    }
+}
+
+/// Query of a destination report. `host` repeats, which the `Query`
+/// extractor cannot express, so it is parsed from the raw query string.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DestinationReportQuery {
+    /// Requested hosts, lowercased and without duplicates
+    pub hosts: Vec<String>,
+    /// Destination whose duplicates are listed; excludes `host`
+    pub url: Option<String>,
+    /// Fewest links a host needs to be listed when grouping (default 1)
+    pub min_links: Option<i64>,
+    /// Page size (default 50, clamped to `PaginationConfig::list_max_limit`)
+    pub limit: Option<i64>,
+    /// Page number, starting from 1
+    pub page: i64,
+    pub format: ReportFormat,
+}
+
+impl DestinationReportQuery {
+    pub fn parse(query: &str) -> Result<Self, ApiError> {
+        let mut parsed = Self {
+            hosts: Vec::new(),
+            url: None,
+            min_links: None,
+            limit: None,
+            page: 1,
+            format: ReportFormat::Json,
+        };
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "host" => {
+                    let host = value.trim().trim_end_matches('.').to_ascii_lowercase();
+                    if host.is_empty() {
+                        return Err(ApiError::BadRequest("host must not be empty".to_string()));
+                    }
+                    if !parsed.hosts.contains(&host) {
+                        parsed.hosts.push(host);
+                    }
+                }
+                "url" => {
+                    let url = value.trim();
+                    if url.is_empty() {
+                        return Err(ApiError::BadRequest("url must not be empty".to_string()));
+                    }
+                    parsed.url = Some(url.to_string());
+                }
+                "min_links" => parsed.min_links = Some(integer(&key, &value)?),
+                "limit" => parsed.limit = Some(integer(&key, &value)?),
+                "page" => parsed.page = integer(&key, &value)?,
+                "format" => parsed.format = ReportFormat::parse(&value)?,
+                _ => {}
+            }
+        }
+
+        if parsed.hosts.len() > MAX_REPORT_HOSTS {
+            return Err(ApiError::BadRequest(format!(
+                "At most {} hosts can be reported at once",
+                MAX_REPORT_HOSTS
+            )));
+        }
+        if parsed.url.is_some() && !parsed.hosts.is_empty() {
+            return Err(ApiError::BadRequest(
+                "url and host cannot be combined".to_string(),
+            ));
+        }
+        if (!parsed.hosts.is_empty() || parsed.url.is_some()) && parsed.min_links.is_some() {
+            return Err(ApiError::BadRequest(
+                "min_links applies only when no host or url is given".to_string(),
+            ));
+        }
+        if parsed.min_links.is_some_and(|min_links| min_links < 1) {
+            return Err(ApiError::BadRequest(
+                "min_links must be at least 1".to_string(),
+            ));
+        }
+        if parsed.page < 1 {
+            return Err(ApiError::BadRequest("Page must be at least 1".to_string()));
+        }
+        Ok(parsed)
+    }
+}
+
+fn integer(key: &str, value: &str) -> Result<i64, ApiError> {
+    value
+        .parse()
+        .map_err(|_| ApiError::BadRequest(format!("{} must be an integer", key)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct DestinationReport {
+    /// Totals per host: each requested host in the order given (all zero
+    /// when nothing points at it), the host of `url`, or the page of grouped
+    /// hosts, most links first
+    pub hosts: Vec<DestinationHostSummary>,
+    /// The form of `url` that links were matched on; absent without `url`
+    /// or when it does not parse as a URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized_url: Option<String>,
+    /// The page of links to the requested hosts, by host and then most
+    /// clicked first, or to `url`, most clicked first; absent when grouping
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<DestinationHostLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_links: Option<i64>,
+    pub limit: i64,
+    pub page: i64,
+}
+
+/// Report active links by destination host (admin only)
+pub async fn destination_report(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    RawQuery(query): RawQuery,
+) -> Result<Response, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Reports are restricted to admins".to_string(),
+        ));
+    }
+    let query = DestinationReportQuery::parse(query.as_deref().unwrap_or_default())?;
+
+    let limit = clamp_limit(
+        query.limit,
+        LIST_DEFAULT_LIMIT,
+        state.config.pagination.list_max_limit,
+    );
+    let offset = (query.page - 1).saturating_mul(limit);
+    if offset > state.config.pagination.max_offset {
+        return Err(ApiError::BadRequest(format!(
+            "Pages may start at most {} rows in",
+            state.config.pagination.max_offset
+        )));
+    }
+
+    let report = if let Some(url) = &query.url {
+        let hosts: Vec<String> = destination_host(url).into_iter().collect();
+        let (totals, links) = tokio::try_join!(
+            state.storage.destination_host_summary(&hosts, 1, 1, 0),
+            state.storage.links_by_destination(url, limit, offset),
+        )
+        .map_err(|e| ApiError::storage("Failed to report destination links", e))?;
+        DestinationReport {
+            hosts: in_requested_order(&hosts, totals),
+            normalized_url: normalize_url(url, &state.config.url_normalization),
+            links: Some(links),
+            min_links: None,
+            limit,
+            page: query.page,
+        }
+    } else if query.hosts.is_empty() {
+        let min_links = query.min_links.unwrap_or(1);
+        let hosts = state
+            .storage
+            .destination_host_summary(&[], min_links, limit, offset)
+            .await
+            .map_err(|e| ApiError::storage("Failed to report destination hosts", e))?;
+        DestinationReport {
+            hosts,
+            normalized_url: None,
+            links: None,
+            min_links: Some(min_links),
+            limit,
+            page: query.page,
+        }
+    } else {
+        let (totals, links) = tokio::try_join!(
+            state
+                .storage
+                .destination_host_summary(&query.hosts, 1, MAX_REPORT_HOSTS as i64, 0),
+            state
+                .storage
+                .links_by_destination_host(&query.hosts, limit, offset),
+        )
+        .map_err(|e| ApiError::storage("Failed to report destination hosts", e))?;
+        DestinationReport {
+            hosts: in_requested_order(&query.hosts, totals),
+            normalized_url: None,
+            links: Some(links),
+            min_links: None,
+            limit,
+            page: query.page,
+        }
+    };
+
+    Ok(match query.format {
+        ReportFormat::Json => Json(report).into_response(),
+        ReportFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"destinations.csv\"",
+                ),
+            ],
+            report_csv(&report),
+        )
+            .into_response(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityReportQuery {
+    /// Number of days to report, ending today (default 30, at most 366)
+    pub days: Option<i64>,
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityReport {
+    /// Effective number of days after clamping
+    pub days: i64,
+    /// Creators and links listed per day
+    pub top: i64,
+    /// One entry per UTC day, oldest first; today is still running
+    pub activity: Vec<ActivityDay>,
+}
+
+/// Report links created, clicks and visits per day (admin only)
+pub async fn activity_report(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<ActivityReportQuery>,
+) -> Result<Response, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Reports are restricted to admins".to_string(),
+        ));
+    }
+    let format = query
+        .format
+        .as_deref()
+        .map_or(Ok(ReportFormat::Json), ReportFormat::parse)?;
+
+    let days = clamp_limit(query.days, ACTIVITY_DEFAULT_DAYS, ACTIVITY_MAX_DAYS);
+    let until = day_start(state.clock.now_epoch_secs()) + DAY_SECS;
+    let since = until - days * DAY_SECS;
+    let activity = state
+        .storage
+        .activity_report(since, until, ACTIVITY_TOP)
+        .await
+        .map_err(|e| ApiError::storage("Failed to report activity", e))?;
+    let report = ActivityReport {
+        days,
+        top: ACTIVITY_TOP,
+        activity,
+    };
+
+    Ok(match format {
+        ReportFormat::Json => Json(report).into_response(),
+        ReportFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"activity.csv\"",
+                ),
+            ],
+            activity_csv(&report.activity),
+        )
+            .into_response(),
+    })
+}
+
+/// The activity report as CSV, one row per day.
+fn activity_csv(activity: &[ActivityDay]) -> String {
+    let mut out = ACTIVITY_CSV_HEADER.to_string();
+    for day in activity {
+        let creators: Vec<String> = day
+            .top_creators
+            .iter()
+            .map(|creator| format!("{}:{}", creator.user_id, creator.links_created))
+            .collect();
+        let links: Vec<String> = day
+            .top_links
+            .iter()
+            .map(|link| format!("{}:{}", link.short_code, link.clicks))
+            .collect();
+        // Writing to a String cannot fail.
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            day.day,
+            iso8601(day.day),
+            day.links_created,
+            day.clicks,
+            day.visits,
+            csv_field(&creators.join(";")),
+            csv_field(&links.join(";")),
+        );
+    }
+    out
+}
+
+/// `totals` for each of `hosts` in turn, with zeros for hosts no active
+/// link points at.
+fn in_requested_order(
+    hosts: &[String],
+    totals: Vec<DestinationHostSummary>,
+) -> Vec<DestinationHostSummary> {
+    let mut totals: HashMap<String, DestinationHostSummary> = totals
+        .into_iter()
+        .map(|summary| (summary.dest_host.clone(), summary))
+        .collect();
+    hosts
+        .iter()
+        .map(|host| {
+            totals.remove(host).unwrap_or(DestinationHostSummary {
+                dest_host: host.clone(),
+                links: 0,
+                clicks: 0,
+                owners: 0,
+            })
+        })
+        .collect()
+}
+
+/// The page of `report` as CSV: its links when hosts or a URL were
+/// requested, its hosts when grouping.
+fn report_csv(report: &DestinationReport) -> String {
+    // Writing to a String cannot fail.
+    match &report.links {
+        Some(links) => {
+            let mut out = LINKS_CSV_HEADER.to_string();
+            for link in links {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_field(&link.dest_host),
+                    csv_field(&link.short_code),
+                    csv_field(&link.original_url),
+                    csv_field(link.created_by.as_deref().unwrap_or_default()),
+                    csv_field(link.owner_email.as_deref().unwrap_or_default()),
+                    link.clicks,
+                    link.created_at,
+                    iso8601(link.created_at.div_euclid(1000)),
+                );
+            }
+            out
+        }
+        None => {
+            let mut out = HOSTS_CSV_HEADER.to_string();
+            for host in &report.hosts {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{}",
+                    csv_field(&host.dest_host),
+                    host.links,
+                    host.clicks,
+                    host.owners,
+                );
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_repeat_and_are_normalized() {
+        let query =
+            DestinationReportQuery::parse("host=A.com&host=b.com.&host=a.com&format=csv&page=2")
+                .unwrap();
+        assert_eq!(query.hosts, ["a.com", "b.com"]);
+        assert_eq!(query.format, ReportFormat::Csv);
+        assert_eq!(query.page, 2);
+    }
+
+    #[test]
+    fn invalid_queries_are_rejected() {
+        for query in [
+            "host=",
+            "host=a.com&min_links=2",
+            "url=",
+            "url=https://a.com/&host=a.com",
+            "url=https://a.com/&min_links=2",
+            "min_links=0",
+            "page=0",
+            "limit=ten",
+            "format=xlsx",
+        ] {
+            assert!(DestinationReportQuery::parse(query).is_err(), "{query:?}");
+        }
+        let too_many: Vec<String> = (0..=MAX_REPORT_HOSTS)
+            .map(|n| format!("host=h{n}.com"))
+            .collect();
+        assert!(DestinationReportQuery::parse(&too_many.join("&")).is_err());
+    }
+
+    #[test]
+    fn csv_rows_match_their_header() {
+        let report = DestinationReport {
+            hosts: Vec::new(),
+            normalized_url: None,
+            links: Some(vec![DestinationHostLink {
+                dest_host: "a.com".to_string(),
+                short_code: "docs".to_string(),
+                original_url: "https://a.com/x?a=1,2".to_string(),
+                created_by: Some("alice".to_string()),
+                owner_email: None,
+                clicks: 7,
+                created_at: 1_698_768_000_500,
+            }]),
+            min_links: None,
+            limit: 50,
+            page: 1,
+        };
+        assert_eq!(
+            report_csv(&report),
+            format!(
+                "{LINKS_CSV_HEADER}a.com,docs,\"https://a.com/x?a=1,2\",alice,,7,1698768000500,2023-10-31T16:00:00Z\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Code reservation: `POST /api/links/reserve` claims short codes before their
+//! destinations are known, e.g. a campaign's `spring24-a` through `spring24-z`.
+//!
+//! Reserved codes belong to the caller and show up in listings with
+//! `reserved_until` set, but redirect as if they did not exist. Setting a
+//! destination through `PATCH /api/urls/{code}` ends the reservation; codes
+//! still reserved at `reserved_until` are deactivated by the expiry sweep.
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use super::handlers::{validated_short_code_max_length, ApiError, AppState, ShortenedUrlResponse};
+use super::public_url::PublicBaseUrl;
+use crate::auth::AuthClaims;
+use crate::storage::StorageError;
+
+/// Largest number of codes one request may reserve.
+pub const MAX_RESERVED_CODES: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ReserveCodesRequest {
+    /// Individual codes to reserve
+    #[serde(default)]
+    pub codes: Vec<String>,
+    /// A block of codes sharing a prefix
+    pub block: Option<CodeBlock>,
+}
+
+/// `prefix` followed by every value from `from` to `to`, inclusive. Both ends
+/// are either single letters of the same case (`a`..`z`) or numbers (`1`..`20`);
+/// numbers are zero-padded to the width of `from` (`01`..`20`).
+#[derive(Debug, Deserialize)]
+pub struct CodeBlock {
+    #[serde(default)]
+    pub prefix: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct ReserveCodesResponse {
+    pub reserved: Vec<ShortenedUrlResponse>,
+    /// When the reservations lapse unless a destination is set, in
+    /// milliseconds since the Unix epoch like the links' own `reserved_until`
+    pub reserved_until: i64,
+}
+
+impl CodeBlock {
+    /// Expand the block into codes, or `None` if the range is not valid.
+    fn expand(&self) -> Option<Vec<String>> {
+        if let (Ok(from), Ok(to)) = (self.from.parse::<u64>(), self.to.parse::<u64>()) {
+            if from > to || to - from >= MAX_RESERVED_CODES as u64 {
+                return None;
+            }
+            let width = self.from.len();
+            return Some(
+                (from..=to)
+                    .map(|n| format!("{}{:0width$}", self.prefix, n))
+                    .collect(),
+            );
+        }
+
+        let (from, to) = (single_char(&self.from)?, single_char(&self.to)?);
+        let same_kind = (from.is_ascii_lowercase() && to.is_ascii_lowercase())
+            || (from.is_ascii_uppercase() && to.is_ascii_uppercase());
+        if !same_kind || from > to {
+            return None;
+        }
+        Some(
+            (from..=to)
+                .map(|c| format!("{}{}", self.prefix, c))
+                .collect(),
+        )
+    }
+}
+
+fn single_char(value: &str) -> Option<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Reserve short codes for the caller without destinations
+pub async fn reserve_codes(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Json(payload): Json<ReserveCodesRequest>,
+) -> Result<(StatusCode, Json<ReserveCodesResponse>), ApiError> {
+    let ReserveCodesRequest { codes, block } = payload;
+
+    // Deduplicate; codes are reserved in sorted order.
+    let mut requested: BTreeSet<String> = codes.into_iter().collect();
+    if let Some(block) = block {
+        let expanded = block.expand().ok_or_else(|| {
+            ApiError::BadRequest(
+                "Block range must run from a lower to a higher letter or number".to_string(),
+            )
+        })?;
+        requested.extend(expanded);
+    }
+
+    if requested.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Provide codes or a block to reserve".to_string(),
+        ));
+    }
+    if requested.len() > MAX_RESERVED_CODES {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} codes can be reserved at once",
+            MAX_RESERVED_CODES
+        )));
+    }
+
+    let max_short_code_length = validated_short_code_max_length(state.config.short_code_max_length);
+    if let Some(code) = requested
+        .iter()
+        .find(|code| code.is_empty() || code.len() > max_short_code_length)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Code '{}' must be 1-{} characters",
+            code, max_short_code_length
+        )));
+    }
+
+    let reserved_until =
+        state.clock.now_epoch_secs() + state.config.reservations.ttl_days.max(1) * 86400;
+    let created_by = claims.as_ref().and_then(|c| c.user_id());
+    let auth_method = claims.as_ref().and_then(|c| c.auth_method());
+    let codes: Vec<String> = requested.into_iter().collect();
+
+    match state
+        .storage
+        .reserve_codes(
+            &codes,
+            created_by.as_deref(),
+            auth_method.as_deref(),
+            reserved_until,
+        )
+        .await
+    {
+        Ok(reserved) => {
+            let base = Some(public_base.as_str());
+            Ok((
+                StatusCode::CREATED,
+                Json(ReserveCodesResponse {
+                    reserved: reserved
+                        .into_iter()
+                        .map(|url| ShortenedUrlResponse::with_base(url, base))
+                        .collect(),
+                    reserved_until: reserved_until.saturating_mul(1000),
+                }),
+            ))
+        }
+        Err(StorageError::Conflict) => Err(ApiError::Conflict(
+            "One or more short codes already exist".to_string(),
+        )),
+        Err(e) => Err(ApiError::storage("Failed to reserve codes", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(prefix: &str, from: &str, to: &str) -> CodeBlock {
+        CodeBlock {
+            prefix: prefix.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn letter_blocks_expand_inclusively() {
+        let codes = block("spring24-", "a", "z").expand().unwrap();
+        assert_eq!(codes.len(), 26);
+        assert_eq!(codes.first().unwrap(), "spring24-a");
+        assert_eq!(codes.last().unwrap(), "spring24-z");
+    }
+
+    #[test]
+    fn number_blocks_keep_the_width_of_from() {
+        assert_eq!(
+            block("q", "08", "11").expand().unwrap(),
+            vec!["q08", "q09", "q10", "q11"]
+        );
+        assert_eq!(block("q", "9", "10").expand().unwrap(), vec!["q9", "q10"]);
+    }
+
+    #[test]
+    fn invalid_blocks_are_rejected() {
+        assert!(block("x", "z", "a").expand().is_none());
+        assert!(block("x", "a", "Z").expand().is_none());
+        assert!(block("x", "ab", "az").expand().is_none());
+        assert!(block("x", "1", "100000").expand().is_none());
+    }
+}
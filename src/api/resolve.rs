@@ -0,0 +1,72 @@
+//! Batch lookup: `POST /api/links/resolve` returns many links in one call, for
+//! tools that render pages of short links and would otherwise fetch each one.
+//!
+//! Every requested code appears in the response, mapped to its link or to
+//! `null`. Non-admins only see links they created; anyone else's come back as
+//! `null`, exactly like codes that do not exist.
+
+use axum::{extract::State, Extension, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use super::handlers::{is_user_admin, ApiError, AppState, ShortenedUrlResponse};
+use super::public_url::PublicBaseUrl;
+use crate::auth::AuthClaims;
+
+/// Largest number of codes one request may resolve.
+pub const MAX_RESOLVE_CODES: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveLinksRequest {
+    pub codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ResolveLinksResponse {
+    /// Each requested code, mapped to its link or `null`
+    pub links: BTreeMap<String, Option<ShortenedUrlResponse>>,
+}
+
+/// Resolve up to [`MAX_RESOLVE_CODES`] short codes in one call
+pub async fn resolve_links(
+    State(state): State<Arc<AppState>>,
+    public_base: PublicBaseUrl,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Json(payload): Json<ResolveLinksRequest>,
+) -> Result<Json<ResolveLinksResponse>, ApiError> {
+    let mut links: BTreeMap<String, Option<ShortenedUrlResponse>> =
+        payload.codes.into_iter().map(|code| (code, None)).collect();
+    if links.len() > MAX_RESOLVE_CODES {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} codes can be resolved at once",
+            MAX_RESOLVE_CODES
+        )));
+    }
+    if links.is_empty() {
+        return Ok(Json(ResolveLinksResponse { links }));
+    }
+
+    let codes: Vec<String> = links.keys().cloned().collect();
+    let found = state
+        .storage
+        .get_many(&codes)
+        .await
+        .map_err(|e| ApiError::storage("Failed to resolve links", e))?;
+
+    let is_admin = is_user_admin(state.storage.as_ref(), &claims).await;
+    let caller = claims.as_ref().and_then(|c| c.user_id());
+    let base = Some(public_base.as_str());
+    let visible: HashMap<String, _> = found
+        .into_iter()
+        .filter(|url| is_admin || (caller.is_some() && url.created_by == caller))
+        .map(|url| (url.short_code.clone(), url))
+        .collect();
+    for (code, link) in links.iter_mut() {
+        *link = visible
+            .get(code)
+            .map(|url| ShortenedUrlResponse::with_base(Arc::clone(url), base));
+    }
+
+    Ok(Json(ResolveLinksResponse { links }))
+}
@@ -1,32 +1,191 @@
 use axum::{
-    middleware,
-    routing::{get, patch, post, put},
+    extract::Request,
+    middleware::{self, Next},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::auth::last_seen::{LastSeenTracker, LAST_SEEN_INTERVAL};
+use crate::auth::usage::ApiUsageTracker;
 use crate::auth::{auth_middleware, AuthService};
+use crate::challenge::{self, CreationChallenge};
+use crate::clock::system_clock;
 use crate::config::Config;
+use crate::redirect::{LiveVisits, RedirectStats};
 use crate::storage::Storage;
+use crate::title::TitleFetcher;
 
-use super::analytics::{get_analytics, get_analytics_aggregate, AnalyticsState};
+use super::aliases::{add_alias, remove_alias};
+use super::analytics::{
+    get_analytics, get_analytics_aggregate, set_analytics_enabled, AnalyticsState,
+};
+use super::analytics_export::{export_link_analytics, export_my_analytics};
+use super::campaigns::{
+    create_campaign, delete_campaign, get_campaign, get_campaign_stats, list_campaigns,
+    rename_campaign,
+};
+use super::challenge::issue_challenge;
+use super::click_history::get_click_history;
+use super::code_rng::CodeRng;
 use super::handlers::{
     create_url, deactivate_url, get_auth_mode, get_url, get_url_history, get_user_info,
     health_check, list_urls, reactivate_url, restore_url, search_urls, update_url, AppState,
 };
-use super::static_files::serve_static;
+use super::hard_delete::hard_delete_link;
+use super::live::stream_live_visits;
+use super::moderation::{approve_link, create_anonymous_url, list_moderation, reject_link};
+use super::profile::{get_my_profile, get_user_profile, get_user_usage, list_users};
+use super::quick::{quick_create, QuickRateLimiter};
+use super::rename::rename_url;
+use super::reports::{activity_report, destination_report};
+use super::reservations::reserve_codes;
+use super::resolve::resolve_links;
+use super::server_info::{get_server_info, RuntimeFacts, ServerInfo};
+use super::slack::slack_command;
+use super::static_files::{api_index, serve_static};
+use super::stats::{
+    cleanup_orphan_analytics, get_analytics_queue_stats, get_cache_stats, get_ip_version_stats,
+    get_orphan_stats, get_pool_stats, get_redirect_stats, get_stats_history,
+};
+use super::timeout::{with_timeout, RequestTimeouts, RouteClass};
 
 pub fn create_api_router(
     storage: Arc<dyn Storage>,
     auth_service: Arc<AuthService>,
     config: Arc<Config>,
     analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+) -> Router {
+    create_api_router_with_redirect_stats(storage, auth_service, config, analytics_aggregator, None)
+}
+
+/// Create the API router, exposing `redirect_stats` through the admin stats endpoint.
+pub fn create_api_router_with_redirect_stats(
+    storage: Arc<dyn Storage>,
+    auth_service: Arc<AuthService>,
+    config: Arc<Config>,
+    analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+    redirect_stats: Option<Arc<RedirectStats>>,
+) -> Router {
+    create_api_router_with_live_visits(
+        storage,
+        auth_service,
+        config,
+        analytics_aggregator,
+        redirect_stats,
+        None,
+    )
+}
+
+/// Create the API router, serving the live visit stream from `live_visits` when provided.
+pub fn create_api_router_with_live_visits(
+    storage: Arc<dyn Storage>,
+    auth_service: Arc<AuthService>,
+    config: Arc<Config>,
+    analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+    redirect_stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+) -> Router {
+    let server_info = Arc::new(ServerInfo::new(&config, &RuntimeFacts::default()));
+    create_api_router_with_server_info(
+        storage,
+        auth_service,
+        config,
+        analytics_aggregator,
+        redirect_stats,
+        live_visits,
+        server_info,
+    )
+}
+
+/// Create the API router, reporting `server_info` at `GET /api/admin/info`.
+pub fn create_api_router_with_server_info(
+    storage: Arc<dyn Storage>,
+    auth_service: Arc<AuthService>,
+    config: Arc<Config>,
+    analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+    redirect_stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    server_info: Arc<ServerInfo>,
+) -> Router {
+    let creation_challenge = challenge::from_config(
+        &config.creation_challenge,
+        Arc::clone(&storage),
+        system_clock(),
+    );
+    create_api_router_with_creation_challenge(
+        storage,
+        auth_service,
+        config,
+        analytics_aggregator,
+        redirect_stats,
+        live_visits,
+        server_info,
+        creation_challenge,
+    )
+}
+
+/// Create the API router, verifying link creation with `creation_challenge`
+/// on the endpoints `CREATION_CHALLENGE_ENDPOINTS` lists.
+#[allow(clippy::too_many_arguments)]
+pub fn create_api_router_with_creation_challenge(
+    storage: Arc<dyn Storage>,
+    auth_service: Arc<AuthService>,
+    config: Arc<Config>,
+    analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+    redirect_stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    server_info: Arc<ServerInfo>,
+    creation_challenge: Option<Arc<dyn CreationChallenge>>,
+) -> Router {
+    create_api_router_with_usage(
+        storage,
+        auth_service,
+        config,
+        analytics_aggregator,
+        redirect_stats,
+        live_visits,
+        server_info,
+        creation_challenge,
+        None,
+    )
+}
+
+/// Create the API router, counting every authenticated request in `usage`
+/// for `GET /api/admin/users/{user_id}/usage`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_api_router_with_usage(
+    storage: Arc<dyn Storage>,
+    auth_service: Arc<AuthService>,
+    config: Arc<Config>,
+    analytics_aggregator: Option<Arc<crate::analytics::AnalyticsAggregator>>,
+    redirect_stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    server_info: Arc<ServerInfo>,
+    creation_challenge: Option<Arc<dyn CreationChallenge>>,
+    usage: Option<Arc<ApiUsageTracker>>,
 ) -> Router {
     let frontend_config = config.frontend.clone();
+    let analytics_max_limit = config.pagination.analytics_max_limit;
+    let hide_stats_by_default = config.stats_privacy.hide_by_default;
+    let title_fetcher = TitleFetcher::from_config(&config.title_fetch);
+    let timeouts = RequestTimeouts::from_config(&config.request_timeout);
     let state = Arc::new(AppState {
         storage: Arc::clone(&storage),
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
         config,
+        redirect_stats,
+        live_visits,
+        title_fetcher,
+        server_info,
+        creation_challenge,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: analytics_aggregator.clone(),
+        usage: usage.clone(),
     });
 
     // Configure CORS
@@ -35,11 +194,18 @@ pub fn create_api_router(
         .allow_methods(Any)
         .allow_headers(Any);
 
+    let last_seen = Arc::new(LastSeenTracker::new(
+        Arc::clone(&storage),
+        LAST_SEEN_INTERVAL,
+    ));
     let auth_service_clone1 = Arc::clone(&auth_service);
+    let last_seen_clone1 = Arc::clone(&last_seen);
+    let usage_clone1 = usage.clone();
     let protected_routes = Router::new()
         .route("/urls", post(create_url))
         .route("/urls", get(list_urls))
         .route("/urls/search", get(search_urls))
+        .route("/quick", get(quick_create))
         .route("/urls/{code}", get(get_url))
         .route("/urls/{code}", patch(update_url))
         .route("/urls/{code}/deactivate", put(deactivate_url))
@@ -49,10 +215,44 @@ pub fn create_api_router(
             "/urls/{code}/history/{history_id}/restore",
             post(restore_url),
         )
+        .route("/links/reserve", post(reserve_codes))
+        .route("/links/resolve", post(resolve_links))
+        .route("/links/{code}/rename", post(rename_url))
+        .route("/links/{code}/aliases", post(add_alias))
+        .route("/links/{code}/aliases/{alias}", delete(remove_alias))
+        .route("/links/{code}/analytics/live", get(stream_live_visits))
+        .route("/links/{code}/clicks/history", get(get_click_history))
+        .route("/campaigns", post(create_campaign))
+        .route("/campaigns", get(list_campaigns))
+        .route("/campaigns/{id}", get(get_campaign))
+        .route("/campaigns/{id}", patch(rename_campaign))
+        .route("/campaigns/{id}", delete(delete_campaign))
+        .route("/campaigns/{id}/stats", get(get_campaign_stats))
         .route("/user/info", get(get_user_info))
+        .route("/me", get(get_my_profile))
+        .route("/stats/redirects", get(get_redirect_stats))
+        .route("/stats/pool", get(get_pool_stats))
+        .route("/stats/cache", get(get_cache_stats))
+        .route("/stats/analytics", get(get_analytics_queue_stats))
+        .route("/stats/orphans", get(get_orphan_stats))
+        .route("/stats/orphans/cleanup", post(cleanup_orphan_analytics))
+        .route("/admin/info", get(get_server_info))
+        .route("/admin/links/{code}", delete(hard_delete_link))
+        .route("/admin/stats/history", get(get_stats_history))
+        .route("/admin/stats/ip-versions", get(get_ip_version_stats))
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/{user_id}", get(get_user_profile))
+        .route("/admin/users/{user_id}/usage", get(get_user_usage))
+        .route("/admin/reports/destinations", get(destination_report))
+        .route("/admin/reports/activity", get(activity_report))
+        .route("/moderation/links", get(list_moderation))
+        .route("/moderation/links/{code}/approve", post(approve_link))
+        .route("/moderation/links/{code}/reject", post(reject_link))
         .route_layer(middleware::from_fn(move |headers, req, next| {
             let auth = Arc::clone(&auth_service_clone1);
-            auth_middleware(auth, headers, req, next)
+            let last_seen = Arc::clone(&last_seen_clone1);
+            let usage = usage_clone1.clone();
+            auth_middleware(auth, last_seen, usage, headers, req, next)
         }))
         .with_state(Arc::clone(&state));
 
@@ -60,28 +260,68 @@ pub fn create_api_router(
     let analytics_state = Arc::new(AnalyticsState {
         storage: Arc::clone(&storage),
         aggregator: analytics_aggregator,
+        max_limit: analytics_max_limit,
+        hide_stats_by_default,
     });
     let auth_service_clone2 = Arc::clone(&auth_service);
+    let last_seen_clone2 = Arc::clone(&last_seen);
+    let usage_clone2 = usage.clone();
     let analytics_routes = Router::new()
         .route("/analytics/{code}", get(get_analytics))
         .route("/analytics/{code}/aggregate", get(get_analytics_aggregate))
+        .route("/admin/analytics/enabled", post(set_analytics_enabled))
         .route_layer(middleware::from_fn(move |headers, req, next| {
             let auth = Arc::clone(&auth_service_clone2);
-            auth_middleware(auth, headers, req, next)
+            let last_seen = Arc::clone(&last_seen_clone2);
+            let usage = usage_clone2.clone();
+            auth_middleware(auth, last_seen, usage, headers, req, next)
         }))
         .with_state(analytics_state);
 
+    // Analytics exports (also protected), gzipped when the client accepts it
+    let auth_service_clone3 = Arc::clone(&auth_service);
+    let last_seen_clone3 = Arc::clone(&last_seen);
+    let usage_clone3 = usage.clone();
+    let export_routes = Router::new()
+        .route("/links/{code}/analytics/export", get(export_link_analytics))
+        .route("/me/analytics/export", get(export_my_analytics))
+        .route_layer(middleware::from_fn(move |headers, req, next| {
+            let auth = Arc::clone(&auth_service_clone3);
+            let last_seen = Arc::clone(&last_seen_clone3);
+            let usage = usage_clone3.clone();
+            auth_middleware(auth, last_seen, usage, headers, req, next)
+        }))
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(move |req: Request, next: Next| {
+            with_timeout(timeouts.budget(RouteClass::Export), req, next)
+        }))
+        .with_state(Arc::clone(&state));
+
     let api_routes = Router::new()
         .route("/health", get(health_check))
         .route("/auth/mode", get(get_auth_mode))
+        // Authenticated by Slack's request signature instead of the auth middleware
+        .route("/integrations/slack", post(slack_command))
+        // Unauthenticated; answers 404 unless ALLOW_ANONYMOUS_CREATE is set
+        .route("/public/urls", post(create_anonymous_url))
+        .route("/public/challenge", get(issue_challenge))
         .merge(protected_routes)
         .merge(analytics_routes)
+        // Exports carry their own, longer budget
+        .layer(middleware::from_fn(move |req: Request, next: Next| {
+            let budget = timeouts.budget(RouteClass::of_method(req.method()));
+            with_timeout(budget, req, next)
+        }))
+        .merge(export_routes)
         .with_state(Arc::clone(&state))
         .layer(cors);
 
+    let router = Router::new().nest("/api", api_routes);
+    if !frontend_config.enabled {
+        return router.route("/", get(api_index));
+    }
+
     // Add frontend static file serving
     let static_dir = frontend_config.static_dir.clone();
-    Router::new()
-        .nest("/api", api_routes)
-        .fallback(move |uri| serve_static(uri, static_dir.clone()))
+    router.fallback(move |uri| serve_static(uri, static_dir.clone()))
 }
@@ -0,0 +1,214 @@
+//! Effective configuration summary for support: logged once at startup and
+//! served to admins at `GET /api/admin/info`.
+//!
+//! Built from the validated [`Config`] plus facts only known once the server
+//! is running, such as the build dates of the loaded GeoIP databases.
+//! Secrets never appear: the database URL is masked and other secrets are
+//! reported only as set or unset.
+
+use axum::{extract::State, Extension, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::handlers::{is_user_admin, ApiError, AppState};
+use super::static_files::has_embedded_frontend;
+use crate::auth::AuthClaims;
+use crate::config::{
+    redact_url, AuthMode, CacheEvictionPolicy, ChallengeProvider, Config, DatabaseBackend,
+    TrustedProxyMode,
+};
+
+/// Facts detected while starting the server rather than read from config.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeFacts {
+    /// Whether `ENABLE_TIMING_HEADERS` turned on redirect timing headers
+    pub timing_headers: bool,
+    /// Build time of the loaded GeoIP City database (Unix timestamp)
+    pub geoip_city_build_epoch: Option<u64>,
+    /// Build time of the loaded GeoIP ASN database (Unix timestamp)
+    pub geoip_asn_build_epoch: Option<u64>,
+    /// Whether an admin had switched analytics off before the restart
+    pub analytics_paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub version: &'static str,
+    pub database: DatabaseInfo,
+    pub auth_mode: AuthMode,
+    pub analytics: AnalyticsInfo,
+    pub cache: CacheInfo,
+    pub redirects: RedirectInfo,
+    /// Links a user may create per minute through `GET /api/quick`
+    pub quick_link_rate_limit_per_minute: u32,
+    /// Captcha or proof of work demanded before creating links
+    pub creation_challenge: ChallengeProvider,
+    pub features: FeatureFlags,
+    pub frontend: FrontendSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseInfo {
+    pub backend: DatabaseBackend,
+    /// Connection string with its credentials masked
+    pub url: String,
+    pub max_connections: u32,
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsInfo {
+    pub enabled: bool,
+    /// Whether visits are being recorded now; `false` while an admin has
+    /// switched analytics off at runtime
+    pub recording: bool,
+    pub ip_anonymization: bool,
+    pub trusted_proxy_mode: TrustedProxyMode,
+    pub flush_interval_secs: u64,
+    pub geoip_city_build_epoch: Option<u64>,
+    pub geoip_asn_build_epoch: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheInfo {
+    pub max_entries: u64,
+    /// Byte budget of the read cache, which then replaces `max_entries`
+    pub max_bytes: Option<u64>,
+    pub negative_max_entries: Option<u64>,
+    pub eviction_policy: CacheEvictionPolicy,
+    pub stale_max_age_secs: Option<u64>,
+    pub entry_ttl_secs: Option<u64>,
+    pub flush_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedirectInfo {
+    pub status: u16,
+    pub timing_headers: bool,
+}
+
+/// Optional features, each on or off.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureFlags {
+    pub redirect_stats: bool,
+    pub live_visits: bool,
+    pub title_fetch: bool,
+    pub code_normalization: bool,
+    pub slack_commands: bool,
+    pub alert_webhook: bool,
+    /// Whether `CURSOR_HMAC_SECRET` is set, so cursors survive restarts
+    pub stable_cursor_key: bool,
+    /// Whether `ALLOW_ANONYMOUS_CREATE` opened `POST /api/public/urls`
+    pub anonymous_create: bool,
+    /// Whether `LINK_INFO_ENABLED` serves `GET /{code}/info.json` on the
+    /// redirect server
+    pub link_info: bool,
+    /// Whether `ALLOW_HARD_DELETE` lets admins delete links permanently
+    pub hard_delete: bool,
+}
+
+/// Where the web UI is served from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontendSource {
+    /// `FRONTEND_STATIC_DIR`, falling back to embedded assets per file
+    Static,
+    /// Assets compiled into the binary
+    Embedded,
+    /// Built without frontend assets and no static directory configured
+    None,
+    /// `FRONTEND_ENABLED=false`: the API server mounts no UI at all
+    Disabled,
+}
+
+impl ServerInfo {
+    pub fn new(config: &Config, facts: &RuntimeFacts) -> Self {
+        let frontend = if !config.frontend.enabled {
+            FrontendSource::Disabled
+        } else if config.frontend.static_dir.is_some() {
+            FrontendSource::Static
+        } else if has_embedded_frontend() {
+            FrontendSource::Embedded
+        } else {
+            FrontendSource::None
+        };
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            database: DatabaseInfo {
+                backend: config.database.backend.clone(),
+                url: redact_url(&config.database.url),
+                max_connections: config.database.max_connections,
+                schema: config.database.schema.clone(),
+            },
+            auth_mode: config.auth.mode.clone(),
+            analytics: AnalyticsInfo {
+                enabled: config.analytics.enabled,
+                recording: config.analytics.enabled && !facts.analytics_paused,
+                ip_anonymization: config.analytics.ip_anonymization,
+                trusted_proxy_mode: config.analytics.trusted_proxy_mode.clone(),
+                flush_interval_secs: config.analytics.flush_interval_secs,
+                geoip_city_build_epoch: facts.geoip_city_build_epoch,
+                geoip_asn_build_epoch: facts.geoip_asn_build_epoch,
+            },
+            cache: CacheInfo {
+                max_entries: config.cache.max_entries,
+                max_bytes: config.cache.max_bytes,
+                negative_max_entries: config.cache.negative_max_entries,
+                eviction_policy: config.cache.eviction_policy,
+                stale_max_age_secs: config.cache.stale_max_age_secs,
+                entry_ttl_secs: config.cache.entry_ttl_secs,
+                flush_interval_secs: config.cache.flush_interval_secs,
+            },
+            redirects: RedirectInfo {
+                status: axum::http::StatusCode::from(config.redirect_status).as_u16(),
+                timing_headers: facts.timing_headers,
+            },
+            quick_link_rate_limit_per_minute: config.quick_link.rate_limit_per_minute,
+            creation_challenge: config.creation_challenge.provider,
+            features: FeatureFlags {
+                redirect_stats: config.redirect_stats.enabled,
+                live_visits: config.live_visits.enabled,
+                title_fetch: config.title_fetch.enabled,
+                code_normalization: config.code_normalization.enabled,
+                slack_commands: config.slack.is_some(),
+                alert_webhook: config.alerts.webhook_url.is_some(),
+                stable_cursor_key: config.pagination.cursor_hmac_secret.is_some(),
+                anonymous_create: config.anonymous_create.enabled,
+                link_info: config.link_info.enabled,
+                hard_delete: config.database.allow_hard_delete,
+            },
+            frontend,
+        }
+    }
+
+    /// Log the summary as one structured event so it can be copied from the
+    /// first lines of a report.
+    pub fn log(&self) {
+        match serde_json::to_string(self) {
+            Ok(summary) => tracing::info!(
+                version = self.version,
+                summary = %summary,
+                "startup summary"
+            ),
+            Err(error) => tracing::warn!(%error, "failed to serialize startup summary"),
+        }
+    }
+}
+
+/// Get the effective configuration summary (admin only)
+pub async fn get_server_info(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<ServerInfo>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Server information is restricted to admins".to_string(),
+        ));
+    }
+    let mut info = state.server_info.as_ref().clone();
+    if let Some(aggregator) = &state.analytics {
+        info.analytics.recording = aggregator.is_enabled();
+    }
+    Ok(Json(info))
+}
@@ -0,0 +1,374 @@
+//! Slack slash command integration: `/shorten https://...` in Slack.
+//!
+//! Slack signs every request with the app's signing secret, so this endpoint
+//! sits outside the auth middleware and trusts the signature instead. Requests
+//! whose timestamp is more than [`MAX_TIMESTAMP_SKEW_SECS`] away from now are
+//! rejected, which stops a captured request from being replayed later.
+//!
+//! User errors (bad URL, unlinked account) are answered with 200 and an
+//! ephemeral message, because Slack shows any other status as a generic
+//! failure.
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use thiserror::Error;
+
+use super::handlers::{
+    create_with_random_code, fill_title, validated_destination, validated_short_code_max_length,
+    ApiError, AppState, ShortenedUrlResponse,
+};
+use super::quota::check_link_quota;
+use crate::config::SlackConfig;
+use crate::models::{CreatedVia, LinkOptions};
+use crate::storage::StorageError;
+
+/// Largest accepted difference between Slack's timestamp and the local clock.
+pub const MAX_TIMESTAMP_SKEW_SECS: i64 = 5 * 60;
+
+const SIGNATURE_HEADER: &str = "x-slack-signature";
+const TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+const SIGNATURE_VERSION: &str = "v0";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SlackRequestError {
+    #[error("missing Slack signature headers")]
+    MissingHeaders,
+    #[error("invalid Slack request timestamp")]
+    InvalidTimestamp,
+    #[error("Slack request timestamp is too old")]
+    StaleTimestamp,
+    #[error("Slack signature mismatch")]
+    BadSignature,
+}
+
+impl IntoResponse for SlackRequestError {
+    fn into_response(self) -> Response {
+        (StatusCode::UNAUTHORIZED, self.to_string()).into_response()
+    }
+}
+
+/// Message returned to Slack; `ephemeral` messages are only shown to the
+/// user who ran the command.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct SlackMessage {
+    pub response_type: &'static str,
+    pub text: String,
+}
+
+impl SlackMessage {
+    fn ephemeral(text: impl Into<String>) -> Self {
+        Self {
+            response_type: "ephemeral",
+            text: text.into(),
+        }
+    }
+}
+
+/// The fields of a slash command payload used here.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SlashCommand {
+    pub user_id: String,
+    pub command: String,
+    pub text: String,
+}
+
+impl SlashCommand {
+    /// Parse the `application/x-www-form-urlencoded` body Slack posts.
+    pub fn parse(body: &[u8]) -> Self {
+        let mut command = Self::default();
+        for (key, value) in url::form_urlencoded::parse(body) {
+            match key.as_ref() {
+                "user_id" => command.user_id = value.into_owned(),
+                "command" => command.command = value.into_owned(),
+                "text" => command.text = value.into_owned(),
+                _ => {}
+            }
+        }
+        command
+    }
+}
+
+/// Check a request signature as described in Slack's "Verifying requests"
+/// guide: `v0=` followed by the hex HMAC-SHA256 of `v0:{timestamp}:{body}`.
+pub fn verify_signature(
+    signing_secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(), SlackRequestError> {
+    let timestamp = header_str(headers, TIMESTAMP_HEADER)?;
+    let signature = header_str(headers, SIGNATURE_HEADER)?;
+
+    let sent_at: i64 = timestamp
+        .parse()
+        .map_err(|_| SlackRequestError::InvalidTimestamp)?;
+    if now.abs_diff(sent_at) > MAX_TIMESTAMP_SKEW_SECS as u64 {
+        return Err(SlackRequestError::StaleTimestamp);
+    }
+
+    let expected = signature
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(|rest| rest.strip_prefix('='))
+        .and_then(decode_hex)
+        .ok_or(SlackRequestError::BadSignature)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())
+        .map_err(|_| SlackRequestError::BadSignature)?;
+    mac.update(SIGNATURE_VERSION.as_bytes());
+    mac.update(b":");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| SlackRequestError::BadSignature)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, SlackRequestError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(SlackRequestError::MissingHeaders)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Lynx user the command's links are attributed to.
+fn lynx_user<'a>(config: &'a SlackConfig, slack_user: &str) -> Option<&'a str> {
+    config
+        .user_map
+        .get(slack_user)
+        .or(config.service_user.as_ref())
+        .map(String::as_str)
+}
+
+/// Handle a Slack slash command
+pub async fn slack_command(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(slack) = state.config.slack.as_ref() else {
+        return ApiError::NotFound("Slack integration is not configured".to_string())
+            .into_response();
+    };
+
+    if let Err(error) = verify_signature(
+        &slack.signing_secret,
+        &headers,
+        &body,
+        state.clock.now_epoch_secs(),
+    ) {
+        tracing::warn!(%error, "Rejected Slack request");
+        return error.into_response();
+    }
+
+    let command = SlashCommand::parse(&body);
+    Json(shorten(&state, slack, &command).await).into_response()
+}
+
+async fn shorten(state: &AppState, slack: &SlackConfig, command: &SlashCommand) -> SlackMessage {
+    let url = command.text.trim();
+    if url.is_empty() || url.eq_ignore_ascii_case("help") {
+        return SlackMessage::ephemeral(format!(
+            "Usage: `{} https://example.com/long/link`",
+            command.command
+        ));
+    }
+
+    let Some(created_by) = lynx_user(slack, &command.user_id) else {
+        return SlackMessage::ephemeral(
+            "Your Slack account is not linked to a Lynx user. Ask an admin to add it to SLACK_USER_MAP.",
+        );
+    };
+
+    let url = match validated_destination(url, &state.config) {
+        Ok(url) => url,
+        Err(error) => {
+            return SlackMessage::ephemeral(format!("Cannot shorten that: {}", error.message()))
+        }
+    };
+
+    // Slash commands carry no admin claims, so the quota always applies.
+    let quota = match check_link_quota(state, Some(created_by), &None).await {
+        Ok(quota) => quota,
+        Err(error) => {
+            return SlackMessage::ephemeral(format!("Cannot shorten that: {}", error.message()))
+        }
+    };
+
+    match create_with_random_code(
+        state.storage.as_ref(),
+        &state.code_rng,
+        &url,
+        Some(created_by),
+        None,
+        CreatedVia::Integration,
+        &LinkOptions::default(),
+        validated_short_code_max_length(state.config.short_code_max_length),
+    )
+    .await
+    {
+        Ok(link) => {
+            fill_title(state, &link);
+            let mut text =
+                ShortenedUrlResponse::short_url(&state.config.short_url_base(), &link.short_code);
+            if let Some(warning) = quota.and_then(|usage| usage.warning_after_create()) {
+                text.push_str(&format!("\n{}", warning.message));
+            }
+            SlackMessage::ephemeral(text)
+        }
+        Err(StorageError::Conflict) => {
+            SlackMessage::ephemeral("Could not find a free short code, please try again.")
+        }
+        Err(error) => {
+            tracing::error!(%error, "Failed to create link from Slack");
+            SlackMessage::ephemeral("Lynx could not create the link, please try again later.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Example request from Slack's "Verifying requests from Slack" guide.
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: i64 = 1531420618;
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    fn headers(timestamp: &str, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, timestamp.parse().unwrap());
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn recorded_slack_request_verifies() {
+        let headers = headers(&TIMESTAMP.to_string(), SIGNATURE);
+        assert_eq!(
+            verify_signature(SECRET, &headers, BODY.as_bytes(), TIMESTAMP + 10),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn tampered_body_or_secret_is_rejected() {
+        let headers = headers(&TIMESTAMP.to_string(), SIGNATURE);
+        let tampered = BODY.replace("text=", "text=https%3A%2F%2Fevil.example");
+        assert_eq!(
+            verify_signature(SECRET, &headers, tampered.as_bytes(), TIMESTAMP),
+            Err(SlackRequestError::BadSignature)
+        );
+        assert_eq!(
+            verify_signature("other-secret", &headers, BODY.as_bytes(), TIMESTAMP),
+            Err(SlackRequestError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn replayed_request_is_rejected() {
+        let headers = headers(&TIMESTAMP.to_string(), SIGNATURE);
+        assert_eq!(
+            verify_signature(
+                SECRET,
+                &headers,
+                BODY.as_bytes(),
+                TIMESTAMP + MAX_TIMESTAMP_SKEW_SECS + 1
+            ),
+            Err(SlackRequestError::StaleTimestamp)
+        );
+    }
+
+    #[test]
+    fn extreme_timestamps_are_stale_rather_than_overflowing() {
+        for sent_at in [i64::MIN, i64::MAX] {
+            let headers = headers(&sent_at.to_string(), SIGNATURE);
+            assert_eq!(
+                verify_signature(SECRET, &headers, BODY.as_bytes(), TIMESTAMP),
+                Err(SlackRequestError::StaleTimestamp)
+            );
+        }
+        let headers = headers(&TIMESTAMP.to_string(), SIGNATURE);
+        assert_eq!(
+            verify_signature(SECRET, &headers, BODY.as_bytes(), i64::MIN),
+            Err(SlackRequestError::StaleTimestamp)
+        );
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        assert_eq!(
+            verify_signature(SECRET, &HeaderMap::new(), BODY.as_bytes(), TIMESTAMP),
+            Err(SlackRequestError::MissingHeaders)
+        );
+        assert_eq!(
+            verify_signature(
+                SECRET,
+                &headers("yesterday", SIGNATURE),
+                BODY.as_bytes(),
+                TIMESTAMP
+            ),
+            Err(SlackRequestError::InvalidTimestamp)
+        );
+        assert_eq!(
+            verify_signature(
+                SECRET,
+                &headers(&TIMESTAMP.to_string(), "v1=abcd"),
+                BODY.as_bytes(),
+                TIMESTAMP
+            ),
+            Err(SlackRequestError::BadSignature)
+        );
+        assert_eq!(
+            verify_signature(
+                SECRET,
+                &headers(&TIMESTAMP.to_string(), "v0=zz"),
+                BODY.as_bytes(),
+                TIMESTAMP
+            ),
+            Err(SlackRequestError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn slash_command_payload_is_parsed() {
+        let command = SlashCommand::parse(BODY.as_bytes());
+        assert_eq!(command.user_id, "U2CERLKJA");
+        assert_eq!(command.command, "/webhook-collect");
+        assert_eq!(command.text, "");
+    }
+
+    #[test]
+    fn mapped_users_take_precedence_over_the_service_user() {
+        let mut config = SlackConfig {
+            signing_secret: SECRET.to_string(),
+            user_map: [("U1".to_string(), "alice".to_string())].into(),
+            service_user: None,
+        };
+        assert_eq!(lynx_user(&config, "U1"), Some("alice"));
+        assert_eq!(lynx_user(&config, "U2"), None);
+
+        config.service_user = Some("slack-bot".to_string());
+        assert_eq!(lynx_user(&config, "U1"), Some("alice"));
+        assert_eq!(lynx_user(&config, "U2"), Some("slack-bot"));
+    }
+}
@@ -2,15 +2,50 @@ use axum::{
     body::Body,
     http::{header, StatusCode, Uri},
     response::Response,
+    Json,
 };
 use mime_guess::from_path;
+#[cfg(feature = "embedded-frontend")]
 use rust_embed::RustEmbed;
+use serde::Serialize;
 use std::path::PathBuf;
 
+#[cfg(feature = "embedded-frontend")]
 #[derive(RustEmbed)]
 #[folder = "frontend/dist"]
 pub struct Assets;
 
+/// Whether the binary carries a built web UI.
+#[cfg(feature = "embedded-frontend")]
+pub fn has_embedded_frontend() -> bool {
+    Assets::get("index.html").is_some()
+}
+
+/// Whether the binary carries a built web UI.
+#[cfg(not(feature = "embedded-frontend"))]
+pub fn has_embedded_frontend() -> bool {
+    false
+}
+
+/// What `GET /` answers on a headless API server (`FRONTEND_ENABLED=false`).
+#[derive(Debug, Serialize)]
+pub struct ApiIndex {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub api: &'static str,
+    pub health: &'static str,
+}
+
+/// Point clients at the API instead of the web UI.
+pub async fn api_index() -> Json<ApiIndex> {
+    Json(ApiIndex {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        api: "/api",
+        health: "/api/health",
+    })
+}
+
 /// Serve static files from embedded assets or filesystem
 pub async fn serve_static(uri: Uri, static_dir: Option<String>) -> Response {
     let path = uri.path().trim_start_matches('/');
@@ -33,6 +68,7 @@ pub async fn serve_static(uri: Uri, static_dir: Option<String>) -> Response {
 }
 
 /// Serve from embedded assets
+#[cfg(feature = "embedded-frontend")]
 async fn serve_embedded(path: &str) -> Response {
     let path = if path.is_empty() { "index.html" } else { path };
 
@@ -63,3 +99,12 @@ async fn serve_embedded(path: &str) -> Response {
         }
     }
 }
+
+/// Built without embedded assets: only `FRONTEND_STATIC_DIR` serves files
+#[cfg(not(feature = "embedded-frontend"))]
+async fn serve_embedded(_path: &str) -> Response {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("404 Not Found"))
+        .unwrap()
+}
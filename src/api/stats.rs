@@ -0,0 +1,269 @@
+//! Admin statistics handlers
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::analytics::is_aggregated;
+use super::handlers::{is_user_admin, ApiError, AppState};
+use super::limits::clamp_limit;
+use crate::analytics::aggregator::AnalyticsQueueStats;
+use crate::analytics::daily::{day_start, DAY_SECS};
+use crate::analytics::{AnalyticsAggregate, AnalyticsGroupBy};
+use crate::auth::AuthClaims;
+use crate::models::{daily_series, InstanceStatsPoint};
+use crate::redirect::stats::RedirectStatsSnapshot;
+use crate::storage::{CacheStats, OrphanCounts, PoolStats};
+
+/// Default number of missing codes returned by the redirect stats endpoint.
+const TOP_MISSING_DEFAULT_LIMIT: i64 = 20;
+
+/// Default and maximum number of days in the instance stats history.
+const STATS_HISTORY_DEFAULT_DAYS: i64 = 90;
+const STATS_HISTORY_MAX_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct RedirectStatsQuery {
+    /// Number of top missing codes to return (default 20, clamped to the tracker capacity)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedirectStatsResponse {
+    #[serde(flatten)]
+    pub stats: RedirectStatsSnapshot,
+    /// Effective number of top missing codes requested after clamping
+    pub limit: i64,
+}
+
+/// Get redirect outcome counters and the most requested missing codes (admin only)
+pub async fn get_redirect_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<RedirectStatsQuery>,
+) -> Result<Json<RedirectStatsResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Redirect statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    let Some(stats) = &state.redirect_stats else {
+        return Err(ApiError::NotFound(
+            "Redirect statistics are disabled; set REDIRECT_STATS_ENABLED=true".to_string(),
+        ));
+    };
+
+    let capacity = state.config.redirect_stats.top_missing_capacity;
+    let limit = clamp_limit(
+        query.limit,
+        TOP_MISSING_DEFAULT_LIMIT,
+        i64::try_from(capacity).unwrap_or(i64::MAX),
+    );
+
+    Ok(Json(RedirectStatsResponse {
+        stats: stats.snapshot(limit as usize),
+        limit,
+    }))
+}
+
+/// Get database connection pool health (admin only)
+pub async fn get_pool_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<PoolStats>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Pool statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    state
+        .storage
+        .pool_stats()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("Storage backend has no connection pool".to_string()))
+}
+
+/// Get read cache sizes per lookup outcome (admin only)
+pub async fn get_cache_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<CacheStats>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Cache statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    state
+        .storage
+        .cache_stats()
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("Storage has no read cache".to_string()))
+}
+
+/// Get the analytics event queue and whether events are being sampled (admin only)
+pub async fn get_analytics_queue_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<AnalyticsQueueStats>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Analytics statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    state
+        .analytics
+        .as_ref()
+        .map(|aggregator| Json(aggregator.queue_stats()))
+        .ok_or_else(|| {
+            ApiError::NotFound("Analytics are disabled; set ANALYTICS_ENABLED=true".to_string())
+        })
+}
+
+/// Count analytics and click history rows for codes that are not in `urls` (admin only)
+pub async fn get_orphan_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<OrphanCounts>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Orphan statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    state
+        .storage
+        .count_orphan_analytics()
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::storage("Failed to count orphaned analytics", e))
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanCleanupResponse {
+    pub deleted: OrphanCounts,
+}
+
+/// Delete analytics and click history rows for codes that are not in `urls` (admin only)
+pub async fn cleanup_orphan_analytics(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+) -> Result<Json<OrphanCleanupResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Orphan cleanup is restricted to admins".to_string(),
+        ));
+    }
+
+    let deleted = state
+        .storage
+        .delete_orphan_analytics()
+        .await
+        .map_err(|e| ApiError::storage("Failed to delete orphaned analytics", e))?;
+    tracing::info!(
+        analytics = deleted.analytics,
+        click_history = deleted.click_history,
+        "Deleted orphaned analytics rows"
+    );
+
+    Ok(Json(OrphanCleanupResponse { deleted }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    /// Number of days to return, ending yesterday (default 90, at most 366)
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsHistoryResponse {
+    /// Effective number of days after clamping
+    pub days: i64,
+    /// One point per UTC day, oldest first; unrecorded days have null counts
+    pub history: Vec<InstanceStatsPoint>,
+}
+
+/// Get daily instance totals for charting growth (admin only)
+pub async fn get_stats_history(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<StatsHistoryQuery>,
+) -> Result<Json<StatsHistoryResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Instance statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    let days = clamp_limit(
+        query.days,
+        STATS_HISTORY_DEFAULT_DAYS,
+        STATS_HISTORY_MAX_DAYS,
+    );
+    // Today is still running, so the history ends with yesterday
+    let until = day_start(state.clock.now_epoch_secs());
+    let since = until - days * DAY_SECS;
+    let recorded = state
+        .storage
+        .instance_stats_history(since)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load instance stats history", e))?;
+
+    Ok(Json(StatsHistoryResponse {
+        days,
+        history: daily_series(since, until, DAY_SECS, &recorded),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IpVersionStatsQuery {
+    /// Start time (Unix timestamp)
+    pub start_time: Option<i64>,
+    /// End time (Unix timestamp)
+    pub end_time: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IpVersionStatsResponse {
+    /// Visits per version (`IPv4`, `IPv6`, `unknown`), most first
+    pub aggregates: Vec<AnalyticsAggregate>,
+    pub total_visits: i64,
+}
+
+/// Get the IPv4/IPv6 split of visits to every link (admin only)
+pub async fn get_ip_version_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Option<AuthClaims>>,
+    Query(query): Query<IpVersionStatsQuery>,
+) -> Result<Json<IpVersionStatsResponse>, ApiError> {
+    if !is_user_admin(state.storage.as_ref(), &claims).await {
+        return Err(ApiError::Forbidden(
+            "Instance statistics are restricted to admins".to_string(),
+        ));
+    }
+
+    let mut aggregates = state
+        .storage
+        .get_instance_ip_version_split(query.start_time, query.end_time)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load the IP version split", e))?;
+    for aggregate in &mut aggregates {
+        aggregate.aggregated = is_aggregated(AnalyticsGroupBy::IpVersion, &aggregate.dimension);
+    }
+    let total_visits = aggregates
+        .iter()
+        .map(|aggregate| aggregate.visit_count)
+        .sum();
+
+    Ok(Json(IpVersionStatsResponse {
+        aggregates,
+        total_visits,
+    }))
+}
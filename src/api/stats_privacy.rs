@@ -0,0 +1,65 @@
+//! Click-count privacy.
+//!
+//! A link's `hide_stats` flag (or `HIDE_STATS_BY_DEFAULT` when it has none)
+//! keeps its clicks from viewers who neither own it nor are admins: link
+//! details come back with clicks cleared and `stats_hidden: true`, and the
+//! analytics endpoints answer `403`.
+
+use std::sync::Arc;
+
+use super::handlers::{is_user_admin, ApiError};
+use crate::auth::AuthClaims;
+use crate::models::ShortenedUrl;
+use crate::storage::Storage;
+
+/// Whether `url` hides its clicks from other viewers.
+pub fn hides_stats(url: &ShortenedUrl, hide_by_default: bool) -> bool {
+    url.options.hides_stats(hide_by_default)
+}
+
+/// Whether `claims` may see the clicks of `url`: always for its owner and
+/// admins, otherwise unless the link hides them.
+pub(crate) async fn can_see_stats(
+    storage: &dyn Storage,
+    claims: &Option<AuthClaims>,
+    url: &ShortenedUrl,
+    hide_by_default: bool,
+) -> bool {
+    if !hides_stats(url, hide_by_default) {
+        return true;
+    }
+    let caller = claims.as_ref().and_then(|c| c.user_id());
+    if caller.is_some() && caller == url.created_by {
+        return true;
+    }
+    is_user_admin(storage, claims).await
+}
+
+/// `url` without its click count and last visit.
+pub(crate) fn without_stats(url: &ShortenedUrl) -> Arc<ShortenedUrl> {
+    Arc::new(ShortenedUrl {
+        clicks: 0,
+        last_visited_at: None,
+        ..url.clone()
+    })
+}
+
+/// Refuse analytics of `short_code` to callers its clicks are hidden from.
+/// Unknown codes pass, as they have no analytics to show.
+pub(crate) async fn authorize_stats(
+    storage: &dyn Storage,
+    claims: &Option<AuthClaims>,
+    short_code: &str,
+    hide_by_default: bool,
+) -> Result<(), ApiError> {
+    let url = storage
+        .get_authoritative(short_code)
+        .await
+        .map_err(|e| ApiError::storage("Failed to load URL", e))?;
+    match url {
+        Some(url) if !can_see_stats(storage, claims, &url, hide_by_default).await => Err(
+            ApiError::Forbidden("The owner of this link keeps its statistics private".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,40 @@
+//! Shared handling of the `tz` query parameter.
+//!
+//! Day-grouped endpoints count days in the zone named by `tz` (an IANA name
+//! such as `Europe/Berlin`) and fall back to UTC when it is absent.
+
+use chrono_tz::Tz;
+
+use super::handlers::ApiError;
+use crate::timezone::parse_time_zone;
+
+/// Resolve an optional `tz` value, rejecting names chrono-tz does not know.
+pub fn time_zone_param(tz: Option<&str>) -> Result<Tz, ApiError> {
+    match tz {
+        None => Ok(Tz::UTC),
+        Some(name) => parse_time_zone(name)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown time zone: {}", name.trim()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_time_zone_is_utc() {
+        assert_eq!(time_zone_param(None).unwrap(), Tz::UTC);
+    }
+
+    #[test]
+    fn unknown_time_zone_is_rejected() {
+        assert!(matches!(
+            time_zone_param(Some("Nowhere/Special")),
+            Err(ApiError::BadRequest(_))
+        ));
+        assert_eq!(
+            time_zone_param(Some("Asia/Tokyo")).unwrap(),
+            Tz::Asia__Tokyo
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! Time budgets for API requests (`API_*_TIMEOUT_SECS`).
+//!
+//! A request still running when its budget runs out is answered with `504`
+//! and its handler future is dropped. Its SQLite statements are interrupted
+//! as well (see [`crate::storage::cancel`]), so a slow query gives up its
+//! pooled connection instead of holding it for a client that has gone.
+//! Budgets end once the response starts, so streamed bodies (exports, live
+//! visits) are not cut off.
+
+use axum::{
+    extract::Request,
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Duration;
+
+use super::handlers::ApiError;
+use crate::config::RequestTimeoutConfig;
+use crate::storage::{with_query_cancel, QueryCancel};
+
+/// Which budget a route gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteClass {
+    /// `GET` and `HEAD`
+    Read,
+    /// Everything that changes something
+    Write,
+    /// Analytics exports
+    Export,
+}
+
+impl RouteClass {
+    pub fn of_method(method: &Method) -> Self {
+        if method == Method::GET || method == Method::HEAD {
+            RouteClass::Read
+        } else {
+            RouteClass::Write
+        }
+    }
+}
+
+/// Per-class budgets; `None` is unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+    pub export: Option<Duration>,
+}
+
+impl RequestTimeouts {
+    pub fn from_config(config: &RequestTimeoutConfig) -> Self {
+        Self {
+            read: config.read_secs.map(Duration::from_secs),
+            write: config.write_secs.map(Duration::from_secs),
+            export: config.export_secs.map(Duration::from_secs),
+        }
+    }
+
+    pub fn budget(&self, class: RouteClass) -> Option<Duration> {
+        match class {
+            RouteClass::Read => self.read,
+            RouteClass::Write => self.write,
+            RouteClass::Export => self.export,
+        }
+    }
+}
+
+/// Run the rest of the stack within `budget`, answering `504` when it is
+/// exceeded. Use through `middleware::from_fn`.
+pub async fn with_timeout(budget: Option<Duration>, request: Request, next: Next) -> Response {
+    let Some(budget) = budget else {
+        return next.run(request).await;
+    };
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let cancel = QueryCancel::new();
+    let run = with_query_cancel(cancel.clone(), next.run(request));
+    match tokio::time::timeout(budget, run).await {
+        Ok(response) => response,
+        Err(_) => {
+            cancel.cancel();
+            tracing::warn!(
+                %method,
+                path,
+                budget_secs = budget.as_secs_f64(),
+                "API request timed out and was cancelled"
+            );
+            ApiError::Timeout(format!(
+                "Request did not complete within {} seconds",
+                budget.as_secs()
+            ))
+            .into_response()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::cancel::interruptible;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// Sets its flag when dropped, like a query future releasing its connection.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn get_request(path: &str) -> Request {
+        axum::http::Request::builder()
+            .uri(path)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn app(budget: Duration, dropped: Arc<AtomicBool>) -> Router {
+        Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let flag = DropFlag(Arc::clone(&dropped));
+                    async move {
+                        let _flag = flag;
+                        std::future::pending::<()>().await;
+                        "never"
+                    }
+                }),
+            )
+            .route("/fast", get(|| async { "done" }))
+            .layer(middleware::from_fn(move |request: Request, next: Next| {
+                with_timeout(Some(budget), request, next)
+            }))
+    }
+
+    #[tokio::test]
+    async fn slow_requests_get_504_and_their_handler_is_dropped() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let app = app(Duration::from_millis(20), Arc::clone(&dropped));
+
+        let response = app.clone().oneshot(get_request("/slow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["error"].as_str().unwrap().contains("did not complete"));
+        assert!(dropped.load(Ordering::SeqCst));
+
+        let response = app.oneshot(get_request("/fast")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Counts one row at a time; a hundred million rows keep SQLite busy
+    /// for tens of seconds.
+    const SLOW_QUERY: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?) SELECT count(*) FROM n";
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn timed_out_query_gives_its_connection_back() {
+        // Built the way `SqliteStorage` builds its pools
+        let pool = interruptible(sqlx::sqlite::SqlitePoolOptions::new().max_connections(1))
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route(
+                "/query",
+                get({
+                    let pool = pool.clone();
+                    move || async move {
+                        let rows: i64 = sqlx::query_scalar(SLOW_QUERY)
+                            .bind(100_000_000_i64)
+                            .fetch_one(&pool)
+                            .await
+                            .unwrap();
+                        rows.to_string()
+                    }
+                }),
+            )
+            .layer(middleware::from_fn(|request: Request, next: Next| {
+                with_timeout(Some(Duration::from_millis(100)), request, next)
+            }));
+
+        let response = app.oneshot(get_request("/query")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        // The only connection must be usable again long before counting
+        // to a hundred million could have finished
+        let mut conn = tokio::time::timeout(Duration::from_secs(1), pool.acquire())
+            .await
+            .expect("connection still held by the cancelled query")
+            .unwrap();
+        let one: i64 = sqlx::query_scalar("SELECT 1")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(one, 1);
+    }
+
+    #[test]
+    fn routes_are_classed_by_method() {
+        let timeouts = RequestTimeouts::from_config(&RequestTimeoutConfig {
+            read_secs: Some(1),
+            write_secs: None,
+            export_secs: Some(3),
+        });
+        assert_eq!(
+            timeouts.budget(RouteClass::of_method(&Method::GET)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(timeouts.budget(RouteClass::of_method(&Method::POST)), None);
+        assert_eq!(
+            timeouts.budget(RouteClass::Export),
+            Some(Duration::from_secs(3))
+        );
+    }
+}
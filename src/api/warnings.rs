@@ -0,0 +1,44 @@
+//! Non-fatal notices returned alongside a successful response.
+//!
+//! A request that succeeded can still have something worth telling the
+//! caller: [`Warning`] is the one shape for that, listed in a `warnings`
+//! array so clients can show them without special-casing each endpoint.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// What a [`Warning`] is about, for clients that react to specific notices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// The owner is close to their link quota
+    QuotaNearlyReached,
+    /// The hash code of the request belongs to another link, so the new
+    /// link got a random code
+    HashCodeCollision,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    /// Human-readable explanation
+    pub message: String,
+    /// Structured data about the notice, shaped per code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context, Result};
-use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, DecodingKey};
 use reqwest::Client;
 use serde::Deserialize;
 use serde_json::Value;
@@ -14,6 +14,11 @@ use tracing::{debug, warn};
 
 use crate::config::CloudflareConfig;
 
+use super::validation::{check_issued_at, token_validation};
+
+/// Longest a certs fetch may take.
+const CERTS_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Cloudflare Zero Trust validator with stale-while-revalidate caching
 #[derive(Clone)]
 pub struct CloudflareValidator {
@@ -24,15 +29,13 @@ pub struct CloudflareValidator {
     keys: Arc<RwLock<HashMap<String, Arc<DecodingKey>>>>,
     last_refresh: Arc<RwLock<Option<Instant>>>,
     cache_ttl: Duration,
+    /// Seconds of clock skew tolerated on `exp`, `nbf` and `iat`
+    clock_skew_secs: u64,
 }
 
 impl CloudflareValidator {
     pub async fn from_config(config: &CloudflareConfig) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent("lynx-cloudflare-validator/0.1.0")
-            .timeout(Duration::from_secs(10))
-            .build()
-            .context("failed to build HTTP client for Cloudflare validation")?;
+        let client = crate::http::client();
 
         let team_domain = config.team_domain.trim_end_matches('/').to_string();
         let certs_uri = format!("{}/cdn-cgi/access/certs", team_domain);
@@ -45,6 +48,7 @@ impl CloudflareValidator {
             keys: Arc::new(RwLock::new(HashMap::new())),
             last_refresh: Arc::new(RwLock::new(None)),
             cache_ttl: Duration::from_secs(config.certs_cache_ttl_secs.max(3600)),
+            clock_skew_secs: config.clock_skew_secs,
         };
 
         // Prime the cache so the first request doesn't incur latency
@@ -65,13 +69,17 @@ impl CloudflareValidator {
 
         let key = self.get_decoding_key(&kid).await?;
 
-        let mut validation = Validation::new(header.alg);
-        validation.set_audience(&[&self.audience]);
-        validation.set_issuer(&[&self.team_domain]);
+        let validation = token_validation(
+            header.alg,
+            &self.audience,
+            &self.team_domain,
+            self.clock_skew_secs,
+        );
 
         let data = decode::<Value>(token, key.as_ref(), &validation)
             .context("token failed signature or structural validation")?;
         let claims = data.claims;
+        check_issued_at(&claims, self.clock_skew_secs)?;
 
         Ok(claims)
     }
@@ -146,6 +154,7 @@ impl CloudflareValidator {
         let response = self
             .client
             .get(&self.certs_uri)
+            .timeout(CERTS_TIMEOUT)
             .send()
             .await
             .context("failed to request Cloudflare certs")?
@@ -0,0 +1,95 @@
+//! Last-seen tracking for signed-in users.
+//!
+//! The auth middleware reports every request it resolves to a user. The
+//! first report of a sign-in within the throttle interval upserts the user,
+//! which bumps `users.last_seen_at`; later ones are absorbed by a per-user
+//! TTL cache, so a busy user costs one write per interval instead of one per
+//! request.
+
+use moka::future::Cache;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::AuthClaims;
+use crate::storage::Storage;
+
+/// How often a signed-in user's `last_seen_at` is bumped at most.
+pub const LAST_SEEN_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Sign-ins remembered at once; past this, the least recently seen ones may
+/// be written again before their interval is up.
+const TRACKED_USERS: u64 = 100_000;
+
+pub struct LastSeenTracker {
+    storage: Arc<dyn Storage>,
+    /// `(user_id, auth_method)` pairs written within the interval
+    recent: Cache<(String, String), ()>,
+}
+
+impl LastSeenTracker {
+    pub fn new(storage: Arc<dyn Storage>, interval: Duration) -> Self {
+        Self {
+            storage,
+            recent: Cache::builder()
+                .max_capacity(TRACKED_USERS)
+                .time_to_live(interval)
+                .build(),
+        }
+    }
+
+    /// Note a request authenticated as `claims`. Returns whether the user
+    /// was written, which happens at most once per interval.
+    pub async fn seen(&self, claims: &AuthClaims) -> bool {
+        let (Some(user_id), Some(auth_method)) = (claims.user_id(), claims.auth_method()) else {
+            return false;
+        };
+        let key = (user_id, auth_method);
+        if !self
+            .recent
+            .entry(key.clone())
+            .or_insert(())
+            .await
+            .is_fresh()
+        {
+            return false;
+        }
+
+        let (user_id, auth_method) = &key;
+        match self
+            .storage
+            .upsert_user(user_id, claims.email().as_deref(), auth_method)
+            .await
+        {
+            Ok(()) => true,
+            Err(error) => {
+                tracing::warn!(%error, user_id, "Failed to record when the user was last seen");
+                // Try again on the user's next request
+                self.recent.invalidate(&key).await;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use serde_json::json;
+
+    fn claims(sub: &str, auth_method: &str) -> AuthClaims {
+        AuthClaims(Arc::new(json!({ "sub": sub, "auth_method": auth_method })))
+    }
+
+    #[tokio::test]
+    async fn writes_each_sign_in_once_per_interval() {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        let tracker = LastSeenTracker::new(Arc::new(storage), LAST_SEEN_INTERVAL);
+
+        assert!(tracker.seen(&claims("alice", "oauth")).await);
+        assert!(!tracker.seen(&claims("alice", "oauth")).await);
+        assert!(tracker.seen(&claims("alice", "cloudflare")).await);
+        assert!(tracker.seen(&claims("bob", "oauth")).await);
+    }
+}
@@ -1,5 +1,8 @@
 mod cloudflare;
+pub mod last_seen;
 mod oauth;
+pub mod usage;
+mod validation;
 
 use std::sync::Arc;
 
@@ -16,10 +19,14 @@ use tracing::warn;
 use crate::config::{AuthConfig, AuthMode};
 
 use self::cloudflare::CloudflareValidator;
+use self::last_seen::LastSeenTracker;
 use self::oauth::OAuthValidator;
+use self::usage::{ApiUsageTracker, UsageClass};
 
 pub struct AuthService {
     strategy: AuthStrategy,
+    /// Let tokens without a usable `sub` through (`AUTH_ALLOW_ANONYMOUS_SUBJECT`)
+    allow_anonymous_subject: bool,
 }
 
 enum AuthStrategy {
@@ -103,6 +110,8 @@ pub enum AuthError {
     Misconfigured(String),
     #[error("token validation failed: {0}")]
     Token(String),
+    #[error("token is missing required claim '{0}'")]
+    MissingClaim(&'static str),
 }
 
 impl AuthError {
@@ -110,7 +119,8 @@ impl AuthError {
         match self {
             AuthError::MissingAuthorization
             | AuthError::InvalidAuthorization
-            | AuthError::Token(_) => StatusCode::UNAUTHORIZED,
+            | AuthError::Token(_)
+            | AuthError::MissingClaim(_) => StatusCode::UNAUTHORIZED,
             AuthError::Misconfigured(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -118,6 +128,17 @@ impl AuthError {
 
 impl AuthService {
     pub async fn new(config: AuthConfig) -> anyhow::Result<Self> {
+        let allow_anonymous_subject = match config.mode {
+            AuthMode::None => false,
+            AuthMode::Oauth => config
+                .oauth
+                .as_ref()
+                .is_some_and(|oauth| oauth.allow_anonymous_subject),
+            AuthMode::Cloudflare => config
+                .cloudflare
+                .as_ref()
+                .is_some_and(|cloudflare| cloudflare.allow_anonymous_subject),
+        };
         let strategy = match config.mode {
             AuthMode::None => AuthStrategy::None,
             AuthMode::Oauth => {
@@ -138,7 +159,21 @@ impl AuthService {
             }
         };
 
-        Ok(Self { strategy })
+        Ok(Self {
+            strategy,
+            allow_anonymous_subject,
+        })
+    }
+
+    /// Reject validated tokens that name no user: links they create would be
+    /// stored with a NULL `created_by` and belong to nobody.
+    fn require_subject(&self, claims: AuthClaims) -> Result<AuthClaims, AuthError> {
+        let has_subject = claims.user_id().is_some_and(|sub| !sub.trim().is_empty());
+        if has_subject || self.allow_anonymous_subject {
+            Ok(claims)
+        } else {
+            Err(AuthError::MissingClaim("sub"))
+        }
     }
 
     pub async fn authenticate(&self, headers: &HeaderMap) -> Result<Option<AuthClaims>, AuthError> {
@@ -182,7 +217,7 @@ impl AuthService {
                     );
                 }
 
-                Ok(Some(AuthClaims(Arc::new(claims))))
+                self.require_subject(AuthClaims(Arc::new(claims))).map(Some)
             }
             AuthStrategy::Cloudflare(validator) => {
                 // For Cloudflare, check the Cf-Access-Jwt-Assertion header
@@ -205,7 +240,7 @@ impl AuthService {
                     );
                 }
 
-                Ok(Some(AuthClaims(Arc::new(claims))))
+                self.require_subject(AuthClaims(Arc::new(claims))).map(Some)
             }
         }
     }
@@ -213,12 +248,21 @@ impl AuthService {
 
 pub async fn auth_middleware(
     auth_service: Arc<AuthService>,
+    last_seen: Arc<LastSeenTracker>,
+    usage: Option<Arc<ApiUsageTracker>>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> Response {
     match auth_service.authenticate(&headers).await {
         Ok(Some(claims)) => {
+            last_seen.seen(&claims).await;
+            if let Some(usage) = &usage {
+                usage.record(
+                    &claims,
+                    UsageClass::of(request.method(), request.uri().path()),
+                );
+            }
             request.extensions_mut().insert(Some(claims));
             next.run(request).await
         }
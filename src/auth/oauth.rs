@@ -5,7 +5,7 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context, Result};
-use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, DecodingKey};
 use reqwest::Client;
 use serde::Deserialize;
 use serde_json::Value;
@@ -14,6 +14,8 @@ use tracing::{debug, warn};
 
 use crate::config::OAuthConfig;
 
+use super::validation::{check_issued_at, token_validation};
+
 #[derive(Clone)]
 pub struct OAuthValidator {
     issuer: String,
@@ -23,14 +25,13 @@ pub struct OAuthValidator {
     keys: Arc<RwLock<HashMap<String, Arc<DecodingKey>>>>,
     last_refresh: Arc<RwLock<Option<Instant>>>,
     cache_ttl: Duration,
+    /// Seconds of clock skew tolerated on `exp`, `nbf` and `iat`
+    clock_skew_secs: u64,
 }
 
 impl OAuthValidator {
     pub async fn from_config(config: &OAuthConfig) -> Result<Self> {
-        let client = Client::builder()
-            .user_agent("lynx-oauth-validator/0.1.0")
-            .build()
-            .context("failed to build HTTP client for OAuth validation")?;
+        let client = crate::http::client();
 
         let jwks_uri = resolve_jwks_uri(config, &client).await?;
         let validator = Self {
@@ -41,6 +42,7 @@ impl OAuthValidator {
             keys: Arc::new(RwLock::new(HashMap::new())),
             last_refresh: Arc::new(RwLock::new(None)),
             cache_ttl: Duration::from_secs(config.jwks_cache_ttl_secs.max(60)),
+            clock_skew_secs: config.clock_skew_secs,
         };
 
         // Prime the JWKS cache so the first request doesn't incur latency.
@@ -58,13 +60,17 @@ impl OAuthValidator {
 
         let key = self.get_decoding_key(&kid).await?;
 
-        let mut validation = Validation::new(header.alg);
-        validation.set_audience(&[&self.audience]);
-        validation.set_issuer(&[&self.issuer]);
+        let validation = token_validation(
+            header.alg,
+            &self.audience,
+            &self.issuer,
+            self.clock_skew_secs,
+        );
 
         let data = decode::<Value>(token, key.as_ref(), &validation)
             .context("token failed signature or structural validation")?;
         let claims = data.claims;
+        check_issued_at(&claims, self.clock_skew_secs)?;
 
         Ok(claims)
     }
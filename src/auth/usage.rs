@@ -0,0 +1,331 @@
+//! Per-user API request accounting for fair-use review.
+//!
+//! The auth middleware counts every request it resolves to a user, by
+//! endpoint class, in memory under the user and the UTC hour. A background
+//! task adds the counts to `user_usage` every [`USAGE_FLUSH_INTERVAL`] in
+//! batches of [`FLUSH_BATCH_SIZE`] rows, so a busy user costs one row per
+//! hour instead of one write per request. Counts not yet flushed are merged
+//! into the admin usage reports, so those are never an hour behind.
+
+use axum::http::Method;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::AuthClaims;
+use crate::clock::Clock;
+use crate::models::{UsageCounts, UserUsageHour};
+use crate::storage::Storage;
+
+/// How often pending counts are written to `user_usage`.
+pub const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Rows written per storage call when flushing.
+pub const FLUSH_BATCH_SIZE: usize = 500;
+
+/// `(user, hour)` counters held at once; requests that would need a new one
+/// past this are dropped until the next flush.
+const TRACKED_HOURS: usize = 100_000;
+
+const HOUR_SECS: i64 = 3600;
+
+/// The endpoint classes requests are counted under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageClass {
+    /// Anything that creates or changes something, including `GET /quick`
+    Create,
+    /// Reads of links, profiles and admin reports
+    Read,
+    /// Link searches
+    Search,
+    /// Analytics, exports, click history and stats
+    Analytics,
+}
+
+impl UsageClass {
+    /// Class of a request to `path` below `/api`, judged by its leading
+    /// segments so a short code never changes the class.
+    pub fn of(method: &Method, path: &str) -> Self {
+        let mut segments = ["", "", ""];
+        for (slot, segment) in segments
+            .iter_mut()
+            .zip(path.split('/').filter(|segment| !segment.is_empty()))
+        {
+            *slot = segment;
+        }
+        let reading = matches!(*method, Method::GET | Method::HEAD);
+        match segments {
+            _ if !reading => Self::Create,
+            ["quick", ..] => Self::Create,
+            ["urls", "search", ""] => Self::Search,
+            ["analytics" | "stats", ..]
+            | ["admin", "analytics" | "stats", _]
+            | ["me", "analytics", _]
+            | ["links", _, "analytics" | "clicks"]
+            | ["campaigns", _, "stats"] => Self::Analytics,
+            _ => Self::Read,
+        }
+    }
+}
+
+impl UsageCounts {
+    fn count(&mut self, class: UsageClass) {
+        match class {
+            UsageClass::Create => self.create_requests += 1,
+            UsageClass::Read => self.read_requests += 1,
+            UsageClass::Search => self.search_requests += 1,
+            UsageClass::Analytics => self.analytics_requests += 1,
+        }
+    }
+}
+
+pub struct ApiUsageTracker {
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    /// Counts not yet written, by `(user_id, hour)`
+    pending: DashMap<(String, i64), UsageCounts>,
+    /// Requests not counted because `pending` was full
+    dropped: AtomicU64,
+}
+
+impl ApiUsageTracker {
+    pub fn new(storage: Arc<dyn Storage>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            storage,
+            clock,
+            pending: DashMap::new(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a request authenticated as `claims`.
+    pub fn record(&self, claims: &AuthClaims, class: UsageClass) {
+        let Some(user_id) = claims.user_id() else {
+            return;
+        };
+        let now = self.clock.now_epoch_secs();
+        let key = (user_id, now - now.rem_euclid(HOUR_SECS));
+        if let Some(mut counts) = self.pending.get_mut(&key) {
+            counts.count(class);
+        } else if self.pending.len() < TRACKED_HOURS {
+            self.pending.entry(key).or_default().count(class);
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts of `user_id` not yet flushed, for hours from `since` onwards.
+    pub fn pending(&self, user_id: &str, since: i64) -> Vec<UserUsageHour> {
+        self.pending
+            .iter()
+            .filter(|entry| entry.key().0 == user_id && entry.key().1 >= since)
+            .map(|entry| UserUsageHour {
+                user_id: entry.key().0.clone(),
+                hour: entry.key().1,
+                counts: *entry.value(),
+            })
+            .collect()
+    }
+
+    /// Counts not yet flushed, summed per user over hours from `since`
+    /// onwards.
+    pub fn pending_totals(&self, since: i64) -> HashMap<String, UsageCounts> {
+        let mut totals = HashMap::<String, UsageCounts>::new();
+        for entry in self.pending.iter().filter(|entry| entry.key().1 >= since) {
+            totals
+                .entry(entry.key().0.clone())
+                .or_default()
+                .add(entry.value());
+        }
+        totals
+    }
+
+    /// Write every pending count to storage. Batches that fail are kept and
+    /// retried on the next flush. Returns the rows written.
+    pub async fn flush(&self) -> usize {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::warn!(
+                dropped,
+                "API usage counters were full; some requests were not counted"
+            );
+        }
+
+        let keys: Vec<_> = self
+            .pending
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        // Requests arriving while the batch is written start a new counter
+        let hours: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key))
+            .map(|((user_id, hour), counts)| UserUsageHour {
+                user_id,
+                hour,
+                counts,
+            })
+            .collect();
+
+        let mut written = 0;
+        for batch in hours.chunks(FLUSH_BATCH_SIZE) {
+            match self.storage.record_user_usage(batch).await {
+                Ok(()) => written += batch.len(),
+                Err(error) => {
+                    tracing::warn!(%error, rows = batch.len(), "Failed to write API usage");
+                    for hour in batch {
+                        self.pending
+                            .entry((hour.user_id.clone(), hour.hour))
+                            .or_default()
+                            .add(&hour.counts);
+                    }
+                }
+            }
+        }
+        written
+    }
+
+    /// Flush every `interval` until the returned task is aborted.
+    pub fn spawn_flush_task(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let tracker = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                tracker.flush().await;
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::storage::SqliteStorage;
+    use serde_json::json;
+
+    fn claims(sub: &str) -> AuthClaims {
+        AuthClaims(Arc::new(json!({ "sub": sub, "auth_method": "oauth" })))
+    }
+
+    #[test]
+    fn requests_are_classified_by_method_and_path() {
+        for (method, path, class) in [
+            (Method::POST, "/urls", UsageClass::Create),
+            (Method::PATCH, "/urls/docs", UsageClass::Create),
+            (Method::GET, "/quick", UsageClass::Create),
+            (Method::POST, "/stats/orphans/cleanup", UsageClass::Create),
+            (Method::GET, "/urls", UsageClass::Read),
+            (Method::GET, "/urls/docs", UsageClass::Read),
+            (Method::GET, "/urls/stats", UsageClass::Read),
+            (Method::GET, "/admin/users", UsageClass::Read),
+            (Method::GET, "/urls/search", UsageClass::Search),
+            (
+                Method::GET,
+                "/analytics/docs/aggregate",
+                UsageClass::Analytics,
+            ),
+            (
+                Method::GET,
+                "/links/docs/clicks/history",
+                UsageClass::Analytics,
+            ),
+            (Method::GET, "/me/analytics/export", UsageClass::Analytics),
+            (Method::GET, "/stats/redirects", UsageClass::Analytics),
+            (Method::GET, "/admin/stats/history", UsageClass::Analytics),
+            (Method::GET, "/campaigns/7/stats", UsageClass::Analytics),
+            (Method::GET, "/campaigns/7", UsageClass::Read),
+        ] {
+            assert_eq!(UsageClass::of(&method, path), class, "{method} {path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_roll_up_per_user_and_hour_and_add_to_stored_ones() {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(storage);
+        let clock = Arc::new(FakeClock::at_epoch_ms(10 * HOUR_SECS * 1000 + 5_000));
+        let tracker = ApiUsageTracker::new(Arc::clone(&storage), clock.clone());
+
+        tracker.record(&claims("alice"), UsageClass::Create);
+        tracker.record(&claims("alice"), UsageClass::Read);
+        tracker.record(&claims("alice"), UsageClass::Read);
+        tracker.record(&claims("bob"), UsageClass::Search);
+        clock.advance(Duration::from_secs(HOUR_SECS as u64));
+        tracker.record(&claims("alice"), UsageClass::Analytics);
+        assert_eq!(tracker.flush().await, 3);
+        assert!(tracker.pending("alice", 0).is_empty());
+
+        // The next flush adds to the hour already stored
+        tracker.record(&claims("alice"), UsageClass::Analytics);
+        assert_eq!(
+            tracker.pending_totals(0)["alice"],
+            UsageCounts {
+                analytics_requests: 1,
+                ..UsageCounts::default()
+            }
+        );
+        assert_eq!(tracker.flush().await, 1);
+
+        let alice = storage.user_usage("alice", 0).await.unwrap();
+        assert_eq!(
+            alice
+                .iter()
+                .map(|hour| (hour.hour, hour.counts))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    10 * HOUR_SECS,
+                    UsageCounts {
+                        create_requests: 1,
+                        read_requests: 2,
+                        ..UsageCounts::default()
+                    }
+                ),
+                (
+                    11 * HOUR_SECS,
+                    UsageCounts {
+                        analytics_requests: 2,
+                        ..UsageCounts::default()
+                    }
+                ),
+            ]
+        );
+
+        let totals = storage
+            .user_usage_totals(&["alice".to_string(), "bob".to_string()], 11 * HOUR_SECS)
+            .await
+            .unwrap();
+        assert_eq!(totals.len(), 1, "bob's only hour is before `since`");
+        assert_eq!(totals[0].user_id, "alice");
+        assert_eq!(totals[0].counts.total(), 2);
+    }
+
+    #[tokio::test]
+    async fn full_counters_drop_new_keys_but_keep_counting_existing_ones() {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        let tracker = ApiUsageTracker::new(Arc::new(storage), Arc::new(FakeClock::at_epoch_ms(0)));
+        for user in 0..TRACKED_HOURS {
+            tracker.record(&claims(&user.to_string()), UsageClass::Read);
+        }
+
+        tracker.record(&claims("late"), UsageClass::Read);
+        tracker.record(&claims("0"), UsageClass::Read);
+        assert_eq!(tracker.pending.len(), TRACKED_HOURS);
+        assert_eq!(tracker.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(tracker.pending("0", 0)[0].counts.read_requests, 2);
+
+        // Flushing writes every counter in batches and frees the space
+        assert_eq!(tracker.flush().await, TRACKED_HOURS);
+        tracker.record(&claims("late"), UsageClass::Read);
+        assert_eq!(tracker.pending.len(), 1);
+    }
+}
@@ -0,0 +1,117 @@
+//! Token time checks shared by the OAuth and Cloudflare validators.
+//!
+//! `exp`, `nbf` and `iat` are all checked with the same leeway, so a client
+//! whose clock runs a little ahead or behind the issuer is not rejected for a
+//! freshly issued token.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use jsonwebtoken::{Algorithm, Validation};
+use serde_json::Value;
+
+/// Signature, audience, issuer, `exp` and `nbf` validation for `alg` tokens,
+/// tolerating `leeway_secs` of clock skew.
+pub(crate) fn token_validation(
+    alg: Algorithm,
+    audience: &str,
+    issuer: &str,
+    leeway_secs: u64,
+) -> Validation {
+    let mut validation = Validation::new(alg);
+    validation.set_audience(&[audience]);
+    validation.set_issuer(&[issuer]);
+    validation.validate_nbf = true;
+    validation.leeway = leeway_secs;
+    validation
+}
+
+/// Reject tokens issued further in the future than `leeway_secs`.
+///
+/// `jsonwebtoken` does not look at `iat`; a token without one passes.
+pub(crate) fn check_issued_at(claims: &Value, leeway_secs: u64) -> Result<()> {
+    let Some(issued_at) = claims.get("iat").and_then(Value::as_u64) else {
+        return Ok(());
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    if issued_at > now.saturating_add(leeway_secs) {
+        bail!("token issued {}s in the future", issued_at - now);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"clock-skew-test-secret";
+    const LEEWAY: u64 = 30;
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Sign `claims` and run them through the same checks as the validators.
+    fn validate(claims: Value) -> Result<Value> {
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )?;
+        let validation = token_validation(Algorithm::HS256, "lynx", "https://issuer", LEEWAY);
+        let data = decode::<Value>(&token, &DecodingKey::from_secret(SECRET), &validation)?;
+        check_issued_at(&data.claims, LEEWAY)?;
+        Ok(data.claims)
+    }
+
+    fn claims(exp_offset: i64, nbf_offset: i64, iat_offset: i64) -> Value {
+        let now = now();
+        json!({
+            "aud": "lynx",
+            "iss": "https://issuer",
+            "sub": "user-1",
+            "exp": now + exp_offset,
+            "nbf": now + nbf_offset,
+            "iat": now + iat_offset,
+        })
+    }
+
+    #[test]
+    fn expiry_is_tolerated_only_within_the_leeway() {
+        assert!(validate(claims(-20, -60, -60)).is_ok());
+        assert!(validate(claims(-45, -60, -60)).is_err());
+    }
+
+    #[test]
+    fn not_before_is_tolerated_only_within_the_leeway() {
+        assert!(validate(claims(300, 20, 0)).is_ok());
+        assert!(validate(claims(300, 45, 0)).is_err());
+    }
+
+    #[test]
+    fn issued_at_is_tolerated_only_within_the_leeway() {
+        assert!(validate(claims(300, 0, 20)).is_ok());
+        assert!(validate(claims(300, 0, 45)).is_err());
+    }
+
+    #[test]
+    fn tokens_without_issued_at_pass() {
+        let mut claims = claims(300, 0, 0);
+        claims.as_object_mut().unwrap().remove("iat");
+        assert!(validate(claims).is_ok());
+    }
+
+    #[test]
+    fn zero_leeway_rejects_any_future_issue_time() {
+        assert!(check_issued_at(&json!({ "iat": now() + 5 }), 0).is_err());
+        assert!(check_issued_at(&json!({ "iat": now() - 5 }), 0).is_ok());
+    }
+}
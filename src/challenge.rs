@@ -0,0 +1,418 @@
+//! Abuse friction for link creation: a captcha or a proof of work checked
+//! before a create request is processed.
+//!
+//! `CREATION_CHALLENGE_PROVIDER` picks the [`CreationChallenge`] and
+//! `CREATION_CHALLENGE_ENDPOINTS` the endpoints that demand it. Clients send
+//! their answer in the [`CHALLENGE_HEADER`] header: the token of the Turnstile
+//! or hCaptcha widget, or `<nonce>:<solution>` for a proof of work issued by
+//! `GET /api/public/challenge`.
+//!
+//! A proof of work needs no third party. The server hands out a signed,
+//! expiring nonce and a difficulty; the client searches for a solution such
+//! that `SHA-256("<nonce>:<solution>")` starts with that many zero bits. The
+//! server checks it with one hash and accepts each nonce once. Nonces are
+//! signed with `CREATION_CHALLENGE_SECRET` and redemptions are kept in
+//! storage, so instances sharing the secret and the database honour each
+//! other's nonces, and one answer buys one link however requests are
+//! balanced.
+
+use async_trait::async_trait;
+use base64::prelude::*;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::clock::Clock;
+use crate::config::{ChallengeProvider, CreationChallengeConfig};
+use crate::storage::Storage;
+
+/// Request header carrying the client's answer.
+pub const CHALLENGE_HEADER: &str = "x-lynx-challenge";
+
+/// Server-side verification endpoint for Cloudflare Turnstile tokens.
+pub const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+/// Server-side verification endpoint for hCaptcha tokens.
+pub const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// Upper bound for one call to a captcha provider.
+pub const VERIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an issued proof-of-work nonce can be redeemed.
+pub const POW_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Error)]
+pub enum ChallengeError {
+    /// The request carried no answer
+    #[error("verification required")]
+    Missing,
+    /// The answer was wrong, expired or already used
+    #[error("verification failed")]
+    Failed,
+    /// The provider could not be asked; retrying may succeed
+    #[error("verification unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// What a client needs before it can answer, served by `GET /api/public/challenge`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuedChallenge {
+    pub provider: ChallengeProvider,
+    /// Proof of work: the nonce to hash with a solution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Proof of work: leading zero bits the hash must have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u8>,
+    /// Proof of work: when the nonce can no longer be redeemed (Unix timestamp)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+/// A verification step a client passes before it may create a link.
+#[async_trait]
+pub trait CreationChallenge: Send + Sync {
+    fn provider(&self) -> ChallengeProvider;
+
+    /// A fresh challenge for a client. Captcha widgets run entirely on the
+    /// client, so by default there is nothing to hand out.
+    fn issue(&self) -> IssuedChallenge {
+        IssuedChallenge {
+            provider: self.provider(),
+            nonce: None,
+            difficulty: None,
+            expires_at: None,
+        }
+    }
+
+    /// Check `answer`, the value of [`CHALLENGE_HEADER`], sent from `remote_ip`
+    /// when it is known.
+    async fn verify(&self, answer: &str, remote_ip: Option<IpAddr>) -> Result<(), ChallengeError>;
+}
+
+/// The challenge configured in `config`, or `None` when none is. A proof of
+/// work records redemptions in `storage` and issues and expires nonces by
+/// `clock`.
+pub fn from_config(
+    config: &CreationChallengeConfig,
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+) -> Option<Arc<dyn CreationChallenge>> {
+    let secret = config.secret.clone().unwrap_or_default();
+    match config.provider {
+        ChallengeProvider::None => None,
+        ChallengeProvider::Turnstile => Some(Arc::new(CaptchaVerifier::new(
+            ChallengeProvider::Turnstile,
+            TURNSTILE_VERIFY_URL,
+            secret,
+        ))),
+        ChallengeProvider::Hcaptcha => Some(Arc::new(CaptchaVerifier::new(
+            ChallengeProvider::Hcaptcha,
+            HCAPTCHA_VERIFY_URL,
+            secret,
+        ))),
+        ChallengeProvider::Pow => Some(Arc::new(ProofOfWork::new(
+            secret.as_bytes(),
+            config.pow_difficulty,
+            storage,
+            clock,
+        ))),
+    }
+}
+
+/// Verifies Turnstile and hCaptcha tokens with the provider. Both take the
+/// same form fields and answer with `{"success": bool, ...}`.
+pub struct CaptchaVerifier {
+    provider: ChallengeProvider,
+    verify_url: String,
+    secret: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+impl CaptchaVerifier {
+    pub fn new(provider: ChallengeProvider, verify_url: &str, secret: String) -> Self {
+        Self {
+            provider,
+            verify_url: verify_url.to_string(),
+            secret,
+            client: crate::http::client(),
+        }
+    }
+}
+
+#[async_trait]
+impl CreationChallenge for CaptchaVerifier {
+    fn provider(&self) -> ChallengeProvider {
+        self.provider
+    }
+
+    async fn verify(&self, answer: &str, remote_ip: Option<IpAddr>) -> Result<(), ChallengeError> {
+        let body = {
+            let mut form = url::form_urlencoded::Serializer::new(String::new());
+            form.append_pair("secret", &self.secret)
+                .append_pair("response", answer);
+            if let Some(ip) = remote_ip {
+                form.append_pair("remoteip", &ip.to_string());
+            }
+            form.finish()
+        };
+
+        let response = self
+            .client
+            .post(&self.verify_url)
+            .timeout(VERIFY_TIMEOUT)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ChallengeError::Unavailable(e.to_string()))?;
+        let result: SiteVerifyResponse = response
+            .json()
+            .await
+            .map_err(|e| ChallengeError::Unavailable(e.to_string()))?;
+
+        if result.success {
+            Ok(())
+        } else {
+            tracing::debug!(provider = ?self.provider, errors = ?result.error_codes, "captcha rejected");
+            Err(ChallengeError::Failed)
+        }
+    }
+}
+
+/// Proof of work: nonces are signed with `key`, so every instance holding
+/// it can check them, and redeemed nonces are recorded in `storage` until
+/// they expire.
+pub struct ProofOfWork {
+    key: Vec<u8>,
+    difficulty: u8,
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl ProofOfWork {
+    pub fn new(
+        key: &[u8],
+        difficulty: u8,
+        storage: Arc<dyn Storage>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            key: key.to_vec(),
+            difficulty,
+            storage,
+            clock,
+        }
+    }
+
+    fn sign(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// A nonce valid until `now + POW_TTL`: `<expires_at>.<random>.<signature>`.
+    pub fn issue_at(&self, now: i64) -> IssuedChallenge {
+        let mut random = [0u8; 16];
+        rand::fill(&mut random);
+        let expires_at = now + POW_TTL.as_secs() as i64;
+        let payload = format!("{}.{}", expires_at, BASE64_URL_SAFE_NO_PAD.encode(random));
+        let signature = BASE64_URL_SAFE_NO_PAD.encode(self.sign(&payload).finalize().into_bytes());
+        IssuedChallenge {
+            provider: ChallengeProvider::Pow,
+            nonce: Some(format!("{}.{}", payload, signature)),
+            difficulty: Some(self.difficulty),
+            expires_at: Some(expires_at),
+        }
+    }
+
+    /// Check `<nonce>:<solution>` at `now`, redeeming the nonce on success.
+    pub async fn verify_at(&self, answer: &str, now: i64) -> Result<(), ChallengeError> {
+        let (nonce, _) = answer.rsplit_once(':').ok_or(ChallengeError::Failed)?;
+        let (payload, signature) = nonce.rsplit_once('.').ok_or(ChallengeError::Failed)?;
+        let signature = BASE64_URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| ChallengeError::Failed)?;
+        self.sign(payload)
+            .verify_slice(&signature)
+            .map_err(|_| ChallengeError::Failed)?;
+
+        let expires_at = payload
+            .split_once('.')
+            .and_then(|(expires_at, _)| expires_at.parse::<i64>().ok())
+            .ok_or(ChallengeError::Failed)?;
+        if expires_at <= now {
+            return Err(ChallengeError::Failed);
+        }
+        if leading_zero_bits(&Sha256::digest(answer.as_bytes())) < u32::from(self.difficulty) {
+            return Err(ChallengeError::Failed);
+        }
+
+        let fresh = self
+            .storage
+            .redeem_challenge_nonce(nonce, expires_at)
+            .await
+            .map_err(|e| ChallengeError::Unavailable(e.to_string()))?;
+        if !fresh {
+            return Err(ChallengeError::Failed);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CreationChallenge for ProofOfWork {
+    fn provider(&self) -> ChallengeProvider {
+        ChallengeProvider::Pow
+    }
+
+    fn issue(&self) -> IssuedChallenge {
+        self.issue_at(self.clock.now_epoch_secs())
+    }
+
+    async fn verify(&self, answer: &str, _remote_ip: Option<IpAddr>) -> Result<(), ChallengeError> {
+        self.verify_at(answer, self.clock.now_epoch_secs()).await
+    }
+}
+
+/// Number of leading zero bits in `hash`.
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            return bits + byte.leading_zeros();
+        }
+    }
+    bits
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::storage::SqliteStorage;
+
+    const NOW: i64 = 1_700_000_000;
+    const KEY: &[u8] = b"shared challenge secret";
+
+    /// Brute-force a solution the way a client would.
+    fn solve(nonce: &str, difficulty: u8) -> String {
+        (0u64..)
+            .map(|solution| format!("{}:{}", nonce, solution))
+            .find(|answer| {
+                leading_zero_bits(&Sha256::digest(answer.as_bytes())) >= u32::from(difficulty)
+            })
+            .unwrap()
+    }
+
+    /// In-memory storage whose clock reads `NOW`.
+    async fn storage() -> (Arc<dyn Storage>, Arc<FakeClock>) {
+        let clock = Arc::new(FakeClock::at_epoch_ms(NOW * 1000));
+        let storage = SqliteStorage::new("sqlite::memory:", 1)
+            .await
+            .unwrap()
+            .with_clock(Arc::clone(&clock) as _);
+        storage.init().await.unwrap();
+        (Arc::new(storage), clock)
+    }
+
+    async fn pow() -> (ProofOfWork, Arc<FakeClock>) {
+        let (storage, clock) = storage().await;
+        let pow = ProofOfWork::new(KEY, 8, storage, Arc::clone(&clock) as _);
+        (pow, clock)
+    }
+
+    #[test]
+    fn leading_zero_bits_counts_across_bytes() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[tokio::test]
+    async fn solved_nonces_verify_once() {
+        let (pow, _) = pow().await;
+        let issued = pow.issue_at(NOW);
+        assert_eq!(issued.difficulty, Some(8));
+        let answer = solve(issued.nonce.as_deref().unwrap(), 8);
+
+        assert!(pow.verify_at(&answer, NOW + 1).await.is_ok());
+        assert!(matches!(
+            pow.verify_at(&answer, NOW + 2).await,
+            Err(ChallengeError::Failed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn instances_sharing_key_and_storage_honour_each_others_nonces() {
+        let (storage, clock) = storage().await;
+        let first = ProofOfWork::new(KEY, 8, Arc::clone(&storage), Arc::clone(&clock) as _);
+        let second = ProofOfWork::new(KEY, 8, storage, Arc::clone(&clock) as _);
+        let answer = solve(first.issue_at(NOW).nonce.as_deref().unwrap(), 8);
+
+        assert!(second.verify_at(&answer, NOW + 1).await.is_ok());
+        assert!(matches!(
+            first.verify_at(&answer, NOW + 2).await,
+            Err(ChallengeError::Failed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn unsolved_expired_and_forged_nonces_fail() {
+        let (pow, _) = pow().await;
+        let nonce = pow.issue_at(NOW).nonce.unwrap();
+        let answer = solve(&nonce, 8);
+
+        let unsolved = (0u64..)
+            .map(|solution| format!("{}:{}", nonce, solution))
+            .find(|answer| leading_zero_bits(&Sha256::digest(answer.as_bytes())) < 8)
+            .unwrap();
+        assert!(pow.verify_at(&unsolved, NOW).await.is_err());
+        assert!(pow
+            .verify_at(&answer, NOW + POW_TTL.as_secs() as i64)
+            .await
+            .is_err());
+
+        // A nonce signed with another key, or with its expiry pushed out.
+        let (storage, clock) = storage().await;
+        let other = ProofOfWork::new(b"another secret", 8, storage, clock as _);
+        assert!(other.verify_at(&answer, NOW).await.is_err());
+        let forged = answer.replacen(&NOW.to_string()[..4], "9999", 1);
+        assert!(pow.verify_at(&forged, NOW).await.is_err());
+        assert!(pow.verify_at("garbage", NOW).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn nonces_are_issued_and_expired_by_the_clock() {
+        let (pow, clock) = pow().await;
+        let issued = pow.issue();
+        assert_eq!(issued.expires_at, Some(NOW + POW_TTL.as_secs() as i64));
+        let answer = solve(issued.nonce.as_deref().unwrap(), 8);
+
+        clock.advance(POW_TTL);
+        assert!(matches!(
+            pow.verify(&answer, None).await,
+            Err(ChallengeError::Failed)
+        ));
+        clock.set_epoch_ms(NOW * 1000);
+        assert!(pow.verify(&answer, None).await.is_ok());
+    }
+}
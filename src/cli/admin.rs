@@ -0,0 +1,117 @@
+//! `lynx admin`: promote, demote and list manually promoted admins.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::config::Config;
+use lynx::notify::{AccountEvent, AccountNotifications};
+use lynx::storage::Storage;
+
+use super::open_verified_storage;
+
+#[derive(Subcommand)]
+pub(crate) enum AdminCommands {
+    /// Promote a user to admin
+    Promote {
+        /// User ID (sub claim from JWT)
+        user_id: String,
+        /// Authentication method (oauth, cloudflare)
+        auth_method: String,
+    },
+    /// Demote a user from admin
+    Demote {
+        /// User ID (sub claim from JWT)
+        user_id: String,
+        /// Authentication method (oauth, cloudflare)
+        auth_method: String,
+    },
+    /// List all manually promoted admins
+    List,
+}
+
+pub(crate) async fn run(command: AdminCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    let storage = open_verified_storage(&config).await?;
+
+    let notifications = AccountNotifications::from_config(config.smtp.as_ref())?;
+
+    match command {
+        AdminCommands::Promote {
+            user_id,
+            auth_method,
+        } => {
+            storage.promote_to_admin(&user_id, &auth_method).await?;
+            println!(
+                "✓ Promoted user '{}' with auth method '{}' to admin",
+                user_id, auth_method
+            );
+            notify_account(
+                &notifications,
+                storage.as_ref(),
+                &user_id,
+                &auth_method,
+                AccountEvent::PromotedToAdmin,
+            )
+            .await;
+        }
+        AdminCommands::Demote {
+            user_id,
+            auth_method,
+        } => {
+            let demoted = storage.demote_from_admin(&user_id, &auth_method).await?;
+            if demoted {
+                println!(
+                    "✓ Demoted user '{}' with auth method '{}' from admin",
+                    user_id, auth_method
+                );
+                notify_account(
+                    &notifications,
+                    storage.as_ref(),
+                    &user_id,
+                    &auth_method,
+                    AccountEvent::DemotedFromAdmin,
+                )
+                .await;
+            } else {
+                println!(
+                    "⚠ User '{}' with auth method '{}' was not an admin",
+                    user_id, auth_method
+                );
+            }
+        }
+        AdminCommands::List => {
+            let admins = storage.list_manual_admins().await?;
+            if admins.is_empty() {
+                println!("No manually promoted admins found.");
+            } else {
+                println!("Manually promoted admins:");
+                println!("{:<40} {:<15} Email", "User ID", "Auth Method");
+                println!("{}", "-".repeat(80));
+                for (user_id, auth_method, email) in admins {
+                    println!("{:<40} {:<15} {}", user_id, auth_method, email);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Email the affected user about an admin change, waiting for the send to
+/// finish or give up so it is not cut off when the command exits. Failures
+/// are logged by the sender and never fail the command.
+async fn notify_account(
+    notifications: &AccountNotifications,
+    storage: &dyn Storage,
+    user_id: &str,
+    auth_method: &str,
+    event: AccountEvent,
+) {
+    if let Some(sending) = notifications
+        .notify(storage, user_id, auth_method, event)
+        .await
+    {
+        let _ = sending.await;
+    }
+}
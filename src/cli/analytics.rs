@@ -0,0 +1,122 @@
+//! `lynx analytics`: inspect and prune stored analytics.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::config::Config;
+use lynx::confirm::{confirm_destructive, Confirm};
+
+use super::open_verified_storage;
+
+#[derive(Subcommand)]
+pub(crate) enum AnalyticsCommands {
+    /// Prune old analytics data by aggregating and dropping dimensions
+    ///
+    /// Note: time_bucket is always set to the cutoff_time (start of the hour) for pruned entries.
+    /// This ensures aggregated data is not immediately deleted and simplifies retention logic.
+    Prune {
+        /// Dimensions to drop (comma-separated: region,city,asn,country_code)
+        /// Do not include time_bucket as it will always be set to cutoff_time
+        #[arg(long, value_delimiter = ',', default_value = "")]
+        drop: Vec<String>,
+        /// Keep data newer than this many days (default: 30)
+        #[arg(long, default_value_t = 30)]
+        retention_days: i64,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Compact old hourly click history into one row per link and month
+    CompactClicks {
+        /// Keep hourly rows newer than this many days
+        /// (default: CLICK_HISTORY_RETENTION_DAYS, or 365)
+        #[arg(long)]
+        retention_days: Option<i64>,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Roll up whole days of analytics into daily totals per link and country
+    ///
+    /// Continues where the previous rollup stopped, like the nightly task.
+    RollupDaily {
+        /// Rebuild the rollup from this UTC day (YYYY-MM-DD) on, e.g. after a
+        /// prune; days after the previous rollup are always included
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+        /// Roll up days older than this many days
+        /// (default: ANALYTICS_DAILY_ROLLUP_AFTER_DAYS, or 30)
+        #[arg(long)]
+        after_days: Option<i64>,
+    },
+}
+
+pub(crate) async fn run(command: AnalyticsCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    let storage = open_verified_storage(&config).await?;
+
+    match command {
+        AnalyticsCommands::Prune {
+            drop,
+            retention_days,
+            yes,
+        } => {
+            println!(
+                "⚠ This will prune analytics data older than {} days",
+                retention_days
+            );
+            println!("   Dimensions to drop: {:?}", drop);
+            println!();
+            confirm_destructive(Confirm::YesNo, yes)?;
+
+            let (deleted, inserted) = storage.prune_analytics(retention_days, &drop).await?;
+
+            println!(
+                "✓ Pruned analytics: {} old entries deleted, {} aggregated entries created",
+                deleted, inserted
+            );
+        }
+        AnalyticsCommands::CompactClicks {
+            retention_days,
+            yes,
+        } => {
+            let retention_days = retention_days.unwrap_or(config.click_history.retention_days);
+            println!(
+                "⚠ This will merge hourly click history older than {} days into monthly rows",
+                retention_days
+            );
+            println!();
+            confirm_destructive(Confirm::YesNo, yes)?;
+
+            let (merged, months) = storage.compact_click_history(retention_days).await?;
+
+            println!(
+                "✓ Compacted click history: {} hourly rows merged into {} monthly rows",
+                merged, months
+            );
+        }
+        AnalyticsCommands::RollupDaily { from, after_days } => {
+            let after_days = after_days
+                .unwrap_or(config.analytics.daily_rollup_after_days)
+                .max(0);
+            let from = from.map(|day| day.and_time(chrono::NaiveTime::MIN).and_utc().timestamp());
+            let until = chrono::Utc::now().timestamp() - after_days * 86_400;
+
+            let rows = storage.rollup_analytics_daily(from, until).await?;
+
+            match storage.analytics_daily_rolled_until().await? {
+                Some(rolled_until) => println!(
+                    "✓ Rolled up daily analytics: {} rows written, days before {} are rolled up",
+                    rows,
+                    chrono::DateTime::from_timestamp(rolled_until, 0)
+                        .map(|day| day.date_naive().to_string())
+                        .unwrap_or_else(|| rolled_until.to_string())
+                ),
+                None => println!("✓ No analytics old enough to roll up"),
+            }
+        }
+    }
+
+    Ok(())
+}
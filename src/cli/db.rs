@@ -0,0 +1,346 @@
+//! `lynx db`: verify a database, copy it to another, or migrate between
+//! backends.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::config::{redact_url, Config, DatabaseBackend};
+use lynx::storage::{CheckStatus, CopyReport, DEFAULT_COPY_BATCH_SIZE};
+
+use super::open_storage;
+
+#[derive(Subcommand)]
+pub(crate) enum DbCommands {
+    /// Check integrity, expected tables and indexes, and orphaned analytics rows
+    ///
+    /// Exits non-zero when problems remain.
+    Verify {
+        /// Recreate missing tables and indexes and delete orphaned analytics rows
+        #[arg(long)]
+        fix: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create the schema or bring it up to date, as the server does on start
+    ///
+    /// Needs a database user allowed to create tables, indexes, triggers and
+    /// (on Postgres) extensions. Other commands only check the schema.
+    Migrate,
+    /// Copy links, users, admins and analytics to another database
+    ///
+    /// Seeds a database before it is mirrored (`DATABASE_MIRROR_URL`) or
+    /// switched to. Links already in the target are skipped, so an
+    /// interrupted copy can be run again.
+    Copy {
+        /// Source database URL (defaults to DATABASE_URL)
+        #[arg(long)]
+        from: Option<String>,
+        /// Target database URL (defaults to DATABASE_MIRROR_URL)
+        #[arg(long)]
+        to: Option<String>,
+        /// Rows read and written per batch
+        #[arg(long, default_value_t = DEFAULT_COPY_BATCH_SIZE)]
+        batch_size: i64,
+    },
+    /// Move everything in DATABASE_URL to a new database
+    ///
+    /// Copies links (keeping their ids), users, admins and analytics, then
+    /// checks that the row counts match.
+    MigrateTo {
+        /// Backend of the new database: sqlite or postgres
+        #[arg(long, value_parser = parse_backend)]
+        backend: DatabaseBackend,
+        /// URL of the new database
+        #[arg(long)]
+        url: String,
+        /// Postgres schema of the new database
+        #[arg(long)]
+        schema: Option<String>,
+        /// Rows read and written per batch
+        #[arg(long, default_value_t = DEFAULT_COPY_BATCH_SIZE)]
+        batch_size: i64,
+        /// Copy into a database that already has rows
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn print_copy_progress(table: &str, report: &CopyReport) {
+    match table {
+        "urls" => println!(
+            "  urls: {} read, {} inserted",
+            report.urls_read, report.urls_inserted
+        ),
+        "users" => println!(
+            "  users: {} read, {} inserted",
+            report.users_read, report.users_inserted
+        ),
+        "analytics" => println!(
+            "  analytics: {} read, {} inserted",
+            report.analytics_read, report.analytics_inserted
+        ),
+        _ => {}
+    }
+}
+
+async fn copy_database(
+    config: &Config,
+    from: Option<String>,
+    to: Option<String>,
+    batch_size: i64,
+) -> Result<()> {
+    let (from_backend, from) = match from {
+        Some(url) => (DatabaseBackend::from_url(&url)?, url),
+        None => (config.database.backend.clone(), config.database.url.clone()),
+    };
+    let mirror = config.database_mirror.as_ref();
+    let (to_backend, to) = match (to, mirror) {
+        (Some(url), _) => (DatabaseBackend::from_url(&url)?, url),
+        (None, Some(mirror)) => (mirror.backend.clone(), mirror.url.clone()),
+        (None, None) => anyhow::bail!("Pass --to or set DATABASE_MIRROR_URL"),
+    };
+    if from == to {
+        anyhow::bail!("Source and target are the same database");
+    }
+
+    let source = open_storage(
+        &from_backend,
+        &from,
+        config.database.schema.as_deref(),
+        config,
+    )
+    .await?;
+    let target = open_storage(
+        &to_backend,
+        &to,
+        mirror.and_then(|mirror| mirror.schema.as_deref()),
+        config,
+    )
+    .await?;
+    source.verify_schema().await?;
+    target.ensure_schema().await?;
+
+    println!(
+        "Copying {:?} {} to {:?} {} in batches of {}",
+        from_backend,
+        redact_url(&from),
+        to_backend,
+        redact_url(&to),
+        batch_size
+    );
+    let report = lynx::storage::copy_storage(
+        source.as_ref(),
+        target.as_ref(),
+        batch_size,
+        false,
+        &mut print_copy_progress,
+    )
+    .await?;
+
+    println!();
+    println!(
+        "✓ Links: {} copied, {} already present",
+        report.urls_inserted,
+        report.urls_read - report.urls_inserted
+    );
+    println!(
+        "✓ Users: {} copied, {} already present",
+        report.users_inserted,
+        report.users_read - report.users_inserted
+    );
+    println!(
+        "✓ Admins: {} copied, {} already present",
+        report.admins_inserted,
+        report.admins_read - report.admins_inserted
+    );
+    println!(
+        "✓ Campaigns: {} copied, {} already present",
+        report.campaigns_inserted,
+        report.campaigns_read - report.campaigns_inserted
+    );
+    match report.analytics_before {
+        Some(before) => println!(
+            "✓ Analytics: {} rows copied from before {}, where the target's own rows start",
+            report.analytics_inserted,
+            chrono::DateTime::from_timestamp(before, 0)
+                .map(|time| time.to_rfc3339())
+                .unwrap_or_else(|| before.to_string())
+        ),
+        None => println!("✓ Analytics: {} rows copied", report.analytics_inserted),
+    }
+    Ok(())
+}
+
+async fn migrate_database(
+    config: &Config,
+    backend: DatabaseBackend,
+    url: String,
+    schema: Option<String>,
+    batch_size: i64,
+    force: bool,
+) -> Result<()> {
+    if url == config.database.url {
+        anyhow::bail!("The target is the configured database (DATABASE_URL)");
+    }
+    let source = open_storage(
+        &config.database.backend,
+        &config.database.url,
+        config.database.schema.as_deref(),
+        config,
+    )
+    .await?;
+    let target = open_storage(&backend, &url, schema.as_deref(), config).await?;
+    source.verify_schema().await?;
+    target.ensure_schema().await?;
+
+    let existing = target.count_rows().await?;
+    if !existing.is_empty() {
+        if !force {
+            anyhow::bail!(
+                "{} is not empty ({} links, {} users, {} admins, {} campaigns, {} analytics rows); pass --force to copy into it anyway",
+                redact_url(&url),
+                existing.urls,
+                existing.users,
+                existing.admins,
+                existing.campaigns,
+                existing.analytics
+            );
+        }
+        println!(
+            "Target is not empty; rows that conflict with existing ones are skipped (--force)"
+        );
+    }
+
+    println!(
+        "Migrating {:?} {} to {:?} {} in batches of {}",
+        config.database.backend,
+        redact_url(&config.database.url),
+        backend,
+        redact_url(&url),
+        batch_size
+    );
+    let report = lynx::storage::copy_storage(
+        source.as_ref(),
+        target.as_ref(),
+        batch_size,
+        true,
+        &mut print_copy_progress,
+    )
+    .await?;
+    println!();
+    println!(
+        "Copied {} links, {} users, {} admins, {} campaigns and {} analytics rows",
+        report.urls_inserted,
+        report.users_inserted,
+        report.admins_inserted,
+        report.campaigns_inserted,
+        report.analytics_inserted
+    );
+
+    // Writes to the source during the migration also show up here.
+    let source_counts = source.count_rows().await?;
+    let target_counts = target.count_rows().await?;
+    let differences = source_counts.differences(&target_counts);
+    if differences.is_empty() {
+        println!("✓ Row counts match");
+        return Ok(());
+    }
+    for (table, source_rows, target_rows) in &differences {
+        eprintln!(
+            "✗ {}: {} rows in the source, {} in the target",
+            table, source_rows, target_rows
+        );
+    }
+    anyhow::bail!("row counts differ in {} table(s)", differences.len())
+}
+
+fn parse_backend(value: &str) -> Result<DatabaseBackend, String> {
+    DatabaseBackend::parse(value).map_err(|error| error.to_string())
+}
+
+pub(crate) async fn run(command: DbCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    if let DbCommands::Copy {
+        from,
+        to,
+        batch_size,
+    } = command
+    {
+        return copy_database(&config, from, to, batch_size).await;
+    }
+    if let DbCommands::MigrateTo {
+        backend,
+        url,
+        schema,
+        batch_size,
+        force,
+    } = command
+    {
+        return migrate_database(&config, backend, url, schema, batch_size, force).await;
+    }
+
+    let storage = open_storage(
+        &config.database.backend,
+        &config.database.url,
+        config.database.schema.as_deref(),
+        &config,
+    )
+    .await?;
+
+    // No init() here: `verify` must see the tables and indexes as they are,
+    // and `migrate` runs it itself.
+    match command {
+        DbCommands::Migrate => {
+            storage.ensure_schema().await?;
+            storage.verify_schema().await?;
+            println!(
+                "✓ Schema of {} is up to date",
+                redact_url(&config.database.url)
+            );
+        }
+        DbCommands::Verify { fix, json } => {
+            let report = storage.verify_database(fix).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!(
+                    "Database verification ({}, {}):",
+                    report.backend,
+                    redact_url(&config.database.url)
+                );
+                println!("{:<10} {:<45} Detail", "Status", "Check");
+                println!("{}", "-".repeat(100));
+                for check in &report.checks {
+                    let status = match check.status {
+                        CheckStatus::Ok => "✓ ok",
+                        CheckStatus::Problem => "✗ problem",
+                        CheckStatus::Fixed => "✓ fixed",
+                    };
+                    println!("{:<10} {:<45} {}", status, check.check, check.detail);
+                }
+                println!();
+            }
+
+            let problems = report.problems();
+            if problems > 0 {
+                if !fix {
+                    eprintln!(
+                        "Run `lynx db verify --fix` to repair missing indexes and orphaned rows."
+                    );
+                }
+                anyhow::bail!("database verification found {} problem(s)", problems);
+            }
+            if !json {
+                println!("✓ No problems found");
+            }
+        }
+        DbCommands::Copy { .. } | DbCommands::MigrateTo { .. } => {
+            unreachable!("handled before opening the storage")
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! `lynx import`: create links from another URL shortener's export.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use lynx::clock::{Clock, SystemClock};
+use lynx::config::Config;
+use lynx::import::{self, ImportFormat};
+
+use super::{format_timestamp, open_verified_storage};
+
+/// Links written per transaction by `lynx import`
+const IMPORT_BATCH_SIZE: usize = 500;
+
+pub(crate) async fn run(
+    format: ImportFormat,
+    file: &Path,
+    dry_run: bool,
+    created_by: Option<&str>,
+) -> Result<()> {
+    let input = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let parsed = import::parse(format, &input)?;
+
+    let config = Config::from_env()?;
+    let storage = open_verified_storage(&config).await?;
+
+    let plan = import::plan(
+        storage.as_ref(),
+        parsed,
+        config.short_code_max_length,
+        &config.destination,
+    )
+    .await?;
+
+    for link in &plan.conflicts {
+        println!(
+            "⚠ Row {}: code '{}' already exists, skipped",
+            link.row, link.short_code
+        );
+    }
+    for error in &plan.rejected {
+        println!("✗ Row {}: {}", error.row, error.reason);
+    }
+
+    if dry_run {
+        println!("Would create {} link(s):", plan.create.len());
+        println!(
+            "{:<20} {:<60} {:<10} Created At",
+            "Short Code", "Original URL", "Clicks"
+        );
+        println!("{}", "-".repeat(110));
+        for link in &plan.create {
+            let created = link
+                .created_at
+                .map(|ms| format_timestamp(ms.div_euclid(1000)))
+                .unwrap_or_else(|| "(now)".to_string());
+            println!(
+                "{:<20} {:<60} {:<10} {}",
+                link.short_code, link.original_url, link.clicks, created
+            );
+        }
+        println!();
+        println!(
+            "Dry run: {} to create, {} conflicting, {} rejected. Nothing was written.",
+            plan.create.len(),
+            plan.conflicts.len(),
+            plan.rejected.len()
+        );
+        return Ok(());
+    }
+
+    let report = import::apply(
+        storage.as_ref(),
+        &plan,
+        created_by,
+        SystemClock.now_epoch_ms(),
+        IMPORT_BATCH_SIZE,
+    )
+    .await?;
+
+    println!(
+        "✓ Imported {} link(s) with {} click(s)",
+        report.created, report.clicks
+    );
+    if report.lost_races > 0 {
+        println!(
+            "⚠ {} link(s) were skipped because their code was taken during the import",
+            report.lost_races
+        );
+    }
+    println!(
+        "   {} conflicting and {} rejected row(s) were not imported.",
+        plan.conflicts.len(),
+        plan.rejected.len()
+    );
+    println!("   Note: Links will become active in cache after instance restart.");
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! `lynx link`: commands on a single link.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::config::Config;
+use lynx::confirm::{confirm_destructive, Confirm};
+use lynx::storage::Storage;
+
+use super::open_verified_storage;
+
+#[derive(Subcommand)]
+pub(crate) enum LinkCommands {
+    /// Permanently delete a link, its aliases and its analytics
+    ///
+    /// Only for links that must not be kept at all; deactivate anything else.
+    /// Needs ALLOW_HARD_DELETE=true. The audit log keeps a record of it.
+    HardDelete {
+        /// Short code to delete
+        short_code: String,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+pub(crate) async fn run(command: LinkCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    match command {
+        LinkCommands::HardDelete { short_code, yes } => {
+            if !config.database.allow_hard_delete {
+                anyhow::bail!("Hard delete is disabled; set ALLOW_HARD_DELETE=true to enable it");
+            }
+            let storage = open_verified_storage(&config).await?;
+            ensure_server_stopped(storage.as_ref()).await?;
+
+            let Some(url) = storage.get_authoritative(&short_code).await? else {
+                println!("✗ Short code '{}' not found", short_code);
+                return Ok(());
+            };
+            let aliases = storage
+                .get_aliases(std::slice::from_ref(&short_code))
+                .await?;
+
+            println!(
+                "⚠ This will permanently delete '{}' ({}) with its analytics and history.",
+                short_code, url.original_url
+            );
+            if !aliases.is_empty() {
+                println!("   Its {} alias(es) are deleted too.", aliases.len());
+            }
+            println!("   This cannot be undone; deactivating the link keeps it instead.");
+            println!();
+            confirm_destructive(Confirm::Retype(&short_code), yes)?;
+            // A server may have started while the prompt waited
+            ensure_server_stopped(storage.as_ref()).await?;
+
+            let Some(report) = storage.hard_delete(&short_code, None).await? else {
+                println!("⚠ Short code '{}' was not deleted (not found)", short_code);
+                return Ok(());
+            };
+
+            println!(
+                "✓ Deleted {} ({} analytics, {} click history and {} history row(s))",
+                report.codes.join(", "),
+                report.analytics_rows,
+                report.click_history_rows,
+                report.url_history_rows
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Refuse to go on while any server holds a lease on the database, wherever
+/// it runs. A running server would keep redirecting a deleted link from its
+/// cache and could write buffered clicks onto a code created again later;
+/// `DELETE /api/admin/links/{code}` deletes through the server instead.
+async fn ensure_server_stopped(storage: &dyn Storage) -> Result<()> {
+    let leases = storage.active_server_leases().await?;
+    if leases.is_empty() {
+        return Ok(());
+    }
+    let servers = leases
+        .iter()
+        .map(|lease| format!("{} on {}", lease.instance_id, lease.host))
+        .collect::<Vec<_>>()
+        .join(", ");
+    anyhow::bail!(
+        "{} server(s) are running against this database ({}). Stop every Lynx instance \
+         first, or delete through DELETE /api/admin/links/{{code}} on a running server so \
+         it drops the link from its cache. A server that stopped without shutting down \
+         holds its lease for up to {} seconds.",
+        leases.len(),
+        servers,
+        lynx::storage::LEASE_TTL.as_secs()
+    )
+}
@@ -0,0 +1,82 @@
+//! Subcommands of the `lynx` binary, one module each, and the helpers they
+//! share.
+
+use anyhow::Result;
+use std::sync::Arc;
+
+use lynx::config::{Config, DatabaseBackend};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+#[cfg(feature = "sqlite")]
+use lynx::storage::SqliteStorage;
+use lynx::storage::{PoolSettings, Storage};
+
+pub(crate) mod admin;
+pub(crate) mod analytics;
+pub(crate) mod db;
+pub(crate) mod import;
+pub(crate) mod link;
+pub(crate) mod patch;
+pub(crate) mod server;
+pub(crate) mod user;
+
+/// A Unix timestamp in seconds as a UTC date and time
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Open the configured database for a CLI command and check its schema is
+/// current. The check is read-only; schema changes are left to the server
+/// and `lynx db migrate`.
+pub(crate) async fn open_verified_storage(config: &Config) -> Result<Arc<dyn Storage>> {
+    let database = &config.database;
+    let storage = open_storage(
+        &database.backend,
+        &database.url,
+        database.schema.as_deref(),
+        config,
+    )
+    .await?;
+    storage.verify_schema().await?;
+    Ok(storage)
+}
+
+/// Open the storage for a database URL with the pool settings of
+/// `config.database` and its `URL_NORMALIZE_STEPS`. `schema` only applies
+/// to Postgres.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub(crate) async fn open_storage(
+    backend: &DatabaseBackend,
+    url: &str,
+    schema: Option<&str>,
+    config: &Config,
+) -> Result<Arc<dyn Storage>> {
+    let database = &config.database;
+    Ok(match backend {
+        #[cfg(feature = "sqlite")]
+        DatabaseBackend::Sqlite => Arc::new(
+            SqliteStorage::new_with_pool_settings(
+                url,
+                database.max_connections,
+                PoolSettings::from_config(database),
+            )
+            .await?
+            .with_url_normalization(config.url_normalization.clone())
+            .with_hard_delete(database.allow_hard_delete),
+        ),
+        #[cfg(feature = "postgres")]
+        DatabaseBackend::Postgres => Arc::new(
+            PostgresStorage::new_in_schema(
+                url,
+                database.max_connections,
+                PoolSettings::from_config(database),
+                schema,
+            )
+            .await?
+            .with_url_normalization(config.url_normalization.clone())
+            .with_hard_delete(database.allow_hard_delete),
+        ),
+    })
+}
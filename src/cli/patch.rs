@@ -0,0 +1,126 @@
+//! `lynx patch`: repair the `created_by` of links.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::config::Config;
+use lynx::confirm::{confirm_destructive, Confirm};
+use lynx::storage::BulkPreview;
+
+use super::open_verified_storage;
+
+#[derive(Subcommand)]
+pub(crate) enum PatchCommands {
+    /// Patch the created_by field for a specific short link
+    Link {
+        /// User identifier to set as created_by
+        user_id: String,
+        /// Short code to patch
+        short_code: String,
+        /// Auth method the user signs in with (e.g. oauth, cloudflare), so the
+        /// link resolves to one entry in the users table; left unknown if omitted
+        #[arg(long)]
+        auth_method: Option<String>,
+    },
+    /// Fix all malformed created_by values (all-zero UUID or null)
+    FixAll {
+        /// User identifier to set for all malformed entries
+        user_id: String,
+        /// Print how many links would be patched, and some of their codes, without patching
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+pub(crate) async fn run(command: PatchCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    let storage = open_verified_storage(&config).await?;
+
+    match command {
+        PatchCommands::Link {
+            user_id,
+            short_code,
+            auth_method,
+        } => {
+            // First verify the short code exists
+            let url = storage.get_authoritative(&short_code).await?;
+            if url.is_none() {
+                println!("✗ Short code '{}' not found", short_code);
+                return Ok(());
+            }
+
+            let url = url.unwrap();
+            println!(
+                "Current created_by: {:?} (auth method {:?})",
+                url.created_by, url.created_by_auth_method
+            );
+
+            // Perform the patch
+            let updated = storage
+                .patch_created_by(&short_code, &user_id, auth_method.as_deref())
+                .await?;
+            if updated {
+                match &auth_method {
+                    Some(auth_method) => println!(
+                        "✓ Updated created_by for short code '{}' to '{}' ({})",
+                        short_code, user_id, auth_method
+                    ),
+                    None => println!(
+                        "✓ Updated created_by for short code '{}' to '{}'",
+                        short_code, user_id
+                    ),
+                }
+            } else {
+                println!("⚠ Short code '{}' was not updated (not found)", short_code);
+            }
+        }
+        PatchCommands::FixAll {
+            user_id,
+            dry_run,
+            yes,
+        } => {
+            if dry_run {
+                let preview = BulkPreview::malformed_created_by(storage.as_ref()).await?;
+                println!("{}", preview.render(&format!("patched to '{}'", user_id)));
+                return Ok(());
+            }
+
+            println!(
+                "⚠ This will update all malformed created_by values (NULL, empty string, or all-zero UUID)"
+            );
+            println!("   to user_id: '{}'", user_id);
+            println!();
+            confirm_destructive(Confirm::Retype(&user_id), yes)?;
+            println!("Checking for malformed entries...");
+
+            // Count malformed entries before patching, to report progress against
+            let pending = storage.count_malformed_created_by().await?;
+            let mut done = 0;
+            let count = storage
+                .patch_all_malformed_created_by_with_progress(&user_id, &mut |patched| {
+                    done += patched;
+                    print!("\r   Patched {} of {}", done, pending.max(done));
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                })
+                .await?;
+            if done > 0 {
+                println!();
+            }
+
+            if count > 0 {
+                println!(
+                    "✓ Successfully patched {} malformed created_by value(s) to '{}'",
+                    count, user_id
+                );
+            } else {
+                println!("✓ No malformed created_by values found. Database is clean!");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Visitor analytics for the server: the aggregator the redirect and API
+//! servers feed, and the task that flushes it to storage.
+
+use std::sync::Arc;
+use tracing::info;
+
+use lynx::alerts::OperatorAlerts;
+use lynx::analytics::AnalyticsAggregator;
+use lynx::api::server_info::RuntimeFacts;
+use lynx::config::Config;
+use lynx::storage::Storage;
+
+/// Start recording visitor analytics when `ANALYTICS_ENABLED` is set: the
+/// aggregator the servers feed and the task that flushes it to `storage`.
+#[cfg(feature = "analytics")]
+pub(super) async fn start_analytics(
+    config: &Config,
+    storage: &Arc<dyn Storage>,
+    operator_alerts: &Arc<OperatorAlerts>,
+    runtime_facts: &mut RuntimeFacts,
+) -> (
+    Option<Arc<AnalyticsAggregator>>,
+    Option<tokio::task::JoinHandle<()>>,
+) {
+    use lynx::analytics::{AnalyticsRollup, GeoIpService, ANALYTICS_ENABLED_SETTING};
+
+    if !config.analytics.enabled {
+        info!("📊 Analytics disabled");
+        return (None, None);
+    }
+
+    info!("📊 Analytics enabled");
+
+    let city_path = config.analytics.geoip_city_db_path.as_deref();
+    let asn_path = config.analytics.geoip_asn_db_path.as_deref();
+
+    let geoip = match GeoIpService::new(city_path, asn_path) {
+        Ok(service) => {
+            if let Some(path) = city_path {
+                info!("   - GeoIP City database loaded from: {}", path);
+            }
+            if let Some(path) = asn_path {
+                info!("   - GeoIP ASN database loaded from: {}", path);
+            }
+            runtime_facts.geoip_city_build_epoch = service.city_build_epoch();
+            runtime_facts.geoip_asn_build_epoch = service.asn_build_epoch();
+            if city_path.is_none() && asn_path.is_none() {
+                tracing::warn!(
+                    "   - No GeoIP databases configured. Analytics will have no geolocation data."
+                );
+            }
+            Some(Arc::new(service))
+        }
+        Err(e) => {
+            tracing::warn!(
+                "   - Failed to load GeoIP databases: {}. Analytics will have no geolocation data.",
+                e
+            );
+            None
+        }
+    };
+
+    let aggregator = Arc::new(
+        AnalyticsAggregator::new()
+            .with_flush_config(config.flush.clone())
+            .with_alerts(Arc::clone(operator_alerts))
+            .with_sampling(&config.analytics.sampling, lynx::clock::system_clock()),
+    );
+    if let Some(one_in) = config.analytics.sampling.one_in() {
+        info!(
+            "   - Sampling 1 in {} visit events while the queue is over {}% full",
+            one_in, config.analytics.sampling.high_water_percent
+        );
+    }
+
+    // An admin may have paused recording before the restart
+    match storage.get_setting(ANALYTICS_ENABLED_SETTING).await {
+        Ok(Some(value)) if value == "false" => {
+            aggregator.set_enabled(false);
+            runtime_facts.analytics_paused = true;
+            tracing::warn!(
+                "   - Recording paused by an admin; POST /api/admin/analytics/enabled to resume"
+            );
+        }
+        Ok(_) => {}
+        Err(error) => tracing::warn!(
+            %error,
+            "   - Failed to read the analytics switch; recording stays on"
+        ),
+    }
+
+    // Start optimized flush task with GeoIP service (if available)
+    let storage_clone = Arc::clone(storage);
+    let flush_handle = if let Some(ref geoip_svc) = geoip {
+        // OPTIMIZED PATH: Use deferred GeoIP lookups
+        let geoip_clone = Arc::clone(geoip_svc);
+        aggregator.start_flush_task_with_geoip(
+            config.analytics.flush_interval_secs,
+            geoip_clone,
+            move |entries| {
+                let storage = Arc::clone(&storage_clone);
+                Box::pin(async move {
+                    if entries.is_empty() {
+                        return Ok(());
+                    }
+
+                    // Convert entries to storage format
+                    let records: Vec<AnalyticsRollup> = entries
+                        .into_iter()
+                        .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                        .collect();
+
+                    // Batch insert to storage; codes missing from urls are
+                    // skipped rather than stored as orphans
+                    let skipped = storage.upsert_known_analytics_batch(records).await?;
+                    if skipped > 0 {
+                        tracing::warn!(
+                            skipped,
+                            "Skipped analytics rows for short codes that do not exist"
+                        );
+                    }
+                    tracing::debug!("Successfully flushed analytics to storage");
+                    Ok(())
+                })
+            },
+        )
+    } else {
+        // FALLBACK: No GeoIP service available, use basic flush task
+        aggregator.start_flush_task_with_storage(
+            config.analytics.flush_interval_secs,
+            move |entries| {
+                let storage = Arc::clone(&storage_clone);
+                Box::pin(async move {
+                    if entries.is_empty() {
+                        return Ok(());
+                    }
+
+                    // Convert entries to storage format
+                    let records: Vec<AnalyticsRollup> = entries
+                        .into_iter()
+                        .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                        .collect();
+
+                    // Batch insert to storage; codes missing from urls are
+                    // skipped rather than stored as orphans
+                    let skipped = storage.upsert_known_analytics_batch(records).await?;
+                    if skipped > 0 {
+                        tracing::warn!(
+                            skipped,
+                            "Skipped analytics rows for short codes that do not exist"
+                        );
+                    }
+                    tracing::debug!("Successfully flushed analytics to storage");
+                    Ok(())
+                })
+            },
+        )
+    };
+
+    info!(
+        "   - IP anonymization: {}",
+        if config.analytics.ip_anonymization {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    info!(
+        "   - Trusted proxy mode: {:?}",
+        config.analytics.trusted_proxy_mode
+    );
+    info!(
+        "   - Flush interval: {} seconds",
+        config.analytics.flush_interval_secs
+    );
+
+    (Some(aggregator), Some(flush_handle))
+}
+
+/// Builds without the `analytics` feature record nothing; loading the
+/// configuration already rejects `ANALYTICS_ENABLED`.
+#[cfg(not(feature = "analytics"))]
+pub(super) async fn start_analytics(
+    _config: &Config,
+    _storage: &Arc<dyn Storage>,
+    _operator_alerts: &Arc<OperatorAlerts>,
+    _runtime_facts: &mut RuntimeFacts,
+) -> (
+    Option<Arc<AnalyticsAggregator>>,
+    Option<tokio::task::JoinHandle<()>>,
+) {
+    info!("📊 Analytics not compiled into this build");
+    (None, None)
+}
@@ -0,0 +1,469 @@
+//! `lynx` with no subcommand: run the API and redirect servers until a
+//! shutdown signal, then flush everything buffered.
+
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use tracing::info;
+
+use lynx::api::server_info::{RuntimeFacts, ServerInfo};
+use lynx::auth::AuthService;
+use lynx::clock::SystemClock;
+use lynx::config::{redact_url, AuthMode, Config};
+use lynx::storage::{CachePolicy, CachedStorage, MirrorStorage, Storage};
+
+use super::open_storage;
+
+mod analytics;
+
+pub(crate) async fn run() -> Result<()> {
+    // Load configuration
+    let config = Arc::new(Config::from_env()?);
+    info!("Loaded configuration");
+
+    // Initialize cursor HMAC key
+    lynx::cursor::init_cursor_hmac_key(config.pagination.cursor_hmac_secret.as_deref());
+    info!("Cursor pagination HMAC key initialized");
+
+    // One client, pool and proxy setup for every call to other services
+    lynx::http::init(&config.outbound_http).context("invalid outbound HTTP proxy settings")?;
+
+    // Initialize storage
+    info!(
+        database = %redact_url(&config.database.url),
+        max_connections = config.database.max_connections,
+        "Using {} storage",
+        config.database.backend.display_name()
+    );
+    let base_storage = open_storage(
+        &config.database.backend,
+        &config.database.url,
+        config.database.schema.as_deref(),
+        &config,
+    )
+    .await?;
+
+    // Initialize database
+    info!("Initializing database...");
+    base_storage.ensure_schema().await?;
+    info!("Database initialized successfully");
+    info!(
+        acquire_timeout_secs = config.database.acquire_timeout_secs,
+        slow_acquire_threshold_ms = config.database.slow_acquire_threshold_ms,
+        "Database pool health probe started"
+    );
+    let pool_probe_handle = lynx::storage::spawn_pool_probe(Arc::clone(&base_storage));
+
+    let mut mirror_storage = None;
+    let base_storage: Arc<dyn Storage> = match &config.database_mirror {
+        Some(mirror) => {
+            info!(
+                mirror = %redact_url(&mirror.url),
+                backend = ?mirror.backend,
+                compare_sample_rate = mirror.compare_sample_rate,
+                "Mirroring writes to a secondary database; reads stay on the primary"
+            );
+            // A mirror never takes the primary down, not even on startup.
+            match open_storage(
+                &mirror.backend,
+                &mirror.url,
+                mirror.schema.as_deref(),
+                &config,
+            )
+            .await
+            {
+                Ok(secondary) => {
+                    let mirror =
+                        MirrorStorage::new(base_storage, secondary, mirror.compare_sample_rate);
+                    // Creates the secondary's tables; the primary's are in place.
+                    mirror.ensure_schema().await?;
+                    let mirror = Arc::new(mirror);
+                    mirror_storage = Some(Arc::clone(&mirror));
+                    mirror
+                }
+                Err(error) => {
+                    tracing::error!(%error, "Could not open the mirror database; running without it");
+                    base_storage
+                }
+            }
+        }
+        None => base_storage,
+    };
+
+    // Wrap with cached storage for performance
+    let cache_capacity = match config.cache.max_bytes {
+        Some(max_bytes) => format!("about {} bytes", max_bytes),
+        None => format!("{} entries", config.cache.max_entries),
+    };
+    info!(
+        "Initializing cache with max {}, {} second DB flush interval, {} ms actor flush interval, and {} actor buffer size",
+        cache_capacity,
+        config.cache.flush_interval_secs,
+        config.cache.actor_flush_interval_ms,
+        config.cache.actor_buffer_size
+    );
+    match config.cache.negative_max_entries {
+        None => info!(
+            "Read cache eviction policy: {:?}; missing codes share the main cache",
+            config.cache.eviction_policy
+        ),
+        Some(0) => info!(
+            "Read cache eviction policy: {:?}; missing codes are not cached",
+            config.cache.eviction_policy
+        ),
+        Some(max) => info!(
+            "Read cache eviction policy: {:?}; missing codes capped at {} entries",
+            config.cache.eviction_policy, max
+        ),
+    }
+    if let Some(ttl) = config.cache.entry_ttl_secs {
+        info!(
+            "Cached lookups are reloaded from the database after {} seconds",
+            ttl
+        );
+    }
+    if let Some(max_age) = config.cache.stale_max_age_secs {
+        info!(
+            "Redirects fall back to links loaded within {} seconds while the database is unreachable",
+            max_age
+        );
+    }
+    info!(
+        "Flush scheduling: ±{}% jitter, minimum {} pending entries, at most {} deferred intervals",
+        config.flush.jitter_percent, config.flush.min_pending, config.flush.max_deferred_intervals
+    );
+    if config.alerts.webhook_url.is_some() {
+        info!("Operator alerts are POSTed to the configured webhook");
+    } else {
+        info!(
+            "Operator alerts are logged under the {} target",
+            lynx::alerts::ALERT_LOG_TARGET
+        );
+    }
+    let (operator_alerts, operator_alerts_handle) =
+        lynx::alerts::OperatorAlerts::spawn(config.alerts.clone(), lynx::clock::system_clock());
+
+    let cached_storage = Arc::new(CachedStorage::new_with_alerts(
+        base_storage,
+        CachePolicy::from_config(&config.cache),
+        config.cache.flush_interval_secs,
+        config.cache.actor_buffer_size,
+        config.cache.actor_flush_interval_ms,
+        config.flush.clone(),
+        Some(Arc::clone(&operator_alerts)),
+    ));
+    let storage: Arc<dyn Storage> = Arc::clone(&cached_storage) as Arc<dyn Storage>;
+
+    info!(
+        "Code reservations last {} days; expired ones are swept every {} seconds",
+        config.reservations.ttl_days, config.reservations.sweep_interval_secs
+    );
+    let reservation_sweep_handle = lynx::storage::spawn_reservation_sweep(
+        Arc::clone(&storage),
+        lynx::clock::system_clock(),
+        std::time::Duration::from_secs(config.reservations.sweep_interval_secs),
+    );
+
+    let instance_stats_handle = lynx::storage::spawn_instance_stats_recorder(
+        Arc::clone(&storage),
+        lynx::clock::system_clock(),
+    );
+
+    // Held until shutdown, so maintenance commands can tell a server is up
+    let server_lease = lynx::storage::ServerLease::starting(&SystemClock);
+    info!(
+        "🪪 Holding server lease {} in the database",
+        server_lease.instance_id
+    );
+    let server_lease_id = server_lease.instance_id.clone();
+    let server_lease_handle = lynx::storage::spawn_server_lease(
+        Arc::clone(&storage),
+        lynx::clock::system_clock(),
+        server_lease,
+    );
+
+    let rollup_after_days = config.analytics.daily_rollup_after_days;
+    let daily_rollup_handle = (config.analytics.enabled && rollup_after_days > 0).then(|| {
+        info!(
+            "Analytics older than {} days are rolled up into daily totals every night",
+            rollup_after_days
+        );
+        lynx::analytics::daily::spawn_daily_rollup(
+            Arc::clone(&storage),
+            lynx::clock::system_clock(),
+            rollup_after_days,
+        )
+    });
+
+    // Initialize auth service
+    let auth_config = config.auth.clone();
+    let auth_service = Arc::new(AuthService::new(auth_config.clone()).await?);
+
+    match auth_config.mode {
+        AuthMode::None => {
+            info!("🔓 Authentication is disabled - all API requests are allowed as admin");
+        }
+        AuthMode::Oauth => {
+            if let Some(oauth) = auth_config.oauth.as_ref() {
+                info!(
+                    "🔐 OAuth authentication enabled (issuer: {}, audience: {})",
+                    oauth.issuer_url, oauth.audience
+                );
+            } else {
+                info!("🔐 OAuth authentication enabled");
+            }
+        }
+        AuthMode::Cloudflare => {
+            if let Some(cf) = auth_config.cloudflare.as_ref() {
+                info!(
+                    "☁️  Cloudflare Zero Trust authentication enabled (team: {}, audience: {})",
+                    cf.team_domain, cf.audience
+                );
+            } else {
+                info!("☁️  Cloudflare Zero Trust authentication enabled");
+            }
+        }
+    }
+
+    // Create routers
+    info!(
+        "🔗 Redirect base URL advertised to clients: {}",
+        config.short_url_base()
+    );
+
+    // Check if timing headers should be enabled (disabled by default for max performance)
+    let enable_timing_headers = std::env::var("ENABLE_TIMING_HEADERS")
+        .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false);
+    let mut runtime_facts = RuntimeFacts {
+        timing_headers: enable_timing_headers,
+        ..RuntimeFacts::default()
+    };
+
+    // Initialize analytics if enabled
+    let (analytics_aggregator, analytics_flush_handle) =
+        analytics::start_analytics(&config, &storage, &operator_alerts, &mut runtime_facts).await;
+
+    let redirect_stats = lynx::redirect::RedirectStats::from_config(
+        &config.redirect_stats,
+        config.short_code_max_length,
+    )
+    .map(Arc::new);
+    if let Some(stats) = &redirect_stats {
+        info!(
+            "📈 Redirect statistics enabled (tracking top {} missing codes)",
+            config.redirect_stats.top_missing_capacity
+        );
+        lynx::redirect::RedirectStats::spawn_latency_rotation(stats);
+    }
+
+    let live_visits = lynx::redirect::LiveVisits::from_config(&config.live_visits).map(Arc::new);
+    if live_visits.is_some() {
+        info!(
+            "📡 Live visit streams enabled (max {} per user)",
+            config.live_visits.max_connections_per_user
+        );
+    }
+
+    let server_info = Arc::new(ServerInfo::new(&config, &runtime_facts));
+    server_info.log();
+
+    let api_usage = Arc::new(lynx::auth::usage::ApiUsageTracker::new(
+        Arc::clone(&storage),
+        lynx::clock::system_clock(),
+    ));
+    let api_usage_handle = api_usage.spawn_flush_task(lynx::auth::usage::USAGE_FLUSH_INTERVAL);
+
+    let api_router = lynx::api::create_api_router_with_usage(
+        Arc::clone(&storage),
+        auth_service,
+        Arc::clone(&config),
+        analytics_aggregator.clone(),
+        redirect_stats.clone(),
+        live_visits.clone(),
+        server_info,
+        lynx::challenge::from_config(
+            &config.creation_challenge,
+            Arc::clone(&storage),
+            lynx::clock::system_clock(),
+        ),
+        Some(Arc::clone(&api_usage)),
+    );
+
+    // Convert RedirectMode to StatusCode for runtime performance
+    let redirect_status: axum::http::StatusCode = config.redirect_status.into();
+    info!("🔀 Redirect status code: {}", redirect_status.as_u16());
+
+    let redirect_analytics = analytics_aggregator.as_ref().and_then(|aggregator| {
+        lynx::redirect::RedirectAnalytics::from_enabled(
+            config.analytics.clone(),
+            Arc::clone(aggregator),
+        )
+    });
+    let code_normalizer = lynx::redirect::CodeNormalizer::from_config(&config.code_normalization);
+    if code_normalizer.is_some() {
+        info!(
+            "🧹 Retrying missed codes after trimming whitespace and trailing '{}'",
+            config.code_normalization.trailing_chars
+        );
+    }
+
+    let self_redirects = lynx::redirect::SelfRedirectGuard::from_config(&config.destination);
+    if !config.destination.self_hosts.is_empty() {
+        info!(
+            "🔁 Resolving links to {} internally (self-referencing destinations {})",
+            config.destination.self_hosts.join(", "),
+            if config.destination.allow_self_redirects {
+                "allowed"
+            } else {
+                "rejected"
+            }
+        );
+    }
+
+    let link_info = lynx::redirect::LinkInfoGate::from_config(&config.link_info)?;
+    if link_info.is_some() {
+        info!(
+            "🔎 Serving link info at /{{code}}/info.json to {} allowlisted ranges{}",
+            config.link_info.allowed_ips.len(),
+            if config.link_info.token.is_some() {
+                " and token holders"
+            } else {
+                ""
+            }
+        );
+    }
+
+    let countdown = lynx::redirect::CountdownPage::from_config(&config.redirect_interstitial)?;
+    if !config.redirect_interstitial.domains.is_empty() {
+        info!(
+            "⏳ Countdown page before redirects to {}",
+            config.redirect_interstitial.domains.join(", ")
+        );
+    }
+    let redirect_router = lynx::redirect::create_redirect_router_with_interstitial(
+        Arc::clone(&cached_storage),
+        redirect_analytics,
+        enable_timing_headers,
+        redirect_status,
+        redirect_stats,
+        live_visits,
+        lynx::redirect::RedirectLookup {
+            normalizer: code_normalizer,
+            self_redirects,
+        },
+        lynx::redirect::RootLanding::from_config(&config.redirect_landing),
+        link_info,
+        countdown,
+    );
+    let redirect_router =
+        lynx::redirect::mount_under_prefix(redirect_router, config.redirect_path_prefix.as_deref());
+    if let Some(prefix) = &config.redirect_path_prefix {
+        info!("🔗 Redirect routes mounted under {}", prefix);
+    }
+
+    // Log frontend configuration
+    if !config.frontend.enabled {
+        info!("🎨 Frontend disabled; the API server serves /api only");
+    } else if let Some(ref static_dir) = config.frontend.static_dir {
+        info!("🎨 Serving frontend from directory: {}", static_dir);
+    } else if lynx::api::static_files::has_embedded_frontend() {
+        info!("🎨 Serving embedded frontend");
+    } else {
+        info!("🎨 No frontend: built without embedded assets and FRONTEND_STATIC_DIR is unset");
+    }
+
+    // Start API server
+    let api_addr = format!("{}:{}", config.api_server.host, config.api_server.port);
+    let api_listener = tokio::net::TcpListener::bind(&api_addr).await?;
+    info!("🚀 API server listening on http://{}", api_addr);
+    info!(
+        "   - API endpoints available at http://{}/api/...",
+        api_addr
+    );
+    info!("   - Frontend UI available at http://{}/", api_addr);
+
+    // Start redirect server
+    let redirect_addr = format!(
+        "{}:{}",
+        config.redirect_server.host, config.redirect_server.port
+    );
+    let redirect_listener = tokio::net::TcpListener::bind(&redirect_addr).await?;
+    info!("🚀 Redirect server listening on http://{}", redirect_addr);
+
+    // Set up graceful shutdown signal, observed by both servers
+    let (shutdown_trigger, shutdown_signal) = lynx::shutdown::channel();
+
+    // Spawn signal handler for both SIGINT and SIGTERM
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut sigint =
+                signal(SignalKind::interrupt()).expect("Failed to install SIGINT signal handler");
+            let mut sigterm =
+                signal(SignalKind::terminate()).expect("Failed to install SIGTERM signal handler");
+
+            tokio::select! {
+                _ = sigint.recv() => {
+                    info!("Received shutdown signal (SIGINT), initiating graceful shutdown...");
+                }
+                _ = sigterm.recv() => {
+                    info!("Received shutdown signal (SIGTERM), initiating graceful shutdown...");
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Failed to install CTRL+C signal handler");
+            info!("Received shutdown signal (SIGINT), initiating graceful shutdown...");
+        }
+
+        shutdown_trigger.trigger();
+    });
+
+    // Run both servers concurrently. On the signal each stops accepting
+    // connections and drains its in-flight requests; flushing waits for both.
+    let (api_result, redirect_result) = tokio::join!(
+        lynx::shutdown::serve(api_listener, api_router, shutdown_signal.clone()),
+        lynx::shutdown::serve(redirect_listener, redirect_router, shutdown_signal),
+    );
+
+    pool_probe_handle.abort();
+    reservation_sweep_handle.abort();
+    instance_stats_handle.abort();
+    if let Some(handle) = daily_rollup_handle {
+        handle.abort();
+    }
+    api_usage_handle.abort();
+
+    // Flush cached data on shutdown
+    info!("Flushing cached data before shutdown...");
+    if let Some(aggregator) = analytics_aggregator.as_ref() {
+        aggregator.shutdown().await;
+    }
+    if let Some(flush_handle) = analytics_flush_handle {
+        if let Err(error) = flush_handle.await {
+            tracing::error!(%error, "analytics flush task panicked during shutdown");
+        }
+    }
+    api_usage.flush().await;
+    cached_storage.shutdown().await;
+    if let Some(mirror) = mirror_storage {
+        info!("Applying queued writes to the mirror database...");
+        mirror.drain().await;
+    }
+    operator_alerts_handle.abort();
+    server_lease_handle.abort();
+    if let Err(error) = storage.release_server_lease(&server_lease_id).await {
+        tracing::warn!(%error, "failed to release the server lease; it lapses on its own");
+    }
+    info!("Shutdown complete");
+
+    api_result?;
+    redirect_result?;
+    Ok(())
+}
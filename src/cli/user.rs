@@ -0,0 +1,278 @@
+//! `lynx user`: list users and their links, and deactivate or reactivate
+//! everything a user owns.
+
+use anyhow::Result;
+use clap::Subcommand;
+
+use lynx::clock::{Clock, SystemClock};
+use lynx::config::Config;
+use lynx::confirm::{confirm_destructive, Confirm};
+use lynx::paging::{fetch_page, is_deep_page};
+use lynx::storage::BulkPreview;
+
+use super::{format_timestamp, open_verified_storage};
+
+#[derive(Subcommand)]
+pub(crate) enum UserCommands {
+    /// List all users
+    List {
+        /// Number of results per page (default: 50)
+        #[arg(short, long, default_value_t = 50)]
+        limit: i64,
+        /// Page number (starts from 1)
+        #[arg(short, long, default_value_t = 1)]
+        page: i64,
+        /// Only list users not seen for this many days
+        #[arg(long)]
+        inactive_days: Option<i64>,
+    },
+    /// List all admin users
+    ListAdmins,
+    /// List all links created by a specific user
+    Links {
+        /// User ID to list links for
+        user_id: String,
+        /// Number of results per page (default: 50)
+        #[arg(short, long, default_value_t = 50)]
+        limit: i64,
+        /// Page number (starts from 1)
+        #[arg(short, long, default_value_t = 1)]
+        page: i64,
+    },
+    /// Deactivate all links created by a user
+    DeactivateLinks {
+        /// User ID whose links to deactivate
+        user_id: String,
+        /// Print how many links would be deactivated, and some of their codes, without deactivating
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Reactivate all links created by a user
+    ReactivateLinks {
+        /// User ID whose links to reactivate
+        user_id: String,
+        /// Print how many links would be reactivated, and some of their codes, without reactivating
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt (required when stdin is not a terminal)
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+pub(crate) async fn run(command: UserCommands) -> Result<()> {
+    let config = Config::from_env()?;
+
+    let storage = open_verified_storage(&config).await?;
+
+    match command {
+        UserCommands::List {
+            limit,
+            page,
+            inactive_days,
+        } => {
+            if page < 1 {
+                println!("✗ Page number must be >= 1");
+                return Ok(());
+            }
+            if limit < 1 {
+                println!("✗ Limit must be >= 1");
+                return Ok(());
+            }
+            if inactive_days.is_some_and(|days| days < 0) {
+                println!("✗ Inactive days must be >= 0");
+                return Ok(());
+            }
+
+            let inactive_since = inactive_days
+                .map(|days| SystemClock.now_epoch_secs() - days.saturating_mul(86_400));
+            warn_deep_page(page, limit);
+            let users = fetch_page(
+                page,
+                limit,
+                |limit, cursor| storage.list_all_users(limit, cursor, inactive_since),
+                |user| {
+                    (
+                        user.created_at,
+                        user.user_id.clone(),
+                        user.auth_method.clone(),
+                    )
+                },
+            )
+            .await?;
+
+            if users.is_empty() {
+                if page == 1 {
+                    println!("No users found.");
+                } else {
+                    println!("No more users found (page {}).", page);
+                }
+            } else {
+                println!("Users (page {}, showing {} results):", page, users.len());
+                println!(
+                    "{:<40} {:<15} {:<40} {:<20} Last Seen",
+                    "User ID", "Auth Method", "Email", "Created At"
+                );
+                println!("{}", "-".repeat(140));
+                for user in users {
+                    println!(
+                        "{:<40} {:<15} {:<40} {:<20} {}",
+                        user.user_id,
+                        user.auth_method,
+                        user.email.as_deref().unwrap_or("N/A"),
+                        format_timestamp(user.created_at),
+                        format_timestamp(user.last_seen_at)
+                    );
+                }
+                println!();
+                println!("To see more results, use: --page {}", page + 1);
+            }
+        }
+        UserCommands::ListAdmins => {
+            let admins = storage.list_manual_admins().await?;
+            if admins.is_empty() {
+                println!("No manually promoted admins found.");
+            } else {
+                println!("Manually promoted admins:");
+                println!("{:<40} {:<15} Email", "User ID", "Auth Method");
+                println!("{}", "-".repeat(80));
+                for (user_id, auth_method, email) in admins {
+                    println!("{:<40} {:<15} {}", user_id, auth_method, email);
+                }
+            }
+        }
+        UserCommands::Links {
+            user_id,
+            limit,
+            page,
+        } => {
+            if page < 1 {
+                println!("✗ Page number must be >= 1");
+                return Ok(());
+            }
+            if limit < 1 {
+                println!("✗ Limit must be >= 1");
+                return Ok(());
+            }
+
+            warn_deep_page(page, limit);
+            let links = fetch_page(
+                page,
+                limit,
+                |limit, cursor| storage.list_user_links(&user_id, limit, cursor),
+                |link| (link.created_at, link.id),
+            )
+            .await?;
+
+            if links.is_empty() {
+                if page == 1 {
+                    println!("No links found for user '{}'.", user_id);
+                } else {
+                    println!(
+                        "No more links found for user '{}' (page {}).",
+                        user_id, page
+                    );
+                }
+            } else {
+                println!(
+                    "Links for user '{}' (page {}, showing {} results):",
+                    user_id,
+                    page,
+                    links.len()
+                );
+                println!(
+                    "{:<15} {:<60} {:<10} {:<10} Created At",
+                    "Short Code", "Original URL", "Clicks", "Active"
+                );
+                println!("{}", "-".repeat(120));
+                for link in links {
+                    let url_display = if link.original_url.len() > 57 {
+                        format!("{}...", &link.original_url[..57])
+                    } else {
+                        link.original_url.clone()
+                    };
+                    let active_str = if link.is_active { "✓" } else { "✗" };
+                    let datetime = chrono::DateTime::from_timestamp_millis(link.created_at)
+                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| link.created_at.to_string());
+                    println!(
+                        "{:<15} {:<60} {:<10} {:<10} {}",
+                        link.short_code, url_display, link.clicks, active_str, datetime
+                    );
+                }
+                println!();
+                println!("To see more results, use: --page {}", page + 1);
+            }
+        }
+        UserCommands::DeactivateLinks {
+            user_id,
+            dry_run,
+            yes,
+        } => {
+            if dry_run {
+                let preview = BulkPreview::user_links(storage.as_ref(), &user_id, true).await?;
+                println!("{}", preview.render("deactivated"));
+                return Ok(());
+            }
+
+            println!(
+                "⚠ This will mark all links created by user '{}' as inactive.",
+                user_id
+            );
+            println!("   Note: Cached links will remain active until instance restart.");
+            println!();
+            confirm_destructive(Confirm::Retype(&user_id), yes)?;
+
+            let count = storage.bulk_deactivate_user_links(&user_id).await?;
+
+            if count > 0 {
+                println!("✓ Deactivated {} link(s) for user '{}'", count, user_id);
+            } else {
+                println!("⚠ No active links found for user '{}'", user_id);
+            }
+        }
+        UserCommands::ReactivateLinks {
+            user_id,
+            dry_run,
+            yes,
+        } => {
+            if dry_run {
+                let preview = BulkPreview::user_links(storage.as_ref(), &user_id, false).await?;
+                println!("{}", preview.render("reactivated"));
+                return Ok(());
+            }
+
+            println!(
+                "⚠ This will mark all links created by user '{}' as active.",
+                user_id
+            );
+            println!("   Note: Links will become active in cache after instance restart.");
+            println!();
+            confirm_destructive(Confirm::Retype(&user_id), yes)?;
+
+            let count = storage.bulk_reactivate_user_links(&user_id).await?;
+
+            if count > 0 {
+                println!("✓ Reactivated {} link(s) for user '{}'", count, user_id);
+            } else {
+                println!("⚠ No inactive links found for user '{}'", user_id);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Page numbers are walked to by cursor, so far pages read every row before them
+fn warn_deep_page(page: i64, limit: i64) {
+    if is_deep_page(page, limit) {
+        println!(
+            "⚠ Page {} starts after {} rows, all of which are read to reach it. Use a larger --limit to page faster.",
+            page,
+            (page - 1).saturating_mul(limit)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Wall-clock time for timestamps that get stored or compared.
+//!
+//! Storage backends and handlers read the time through a [`Clock`] instead of
+//! calling `SystemTime::now` themselves. Production uses [`SystemClock`];
+//! tests hand in a [`FakeClock`] so creation order, expiry and analytics
+//! buckets do not depend on how fast the test runs.
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_epoch_ms(&self) -> i64;
+
+    /// Whole seconds since the Unix epoch.
+    fn now_epoch_secs(&self) -> i64 {
+        self.now_epoch_ms().div_euclid(1000)
+    }
+}
+
+/// The system's wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_epoch_ms(&self) -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+}
+
+/// The clock used when none is injected.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that stands still until told to move.
+#[derive(Debug)]
+pub struct FakeClock {
+    now_ms: AtomicI64,
+}
+
+impl FakeClock {
+    /// A clock reading `epoch_ms` milliseconds since the Unix epoch.
+    pub fn at_epoch_ms(epoch_ms: i64) -> Self {
+        Self {
+            now_ms: AtomicI64::new(epoch_ms),
+        }
+    }
+
+    /// A clock starting at the current wall-clock time, for tests that mix
+    /// its timestamps with real ones.
+    pub fn starting_now() -> Self {
+        Self::at_epoch_ms(SystemClock.now_epoch_ms())
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        let by = i64::try_from(by.as_millis()).unwrap_or(i64::MAX);
+        self.now_ms.fetch_add(by, Ordering::SeqCst);
+    }
+
+    /// Set the clock to `epoch_ms`.
+    pub fn set_epoch_ms(&self, epoch_ms: i64) {
+        self.now_ms.store(epoch_ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now_epoch_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_clock_moves_only_when_told() {
+        let clock = FakeClock::at_epoch_ms(1_700_000_000_999);
+        assert_eq!(clock.now_epoch_ms(), 1_700_000_000_999);
+        assert_eq!(clock.now_epoch_secs(), 1_700_000_000);
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(clock.now_epoch_secs(), 1_700_000_001);
+
+        clock.set_epoch_ms(-1);
+        assert_eq!(clock.now_epoch_secs(), -1);
+    }
+}
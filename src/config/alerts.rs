@@ -0,0 +1,97 @@
+//! Operator alerts (see `crate::alerts`).
+
+use super::REDACTED;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Operator alerts raised when buffers saturate or flushes keep failing.
+///
+/// A threshold of 0 disables that condition.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    /// URL that receives alerts as JSON POSTs; unset logs them at error level
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Analytics events dropped within one minute before alerting
+    #[serde(default = "AlertConfig::default_dropped_events_per_minute")]
+    pub dropped_events_per_minute: u64,
+    /// Percentage of a click or analytics queue in use before alerting
+    #[serde(default = "AlertConfig::default_channel_depth_percent")]
+    pub channel_depth_percent: u8,
+    /// Consecutive failed click or analytics flushes before alerting
+    #[serde(default = "AlertConfig::default_flush_failure_streak")]
+    pub flush_failure_streak: u32,
+    /// Seconds before the same condition may alert again
+    #[serde(default = "AlertConfig::default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+/// Webhook URLs often embed a token in their path, so only whether one is set
+/// is shown.
+impl fmt::Debug for AlertConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlertConfig")
+            .field("webhook_url", &self.webhook_url.as_ref().map(|_| REDACTED))
+            .field("dropped_events_per_minute", &self.dropped_events_per_minute)
+            .field("channel_depth_percent", &self.channel_depth_percent)
+            .field("flush_failure_streak", &self.flush_failure_streak)
+            .field("cooldown_secs", &self.cooldown_secs)
+            .finish()
+    }
+}
+
+impl AlertConfig {
+    pub const fn default_dropped_events_per_minute() -> u64 {
+        100
+    }
+
+    pub const fn default_channel_depth_percent() -> u8 {
+        90
+    }
+
+    pub const fn default_flush_failure_streak() -> u32 {
+        3
+    }
+
+    pub const fn default_cooldown_secs() -> u64 {
+        900
+    }
+
+    /// Read from the `ALERT_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            webhook_url: std::env::var("ALERT_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            dropped_events_per_minute: std::env::var("ALERT_DROPPED_EVENTS_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_dropped_events_per_minute),
+            channel_depth_percent: std::env::var("ALERT_CHANNEL_DEPTH_PERCENT")
+                .ok()
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or_else(Self::default_channel_depth_percent)
+                .min(100),
+            flush_failure_streak: std::env::var("ALERT_FLUSH_FAILURE_STREAK")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_flush_failure_streak),
+            cooldown_secs: std::env::var("ALERT_COOLDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_cooldown_secs),
+        }
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            dropped_events_per_minute: Self::default_dropped_events_per_minute(),
+            channel_depth_percent: Self::default_channel_depth_percent(),
+            flush_failure_streak: Self::default_flush_failure_streak(),
+            cooldown_secs: Self::default_cooldown_secs(),
+        }
+    }
+}
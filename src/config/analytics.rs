@@ -0,0 +1,202 @@
+//! Visitor analytics and the sampling of visit events under load.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsConfig {
+    /// Enable visitor IP analytics
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Path to MaxMind GeoLite2-City or GeoIP2-City database file (.mmdb)
+    pub geoip_city_db_path: Option<String>,
+
+    /// Path to MaxMind GeoLite2-ASN database file (.mmdb)
+    pub geoip_asn_db_path: Option<String>,
+
+    /// Enable IP address anonymization (truncate to network prefix)
+    #[serde(default)]
+    pub ip_anonymization: bool,
+
+    /// Trusted proxy mode for client IP extraction
+    #[serde(default)]
+    pub trusted_proxy_mode: TrustedProxyMode,
+
+    /// List of trusted proxy CIDR ranges (e.g., ["10.0.0.0/8", "172.16.0.0/12"])
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+
+    /// Number of trusted proxies to skip from the right in X-Forwarded-For
+    pub num_trusted_proxies: Option<usize>,
+
+    /// Flush interval for analytics aggregator (seconds)
+    #[serde(default = "AnalyticsConfig::default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+
+    /// Days after which whole days are rolled up into `analytics_daily` by
+    /// the nightly task; 0 turns the task off
+    #[serde(default = "AnalyticsConfig::default_daily_rollup_after_days")]
+    pub daily_rollup_after_days: i64,
+
+    /// Sampling of visit events while the event queue is backed up
+    #[serde(default)]
+    pub sampling: AnalyticsSamplingConfig,
+}
+
+/// Sampling of visit events under load; see `crate::analytics::sampling`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AnalyticsSamplingConfig {
+    /// Share of visit events recorded while sampling, above 0 and below 1.
+    /// Unset, every event is queued however far behind the queue is.
+    #[serde(default)]
+    pub floor_rate: Option<f64>,
+    /// Queue fill, in percent of its capacity, at which sampling starts
+    #[serde(default = "AnalyticsSamplingConfig::default_high_water_percent")]
+    pub high_water_percent: u8,
+    /// Queue fill at which sampling stops again; below `high_water_percent`
+    /// so a queue hovering at the mark does not switch back and forth
+    #[serde(default = "AnalyticsSamplingConfig::default_low_water_percent")]
+    pub low_water_percent: u8,
+}
+
+impl Default for AnalyticsSamplingConfig {
+    fn default() -> Self {
+        Self {
+            floor_rate: None,
+            high_water_percent: Self::default_high_water_percent(),
+            low_water_percent: Self::default_low_water_percent(),
+        }
+    }
+}
+
+impl AnalyticsSamplingConfig {
+    pub const fn default_high_water_percent() -> u8 {
+        80
+    }
+
+    pub const fn default_low_water_percent() -> u8 {
+        50
+    }
+
+    /// Sampling keeps one event in this many, or `None` when it is off.
+    pub fn one_in(&self) -> Option<u32> {
+        self.floor_rate
+            .filter(|rate| *rate > 0.0 && *rate < 1.0)
+            .map(|rate| (1.0 / rate).round() as u32)
+            .filter(|one_in| *one_in > 1)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[derive(Default)]
+pub enum TrustedProxyMode {
+    /// No proxy trust - use socket remote address only
+    #[default]
+    None,
+    /// Trust standard headers (Forwarded, X-Forwarded-For) with trust validation
+    Standard,
+    /// Trust Cloudflare-specific header (CF-Connecting-IP)
+    Cloudflare,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            geoip_city_db_path: None,
+            geoip_asn_db_path: None,
+            ip_anonymization: false,
+            trusted_proxy_mode: TrustedProxyMode::None,
+            trusted_proxies: Vec::new(),
+            num_trusted_proxies: None,
+            flush_interval_secs: Self::default_flush_interval_secs(),
+            daily_rollup_after_days: Self::default_daily_rollup_after_days(),
+            sampling: AnalyticsSamplingConfig::default(),
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    const fn default_flush_interval_secs() -> u64 {
+        60 // 1 minute
+    }
+
+    pub const fn default_daily_rollup_after_days() -> i64 {
+        30
+    }
+
+    /// Read from the `ANALYTICS_*` variables; with `ANALYTICS_ENABLED` unset
+    /// the rest are ignored.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let enabled = std::env::var("ANALYTICS_ENABLED")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+        #[cfg(not(feature = "analytics"))]
+        if enabled {
+            anyhow::bail!(
+                "ANALYTICS_ENABLED needs a build with analytics; rebuild with `--features analytics`"
+            );
+        }
+        if !enabled {
+            return Ok(Self::default());
+        }
+
+        let trusted_proxy_mode = std::env::var("ANALYTICS_TRUSTED_PROXY_MODE")
+            .unwrap_or_else(|_| "none".to_string())
+            .to_lowercase();
+        let trusted_proxy_mode = match trusted_proxy_mode.as_str() {
+            "cloudflare" => TrustedProxyMode::Cloudflare,
+            "standard" => TrustedProxyMode::Standard,
+            _ => TrustedProxyMode::None,
+        };
+
+        let percent = |name: &str, default: u8| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u8>().ok())
+                .filter(|percent| (1..=100).contains(percent))
+                .unwrap_or(default)
+        };
+        let sampling = AnalyticsSamplingConfig {
+            floor_rate: std::env::var("ANALYTICS_SAMPLING_FLOOR_RATE")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok()),
+            high_water_percent: percent(
+                "ANALYTICS_SAMPLING_HIGH_WATER_PERCENT",
+                AnalyticsSamplingConfig::default_high_water_percent(),
+            ),
+            low_water_percent: percent(
+                "ANALYTICS_SAMPLING_LOW_WATER_PERCENT",
+                AnalyticsSamplingConfig::default_low_water_percent(),
+            ),
+        };
+
+        Ok(Self {
+            enabled: true,
+            geoip_city_db_path: std::env::var("ANALYTICS_GEOIP_CITY_DB_PATH").ok(),
+            geoip_asn_db_path: std::env::var("ANALYTICS_GEOIP_ASN_DB_PATH").ok(),
+            ip_anonymization: std::env::var("ANALYTICS_IP_ANONYMIZATION")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            trusted_proxy_mode,
+            trusted_proxies: std::env::var("ANALYTICS_TRUSTED_PROXIES")
+                .ok()
+                .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            num_trusted_proxies: std::env::var("ANALYTICS_NUM_TRUSTED_PROXIES")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok()),
+            flush_interval_secs: std::env::var("ANALYTICS_FLUSH_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_flush_interval_secs),
+            daily_rollup_after_days: std::env::var("ANALYTICS_DAILY_ROLLUP_AFTER_DAYS")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|days| *days >= 0)
+                .unwrap_or_else(Self::default_daily_rollup_after_days),
+            sampling,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+//! API authentication through OAuth or Cloudflare Access.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    None,
+    Oauth,
+    Cloudflare,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub mode: AuthMode,
+    #[serde(default)]
+    pub oauth: Option<OAuthConfig>,
+    #[serde(default)]
+    pub cloudflare: Option<CloudflareConfig>,
+}
+
+impl AuthConfig {
+    /// Read from `AUTH_MODE` (or `DISABLE_AUTH`) and the variables of the
+    /// chosen provider, which must all be set.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let disable_auth = std::env::var("DISABLE_AUTH")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+
+        let mut mode = std::env::var("AUTH_MODE")
+            .unwrap_or_else(|_| "none".to_string())
+            .to_lowercase();
+
+        if disable_auth {
+            mode = "none".to_string();
+        }
+
+        let mode = match mode.as_str() {
+            "none" => AuthMode::None,
+            "oauth" => AuthMode::Oauth,
+            "cloudflare" => AuthMode::Cloudflare,
+            other => {
+                tracing::warn!(
+                    "Unknown AUTH_MODE '{other}', falling back to 'none'. Supported values: none, oauth, cloudflare"
+                );
+                AuthMode::None
+            }
+        };
+
+        let allow_anonymous_subject = std::env::var("AUTH_ALLOW_ANONYMOUS_SUBJECT")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+
+        let oauth = match mode {
+            AuthMode::Oauth => Some(OAuthConfig::from_env(allow_anonymous_subject)?),
+            _ => None,
+        };
+        let cloudflare = match mode {
+            AuthMode::Cloudflare => Some(CloudflareConfig::from_env(allow_anonymous_subject)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            mode,
+            oauth,
+            cloudflare,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    pub issuer_url: String,
+    pub audience: String,
+    pub client_id: String,
+    #[serde(default = "OAuthConfig::default_scopes")]
+    pub scopes: String,
+    pub redirect_uri: String,
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    #[serde(default = "OAuthConfig::default_cache_ttl_secs")]
+    pub jwks_cache_ttl_secs: u64,
+    /// Seconds of clock skew tolerated on `exp`, `nbf` and `iat`, at most
+    /// [`MAX_CLOCK_SKEW_SECS`]
+    #[serde(default = "default_clock_skew_secs")]
+    pub clock_skew_secs: u64,
+    /// Accept tokens without a usable `sub`; requests then act without a user
+    #[serde(default)]
+    pub allow_anonymous_subject: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudflareConfig {
+    pub team_domain: String,
+    pub audience: String,
+    #[serde(default = "CloudflareConfig::default_cache_ttl_secs")]
+    pub certs_cache_ttl_secs: u64,
+    /// Seconds of clock skew tolerated on `exp`, `nbf` and `iat`, at most
+    /// [`MAX_CLOCK_SKEW_SECS`]
+    #[serde(default = "default_clock_skew_secs")]
+    pub clock_skew_secs: u64,
+    /// Accept tokens without a usable `sub`; requests then act without a user
+    #[serde(default)]
+    pub allow_anonymous_subject: bool,
+}
+
+/// Largest accepted token clock skew; more would keep expired tokens usable.
+pub const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+const fn default_clock_skew_secs() -> u64 {
+    30
+}
+
+/// Read a clock skew variable, clamped to [`MAX_CLOCK_SKEW_SECS`].
+fn clock_skew_secs_from_env(name: &str) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or_else(default_clock_skew_secs)
+        .min(MAX_CLOCK_SKEW_SECS)
+}
+
+impl OAuthConfig {
+    const fn default_cache_ttl_secs() -> u64 {
+        300
+    }
+
+    fn default_scopes() -> String {
+        "openid profile email".to_string()
+    }
+
+    fn from_env(allow_anonymous_subject: bool) -> anyhow::Result<Self> {
+        let issuer_url = std::env::var("OAUTH_ISSUER_URL")
+            .context("OAUTH_ISSUER_URL must be set when AUTH_MODE=oauth")?;
+        let client_id = std::env::var("OAUTH_CLIENT_ID")
+            .context("OAUTH_CLIENT_ID must be set when AUTH_MODE=oauth")?;
+        let audience = std::env::var("OAUTH_AUDIENCE").unwrap_or_else(|_| client_id.clone());
+        let scopes = std::env::var("OAUTH_SCOPES").unwrap_or_else(|_| Self::default_scopes());
+        let redirect_uri = std::env::var("OAUTH_REDIRECT_URI")
+            .context("OAUTH_REDIRECT_URI must be set when AUTH_MODE=oauth")?;
+        let jwks_url = std::env::var("OAUTH_JWKS_URL").ok();
+        let jwks_cache_ttl_secs = std::env::var("OAUTH_JWKS_CACHE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_else(Self::default_cache_ttl_secs);
+
+        Ok(Self {
+            issuer_url,
+            audience,
+            client_id,
+            scopes,
+            redirect_uri,
+            jwks_url,
+            jwks_cache_ttl_secs,
+            clock_skew_secs: clock_skew_secs_from_env("OAUTH_CLOCK_SKEW_SECS"),
+            allow_anonymous_subject,
+        })
+    }
+}
+
+impl CloudflareConfig {
+    const fn default_cache_ttl_secs() -> u64 {
+        86400 // 24 hours
+    }
+
+    fn from_env(allow_anonymous_subject: bool) -> anyhow::Result<Self> {
+        let team_domain = std::env::var("CLOUDFLARE_TEAM_DOMAIN")
+            .context("CLOUDFLARE_TEAM_DOMAIN must be set when AUTH_MODE=cloudflare")?;
+        let audience = std::env::var("CLOUDFLARE_AUDIENCE")
+            .context("CLOUDFLARE_AUDIENCE must be set when AUTH_MODE=cloudflare")?;
+        let certs_cache_ttl_secs = std::env::var("CLOUDFLARE_CERTS_CACHE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_else(Self::default_cache_ttl_secs);
+
+        Ok(Self {
+            team_domain,
+            audience,
+            certs_cache_ttl_secs,
+            clock_skew_secs: clock_skew_secs_from_env("CLOUDFLARE_CLOCK_SKEW_SECS"),
+            allow_anonymous_subject,
+        })
+    }
+}
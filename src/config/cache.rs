@@ -0,0 +1,190 @@
+//! The read cache in front of storage and the scheduling of click flushes.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    #[serde(default = "CacheConfig::default_max_entries")]
+    pub max_entries: u64,
+    /// Cap for the read cache in approximate bytes instead of entries. When
+    /// set, `max_entries` no longer limits it; the two are set exclusively.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    #[serde(default = "CacheConfig::default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+    #[serde(default = "CacheConfig::default_actor_buffer_size")]
+    pub actor_buffer_size: usize,
+    #[serde(default = "CacheConfig::default_actor_flush_interval_ms")]
+    pub actor_flush_interval_ms: u64,
+    /// Cap for cached lookups of codes that do not exist. `None` keeps them in
+    /// the main cache under `max_entries`; `Some(0)` stops caching them; any
+    /// other value moves them to a separate cache so that a flood of unknown
+    /// codes cannot evict existing links.
+    #[serde(default)]
+    pub negative_max_entries: Option<u64>,
+    #[serde(default)]
+    pub eviction_policy: CacheEvictionPolicy,
+    /// When a cache miss cannot reach the database, redirect from the last
+    /// copy of the link loaded within this many seconds instead of failing.
+    /// `None` disables stale serving.
+    #[serde(default)]
+    pub stale_max_age_secs: Option<u64>,
+    /// Reload every cached lookup from the database once it is this many
+    /// seconds old, bounding how long changes made by other instances go
+    /// unseen. `None` keeps entries until evicted or changed here.
+    #[serde(default)]
+    pub entry_ttl_secs: Option<u64>,
+    /// Fail a cache miss whose database query takes longer than this, along
+    /// with every concurrent miss for the same code waiting on it. `0` waits
+    /// as long as the query does.
+    #[serde(default = "CacheConfig::default_lookup_timeout_ms")]
+    pub lookup_timeout_ms: u64,
+}
+
+impl CacheConfig {
+    pub(crate) const fn default_max_entries() -> u64 {
+        500_000
+    }
+
+    const fn default_flush_interval_secs() -> u64 {
+        5
+    }
+
+    const fn default_actor_buffer_size() -> usize {
+        1_000_000
+    }
+
+    const fn default_actor_flush_interval_ms() -> u64 {
+        100
+    }
+
+    pub(crate) const fn default_lookup_timeout_ms() -> u64 {
+        5_000
+    }
+
+    /// Read from the `CACHE_*` and `ACTOR_*` variables.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let max_bytes = std::env::var("CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|bytes| *bytes > 0);
+        if max_bytes.is_some() && std::env::var_os("CACHE_MAX_ENTRIES").is_some() {
+            anyhow::bail!("CACHE_MAX_BYTES and CACHE_MAX_ENTRIES cannot both be set");
+        }
+
+        let eviction_policy = match std::env::var("CACHE_EVICTION_POLICY")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "lru" => CacheEvictionPolicy::Lru,
+            _ => CacheEvictionPolicy::TinyLfu,
+        };
+
+        Ok(Self {
+            max_entries: std::env::var("CACHE_MAX_ENTRIES")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_max_entries),
+            max_bytes,
+            flush_interval_secs: std::env::var("CACHE_FLUSH_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_flush_interval_secs),
+            actor_buffer_size: std::env::var("ACTOR_BUFFER_SIZE")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_else(Self::default_actor_buffer_size),
+            actor_flush_interval_ms: std::env::var("ACTOR_FLUSH_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_actor_flush_interval_ms),
+            negative_max_entries: std::env::var("CACHE_NEGATIVE_MAX_ENTRIES")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok()),
+            eviction_policy,
+            stale_max_age_secs: std::env::var("CACHE_STALE_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0),
+            entry_ttl_secs: std::env::var("CACHE_ENTRY_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0),
+            lookup_timeout_ms: std::env::var("CACHE_LOOKUP_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_lookup_timeout_ms),
+        })
+    }
+}
+
+/// How the read cache picks entries to evict once it is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheEvictionPolicy {
+    /// Admit new entries only if they are likely to be read more often than
+    /// the entry they would evict, so one-off lookups do not flush hot links.
+    #[default]
+    TinyLfu,
+    /// Always admit new entries and evict the least recently used one.
+    Lru,
+}
+
+/// Scheduling of periodic click and analytics flushes to the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlushConfig {
+    /// Random jitter applied to each flush interval, as a percentage (capped at 50)
+    #[serde(default = "FlushConfig::default_jitter_percent")]
+    pub jitter_percent: u8,
+    /// Minimum number of pending entries before a periodic flush is issued
+    #[serde(default = "FlushConfig::default_min_pending")]
+    pub min_pending: usize,
+    /// Flush regardless of `min_pending` after this many deferred intervals
+    #[serde(default = "FlushConfig::default_max_deferred_intervals")]
+    pub max_deferred_intervals: u32,
+}
+
+impl FlushConfig {
+    const fn default_jitter_percent() -> u8 {
+        10
+    }
+
+    const fn default_min_pending() -> usize {
+        1
+    }
+
+    const fn default_max_deferred_intervals() -> u32 {
+        5
+    }
+
+    /// Read from the `FLUSH_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            jitter_percent: std::env::var("FLUSH_JITTER_PERCENT")
+                .ok()
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or_else(Self::default_jitter_percent)
+                .min(crate::flush::MAX_JITTER_PERCENT),
+            min_pending: std::env::var("FLUSH_MIN_PENDING")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_else(Self::default_min_pending)
+                .max(1),
+            max_deferred_intervals: std::env::var("FLUSH_MAX_DEFERRED_INTERVALS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_max_deferred_intervals),
+        }
+    }
+}
+
+impl Default for FlushConfig {
+    fn default() -> Self {
+        Self {
+            jitter_percent: Self::default_jitter_percent(),
+            min_pending: Self::default_min_pending(),
+            max_deferred_intervals: Self::default_max_deferred_intervals(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Anonymous link creation and the captcha or proof of work it can demand.
+
+use super::REDACTED;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Link creation without signing in (`POST /api/public/urls`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnonymousCreateConfig {
+    /// Accept unauthenticated link creation
+    #[serde(default)]
+    pub enabled: bool,
+    /// Links a single client IP may create per minute (0 disables the limit)
+    #[serde(default = "AnonymousCreateConfig::default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+    /// Keep new links inactive until an admin approves them
+    #[serde(default)]
+    pub require_approval: bool,
+}
+
+impl AnonymousCreateConfig {
+    pub const fn default_rate_limit_per_minute() -> u32 {
+        3
+    }
+
+    /// Read from `ALLOW_ANONYMOUS_CREATE` and the `ANONYMOUS_CREATE_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("ALLOW_ANONYMOUS_CREATE")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            rate_limit_per_minute: std::env::var("ANONYMOUS_CREATE_RATE_LIMIT_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_rate_limit_per_minute),
+            require_approval: std::env::var("ANONYMOUS_CREATE_REQUIRE_APPROVAL")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl Default for AnonymousCreateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate_limit_per_minute: Self::default_rate_limit_per_minute(),
+            require_approval: false,
+        }
+    }
+}
+
+/// Verification a client must pass before creating a link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeProvider {
+    /// No verification
+    #[default]
+    None,
+    /// Cloudflare Turnstile token, verified server-side
+    Turnstile,
+    /// hCaptcha token, verified server-side
+    Hcaptcha,
+    /// Proof of work issued by `GET /api/public/challenge`, checked locally
+    Pow,
+}
+
+/// Create endpoints that can demand a challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeEndpoint {
+    /// `POST /api/public/urls`
+    Public,
+    /// `POST /api/urls`
+    Api,
+    /// `GET /api/quick`
+    Quick,
+}
+
+impl ChallengeEndpoint {
+    /// Parse a `CREATION_CHALLENGE_ENDPOINTS` entry.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "public" => Some(Self::Public),
+            "api" => Some(Self::Api),
+            "quick" => Some(Self::Quick),
+            _ => None,
+        }
+    }
+}
+
+/// Captcha or proof-of-work verification on link creation.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CreationChallengeConfig {
+    #[serde(default)]
+    pub provider: ChallengeProvider,
+    /// Server-side secret of the Turnstile or hCaptcha site, or the key
+    /// proof-of-work nonces are signed with, the same on every instance
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Endpoints that require a solved challenge
+    #[serde(default = "CreationChallengeConfig::default_endpoints")]
+    pub endpoints: Vec<ChallengeEndpoint>,
+    /// Leading zero bits the proof-of-work hash must have
+    #[serde(default = "CreationChallengeConfig::default_pow_difficulty")]
+    pub pow_difficulty: u8,
+}
+
+impl CreationChallengeConfig {
+    /// Hardest accepted proof of work; each bit doubles the client's work.
+    pub const MAX_POW_DIFFICULTY: u8 = 32;
+
+    pub fn default_endpoints() -> Vec<ChallengeEndpoint> {
+        vec![ChallengeEndpoint::Public]
+    }
+
+    pub const fn default_pow_difficulty() -> u8 {
+        20
+    }
+
+    /// Whether `endpoint` requires a solved challenge.
+    pub fn requires(&self, endpoint: ChallengeEndpoint) -> bool {
+        self.provider != ChallengeProvider::None && self.endpoints.contains(&endpoint)
+    }
+
+    /// Read from the `CREATION_CHALLENGE_*` variables; every provider needs
+    /// the secret.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let provider = match std::env::var("CREATION_CHALLENGE_PROVIDER")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "none" => ChallengeProvider::None,
+            "turnstile" => ChallengeProvider::Turnstile,
+            "hcaptcha" => ChallengeProvider::Hcaptcha,
+            "pow" => ChallengeProvider::Pow,
+            other => anyhow::bail!(
+                "CREATION_CHALLENGE_PROVIDER must be none, turnstile, hcaptcha or pow, got '{}'",
+                other
+            ),
+        };
+
+        let secret = std::env::var("CREATION_CHALLENGE_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        if provider != ChallengeProvider::None && secret.is_none() {
+            anyhow::bail!(
+                "CREATION_CHALLENGE_SECRET must be set when CREATION_CHALLENGE_PROVIDER is"
+            );
+        }
+
+        let endpoints = match std::env::var("CREATION_CHALLENGE_ENDPOINTS") {
+            Ok(list) => list
+                .split(',')
+                .map(|endpoint| endpoint.trim().to_lowercase())
+                .filter(|endpoint| !endpoint.is_empty())
+                .map(|endpoint| {
+                    ChallengeEndpoint::parse(&endpoint).with_context(|| {
+                        format!(
+                            "CREATION_CHALLENGE_ENDPOINTS entries must be public, api or quick, got '{}'",
+                            endpoint
+                        )
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            Err(_) => Self::default_endpoints(),
+        };
+
+        Ok(Self {
+            provider,
+            secret,
+            endpoints,
+            pow_difficulty: std::env::var("CREATION_CHALLENGE_POW_DIFFICULTY")
+                .ok()
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or_else(Self::default_pow_difficulty)
+                .clamp(1, Self::MAX_POW_DIFFICULTY),
+        })
+    }
+}
+
+impl Default for CreationChallengeConfig {
+    fn default() -> Self {
+        Self {
+            provider: ChallengeProvider::None,
+            secret: None,
+            endpoints: Self::default_endpoints(),
+            pow_difficulty: Self::default_pow_difficulty(),
+        }
+    }
+}
+
+impl fmt::Debug for CreationChallengeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreationChallengeConfig")
+            .field("provider", &self.provider)
+            .field("secret", &self.secret.as_ref().map(|_| REDACTED))
+            .field("endpoints", &self.endpoints)
+            .field("pow_difficulty", &self.pow_difficulty)
+            .finish()
+    }
+}
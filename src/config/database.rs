@@ -0,0 +1,205 @@
+//! The primary database and the optional mirror.
+
+use super::redact_url;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
+    pub url: String,
+    #[serde(default = "DatabaseConfig::default_max_connections")]
+    pub max_connections: u32,
+    /// Seconds to wait for a pooled connection before responding with 503
+    #[serde(default = "DatabaseConfig::default_acquire_timeout_secs")]
+    pub acquire_timeout_secs: u64,
+    /// Pool waits above this many milliseconds are logged as slow
+    #[serde(default = "DatabaseConfig::default_slow_acquire_threshold_ms")]
+    pub slow_acquire_threshold_ms: u64,
+    /// Postgres schema to create and keep all tables in instead of `public`
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// Whether admins may permanently delete a link (`ALLOW_HARD_DELETE`);
+    /// off, links can only be deactivated
+    #[serde(default)]
+    pub allow_hard_delete: bool,
+}
+
+impl fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("backend", &self.backend)
+            .field("url", &redact_url(&self.url))
+            .field("max_connections", &self.max_connections)
+            .field("acquire_timeout_secs", &self.acquire_timeout_secs)
+            .field("slow_acquire_threshold_ms", &self.slow_acquire_threshold_ms)
+            .field("schema", &self.schema)
+            .field("allow_hard_delete", &self.allow_hard_delete)
+            .finish()
+    }
+}
+
+impl DatabaseConfig {
+    const fn default_max_connections() -> u32 {
+        30
+    }
+
+    pub(crate) const fn default_acquire_timeout_secs() -> u64 {
+        5
+    }
+
+    pub(crate) const fn default_slow_acquire_threshold_ms() -> u64 {
+        500
+    }
+
+    /// Read from `DATABASE_BACKEND`, `DATABASE_URL` and the pool settings.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let backend_str =
+            std::env::var("DATABASE_BACKEND").unwrap_or_else(|_| "sqlite".to_string());
+        let backend = match backend_str.to_lowercase().as_str() {
+            "postgres" | "postgresql" => DatabaseBackend::parse("postgres"),
+            _ => DatabaseBackend::parse("sqlite"),
+        }
+        .context("DATABASE_BACKEND")?;
+
+        Ok(Self {
+            backend,
+            url: std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://./lynx.db".to_string()),
+            max_connections: std::env::var("DATABASE_MAX_CONNECTIONS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_max_connections),
+            acquire_timeout_secs: std::env::var("DATABASE_ACQUIRE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or_else(Self::default_acquire_timeout_secs),
+            slow_acquire_threshold_ms: std::env::var("DATABASE_SLOW_ACQUIRE_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_slow_acquire_threshold_ms),
+            schema: std::env::var("DATABASE_SCHEMA")
+                .ok()
+                .filter(|schema| !schema.is_empty()),
+            allow_hard_delete: std::env::var("ALLOW_HARD_DELETE")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// Dark launch of a new database: writes go to both, reads to the primary.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DatabaseMirrorConfig {
+    pub backend: DatabaseBackend,
+    pub url: String,
+    /// Postgres schema of the mirror (see `DatabaseConfig::schema`)
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// Share of link lookups (0.0 to 1.0) repeated on the mirror and
+    /// compared with the primary's answer
+    #[serde(default)]
+    pub compare_sample_rate: f64,
+}
+
+impl fmt::Debug for DatabaseMirrorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseMirrorConfig")
+            .field("backend", &self.backend)
+            .field("url", &redact_url(&self.url))
+            .field("schema", &self.schema)
+            .field("compare_sample_rate", &self.compare_sample_rate)
+            .finish()
+    }
+}
+
+impl DatabaseMirrorConfig {
+    /// Read from the `DATABASE_MIRROR_*` variables; `None` unless
+    /// `DATABASE_MIRROR_URL` is set.
+    pub(super) fn from_env() -> anyhow::Result<Option<Self>> {
+        let url = match std::env::var("DATABASE_MIRROR_URL") {
+            Ok(url) if !url.trim().is_empty() => url.trim().to_string(),
+            _ => return Ok(None),
+        };
+        let backend = match std::env::var("DATABASE_MIRROR_BACKEND") {
+            Ok(value) => DatabaseBackend::parse(&value).context("DATABASE_MIRROR_BACKEND")?,
+            Err(_) => DatabaseBackend::from_url(&url).context("DATABASE_MIRROR_URL")?,
+        };
+        let compare_sample_rate = std::env::var("DATABASE_MIRROR_COMPARE_RATE")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|rate| rate.is_finite())
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0);
+        Ok(Some(Self {
+            backend,
+            url,
+            schema: std::env::var("DATABASE_MIRROR_SCHEMA")
+                .ok()
+                .filter(|schema| !schema.is_empty()),
+            compare_sample_rate,
+        }))
+    }
+}
+
+impl DatabaseBackend {
+    #[cfg(feature = "sqlite")]
+    const SQLITE: Option<Self> = Some(Self::Sqlite);
+    #[cfg(not(feature = "sqlite"))]
+    const SQLITE: Option<Self> = None;
+    #[cfg(feature = "postgres")]
+    const POSTGRES: Option<Self> = Some(Self::Postgres);
+    #[cfg(not(feature = "postgres"))]
+    const POSTGRES: Option<Self> = None;
+
+    /// The backend called `name` (`sqlite`, `postgres` or `postgresql`).
+    /// Fails for other names and for backends this build leaves out.
+    pub fn parse(name: &str) -> anyhow::Result<Self> {
+        let (backend, feature) = match name.to_lowercase().as_str() {
+            "sqlite" => (Self::SQLITE, "sqlite"),
+            "postgres" | "postgresql" => (Self::POSTGRES, "postgres"),
+            other => anyhow::bail!("expected sqlite or postgres, got '{}'", other),
+        };
+        backend.ok_or_else(|| {
+            anyhow::anyhow!(
+                "this build has no {} backend; rebuild with `--features {}`",
+                feature,
+                feature
+            )
+        })
+    }
+
+    /// Name of the backend for logs.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => "SQLite",
+            #[cfg(feature = "postgres")]
+            Self::Postgres => "PostgreSQL",
+        }
+    }
+
+    /// The backend a database URL points at: Postgres for `postgres://` and
+    /// `postgresql://` URLs, SQLite otherwise.
+    pub fn from_url(url: &str) -> anyhow::Result<Self> {
+        let scheme = url
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match scheme.as_str() {
+            "postgres" | "postgresql" => Self::parse("postgres"),
+            _ => Self::parse("sqlite"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    #[cfg(feature = "postgres")]
+    Postgres,
+}
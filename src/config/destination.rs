@@ -0,0 +1,237 @@
+//! Checks and normalization applied to destination URLs, and the title
+//! fetched for new links.
+
+use serde::{Deserialize, Serialize};
+
+/// Rules applied to destination URLs when links are created or updated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DestinationConfig {
+    /// Maximum length of a destination URL after normalization
+    #[serde(default = "DestinationConfig::default_max_length")]
+    pub max_length: usize,
+    /// Non-web schemes (e.g. `mailto`, `tel`) accepted in addition to http(s).
+    /// These are served through an interstitial page instead of a redirect.
+    #[serde(default)]
+    pub extra_schemes: Vec<String>,
+    /// Hosts that serve this instance's redirects (the host of
+    /// `redirect_base_url` plus any extra domains). Destinations on them
+    /// would redirect back into Lynx and are rejected.
+    #[serde(default)]
+    pub self_hosts: Vec<String>,
+    /// Accept destinations on `self_hosts`, e.g. to chain one short link
+    /// to another on purpose
+    #[serde(default)]
+    pub allow_self_redirects: bool,
+}
+
+impl DestinationConfig {
+    const fn default_max_length() -> usize {
+        2048
+    }
+
+    /// Read from the `URL_*` variables. `redirect_base_url` and
+    /// `REDIRECT_EXTRA_DOMAINS` make up `self_hosts`.
+    pub(super) fn from_env(redirect_base_url: &str) -> Self {
+        let extra_schemes: Vec<String> = std::env::var("URL_EXTRA_SCHEMES")
+            .ok()
+            .map(|s| {
+                s.split(',')
+                    .map(|scheme| scheme.trim().to_lowercase())
+                    .filter(|scheme| !scheme.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for scheme in &extra_schemes {
+            if !crate::destination::is_configurable_scheme(scheme) {
+                tracing::warn!(
+                    "Ignoring URL_EXTRA_SCHEMES entry '{scheme}': scheme cannot be added"
+                );
+            }
+        }
+        let extra_schemes = extra_schemes
+            .into_iter()
+            .filter(|scheme| crate::destination::is_configurable_scheme(scheme))
+            .collect();
+
+        let mut self_hosts: Vec<String> = url::Url::parse(redirect_base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+            .into_iter()
+            .collect();
+        if let Ok(domains) = std::env::var("REDIRECT_EXTRA_DOMAINS") {
+            for domain in domains.split(',') {
+                let domain = domain.trim().trim_end_matches('.').to_lowercase();
+                if !domain.is_empty() && !self_hosts.contains(&domain) {
+                    self_hosts.push(domain);
+                }
+            }
+        }
+
+        Self {
+            max_length: std::env::var("URL_MAX_LENGTH")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_else(Self::default_max_length),
+            extra_schemes,
+            self_hosts,
+            allow_self_redirects: std::env::var("URL_ALLOW_SELF_REDIRECTS")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl Default for DestinationConfig {
+    fn default() -> Self {
+        Self {
+            max_length: Self::default_max_length(),
+            extra_schemes: Vec::new(),
+            self_hosts: Vec::new(),
+            allow_self_redirects: false,
+        }
+    }
+}
+
+/// An optional step of destination normalization, as named in
+/// `URL_NORMALIZE_STEPS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlNormalizationStep {
+    /// Treat `http://` and `https://` as the same destination
+    Https,
+    /// Drop `#fragment`
+    Fragment,
+    /// Drop a trailing `/` from non-root paths
+    TrailingSlash,
+    /// Drop the query parameters in `strip_params`
+    TrackingParams,
+    /// Decode escaped unreserved characters and uppercase the remaining escapes
+    PercentEncoding,
+}
+
+impl UrlNormalizationStep {
+    pub const ALL: [UrlNormalizationStep; 5] = [
+        UrlNormalizationStep::Https,
+        UrlNormalizationStep::Fragment,
+        UrlNormalizationStep::TrailingSlash,
+        UrlNormalizationStep::TrackingParams,
+        UrlNormalizationStep::PercentEncoding,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UrlNormalizationStep::Https => "https",
+            UrlNormalizationStep::Fragment => "fragment",
+            UrlNormalizationStep::TrailingSlash => "trailing_slash",
+            UrlNormalizationStep::TrackingParams => "tracking_params",
+            UrlNormalizationStep::PercentEncoding => "percent_encoding",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.as_str() == name)
+    }
+}
+
+/// How destinations are normalized into `urls.normalized_url`, the form
+/// that dedupe and domain queries compare. The stored `original_url` is
+/// never changed. Scheme and host are always lowercased and default ports
+/// dropped; `steps` adds the rest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrlNormalizationConfig {
+    #[serde(default = "UrlNormalizationConfig::default_steps")]
+    pub steps: Vec<UrlNormalizationStep>,
+    /// Query parameters dropped by the `tracking_params` step; a trailing
+    /// `*` matches any parameter with that prefix
+    #[serde(default = "UrlNormalizationConfig::default_strip_params")]
+    pub strip_params: Vec<String>,
+}
+
+impl UrlNormalizationConfig {
+    fn default_steps() -> Vec<UrlNormalizationStep> {
+        vec![
+            UrlNormalizationStep::Fragment,
+            UrlNormalizationStep::TrailingSlash,
+            UrlNormalizationStep::TrackingParams,
+            UrlNormalizationStep::PercentEncoding,
+        ]
+    }
+
+    fn default_strip_params() -> Vec<String> {
+        ["utm_*", "fbclid", "gclid", "msclkid"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    pub fn runs(&self, step: UrlNormalizationStep) -> bool {
+        self.steps.contains(&step)
+    }
+
+    /// Read from the `URL_NORMALIZE_*` variables.
+    pub(super) fn from_env() -> Self {
+        let steps = match std::env::var("URL_NORMALIZE_STEPS") {
+            Ok(steps) => steps
+                .split(',')
+                .map(|step| step.trim().to_lowercase())
+                .filter(|step| !step.is_empty() && step != "none")
+                .filter_map(|step| {
+                    let parsed = UrlNormalizationStep::parse(&step);
+                    if parsed.is_none() {
+                        tracing::warn!("Ignoring unknown URL_NORMALIZE_STEPS entry '{step}'");
+                    }
+                    parsed
+                })
+                .collect(),
+            Err(_) => Self::default_steps(),
+        };
+
+        Self {
+            steps,
+            strip_params: std::env::var("URL_NORMALIZE_STRIP_PARAMS")
+                .map(|params| {
+                    params
+                        .split(',')
+                        .map(|param| param.trim().to_string())
+                        .filter(|param| !param.is_empty())
+                        .collect()
+                })
+                .unwrap_or_else(|_| Self::default_strip_params()),
+        }
+    }
+}
+
+impl Default for UrlNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            steps: Self::default_steps(),
+            strip_params: Self::default_strip_params(),
+        }
+    }
+}
+
+/// Background fetch of a new link's page title (see `crate::title`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TitleFetchConfig {
+    /// Fetch the destination's `<title>` after a link is created
+    #[serde(default)]
+    pub enabled: bool,
+    /// Also fetch from private, loopback and link-local addresses, for
+    /// instances that shorten intranet pages
+    #[serde(default)]
+    pub allow_private_addresses: bool,
+}
+
+impl TitleFetchConfig {
+    /// Read from the `TITLE_FETCH_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("TITLE_FETCH_ENABLED")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            allow_private_addresses: std::env::var("TITLE_FETCH_ALLOW_PRIVATE_ADDRESSES")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
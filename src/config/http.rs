@@ -0,0 +1,77 @@
+//! The HTTP client for calls the server makes itself.
+
+use super::redact_url;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The HTTP client behind calls the server makes itself (see `crate::http`).
+/// Without an explicit proxy, `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+/// apply.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OutboundHttpConfig {
+    /// Proxy for every outbound call, overriding `HTTP_PROXY` and `HTTPS_PROXY`
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// Hosts reached directly, overriding `NO_PROXY`
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// Seconds to establish a connection
+    #[serde(default = "OutboundHttpConfig::default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for each read of a response
+    #[serde(default = "OutboundHttpConfig::default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+}
+
+/// Proxy URLs may carry credentials, so only whether one is set is shown.
+impl fmt::Debug for OutboundHttpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutboundHttpConfig")
+            .field("proxy_url", &self.proxy_url.as_deref().map(redact_url))
+            .field("no_proxy", &self.no_proxy)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("read_timeout_secs", &self.read_timeout_secs)
+            .finish()
+    }
+}
+
+impl OutboundHttpConfig {
+    pub const fn default_connect_timeout_secs() -> u64 {
+        5
+    }
+
+    pub const fn default_read_timeout_secs() -> u64 {
+        30
+    }
+
+    /// Read from the `OUTBOUND_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            proxy_url: std::env::var("OUTBOUND_PROXY_URL")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            no_proxy: std::env::var("OUTBOUND_NO_PROXY").ok(),
+            connect_timeout_secs: std::env::var("OUTBOUND_CONNECT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or_else(Self::default_connect_timeout_secs),
+            read_timeout_secs: std::env::var("OUTBOUND_READ_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or_else(Self::default_read_timeout_secs),
+        }
+    }
+}
+
+impl Default for OutboundHttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy_url: None,
+            no_proxy: None,
+            connect_timeout_secs: Self::default_connect_timeout_secs(),
+            read_timeout_secs: Self::default_read_timeout_secs(),
+        }
+    }
+}
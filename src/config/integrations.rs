@@ -0,0 +1,169 @@
+//! Slack slash commands and account notification email.
+
+use super::REDACTED;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Slack slash command (`POST /api/integrations/slack`) settings.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SlackConfig {
+    /// Signing secret of the Slack app, used to verify request signatures
+    pub signing_secret: String,
+    /// Slack user id → Lynx user id that links are attributed to
+    #[serde(default)]
+    pub user_map: HashMap<String, String>,
+    /// Lynx user id for Slack users missing from `user_map`; when unset,
+    /// unmapped users are told to ask for their account to be linked
+    #[serde(default)]
+    pub service_user: Option<String>,
+}
+
+impl fmt::Debug for SlackConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlackConfig")
+            .field("signing_secret", &REDACTED)
+            .field("user_map", &self.user_map)
+            .field("service_user", &self.service_user)
+            .finish()
+    }
+}
+
+impl SlackConfig {
+    /// Read from the `SLACK_*` variables; `None` unless
+    /// `SLACK_SIGNING_SECRET` is set.
+    pub(super) fn from_env() -> Option<Self> {
+        let signing_secret = std::env::var("SLACK_SIGNING_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty())?;
+
+        let mut user_map = HashMap::new();
+        for entry in std::env::var("SLACK_USER_MAP")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            match entry.split_once('=') {
+                Some((slack_user, lynx_user))
+                    if !slack_user.trim().is_empty() && !lynx_user.trim().is_empty() =>
+                {
+                    user_map.insert(slack_user.trim().to_string(), lynx_user.trim().to_string());
+                }
+                _ => tracing::warn!(
+                    "Ignoring SLACK_USER_MAP entry '{entry}': expected SLACK_ID=LYNX_USER"
+                ),
+            }
+        }
+
+        Some(Self {
+            signing_secret,
+            user_map,
+            service_user: std::env::var("SLACK_SERVICE_USER")
+                .ok()
+                .filter(|user| !user.is_empty()),
+        })
+    }
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTlsMode {
+    /// Plain connection upgraded with STARTTLS, which the server must offer
+    #[default]
+    StartTls,
+    /// TLS from the first byte (SMTPS)
+    Tls,
+    /// No encryption, for a relay on the same host or network
+    None,
+}
+
+impl SmtpTlsMode {
+    /// The mode called `name` (`starttls`, `tls` or `none`).
+    pub fn parse(name: &str) -> anyhow::Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "starttls" => Ok(Self::StartTls),
+            "tls" | "smtps" => Ok(Self::Tls),
+            "none" => Ok(Self::None),
+            other => anyhow::bail!("expected starttls, tls or none, got '{}'", other),
+        }
+    }
+
+    /// The usual port for this mode, used when `SMTP_PORT` is unset.
+    pub const fn default_port(self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Tls => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+/// SMTP server that account notifications are sent through.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    /// Sender address, optionally with a name (`Lynx <lynx@example.com>`)
+    pub from: String,
+    #[serde(default)]
+    pub tls: SmtpTlsMode,
+    /// Sign-in for servers that require one; sent only with a password
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("from", &self.from)
+            .field("tls", &self.tls)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl SmtpConfig {
+    /// Read from the `SMTP_*` variables; `None` unless `SMTP_HOST` is set,
+    /// in which case `SMTP_FROM` must be too.
+    pub(super) fn from_env() -> anyhow::Result<Option<Self>> {
+        let host = match std::env::var("SMTP_HOST") {
+            Ok(host) if !host.trim().is_empty() => host.trim().to_string(),
+            _ => return Ok(None),
+        };
+        let tls = match std::env::var("SMTP_TLS") {
+            Ok(value) => SmtpTlsMode::parse(&value).context("SMTP_TLS")?,
+            Err(_) => SmtpTlsMode::default(),
+        };
+        let port = match std::env::var("SMTP_PORT") {
+            Ok(value) => value
+                .trim()
+                .parse::<u16>()
+                .with_context(|| format!("SMTP_PORT must be a port number, got '{value}'"))?,
+            Err(_) => tls.default_port(),
+        };
+        let from = std::env::var("SMTP_FROM")
+            .ok()
+            .filter(|from| !from.trim().is_empty())
+            .context("SMTP_FROM must be set when SMTP_HOST is")?;
+        Ok(Some(Self {
+            host,
+            port,
+            from: from.trim().to_string(),
+            tls,
+            username: std::env::var("SMTP_USERNAME")
+                .ok()
+                .filter(|username| !username.is_empty()),
+            password: std::env::var("SMTP_PASSWORD")
+                .ok()
+                .filter(|password| !password.is_empty()),
+        }))
+    }
+}
@@ -0,0 +1,273 @@
+//! Limits and defaults for creating and owning links.
+
+use super::REDACTED;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Limits for the bookmarklet endpoint (`GET /api/quick`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickLinkConfig {
+    /// Links a single user may create through the endpoint per minute (0 disables the limit)
+    #[serde(default = "QuickLinkConfig::default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+}
+
+impl QuickLinkConfig {
+    const fn default_rate_limit_per_minute() -> u32 {
+        30
+    }
+
+    /// Read from `QUICK_LINK_RATE_LIMIT_PER_MINUTE`.
+    pub(super) fn from_env() -> Self {
+        Self {
+            rate_limit_per_minute: std::env::var("QUICK_LINK_RATE_LIMIT_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_rate_limit_per_minute),
+        }
+    }
+}
+
+impl Default for QuickLinkConfig {
+    fn default() -> Self {
+        Self {
+            rate_limit_per_minute: Self::default_rate_limit_per_minute(),
+        }
+    }
+}
+
+/// Opt-in live visit feed (`GET /api/links/{code}/analytics/live`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveVisitsConfig {
+    /// Publish redirects to live subscribers
+    #[serde(default)]
+    pub enabled: bool,
+    /// Concurrent live connections allowed per user
+    #[serde(default = "LiveVisitsConfig::default_max_connections_per_user")]
+    pub max_connections_per_user: usize,
+}
+
+impl LiveVisitsConfig {
+    const fn default_max_connections_per_user() -> usize {
+        3
+    }
+
+    /// Read from the `LIVE_VISITS_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("LIVE_VISITS_ENABLED")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            max_connections_per_user: std::env::var("LIVE_VISITS_MAX_CONNECTIONS_PER_USER")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_else(Self::default_max_connections_per_user)
+                .max(1),
+        }
+    }
+}
+
+impl Default for LiveVisitsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_connections_per_user: Self::default_max_connections_per_user(),
+        }
+    }
+}
+
+/// Hourly click history kept alongside the lifetime click counter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClickHistoryConfig {
+    /// Days kept at hourly granularity by `lynx analytics compact-clicks`;
+    /// older hours are merged into one row per month
+    #[serde(default = "ClickHistoryConfig::default_retention_days")]
+    pub retention_days: i64,
+}
+
+impl ClickHistoryConfig {
+    pub const fn default_retention_days() -> i64 {
+        365
+    }
+
+    /// Read from `CLICK_HISTORY_RETENTION_DAYS`.
+    pub(super) fn from_env() -> Self {
+        Self {
+            retention_days: std::env::var("CLICK_HISTORY_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_retention_days)
+                .max(1),
+        }
+    }
+}
+
+impl Default for ClickHistoryConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: Self::default_retention_days(),
+        }
+    }
+}
+
+/// Short codes reserved before their destination is known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationConfig {
+    /// Days a reservation lasts before the expiry sweep deactivates it
+    #[serde(default = "ReservationConfig::default_ttl_days")]
+    pub ttl_days: i64,
+    /// Seconds between expiry sweeps on the API server
+    #[serde(default = "ReservationConfig::default_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+}
+
+impl ReservationConfig {
+    pub const fn default_ttl_days() -> i64 {
+        30
+    }
+
+    pub const fn default_sweep_interval_secs() -> u64 {
+        3600
+    }
+
+    /// Read from the `RESERVATION_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            ttl_days: std::env::var("RESERVATION_TTL_DAYS")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_ttl_days)
+                .max(1),
+            sweep_interval_secs: std::env::var("RESERVATION_SWEEP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or_else(Self::default_sweep_interval_secs)
+                .max(1),
+        }
+    }
+}
+
+impl Default for ReservationConfig {
+    fn default() -> Self {
+        Self {
+            ttl_days: Self::default_ttl_days(),
+            sweep_interval_secs: Self::default_sweep_interval_secs(),
+        }
+    }
+}
+
+/// How many links a user may own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkQuotaConfig {
+    /// Active links a user may own; admins are exempt (`None` = unlimited)
+    #[serde(default)]
+    pub max_links_per_user: Option<u64>,
+}
+
+impl LinkQuotaConfig {
+    /// Read from `LINK_QUOTA_PER_USER`; `0` is unlimited.
+    pub(super) fn from_env() -> Self {
+        Self {
+            max_links_per_user: std::env::var("LINK_QUOTA_PER_USER")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|limit| *limit > 0),
+        }
+    }
+}
+
+/// Who sees click counts of links they do not own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsPrivacyConfig {
+    /// Hide clicks from non-owners on links that have not chosen either way
+    #[serde(default)]
+    pub hide_by_default: bool,
+}
+
+impl StatsPrivacyConfig {
+    /// Read from `HIDE_STATS_BY_DEFAULT`.
+    pub(super) fn from_env() -> Self {
+        Self {
+            hide_by_default: std::env::var("HIDE_STATS_BY_DEFAULT")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// How `POST /api/urls` picks a code when the request has no custom one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeStrategy {
+    /// A random code, a new one on every request
+    #[default]
+    Random,
+    /// A code derived from the normalized destination, the owner and
+    /// `CODE_HASH_SALT`, so repeating a request returns the same link
+    Hash,
+}
+
+/// Generated short codes; see `crate::api::code_hash`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CodeGenerationConfig {
+    /// Strategy for requests that do not name one
+    #[serde(default)]
+    pub strategy: CodeStrategy,
+    /// Instance secret mixed into hash codes. Without it the redirect base
+    /// URL is used, which keeps codes stable but lets anyone predict them.
+    #[serde(default)]
+    pub hash_salt: Option<String>,
+    /// Length of hash codes, within the short code length limits
+    #[serde(default = "CodeGenerationConfig::default_hash_length")]
+    pub hash_length: usize,
+}
+
+/// The salt is what keeps hash codes from being guessed, so it is hidden.
+impl fmt::Debug for CodeGenerationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodeGenerationConfig")
+            .field("strategy", &self.strategy)
+            .field("hash_salt", &self.hash_salt.as_ref().map(|_| REDACTED))
+            .field("hash_length", &self.hash_length)
+            .finish()
+    }
+}
+
+impl CodeGenerationConfig {
+    pub const fn default_hash_length() -> usize {
+        8
+    }
+
+    /// Read from the `CODE_*` variables.
+    pub(super) fn from_env() -> Self {
+        let strategy = match std::env::var("CODE_STRATEGY")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "hash" => CodeStrategy::Hash,
+            _ => CodeStrategy::Random,
+        };
+
+        Self {
+            strategy,
+            hash_salt: std::env::var("CODE_HASH_SALT")
+                .ok()
+                .filter(|salt| !salt.is_empty()),
+            hash_length: std::env::var("CODE_HASH_LENGTH")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_else(Self::default_hash_length),
+        }
+    }
+}
+
+impl Default for CodeGenerationConfig {
+    fn default() -> Self {
+        Self {
+            strategy: CodeStrategy::default(),
+            hash_salt: None,
+            hash_length: Self::default_hash_length(),
+        }
+    }
+}
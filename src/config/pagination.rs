@@ -0,0 +1,136 @@
+//! Page size limits and cursor signing for list endpoints.
+
+use super::REDACTED;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    /// HMAC secret for cursor signing
+    /// If None, a dynamic key is generated at startup (not recommended for production)
+    pub cursor_hmac_secret: Option<String>,
+    /// Largest `limit` accepted by the URL list endpoint
+    #[serde(default = "PaginationConfig::default_list_max_limit")]
+    pub list_max_limit: i64,
+    /// Largest `limit` accepted by the URL search endpoint
+    #[serde(default = "PaginationConfig::default_search_max_limit")]
+    pub search_max_limit: i64,
+    /// Largest `limit` accepted by the analytics endpoints
+    #[serde(default = "PaginationConfig::default_analytics_max_limit")]
+    pub analytics_max_limit: i64,
+    /// Largest `offset` accepted by endpoints that still page by offset
+    #[serde(default = "PaginationConfig::default_max_offset")]
+    pub max_offset: i64,
+    /// Signed-only cursors issued before encryption are refused from this
+    /// time on (milliseconds since the Unix epoch)
+    #[serde(default = "PaginationConfig::default_legacy_cursors_until")]
+    pub legacy_cursors_until: i64,
+}
+
+impl fmt::Debug for PaginationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaginationConfig")
+            .field(
+                "cursor_hmac_secret",
+                &self.cursor_hmac_secret.as_ref().map(|_| REDACTED),
+            )
+            .field("list_max_limit", &self.list_max_limit)
+            .field("search_max_limit", &self.search_max_limit)
+            .field("analytics_max_limit", &self.analytics_max_limit)
+            .field("max_offset", &self.max_offset)
+            .field("legacy_cursors_until", &self.legacy_cursors_until)
+            .finish()
+    }
+}
+
+impl PaginationConfig {
+    const fn default_list_max_limit() -> i64 {
+        200
+    }
+
+    const fn default_search_max_limit() -> i64 {
+        200
+    }
+
+    const fn default_analytics_max_limit() -> i64 {
+        1000
+    }
+
+    const fn default_max_offset() -> i64 {
+        10_000
+    }
+
+    /// 2027-01-01T00:00:00Z, long enough for clients to page past the
+    /// upgrade that introduced encrypted cursors.
+    pub const fn default_legacy_cursors_until() -> i64 {
+        1_798_761_600_000
+    }
+
+    /// Whether a legacy signed cursor is still accepted at `now_ms`.
+    pub fn accepts_legacy_cursors(&self, now_ms: i64) -> bool {
+        now_ms < self.legacy_cursors_until
+    }
+
+    /// Read from the `CURSOR_*` variables and the page size limits, warning
+    /// when cursors will not survive a restart.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let cursor_hmac_secret = std::env::var("CURSOR_HMAC_SECRET").ok();
+        if cursor_hmac_secret.is_none() {
+            tracing::warn!(
+                "CURSOR_HMAC_SECRET is not set. Using a dynamic key generated at runtime. \
+                Previous cursors won't work after server restart. \
+                For production, set CURSOR_HMAC_SECRET in your environment."
+            );
+        }
+
+        let legacy_cursors_until = match std::env::var("CURSOR_LEGACY_UNTIL") {
+            Ok(value) if !value.trim().is_empty() => {
+                chrono::DateTime::parse_from_rfc3339(value.trim())
+                    .with_context(|| {
+                        format!("CURSOR_LEGACY_UNTIL must be an RFC 3339 time, got '{value}'")
+                    })?
+                    .timestamp_millis()
+            }
+            _ => Self::default_legacy_cursors_until(),
+        };
+
+        Ok(Self {
+            cursor_hmac_secret,
+            list_max_limit: std::env::var("LIST_MAX_LIMIT")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_list_max_limit)
+                .max(1),
+            search_max_limit: std::env::var("SEARCH_MAX_LIMIT")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_search_max_limit)
+                .max(1),
+            analytics_max_limit: std::env::var("ANALYTICS_MAX_LIMIT")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_analytics_max_limit)
+                .max(1),
+            max_offset: std::env::var("PAGINATION_MAX_OFFSET")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_else(Self::default_max_offset)
+                .max(0),
+            legacy_cursors_until,
+        })
+    }
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            cursor_hmac_secret: None,
+            list_max_limit: Self::default_list_max_limit(),
+            search_max_limit: Self::default_search_max_limit(),
+            analytics_max_limit: Self::default_analytics_max_limit(),
+            max_offset: Self::default_max_offset(),
+            legacy_cursors_until: Self::default_legacy_cursors_until(),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Redirect server behaviour: the status code, outcome counters, lookups of
+//! mangled codes, the root and expired pages, the interstitial and the link
+//! info endpoint.
+
+use super::{ServerConfig, REDACTED};
+use anyhow::Context;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// HTTP redirect status code configuration
+///
+/// This enum represents the valid redirect status codes (3xx) that can be used
+/// for URL redirection. It validates the configuration at startup to ensure
+/// only valid redirect codes are used.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "u16")]
+#[derive(Default)]
+pub enum RedirectMode {
+    /// HTTP 308: Permanent Redirect (Axum default).
+    /// Method and body are preserved (e.g., POST stays POST).
+    #[default]
+    Permanent,
+
+    /// HTTP 307: Temporary Redirect.
+    /// Method and body are preserved.
+    Temporary,
+
+    /// HTTP 303: See Other.
+    /// Always converts to GET. Specific for "Post/Redirect/Get" pattern.
+    SeeOther,
+
+    /// HTTP 301: Moved Permanently (Legacy).
+    /// May change method from POST to GET.
+    MovedPermanently,
+
+    /// HTTP 302: Found (Legacy).
+    /// May change method from POST to GET.
+    Found,
+}
+
+impl TryFrom<u16> for RedirectMode {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            308 => Ok(RedirectMode::Permanent),
+            307 => Ok(RedirectMode::Temporary),
+            303 => Ok(RedirectMode::SeeOther),
+            301 => Ok(RedirectMode::MovedPermanently),
+            302 => Ok(RedirectMode::Found),
+            other => Err(format!(
+                "Invalid redirect code: {}. Allowed: 301, 302, 303, 307, 308",
+                other
+            )),
+        }
+    }
+}
+
+impl RedirectMode {
+    /// Read from `REDIRECT_STATUS_CODE`; other codes fall back to 308.
+    pub(super) fn from_env() -> Self {
+        std::env::var("REDIRECT_STATUS_CODE")
+            .ok()
+            .and_then(|v| v.parse::<u16>().ok())
+            .and_then(|code| RedirectMode::try_from(code).ok())
+            .unwrap_or_default()
+    }
+}
+
+// Zero-cost conversion back to StatusCode for the response
+impl From<RedirectMode> for StatusCode {
+    fn from(mode: RedirectMode) -> Self {
+        match mode {
+            RedirectMode::Permanent => StatusCode::PERMANENT_REDIRECT, // 308
+            RedirectMode::Temporary => StatusCode::TEMPORARY_REDIRECT, // 307
+            RedirectMode::SeeOther => StatusCode::SEE_OTHER,           // 303
+            RedirectMode::MovedPermanently => StatusCode::MOVED_PERMANENTLY, // 301
+            RedirectMode::Found => StatusCode::FOUND,                  // 302
+        }
+    }
+}
+
+/// Opt-in counters for redirect outcomes on the redirect server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedirectStatsConfig {
+    /// Count found/inactive/not-found/expired redirect outcomes
+    #[serde(default)]
+    pub enabled: bool,
+    /// Number of distinct missing codes tracked for the "top missing" report
+    /// (0 disables the tracker, capped at `redirect::stats::MAX_TOP_MISSING_CAPACITY`)
+    #[serde(default)]
+    pub top_missing_capacity: usize,
+}
+
+impl RedirectStatsConfig {
+    /// Read from the `REDIRECT_STATS_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("REDIRECT_STATS_ENABLED")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            top_missing_capacity: std::env::var("REDIRECT_STATS_TOP_MISSING")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(0)
+                .min(crate::redirect::stats::MAX_TOP_MISSING_CAPACITY),
+        }
+    }
+}
+
+/// Second lookup for redirect codes that arrive mangled (`abc123.`,
+/// `abc123%20`) and do not exist as requested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeNormalizationConfig {
+    /// Retry missed codes after percent-decoding, trimming whitespace and
+    /// stripping trailing punctuation
+    #[serde(default = "CodeNormalizationConfig::default_enabled")]
+    pub enabled: bool,
+    /// Characters stripped from the end of a missed code
+    #[serde(default = "CodeNormalizationConfig::default_trailing_chars")]
+    pub trailing_chars: String,
+}
+
+impl CodeNormalizationConfig {
+    const fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_trailing_chars() -> String {
+        ".,);]".to_string()
+    }
+
+    /// Read from the `REDIRECT_NORMALIZE_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("REDIRECT_NORMALIZE_CODES")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or_else(|_| Self::default_enabled()),
+            trailing_chars: std::env::var("REDIRECT_NORMALIZE_TRAILING_CHARS")
+                .unwrap_or_else(|_| Self::default_trailing_chars()),
+        }
+    }
+}
+
+impl Default for CodeNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            trailing_chars: Self::default_trailing_chars(),
+        }
+    }
+}
+
+/// What the redirect server answers at its root path (`GET /`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedirectLandingConfig {
+    /// Send visitors of the bare redirect domain here instead of showing the
+    /// minimal info page
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Send visitors of expired links here instead of answering 404
+    #[serde(default)]
+    pub expired_page: Option<String>,
+}
+
+impl RedirectLandingConfig {
+    /// Read from `REDIRECT_HOMEPAGE_URL` and `REDIRECT_EXPIRED_URL`.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            homepage: http_url_from_env("REDIRECT_HOMEPAGE_URL")?,
+            expired_page: http_url_from_env("REDIRECT_EXPIRED_URL")?,
+        })
+    }
+}
+
+/// The countdown page redirects show instead of sending the visitor on at
+/// once; see `crate::redirect::countdown`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectInterstitialConfig {
+    /// Destination domains whose links always get the page, besides links
+    /// flagged `interstitial`; a domain also covers its subdomains
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Seconds the page counts down before moving on to the destination
+    #[serde(default = "RedirectInterstitialConfig::default_countdown_secs")]
+    pub countdown_secs: u32,
+    /// HTML file used instead of the built-in page
+    #[serde(default)]
+    pub template_path: Option<String>,
+}
+
+impl RedirectInterstitialConfig {
+    pub const fn default_countdown_secs() -> u32 {
+        5
+    }
+
+    /// Read from the `INTERSTITIAL_*` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            domains: std::env::var("INTERSTITIAL_DOMAINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|domain| domain.trim().trim_end_matches('.').to_ascii_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect(),
+            countdown_secs: std::env::var("INTERSTITIAL_COUNTDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or_else(Self::default_countdown_secs),
+            template_path: std::env::var("INTERSTITIAL_TEMPLATE_PATH")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+        }
+    }
+}
+
+impl Default for RedirectInterstitialConfig {
+    fn default() -> Self {
+        Self {
+            domains: Vec::new(),
+            countdown_secs: Self::default_countdown_secs(),
+            template_path: None,
+        }
+    }
+}
+
+/// `GET /{code}/info.json` on the redirect server, for trusted internal
+/// services that expand links without following them.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LinkInfoConfig {
+    /// Serve the endpoint; when off the route does not exist
+    #[serde(default)]
+    pub enabled: bool,
+    /// Peer addresses or CIDR ranges let in without a token
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+    /// Shared secret that lets any peer in when sent in `X-Link-Info-Token`
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl fmt::Debug for LinkInfoConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkInfoConfig")
+            .field("enabled", &self.enabled)
+            .field("allowed_ips", &self.allowed_ips)
+            .field("token", &self.token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl LinkInfoConfig {
+    /// Read from the `LINK_INFO_*` variables; when enabled, an address
+    /// list or a token must let someone in.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let enabled = std::env::var("LINK_INFO_ENABLED")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+        let allowed_ips: Vec<String> = std::env::var("LINK_INFO_ALLOWED_IPS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect();
+        for entry in &allowed_ips {
+            if entry.parse::<ipnet::IpNet>().is_err() && entry.parse::<std::net::IpAddr>().is_err()
+            {
+                anyhow::bail!(
+                    "LINK_INFO_ALLOWED_IPS entries must be IP addresses or CIDR ranges, got '{}'",
+                    entry
+                );
+            }
+        }
+        let token = std::env::var("LINK_INFO_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        if enabled && allowed_ips.is_empty() && token.is_none() {
+            anyhow::bail!("LINK_INFO_ENABLED needs LINK_INFO_ALLOWED_IPS or LINK_INFO_TOKEN");
+        }
+
+        Ok(Self {
+            enabled,
+            allowed_ips,
+            token,
+        })
+    }
+}
+
+/// Read an optional http or https URL variable; unset or blank is `None`.
+fn http_url_from_env(name: &str) -> anyhow::Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) if !value.trim().is_empty() => {
+            let value = value.trim();
+            let parsed = url::Url::parse(value)
+                .with_context(|| format!("{} is not a valid URL: {}", name, value))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                anyhow::bail!("{} must be an http or https URL", name);
+            }
+            Ok(Some(value.to_string()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Read `REDIRECT_BASE_URL`, or build it from `REDIRECT_SCHEME` and the
+/// redirect listener, leaving out the scheme's default port.
+pub(super) fn base_url_from_env(server: &ServerConfig) -> String {
+    let scheme = std::env::var("REDIRECT_SCHEME")
+        .ok()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| if server.port == 443 { "https" } else { "http" }.to_string());
+
+    std::env::var("REDIRECT_BASE_URL")
+        .ok()
+        .map(|value| value.trim().trim_end_matches('/').to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| match (scheme.as_str(), server.port) {
+            ("http", 80) | ("https", 443) => format!("{}://{}", scheme, server.host),
+            _ => format!("{}://{}:{}", scheme, server.host, server.port),
+        })
+}
@@ -0,0 +1,139 @@
+//! The listeners and how the API server answers requests.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServerConfig {
+    /// Read a listener from `host_var` and `port_var`, defaulting to
+    /// `127.0.0.1` and `default_port`.
+    pub(super) fn from_env(
+        host_var: &str,
+        port_var: &str,
+        default_port: u16,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            host: std::env::var(host_var).unwrap_or_else(|_| "127.0.0.1".to_string()),
+            port: std::env::var(port_var)
+                .unwrap_or_else(|_| default_port.to_string())
+                .parse::<u16>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrontendConfig {
+    /// Serve the web UI from the API server. When off, only `/api` is
+    /// mounted and `/` answers with a JSON index of the API.
+    #[serde(default = "FrontendConfig::default_enabled")]
+    pub enabled: bool,
+    /// Path to directory containing static frontend files
+    /// If None, uses embedded frontend (if available)
+    pub static_dir: Option<String>,
+}
+
+impl FrontendConfig {
+    const fn default_enabled() -> bool {
+        true
+    }
+
+    /// Read from the `FRONTEND_*` variables.
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let enabled = std::env::var("FRONTEND_ENABLED")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or_else(|_| Self::default_enabled());
+        let static_dir = std::env::var("FRONTEND_STATIC_DIR").ok();
+        if !enabled && static_dir.is_some() {
+            anyhow::bail!("FRONTEND_STATIC_DIR cannot be set when FRONTEND_ENABLED is false");
+        }
+        Ok(Self {
+            enabled,
+            static_dir,
+        })
+    }
+}
+
+/// How the API builds absolute URLs (short links, the quick-create page).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PublicUrlConfig {
+    /// Take the scheme and host from `X-Forwarded-Proto`/`X-Forwarded-Host`
+    /// instead of `redirect_base_url` when a trusted proxy sends them (see
+    /// `AnalyticsConfig::trusted_proxy_mode`)
+    #[serde(default)]
+    pub from_forwarded_headers: bool,
+}
+
+impl PublicUrlConfig {
+    /// Read from `PUBLIC_URL_FROM_FORWARDED_HEADERS`.
+    pub(super) fn from_env() -> Self {
+        Self {
+            from_forwarded_headers: std::env::var("PUBLIC_URL_FROM_FORWARDED_HEADERS")
+                .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// How long an API request may run before it is abandoned with `504`.
+/// Budgets are in seconds; `None` lets requests of that class run unbounded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestTimeoutConfig {
+    /// `GET` and `HEAD` requests
+    #[serde(default = "RequestTimeoutConfig::default_read_secs")]
+    pub read_secs: Option<u64>,
+    /// Requests that change something
+    #[serde(default = "RequestTimeoutConfig::default_write_secs")]
+    pub write_secs: Option<u64>,
+    /// Analytics exports, until their response starts streaming
+    #[serde(default = "RequestTimeoutConfig::default_export_secs")]
+    pub export_secs: Option<u64>,
+}
+
+impl RequestTimeoutConfig {
+    fn default_read_secs() -> Option<u64> {
+        Some(15)
+    }
+
+    fn default_write_secs() -> Option<u64> {
+        Some(30)
+    }
+
+    fn default_export_secs() -> Option<u64> {
+        Some(300)
+    }
+
+    /// Read from the `API_*_TIMEOUT_SECS` variables.
+    pub(super) fn from_env() -> Self {
+        Self {
+            read_secs: timeout_secs_from_env("API_READ_TIMEOUT_SECS", Self::default_read_secs()),
+            write_secs: timeout_secs_from_env("API_WRITE_TIMEOUT_SECS", Self::default_write_secs()),
+            export_secs: timeout_secs_from_env(
+                "API_EXPORT_TIMEOUT_SECS",
+                Self::default_export_secs(),
+            ),
+        }
+    }
+}
+
+impl Default for RequestTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            read_secs: Self::default_read_secs(),
+            write_secs: Self::default_write_secs(),
+            export_secs: Self::default_export_secs(),
+        }
+    }
+}
+
+/// Read a request timeout variable; `0` disables the timeout.
+fn timeout_secs_from_env(name: &str, default: Option<u64>) -> Option<u64> {
+    match std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok()) {
+        Some(0) => None,
+        Some(secs) => Some(secs),
+        None => default,
+    }
+}
@@ -0,0 +1,133 @@
+//! Confirmation for destructive CLI commands.
+//!
+//! Commands that rewrite many rows at once (`lynx patch fix-all`,
+//! `lynx user deactivate-links`, ...) ask before they write. Run from a
+//! terminal, they prompt: either for a value the operator must retype, such
+//! as the user id whose links are about to change, or for a plain y/N. `--yes`
+//! skips the prompt for scripts. Without a terminal and without `--yes` the
+//! command fails instead of guessing, so automation that forgot the flag
+//! exits non-zero before anything is changed.
+
+use anyhow::{bail, Result};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// What the operator must type to go ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirm<'a> {
+    /// Exactly this value, e.g. the user id the command targets
+    Retype(&'a str),
+    /// `y` or `yes`, in any case
+    YesNo,
+}
+
+/// Ask on the terminal whether to run a destructive command.
+///
+/// Returns an error, and the command must not write, when the operator
+/// declines or when stdin is not a terminal and `assume_yes` is not set.
+pub fn confirm_destructive(confirm: Confirm<'_>, assume_yes: bool) -> Result<()> {
+    let stdin = std::io::stdin();
+    let is_terminal = stdin.is_terminal();
+    confirm_with(
+        confirm,
+        assume_yes,
+        is_terminal,
+        &mut stdin.lock(),
+        &mut std::io::stderr(),
+    )
+}
+
+/// [`confirm_destructive`] reading the answer from `input` and writing the
+/// prompt to `output`.
+pub fn confirm_with(
+    confirm: Confirm<'_>,
+    assume_yes: bool,
+    is_terminal: bool,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<()> {
+    if assume_yes {
+        return Ok(());
+    }
+    if !is_terminal {
+        bail!("refusing to continue without confirmation: stdin is not a terminal; pass --yes to run this command non-interactively");
+    }
+
+    match confirm {
+        Confirm::Retype(expected) => write!(output, "Type '{}' to confirm: ", expected)?,
+        Confirm::YesNo => write!(output, "Continue? [y/N] ")?,
+    }
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let answer = answer.trim();
+    let confirmed = match confirm {
+        Confirm::Retype(expected) => answer == expected,
+        Confirm::YesNo => answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"),
+    };
+    if !confirmed {
+        bail!("not confirmed; nothing was changed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn answer(confirm: Confirm<'_>, typed: &str) -> (Result<()>, String) {
+        let mut output = Vec::new();
+        let result = confirm_with(
+            confirm,
+            false,
+            true,
+            &mut Cursor::new(typed.as_bytes()),
+            &mut output,
+        );
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn retyped_value_must_match_exactly() {
+        let (result, prompt) = answer(Confirm::Retype("alice"), "alice\n");
+        assert!(result.is_ok());
+        assert_eq!(prompt, "Type 'alice' to confirm: ");
+
+        assert!(answer(Confirm::Retype("alice"), "alicee\n").0.is_err());
+        assert!(answer(Confirm::Retype("alice"), "y\n").0.is_err());
+        assert!(answer(Confirm::Retype("alice"), "").0.is_err());
+    }
+
+    #[test]
+    fn yes_no_defaults_to_no() {
+        assert!(answer(Confirm::YesNo, "y\n").0.is_ok());
+        assert!(answer(Confirm::YesNo, "YES\n").0.is_ok());
+        assert!(answer(Confirm::YesNo, "\n").0.is_err());
+        assert!(answer(Confirm::YesNo, "n\n").0.is_err());
+    }
+
+    #[test]
+    fn non_terminal_input_needs_the_yes_flag() {
+        let mut output = Vec::new();
+        let error = confirm_with(
+            Confirm::YesNo,
+            false,
+            false,
+            &mut Cursor::new(b"y\n".as_slice()),
+            &mut output,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("--yes"));
+        assert!(output.is_empty(), "no prompt without a terminal");
+
+        assert!(confirm_with(
+            Confirm::Retype("alice"),
+            true,
+            false,
+            &mut Cursor::new(b"".as_slice()),
+            &mut output,
+        )
+        .is_ok());
+    }
+}
@@ -1,5 +1,19 @@
+//! Opaque pagination cursors.
+//!
+//! A cursor carries the `(created_at, id)` keyset position of the last row on
+//! a page. It is encrypted with XChaCha20-Poly1305 under a key derived from the
+//! cursor secret, so clients can neither read the row id (and infer how many
+//! links exist) nor forge a position.
+//!
+//! `created_at` is in milliseconds. The signed-only cursors issued before
+//! encryption carry seconds; until `CURSOR_LEGACY_UNTIL` they are still
+//! accepted and converted so clients paging across an upgrade are not cut
+//! off, and after it they are refused.
+
 use anyhow::{anyhow, Result};
 use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
@@ -8,6 +22,18 @@ use std::sync::OnceLock;
 /// Global HMAC key for cursor signing
 static HMAC_KEY: OnceLock<Vec<u8>> = OnceLock::new();
 
+/// Cursor cipher, keyed from [`HMAC_KEY`] on first use
+static CIPHER: OnceLock<XChaCha20Poly1305> = OnceLock::new();
+
+/// Prefix of encrypted cursors. Legacy signed cursors start with base64 JSON.
+const ENCRYPTED_PREFIX: &str = "v2.";
+
+/// Bound into every ciphertext so cursors cannot be swapped with other data
+/// encrypted under the same key.
+const CURSOR_AAD: &[u8] = b"lynx-cursor-v2";
+
+const NONCE_LEN: usize = 24;
+
 /// Initialize the HMAC key for cursor signing
 /// If secret is None, generates a random key (WARNING: cursors won't survive restarts)
 pub fn init_cursor_hmac_key(secret: Option<&str>) {
@@ -31,35 +57,104 @@ fn get_hmac_key() -> &'static [u8] {
     })
 }
 
+/// Derive the encryption key from the cursor secret rather than reusing the
+/// HMAC key directly.
+fn cipher() -> &'static XChaCha20Poly1305 {
+    CIPHER.get_or_init(|| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(get_hmac_key())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"lynx cursor encryption v1");
+        XChaCha20Poly1305::new(&mac.finalize().into_bytes())
+    })
+}
+
 /// Cursor data for pagination
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CursorData {
     pub created_at: i64,
     pub id: i64,
+    /// Set on cursors of listings ordered by last visit, which page by
+    /// `(last_visited_at or 0, id)` instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_visited_at: Option<i64>,
+    /// Set on cursors of relevance-sorted searches, which page by position
+    /// in the ranking instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
 }
 
-/// Create a signed cursor from data
+/// Create an encrypted cursor from data
 pub fn create_cursor(data: &CursorData) -> Result<String> {
-    // Serialize the cursor data
-    let json = serde_json::to_string(data)?;
-    let payload = BASE64_URL_SAFE_NO_PAD.encode(json.as_bytes());
+    let json = serde_json::to_vec(data)?;
 
-    // Create HMAC signature
-    let key = get_hmac_key();
-    let mut mac =
-        Hmac::<Sha256>::new_from_slice(key).map_err(|e| anyhow!("Failed to create HMAC: {}", e))?;
+    // A fresh random nonce per cursor; 24 bytes make collisions negligible.
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::fill(&mut nonce);
+    let ciphertext = cipher()
+        .encrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: &json,
+                aad: CURSOR_AAD,
+            },
+        )
+        .map_err(|_| anyhow!("Failed to encrypt cursor"))?;
 
-    mac.update(payload.as_bytes());
-    let signature = mac.finalize();
-    let signature_bytes = signature.into_bytes();
-    let signature_b64 = BASE64_URL_SAFE_NO_PAD.encode(signature_bytes);
+    // Return v2.base64(nonce || ciphertext)
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_PREFIX,
+        BASE64_URL_SAFE_NO_PAD.encode(sealed)
+    ))
+}
 
-    // Return payload.signature
-    Ok(format!("{}.{}", payload, signature_b64))
+/// Verify and decode a cursor: encrypted, or legacy signed while
+/// `accept_legacy` (see `PaginationConfig::accepts_legacy_cursors`).
+pub fn verify_cursor(cursor: &str, accept_legacy: bool) -> Result<CursorData> {
+    if let Some(sealed) = cursor.strip_prefix(ENCRYPTED_PREFIX) {
+        return decrypt_cursor(sealed);
+    }
+    if !accept_legacy {
+        return Err(anyhow!(
+            "Cursor has expired; start again from the first page"
+        ));
+    }
+    let data = verify_signed_cursor(cursor)?;
+    Ok(CursorData {
+        created_at: data.created_at.saturating_mul(1000),
+        ..data
+    })
 }
 
-/// Verify and decode a cursor
-pub fn verify_cursor(cursor: &str) -> Result<CursorData> {
+fn decrypt_cursor(sealed: &str) -> Result<CursorData> {
+    let sealed = BASE64_URL_SAFE_NO_PAD
+        .decode(sealed)
+        .map_err(|_| anyhow!("Invalid cursor encoding"))?;
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("Invalid cursor format"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into()?;
+    let json = cipher()
+        .decrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: CURSOR_AAD,
+            },
+        )
+        .map_err(|_| anyhow!("Cursor verification failed"))?;
+    serde_json::from_slice(&json).map_err(|_| anyhow!("Invalid cursor data"))
+}
+
+/// Verify and decode a legacy `payload.signature` cursor.
+///
+/// Deprecated: only accepted until `CURSOR_LEGACY_UNTIL` so cursors handed
+/// out before encryption keep working through one upgrade. Their
+/// `created_at` is in seconds.
+fn verify_signed_cursor(cursor: &str) -> Result<CursorData> {
     // Split cursor into payload and signature
     let parts: Vec<&str> = cursor.split('.').collect();
     if parts.len() != 2 {
@@ -71,8 +166,8 @@ pub fn verify_cursor(cursor: &str) -> Result<CursorData> {
 
     // Verify signature
     let key = get_hmac_key();
-    let mut mac =
-        Hmac::<Sha256>::new_from_slice(key).map_err(|e| anyhow!("Failed to create HMAC: {}", e))?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+        .map_err(|e| anyhow!("Failed to create HMAC: {}", e))?;
 
     mac.update(payload.as_bytes());
 
@@ -112,10 +207,12 @@ mod tests {
         let data = CursorData {
             created_at: 1234567890,
             id: 42,
+            last_visited_at: None,
+            offset: None,
         };
 
         let cursor = create_cursor(&data).unwrap();
-        let verified = verify_cursor(&cursor).unwrap();
+        let verified = verify_cursor(&cursor, true).unwrap();
 
         assert_eq!(verified.created_at, data.created_at);
         assert_eq!(verified.id, data.id);
@@ -128,6 +225,8 @@ mod tests {
         let data = CursorData {
             created_at: 1234567890,
             id: 42,
+            last_visited_at: None,
+            offset: None,
         };
 
         let cursor = create_cursor(&data).unwrap();
@@ -136,12 +235,114 @@ mod tests {
         let parts: Vec<&str> = cursor.split('.').collect();
         let tampered = format!("{}.invalid_signature", parts[0]);
 
-        assert!(verify_cursor(&tampered).is_err());
+        assert!(verify_cursor(&tampered, true).is_err());
     }
 
     #[test]
     fn test_cursor_invalid_format() {
-        assert!(verify_cursor("invalid").is_err());
-        assert!(verify_cursor("invalid.format.extra").is_err());
+        assert!(verify_cursor("invalid", true).is_err());
+        assert!(verify_cursor("invalid.format.extra", true).is_err());
+        assert!(verify_cursor("v2.", true).is_err());
+        assert!(verify_cursor("v2.not base64", true).is_err());
+    }
+
+    /// A cursor in the signed-only format issued before encryption.
+    fn legacy_cursor(data: &CursorData) -> String {
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(data).unwrap());
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(get_hmac_key()).unwrap();
+        mac.update(payload.as_bytes());
+        let signature = BASE64_URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    #[test]
+    fn test_cursor_hides_its_position() {
+        init_cursor_hmac_key(Some("test_secret_key_for_hmac_signing"));
+
+        let data = CursorData {
+            created_at: 1234567890,
+            id: 42,
+            last_visited_at: None,
+            offset: None,
+        };
+        let cursor = create_cursor(&data).unwrap();
+        let sealed = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor.strip_prefix(ENCRYPTED_PREFIX).unwrap())
+            .unwrap();
+        let readable = String::from_utf8_lossy(&sealed);
+        assert!(!readable.contains("\"id\"") && !readable.contains("1234567890"));
+
+        // Random nonces: the same position never yields the same cursor.
+        assert_ne!(cursor, create_cursor(&data).unwrap());
+    }
+
+    #[test]
+    fn test_cursor_ciphertext_tampering_detection() {
+        init_cursor_hmac_key(Some("test_secret_key_for_hmac_signing"));
+
+        let cursor = create_cursor(&CursorData {
+            created_at: 1234567890,
+            id: 42,
+            last_visited_at: None,
+            offset: None,
+        })
+        .unwrap();
+        let mut sealed = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor.strip_prefix(ENCRYPTED_PREFIX).unwrap())
+            .unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        let tampered = format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            BASE64_URL_SAFE_NO_PAD.encode(sealed)
+        );
+
+        assert!(verify_cursor(&tampered, true).is_err());
+    }
+
+    #[test]
+    fn test_legacy_signed_cursors_still_verify() {
+        init_cursor_hmac_key(Some("test_secret_key_for_hmac_signing"));
+
+        let data = CursorData {
+            created_at: 1234567890,
+            id: 42,
+            last_visited_at: None,
+            offset: None,
+        };
+        let verified = verify_cursor(&legacy_cursor(&data), true).unwrap();
+        assert_eq!(verified.created_at, data.created_at * 1000);
+        assert_eq!(verified.id, data.id);
+
+        let forged = legacy_cursor(&data).replace('.', ".x");
+        assert!(verify_cursor(&forged, true).is_err());
+    }
+
+    #[test]
+    fn test_legacy_signed_cursors_are_refused_after_the_cutoff() {
+        init_cursor_hmac_key(Some("test_secret_key_for_hmac_signing"));
+
+        let pagination = crate::config::PaginationConfig {
+            legacy_cursors_until: 1_800_000_000_000,
+            ..crate::config::PaginationConfig::default()
+        };
+        let data = CursorData {
+            created_at: 1234567890,
+            id: 42,
+            last_visited_at: None,
+            offset: None,
+        };
+        let legacy = legacy_cursor(&data);
+        let current = create_cursor(&data).unwrap();
+
+        let before = pagination.accepts_legacy_cursors(1_799_999_999_999);
+        assert!(verify_cursor(&legacy, before).is_ok());
+
+        for now in [1_800_000_000_000, 1_900_000_000_000] {
+            let accepted = pagination.accepts_legacy_cursors(now);
+            assert!(verify_cursor(&legacy, accepted).is_err());
+            assert_eq!(verify_cursor(&current, accepted).unwrap().id, data.id);
+        }
     }
 }
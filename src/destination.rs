@@ -0,0 +1,604 @@
+//! Validation and normalization of destination URLs.
+//!
+//! Destinations end up in `Location` headers, HTML and exports, so anything
+//! that could smuggle extra lines or script into those contexts is rejected
+//! before it reaches storage. Accepted URLs are stored in the canonical form
+//! produced by the `url` crate.
+//!
+//! Rows written before ingest validation existed may still hold such
+//! characters, so [`location_header`] encodes the stored value again on the
+//! way out.
+
+use axum::http::HeaderValue;
+use std::fmt::Write as _;
+use thiserror::Error;
+use url::Url;
+
+use crate::config::{DestinationConfig, UrlNormalizationConfig, UrlNormalizationStep};
+
+/// Schemes that are always redirected with a `Location` header.
+pub const WEB_SCHEMES: &[&str] = &["http", "https"];
+
+/// Schemes that can never be configured as destinations because browsers
+/// execute or embed them in the context of the page that follows the link.
+pub const BLOCKED_SCHEMES: &[&str] = &["javascript", "vbscript", "data", "file", "blob"];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DestinationError {
+    #[error("URL cannot be empty")]
+    Empty,
+    #[error("URL exceeds the maximum length of {max} characters")]
+    TooLong { max: usize },
+    #[error("URL must not contain control characters (such as CR, LF or tab)")]
+    ControlCharacter,
+    #[error("URL must not contain whitespace")]
+    Whitespace,
+    #[error("URL is not valid: {0}")]
+    Invalid(String),
+    #[error("URL scheme '{0}' is not allowed")]
+    SchemeNotAllowed(String),
+    #[error("URL must not point at this shortener ('{0}'), which could create a redirect loop")]
+    SelfRedirect(String),
+}
+
+/// Whether `scheme` may be configured as an additional destination scheme.
+pub fn is_configurable_scheme(scheme: &str) -> bool {
+    !WEB_SCHEMES.contains(&scheme) && !BLOCKED_SCHEMES.contains(&scheme)
+}
+
+/// Whether `host` is one of the hosts this instance serves redirects on.
+pub fn is_self_host(host: &str, self_hosts: &[String]) -> bool {
+    let host = host.trim_end_matches('.');
+    self_hosts
+        .iter()
+        .any(|self_host| self_host.eq_ignore_ascii_case(host))
+}
+
+/// Whether a stored destination names a non-web scheme (for example
+/// `mailto:` or `tel:`) and must be served through an interstitial page
+/// instead of a `Location` redirect.
+///
+/// Legacy destinations without a scheme keep their plain redirect.
+pub fn requires_interstitial(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    let is_scheme = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    is_scheme
+        && !WEB_SCHEMES
+            .iter()
+            .any(|web| scheme.eq_ignore_ascii_case(web))
+}
+
+/// The host a stored destination points at, lowercased and without a
+/// trailing dot, as kept in `urls.dest_host`. `None` for destinations
+/// without one, such as `mailto:` links and reserved placeholders.
+pub fn destination_host(url: &str) -> Option<String> {
+    let url = Url::parse(url.trim()).ok()?;
+    let host = url.host_str()?.trim_end_matches('.');
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// The form of a stored destination that dedupe and domain queries compare,
+/// as kept in `urls.normalized_url`. `None` for values that do not parse as
+/// URLs, such as reserved placeholders.
+///
+/// Parsing already lowercases the scheme and web hosts and drops default
+/// ports; a trailing dot on the host is dropped too. `config` picks the
+/// remaining steps.
+pub fn normalize_url(url: &str, config: &UrlNormalizationConfig) -> Option<String> {
+    let mut url = Url::parse(url.trim()).ok()?;
+
+    if let Some(host) = url.host_str() {
+        let trimmed = host.trim_end_matches('.');
+        if trimmed.len() != host.len() && !trimmed.is_empty() {
+            let trimmed = trimmed.to_ascii_lowercase();
+            url.set_host(Some(&trimmed)).ok()?;
+        }
+    }
+    if config.runs(UrlNormalizationStep::Https) && url.scheme() == "http" {
+        // Also drops an explicit :443, now the default port.
+        url.set_scheme("https").ok()?;
+    }
+    if config.runs(UrlNormalizationStep::Fragment) {
+        url.set_fragment(None);
+    }
+    if config.runs(UrlNormalizationStep::PercentEncoding) {
+        let path = normalize_percent_encoding(url.path());
+        url.set_path(&path);
+        if let Some(query) = url.query().map(normalize_percent_encoding) {
+            url.set_query(Some(&query));
+        }
+    }
+    if config.runs(UrlNormalizationStep::TrackingParams) {
+        if let Some(query) = url.query() {
+            let kept: Vec<&str> = query
+                .split('&')
+                .filter(|pair| {
+                    let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+                    !pair.is_empty() && !is_stripped_param(name, &config.strip_params)
+                })
+                .collect();
+            let kept = kept.join("&");
+            url.set_query((!kept.is_empty()).then_some(kept.as_str()));
+        }
+    }
+    if config.runs(UrlNormalizationStep::TrailingSlash) && url.path().len() > 1 {
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(&path);
+    }
+
+    Some(String::from(url))
+}
+
+/// Whether query parameter `name` is on `strip_params`, where a trailing `*`
+/// matches by prefix.
+fn is_stripped_param(name: &str, strip_params: &[String]) -> bool {
+    strip_params
+        .iter()
+        .any(|param| match param.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == param,
+        })
+}
+
+/// Decode `%XX` escapes of unreserved characters (RFC 3986 section 2.3)
+/// and uppercase the hex digits of every other escape.
+fn normalize_percent_encoding(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut output = String::with_capacity(input.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                output.push(char::from(byte));
+                index += 3;
+            }
+            Some(byte) => {
+                let _ = write!(output, "%{byte:02X}");
+                index += 3;
+            }
+            None => {
+                let next = input[index..]
+                    .chars()
+                    .next()
+                    .expect("index is on a char boundary");
+                output.push(next);
+                index += next.len_utf8();
+            }
+        }
+    }
+    output
+}
+
+/// Validate `raw` against `config` and return its normalized form.
+pub fn sanitize_destination(
+    raw: &str,
+    config: &DestinationConfig,
+) -> Result<String, DestinationError> {
+    // `Url::parse` silently strips tabs and newlines, so they must be caught
+    // on the raw input before parsing.
+    if raw.chars().any(char::is_control) {
+        return Err(DestinationError::ControlCharacter);
+    }
+
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err(DestinationError::Empty);
+    }
+    if trimmed.chars().any(char::is_whitespace) {
+        return Err(DestinationError::Whitespace);
+    }
+    if trimmed.len() > config.max_length {
+        return Err(DestinationError::TooLong {
+            max: config.max_length,
+        });
+    }
+
+    let url = Url::parse(trimmed).map_err(|error| DestinationError::Invalid(error.to_string()))?;
+    let scheme = url.scheme();
+    let allowed = WEB_SCHEMES.contains(&scheme)
+        || (is_configurable_scheme(scheme)
+            && config.extra_schemes.iter().any(|extra| extra == scheme));
+    if !allowed {
+        return Err(DestinationError::SchemeNotAllowed(scheme.to_string()));
+    }
+    if !config.allow_self_redirects {
+        if let Some(host) = url.host_str() {
+            if is_self_host(host, &config.self_hosts) {
+                return Err(DestinationError::SelfRedirect(host.to_string()));
+            }
+        }
+    }
+
+    // Normalization percent-encodes some characters, which can grow the URL.
+    let normalized = String::from(url);
+    if normalized.len() > config.max_length {
+        return Err(DestinationError::TooLong {
+            max: config.max_length,
+        });
+    }
+
+    Ok(normalized)
+}
+
+/// Build a `Location` header value from a stored destination.
+///
+/// CR and LF are stripped so a legacy row can never start a new header line,
+/// and every other byte outside visible ASCII (controls, spaces and non-ASCII
+/// UTF-8) is percent-encoded. Returns `None` when nothing usable remains.
+pub fn location_header(url: &str) -> Option<HeaderValue> {
+    let mut encoded = String::with_capacity(url.len());
+    for byte in url.bytes() {
+        match byte {
+            b'\r' | b'\n' => {}
+            b'!'..=b'~' => encoded.push(byte as char),
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+
+    if encoded.is_empty() {
+        return None;
+    }
+    HeaderValue::try_from(encoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DestinationConfig {
+        DestinationConfig {
+            max_length: 64,
+            extra_schemes: vec!["mailto".to_string(), "tel".to_string()],
+            self_hosts: vec!["short.example".to_string(), "go.example".to_string()],
+            allow_self_redirects: false,
+        }
+    }
+
+    #[test]
+    fn destination_hosts_are_lowercased_without_trailing_dots() {
+        for (url, host) in [
+            ("https://Docs.Example.com./a?b=c", Some("docs.example.com")),
+            ("http://127.0.0.1:8080/", Some("127.0.0.1")),
+            ("https://[::1]/x", Some("[::1]")),
+            ("mailto:team@example.com", None),
+            ("", None),
+            ("not a url", None),
+        ] {
+            assert_eq!(destination_host(url).as_deref(), host, "{url:?}");
+        }
+    }
+
+    #[test]
+    fn destinations_normalize_for_dedupe() {
+        let defaults = UrlNormalizationConfig::default();
+        let everything = UrlNormalizationConfig {
+            steps: UrlNormalizationStep::ALL.to_vec(),
+            ..UrlNormalizationConfig::default()
+        };
+        let nothing = UrlNormalizationConfig {
+            steps: Vec::new(),
+            ..UrlNormalizationConfig::default()
+        };
+        for (url, config, normalized) in [
+            // Always applied
+            (
+                "HTTPS://Example.COM/Path",
+                &nothing,
+                Some("https://example.com/Path"),
+            ),
+            (
+                "https://example.com:443/a",
+                &nothing,
+                Some("https://example.com/a"),
+            ),
+            (
+                "http://example.com:80/a",
+                &nothing,
+                Some("http://example.com/a"),
+            ),
+            (
+                "https://example.com./a",
+                &nothing,
+                Some("https://example.com/a"),
+            ),
+            (
+                "https://example.com:8443/a",
+                &nothing,
+                Some("https://example.com:8443/a"),
+            ),
+            (
+                "https://example.com/a/#top",
+                &nothing,
+                Some("https://example.com/a/#top"),
+            ),
+            // Fragment
+            (
+                "https://example.com/a#top",
+                &defaults,
+                Some("https://example.com/a"),
+            ),
+            // Trailing slash, except on the root path
+            (
+                "https://example.com/a/",
+                &defaults,
+                Some("https://example.com/a"),
+            ),
+            (
+                "https://example.com/a//",
+                &defaults,
+                Some("https://example.com/a"),
+            ),
+            (
+                "https://example.com/",
+                &defaults,
+                Some("https://example.com/"),
+            ),
+            (
+                "https://example.com",
+                &defaults,
+                Some("https://example.com/"),
+            ),
+            // Tracking parameters
+            (
+                "https://example.com/a?utm_source=x&id=7&fbclid=y",
+                &defaults,
+                Some("https://example.com/a?id=7"),
+            ),
+            (
+                "https://example.com/a?utm_medium=email",
+                &defaults,
+                Some("https://example.com/a"),
+            ),
+            (
+                "https://example.com/a?utm=1&gclid",
+                &defaults,
+                Some("https://example.com/a?utm=1"),
+            ),
+            (
+                "https://example.com/a?id=7&&",
+                &defaults,
+                Some("https://example.com/a?id=7"),
+            ),
+            // Percent-encoding
+            (
+                "https://example.com/%7euser/%2f",
+                &defaults,
+                Some("https://example.com/~user/%2F"),
+            ),
+            (
+                "https://example.com/a?q=%61%3d",
+                &defaults,
+                Some("https://example.com/a?q=a%3D"),
+            ),
+            (
+                "https://example.com/%zz",
+                &defaults,
+                Some("https://example.com/%zz"),
+            ),
+            (
+                "https://example.com/caf%C3%A9",
+                &defaults,
+                Some("https://example.com/caf%C3%A9"),
+            ),
+            // http and https only match when asked to
+            (
+                "http://example.com/a",
+                &defaults,
+                Some("http://example.com/a"),
+            ),
+            (
+                "http://example.com/a",
+                &everything,
+                Some("https://example.com/a"),
+            ),
+            (
+                "http://example.com:443/a",
+                &everything,
+                Some("https://example.com/a"),
+            ),
+            // Non-web and unparsable values
+            (
+                "mailto:Team@Example.com",
+                &defaults,
+                Some("mailto:Team@Example.com"),
+            ),
+            ("reserved", &defaults, None),
+            ("", &defaults, None),
+        ] {
+            assert_eq!(
+                normalize_url(url, config).as_deref(),
+                normalized,
+                "{url:?} with {:?}",
+                config.steps
+            );
+        }
+    }
+
+    #[test]
+    fn strip_params_match_names_and_prefixes() {
+        let strip = vec!["ref".to_string(), "mc_*".to_string()];
+        assert!(is_stripped_param("ref", &strip));
+        assert!(is_stripped_param("mc_cid", &strip));
+        assert!(!is_stripped_param("referrer", &strip));
+        assert!(!is_stripped_param("mc", &strip));
+    }
+
+    #[test]
+    fn web_urls_are_normalized() {
+        assert_eq!(
+            sanitize_destination("  HTTPS://Example.COM/path?q=1  ", &config()).unwrap(),
+            "https://example.com/path?q=1"
+        );
+        assert_eq!(
+            sanitize_destination("http://example.com", &config()).unwrap(),
+            "http://example.com/"
+        );
+    }
+
+    #[test]
+    fn interior_whitespace_is_rejected() {
+        for raw in ["https://example.com/a b", "https://example.com/\u{a0}x"] {
+            assert_eq!(
+                sanitize_destination(raw, &config()),
+                Err(DestinationError::Whitespace),
+                "{raw:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn header_injection_is_rejected() {
+        for raw in [
+            "https://example.com/\r\nSet-Cookie: a=b",
+            "https://example.com/%0d%0a\nX-Injected: 1",
+            "https://example.com/\n",
+            "https://exa\tmple.com/",
+            "https://example.com/\u{0}",
+            "https://example.com/\u{85}",
+        ] {
+            assert_eq!(
+                sanitize_destination(raw, &config()),
+                Err(DestinationError::ControlCharacter),
+                "{raw:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn encoded_crlf_stays_encoded() {
+        let url =
+            sanitize_destination("https://example.com/%0d%0aX-Injected:1", &config()).unwrap();
+        assert!(!url.contains('\r') && !url.contains('\n'));
+    }
+
+    #[test]
+    fn dangerous_schemes_are_rejected_even_if_configured() {
+        let config = DestinationConfig {
+            extra_schemes: vec!["javascript".to_string(), "data".to_string()],
+            ..config()
+        };
+        for raw in [
+            "javascript:alert(1)",
+            "data:text/html,<b>x</b>",
+            "file:///etc/passwd",
+        ] {
+            assert!(matches!(
+                sanitize_destination(raw, &config),
+                Err(DestinationError::SchemeNotAllowed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn extra_schemes_require_configuration() {
+        assert_eq!(
+            sanitize_destination("mailto:team@example.com", &config()).unwrap(),
+            "mailto:team@example.com"
+        );
+        assert_eq!(
+            sanitize_destination("mailto:team@example.com", &DestinationConfig::default()),
+            Err(DestinationError::SchemeNotAllowed("mailto".to_string()))
+        );
+        assert_eq!(
+            sanitize_destination("ftp://example.com/file", &config()),
+            Err(DestinationError::SchemeNotAllowed("ftp".to_string()))
+        );
+    }
+
+    #[test]
+    fn length_and_parse_errors_are_reported() {
+        let long = format!("https://example.com/{}", "a".repeat(64));
+        assert_eq!(
+            sanitize_destination(&long, &config()),
+            Err(DestinationError::TooLong { max: 64 })
+        );
+        assert_eq!(
+            sanitize_destination("   ", &config()),
+            Err(DestinationError::Empty)
+        );
+        assert!(matches!(
+            sanitize_destination("not a url", &config()),
+            Err(DestinationError::Whitespace)
+        ));
+        assert!(matches!(
+            sanitize_destination("example.com/path", &config()),
+            Err(DestinationError::Invalid(_))
+        ));
+        assert!(matches!(
+            sanitize_destination("https://", &config()),
+            Err(DestinationError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn destinations_on_our_own_hosts_are_rejected() {
+        for raw in [
+            "https://short.example/abc",
+            "http://SHORT.example./abc",
+            "https://go.example:8443/",
+        ] {
+            assert!(
+                matches!(
+                    sanitize_destination(raw, &config()),
+                    Err(DestinationError::SelfRedirect(_))
+                ),
+                "{raw:?}"
+            );
+        }
+        assert!(sanitize_destination("https://docs.short.example/abc", &config()).is_ok());
+
+        let allowed = DestinationConfig {
+            allow_self_redirects: true,
+            ..config()
+        };
+        assert_eq!(
+            sanitize_destination("https://short.example/abc", &allowed).unwrap(),
+            "https://short.example/abc"
+        );
+    }
+
+    #[test]
+    fn only_non_web_schemes_require_an_interstitial() {
+        assert!(!requires_interstitial("https://example.com/"));
+        assert!(!requires_interstitial("HTTP://example.com/"));
+        assert!(!requires_interstitial("/relative/path?at=12:00"));
+        assert!(!requires_interstitial("no-scheme"));
+        assert!(requires_interstitial("mailto:team@example.com"));
+        assert!(requires_interstitial("tel:+15551234567"));
+    }
+
+    #[test]
+    fn clean_locations_are_unchanged() {
+        let url = "https://example.com/path?q=1&r=%20#frag";
+        assert_eq!(location_header(url).unwrap(), url);
+    }
+
+    #[test]
+    fn location_strips_crlf_and_encodes_illegal_octets() {
+        assert_eq!(
+            location_header("https://example.com/a\r\nSet-Cookie: evil=1").unwrap(),
+            "https://example.com/aSet-Cookie:%20evil=1"
+        );
+        assert_eq!(
+            location_header("https://example.com/caf\u{e9}\t\u{0}\u{7f}").unwrap(),
+            "https://example.com/caf%C3%A9%09%00%7F"
+        );
+    }
+
+    #[test]
+    fn location_without_content_is_rejected() {
+        assert!(location_header("").is_none());
+        assert!(location_header("\r\n\r\n").is_none());
+    }
+}
@@ -0,0 +1,316 @@
+//! Flush scheduling shared by the click counter and the analytics aggregator.
+//!
+//! Replicas that start together and flush on identical fixed intervals hit the
+//! shared database in synchronized bursts. [`FlushTicker`] spreads each
+//! deadline by a random jitter, and [`FlushCoalescer`] lets idle or nearly-idle
+//! instances skip small flushes until enough work (or time) has accumulated.
+//! [`FlushBackoff`] spaces out retries while the database keeps failing them.
+//! Every flush ends with a [`FlushReport`] in the logs.
+
+use rand::distr::{Distribution, Uniform};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+use crate::config::FlushConfig;
+
+/// Upper bound for the configured jitter so a deadline never collapses to zero.
+pub const MAX_JITTER_PERCENT: u8 = 50;
+
+/// Smallest period a ticker will sleep for, guarding against busy loops.
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// Scale `period` by a random factor in `[1 - jitter, 1 + jitter]`.
+pub fn jittered(period: Duration, jitter_percent: u8) -> Duration {
+    let period = period.max(MIN_PERIOD);
+    let jitter_percent = jitter_percent.min(MAX_JITTER_PERCENT);
+    if jitter_percent == 0 {
+        return period;
+    }
+
+    let spread = f64::from(jitter_percent) / 100.0;
+    let factor = Uniform::new_inclusive(1.0 - spread, 1.0 + spread)
+        .map(|distribution| distribution.sample(&mut rand::rng()))
+        .unwrap_or(1.0);
+    period.mul_f64(factor).max(MIN_PERIOD)
+}
+
+/// A cancel-safe periodic ticker whose every period is independently jittered.
+///
+/// Like a `tokio::time::Interval` whose immediate first tick has been consumed,
+/// the first `tick()` completes one (jittered) period after construction.
+pub struct FlushTicker {
+    period: Duration,
+    jitter_percent: u8,
+    deadline: Instant,
+}
+
+impl FlushTicker {
+    pub fn new(period: Duration, jitter_percent: u8) -> Self {
+        Self {
+            period,
+            jitter_percent,
+            deadline: Instant::now() + jittered(period, jitter_percent),
+        }
+    }
+
+    /// Wait for the current deadline, then schedule the next one.
+    ///
+    /// Dropping the returned future before it completes leaves the deadline
+    /// untouched, so this is safe to use inside `tokio::select!`.
+    pub async fn tick(&mut self) {
+        time::sleep_until(self.deadline).await;
+        self.deadline = Instant::now() + jittered(self.period, self.jitter_percent);
+    }
+}
+
+/// Decides whether a periodic flush is worth a database round trip.
+///
+/// Empty flushes are always skipped. Below `min_pending` entries the flush is
+/// deferred, but never for more than `max_deferred_intervals` consecutive ticks,
+/// which bounds how stale persisted data can become.
+#[derive(Debug)]
+pub struct FlushCoalescer {
+    min_pending: usize,
+    max_deferred_intervals: u32,
+    deferred: u32,
+}
+
+impl FlushCoalescer {
+    pub fn new(config: &FlushConfig) -> Self {
+        Self {
+            min_pending: config.min_pending.max(1),
+            max_deferred_intervals: config.max_deferred_intervals,
+            deferred: 0,
+        }
+    }
+
+    /// Record one elapsed interval with `pending` entries and report whether
+    /// they should be flushed now.
+    pub fn should_flush(&mut self, pending: usize) -> bool {
+        if pending == 0 {
+            self.deferred = 0;
+            return false;
+        }
+
+        if pending >= self.min_pending || self.deferred >= self.max_deferred_intervals {
+            self.deferred = 0;
+            return true;
+        }
+
+        self.deferred += 1;
+        false
+    }
+}
+
+/// Longest run of intervals a failing flush is put off for.
+pub const MAX_BACKOFF_INTERVALS: u32 = 32;
+
+/// Skips flushes while the database keeps failing them.
+///
+/// After `n` consecutive failed flushes the next `2^(n-1)` intervals are
+/// skipped, up to [`MAX_BACKOFF_INTERVALS`], so an unreachable database is
+/// retried less and less often instead of on every tick. Pending work is kept
+/// by the caller and goes out with the first attempt that succeeds.
+#[derive(Debug, Default)]
+pub struct FlushBackoff {
+    skipped: u32,
+}
+
+impl FlushBackoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one elapsed interval after `failure_streak` consecutive failed
+    /// flushes and report whether to try flushing now.
+    pub fn should_attempt(&mut self, failure_streak: u32) -> bool {
+        if failure_streak == 0 || self.skipped >= backoff_intervals(failure_streak) {
+            self.skipped = 0;
+            return true;
+        }
+
+        self.skipped += 1;
+        false
+    }
+}
+
+/// Intervals skipped after `failure_streak` (at least one) failed flushes.
+fn backoff_intervals(failure_streak: u32) -> u32 {
+    1u32.checked_shl(failure_streak - 1)
+        .unwrap_or(u32::MAX)
+        .min(MAX_BACKOFF_INTERVALS)
+}
+
+/// One flush, logged as a structured `flush completed` event so operators
+/// can graph flush sizes, durations and lag from logs alone.
+///
+/// `lag` is the age of the oldest entry in the batch when the flush started:
+/// how long the stalest buffered click or visit waited to be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushReport {
+    /// Which flush ran: `clicks`, `analytics_events` (visits resolved into
+    /// aggregates) or `analytics_aggregates` (aggregates written to storage)
+    pub stage: &'static str,
+    /// Click counters, events or aggregate rows in the batch
+    pub entries: usize,
+    /// Approximate in-memory size of the batch
+    pub bytes: usize,
+    pub duration: Duration,
+    pub lag: Duration,
+    /// Whether the batch was written; failed batches are requeued
+    pub succeeded: bool,
+}
+
+impl FlushReport {
+    pub fn log(&self) {
+        tracing::info!(
+            stage = self.stage,
+            entries = self.entries,
+            bytes = self.bytes,
+            duration_ms = self.duration.as_millis() as u64,
+            lag_ms = self.lag.as_millis() as u64,
+            succeeded = self.succeeded,
+            "flush completed"
+        );
+    }
+}
+
+/// Captures `flush completed` events for tests.
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Fields of each captured flush event, rendered as strings.
+    pub(crate) type Captured = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+    struct FlushEvents(Captured);
+
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for FlushEvents {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            let mut fields = Fields(HashMap::new());
+            event.record(&mut fields);
+            if fields.0.get("message").map(String::as_str) == Some("flush completed") {
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+    }
+
+    /// Capture flush events on this thread until the guard is dropped. Tasks
+    /// spawned on a current-thread runtime are captured too.
+    pub(crate) fn flush_events() -> (Captured, tracing::subscriber::DefaultGuard) {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry().with(FlushEvents(Arc::clone(&captured)));
+        (captured, tracing::subscriber::set_default(subscriber))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(min_pending: usize, max_deferred_intervals: u32) -> FlushConfig {
+        FlushConfig {
+            jitter_percent: 0,
+            min_pending,
+            max_deferred_intervals,
+        }
+    }
+
+    #[test]
+    fn zero_jitter_keeps_the_configured_period() {
+        let period = Duration::from_secs(5);
+        assert_eq!(jittered(period, 0), period);
+    }
+
+    #[test]
+    fn jitter_stays_within_the_configured_spread() {
+        let period = Duration::from_secs(10);
+        for _ in 0..1_000 {
+            let value = jittered(period, 20);
+            assert!(value >= Duration::from_secs(8), "{value:?} below bound");
+            assert!(value <= Duration::from_secs(12), "{value:?} above bound");
+        }
+    }
+
+    #[test]
+    fn jitter_is_capped() {
+        let period = Duration::from_secs(10);
+        for _ in 0..1_000 {
+            assert!(jittered(period, u8::MAX) >= Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn empty_intervals_never_flush() {
+        let mut coalescer = FlushCoalescer::new(&config(1, 0));
+        assert!(!coalescer.should_flush(0));
+        assert!(!coalescer.should_flush(0));
+    }
+
+    #[test]
+    fn default_policy_flushes_any_pending_entry() {
+        let mut coalescer = FlushCoalescer::new(&FlushConfig::default());
+        assert!(coalescer.should_flush(1));
+    }
+
+    #[test]
+    fn small_batches_are_deferred_for_a_bounded_number_of_intervals() {
+        let mut coalescer = FlushCoalescer::new(&config(100, 2));
+        assert!(!coalescer.should_flush(3));
+        assert!(!coalescer.should_flush(4));
+        assert!(coalescer.should_flush(5));
+        assert!(!coalescer.should_flush(1));
+    }
+
+    #[test]
+    fn large_batches_flush_immediately() {
+        let mut coalescer = FlushCoalescer::new(&config(100, 10));
+        assert!(!coalescer.should_flush(3));
+        assert!(coalescer.should_flush(100));
+    }
+
+    #[test]
+    fn healthy_flushes_are_never_skipped() {
+        let mut backoff = FlushBackoff::new();
+        assert!((0..10).all(|_| backoff.should_attempt(0)));
+    }
+
+    #[test]
+    fn failed_flushes_back_off_exponentially_up_to_the_cap() {
+        let mut backoff = FlushBackoff::new();
+        let attempts = |backoff: &mut FlushBackoff, failure_streak: u32| {
+            (0..40)
+                .map(|_| backoff.should_attempt(failure_streak))
+                .position(|attempt| attempt)
+        };
+
+        assert_eq!(attempts(&mut backoff, 1), Some(1));
+        assert_eq!(attempts(&mut backoff, 2), Some(2));
+        assert_eq!(attempts(&mut backoff, 3), Some(4));
+        assert_eq!(
+            attempts(&mut backoff, 40),
+            Some(MAX_BACKOFF_INTERVALS as usize)
+        );
+
+        // A success resets the backoff.
+        assert!(backoff.should_attempt(0));
+        assert_eq!(attempts(&mut backoff, 1), Some(1));
+    }
+}
@@ -0,0 +1,264 @@
+//! The HTTP client for calls the server makes itself: OpenID discovery and
+//! JWKS fetches, Cloudflare Access certs, captcha verification and operator
+//! alert webhooks.
+//!
+//! Every one of them clones the same [`client`], so they share one
+//! connection pool, one set of connect and read timeouts, and one proxy
+//! setup. `OUTBOUND_PROXY_URL` sends all of them through a proxy; without
+//! it, `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY` apply by scheme. Hosts in
+//! `OUTBOUND_NO_PROXY`, or else `NO_PROXY`, are reached directly. Callers
+//! set their own overall timeout per request.
+//!
+//! Title fetches keep a client of their own that never uses a proxy: their
+//! private-address guard has to resolve the destination itself.
+
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::config::{redact_url, OutboundHttpConfig};
+
+/// Sent with every outbound request.
+pub const USER_AGENT: &str = concat!("Lynx/", env!("CARGO_PKG_VERSION"));
+
+/// Idle connections kept per host in the shared pool.
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+
+/// Idle pooled connections are closed after this long.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+static SHARED: OnceLock<Client> = OnceLock::new();
+
+/// Proxies outbound requests go through, by target scheme.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxySettings {
+    pub http: Option<String>,
+    pub https: Option<String>,
+    /// Comma-separated hosts, domains and networks reached directly
+    pub no_proxy: Option<String>,
+}
+
+impl ProxySettings {
+    /// Settings from `config`, falling back to the process environment.
+    pub fn from_env(config: &OutboundHttpConfig) -> Self {
+        Self::resolve(config, |name| std::env::var(name).ok())
+    }
+
+    /// Settings from `config`, falling back to the variables `env` returns.
+    /// Upper-case variables win over lower-case ones; empty ones are unset.
+    pub fn resolve(config: &OutboundHttpConfig, env: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            [name.to_ascii_uppercase(), name.to_ascii_lowercase()]
+                .into_iter()
+                .find_map(|name| env(&name).filter(|value| !value.trim().is_empty()))
+        };
+        let no_proxy = config.no_proxy.clone().or_else(|| var("no_proxy"));
+        if let Some(proxy) = &config.proxy_url {
+            return Self {
+                http: Some(proxy.clone()),
+                https: Some(proxy.clone()),
+                no_proxy,
+            };
+        }
+        let all = var("all_proxy");
+        Self {
+            http: var("http_proxy").or_else(|| all.clone()),
+            https: var("https_proxy").or(all),
+            no_proxy,
+        }
+    }
+
+    fn proxies(&self) -> reqwest::Result<Vec<Proxy>> {
+        let no_proxy = || self.no_proxy.as_deref().and_then(NoProxy::from_string);
+        let mut proxies = Vec::new();
+        if let Some(url) = &self.http {
+            proxies.push(Proxy::http(url)?.no_proxy(no_proxy()));
+        }
+        if let Some(url) = &self.https {
+            proxies.push(Proxy::https(url)?.no_proxy(no_proxy()));
+        }
+        Ok(proxies)
+    }
+}
+
+/// A client builder with the timeouts, pool limits and proxies of `config`.
+pub fn builder(config: &OutboundHttpConfig) -> reqwest::Result<ClientBuilder> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.read_timeout_secs))
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        // Proxies come from `ProxySettings` only, never from reqwest's own
+        // environment lookup
+        .no_proxy();
+    for proxy in ProxySettings::from_env(config).proxies()? {
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder)
+}
+
+/// Build the shared client from `config`. Call once at startup, before any
+/// component makes a request; a bad proxy URL is reported here. Later
+/// calls keep the first client.
+pub fn init(config: &OutboundHttpConfig) -> reqwest::Result<()> {
+    let client = builder(config)?.build()?;
+    let settings = ProxySettings::from_env(config);
+    tracing::info!(
+        http_proxy = settings.http.as_deref().map(redact_url),
+        https_proxy = settings.https.as_deref().map(redact_url),
+        no_proxy = settings.no_proxy,
+        "Outbound HTTP client ready"
+    );
+    let _ = SHARED.set(client);
+    Ok(())
+}
+
+/// The shared client. Without [`init`] it is built from the environment
+/// alone, as in CLI commands and tests.
+pub fn client() -> Client {
+    SHARED
+        .get_or_init(|| {
+            builder(&OutboundHttpConfig::default())
+                .and_then(ClientBuilder::build)
+                .unwrap_or_else(|error| {
+                    tracing::error!(%error, "Invalid proxy settings; outbound requests go direct");
+                    Client::new()
+                })
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn config(proxy_url: Option<&str>, no_proxy: Option<&str>) -> OutboundHttpConfig {
+        OutboundHttpConfig {
+            proxy_url: proxy_url.map(str::to_string),
+            no_proxy: no_proxy.map(str::to_string),
+            ..OutboundHttpConfig::default()
+        }
+    }
+
+    #[test]
+    fn environment_proxies_apply_by_scheme() {
+        let settings = ProxySettings::resolve(
+            &config(None, None),
+            env(&[
+                ("http_proxy", "http://lower:3128"),
+                ("HTTP_PROXY", "http://upper:3128"),
+                ("ALL_PROXY", "http://all:3128"),
+                ("no_proxy", "internal.example"),
+            ]),
+        );
+        assert_eq!(
+            settings,
+            ProxySettings {
+                http: Some("http://upper:3128".to_string()),
+                https: Some("http://all:3128".to_string()),
+                no_proxy: Some("internal.example".to_string()),
+            }
+        );
+        assert_eq!(
+            ProxySettings::resolve(&config(None, None), env(&[("HTTPS_PROXY", " ")])),
+            ProxySettings::default()
+        );
+    }
+
+    #[test]
+    fn explicit_settings_override_the_environment() {
+        let settings = ProxySettings::resolve(
+            &config(Some("http://explicit:8080"), Some("localhost")),
+            env(&[
+                ("HTTPS_PROXY", "http://env:3128"),
+                ("NO_PROXY", "internal.example"),
+            ]),
+        );
+        assert_eq!(
+            settings,
+            ProxySettings {
+                http: Some("http://explicit:8080".to_string()),
+                https: Some("http://explicit:8080".to_string()),
+                no_proxy: Some("localhost".to_string()),
+            }
+        );
+    }
+
+    /// Answer every HTTP request on a local port with `{}` and report its
+    /// request line.
+    async fn stub_server() -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0u8; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        let read = socket.read(&mut buffer).await.unwrap();
+                        if read == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let _ = sender.send(request.lines().next().unwrap_or_default().to_string());
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}")
+                        .await;
+                });
+            }
+        });
+        (format!("http://{address}"), receiver)
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_proxy_unless_exempt() {
+        let (proxy, mut proxied) = stub_server().await;
+        let (direct, mut reached_directly) = stub_server().await;
+        let client = builder(&config(Some(&proxy), Some("127.0.0.1")))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // The proxy sees the absolute URL of a host it forwards to
+        let response = client
+            .get("http://jwks.example.invalid/keys")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            proxied.recv().await.unwrap(),
+            "GET http://jwks.example.invalid/keys HTTP/1.1"
+        );
+
+        // NO_PROXY hosts are reached directly
+        client.get(format!("{direct}/direct")).send().await.unwrap();
+        assert_eq!(
+            reached_directly.recv().await.unwrap(),
+            "GET /direct HTTP/1.1"
+        );
+        assert!(proxied.try_recv().is_err());
+    }
+
+    #[test]
+    fn invalid_proxy_urls_are_rejected() {
+        assert!(builder(&config(Some("not a url"), None)).is_err());
+    }
+}
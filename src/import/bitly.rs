@@ -0,0 +1,106 @@
+//! Bitly CSV exports. Their columns have been named differently over time,
+//! so each field is found under any of the names Bitly has used. The short
+//! code is the last path segment of the bitlink (`bit.ly/3abcDEF`).
+
+use anyhow::{anyhow, Result};
+
+use super::csv::{self, field, Header};
+use super::{code_from_short_url, optional_fields, ImportedLink, ParsedImport, RowError};
+
+const SHORT_URL: &[&str] = &[
+    "bitlink",
+    "link",
+    "short url",
+    "short_url",
+    "short link",
+    "id",
+];
+const LONG_URL: &[&str] = &["long_url", "long url", "destination url", "original url"];
+const CREATED: &[&str] = &[
+    "created_at",
+    "created",
+    "created at",
+    "date created",
+    "creation date",
+];
+const CLICKS: &[&str] = &["clicks", "total clicks", "engagements"];
+
+pub(super) fn parse(input: &str) -> Result<ParsedImport> {
+    let records = csv::parse(input).map_err(|error| anyhow!("Bitly export: {error}"))?;
+    let Some((header, rows)) = records.split_first() else {
+        return Ok(ParsedImport::default());
+    };
+    let header = Header::new(header);
+    let short_url = header
+        .require(SHORT_URL)
+        .map_err(|error| anyhow!("Bitly export: {error}"))?;
+    let long_url = header
+        .require(LONG_URL)
+        .map_err(|error| anyhow!("Bitly export: {error}"))?;
+    let title = header.position(&["title"]);
+    let created = header.position(CREATED);
+    let clicks = header.position(CLICKS);
+
+    let mut parsed = ParsedImport::default();
+    for record in rows {
+        parsed.push((|| {
+            let short_code = field(record, Some(short_url))
+                .and_then(code_from_short_url)
+                .ok_or_else(|| RowError::new(record.line, "no bitlink"))?;
+            let original_url = field(record, Some(long_url))
+                .ok_or_else(|| RowError::new(record.line, "no long URL"))?;
+            let (clicks, created_at) =
+                optional_fields(record.line, field(record, clicks), field(record, created))?;
+            Ok(ImportedLink {
+                row: record.line,
+                short_code,
+                original_url: original_url.to_string(),
+                clicks,
+                created_at,
+                title: field(record, title).map(str::to_string),
+            })
+        })());
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_sample_export() {
+        let parsed = parse(include_str!("../../tests/fixtures/import/bitly.csv")).unwrap();
+        assert_eq!(parsed.links.len(), 3);
+        let promo = &parsed.links[0];
+        assert_eq!(promo.row, 2);
+        assert_eq!(promo.short_code, "3xYzAbC");
+        assert_eq!(
+            promo.original_url,
+            "https://example.com/promo?utm_source=bitly"
+        );
+        assert_eq!(promo.title.as_deref(), Some("Spring promo"));
+        assert_eq!(promo.clicks, 310);
+        // 2022-04-05T10:20:30+0000
+        assert_eq!(promo.created_at, Some(1_649_154_030_000));
+        // A quoted title spanning two lines keeps the next record's line
+        assert_eq!(parsed.links[1].title.as_deref(), Some("Line one\nline two"));
+        assert_eq!(parsed.links[2].row, 5);
+        assert_eq!(parsed.links[2].short_code, "custom-name");
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].row, 6);
+        assert_eq!(parsed.errors[0].reason, "no bitlink");
+    }
+
+    #[test]
+    fn finds_columns_under_other_names() {
+        let parsed = parse(
+            "Short URL,Destination URL,Total Clicks\nhttps://bit.ly/q,https://example.com,3\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.links[0].short_code, "q");
+        assert_eq!(parsed.links[0].clicks, 3);
+        assert!(parse("title\nx\n").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! A small RFC 4180 reader for the CSV exports of other shorteners.
+//!
+//! Fields may be quoted, with `""` for a quote inside them, and quoted
+//! fields may span lines. Lines end in LF or CRLF; blank lines are skipped.
+
+use std::collections::HashMap;
+
+/// A parsed record and the line it starts on (1-based).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Split `input` into records. Fails on a quote that is never closed.
+pub fn parse(input: &str) -> Result<Vec<Record>, String> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                fields.push(std::mem::take(&mut field));
+                push_record(&mut records, record_line, std::mem::take(&mut fields));
+                line += 1;
+                record_line = line;
+            }
+            ('\n', true) => {
+                line += 1;
+                field.push(c);
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "unterminated quoted field starting on line {record_line}"
+        ));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        push_record(&mut records, record_line, fields);
+    }
+    Ok(records)
+}
+
+fn push_record(records: &mut Vec<Record>, line: usize, fields: Vec<String>) {
+    let blank = fields.iter().all(|field| field.trim().is_empty());
+    if !blank {
+        records.push(Record { line, fields });
+    }
+}
+
+/// Column positions by lowercased, trimmed header name.
+#[derive(Debug)]
+pub struct Header(HashMap<String, usize>);
+
+impl Header {
+    pub fn new(record: &Record) -> Self {
+        Self(
+            record
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (name.trim().to_ascii_lowercase(), index))
+                .collect(),
+        )
+    }
+
+    /// Position of the first of `names` present in the header.
+    pub fn position(&self, names: &[&str]) -> Option<usize> {
+        names.iter().find_map(|name| self.0.get(*name).copied())
+    }
+
+    /// Position of the first of `names`, or an error naming the column.
+    pub fn require(&self, names: &[&str]) -> Result<usize, String> {
+        self.position(names)
+            .ok_or_else(|| format!("missing a `{}` column", names[0]))
+    }
+}
+
+/// The trimmed field at `index`, or `None` when absent or blank.
+pub fn field(record: &Record, index: Option<usize>) -> Option<&str> {
+    let value = record.fields.get(index?)?.trim();
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quotes_crlf_and_multiline_fields() {
+        let records = parse("a,b\r\n\"x, \"\"y\"\"\",2\n\n\"multi\nline\",3").unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].fields, ["x, \"y\"", "2"]);
+        assert_eq!(records[1].line, 2);
+        assert_eq!(records[2].fields, ["multi\nline", "3"]);
+        assert_eq!(records[2].line, 4);
+        assert!(parse("a,\"open").is_err());
+    }
+}
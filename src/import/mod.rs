@@ -0,0 +1,417 @@
+//! Import links exported from other URL shorteners (`lynx import`).
+//!
+//! Each supported format has a parser that maps its fields (short code or
+//! short URL, long URL, clicks, creation date, title) onto
+//! [`ImportedLink`]. Rows it cannot read become [`RowError`]s rather than
+//! failing the whole file. [`plan`] then validates the links the way the API
+//! validates custom codes and destinations and checks which codes are taken
+//! already, and [`apply`] writes the rest through [`Storage::import_urls`],
+//! which keeps their clicks and creation dates. Imported links are recorded
+//! as created via `import`.
+
+mod bitly;
+pub mod csv;
+mod shlink;
+mod yourls;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::config::DestinationConfig;
+use crate::destination::sanitize_destination;
+use crate::models::{CreatedVia, LinkOptions, ShortenedUrl};
+use crate::storage::Storage;
+
+/// Export formats `lynx import` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// CSV of the YOURLS `yourls_url` table: keyword, url, title, timestamp, clicks
+    Yourls,
+    /// JSON from Shlink's `GET /rest/v3/short-urls` (or `shortUrls.data` alone)
+    Shlink,
+    /// Bitly's CSV link export
+    BitlyCsv,
+}
+
+/// A link read from an export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportedLink {
+    /// Line of the CSV record, or position in a JSON list (1-based)
+    pub row: usize,
+    pub short_code: String,
+    pub original_url: String,
+    pub clicks: i64,
+    /// Creation time in milliseconds since the Unix epoch, when exported
+    pub created_at: Option<i64>,
+    pub title: Option<String>,
+}
+
+/// A row that is not imported, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    pub reason: String,
+}
+
+impl RowError {
+    fn new(row: usize, reason: impl Into<String>) -> Self {
+        Self {
+            row,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// What a parser read from an export.
+#[derive(Debug, Default)]
+pub struct ParsedImport {
+    pub links: Vec<ImportedLink>,
+    pub errors: Vec<RowError>,
+}
+
+impl ParsedImport {
+    fn push(&mut self, row: Result<ImportedLink, RowError>) {
+        match row {
+            Ok(link) => self.links.push(link),
+            Err(error) => self.errors.push(error),
+        }
+    }
+}
+
+/// Parse an export in `format`. Fails only when the file as a whole cannot
+/// be read, such as a CSV without the required columns.
+pub fn parse(format: ImportFormat, input: &str) -> Result<ParsedImport> {
+    match format {
+        ImportFormat::Yourls => yourls::parse(input),
+        ImportFormat::Shlink => shlink::parse(input),
+        ImportFormat::BitlyCsv => bitly::parse(input),
+    }
+}
+
+/// Read a creation date as milliseconds since the Unix epoch: RFC 3339 or
+/// ISO 8601 with an offset, a date and time without one (taken as UTC), a
+/// bare date, or Unix seconds.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<i64>() {
+        return seconds.checked_mul(1000);
+    }
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Some(parsed.timestamp_millis());
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%z",
+        "%Y-%m-%d %H:%M:%S%z",
+        "%Y-%m-%d %H:%M:%S %z",
+    ] {
+        if let Ok(parsed) = DateTime::parse_from_str(value, format) {
+            return Some(parsed.timestamp_millis());
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(parsed.and_utc().timestamp_millis());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(|date| {
+            date.and_time(chrono::NaiveTime::MIN)
+                .and_utc()
+                .timestamp_millis()
+        })
+}
+
+/// The code of a short URL such as `https://bit.ly/3abcDEF` or `bit.ly/x`:
+/// its last path segment. A value without a slash is taken as the code.
+pub fn code_from_short_url(value: &str) -> Option<String> {
+    let value = value.trim();
+    let without_scheme = value.split_once("://").map_or(value, |(_, rest)| rest);
+    let path = without_scheme.split(['?', '#']).next().unwrap_or_default();
+    let code = match path.split_once('/') {
+        Some((_, path)) => path.rsplit('/').find(|segment| !segment.is_empty()),
+        None => Some(path),
+    }?;
+    (!code.is_empty()).then(|| code.to_string())
+}
+
+/// Read the optional clicks and creation date fields shared by every format.
+fn optional_fields(
+    row: usize,
+    clicks: Option<&str>,
+    created: Option<&str>,
+) -> Result<(i64, Option<i64>), RowError> {
+    let clicks = match clicks {
+        None => 0,
+        Some(value) => value
+            .parse::<i64>()
+            .ok()
+            .filter(|clicks| *clicks >= 0)
+            .ok_or_else(|| RowError::new(row, format!("unreadable click count '{value}'")))?,
+    };
+    let created_at = created
+        .map(|value| {
+            parse_timestamp(value)
+                .ok_or_else(|| RowError::new(row, format!("unreadable creation date '{value}'")))
+        })
+        .transpose()?;
+    Ok((clicks, created_at))
+}
+
+/// What an import will do: links to create, links whose code is taken in
+/// this instance, and rows that are not imported.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportPlan {
+    pub create: Vec<ImportedLink>,
+    pub conflicts: Vec<ImportedLink>,
+    pub rejected: Vec<RowError>,
+}
+
+/// Validate parsed links and sort them into an [`ImportPlan`]. Codes may be
+/// up to `max_code_length` long and must fit in a URL path, destinations are
+/// normalized like API destinations, and a code repeated in the file is
+/// imported once.
+pub async fn plan(
+    storage: &dyn Storage,
+    parsed: ParsedImport,
+    max_code_length: usize,
+    destination: &DestinationConfig,
+) -> Result<ImportPlan> {
+    let mut plan = ImportPlan {
+        rejected: parsed.errors,
+        ..Default::default()
+    };
+    let mut first_row: HashMap<String, usize> = HashMap::new();
+    let mut candidates = Vec::new();
+    for mut link in parsed.links {
+        if link.short_code.len() > max_code_length {
+            plan.rejected.push(RowError::new(
+                link.row,
+                format!(
+                    "code '{}' is longer than SHORT_CODE_MAX_LENGTH ({max_code_length})",
+                    link.short_code
+                ),
+            ));
+            continue;
+        }
+        if link
+            .short_code
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '/' | '?' | '#'))
+        {
+            plan.rejected.push(RowError::new(
+                link.row,
+                format!("code '{}' cannot be used in a URL path", link.short_code),
+            ));
+            continue;
+        }
+        match sanitize_destination(&link.original_url, destination) {
+            Ok(url) => link.original_url = url,
+            Err(error) => {
+                plan.rejected
+                    .push(RowError::new(link.row, error.to_string()));
+                continue;
+            }
+        }
+        if let Some(first) = first_row.get(&link.short_code) {
+            plan.rejected.push(RowError::new(
+                link.row,
+                format!("code '{}' already appears on row {first}", link.short_code),
+            ));
+            continue;
+        }
+        first_row.insert(link.short_code.clone(), link.row);
+        candidates.push(link);
+    }
+
+    let codes: Vec<String> = candidates
+        .iter()
+        .map(|link| link.short_code.clone())
+        .collect();
+    let taken: HashSet<String> = storage
+        .get_many(&codes)
+        .await?
+        .into_iter()
+        .map(|url| url.short_code.clone())
+        .collect();
+    for link in candidates {
+        if taken.contains(&link.short_code) {
+            plan.conflicts.push(link);
+        } else {
+            plan.create.push(link);
+        }
+    }
+    plan.rejected.sort_by_key(|error| error.row);
+    Ok(plan)
+}
+
+/// What an import wrote.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub created: u64,
+    /// Planned links whose code was taken between planning and writing
+    pub lost_races: u64,
+    /// Clicks carried over by the planned links
+    pub clicks: i64,
+}
+
+/// Write the planned links in batches of `batch_size`, owned by
+/// `created_by`. Links without an exported creation date are dated `now_ms`.
+pub async fn apply(
+    storage: &dyn Storage,
+    plan: &ImportPlan,
+    created_by: Option<&str>,
+    now_ms: i64,
+    batch_size: usize,
+) -> Result<ImportReport> {
+    anyhow::ensure!(batch_size > 0, "batch size must be positive");
+    let mut report = ImportReport::default();
+    for batch in plan.create.chunks(batch_size) {
+        let urls: Vec<Arc<ShortenedUrl>> = batch
+            .iter()
+            .map(|link| Arc::new(to_url(link, created_by, now_ms)))
+            .collect();
+        let inserted = storage.import_urls(&urls, false).await?;
+        report.created += inserted;
+        report.lost_races += batch.len() as u64 - inserted;
+    }
+    report.clicks = plan.create.iter().map(|link| link.clicks).sum();
+    Ok(report)
+}
+
+fn to_url(link: &ImportedLink, created_by: Option<&str>, now_ms: i64) -> ShortenedUrl {
+    let created_at = link.created_at.unwrap_or(now_ms);
+    ShortenedUrl {
+        id: 0,
+        short_code: link.short_code.clone(),
+        original_url: link.original_url.clone(),
+        created_at,
+        created_by: created_by.map(str::to_string),
+        created_by_auth_method: None,
+        clicks: link.clicks,
+        is_active: true,
+        reserved_until: None,
+        alias_of: None,
+        title: link.title.clone(),
+        created_via: CreatedVia::Import,
+        last_visited_at: None,
+        updated_at: created_at,
+        options: LinkOptions::default(),
+        campaign_id: None,
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    #[test]
+    fn timestamps_in_export_formats() {
+        // 2021-03-04T05:06:07Z
+        let expected = Some(1_614_834_367_000);
+        assert_eq!(parse_timestamp("2021-03-04T05:06:07Z"), expected);
+        assert_eq!(parse_timestamp("2021-03-04T07:06:07+02:00"), expected);
+        assert_eq!(parse_timestamp("2021-03-04T05:06:07+0000"), expected);
+        assert_eq!(parse_timestamp("2021-03-04 05:06:07"), expected);
+        assert_eq!(parse_timestamp(" 1614834367 "), expected);
+        assert_eq!(parse_timestamp("2021-03-04"), Some(1_614_816_000_000));
+        assert_eq!(parse_timestamp("04/03/2021"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+
+    #[test]
+    fn codes_from_short_urls() {
+        let code = |value| code_from_short_url(value);
+        assert_eq!(code("https://bit.ly/3abcDEF").as_deref(), Some("3abcDEF"));
+        assert_eq!(code("bit.ly/abc/").as_deref(), Some("abc"));
+        assert_eq!(
+            code("http://s.example.com/x?ref=1#top").as_deref(),
+            Some("x")
+        );
+        assert_eq!(code("plain").as_deref(), Some("plain"));
+        assert_eq!(code("https://bit.ly/"), None);
+        assert_eq!(code(""), None);
+    }
+
+    fn imported(row: usize, code: &str, url: &str) -> ImportedLink {
+        ImportedLink {
+            row,
+            short_code: code.to_string(),
+            original_url: url.to_string(),
+            clicks: 0,
+            created_at: None,
+            title: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_sorts_rows_and_apply_keeps_their_history() {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        storage
+            .create_with_code("taken", "https://example.com/mine", Some("owner"))
+            .await
+            .unwrap();
+
+        let parsed = ParsedImport {
+            links: vec![
+                ImportedLink {
+                    clicks: 17,
+                    created_at: Some(1_600_000_000_000),
+                    title: Some("Old".to_string()),
+                    ..imported(2, "old", "https://example.com/old")
+                },
+                imported(3, "taken", "https://example.com/theirs"),
+                imported(4, "old", "https://example.com/again"),
+                imported(5, "far-too-long-for-this", "https://example.com/long"),
+                imported(6, "a/b", "https://example.com/slash"),
+                imported(7, "js", "javascript:alert(1)"),
+                imported(8, "new", "https://example.com/new"),
+            ],
+            errors: vec![RowError::new(1, "no url")],
+        };
+        let plan = plan(&storage, parsed, 12, &DestinationConfig::default())
+            .await
+            .unwrap();
+        let codes = |links: &[ImportedLink]| -> Vec<String> {
+            links.iter().map(|link| link.short_code.clone()).collect()
+        };
+        assert_eq!(codes(&plan.create), ["old", "new"]);
+        assert_eq!(codes(&plan.conflicts), ["taken"]);
+        let rejected: Vec<usize> = plan.rejected.iter().map(|error| error.row).collect();
+        assert_eq!(rejected, [1, 4, 5, 6, 7]);
+        assert!(plan.rejected[1].reason.contains("row 2"));
+
+        let report = apply(&storage, &plan, Some("importer"), 1_700_000_000_000, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                created: 2,
+                lost_races: 0,
+                clicks: 17
+            }
+        );
+
+        let old = storage.get("old").await.unwrap().unwrap();
+        assert_eq!(old.clicks, 17);
+        assert_eq!(old.created_at, 1_600_000_000_000);
+        assert_eq!(old.title.as_deref(), Some("Old"));
+        assert_eq!(old.created_by.as_deref(), Some("importer"));
+        assert_eq!(old.created_via, CreatedVia::Import);
+        let new = storage.get("new").await.unwrap().unwrap();
+        assert_eq!(new.created_at, 1_700_000_000_000);
+        let untouched = storage.get("taken").await.unwrap().unwrap();
+        assert_eq!(untouched.original_url, "https://example.com/mine");
+
+        // Applying the same plan again finds every code taken
+        let again = apply(&storage, &plan, None, 0, 10).await.unwrap();
+        assert_eq!(again.created, 0);
+        assert_eq!(again.lost_races, 2);
+    }
+}
@@ -0,0 +1,86 @@
+//! Shlink exports: the JSON body of `GET /rest/v3/short-urls` (all pages
+//! requested with `itemsPerPage=-1`), or its `shortUrls.data` list alone.
+//! Each entry has `shortCode`, `longUrl`, `dateCreated`, `title` and a visit
+//! count in `visitsSummary.total` (`visitsCount` before Shlink 3).
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use super::{optional_fields, ImportedLink, ParsedImport, RowError};
+
+pub(super) fn parse(input: &str) -> Result<ParsedImport> {
+    let document: Value = serde_json::from_str(input).context("Shlink export is not JSON")?;
+    let entries = document
+        .pointer("/shortUrls/data")
+        .unwrap_or(&document)
+        .as_array()
+        .ok_or_else(|| anyhow!("Shlink export: expected shortUrls.data or a list of short URLs"))?;
+
+    let mut parsed = ParsedImport::default();
+    for (index, entry) in entries.iter().enumerate() {
+        parsed.push(link(index + 1, entry));
+    }
+    Ok(parsed)
+}
+
+fn link(row: usize, entry: &Value) -> Result<ImportedLink, RowError> {
+    let text = |key: &str| {
+        entry
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let short_code = text("shortCode").ok_or_else(|| RowError::new(row, "no shortCode"))?;
+    let original_url = text("longUrl").ok_or_else(|| RowError::new(row, "no longUrl"))?;
+    let visits = entry
+        .pointer("/visitsSummary/total")
+        .or_else(|| entry.get("visitsCount"))
+        .filter(|visits| !visits.is_null())
+        .map(|visits| visits.to_string());
+    let (clicks, created_at) = optional_fields(row, visits.as_deref(), text("dateCreated"))?;
+    Ok(ImportedLink {
+        row,
+        short_code: short_code.to_string(),
+        original_url: original_url.to_string(),
+        clicks,
+        created_at,
+        title: text("title").map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_sample_export() {
+        let parsed = parse(include_str!("../../tests/fixtures/import/shlink.json")).unwrap();
+        assert_eq!(parsed.links.len(), 2);
+        let launch = &parsed.links[0];
+        assert_eq!(launch.row, 1);
+        assert_eq!(launch.short_code, "launch");
+        assert_eq!(launch.original_url, "https://example.com/launch");
+        assert_eq!(launch.title.as_deref(), Some("Launch day"));
+        assert_eq!(launch.clicks, 1234);
+        // 2023-05-06T07:08:09+02:00
+        assert_eq!(launch.created_at, Some(1_683_349_689_000));
+        // Shlink 2 visit counts
+        assert_eq!(parsed.links[1].clicks, 5);
+        assert_eq!(parsed.links[1].title, None);
+
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(parsed.errors[0].row, 3);
+        assert_eq!(parsed.errors[0].reason, "no longUrl");
+        assert!(parsed.errors[1].reason.contains("creation date"));
+    }
+
+    #[test]
+    fn accepts_a_bare_list_and_rejects_other_json() {
+        let parsed = parse(r#"[{"shortCode": "a", "longUrl": "https://example.com"}]"#).unwrap();
+        assert_eq!(parsed.links[0].clicks, 0);
+        assert_eq!(parsed.links[0].created_at, None);
+        assert!(parse(r#"{"shortUrls": {}}"#).is_err());
+        assert!(parse("keyword,url").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! YOURLS exports: the `yourls_url` table as CSV (as phpMyAdmin, `mysql
+//! --batch` converted to CSV, or the export plugins write it), with a header
+//! row naming `keyword`, `url` and optionally `title`, `timestamp` and
+//! `clicks`. Timestamps are the server's local time, read as UTC.
+
+use anyhow::{anyhow, Result};
+
+use super::csv::{self, field, Header};
+use super::{optional_fields, ImportedLink, ParsedImport, RowError};
+
+pub(super) fn parse(input: &str) -> Result<ParsedImport> {
+    let records = csv::parse(input).map_err(|error| anyhow!("YOURLS export: {error}"))?;
+    let Some((header, rows)) = records.split_first() else {
+        return Ok(ParsedImport::default());
+    };
+    let header = Header::new(header);
+    let keyword = header
+        .require(&["keyword"])
+        .map_err(|error| anyhow!("YOURLS export: {error}"))?;
+    let url = header
+        .require(&["url"])
+        .map_err(|error| anyhow!("YOURLS export: {error}"))?;
+    let title = header.position(&["title"]);
+    let timestamp = header.position(&["timestamp"]);
+    let clicks = header.position(&["clicks"]);
+
+    let mut parsed = ParsedImport::default();
+    for record in rows {
+        parsed.push((|| {
+            let short_code = field(record, Some(keyword))
+                .ok_or_else(|| RowError::new(record.line, "no keyword"))?;
+            let original_url =
+                field(record, Some(url)).ok_or_else(|| RowError::new(record.line, "no url"))?;
+            let (clicks, created_at) =
+                optional_fields(record.line, field(record, clicks), field(record, timestamp))?;
+            Ok(ImportedLink {
+                row: record.line,
+                short_code: short_code.to_string(),
+                original_url: original_url.to_string(),
+                clicks,
+                created_at,
+                title: field(record, title).map(str::to_string),
+            })
+        })());
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_sample_export() {
+        let parsed = parse(include_str!("../../tests/fixtures/import/yourls.csv")).unwrap();
+        assert_eq!(parsed.links.len(), 3);
+        let docs = &parsed.links[0];
+        assert_eq!(docs.row, 2);
+        assert_eq!(docs.short_code, "docs");
+        assert_eq!(docs.original_url, "https://example.com/docs?page=1");
+        assert_eq!(docs.title.as_deref(), Some("Docs, \"v2\""));
+        assert_eq!(docs.clicks, 42);
+        // 2021-03-04 05:06:07 UTC
+        assert_eq!(docs.created_at, Some(1_614_834_367_000));
+        assert_eq!(parsed.links[2].title, None);
+        assert_eq!(parsed.links[2].clicks, 0);
+
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(parsed.errors[0].row, 3);
+        assert!(parsed.errors[0].reason.contains("click count"));
+        assert_eq!(parsed.errors[1].reason, "no url");
+    }
+
+    #[test]
+    fn requires_keyword_and_url_columns() {
+        let error = parse("code,url\nx,https://example.com\n").unwrap_err();
+        assert!(error.to_string().contains("keyword"));
+        assert!(parse("").unwrap().links.is_empty());
+    }
+}
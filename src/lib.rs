@@ -1,8 +1,26 @@
+#[cfg(not(any(feature = "sqlite", feature = "postgres")))]
+compile_error!("enable at least one database backend feature: `sqlite` or `postgres`");
+
+pub mod alerts;
 pub mod analytics;
 pub mod api;
 pub mod auth;
+pub mod challenge;
+pub mod clock;
 pub mod config;
+pub mod confirm;
 pub mod cursor;
+pub mod destination;
+pub mod flush;
+pub mod http;
+pub mod import;
 pub mod models;
+pub mod notify;
+pub mod paging;
 pub mod redirect;
+pub mod shutdown;
 pub mod storage;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+pub mod timezone;
+pub mod title;
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One of the users who created the most links on a day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityCreator {
+    pub user_id: String,
+    pub links_created: i64,
+}
+
+/// One of the most clicked links on a day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityLink {
+    pub short_code: String,
+    pub clicks: i64,
+}
+
+/// One UTC day of instance activity. Aliases and unfilled reservations are
+/// not counted as links.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityDay {
+    /// Start of the day (Unix seconds)
+    pub day: i64,
+    /// Links created during the day
+    pub links_created: i64,
+    /// Clicks flushed during the day, from the hourly click history
+    pub clicks: i64,
+    /// Visits recorded by analytics during the day
+    pub visits: i64,
+    /// Most links created by one user, most first
+    pub top_creators: Vec<ActivityCreator>,
+    /// Most clicked links, most first
+    pub top_links: Vec<ActivityLink>,
+}
+
+impl ActivityDay {
+    fn empty(day: i64) -> Self {
+        Self {
+            day,
+            links_created: 0,
+            clicks: 0,
+            visits: 0,
+            top_creators: Vec::new(),
+            top_links: Vec::new(),
+        }
+    }
+}
+
+/// A row of a per-day ranking: the day's total, and one of its top entries
+/// with its count. `key` is `None` for a day whose only entries are not
+/// ranked (links without a creator), which still carries the total.
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct DailyRanking {
+    pub day: i64,
+    pub key: Option<String>,
+    pub count: i64,
+    pub total: i64,
+}
+
+/// One day per `day_secs` step from `since` (a day start) up to but not
+/// including `until`, filled from the per-day creator and link rankings
+/// (each ordered by day, then rank) and visit totals `(day, visits)`. Days
+/// without activity are kept with zeros.
+pub fn activity_series(
+    since: i64,
+    until: i64,
+    day_secs: i64,
+    creators: Vec<DailyRanking>,
+    links: Vec<DailyRanking>,
+    visits: Vec<(i64, i64)>,
+) -> Vec<ActivityDay> {
+    let mut days: Vec<ActivityDay> = (since..until)
+        .step_by(day_secs as usize)
+        .map(ActivityDay::empty)
+        .collect();
+    let index =
+        |day: i64| (day >= since && day < until).then(|| ((day - since) / day_secs) as usize);
+
+    for row in creators {
+        if let Some(day) = index(row.day).and_then(|i| days.get_mut(i)) {
+            day.links_created = row.total;
+            if let Some(user_id) = row.key {
+                day.top_creators.push(ActivityCreator {
+                    user_id,
+                    links_created: row.count,
+                });
+            }
+        }
+    }
+    for row in links {
+        if let Some(day) = index(row.day).and_then(|i| days.get_mut(i)) {
+            day.clicks = row.total;
+            if let Some(short_code) = row.key {
+                day.top_links.push(ActivityLink {
+                    short_code,
+                    clicks: row.count,
+                });
+            }
+        }
+    }
+    for (day, visits) in visits {
+        if let Some(day) = index(day).and_then(|i| days.get_mut(i)) {
+            day.visits = visits;
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(day: i64, key: Option<&str>, count: i64, total: i64) -> DailyRanking {
+        DailyRanking {
+            day,
+            key: key.map(str::to_string),
+            count,
+            total,
+        }
+    }
+
+    #[test]
+    fn series_covers_every_day_and_ignores_rows_outside_it() {
+        let series = activity_series(
+            10,
+            40,
+            10,
+            vec![
+                ranking(10, Some("alice"), 3, 4),
+                ranking(10, Some("bob"), 1, 4),
+                ranking(30, None, 2, 2),
+                ranking(40, Some("carol"), 9, 9),
+            ],
+            vec![
+                ranking(20, Some("abc"), 7, 8),
+                ranking(0, Some("old"), 1, 1),
+            ],
+            vec![(20, 5), (30, 1)],
+        );
+
+        let days: Vec<_> = series.iter().map(|day| day.day).collect();
+        assert_eq!(days, [10, 20, 30]);
+        assert_eq!(series[0].links_created, 4);
+        assert_eq!(
+            series[0].top_creators,
+            [
+                ActivityCreator {
+                    user_id: "alice".to_string(),
+                    links_created: 3
+                },
+                ActivityCreator {
+                    user_id: "bob".to_string(),
+                    links_created: 1
+                },
+            ]
+        );
+        assert_eq!((series[1].clicks, series[1].visits), (8, 5));
+        assert_eq!(series[1].top_links[0].short_code, "abc");
+        assert_eq!(series[2].links_created, 2);
+        assert!(series[2].top_creators.is_empty());
+    }
+}
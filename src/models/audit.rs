@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// An action an admin took on behalf of another user, such as creating a link
+/// that the user owns.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// What was done: `create`, `update` or `hard_delete`
+    pub action: String,
+    pub short_code: String,
+    /// The admin who made the request
+    pub actor: Option<String>,
+    /// The user the action was taken for, who owns the resulting link
+    pub effective_user: String,
+    pub created_at: i64,
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::analytics::AnalyticsAggregate;
+
+/// A named group of links whose clicks and visits are reported together.
+///
+/// Links join a campaign through `urls.campaign_id`; a link is in at most
+/// one. Deleting a campaign leaves its links as they are, outside any
+/// campaign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct Campaign {
+    pub id: i64,
+    pub name: String,
+    /// User who created the campaign; only they and admins manage it
+    pub owner: Option<String>,
+    /// Creation time in milliseconds since the Unix epoch
+    pub created_at: i64,
+}
+
+/// Clicks and visits of one link in a campaign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct CampaignLinkStats {
+    pub short_code: String,
+    /// Clicks since the link was created
+    pub clicks: i64,
+    /// Visits in the requested range, from visitor analytics
+    pub visits: i64,
+}
+
+/// Clicks and visitor analytics summed over the links in a campaign.
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignStats {
+    /// Member links, most clicked first
+    pub links: Vec<CampaignLinkStats>,
+    pub total_clicks: i64,
+    pub total_visits: i64,
+    /// Visits in the requested range by country, most first
+    pub countries: Vec<AnalyticsAggregate>,
+}
+
+impl CampaignStats {
+    /// Stats for `links`, with the totals summed from them.
+    pub fn new(links: Vec<CampaignLinkStats>, countries: Vec<AnalyticsAggregate>) -> Self {
+        Self {
+            total_clicks: links.iter().map(|link| link.clicks).sum(),
+            total_visits: links.iter().map(|link| link.visits).sum(),
+            links,
+            countries,
+        }
+    }
+}
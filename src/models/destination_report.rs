@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Active links pointing at one destination host, counted together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct DestinationHostSummary {
+    /// Lowercased host, as stored in `urls.dest_host`
+    pub dest_host: String,
+    pub links: i64,
+    /// Clicks across all of these links
+    pub clicks: i64,
+    /// Distinct creators; anonymous links are not counted
+    pub owners: i64,
+}
+
+/// An active link in a destination host report, with its owner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct DestinationHostLink {
+    pub dest_host: String,
+    pub short_code: String,
+    pub original_url: String,
+    pub created_by: Option<String>,
+    /// Email of the creator's account under the auth method they created the
+    /// link with; `None` when unknown
+    pub owner_email: Option<String>,
+    pub clicks: i64,
+    /// When the link was created, in milliseconds since the Unix epoch
+    pub created_at: i64,
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One UTC day of instance-wide numbers, recorded once the day is over.
+/// Aliases and unfilled reservations are not counted as links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct InstanceStatsDay {
+    /// Start of the day (Unix seconds)
+    pub day: i64,
+    /// Links created by the end of the day
+    pub total_links: i64,
+    /// Of those, the ones still active when the day was recorded
+    pub active_links: i64,
+    /// Links created during the day
+    pub links_created: i64,
+    /// Clicks during the day, from the hourly click history
+    pub clicks: i64,
+    /// Distinct users who created a link during the day
+    pub active_users: i64,
+}
+
+/// A day of the stats history. Days that were never recorded (the server
+/// was down when they ended) keep their place with every number `null`, so
+/// charts show a gap instead of a jump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct InstanceStatsPoint {
+    pub day: i64,
+    pub total_links: Option<i64>,
+    pub active_links: Option<i64>,
+    pub links_created: Option<i64>,
+    pub clicks: Option<i64>,
+    pub active_users: Option<i64>,
+}
+
+impl InstanceStatsPoint {
+    fn missing(day: i64) -> Self {
+        Self {
+            day,
+            total_links: None,
+            active_links: None,
+            links_created: None,
+            clicks: None,
+            active_users: None,
+        }
+    }
+}
+
+impl From<InstanceStatsDay> for InstanceStatsPoint {
+    fn from(stats: InstanceStatsDay) -> Self {
+        Self {
+            day: stats.day,
+            total_links: Some(stats.total_links),
+            active_links: Some(stats.active_links),
+            links_created: Some(stats.links_created),
+            clicks: Some(stats.clicks),
+            active_users: Some(stats.active_users),
+        }
+    }
+}
+
+/// One point per `day_secs` step from `since` (a day start) up to but not
+/// including `until`, filled from `recorded` (sorted by day, oldest first).
+pub fn daily_series(
+    since: i64,
+    until: i64,
+    day_secs: i64,
+    recorded: &[InstanceStatsDay],
+) -> Vec<InstanceStatsPoint> {
+    let mut recorded = recorded.iter().peekable();
+    let mut series = Vec::new();
+    let mut day = since;
+    while day < until {
+        while recorded.next_if(|stats| stats.day < day).is_some() {}
+        series.push(match recorded.next_if(|stats| stats.day == day) {
+            Some(stats) => InstanceStatsPoint::from(*stats),
+            None => InstanceStatsPoint::missing(day),
+        });
+        day += day_secs;
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_keeps_unrecorded_days_as_gaps() {
+        let recorded = [
+            InstanceStatsDay {
+                day: 0,
+                total_links: 3,
+                ..Default::default()
+            },
+            InstanceStatsDay {
+                day: 20,
+                total_links: 5,
+                ..Default::default()
+            },
+        ];
+        let series = daily_series(0, 30, 10, &recorded);
+        let days: Vec<_> = series.iter().map(|point| point.day).collect();
+        assert_eq!(days, [0, 10, 20]);
+        assert_eq!(series[0].total_links, Some(3));
+        assert_eq!(series[1], InstanceStatsPoint::missing(10));
+        assert_eq!(series[2].total_links, Some(5));
+        assert!(daily_series(30, 30, 10, &recorded).is_empty());
+    }
+}
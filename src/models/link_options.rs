@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+
+/// Per-link settings, stored together as one JSON object in `urls.options`.
+///
+/// Every field is optional and missing fields read as their default, so
+/// options added later need no migration of existing rows. Handlers go
+/// through the accessors instead of the stored JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkOptions {
+    /// Whether clicks are hidden from viewers who neither own the link nor
+    /// administer the instance; `None` follows `HIDE_STATS_BY_DEFAULT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hide_stats: Option<bool>,
+    /// Whether redirects show a countdown page before the destination
+    /// instead of redirecting at once; see `crate::redirect::countdown`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interstitial: Option<bool>,
+    /// Milliseconds since the Unix epoch from which the link stops
+    /// redirecting; `None` never expires. The row stays and the API still returns it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Clicks after which the link stops redirecting, as if deactivated;
+    /// `None` has no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_clicks: Option<i64>,
+}
+
+impl LinkOptions {
+    /// Whether the link hides its clicks, given the instance default.
+    pub fn hides_stats(&self, hide_by_default: bool) -> bool {
+        self.hide_stats.unwrap_or(hide_by_default)
+    }
+
+    /// Whether the link is flagged for the countdown page. Destinations on
+    /// `INTERSTITIAL_DOMAINS` get it whatever this says.
+    pub fn shows_interstitial(&self) -> bool {
+        self.interstitial.unwrap_or(false)
+    }
+
+    /// Whether the link has expired at `now`, in milliseconds since the Unix
+    /// epoch.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// These options with every option `changes` sets replaced by its value.
+    /// Options `changes` leaves out keep their current value.
+    pub fn merged(&self, changes: &LinkOptions) -> Self {
+        Self {
+            hide_stats: changes.hide_stats.or(self.hide_stats),
+            interstitial: changes.interstitial.or(self.interstitial),
+            expires_at: changes.expires_at.or(self.expires_at),
+            max_clicks: changes.max_clicks.or(self.max_clicks),
+        }
+    }
+
+    /// Names of the options `changes` sets to a value other than this one's.
+    pub fn changed_by(&self, changes: &LinkOptions) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if changes
+            .hide_stats
+            .is_some_and(|v| self.hide_stats != Some(v))
+        {
+            changed.push("hide_stats");
+        }
+        if changes
+            .interstitial
+            .is_some_and(|v| self.interstitial != Some(v))
+        {
+            changed.push("interstitial");
+        }
+        if changes
+            .expires_at
+            .is_some_and(|v| self.expires_at != Some(v))
+        {
+            changed.push("expires_at");
+        }
+        if changes
+            .max_clicks
+            .is_some_and(|v| self.max_clicks != Some(v))
+        {
+            changed.push("max_clicks");
+        }
+        changed
+    }
+
+    /// Whether no option is set, so a request changes nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check options a create or update request asks for. `hide_stats` and
+    /// `interstitial` take either value; options with limits reject values outside them.
+    /// Whether `expires_at` is still ahead depends on the clock, so callers
+    /// check that themselves.
+    pub fn validate(&self) -> Result<(), String> {
+        // A link limited to no clicks could never be followed
+        if self.max_clicks.is_some_and(|max_clicks| max_clicks < 1) {
+            return Err("max_clicks must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// The value stored in `urls.options`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("link options always serialize")
+    }
+}
+
+/// Reads the stored JSON. Unknown fields are ignored, but a value that is
+/// not an options object fails the row: reading it as the defaults would
+/// drop `expires_at` and `max_clicks` and let a spent link redirect again.
+impl TryFrom<String> for LinkOptions {
+    type Error = serde_json::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        serde_json::from_str(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_and_unknown_fields_read_as_defaults() {
+        assert_eq!(
+            LinkOptions::try_from("{}".to_string()).unwrap(),
+            LinkOptions::default()
+        );
+        assert_eq!(
+            LinkOptions::try_from(r#"{"hide_stats":true,"added_later":1}"#.to_string()).unwrap(),
+            LinkOptions {
+                hide_stats: Some(true),
+                ..LinkOptions::default()
+            }
+        );
+    }
+
+    #[test]
+    fn corrupt_options_fail_to_read() {
+        for corrupt in ["[", "", r#"{"max_clicks":"ten"}"#, "null"] {
+            assert!(
+                LinkOptions::try_from(corrupt.to_string()).is_err(),
+                "{corrupt}"
+            );
+        }
+    }
+
+    #[test]
+    fn stores_only_the_options_that_are_set() {
+        assert_eq!(LinkOptions::default().to_json(), "{}");
+        let hidden = LinkOptions {
+            hide_stats: Some(true),
+            ..LinkOptions::default()
+        };
+        assert_eq!(hidden.to_json(), r#"{"hide_stats":true}"#);
+        assert_eq!(LinkOptions::try_from(hidden.to_json()).unwrap(), hidden);
+        let flagged = LinkOptions {
+            interstitial: Some(true),
+            ..hidden.clone()
+        };
+        assert_eq!(
+            flagged.to_json(),
+            r#"{"hide_stats":true,"interstitial":true}"#
+        );
+        assert_eq!(LinkOptions::try_from(flagged.to_json()).unwrap(), flagged);
+    }
+
+    #[test]
+    fn merging_keeps_options_the_changes_leave_out() {
+        let hidden = LinkOptions {
+            hide_stats: Some(true),
+            interstitial: Some(true),
+            ..LinkOptions::default()
+        };
+        assert_eq!(hidden.merged(&LinkOptions::default()), hidden);
+        let shown = LinkOptions {
+            hide_stats: Some(false),
+            ..LinkOptions::default()
+        };
+        assert_eq!(
+            hidden.merged(&shown),
+            LinkOptions {
+                hide_stats: Some(false),
+                interstitial: Some(true),
+                ..LinkOptions::default()
+            }
+        );
+        assert!(!shown.hides_stats(true));
+        assert!(LinkOptions::default().hides_stats(true));
+        assert!(!LinkOptions::default().shows_interstitial());
+    }
+
+    #[test]
+    fn changes_name_only_the_options_they_alter() {
+        let current = LinkOptions {
+            hide_stats: Some(true),
+            expires_at: Some(1_800_000_000_000),
+            ..LinkOptions::default()
+        };
+        assert!(current.changed_by(&LinkOptions::default()).is_empty());
+        assert!(current.changed_by(&current).is_empty());
+        let changes = LinkOptions {
+            hide_stats: Some(true),
+            interstitial: Some(true),
+            expires_at: Some(1_900_000_000_000),
+            max_clicks: None,
+        };
+        assert_eq!(current.changed_by(&changes), ["interstitial", "expires_at"]);
+    }
+
+    #[test]
+    fn limits_are_stored_and_checked() {
+        let limited = LinkOptions {
+            expires_at: Some(1_800_000_000_000),
+            max_clicks: Some(10),
+            ..LinkOptions::default()
+        };
+        assert_eq!(
+            limited.to_json(),
+            r#"{"expires_at":1800000000000,"max_clicks":10}"#
+        );
+        assert_eq!(LinkOptions::try_from(limited.to_json()).unwrap(), limited);
+        assert!(limited.validate().is_ok());
+        assert!(!limited.is_expired(1_799_999_999_999));
+        assert!(limited.is_expired(1_800_000_000_000));
+        assert!(!LinkOptions::default().is_expired(i64::MAX));
+        for max_clicks in [0, -1] {
+            let options = LinkOptions {
+                max_clicks: Some(max_clicks),
+                ..LinkOptions::default()
+            };
+            assert!(options.validate().is_err(), "{max_clicks}");
+        }
+    }
+}
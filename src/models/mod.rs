@@ -1,3 +1,24 @@
+pub mod activity;
+pub mod audit;
+pub mod campaign;
+pub mod destination_report;
+pub mod instance_stats;
+pub mod link_options;
+pub mod moderation;
 pub mod url;
+pub mod usage;
+pub mod user;
 
-pub use url::{CreateUrlRequest, ShortenedUrl, UpdateUrlRequest, UrlHistoryEntry};
+pub use activity::{activity_series, ActivityCreator, ActivityDay, ActivityLink, DailyRanking};
+pub use audit::AuditEntry;
+pub use campaign::{Campaign, CampaignLinkStats, CampaignStats};
+pub use destination_report::{DestinationHostLink, DestinationHostSummary};
+pub use instance_stats::{daily_series, InstanceStatsDay, InstanceStatsPoint};
+pub use link_options::LinkOptions;
+pub use moderation::{ModerationEntry, ModerationStatus};
+pub use url::{
+    ClickHistoryEntry, CreateUrlRequest, CreatedVia, ShortenedUrl, UpdateUrlRequest,
+    UrlHistoryEntry,
+};
+pub use usage::{daily_usage, UsageCounts, UsageDay, UserUsageHour, UserUsageTotal};
+pub use user::{UserAccount, UserLinkCounts};
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+
+/// Review state of a link created without signing in.
+///
+/// Stored as lowercase text in `link_moderation.status`. The column is
+/// constrained to these values; anything else reads as `Rejected` so an
+/// unexpected row never puts a link back into service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationStatus {
+    /// Waiting for an admin; the link stays inactive
+    Pending,
+    /// Approved by an admin, or accepted without review
+    Approved,
+    /// Rejected by an admin; the link is deactivated
+    Rejected,
+}
+
+impl ModerationStatus {
+    pub const ALL: [Self; 3] = [Self::Pending, Self::Approved, Self::Rejected];
+
+    /// The value stored in the database and accepted by listing filters.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    /// Parse a stored or requested value, or `None` when it names no state.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+    }
+}
+
+impl fmt::Display for ModerationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Unrecognized values read as `Rejected`.
+impl From<String> for ModerationStatus {
+    fn from(value: String) -> Self {
+        Self::parse(&value).unwrap_or(Self::Rejected)
+    }
+}
+
+/// A link created through `POST /api/public/urls` and its review state.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ModerationEntry {
+    pub short_code: String,
+    pub original_url: String,
+    /// When the link was created, in milliseconds since the Unix epoch
+    pub created_at: i64,
+    #[sqlx(try_from = "String")]
+    pub status: ModerationStatus,
+    /// Why the link was rejected
+    pub reason: Option<String>,
+    /// When an admin approved or rejected the link
+    pub decided_at: Option<i64>,
+    /// The admin who approved or rejected the link
+    pub decided_by: Option<String>,
+}
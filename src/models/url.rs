@@ -1,21 +1,206 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
+use std::fmt;
+
+use super::LinkOptions;
+use crate::config::CodeStrategy;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ShortenedUrl {
+    /// Row id, used only for keyset pagination. Never serialized: it would
+    /// reveal how many links exist, and `short_code` identifies a link.
+    #[serde(skip_serializing, default)]
     pub id: i64,
     pub short_code: String,
     pub original_url: String,
+    /// Creation time in milliseconds since the Unix epoch. Links created
+    /// before millisecond precision keep whole seconds (a multiple of 1000).
     pub created_at: i64,
     pub created_by: Option<String>,
+    /// Auth method `created_by` signed in with (`oauth`, `cloudflare`, ...).
+    /// The same subject under two methods can be two people, and users are
+    /// keyed by the pair. `None` for links created before it was recorded,
+    /// anonymously, or by an admin on someone else's behalf.
+    #[serde(default)]
+    pub created_by_auth_method: Option<String>,
     pub clicks: i64,
     pub is_active: bool,
+    /// Set while the code is reserved without a destination (see
+    /// `POST /api/links/reserve`). The reservation lapses at this Unix
+    /// timestamp unless a destination is set first. Sent in milliseconds
+    /// like the other link timestamps.
+    #[serde(with = "epoch_secs_as_millis")]
+    pub reserved_until: Option<i64>,
+    /// Set on a code that was renamed (see `POST /api/links/{code}/rename`):
+    /// the canonical code it now redirects to. Aliases never point at aliases.
+    pub alias_of: Option<String>,
+    /// Page title of the destination, filled in after creation when title
+    /// fetching is enabled
+    pub title: Option<String>,
+    /// Entry point the link was created through
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub created_via: CreatedVia,
+    /// When the click flush last counted a visit (Unix timestamp, precise to
+    /// the flush interval); `None` if never visited since tracking began.
+    /// Sent in milliseconds like the other link timestamps.
+    #[serde(default, with = "epoch_secs_as_millis")]
+    pub last_visited_at: Option<i64>,
+    /// When the destination, owner, alias target, reservation, options or
+    /// active state last changed, in milliseconds since the Unix epoch; equal to
+    /// `created_at` until then. Clicks and fetched titles do not count.
+    #[serde(default)]
+    pub updated_at: i64,
+    /// Per-link settings such as `hide_stats`, serialized inline
+    #[serde(flatten)]
+    #[sqlx(try_from = "String")]
+    pub options: LinkOptions,
+    /// Campaign the link belongs to, if any (see [`Campaign`](super::Campaign))
+    #[serde(default)]
+    pub campaign_id: Option<i64>,
+}
+
+/// Optional times kept in Unix seconds but sent in milliseconds, so every
+/// timestamp on a link has the same unit in the API.
+pub mod epoch_secs_as_millis {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(secs: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+        secs.map(|secs| secs.saturating_mul(1000))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<i64>, D::Error> {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(|millis| millis.div_euclid(1000)))
+    }
+}
+
+impl ShortenedUrl {
+    /// Destination stored for reserved codes until one is set.
+    pub const RESERVED_DESTINATION: &'static str = "";
+
+    /// Whether the code is reserved and has no destination yet.
+    pub fn is_reserved(&self) -> bool {
+        self.reserved_until.is_some()
+    }
+
+    /// Whether the code redirects through another code after a rename.
+    pub fn is_alias(&self) -> bool {
+        self.alias_of.is_some()
+    }
+
+    /// Whether the link has expired at `now`, in milliseconds since the Unix
+    /// epoch.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.options.is_expired(now)
+    }
+}
+
+/// How a link was created, recorded by each entry point for auditing.
+///
+/// Stored as lowercase text in `urls.created_via`. Links created before the
+/// column existed are `Unknown`, and so is any value this build does not
+/// recognize, so reading a row never fails on it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CreatedVia {
+    /// `POST /api/urls` and other JSON API endpoints
+    Api,
+    /// The `lynx` command line
+    Cli,
+    /// `GET /api/quick`, used by the bookmarklet
+    Bookmarklet,
+    /// Bulk imports
+    Import,
+    /// Chat integrations such as the Slack slash command
+    Integration,
+    #[default]
+    Unknown,
+}
+
+impl CreatedVia {
+    pub const ALL: [Self; 6] = [
+        Self::Api,
+        Self::Cli,
+        Self::Bookmarklet,
+        Self::Import,
+        Self::Integration,
+        Self::Unknown,
+    ];
+
+    /// The value stored in the database and accepted by search filters.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Api => "api",
+            Self::Cli => "cli",
+            Self::Bookmarklet => "bookmarklet",
+            Self::Import => "import",
+            Self::Integration => "integration",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Parse a stored or requested value, or `None` when it names no source.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|via| via.as_str() == value)
+    }
+}
+
+impl fmt::Display for CreatedVia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Unrecognized values read as `Unknown`.
+impl From<String> for CreatedVia {
+    fn from(value: String) -> Self {
+        Self::parse(&value).unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUrlRequest {
     pub url: String,
     pub custom_code: Option<String>,
+    /// User to create the link for (admins only)
+    #[serde(default)]
+    pub created_by_override: Option<String>,
+    /// Hide clicks from other viewers; omitted follows the instance default
+    #[serde(default)]
+    pub hide_stats: Option<bool>,
+    /// Show a countdown page before redirecting; omitted leaves it off
+    #[serde(default)]
+    pub interstitial: Option<bool>,
+    /// How to generate the code when `custom_code` is not set; omitted
+    /// follows `CODE_STRATEGY`
+    #[serde(default)]
+    pub code_strategy: Option<CodeStrategy>,
+    /// Campaign to add the link to; the caller must own it or be an admin
+    #[serde(default)]
+    pub campaign_id: Option<i64>,
+    /// Milliseconds since the Unix epoch from which the link stops
+    /// redirecting; must be in the future. Omitted never expires.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Clicks after which the link stops redirecting; at least 1. Omitted
+    /// has no limit.
+    #[serde(default)]
+    pub max_clicks: Option<i64>,
+}
+
+impl CreateUrlRequest {
+    /// The per-link options the request sets.
+    pub fn link_options(&self) -> LinkOptions {
+        LinkOptions {
+            hide_stats: self.hide_stats,
+            interstitial: self.interstitial,
+            expires_at: self.expires_at,
+            max_clicks: self.max_clicks,
+        }
+    }
 }
 
 /// A historical destination for a shortened URL, recorded each time the
@@ -29,7 +214,125 @@ pub struct UrlHistoryEntry {
     pub changed_by: Option<String>,
 }
 
+/// Clicks of a shortened URL on one day of the requested time zone.
+///
+/// Months compacted by `lynx analytics compact-clicks` are counted on the day
+/// containing the first UTC hour of the month.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct ClickHistoryEntry {
+    /// Start of the day (Unix timestamp)
+    pub day: i64,
+    pub clicks: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateUrlRequest {
     pub url: String,
+    /// User to hand the link to (admins only)
+    #[serde(default)]
+    pub created_by_override: Option<String>,
+    /// Hide (or show) clicks to other viewers; omitted keeps the current setting
+    #[serde(default)]
+    pub hide_stats: Option<bool>,
+    /// Turn the countdown page on or off; omitted keeps the current setting
+    #[serde(default)]
+    pub interstitial: Option<bool>,
+    /// Campaign to move the link to, or `null` to take it out of its
+    /// campaign; omitted keeps the current one
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub campaign_id: Option<Option<i64>>,
+}
+
+impl UpdateUrlRequest {
+    /// The per-link options the request changes.
+    pub fn link_options(&self) -> LinkOptions {
+        LinkOptions {
+            hide_stats: self.hide_stats,
+            interstitial: self.interstitial,
+            ..LinkOptions::default()
+        }
+    }
+}
+
+/// Read a field that may be `null` as `Some(None)`, so a request can tell
+/// clearing a value apart from leaving the field out (`None`, with
+/// `#[serde(default)]`).
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn created_via_round_trips_and_reads_unknown_values_as_unknown() {
+        for via in CreatedVia::ALL {
+            assert_eq!(CreatedVia::parse(via.as_str()), Some(via));
+            assert_eq!(CreatedVia::from(via.to_string()), via);
+            assert_eq!(serde_json::to_value(via).unwrap(), via.as_str());
+        }
+        assert_eq!(CreatedVia::parse("API"), None);
+        assert_eq!(
+            CreatedVia::from("carrier-pigeon".to_string()),
+            CreatedVia::Unknown
+        );
+    }
+
+    #[test]
+    fn link_timestamps_are_all_sent_in_milliseconds() {
+        let url = ShortenedUrl {
+            id: 1,
+            short_code: "abc".to_string(),
+            original_url: "https://example.com".to_string(),
+            created_at: 1_700_000_000_123,
+            created_by: None,
+            created_by_auth_method: None,
+            clicks: 0,
+            is_active: true,
+            reserved_until: Some(1_700_086_400),
+            alias_of: None,
+            title: None,
+            created_via: CreatedVia::Api,
+            last_visited_at: Some(1_700_000_060),
+            updated_at: 1_700_000_000_123,
+            options: LinkOptions::default(),
+            campaign_id: None,
+        };
+        let json = serde_json::to_value(&url).unwrap();
+        assert_eq!(json["reserved_until"], 1_700_086_400_000i64);
+        assert_eq!(json["last_visited_at"], 1_700_000_060_000i64);
+
+        let read: ShortenedUrl = serde_json::from_value(json).unwrap();
+        assert_eq!(read.reserved_until, url.reserved_until);
+        assert_eq!(read.last_visited_at, url.last_visited_at);
+
+        let unset = serde_json::to_value(ShortenedUrl {
+            reserved_until: None,
+            last_visited_at: None,
+            ..url
+        })
+        .unwrap();
+        assert!(unset["reserved_until"].is_null());
+        assert!(unset["last_visited_at"].is_null());
+    }
+
+    #[test]
+    fn update_requests_tell_a_null_campaign_from_a_missing_one() {
+        let request = |body: &str| serde_json::from_str::<UpdateUrlRequest>(body).unwrap();
+        let url = r#""url": "https://example.com""#;
+        assert_eq!(request(&format!("{{{url}}}")).campaign_id, None);
+        assert_eq!(
+            request(&format!(r#"{{{url}, "campaign_id": null}}"#)).campaign_id,
+            Some(None)
+        );
+        assert_eq!(
+            request(&format!(r#"{{{url}, "campaign_id": 7}}"#)).campaign_id,
+            Some(Some(7))
+        );
+    }
 }
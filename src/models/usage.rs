@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// API requests of a user, by endpoint class (see `auth::usage::UsageClass`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct UsageCounts {
+    /// Requests that create or change something
+    pub create_requests: i64,
+    /// Requests that read links, profiles or admin reports
+    pub read_requests: i64,
+    /// Link searches
+    pub search_requests: i64,
+    /// Analytics, click history and stats requests
+    pub analytics_requests: i64,
+}
+
+impl UsageCounts {
+    pub fn total(&self) -> i64 {
+        self.create_requests + self.read_requests + self.search_requests + self.analytics_requests
+    }
+
+    pub fn add(&mut self, other: &UsageCounts) {
+        self.create_requests += other.create_requests;
+        self.read_requests += other.read_requests;
+        self.search_requests += other.search_requests;
+        self.analytics_requests += other.analytics_requests;
+    }
+}
+
+/// API requests of one user in one UTC hour, as stored in `user_usage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct UserUsageHour {
+    pub user_id: String,
+    /// Start of the hour (Unix seconds)
+    pub hour: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub counts: UsageCounts,
+}
+
+/// API requests of one user summed over a span of hours.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct UserUsageTotal {
+    pub user_id: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub counts: UsageCounts,
+}
+
+/// A UTC day of one user's API requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UsageDay {
+    /// Start of the day (Unix seconds)
+    pub day: i64,
+    #[serde(flatten)]
+    pub counts: UsageCounts,
+    pub total: i64,
+}
+
+/// Sum `hours` into UTC days of `day_secs`, oldest first. Days without
+/// requests are omitted.
+pub fn daily_usage<'a>(
+    hours: impl IntoIterator<Item = &'a UserUsageHour>,
+    day_secs: i64,
+) -> Vec<UsageDay> {
+    let mut days = std::collections::BTreeMap::<i64, UsageCounts>::new();
+    for hour in hours {
+        let day = hour.hour - hour.hour.rem_euclid(day_secs);
+        days.entry(day).or_default().add(&hour.counts);
+    }
+    days.into_iter()
+        .map(|(day, counts)| UsageDay {
+            day,
+            counts,
+            total: counts.total(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn hour(hour: i64, create: i64, read: i64, search: i64, analytics: i64) -> UserUsageHour {
+        UserUsageHour {
+            user_id: "alice".to_string(),
+            hour,
+            counts: UsageCounts {
+                create_requests: create,
+                read_requests: read,
+                search_requests: search,
+                analytics_requests: analytics,
+            },
+        }
+    }
+
+    #[test]
+    fn hours_sum_into_their_utc_day() {
+        let hours = [
+            hour(DAY + 23 * 3_600, 1, 2, 0, 0),
+            hour(0, 1, 0, 0, 0),
+            hour(DAY, 0, 5, 1, 2),
+            // Unsorted input and a repeated hour, as when stored rows and
+            // pending counts are merged
+            hour(3 * 3_600, 2, 0, 3, 0),
+            hour(DAY, 1, 0, 0, 0),
+        ];
+
+        let days = daily_usage(&hours, DAY);
+        assert_eq!(
+            days,
+            vec![
+                UsageDay {
+                    day: 0,
+                    counts: UsageCounts {
+                        create_requests: 3,
+                        read_requests: 0,
+                        search_requests: 3,
+                        analytics_requests: 0,
+                    },
+                    total: 6,
+                },
+                UsageDay {
+                    day: DAY,
+                    counts: UsageCounts {
+                        create_requests: 2,
+                        read_requests: 7,
+                        search_requests: 1,
+                        analytics_requests: 2,
+                    },
+                    total: 12,
+                },
+            ]
+        );
+        let total: i64 = days.iter().map(|day| day.total).sum();
+        assert_eq!(
+            total,
+            hours.iter().map(|hour| hour.counts.total()).sum::<i64>()
+        );
+    }
+
+    #[test]
+    fn no_hours_make_no_days() {
+        assert!(daily_usage(&[], DAY).is_empty());
+    }
+}
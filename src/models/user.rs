@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One sign-in of a user: the same user ID under a given auth method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct UserAccount {
+    pub user_id: String,
+    pub auth_method: String,
+    pub email: Option<String>,
+    /// When the user first signed in this way (Unix seconds)
+    pub created_at: i64,
+    /// When a request last authenticated as this sign-in (Unix seconds),
+    /// updated at most every few minutes
+    pub last_seen_at: i64,
+    /// Promoted with `lynx admin promote`; admins by claim are not recorded
+    pub is_manual_admin: bool,
+}
+
+/// Links a user created, by state. Every link falls in exactly one state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct UserLinkCounts {
+    /// Active links with a destination
+    pub active: i64,
+    /// Deactivated links, including lapsed reservations
+    pub inactive: i64,
+    /// Reserved codes still waiting for their destination
+    pub reserved: i64,
+    /// Aliases of other links, active or not
+    pub aliases: i64,
+    /// Clicks across all of these links
+    pub total_clicks: i64,
+}
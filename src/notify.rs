@@ -0,0 +1,396 @@
+//! Email to users when their account changes.
+//!
+//! Changes made on someone's behalf, such as `lynx admin promote`, are sent
+//! to the email stored for the affected account through a [`Notifier`]. With
+//! `SMTP_HOST` set that is [`SmtpNotifier`]; otherwise [`NoopNotifier`] drops
+//! them, and accounts without a stored email are skipped either way.
+//!
+//! Sending runs on a spawned task that retries a few times and then logs the
+//! failure, so a slow or broken mail server never fails or holds up the
+//! change that triggered it. Callers that exit right after, like the CLI,
+//! await the returned handle.
+
+use anyhow::Context;
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::config::{SmtpConfig, SmtpTlsMode};
+use crate::models::UserAccount;
+use crate::storage::Storage;
+
+/// Attempts per notification before it is given up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How long one conversation with the SMTP server may take.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A change to a user's account that they are told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountEvent {
+    PromotedToAdmin,
+    DemotedFromAdmin,
+}
+
+impl AccountEvent {
+    fn subject(self) -> &'static str {
+        match self {
+            Self::PromotedToAdmin => "Your Lynx account is now an admin",
+            Self::DemotedFromAdmin => "Your Lynx account is no longer an admin",
+        }
+    }
+
+    fn summary(self) -> &'static str {
+        match self {
+            Self::PromotedToAdmin => "was promoted to admin",
+            Self::DemotedFromAdmin => "was demoted from admin",
+        }
+    }
+}
+
+/// One email about `event` for the sign-in `user_id` under `auth_method`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountNotice {
+    pub event: AccountEvent,
+    pub user_id: String,
+    pub auth_method: String,
+}
+
+impl AccountNotice {
+    pub fn subject(&self) -> &'static str {
+        self.event.subject()
+    }
+
+    pub fn body(&self) -> String {
+        format!(
+            "Your Lynx account '{}' (signed in with {}) {}.\n\n\
+             If you did not expect this change, contact your Lynx administrator.\n",
+            self.user_id,
+            self.auth_method,
+            self.event.summary()
+        )
+    }
+}
+
+/// Delivers account notices to an email address.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, to: &str, notice: &AccountNotice) -> anyhow::Result<()>;
+}
+
+/// Drops every notice; used when no mail server is configured.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopNotifier;
+
+#[async_trait]
+impl Notifier for NoopNotifier {
+    async fn send(&self, _to: &str, _notice: &AccountNotice) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sends notices as plain-text email through an SMTP server.
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpNotifier {
+    /// A sender for `config`. Fails on an unparsable `SMTP_FROM`; the server
+    /// itself is first contacted when a notice is sent.
+    pub fn new(config: &SmtpConfig) -> anyhow::Result<Self> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .with_context(|| format!("SMTP_FROM is not an email address: '{}'", config.from))?;
+        let mut builder = match config.tls {
+            SmtpTlsMode::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTlsMode::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpTlsMode::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+        }
+        .port(config.port)
+        .timeout(Some(SMTP_TIMEOUT));
+        if let Some(password) = &config.password {
+            let username = config.username.clone().unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username, password.clone()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    async fn send(&self, to: &str, notice: &AccountNotice) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse::<Mailbox>().context("invalid recipient address")?)
+            .subject(notice.subject())
+            .header(ContentType::TEXT_PLAIN)
+            .body(notice.body())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Looks up who to tell and sends notices in the background.
+#[derive(Clone)]
+pub struct AccountNotifications {
+    notifier: Arc<dyn Notifier>,
+    retry_delay: Duration,
+}
+
+impl Default for AccountNotifications {
+    fn default() -> Self {
+        Self::new(Arc::new(NoopNotifier))
+    }
+}
+
+impl AccountNotifications {
+    pub fn new(notifier: Arc<dyn Notifier>) -> Self {
+        Self {
+            notifier,
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    /// Notifications over SMTP when `smtp` is configured, dropped otherwise.
+    pub fn from_config(smtp: Option<&SmtpConfig>) -> anyhow::Result<Self> {
+        Ok(match smtp {
+            Some(smtp) => Self::new(Arc::new(SmtpNotifier::new(smtp)?)),
+            None => Self::default(),
+        })
+    }
+
+    /// Tell the user behind `user_id` and `auth_method` about `event`.
+    ///
+    /// The email of that sign-in is used, or else of another sign-in of the
+    /// same user. Returns the sending task, or `None` when there is no email
+    /// to send to or it could not be looked up.
+    pub async fn notify(
+        &self,
+        storage: &dyn Storage,
+        user_id: &str,
+        auth_method: &str,
+        event: AccountEvent,
+    ) -> Option<JoinHandle<()>> {
+        let accounts = match storage.user_accounts(user_id).await {
+            Ok(accounts) => accounts,
+            Err(error) => {
+                tracing::warn!(%error, user_id, ?event, "failed to look up email for account notice");
+                return None;
+            }
+        };
+        let email_of = |account: &UserAccount| account.email.clone().filter(|e| !e.is_empty());
+        let email = accounts
+            .iter()
+            .find(|account| account.auth_method == auth_method)
+            .and_then(email_of)
+            .or_else(|| accounts.iter().find_map(email_of))?;
+
+        Some(self.spawn(
+            email,
+            AccountNotice {
+                event,
+                user_id: user_id.to_string(),
+                auth_method: auth_method.to_string(),
+            },
+        ))
+    }
+
+    /// Send `notice` to `to`, retrying with backoff until it goes through or
+    /// [`MAX_ATTEMPTS`] are used up.
+    fn spawn(&self, to: String, notice: AccountNotice) -> JoinHandle<()> {
+        let notifier = Arc::clone(&self.notifier);
+        let mut delay = self.retry_delay;
+        tokio::spawn(async move {
+            for attempt in 1..=MAX_ATTEMPTS {
+                match notifier.send(&to, &notice).await {
+                    Ok(()) => return,
+                    Err(error) if attempt == MAX_ATTEMPTS => {
+                        tracing::warn!(
+                            error = %format!("{error:#}"),
+                            user_id = %notice.user_id,
+                            event = ?notice.event,
+                            "giving up on account notice after {MAX_ATTEMPTS} attempts"
+                        );
+                    }
+                    Err(error) => {
+                        tracing::debug!(error = %format!("{error:#}"), attempt, "account notice failed; retrying");
+                        tokio::time::sleep(delay).await;
+                        delay *= 2;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use std::sync::Mutex;
+
+    /// Records each call and fails the first `failures` of them.
+    #[derive(Default)]
+    struct MockNotifier {
+        calls: Mutex<Vec<(String, AccountNotice)>>,
+        failures: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl Notifier for MockNotifier {
+        async fn send(&self, to: &str, notice: &AccountNotice) -> anyhow::Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((to.to_string(), notice.clone()));
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                anyhow::bail!("mail server unavailable");
+            }
+            Ok(())
+        }
+    }
+
+    fn mock_notifications(failures: u32) -> (AccountNotifications, Arc<MockNotifier>) {
+        let mock = Arc::new(MockNotifier {
+            failures: Mutex::new(failures),
+            ..MockNotifier::default()
+        });
+        let notifications = AccountNotifications {
+            notifier: Arc::clone(&mock) as Arc<dyn Notifier>,
+            retry_delay: Duration::from_millis(1),
+        };
+        (notifications, mock)
+    }
+
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+        storage.init().await.unwrap();
+        storage
+            .upsert_user("alice", Some("alice@example.com"), "oauth")
+            .await
+            .unwrap();
+        storage
+            .upsert_user("alice", Some("alice@corp.example"), "cloudflare")
+            .await
+            .unwrap();
+        storage
+            .upsert_user("carol", Some("carol@example.com"), "oauth")
+            .await
+            .unwrap();
+        storage.upsert_user("carol", None, "saml").await.unwrap();
+        storage.upsert_user("bob", None, "oauth").await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn notices_go_to_the_email_of_the_affected_sign_in() {
+        let storage = storage().await;
+        let (notifications, mock) = mock_notifications(0);
+
+        for (user_id, auth_method, event) in [
+            ("alice", "cloudflare", AccountEvent::PromotedToAdmin),
+            ("alice", "oauth", AccountEvent::DemotedFromAdmin),
+            ("carol", "saml", AccountEvent::PromotedToAdmin),
+        ] {
+            notifications
+                .notify(&storage, user_id, auth_method, event)
+                .await
+                .unwrap()
+                .await
+                .unwrap();
+        }
+
+        let calls = mock.calls.lock().unwrap();
+        let recipients: Vec<&str> = calls.iter().map(|(to, _)| to.as_str()).collect();
+        assert_eq!(
+            recipients,
+            [
+                "alice@corp.example",
+                "alice@example.com",
+                "carol@example.com"
+            ]
+        );
+        assert_eq!(
+            calls[0].1,
+            AccountNotice {
+                event: AccountEvent::PromotedToAdmin,
+                user_id: "alice".to_string(),
+                auth_method: "cloudflare".to_string(),
+            }
+        );
+        assert_eq!(calls[1].1.event, AccountEvent::DemotedFromAdmin);
+    }
+
+    #[tokio::test]
+    async fn users_without_an_email_are_skipped() {
+        let storage = storage().await;
+        let (notifications, mock) = mock_notifications(0);
+
+        for user_id in ["bob", "nobody"] {
+            assert!(notifications
+                .notify(&storage, user_id, "oauth", AccountEvent::PromotedToAdmin)
+                .await
+                .is_none());
+        }
+        assert!(mock.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_sends_are_retried_a_bounded_number_of_times() {
+        let storage = storage().await;
+
+        let (notifications, mock) = mock_notifications(2);
+        notifications
+            .notify(&storage, "alice", "oauth", AccountEvent::PromotedToAdmin)
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(mock.calls.lock().unwrap().len(), 3);
+
+        let (notifications, mock) = mock_notifications(u32::MAX);
+        notifications
+            .notify(&storage, "alice", "oauth", AccountEvent::PromotedToAdmin)
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(mock.calls.lock().unwrap().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn smtp_senders_need_a_valid_from_address() {
+        let config = SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 587,
+            from: "Lynx <lynx@example.com>".to_string(),
+            tls: SmtpTlsMode::StartTls,
+            username: None,
+            password: None,
+        };
+        assert!(SmtpNotifier::new(&config).is_ok());
+        assert!(SmtpNotifier::new(&SmtpConfig {
+            from: "not an address".to_string(),
+            ..config
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Page numbers over cursor-paginated listings.
+//!
+//! `lynx user list` and `lynx user links` take `--page` and `--limit`, but
+//! the storage listings behind them page by cursor so that deep pages do not
+//! cost a growing OFFSET scan. [`fetch_page`] reaches page N by fetching the
+//! rows before it in batches, each continuing from the last row of the one
+//! before. That is cheap for the first pages and linear in the page number
+//! after that, so the CLI warns for pages past [`DEEP_PAGE_ROWS`].
+
+use anyhow::Result;
+use std::future::Future;
+
+/// Pages starting after this many rows get a warning: every earlier row is
+/// read to reach them
+pub const DEEP_PAGE_ROWS: i64 = 10_000;
+
+/// Rows read per fetch while walking to the requested page
+const SKIP_BATCH: i64 = 1_000;
+
+/// Whether page `page` (1-based) of `limit` rows starts past [`DEEP_PAGE_ROWS`]
+pub fn is_deep_page(page: i64, limit: i64) -> bool {
+    (page - 1).saturating_mul(limit) > DEEP_PAGE_ROWS
+}
+
+/// Fetch page `page` (1-based) of `limit` rows.
+///
+/// `fetch(n, cursor)` returns up to `n` rows following `cursor` (from the
+/// start when `None`), and `cursor_of` gives the cursor that continues after
+/// a row. Past the end of the listing the page is empty.
+pub async fn fetch_page<T, C, F, Fut>(
+    page: i64,
+    limit: i64,
+    mut fetch: F,
+    cursor_of: impl Fn(&T) -> C,
+) -> Result<Vec<T>>
+where
+    F: FnMut(i64, Option<C>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let mut skip = (page - 1).saturating_mul(limit);
+    let mut cursor = None;
+    while skip > 0 {
+        let batch_size = skip.min(SKIP_BATCH);
+        let batch = fetch(batch_size, cursor.take()).await?;
+        match batch.last() {
+            Some(last) if batch.len() as i64 == batch_size => cursor = Some(cursor_of(last)),
+            _ => return Ok(Vec::new()),
+        }
+        skip -= batch_size;
+    }
+    fetch(limit, cursor).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn page_of(rows: &[i64], page: i64, limit: i64) -> Vec<i64> {
+        fetch_page(
+            page,
+            limit,
+            |n, after: Option<i64>| async move {
+                Ok(rows
+                    .iter()
+                    .copied()
+                    .filter(|row| after.is_none_or(|after| *row > after))
+                    .take(n as usize)
+                    .collect())
+            },
+            |row| *row,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn pages_match_offset_paging() {
+        let rows: Vec<i64> = (0..2_500).collect();
+        for (page, limit) in [(1, 10), (2, 10), (3, 7), (150, 10), (250, 10), (2, 1_500)] {
+            let offset = ((page - 1) * limit) as usize;
+            let expected: Vec<i64> = rows
+                .iter()
+                .copied()
+                .skip(offset)
+                .take(limit as usize)
+                .collect();
+            assert_eq!(
+                page_of(&rows, page, limit).await,
+                expected,
+                "page {page} of {limit}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_past_the_end_are_empty() {
+        let rows: Vec<i64> = (0..25).collect();
+        assert!(page_of(&rows, 4, 10).await.is_empty());
+        assert!(page_of(&rows, 3_000, 10).await.is_empty());
+    }
+
+    #[test]
+    fn deep_pages_start_past_the_threshold() {
+        assert!(!is_deep_page(1, 1_000));
+        assert!(!is_deep_page(11, 1_000));
+        assert!(is_deep_page(12, 1_000));
+        assert!(!is_deep_page(i64::MAX, 0));
+    }
+}
@@ -0,0 +1,188 @@
+//! Countdown page shown before leaving for some destinations.
+//!
+//! Links flagged with the `interstitial` option, and links whose destination
+//! is on one of `INTERSTITIAL_DOMAINS`, answer with a small page saying the
+//! visitor is leaving and will be sent on in `INTERSTITIAL_COUNTDOWN_SECS`,
+//! instead of redirecting at once. The page moves on by itself through a
+//! meta refresh and also links the destination directly, so it needs no
+//! script. The click and analytics are recorded when the page is served,
+//! and the refresh goes straight to the destination, so a visit still
+//! counts once.
+//!
+//! `INTERSTITIAL_TEMPLATE_PATH` replaces the built-in page with an HTML file
+//! read at startup. `{{url}}` in it becomes the escaped destination and
+//! `{{seconds}}` the countdown.
+
+use anyhow::Context;
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use super::interstitial::escape_html;
+use super::self_redirect::authority_host;
+use crate::config::RedirectInterstitialConfig;
+
+const URL_PLACEHOLDER: &str = "{{url}}";
+const SECONDS_PLACEHOLDER: &str = "{{seconds}}";
+
+const DEFAULT_TEMPLATE: &str = "<!doctype html>\n<html lang=\"en\">\n<head>\n\
+     <meta charset=\"utf-8\">\n\
+     <meta http-equiv=\"refresh\" content=\"{{seconds}}; url={{url}}\">\n\
+     <title>Leaving this site</title>\n</head>\n<body>\n\
+     <p>You are leaving this site. Redirecting in {{seconds}} seconds to:</p>\n\
+     <p><a href=\"{{url}}\" rel=\"noopener noreferrer\">{{url}}</a></p>\n\
+     </body>\n</html>\n";
+
+/// Decides which redirects get the countdown page and renders it.
+#[derive(Debug, Clone)]
+pub struct CountdownPage {
+    /// Lowercased, without trailing dots
+    domains: Vec<String>,
+    seconds: u32,
+    template: Arc<str>,
+}
+
+impl Default for CountdownPage {
+    /// The built-in page for flagged links only.
+    fn default() -> Self {
+        Self::new(
+            Vec::new(),
+            RedirectInterstitialConfig::default_countdown_secs(),
+        )
+    }
+}
+
+impl CountdownPage {
+    /// The built-in page, shown for flagged links and destinations on
+    /// `domains`.
+    pub fn new(domains: Vec<String>, seconds: u32) -> Self {
+        Self {
+            domains: domains
+                .iter()
+                .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+            seconds,
+            template: Arc::from(DEFAULT_TEMPLATE),
+        }
+    }
+
+    /// The page `config` describes, reading its template file if it names
+    /// one. A template without `{{url}}` is refused, since it would leave
+    /// visitors no way on.
+    pub fn from_config(config: &RedirectInterstitialConfig) -> anyhow::Result<Self> {
+        let page = Self::new(config.domains.clone(), config.countdown_secs);
+        let Some(path) = &config.template_path else {
+            return Ok(page);
+        };
+        let template = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read INTERSTITIAL_TEMPLATE_PATH {path}"))?;
+        page.with_template(template)
+    }
+
+    /// This page rendered from `template` instead of the built-in one.
+    pub fn with_template(self, template: String) -> anyhow::Result<Self> {
+        if !template.contains(URL_PLACEHOLDER) {
+            anyhow::bail!("The interstitial template must contain {URL_PLACEHOLDER}");
+        }
+        Ok(Self {
+            template: Arc::from(template),
+            ..self
+        })
+    }
+
+    /// Whether a redirect to `destination` gets the page, given whether
+    /// the link is `flagged` for it.
+    pub fn applies(&self, flagged: bool, destination: &str) -> bool {
+        flagged || self.covers(destination)
+    }
+
+    /// Whether `destination` is on one of the configured domains.
+    fn covers(&self, destination: &str) -> bool {
+        if self.domains.is_empty() {
+            return false;
+        }
+        let Some(host) = authority_host(destination) else {
+            return false;
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        })
+    }
+
+    /// The page for `destination`. Styles inline in a custom template are
+    /// allowed; scripts and anything loaded from elsewhere are not.
+    pub fn response(&self, destination: &str) -> Response {
+        (
+            StatusCode::OK,
+            [
+                (CONTENT_TYPE, "text/html; charset=utf-8"),
+                (
+                    CONTENT_SECURITY_POLICY,
+                    "default-src 'none'; style-src 'unsafe-inline'",
+                ),
+                (REFERRER_POLICY, "no-referrer"),
+                (CACHE_CONTROL, "no-store"),
+            ],
+            self.render(destination),
+        )
+            .into_response()
+    }
+
+    /// The template with its placeholders filled in. The destination goes
+    /// in last, so placeholder text inside it is left alone.
+    fn render(&self, destination: &str) -> String {
+        self.template
+            .replace(SECONDS_PLACEHOLDER, &self.seconds.to_string())
+            .replace(URL_PLACEHOLDER, &escape_html(destination))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains_cover_their_subdomains_only() {
+        let page = CountdownPage::new(vec!["Partner.example.".to_string()], 5);
+        assert!(page.applies(false, "https://partner.example/offer"));
+        assert!(page.applies(false, "https://shop.PARTNER.example./offer"));
+        assert!(page.applies(false, "https://user@partner.example:8443/"));
+        assert!(!page.applies(false, "https://notpartner.example/"));
+        assert!(!page.applies(false, "https://partner.example.com/"));
+        assert!(!page.applies(false, "https://example.com/?to=partner.example"));
+        assert!(page.applies(true, "https://example.com/"));
+        assert!(!CountdownPage::default().applies(false, "https://partner.example/"));
+    }
+
+    #[test]
+    fn the_page_counts_down_to_the_escaped_destination() {
+        let page = CountdownPage::new(Vec::new(), 3);
+        let html = page.render("https://example.com/?a=1&b=\"{{seconds}}\"");
+        assert!(html
+            .contains("content=\"3; url=https://example.com/?a=1&amp;b=&quot;{{seconds}}&quot;\""));
+        assert!(html.contains("Redirecting in 3 seconds"));
+    }
+
+    #[test]
+    fn custom_templates_need_the_destination() {
+        let page = CountdownPage::default()
+            .with_template("<a href=\"{{url}}\">Continue</a> ({{seconds}})".to_string())
+            .unwrap();
+        assert_eq!(
+            page.render("https://example.com/"),
+            "<a href=\"https://example.com/\">Continue</a> (5)"
+        );
+        assert!(CountdownPage::default()
+            .with_template("<p>Bye</p>".to_string())
+            .is_err());
+    }
+}
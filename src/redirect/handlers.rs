@@ -5,17 +5,23 @@ use axum::{
         StatusCode,
     },
     response::{IntoResponse, Response},
-    Extension, Json,
+    Extension,
 };
-use serde::Serialize;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use super::countdown::CountdownPage;
+use super::interstitial::interstitial_response;
+use super::landing::{code_from_path, RootLanding};
+use super::latency::LatencyOutcome;
 use super::middleware::RequestStart;
+use super::normalize::CodeNormalizer;
+use super::self_redirect::SelfRedirectGuard;
+use super::stats::{RedirectOutcome, RedirectStats};
 use crate::analytics::AnalyticsAggregator;
 use crate::config::AnalyticsConfig;
-use crate::storage::{CachedStorage, LookupMetadata, RedirectTarget};
+use crate::storage::{CachedStorage, LookupMetadata, RedirectLookup, RedirectTarget, StorageError};
 
 #[derive(Clone)]
 pub struct RedirectAnalytics {
@@ -31,9 +37,14 @@ impl RedirectAnalytics {
         config.enabled.then_some(Self { config, aggregator })
     }
 
-    fn record(&self, short_code: Arc<str>, headers: &HeaderMap, socket_ip: std::net::IpAddr) {
+    fn record(&self, target: &RedirectTarget, headers: &HeaderMap, socket_ip: std::net::IpAddr) {
+        // Paused by an admin at runtime
+        if !self.aggregator.is_enabled() {
+            return;
+        }
         record_analytics(
-            short_code,
+            target.analytics_code(),
+            target.alias_used(),
             headers,
             socket_ip,
             &self.config,
@@ -48,6 +59,16 @@ pub struct RedirectState {
     /// Configurable redirect status code (301/302/303/307/308).
     /// Stored as StatusCode for zero-cost access during redirects.
     pub(super) redirect_status: StatusCode,
+    /// Opt-in outcome counters; `None` keeps the hot path free of bookkeeping.
+    pub(super) stats: Option<Arc<RedirectStats>>,
+    /// Cleans up mangled codes for a second lookup after a miss.
+    pub(super) normalizer: Option<CodeNormalizer>,
+    /// Resolves destinations that are our own links internally.
+    pub(super) self_redirects: Option<SelfRedirectGuard>,
+    /// What the bare domain answers.
+    pub(super) landing: RootLanding,
+    /// Shown instead of redirecting for flagged links and covered domains.
+    pub(super) countdown: CountdownPage,
 }
 
 /// Minimal redirect path used when analytics and timing headers are disabled.
@@ -56,9 +77,9 @@ pub async fn redirect_url(
     Path(code): Path<String>,
 ) -> Response {
     match prepare_redirect(&state, &code).await {
-        Ok(url) => {
-            let response = redirect_response(&state, &url);
-            buffer_click(&state, code);
+        Ok(accepted) => {
+            let response = redirect_response(&state, &accepted);
+            buffer_click(&state, &accepted.target, code);
             response
         }
         Err(response) => response,
@@ -73,14 +94,14 @@ pub async fn redirect_url_with_analytics(
     headers: HeaderMap,
 ) -> Response {
     match prepare_redirect(&state, &code).await {
-        Ok(url) => {
+        Ok(accepted) => {
             state
                 .analytics
                 .as_ref()
                 .expect("analytics handler requires analytics runtime")
-                .record(url.analytics_code(), &headers, addr.ip());
-            let response = redirect_response(&state, &url);
-            buffer_click(&state, code);
+                .record(&accepted.target, &headers, addr.ip());
+            let response = redirect_response(&state, &accepted);
+            buffer_click(&state, &accepted.target, code);
             response
         }
         Err(response) => response,
@@ -95,10 +116,10 @@ pub async fn redirect_url_with_timing(
 ) -> Response {
     let handler_start = Instant::now();
     match prepare_measured_redirect(&state, &code).await {
-        Ok((url, metadata)) => {
+        Ok((accepted, metadata)) => {
             let response =
-                timed_redirect_response(&state, &url, metadata, handler_start, request_start);
-            buffer_click(&state, code);
+                timed_redirect_response(&state, &accepted, metadata, handler_start, request_start);
+            buffer_click(&state, &accepted.target, code);
             response
         }
         Err(response) => response,
@@ -115,63 +136,306 @@ pub async fn redirect_url_with_analytics_and_timing(
 ) -> Response {
     let handler_start = Instant::now();
     match prepare_measured_redirect(&state, &code).await {
-        Ok((url, metadata)) => {
+        Ok((accepted, metadata)) => {
             state
                 .analytics
                 .as_ref()
                 .expect("analytics handler requires analytics runtime")
-                .record(url.analytics_code(), &headers, addr.ip());
+                .record(&accepted.target, &headers, addr.ip());
             let response =
-                timed_redirect_response(&state, &url, metadata, handler_start, request_start);
-            buffer_click(&state, code);
+                timed_redirect_response(&state, &accepted, metadata, handler_start, request_start);
+            buffer_click(&state, &accepted.target, code);
             response
         }
         Err(response) => response,
     }
 }
 
-async fn prepare_redirect(state: &RedirectState, code: &str) -> Result<RedirectTarget, Response> {
+/// A link accepted for redirecting.
+struct Accepted {
+    /// The requested link, credited with the click and analytics
+    target: RedirectTarget,
+    /// The last of our own links the requested one chains through, whose
+    /// external destination is served instead
+    hop: Option<RedirectTarget>,
+}
+
+impl Accepted {
+    /// The link whose destination the response redirects to.
+    fn served(&self) -> &RedirectTarget {
+        self.hop.as_ref().unwrap_or(&self.target)
+    }
+
+    /// The countdown page to answer with instead of a redirect, when the
+    /// requested link or the one served is flagged for it or the
+    /// destination is on a covered domain.
+    fn countdown(&self, page: &CountdownPage) -> Option<Response> {
+        let served = self.served();
+        let flagged = self.target.flagged_for_countdown() || served.flagged_for_countdown();
+        page.applies(flagged, served.original_url())
+            .then(|| page.response(served.original_url()))
+    }
+}
+
+async fn prepare_redirect(state: &RedirectState, code: &str) -> Result<Accepted, Response> {
+    // Latency percentiles need the lookup timings, so take the measured
+    // path whenever outcomes are counted
+    let target = if state.stats.is_some() {
+        lookup_measured_redirect(state, code).await?.0
+    } else {
+        lookup_redirect(state, code).await?
+    };
+    follow_self_redirects(state, target).await
+}
+
+async fn prepare_measured_redirect(
+    state: &RedirectState,
+    code: &str,
+) -> Result<(Accepted, LookupMetadata), Response> {
+    let (target, metadata) = lookup_measured_redirect(state, code).await?;
+    Ok((follow_self_redirects(state, target).await?, metadata))
+}
+
+async fn lookup_redirect(state: &RedirectState, path: &str) -> Result<RedirectTarget, Response> {
+    let code = code_in_path(state, path)?;
     let url = state
         .storage
         .get_redirect(code)
         .await
-        .map_err(|_| internal_error())?;
-    accept_redirect(url).map_err(IntoResponse::into_response)
+        .map_err(lookup_failed)?;
+    if url.is_none() {
+        if let Some(normalized) = normalized_code(state, code) {
+            let url = state
+                .storage
+                .get_redirect(&normalized)
+                .await
+                .map_err(lookup_failed)?;
+            return accept_normalized(state, code, &normalized, url);
+        }
+    }
+    accept_redirect(state, code, url)
 }
 
-async fn prepare_measured_redirect(
+async fn lookup_measured_redirect(
     state: &RedirectState,
-    code: &str,
+    path: &str,
 ) -> Result<(RedirectTarget, LookupMetadata), Response> {
+    let code = code_in_path(state, path)?;
     let result = state
         .storage
         .get_redirect_with_metadata(code)
         .await
-        .map_err(|_| internal_error())?;
-    let url = accept_redirect(result.target).map_err(IntoResponse::into_response)?;
+        .map_err(lookup_failed)?;
+    if result.target.is_none() {
+        if let Some(normalized) = normalized_code(state, code) {
+            let retry = state
+                .storage
+                .get_redirect_with_metadata(&normalized)
+                .await
+                .map_err(lookup_failed)?;
+            record_latency(
+                state,
+                &retry,
+                lookup_time(&result.metadata) + lookup_time(&retry.metadata),
+            );
+            let url = accept_normalized(state, code, &normalized, retry.target)?;
+            return Ok((url, retry.metadata));
+        }
+    }
+    record_latency(state, &result, lookup_time(&result.metadata));
+    let url = accept_redirect(state, code, result.target)?;
     Ok((url, result.metadata))
 }
 
+/// Time a lookup spent in the cache and the database.
+fn lookup_time(metadata: &LookupMetadata) -> Duration {
+    metadata.cache_duration.unwrap_or_default() + metadata.db_duration.unwrap_or_default()
+}
+
+/// Count `elapsed` against where `lookup` found its answer. A code retried
+/// after normalization is timed across both lookups.
+fn record_latency(state: &RedirectState, lookup: &RedirectLookup, elapsed: Duration) {
+    let Some(stats) = &state.stats else {
+        return;
+    };
+    let outcome = match (&lookup.target, lookup.metadata.cache_hit) {
+        (None, _) => LatencyOutcome::Miss,
+        (Some(_), true) => LatencyOutcome::CacheHit,
+        (Some(_), false) => LatencyOutcome::DbHit,
+    };
+    stats.record_latency(outcome, elapsed);
+}
+
+/// The code named by a request path, or the 404 for a path that cannot name
+/// one, answered without a lookup.
+#[allow(clippy::result_large_err)]
+fn code_in_path<'a>(state: &RedirectState, path: &'a str) -> Result<&'a str, Response> {
+    code_from_path(path).ok_or_else(|| {
+        if let Some(stats) = &state.stats {
+            stats.record(RedirectOutcome::NotFound, path);
+        }
+        (StatusCode::NOT_FOUND, "URL not found").into_response()
+    })
+}
+
+/// Resolve a chain of our own links internally when `target` points at one.
+///
+/// Hops that cannot be redirected through (missing, reserved, deactivated,
+/// expired or used-up links) end the chain there, and the client is sent to that link to get
+/// its 404 or 410 from us. A chain that revisits a link or outgrows the
+/// guard's hop limit is answered with `508 Loop Detected`.
+async fn follow_self_redirects(
+    state: &RedirectState,
+    target: RedirectTarget,
+) -> Result<Accepted, Response> {
+    let Some(guard) = &state.self_redirects else {
+        return Ok(Accepted { target, hop: None });
+    };
+    let Some(mut next) = guard.next_code(target.original_url()) else {
+        return Ok(Accepted { target, hop: None });
+    };
+
+    let mut visited = vec![target.short_code().to_owned()];
+    let mut hop: Option<RedirectTarget> = None;
+    for _ in 0..guard.max_hops() {
+        if visited.contains(&next) {
+            return Err(loop_detected(&target));
+        }
+        let Some(link) = state
+            .storage
+            .get_redirect(&next)
+            .await
+            .map_err(lookup_failed)?
+            .filter(|link| {
+                link.is_active()
+                    && !link.is_reserved()
+                    && !link.is_expired()
+                    && !state.storage.clicks_exhausted(link)
+            })
+        else {
+            return Ok(Accepted { target, hop });
+        };
+        if visited.iter().any(|code| code == link.short_code()) {
+            return Err(loop_detected(&target));
+        }
+        visited.push(next);
+        visited.push(link.short_code().to_owned());
+
+        match guard.next_code(link.original_url()) {
+            Some(code) => {
+                next = code;
+                hop = Some(link);
+            }
+            None => {
+                return Ok(Accepted {
+                    target,
+                    hop: Some(link),
+                })
+            }
+        }
+    }
+    Err(loop_detected(&target))
+}
+
+fn loop_detected(target: &RedirectTarget) -> Response {
+    tracing::warn!(
+        short_code = %target.short_code(),
+        url = %target.original_url(),
+        "Redirect loop through this shortener's own links"
+    );
+    (StatusCode::LOOP_DETECTED, "Redirect loop detected").into_response()
+}
+
+/// The cleaned-up form of a code that missed, when normalization is on and
+/// changes anything.
+fn normalized_code(state: &RedirectState, code: &str) -> Option<String> {
+    state.normalizer.as_ref()?.normalize(code)
+}
+
+/// Classify the result of the second lookup under `normalized`. A miss is
+/// reported against the code as requested, so the top-missing report shows
+/// what clients actually sent.
+#[allow(clippy::result_large_err)]
+fn accept_normalized(
+    state: &RedirectState,
+    code: &str,
+    normalized: &str,
+    target: Option<RedirectTarget>,
+) -> Result<RedirectTarget, Response> {
+    if target.is_none() {
+        return accept_redirect(state, code, None);
+    }
+    let url = accept_redirect(state, normalized, target)?;
+    tracing::debug!(requested = %code, short_code = %normalized, "redirect rescued by code normalization");
+    if let Some(stats) = &state.stats {
+        stats.record_normalized();
+    }
+    Ok(url)
+}
+
+/// Classify a lookup result. Only an active, non-reserved, unexpired target
+/// with clicks left comes back as `Ok`, and callers record clicks and
+/// analytics only on that branch, so 404s, 410s and expired links never
+/// reach the click buffer or the aggregator. A link that has used up its
+/// `max_clicks` answers like a deactivated one.
+#[allow(clippy::result_large_err)]
 fn accept_redirect(
+    state: &RedirectState,
+    code: &str,
     target: Option<RedirectTarget>,
-) -> Result<RedirectTarget, (StatusCode, &'static str)> {
-    let Some(target) = target else {
-        return Err((StatusCode::NOT_FOUND, "URL not found"));
+) -> Result<RedirectTarget, Response> {
+    let (outcome, result) = match target {
+        None => (
+            RedirectOutcome::NotFound,
+            Err((StatusCode::NOT_FOUND, "URL not found").into_response()),
+        ),
+        // Reserved codes have no destination yet and look like missing ones.
+        Some(target) if target.is_reserved() => (
+            RedirectOutcome::NotFound,
+            Err((StatusCode::NOT_FOUND, "URL not found").into_response()),
+        ),
+        Some(target) if !target.is_active() => (
+            RedirectOutcome::Inactive,
+            Err((StatusCode::GONE, "This link has been deactivated").into_response()),
+        ),
+        Some(target) if target.is_expired() => (
+            RedirectOutcome::Expired,
+            Err(state.landing.expired_response()),
+        ),
+        Some(target) if !state.storage.admit_click(&target) => (
+            RedirectOutcome::Inactive,
+            Err((StatusCode::GONE, "This link has been deactivated").into_response()),
+        ),
+        Some(target) => (RedirectOutcome::Found, Ok(target)),
     };
-    if !target.is_active() {
-        return Err((StatusCode::GONE, "This link has been deactivated"));
+    if let Some(stats) = &state.stats {
+        stats.record(outcome, code);
     }
 
-    Ok(target)
+    result
 }
 
-fn buffer_click(state: &RedirectState, code: String) {
+/// Count a click for the resolved link. A request through an alias resolves
+/// to its canonical code, which gets the click instead.
+fn buffer_click(state: &RedirectState, target: &RedirectTarget, code: String) {
+    let code = if target.short_code() == code {
+        code
+    } else {
+        target.short_code().to_owned()
+    };
     if let Err(error) = state.storage.buffer_click_owned(code, 1) {
         tracing::warn!(short_code = %error.short_code(), error = %error, "failed to buffer click increment");
     }
 }
 
-fn redirect_response(state: &RedirectState, target: &RedirectTarget) -> Response {
+fn redirect_response(state: &RedirectState, accepted: &Accepted) -> Response {
+    let target = accepted.served();
+    if target.requires_interstitial() {
+        return interstitial_response(target.original_url());
+    }
+    if let Some(page) = accepted.countdown(&state.countdown) {
+        return page;
+    }
     match location_header(target) {
         Some(location) => (state.redirect_status, [(LOCATION, location)]).into_response(),
         None => internal_error(),
@@ -180,11 +444,18 @@ fn redirect_response(state: &RedirectState, target: &RedirectTarget) -> Response
 
 fn timed_redirect_response(
     state: &RedirectState,
-    target: &RedirectTarget,
+    accepted: &Accepted,
     metadata: LookupMetadata,
     handler_start: Instant,
     request_start: Instant,
 ) -> Response {
+    let target = accepted.served();
+    if target.requires_interstitial() {
+        return interstitial_response(target.original_url());
+    }
+    if let Some(page) = accepted.countdown(&state.countdown) {
+        return page;
+    }
     let location = match location_header(target) {
         Some(location) => location,
         None => return internal_error(),
@@ -221,19 +492,34 @@ fn location_header(target: &RedirectTarget) -> Option<HeaderValue> {
     if location.is_none() {
         tracing::error!(
             short_code = %target.short_code(),
-            url = %target.original_url(),
-            "Failed to create Location header - URL contains invalid characters"
+            url = ?target.original_url(),
+            "Failed to create Location header - stored URL has no usable characters"
         );
     }
     location
 }
 
+/// A lookup that failed even after falling back to stale links. An
+/// unreachable or overloaded database answers 503 so clients retry; any
+/// other failure is a 500.
+fn lookup_failed(error: anyhow::Error) -> Response {
+    if let StorageError::Unavailable(_) = StorageError::from(error) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Service temporarily unavailable",
+        )
+            .into_response();
+    }
+    internal_error()
+}
+
 fn internal_error() -> Response {
     (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response()
 }
 
 fn record_analytics(
     short_code: Arc<str>,
+    alias_used: Option<Arc<str>>,
     headers: &HeaderMap,
     socket_ip: std::net::IpAddr,
     config: &AnalyticsConfig,
@@ -256,20 +542,15 @@ fn record_analytics(
         short_code,
         timestamp: chrono::Utc::now().timestamp(),
         client_ip,
+        alias_used,
+        weight: 1,
     };
 
     // Record event in aggregator (non-blocking, no GeoIP lookup!)
     aggregator.record_event(event);
 }
 
-/// Health check endpoint
-pub async fn health_check() -> impl IntoResponse {
-    #[derive(Serialize)]
-    struct HealthResponse {
-        status: String,
-    }
-
-    Json(HealthResponse {
-        status: "OK".to_string(),
-    })
+/// Landing response for the bare redirect domain (`GET /`)
+pub async fn root_landing(State(state): State<Arc<RedirectState>>) -> Response {
+    state.landing.response()
 }
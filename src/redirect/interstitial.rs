@@ -0,0 +1,64 @@
+//! Landing page for destinations that cannot be sent as a `Location` redirect.
+//!
+//! Non-web schemes such as `mailto:` and `tel:` hand off to another
+//! application, so instead of redirecting blindly the user gets a minimal page
+//! with an explicit link. The page is script-free and locked down by CSP.
+
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+
+pub fn interstitial_response(destination: &str) -> Response {
+    let escaped = escape_html(destination);
+    let body = format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Open link</title>\n</head>\n<body>\n\
+         <p>This link opens outside your browser:</p>\n\
+         <p><a href=\"{escaped}\" rel=\"noopener noreferrer\">{escaped}</a></p>\n\
+         </body>\n</html>\n"
+    );
+
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, "text/html; charset=utf-8"),
+            (CONTENT_SECURITY_POLICY, "default-src 'none'"),
+            (REFERRER_POLICY, "no-referrer"),
+            (CACHE_CONTROL, "no-store"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_in_destination_is_escaped() {
+        assert_eq!(
+            escape_html(r#"mailto:a@b.c?subject="><script>x</script>&'"#),
+            "mailto:a@b.c?subject=&quot;&gt;&lt;script&gt;x&lt;/script&gt;&amp;&#39;"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Requests the redirect server answers without looking anything up.
+//!
+//! The bare domain (`GET /`) gets a landing response: a redirect to the
+//! configured homepage, or else a minimal info page that also serves load
+//! balancer health checks. Expired links likewise redirect to the configured
+//! expired page, or else answer 404. Only single-segment paths can name a short code;
+//! a trailing slash (`/abc/`) is dropped, and anything with more segments
+//! (`/abc/def`, `//abc`) is a 404 without a storage call.
+
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, CONTENT_TYPE, LOCATION},
+        HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+
+use crate::config::RedirectLandingConfig;
+
+const INFO_PAGE: &str = "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+     <title>Lynx</title>\n</head>\n<body>\n\
+     <p>This domain serves short links.</p>\n\
+     </body>\n</html>\n";
+
+/// What `GET /` and expired links answer on the redirect server.
+#[derive(Debug, Clone, Default)]
+pub struct RootLanding {
+    homepage: Option<HeaderValue>,
+    expired_page: Option<HeaderValue>,
+}
+
+impl RootLanding {
+    /// Landing from configuration; a page that cannot be sent as a header
+    /// falls back to the built-in answer.
+    pub fn from_config(config: &RedirectLandingConfig) -> Self {
+        Self {
+            homepage: location(config.homepage.as_deref(), "REDIRECT_HOMEPAGE_URL"),
+            expired_page: location(config.expired_page.as_deref(), "REDIRECT_EXPIRED_URL"),
+        }
+    }
+
+    pub fn response(&self) -> Response {
+        match &self.homepage {
+            // Temporary, so browsers don't pin the domain to an old homepage.
+            Some(location) => (StatusCode::FOUND, [(LOCATION, location.clone())]).into_response(),
+            None => (
+                StatusCode::OK,
+                [
+                    (CONTENT_TYPE, "text/html; charset=utf-8"),
+                    (CONTENT_SECURITY_POLICY, "default-src 'none'"),
+                    (CACHE_CONTROL, "no-store"),
+                ],
+                INFO_PAGE,
+            )
+                .into_response(),
+        }
+    }
+
+    /// What a link past its expiry answers instead of a redirect.
+    pub fn expired_response(&self) -> Response {
+        match &self.expired_page {
+            Some(location) => (
+                StatusCode::FOUND,
+                [
+                    (LOCATION, location.clone()),
+                    (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+                ],
+            )
+                .into_response(),
+            None => (StatusCode::NOT_FOUND, "This link has expired").into_response(),
+        }
+    }
+}
+
+/// `url` as a `Location` header value, or `None` with a warning naming the
+/// `setting` it came from when it cannot be sent as one.
+fn location(url: Option<&str>, setting: &str) -> Option<HeaderValue> {
+    let url = url?;
+    let value = HeaderValue::from_str(url).ok();
+    if value.is_none() {
+        tracing::warn!(url, "{} cannot be sent as a Location header", setting);
+    }
+    value
+}
+
+/// The short code a request path after the leading `/` names, or `None`
+/// when the path has more than one segment (or none) and cannot be a code.
+pub fn code_from_path(path: &str) -> Option<&str> {
+    let code = path.strip_suffix('/').unwrap_or(path);
+    (!code.is_empty() && !code.contains('/')).then_some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_single_segment_paths_are_codes() {
+        assert_eq!(code_from_path("abc"), Some("abc"));
+        assert_eq!(code_from_path("abc/"), Some("abc"));
+        assert_eq!(code_from_path("abc.)"), Some("abc.)"));
+        assert_eq!(code_from_path("abc//"), None);
+        assert_eq!(code_from_path("abc/def"), None);
+        assert_eq!(code_from_path("/abc"), None);
+        assert_eq!(code_from_path("/"), None);
+        assert_eq!(code_from_path(""), None);
+    }
+
+    #[test]
+    fn root_redirects_to_the_homepage_when_configured() {
+        let landing = RootLanding::from_config(&RedirectLandingConfig {
+            homepage: Some("https://example.com/about".to_string()),
+            ..RedirectLandingConfig::default()
+        });
+        let response = landing.response();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[LOCATION],
+            HeaderValue::from_static("https://example.com/about")
+        );
+
+        let response = RootLanding::default().response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(LOCATION).is_none());
+    }
+
+    #[test]
+    fn expired_links_go_to_the_expired_page_when_configured() {
+        let response = RootLanding::default().expired_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let landing = RootLanding::from_config(&RedirectLandingConfig {
+            expired_page: Some("https://example.com/expired".to_string()),
+            ..RedirectLandingConfig::default()
+        });
+        let response = landing.expired_response();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[LOCATION],
+            HeaderValue::from_static("https://example.com/expired")
+        );
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-store");
+    }
+}
@@ -0,0 +1,293 @@
+//! Sliding-window redirect latency percentiles, split by lookup outcome.
+//!
+//! Each outcome keeps one histogram per minute of the window. Buckets are
+//! log-linear in microseconds, eight per power of two, so a reported
+//! percentile is never more than 12.5% above the true value. Recording is
+//! one relaxed load and one relaxed `fetch_add`; a timer calls
+//! [`LatencyWindow::rotate`] every [`SLOT_DURATION`] to retire the oldest
+//! minute.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// How long one histogram slot collects samples before the next takes over.
+pub const SLOT_DURATION: Duration = Duration::from_secs(60);
+
+/// Slots in the window; the oldest is cleared when the window rotates.
+pub const SLOTS: usize = 5;
+
+/// Sub-buckets per power of two, as a bit count (8 sub-buckets)
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+
+/// Samples are capped at 2^32 µs (about 71 minutes).
+const MAX_MICROS: u64 = (1 << 32) - 1;
+
+/// Buckets needed to cover `0..=MAX_MICROS`.
+const BUCKETS: usize = (32 - SUB_BUCKET_BITS as usize + 1) * SUB_BUCKETS;
+
+/// Where a redirect lookup found its answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyOutcome {
+    /// The link was served from the read cache.
+    CacheHit,
+    /// The link was loaded from the database.
+    DbHit,
+    /// No link exists for the code, whether the cache or the database said so.
+    Miss,
+}
+
+impl LatencyOutcome {
+    const ALL: [LatencyOutcome; 3] = [Self::CacheHit, Self::DbHit, Self::Miss];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Per-outcome latency histograms over the last [`SLOTS`] minutes.
+#[derive(Debug)]
+pub struct LatencyWindow {
+    /// `SLOTS * outcomes * BUCKETS` counters, slot-major
+    counts: Box<[AtomicU64]>,
+    /// Slot new samples go to
+    current: AtomicUsize,
+}
+
+impl LatencyWindow {
+    pub fn new() -> Self {
+        Self {
+            counts: (0..SLOTS * LatencyOutcome::ALL.len() * BUCKETS)
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count one lookup that took `elapsed`.
+    pub fn record(&self, outcome: LatencyOutcome, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let slot = self.current.load(Ordering::Relaxed);
+        self.counts[offset(slot, outcome) + bucket_of(micros)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Clear the oldest slot and send new samples to it.
+    pub fn rotate(&self) {
+        let next = (self.current.load(Ordering::Relaxed) + 1) % SLOTS;
+        for outcome in LatencyOutcome::ALL {
+            let start = offset(next, outcome);
+            for count in &self.counts[start..start + BUCKETS] {
+                count.store(0, Ordering::Relaxed);
+            }
+        }
+        self.current.store(next, Ordering::Relaxed);
+    }
+
+    /// Percentiles of every outcome over the whole window.
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let outcome = |outcome| {
+            let mut buckets = [0u64; BUCKETS];
+            for slot in 0..SLOTS {
+                let start = offset(slot, outcome);
+                for (total, count) in buckets.iter_mut().zip(&self.counts[start..start + BUCKETS]) {
+                    *total += count.load(Ordering::Relaxed);
+                }
+            }
+            OutcomeLatency::from_buckets(&buckets)
+        };
+        LatencySnapshot {
+            window_secs: SLOT_DURATION.as_secs() * SLOTS as u64,
+            cache_hit: outcome(LatencyOutcome::CacheHit),
+            db_hit: outcome(LatencyOutcome::DbHit),
+            miss: outcome(LatencyOutcome::Miss),
+        }
+    }
+}
+
+impl Default for LatencyWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySnapshot {
+    /// Span the percentiles cover; the newest minute is still filling up
+    pub window_secs: u64,
+    pub cache_hit: OutcomeLatency,
+    pub db_hit: OutcomeLatency,
+    pub miss: OutcomeLatency,
+}
+
+/// Lookup latency percentiles in milliseconds; `null` without samples.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutcomeLatency {
+    pub count: u64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+impl OutcomeLatency {
+    fn from_buckets(buckets: &[u64; BUCKETS]) -> Self {
+        let count = buckets.iter().sum();
+        let millis = |quantile| percentile(buckets, count, quantile).map(|us| us as f64 / 1000.0);
+        Self {
+            count,
+            p50_ms: millis(0.50),
+            p95_ms: millis(0.95),
+            p99_ms: millis(0.99),
+        }
+    }
+}
+
+fn offset(slot: usize, outcome: LatencyOutcome) -> usize {
+    (slot * LatencyOutcome::ALL.len() + outcome.index()) * BUCKETS
+}
+
+/// Bucket holding `micros`: exact below 8 µs, then eight buckets per power
+/// of two.
+fn bucket_of(micros: u64) -> usize {
+    let micros = micros.min(MAX_MICROS);
+    if micros < SUB_BUCKETS as u64 {
+        return micros as usize;
+    }
+    let exponent = 63 - micros.leading_zeros();
+    let sub_bucket = (micros >> (exponent - SUB_BUCKET_BITS)) as usize & (SUB_BUCKETS - 1);
+    (exponent - SUB_BUCKET_BITS + 1) as usize * SUB_BUCKETS + sub_bucket
+}
+
+/// Largest value that falls into `bucket`.
+fn bucket_ceiling(bucket: usize) -> u64 {
+    if bucket < SUB_BUCKETS {
+        return bucket as u64;
+    }
+    let shift = (bucket / SUB_BUCKETS - 1) as u32;
+    let floor = ((SUB_BUCKETS + bucket % SUB_BUCKETS) as u64) << shift;
+    floor + (1 << shift) - 1
+}
+
+/// The value at `quantile` of `count` samples, reported as the ceiling of
+/// the bucket that holds it.
+fn percentile(buckets: &[u64; BUCKETS], count: u64, quantile: f64) -> Option<u64> {
+    if count == 0 {
+        return None;
+    }
+    let rank = ((quantile * count as f64).ceil() as u64).clamp(1, count);
+    let mut seen = 0;
+    for (bucket, samples) in buckets.iter().enumerate() {
+        seen += samples;
+        if seen >= rank {
+            return Some(bucket_ceiling(bucket));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(value: u64) -> Duration {
+        Duration::from_micros(value)
+    }
+
+    #[test]
+    fn every_value_lands_in_a_bucket_within_an_eighth_of_it() {
+        for value in (0..5_000).chain([65_535, 1_000_000, MAX_MICROS]) {
+            let bucket = bucket_of(value);
+            assert!(bucket < BUCKETS, "{value} overflows");
+            let ceiling = bucket_ceiling(bucket);
+            assert!(
+                ceiling >= value,
+                "{value} above its bucket ceiling {ceiling}"
+            );
+            assert!(
+                ceiling - value <= value / 8,
+                "{value} reported as {ceiling}"
+            );
+            assert!(bucket == 0 || bucket_ceiling(bucket - 1) < value);
+        }
+        assert_eq!(bucket_of(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn percentiles_follow_the_rank_of_injected_samples() {
+        let window = LatencyWindow::new();
+        for _ in 0..94 {
+            window.record(LatencyOutcome::CacheHit, micros(5));
+        }
+        for _ in 0..4 {
+            window.record(LatencyOutcome::CacheHit, micros(6));
+        }
+        for _ in 0..2 {
+            window.record(LatencyOutcome::CacheHit, micros(7));
+        }
+
+        let cache_hit = window.snapshot().cache_hit;
+        // Values below 8 µs have buckets of their own, so the ranks are exact:
+        // the 50th and 95th samples are 5 and 6 µs, the 99th is 7 µs
+        assert_eq!(
+            cache_hit,
+            OutcomeLatency {
+                count: 100,
+                p50_ms: Some(0.005),
+                p95_ms: Some(0.006),
+                p99_ms: Some(0.007),
+            }
+        );
+    }
+
+    #[test]
+    fn percentiles_of_wide_samples_stay_within_bucket_precision() {
+        let window = LatencyWindow::new();
+        for millis in 1..=1_000 {
+            window.record(LatencyOutcome::DbHit, Duration::from_millis(millis));
+        }
+
+        let db_hit = window.snapshot().db_hit;
+        assert_eq!(db_hit.count, 1_000);
+        for (reported, expected) in [
+            (db_hit.p50_ms, 500.0),
+            (db_hit.p95_ms, 950.0),
+            (db_hit.p99_ms, 990.0),
+        ] {
+            let reported = reported.unwrap();
+            assert!(
+                reported >= expected && reported <= expected * 1.125,
+                "{reported} for {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn outcomes_are_kept_apart_and_empty_ones_report_null() {
+        let window = LatencyWindow::new();
+        window.record(LatencyOutcome::Miss, micros(3));
+
+        let snapshot = window.snapshot();
+        assert_eq!(snapshot.miss.count, 1);
+        assert_eq!(snapshot.miss.p99_ms, Some(0.003));
+        assert_eq!(snapshot.cache_hit.count, 0);
+        assert_eq!(snapshot.cache_hit.p50_ms, None);
+        assert_eq!(snapshot.db_hit.count, 0);
+    }
+
+    #[test]
+    fn samples_leave_the_window_after_a_full_rotation() {
+        let window = LatencyWindow::new();
+        window.record(LatencyOutcome::CacheHit, micros(2));
+        for _ in 0..SLOTS - 1 {
+            window.rotate();
+            window.record(LatencyOutcome::CacheHit, micros(4));
+        }
+        assert_eq!(window.snapshot().cache_hit.count, SLOTS as u64);
+
+        // The slot holding the first sample is reused
+        window.rotate();
+        let cache_hit = window.snapshot().cache_hit;
+        assert_eq!(cache_hit.count, SLOTS as u64 - 1);
+        assert_eq!(cache_hit.p50_ms, Some(0.004));
+    }
+}
@@ -0,0 +1,242 @@
+//! Link info for trusted internal services.
+//!
+//! `GET /{code}/info.json` answers where a link goes without redirecting, so
+//! a service such as a mail gateway can expand links it sends. It reports the
+//! destination, whether the link is active and when it was created, never
+//! clicks or analytics, and visiting it counts no click.
+//!
+//! The route exists only when `LINK_INFO_ENABLED` is set. Callers must come
+//! from `LINK_INFO_ALLOWED_IPS` (the socket peer, not forwarded headers) or
+//! send `LINK_INFO_TOKEN` in `X-Link-Info-Token`; anyone else gets `403`.
+//! Short codes are a single path segment (see [`super::landing`]), so the
+//! two-segment info path never shadows a code: `/info.json` still redirects
+//! a link named `info.json`.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header::CACHE_CONTROL, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use ipnet::IpNet;
+use serde::Serialize;
+use subtle::ConstantTimeEq;
+
+use crate::config::LinkInfoConfig;
+use crate::storage::CachedStorage;
+
+/// Path suffix that turns a code into an info request.
+pub const INFO_SUFFIX: &str = "/info.json";
+
+/// Header carrying the shared token.
+pub const TOKEN_HEADER: &str = "x-link-info-token";
+
+/// Who may read link info.
+#[derive(Debug, Clone)]
+pub struct LinkInfoGate {
+    allowed: Vec<IpNet>,
+    token: Option<String>,
+}
+
+impl LinkInfoGate {
+    /// The gate configured by `config`, or `None` when the endpoint is off.
+    /// An entry that is neither an address nor a range is refused, since
+    /// skipping it would quietly lock out the caller it was meant for.
+    pub fn from_config(config: &LinkInfoConfig) -> anyhow::Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let allowed = config
+            .allowed_ips
+            .iter()
+            .map(|entry| {
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        anyhow::anyhow!(
+                            "LINK_INFO_ALLOWED_IPS entries must be IP addresses or CIDR ranges, got '{entry}'"
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Some(Self {
+            allowed,
+            token: config.token.clone(),
+        }))
+    }
+
+    /// Whether a request from `peer` with `headers` may read link info.
+    pub fn admits(&self, peer: IpAddr, headers: &HeaderMap) -> bool {
+        let peer = peer.to_canonical();
+        self.allowed.iter().any(|range| range.contains(&peer)) || self.admits_token(headers)
+    }
+
+    /// Whether `headers` carry the shared token.
+    fn admits_token(&self, headers: &HeaderMap) -> bool {
+        match (&self.token, headers.get(TOKEN_HEADER)) {
+            (Some(token), Some(sent)) => token.as_bytes().ct_eq(sent.as_bytes()).into(),
+            _ => false,
+        }
+    }
+}
+
+/// What the endpoint reports about a link.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkInfo {
+    pub short_code: String,
+    /// Where the link redirects; for an alias, the destination of its target
+    pub destination: String,
+    pub is_active: bool,
+    /// Creation time in milliseconds since the Unix epoch
+    pub created_at: i64,
+}
+
+/// State of the layer that picks info requests off the redirect route.
+#[derive(Clone)]
+pub(super) struct LinkInfoLayer {
+    storage: Arc<CachedStorage>,
+    gate: Arc<LinkInfoGate>,
+}
+
+impl LinkInfoLayer {
+    pub(super) fn new(storage: Arc<CachedStorage>, gate: LinkInfoGate) -> Self {
+        Self {
+            storage,
+            gate: Arc::new(gate),
+        }
+    }
+}
+
+/// The code an info request is about: `path` (as the redirect route captured
+/// it, decoded) when `raw_path` is `/{code}/info.json` for a single-segment
+/// code, else `None`.
+fn info_code<'a>(raw_path: &str, path: &'a str) -> Option<&'a str> {
+    let is_info = raw_path
+        .strip_suffix(INFO_SUFFIX)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .is_some_and(|code| !code.is_empty() && !code.contains('/'));
+    is_info.then(|| path.strip_suffix(INFO_SUFFIX)).flatten()
+}
+
+/// Answer `/{code}/info.json` in front of the redirect route; every other
+/// path redirects as usual. The redirect route's catch-all cannot sit next
+/// to a two-segment route, so info requests are picked off here instead.
+pub(super) async fn serve_link_info(
+    State(layer): State<LinkInfoLayer>,
+    Path(path): Path<String>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(code) = info_code(request.uri().path(), &path) else {
+        return next.run(request).await;
+    };
+    // Without a peer address only the token can let the caller in
+    let headers = request.headers();
+    let admitted = match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => layer.gate.admits(addr.ip(), headers),
+        None => layer.gate.admits_token(headers),
+    };
+    if !admitted {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    link_info(&layer.storage, code).await
+}
+
+async fn link_info(storage: &CachedStorage, code: &str) -> Response {
+    let target = match storage.get_redirect(code).await {
+        Ok(target) => target,
+        Err(error) => {
+            tracing::error!(%error, short_code = %code, "link info lookup failed");
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+    match target {
+        Some(target) if !target.is_reserved() => (
+            [(CACHE_CONTROL, "no-store")],
+            Json(LinkInfo {
+                short_code: code.to_string(),
+                destination: target.original_url().to_string(),
+                is_active: target.is_active(),
+                created_at: target.created_at(),
+            }),
+        )
+            .into_response(),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(allowed_ips: &[&str], token: Option<&str>) -> LinkInfoGate {
+        LinkInfoGate::from_config(&LinkInfoConfig {
+            enabled: true,
+            allowed_ips: allowed_ips.iter().map(|ip| ip.to_string()).collect(),
+            token: token.map(str::to_string),
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn with_token(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TOKEN_HEADER, token.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn only_two_segment_info_paths_are_picked_off() {
+        assert_eq!(info_code("/docs/info.json", "docs/info.json"), Some("docs"));
+        assert_eq!(info_code("/d%20s/info.json", "d s/info.json"), Some("d s"));
+        assert_eq!(info_code("/info.json", "info.json"), None);
+        assert_eq!(info_code("//info.json", "/info.json"), None);
+        assert_eq!(info_code("/a/docs/info.json", "a/docs/info.json"), None);
+        assert_eq!(info_code("/docs/info.jsonx", "docs/info.jsonx"), None);
+        assert_eq!(info_code("/docs", "docs"), None);
+    }
+
+    #[test]
+    fn disabled_endpoint_has_no_gate() {
+        assert!(LinkInfoGate::from_config(&LinkInfoConfig::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn unparsable_allowlist_entries_are_refused() {
+        let config = LinkInfoConfig {
+            enabled: true,
+            allowed_ips: vec!["10.0.0.0/8".to_string(), "10.0.0.0/33".to_string()],
+            token: None,
+        };
+        let err = LinkInfoGate::from_config(&config).unwrap_err();
+        assert!(err.to_string().contains("10.0.0.0/33"));
+    }
+
+    #[test]
+    fn allowlisted_peers_need_no_token() {
+        let gate = gate(&["10.0.0.0/8", "2001:db8::1"], None);
+        let none = HeaderMap::new();
+        assert!(gate.admits("10.1.2.3".parse().unwrap(), &none));
+        assert!(gate.admits("::ffff:10.1.2.3".parse().unwrap(), &none));
+        assert!(gate.admits("2001:db8::1".parse().unwrap(), &none));
+        assert!(!gate.admits("192.0.2.1".parse().unwrap(), &none));
+        assert!(!gate.admits("192.0.2.1".parse().unwrap(), &with_token("guess")));
+    }
+
+    #[test]
+    fn token_lets_any_peer_in() {
+        let gate = gate(&[], Some("s3cret"));
+        let peer = "192.0.2.1".parse().unwrap();
+        assert!(gate.admits(peer, &with_token("s3cret")));
+        assert!(!gate.admits(peer, &with_token("s3cret ")));
+        assert!(!gate.admits(peer, &with_token("")));
+        assert!(!gate.admits(peer, &HeaderMap::new()));
+    }
+}
@@ -0,0 +1,299 @@
+//! Live visit feed for the analytics dashboard.
+//!
+//! Each watched short code gets a bounded [`broadcast`] channel, created by the
+//! first subscriber and removed with the last one. Publishing never waits:
+//! a subscriber that falls more than [`LIVE_CHANNEL_CAPACITY`] events behind
+//! loses the oldest ones instead of backing up redirects. Codes nobody watches
+//! cost a single map lookup per redirect.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header::USER_AGENT, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use super::landing::code_from_path;
+use crate::config::LiveVisitsConfig;
+
+/// Events buffered per code before slow subscribers start dropping them.
+pub const LIVE_CHANNEL_CAPACITY: usize = 64;
+
+/// Country reported for live visits. GeoIP lookups are deferred to the
+/// analytics flush, so a location is never known when the visit is published.
+pub const PENDING_COUNTRY: &str = "pending";
+
+/// Rough device class derived from the `User-Agent` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceClass {
+    Desktop,
+    Mobile,
+    Tablet,
+    Bot,
+    Unknown,
+}
+
+impl DeviceClass {
+    pub fn from_user_agent(user_agent: Option<&str>) -> Self {
+        let Some(user_agent) = user_agent.filter(|ua| !ua.trim().is_empty()) else {
+            return DeviceClass::Unknown;
+        };
+        let ua = user_agent.to_ascii_lowercase();
+        let has = |needle: &str| ua.contains(needle);
+
+        if [
+            "bot", "crawler", "spider", "slurp", "curl/", "wget/", "python-",
+        ]
+        .into_iter()
+        .any(has)
+        {
+            DeviceClass::Bot
+        } else if has("ipad") || has("tablet") || (has("android") && !has("mobile")) {
+            DeviceClass::Tablet
+        } else if has("mobi") || has("iphone") || has("ipod") {
+            DeviceClass::Mobile
+        } else {
+            DeviceClass::Desktop
+        }
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self::from_user_agent(headers.get(USER_AGENT).and_then(|v| v.to_str().ok()))
+    }
+}
+
+/// One redirect, as pushed to live subscribers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiveVisit {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub country: &'static str,
+    pub device: DeviceClass,
+}
+
+/// Per-code visit channels shared by the redirect and API servers.
+#[derive(Debug)]
+pub struct LiveVisits {
+    channels: DashMap<String, broadcast::Sender<LiveVisit>>,
+    /// Open subscriptions per user id
+    connections: DashMap<String, usize>,
+    max_connections_per_user: usize,
+}
+
+impl LiveVisits {
+    /// Build the feed from configuration, or `None` when it is disabled.
+    pub fn from_config(config: &LiveVisitsConfig) -> Option<Self> {
+        config
+            .enabled
+            .then(|| Self::new(config.max_connections_per_user))
+    }
+
+    pub fn new(max_connections_per_user: usize) -> Self {
+        Self {
+            channels: DashMap::new(),
+            connections: DashMap::new(),
+            max_connections_per_user,
+        }
+    }
+
+    pub fn max_connections_per_user(&self) -> usize {
+        self.max_connections_per_user
+    }
+
+    /// Whether anyone is subscribed to `code`.
+    pub fn is_watched(&self, code: &str) -> bool {
+        self.channels.contains_key(code)
+    }
+
+    /// Push a visit to the subscribers of `code`, if any.
+    pub fn publish(&self, code: &str, device: DeviceClass) {
+        let Some(sender) = self.channels.get(code) else {
+            return;
+        };
+        let visit = LiveVisit {
+            timestamp: chrono::Utc::now().timestamp(),
+            country: PENDING_COUNTRY,
+            device,
+        };
+        if sender.send(visit).is_err() {
+            // Every receiver is gone; drop the channel their guards left behind.
+            drop(sender);
+            self.channels
+                .remove_if(code, |_, sender| sender.receiver_count() == 0);
+        }
+    }
+
+    /// Subscribe `user` to visits of `code`, or `None` if the user already
+    /// holds the maximum number of live connections.
+    pub fn subscribe(self: &Arc<Self>, code: &str, user: &str) -> Option<LiveSubscription> {
+        {
+            let mut open = self.connections.entry(user.to_string()).or_insert(0);
+            if *open >= self.max_connections_per_user {
+                return None;
+            }
+            *open += 1;
+        }
+
+        let receiver = self
+            .channels
+            .entry(code.to_string())
+            .or_insert_with(|| broadcast::channel(LIVE_CHANNEL_CAPACITY).0)
+            .subscribe();
+
+        Some(LiveSubscription {
+            receiver,
+            guard: SubscriptionGuard {
+                live: Arc::clone(self),
+                code: code.to_string(),
+                user: user.to_string(),
+            },
+        })
+    }
+}
+
+/// An open subscription. Dropping it frees the user's connection slot.
+pub struct LiveSubscription {
+    pub receiver: broadcast::Receiver<LiveVisit>,
+    pub guard: SubscriptionGuard,
+}
+
+/// Releases a subscription's connection slot and, with the last subscriber,
+/// its channel.
+pub struct SubscriptionGuard {
+    live: Arc<LiveVisits>,
+    code: String,
+    user: String,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        if let Some(mut open) = self.live.connections.get_mut(&self.user) {
+            *open = open.saturating_sub(1);
+        }
+        self.live
+            .connections
+            .remove_if(&self.user, |_, open| *open == 0);
+        self.live
+            .channels
+            .remove_if(&self.code, |_, sender| sender.receiver_count() == 0);
+    }
+}
+
+/// Publish successful redirects (and interstitial pages) of watched codes.
+pub async fn record_live_visit(
+    State(live): State<Arc<LiveVisits>>,
+    Path(code): Path<String>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(code) = code_from_path(&code).filter(|code| live.is_watched(code)) else {
+        return next.run(request).await;
+    };
+
+    let device = DeviceClass::from_headers(request.headers());
+    let response = next.run(request).await;
+    let status = response.status();
+    if status.is_redirection() || status == StatusCode::OK {
+        live.publish(code, device);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    #[test]
+    fn user_agents_are_classified() {
+        let cases = [
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) Mobile/15E148",
+                DeviceClass::Mobile,
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; Pixel 8) Chrome/120.0 Mobile Safari/537.36",
+                DeviceClass::Mobile,
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 13; SM-X700) Chrome/120.0 Safari/537.36",
+                DeviceClass::Tablet,
+            ),
+            (
+                "Mozilla/5.0 (iPad; CPU OS 17_0 like Mac OS X)",
+                DeviceClass::Tablet,
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/120.0 Safari/537.36",
+                DeviceClass::Desktop,
+            ),
+            (
+                "Googlebot/2.1 (+http://www.google.com/bot.html)",
+                DeviceClass::Bot,
+            ),
+            ("curl/8.5.0", DeviceClass::Bot),
+        ];
+        for (user_agent, expected) in cases {
+            assert_eq!(
+                DeviceClass::from_user_agent(Some(user_agent)),
+                expected,
+                "{user_agent}"
+            );
+        }
+        assert_eq!(DeviceClass::from_user_agent(None), DeviceClass::Unknown);
+        assert_eq!(
+            DeviceClass::from_user_agent(Some(" ")),
+            DeviceClass::Unknown
+        );
+    }
+
+    #[test]
+    fn visits_reach_only_subscribers_of_the_code() {
+        let live = Arc::new(LiveVisits::new(3));
+        let mut promo = live.subscribe("promo", "alice").unwrap();
+        let mut other = live.subscribe("other", "alice").unwrap();
+
+        live.publish("promo", DeviceClass::Mobile);
+        live.publish("unwatched", DeviceClass::Desktop);
+
+        let visit = promo.receiver.try_recv().unwrap();
+        assert_eq!(visit.country, PENDING_COUNTRY);
+        assert_eq!(visit.device, DeviceClass::Mobile);
+        assert_eq!(other.receiver.try_recv(), Err(TryRecvError::Empty));
+        assert!(!live.is_watched("unwatched"));
+    }
+
+    #[test]
+    fn slow_subscribers_drop_old_events() {
+        let live = Arc::new(LiveVisits::new(1));
+        let mut slow = live.subscribe("promo", "alice").unwrap();
+
+        for _ in 0..LIVE_CHANNEL_CAPACITY + 5 {
+            live.publish("promo", DeviceClass::Desktop);
+        }
+
+        assert_eq!(slow.receiver.try_recv(), Err(TryRecvError::Lagged(5)));
+        assert!(slow.receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn connections_are_limited_per_user_and_released_on_drop() {
+        let live = Arc::new(LiveVisits::new(2));
+        let first = live.subscribe("promo", "alice").unwrap();
+        let second = live.subscribe("other", "alice").unwrap();
+        assert!(live.subscribe("promo", "alice").is_none());
+        assert!(live.subscribe("promo", "bob").is_some());
+
+        drop(first);
+        assert!(live.subscribe("promo", "alice").is_some());
+
+        drop(second);
+        assert!(!live.is_watched("other"));
+    }
+}
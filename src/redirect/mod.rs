@@ -1,6 +1,29 @@
+pub mod countdown;
 pub mod handlers;
+pub(crate) mod interstitial;
+pub mod landing;
+pub mod latency;
+pub mod link_info;
+pub mod live;
 pub mod middleware;
+pub mod normalize;
+pub mod prefix;
 pub mod routes;
+pub mod self_redirect;
+pub mod stats;
 
+pub use countdown::CountdownPage;
 pub use handlers::RedirectAnalytics;
-pub use routes::create_redirect_router;
+pub use landing::RootLanding;
+pub use link_info::LinkInfoGate;
+pub use live::LiveVisits;
+pub use normalize::CodeNormalizer;
+pub use prefix::mount_under_prefix;
+pub use routes::{
+    create_redirect_router, create_redirect_router_with_interstitial,
+    create_redirect_router_with_landing, create_redirect_router_with_link_info,
+    create_redirect_router_with_live_visits, create_redirect_router_with_lookup,
+    create_redirect_router_with_normalization, create_redirect_router_with_stats, RedirectLookup,
+};
+pub use self_redirect::SelfRedirectGuard;
+pub use stats::RedirectStats;
@@ -0,0 +1,157 @@
+//! Rescue short codes mangled on their way to the redirect server.
+//!
+//! Links pasted into emails and chat often arrive with a trailing period,
+//! a closing parenthesis, or an encoded space left over from line wrapping:
+//! `abc123.`, `abc123)`, `abc123%20`. When the code as requested does not
+//! exist, the redirect handler asks a [`CodeNormalizer`] for a cleaned-up
+//! form and looks that up once. Well-formed requests hit on the first lookup
+//! and never pay for normalization.
+
+use crate::config::CodeNormalizationConfig;
+
+/// How many rounds of percent-decoding are applied, so that a code encoded
+/// twice by a mail client (`abc123%2520`) still comes out clean.
+const MAX_DECODE_ROUNDS: usize = 2;
+
+/// Cleans up mangled short codes before a second lookup.
+#[derive(Debug, Clone)]
+pub struct CodeNormalizer {
+    trailing_chars: Vec<char>,
+}
+
+impl CodeNormalizer {
+    /// Build a normalizer from configuration, or `None` when disabled.
+    pub fn from_config(config: &CodeNormalizationConfig) -> Option<Self> {
+        config.enabled.then(|| Self::new(&config.trailing_chars))
+    }
+
+    /// Normalizer that strips any of `trailing_chars` from the end of a code.
+    pub fn new(trailing_chars: &str) -> Self {
+        Self {
+            trailing_chars: trailing_chars
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect(),
+        }
+    }
+
+    /// The normalized form of `code`, or `None` when normalizing changes
+    /// nothing or leaves nothing to look up.
+    ///
+    /// The code is percent-decoded, trimmed of whitespace, and stripped of
+    /// trailing punctuation, repeating until it stops changing so that
+    /// `abc123.)` and `abc123. ` both come out as `abc123`.
+    pub fn normalize(&self, code: &str) -> Option<String> {
+        let mut normalized = code.to_owned();
+        for _ in 0..MAX_DECODE_ROUNDS {
+            match percent_decode(&normalized) {
+                Some(decoded) if decoded != normalized => normalized = decoded,
+                _ => break,
+            }
+        }
+
+        let mut rest = normalized.as_str();
+        loop {
+            let stripped = rest.trim().trim_end_matches(self.trailing_chars.as_slice());
+            if stripped == rest {
+                break;
+            }
+            rest = stripped;
+        }
+
+        (!rest.is_empty() && rest != code).then(|| rest.to_owned())
+    }
+}
+
+/// Decode `%XX` escapes, or `None` when there are none to decode or the
+/// result is not valid UTF-8. Malformed escapes are kept as they are.
+pub(super) fn percent_decode(input: &str) -> Option<String> {
+    if !input.contains('%') {
+        return None;
+    }
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                decoded.push((high << 4) | low);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer() -> CodeNormalizer {
+        CodeNormalizer::from_config(&CodeNormalizationConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn common_mangled_forms_are_cleaned_up() {
+        let normalizer = normalizer();
+        for mangled in [
+            "abc123.",
+            "abc123,",
+            "abc123)",
+            "abc123;",
+            "abc123]",
+            "abc123.)",
+            " abc123",
+            "abc123 ",
+            "abc123\n",
+            "abc123%20",
+            "abc123%2520",
+            "abc123%2E",
+            "abc123. ",
+        ] {
+            assert_eq!(
+                normalizer.normalize(mangled).as_deref(),
+                Some("abc123"),
+                "{:?}",
+                mangled
+            );
+        }
+    }
+
+    #[test]
+    fn clean_or_unrescuable_codes_are_left_alone() {
+        let normalizer = normalizer();
+        assert_eq!(normalizer.normalize("abc123"), None);
+        assert_eq!(normalizer.normalize("a.b"), None);
+        assert_eq!(normalizer.normalize("..."), None);
+        assert_eq!(normalizer.normalize("%"), None);
+        assert_eq!(normalizer.normalize("abc%zz"), None);
+        assert_eq!(normalizer.normalize("abc123!"), None);
+
+        let custom = CodeNormalizer::new("!");
+        assert_eq!(custom.normalize("abc123!").as_deref(), Some("abc123"));
+        assert_eq!(custom.normalize("abc123.").as_deref(), None);
+        assert_eq!(custom.normalize(" abc123 ").as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn disabled_config_builds_no_normalizer() {
+        let config = CodeNormalizationConfig {
+            enabled: false,
+            ..CodeNormalizationConfig::default()
+        };
+        assert!(CodeNormalizer::from_config(&config).is_none());
+    }
+}
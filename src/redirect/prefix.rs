@@ -0,0 +1,101 @@
+//! Serving short links under a path prefix.
+//!
+//! Behind a proxy that forwards `https://example.com/s/...` to the redirect
+//! server without stripping `/s`, `REDIRECT_PATH_PREFIX=/s` mounts every
+//! redirect route under the prefix (`/s/{code}`, `/s/{code}/info.json`, and
+//! `/s` for the landing response) and adds it to the short URLs the API
+//! hands out. Paths outside the prefix are a 404.
+
+use anyhow::bail;
+use axum::Router;
+
+/// First segments the API server owns; a prefix starting with one would
+/// shadow it when both servers sit behind one proxy host.
+pub const RESERVED_FIRST_SEGMENTS: [&str; 2] = ["api", "assets"];
+
+/// A validated prefix from `value`: `None` when blank or `/`, else the
+/// prefix with a leading and no trailing slash. Segments may use unreserved
+/// URL characters only, and neither the API paths nor the link info suffix
+/// may appear in it.
+pub fn parse_path_prefix(value: &str) -> anyhow::Result<Option<String>> {
+    let value = value.trim();
+    if value.is_empty() || value == "/" {
+        return Ok(None);
+    }
+    let Some(path) = value.strip_prefix('/') else {
+        bail!("REDIRECT_PATH_PREFIX must start with `/`, got {value:?}");
+    };
+    let path = path.strip_suffix('/').unwrap_or(path);
+    for (index, segment) in path.split('/').enumerate() {
+        if segment.is_empty() || segment == "." || segment == ".." {
+            bail!("REDIRECT_PATH_PREFIX has an empty or relative segment: {value:?}");
+        }
+        if !segment
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~'))
+        {
+            bail!("REDIRECT_PATH_PREFIX may only use letters, digits and `-_.~`: {value:?}");
+        }
+        if index == 0 && RESERVED_FIRST_SEGMENTS.contains(&segment) {
+            bail!("REDIRECT_PATH_PREFIX must not start with the reserved path /{segment}");
+        }
+        if segment == "info.json" {
+            bail!("REDIRECT_PATH_PREFIX must not contain the reserved segment info.json");
+        }
+    }
+    Ok(Some(format!("/{path}")))
+}
+
+/// `router` mounted under `prefix`, or as is without one.
+pub fn mount_under_prefix(router: Router, prefix: Option<&str>) -> Router {
+    match prefix {
+        Some(prefix) => Router::new().nest(prefix, router),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_are_normalized() {
+        for (value, expected) in [
+            ("", None),
+            (" / ", None),
+            ("/s", Some("/s")),
+            ("/s/", Some("/s")),
+            (" /go/links ", Some("/go/links")),
+            ("/v1.0~x_y-z", Some("/v1.0~x_y-z")),
+            ("/apis", Some("/apis")),
+            ("/s/api", Some("/s/api")),
+        ] {
+            assert_eq!(
+                parse_path_prefix(value).unwrap().as_deref(),
+                expected,
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_or_reserved_prefixes_are_rejected() {
+        for value in [
+            "s",
+            "//s",
+            "/s//t",
+            "/s/../t",
+            "/./s",
+            "/s?x=1",
+            "/s#top",
+            "/s t",
+            "/%73",
+            "/api",
+            "/api/s",
+            "/assets",
+            "/s/info.json",
+        ] {
+            assert!(parse_path_prefix(value).is_err(), "{value:?}");
+        }
+    }
+}
@@ -4,27 +4,212 @@ use std::sync::Arc;
 use crate::storage::CachedStorage;
 use axum::http::StatusCode;
 
+use super::countdown::CountdownPage;
 use super::handlers::{
-    health_check, redirect_url, redirect_url_with_analytics,
-    redirect_url_with_analytics_and_timing, redirect_url_with_timing, RedirectAnalytics,
-    RedirectState,
+    redirect_url, redirect_url_with_analytics, redirect_url_with_analytics_and_timing,
+    redirect_url_with_timing, root_landing, RedirectAnalytics, RedirectState,
 };
+use super::landing::RootLanding;
+use super::link_info::{serve_link_info, LinkInfoGate, LinkInfoLayer};
+use super::live::{record_live_visit, LiveVisits};
 use super::middleware::record_request_start;
+use super::normalize::CodeNormalizer;
+use super::self_redirect::SelfRedirectGuard;
+use super::stats::RedirectStats;
+
+/// What the redirect handler tries beyond looking up the code as requested.
+#[derive(Debug, Clone, Default)]
+pub struct RedirectLookup {
+    /// Retry codes that miss in their cleaned-up form
+    pub normalizer: Option<CodeNormalizer>,
+    /// Resolve destinations that are our own links internally
+    pub self_redirects: Option<SelfRedirectGuard>,
+}
 
 pub fn create_redirect_router(
     storage: Arc<CachedStorage>,
     analytics: Option<RedirectAnalytics>,
     enable_timing_headers: bool,
     redirect_status: StatusCode,
+) -> Router {
+    create_redirect_router_with_stats(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        None,
+    )
+}
+
+/// Create the redirect router, counting outcomes into `stats` when provided.
+pub fn create_redirect_router_with_stats(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+) -> Router {
+    create_redirect_router_with_live_visits(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        None,
+    )
+}
+
+/// Create the redirect router, also publishing visits of watched codes to
+/// `live_visits` when provided.
+pub fn create_redirect_router_with_live_visits(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+) -> Router {
+    create_redirect_router_with_normalization(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        live_visits,
+        None,
+    )
+}
+
+/// Create the redirect router, retrying codes that miss with the form
+/// `normalizer` cleans them up to when provided.
+pub fn create_redirect_router_with_normalization(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    normalizer: Option<CodeNormalizer>,
+) -> Router {
+    create_redirect_router_with_lookup(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        live_visits,
+        RedirectLookup {
+            normalizer,
+            ..RedirectLookup::default()
+        },
+    )
+}
+
+/// Create the redirect router with the lookup fallbacks in `lookup`.
+pub fn create_redirect_router_with_lookup(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    lookup: RedirectLookup,
+) -> Router {
+    create_redirect_router_with_landing(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        live_visits,
+        lookup,
+        RootLanding::default(),
+    )
+}
+
+/// Create the redirect router, answering the bare domain with `landing`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_redirect_router_with_landing(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    lookup: RedirectLookup,
+    landing: RootLanding,
+) -> Router {
+    create_redirect_router_with_link_info(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        live_visits,
+        lookup,
+        landing,
+        None,
+    )
+}
+
+/// Create the redirect router, also serving `GET /{code}/info.json` to the
+/// callers `link_info` admits when provided. Without it the route does not
+/// exist and the path is an ordinary miss.
+#[allow(clippy::too_many_arguments)]
+pub fn create_redirect_router_with_link_info(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    lookup: RedirectLookup,
+    landing: RootLanding,
+    link_info: Option<LinkInfoGate>,
+) -> Router {
+    create_redirect_router_with_interstitial(
+        storage,
+        analytics,
+        enable_timing_headers,
+        redirect_status,
+        stats,
+        live_visits,
+        lookup,
+        landing,
+        link_info,
+        CountdownPage::default(),
+    )
+}
+
+/// Create the redirect router, answering links `countdown` applies to with
+/// its page instead of a redirect.
+#[allow(clippy::too_many_arguments)]
+pub fn create_redirect_router_with_interstitial(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    enable_timing_headers: bool,
+    redirect_status: StatusCode,
+    stats: Option<Arc<RedirectStats>>,
+    live_visits: Option<Arc<LiveVisits>>,
+    lookup: RedirectLookup,
+    landing: RootLanding,
+    link_info: Option<LinkInfoGate>,
+    countdown: CountdownPage,
 ) -> Router {
     let analytics_enabled = analytics.is_some();
+    let link_info = link_info.map(|gate| LinkInfoLayer::new(Arc::clone(&storage), gate));
     let state = Arc::new(RedirectState {
         storage,
         analytics,
         redirect_status,
+        stats,
+        normalizer: lookup.normalizer,
+        self_redirects: lookup.self_redirects,
+        landing,
+        countdown,
     });
 
-    let redirect_route = match (analytics_enabled, enable_timing_headers) {
+    let mut redirect_route = match (analytics_enabled, enable_timing_headers) {
         (false, false) => get(redirect_url),
         (true, false) => get(redirect_url_with_analytics),
         (false, true) => {
@@ -33,9 +218,20 @@ pub fn create_redirect_router(
         (true, true) => get(redirect_url_with_analytics_and_timing)
             .layer(middleware::from_fn(record_request_start)),
     };
+    if let Some(live_visits) = live_visits {
+        redirect_route = redirect_route.layer(middleware::from_fn_with_state(
+            live_visits,
+            record_live_visit,
+        ));
+    }
+
+    if let Some(link_info) = link_info {
+        redirect_route =
+            redirect_route.layer(middleware::from_fn_with_state(link_info, serve_link_info));
+    }
 
     Router::new()
-        .route("/", get(health_check))
+        .route("/", get(root_landing))
         .route("/{*code}", redirect_route)
         .with_state(state)
 }
@@ -0,0 +1,125 @@
+//! Defense against links that point back at this shortener.
+//!
+//! Destinations on our own redirect domains are rejected when links are
+//! created or updated, unless `URL_ALLOW_SELF_REDIRECTS` is set. Rows written
+//! before that check, or chains built on purpose with it enabled, can still
+//! send browsers around in circles (`/a → /b → /a`). When a redirect's
+//! destination is one of our own links, the handler resolves the chain
+//! internally with a [`SelfRedirectGuard`] and serves the final external
+//! destination directly, or answers `508 Loop Detected` when the chain loops
+//! or runs longer than [`MAX_SELF_REDIRECT_HOPS`].
+
+use url::Url;
+
+use super::normalize::percent_decode;
+use crate::config::DestinationConfig;
+use crate::destination::is_self_host;
+
+/// Links followed internally before a chain is treated as a loop.
+pub const MAX_SELF_REDIRECT_HOPS: usize = 5;
+
+/// Recognizes destinations that are short links on this instance.
+#[derive(Debug, Clone)]
+pub struct SelfRedirectGuard {
+    hosts: Vec<String>,
+    max_hops: usize,
+}
+
+impl SelfRedirectGuard {
+    /// Guard for the configured redirect hosts, or `None` when none are known.
+    pub fn from_config(config: &DestinationConfig) -> Option<Self> {
+        (!config.self_hosts.is_empty())
+            .then(|| Self::new(config.self_hosts.clone(), MAX_SELF_REDIRECT_HOPS))
+    }
+
+    pub fn new(hosts: Vec<String>, max_hops: usize) -> Self {
+        Self { hosts, max_hops }
+    }
+
+    /// Links followed internally before giving up.
+    pub fn max_hops(&self) -> usize {
+        self.max_hops
+    }
+
+    /// The short code `destination` redirects to when it points at one of
+    /// our own hosts, or `None` for external destinations.
+    ///
+    /// The host is compared before anything is parsed, so external
+    /// destinations cost a scan of the authority and no allocation.
+    pub fn next_code(&self, destination: &str) -> Option<String> {
+        let host = authority_host(destination)?;
+        if !is_self_host(host, &self.hosts) {
+            return None;
+        }
+        let url = Url::parse(destination).ok()?;
+        let path = url.path().trim_start_matches('/');
+        if path.is_empty() {
+            return None;
+        }
+        Some(percent_decode(path).unwrap_or_else(|| path.to_owned()))
+    }
+}
+
+/// The host of an absolute URL, without userinfo or port.
+pub(super) fn authority_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(']') && port.bytes().all(|b| b.is_ascii_digit()) => {
+            host
+        }
+        _ => authority,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> SelfRedirectGuard {
+        SelfRedirectGuard::new(vec!["short.example".to_string()], 3)
+    }
+
+    #[test]
+    fn own_links_yield_their_code() {
+        let guard = guard();
+        assert_eq!(
+            guard.next_code("https://short.example/abc").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            guard
+                .next_code("http://user@SHORT.example:8080/promo/2024?utm=x#top")
+                .as_deref(),
+            Some("promo/2024")
+        );
+        assert_eq!(
+            guard
+                .next_code("https://short.example/caf%C3%A9")
+                .as_deref(),
+            Some("café")
+        );
+    }
+
+    #[test]
+    fn external_and_codeless_destinations_are_ignored() {
+        let guard = guard();
+        assert_eq!(guard.next_code("https://example.com/abc"), None);
+        assert_eq!(guard.next_code("https://short.example.com/abc"), None);
+        assert_eq!(
+            guard.next_code("https://example.com/?u=short.example"),
+            None
+        );
+        assert_eq!(guard.next_code("https://short.example/"), None);
+        assert_eq!(guard.next_code("mailto:team@short.example"), None);
+    }
+
+    #[test]
+    fn no_hosts_means_no_guard() {
+        assert!(SelfRedirectGuard::from_config(&DestinationConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! Opt-in redirect outcome counters.
+//!
+//! Every redirect resolves to one [`RedirectOutcome`], counted with relaxed
+//! atomics so the hot path never takes a lock. Optionally, codes that resolve
+//! to nothing are fed into a [`MissingCodeTracker`], a fixed-capacity
+//! space-saving counter that surfaces the most requested nonexistent codes
+//! (typically typos of real campaigns) in bounded memory. Lookup latency is
+//! kept per outcome in a [`LatencyWindow`] covering the last five minutes.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::latency::{LatencyOutcome, LatencySnapshot, LatencyWindow, SLOT_DURATION};
+use crate::config::RedirectStatsConfig;
+
+/// Upper bound for the number of distinct missing codes tracked at once.
+pub const MAX_TOP_MISSING_CAPACITY: usize = 10_000;
+
+/// How a single redirect request was resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectOutcome {
+    /// The code exists and is active; a redirect was issued.
+    Found,
+    /// The code exists but has been deactivated or used up its `max_clicks`.
+    Inactive,
+    /// No link exists for the code.
+    NotFound,
+    /// The link exists but is past its `expires_at`.
+    Expired,
+}
+
+/// Process-wide redirect outcome counters shared by the redirect and API servers.
+#[derive(Debug)]
+pub struct RedirectStats {
+    found: AtomicU64,
+    inactive: AtomicU64,
+    not_found: AtomicU64,
+    expired: AtomicU64,
+    normalized: AtomicU64,
+    missing: Option<MissingCodeTracker>,
+    latency: LatencyWindow,
+}
+
+impl RedirectStats {
+    /// Build counters from configuration, or `None` when the feature is disabled.
+    ///
+    /// Missing codes longer than `max_code_length` are counted but never
+    /// tracked, since no link can exist under them.
+    pub fn from_config(config: &RedirectStatsConfig, max_code_length: usize) -> Option<Self> {
+        config
+            .enabled
+            .then(|| Self::new(config.top_missing_capacity, max_code_length))
+    }
+
+    pub fn new(top_missing_capacity: usize, max_code_length: usize) -> Self {
+        let capacity = top_missing_capacity.min(MAX_TOP_MISSING_CAPACITY);
+        Self {
+            found: AtomicU64::new(0),
+            inactive: AtomicU64::new(0),
+            not_found: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+            normalized: AtomicU64::new(0),
+            missing: (capacity > 0).then(|| MissingCodeTracker::new(capacity, max_code_length)),
+            latency: LatencyWindow::new(),
+        }
+    }
+
+    /// Count one redirect outcome for `code`.
+    pub fn record(&self, outcome: RedirectOutcome, code: &str) {
+        let counter = match outcome {
+            RedirectOutcome::Found => &self.found,
+            RedirectOutcome::Inactive => &self.inactive,
+            RedirectOutcome::NotFound => &self.not_found,
+            RedirectOutcome::Expired => &self.expired,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        if outcome == RedirectOutcome::NotFound {
+            if let Some(missing) = &self.missing {
+                missing.record(code);
+            }
+        }
+    }
+
+    /// Count a redirect that only resolved after its code was normalized.
+    pub fn record_normalized(&self) {
+        self.normalized.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count how long a redirect lookup took to reach `outcome`.
+    pub fn record_latency(&self, outcome: LatencyOutcome, elapsed: Duration) {
+        self.latency.record(outcome, elapsed);
+    }
+
+    /// Move the latency window on by one minute, forgetting the oldest.
+    pub fn rotate_latency(&self) {
+        self.latency.rotate();
+    }
+
+    /// Rotate the latency window of `stats` every minute until the stats
+    /// are dropped.
+    pub fn spawn_latency_rotation(stats: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let stats = Arc::downgrade(stats);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SLOT_DURATION);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(stats) = stats.upgrade() else {
+                    break;
+                };
+                stats.rotate_latency();
+            }
+        })
+    }
+
+    /// Point-in-time copy of every counter, with up to `top` missing codes.
+    pub fn snapshot(&self, top: usize) -> RedirectStatsSnapshot {
+        RedirectStatsSnapshot {
+            found: self.found.load(Ordering::Relaxed),
+            inactive: self.inactive.load(Ordering::Relaxed),
+            not_found: self.not_found.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            normalized: self.normalized.load(Ordering::Relaxed),
+            top_missing_capacity: self.missing.as_ref().map_or(0, |m| m.capacity),
+            top_missing: self
+                .missing
+                .as_ref()
+                .map(|missing| missing.top(top))
+                .unwrap_or_default(),
+            latency: self.latency.snapshot(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedirectStatsSnapshot {
+    pub found: u64,
+    pub inactive: u64,
+    pub not_found: u64,
+    pub expired: u64,
+    /// Redirects found only after trimming or decoding a mangled code;
+    /// these are also counted in `found`
+    pub normalized: u64,
+    /// Number of distinct missing codes the tracker can hold (0 when disabled)
+    pub top_missing_capacity: usize,
+    /// Most requested missing codes, highest count first
+    pub top_missing: Vec<MissingCodeCount>,
+    /// Lookup latency percentiles per outcome over the last five minutes
+    pub latency: LatencySnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingCodeCount {
+    pub code: String,
+    /// Estimated request count; an upper bound once the tracker has evicted codes
+    pub count: u64,
+}
+
+/// Space-saving heavy-hitter counter for codes that did not resolve.
+///
+/// At most `capacity` codes are held. When a new code arrives at capacity it
+/// replaces the least-counted entry and inherits that count plus one, so
+/// frequently requested codes survive while one-off probes churn out.
+#[derive(Debug)]
+pub struct MissingCodeTracker {
+    capacity: usize,
+    max_code_length: usize,
+    counts: Mutex<HashMap<Box<str>, u64>>,
+}
+
+impl MissingCodeTracker {
+    pub fn new(capacity: usize, max_code_length: usize) -> Self {
+        Self {
+            capacity,
+            max_code_length,
+            counts: Mutex::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    pub fn record(&self, code: &str) {
+        if self.capacity == 0 || code.is_empty() || code.len() > self.max_code_length {
+            return;
+        }
+
+        let mut counts = self.counts.lock().expect("missing code tracker poisoned");
+        if let Some(count) = counts.get_mut(code) {
+            *count += 1;
+            return;
+        }
+
+        if counts.len() < self.capacity {
+            counts.insert(code.into(), 1);
+            return;
+        }
+
+        let (evicted, floor) = counts
+            .iter()
+            .min_by_key(|(_, count)| **count)
+            .map(|(code, count)| (code.clone(), *count))
+            .expect("tracker at capacity is not empty");
+        counts.remove(&evicted);
+        counts.insert(code.into(), floor + 1);
+    }
+
+    /// The `limit` highest-counted codes, ties broken alphabetically.
+    pub fn top(&self, limit: usize) -> Vec<MissingCodeCount> {
+        let counts = self.counts.lock().expect("missing code tracker poisoned");
+        let mut top: Vec<MissingCodeCount> = counts
+            .iter()
+            .map(|(code, count)| MissingCodeCount {
+                code: code.to_string(),
+                count: *count,
+            })
+            .collect();
+        drop(counts);
+
+        top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+        top.truncate(limit);
+        top
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_config_builds_no_stats() {
+        assert!(RedirectStats::from_config(&RedirectStatsConfig::default(), 50).is_none());
+    }
+
+    #[test]
+    fn outcomes_are_counted_separately() {
+        let stats = RedirectStats::new(0, 50);
+        stats.record(RedirectOutcome::Found, "a");
+        stats.record(RedirectOutcome::Found, "a");
+        stats.record(RedirectOutcome::Inactive, "b");
+        stats.record(RedirectOutcome::NotFound, "c");
+
+        let snapshot = stats.snapshot(10);
+        assert_eq!(snapshot.found, 2);
+        assert_eq!(snapshot.inactive, 1);
+        assert_eq!(snapshot.not_found, 1);
+        assert_eq!(snapshot.expired, 0);
+        assert!(snapshot.top_missing.is_empty());
+    }
+
+    #[test]
+    fn only_not_found_codes_are_tracked() {
+        let stats = RedirectStats::new(4, 50);
+        stats.record(RedirectOutcome::Found, "real");
+        stats.record(RedirectOutcome::Inactive, "old");
+        stats.record(RedirectOutcome::NotFound, "typo");
+
+        let top = stats.snapshot(10).top_missing;
+        assert_eq!(
+            top,
+            vec![MissingCodeCount {
+                code: "typo".to_string(),
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn tracker_never_exceeds_capacity() {
+        let tracker = MissingCodeTracker::new(3, 50);
+        for i in 0..100 {
+            tracker.record(&format!("probe-{i}"));
+        }
+        assert_eq!(tracker.top(usize::MAX).len(), 3);
+    }
+
+    #[test]
+    fn frequent_codes_survive_one_off_probes() {
+        let tracker = MissingCodeTracker::new(3, 50);
+        for _ in 0..50 {
+            tracker.record("campain");
+        }
+        for _ in 0..40 {
+            tracker.record("promo-2O24");
+        }
+        for i in 0..30 {
+            tracker.record(&format!("scan-{i}"));
+        }
+
+        let top = tracker.top(2);
+        assert_eq!(top[0].code, "campain");
+        assert_eq!(top[0].count, 50);
+        assert_eq!(top[1].code, "promo-2O24");
+    }
+
+    #[test]
+    fn overlong_codes_are_not_tracked() {
+        let tracker = MissingCodeTracker::new(3, 8);
+        tracker.record("wp-admin/setup-config.php");
+        tracker.record("");
+        assert!(tracker.top(10).is_empty());
+    }
+
+    #[test]
+    fn capacity_is_bounded() {
+        let stats = RedirectStats::new(usize::MAX, 50);
+        assert_eq!(
+            stats.snapshot(0).top_missing_capacity,
+            MAX_TOP_MISSING_CAPACITY
+        );
+    }
+}
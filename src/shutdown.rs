@@ -0,0 +1,60 @@
+//! Graceful shutdown shared by the API and redirect servers.
+//!
+//! One [`ShutdownTrigger`] fans out to any number of [`ShutdownSignal`]s, so
+//! both listeners stop accepting connections on the same SIGTERM and each
+//! lets its in-flight requests finish before [`serve`] returns. Buffered
+//! clicks and analytics are flushed only once both servers have drained.
+
+use axum::Router;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+
+/// Fires the shutdown observed by every [`ShutdownSignal`] of its channel.
+#[derive(Debug)]
+pub struct ShutdownTrigger {
+    sender: watch::Sender<bool>,
+}
+
+/// Resolves once the paired [`ShutdownTrigger`] fires or is dropped.
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+/// Create a trigger and a signal that can be cloned for each server.
+pub fn channel() -> (ShutdownTrigger, ShutdownSignal) {
+    let (sender, receiver) = watch::channel(false);
+    (ShutdownTrigger { sender }, ShutdownSignal { receiver })
+}
+
+impl ShutdownTrigger {
+    /// Ask every server holding a signal to stop accepting connections.
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+}
+
+impl ShutdownSignal {
+    /// Wait for shutdown. A dropped trigger counts as shutdown so a server
+    /// never outlives the task that owns its trigger.
+    pub async fn wait(mut self) {
+        let _ = self.receiver.wait_for(|requested| *requested).await;
+    }
+}
+
+/// Serve `router` on `listener` until `shutdown` fires, then stop accepting
+/// connections and return once in-flight requests have completed. Peer
+/// addresses are attached for handlers that rate-limit or log per client.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    shutdown: ShutdownSignal,
+) -> std::io::Result<()> {
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown.wait())
+    .await
+}
@@ -0,0 +1,328 @@
+//! The click counter actor behind `CachedStorage::buffer_click_owned`.
+//!
+//! Clicks pass through three layers: a buffer only the actor touches, a
+//! shared `DashMap` that lookups add to the stored counts, and the database,
+//! which the slow flush writes to in the background.
+
+use crate::alerts::{AlertCondition, OperatorAlerts};
+use crate::config::FlushConfig;
+use crate::flush::{FlushBackoff, FlushCoalescer, FlushReport, FlushTicker};
+use crate::storage::{ClickIncrement, OwnedClickError, Storage};
+use anyhow::Result;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, mpsc::error::TrySendError, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Message types for the ClickCounterActor
+pub(super) enum ActorMessage {
+    /// Increment a short code's click count by the given amount
+    BatchIncrement(String, u64),
+    /// Persist every buffered click, then acknowledge on the sender
+    Flush(oneshot::Sender<()>),
+    /// Drop the clicks buffered for these codes, then acknowledge
+    Discard(Vec<String>, oneshot::Sender<()>),
+    /// Shutdown signal - flush all data
+    Shutdown,
+}
+
+/// Clicks buffered for one short code, and when the oldest of them arrived.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PendingClicks {
+    pub(super) count: u64,
+    since: Instant,
+}
+
+impl PendingClicks {
+    fn new(count: u64, since: Instant) -> Self {
+        Self { count, since }
+    }
+
+    /// Fold `other` into this bucket, keeping the older arrival time. A
+    /// bucket a flush has zeroed takes the arrival time of `other`.
+    fn merge(&mut self, other: PendingClicks) {
+        if self.count == 0 || other.since < self.since {
+            self.since = other.since;
+        }
+        self.count += other.count;
+    }
+}
+
+fn merge_pending(
+    read_view: &DashMap<String, PendingClicks>,
+    short_code: String,
+    pending: PendingClicks,
+) {
+    read_view
+        .entry(short_code)
+        .and_modify(|current| current.merge(pending))
+        .or_insert(pending);
+}
+
+pub(super) fn enqueue_click_increment(
+    actor_tx: &mpsc::Sender<ActorMessage>,
+    read_view: &DashMap<String, PendingClicks>,
+    short_code: String,
+    amount: u64,
+) -> Result<(), OwnedClickError> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    match actor_tx.try_send(ActorMessage::BatchIncrement(short_code, amount)) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(message)) => {
+            let ActorMessage::BatchIncrement(short_code, amount) = message else {
+                unreachable!("only batch increments are sent by this function")
+            };
+            merge_pending(
+                read_view,
+                short_code,
+                PendingClicks::new(amount, Instant::now()),
+            );
+            Ok(())
+        }
+        Err(TrySendError::Closed(message)) => {
+            let ActorMessage::BatchIncrement(short_code, _) = message else {
+                unreachable!("only batch increments are sent by this function")
+            };
+            Err(OwnedClickError::new(
+                short_code,
+                anyhow::anyhow!("click counter actor channel closed"),
+            ))
+        }
+    }
+}
+
+/// Actor that manages click counting with a lock-free buffer
+pub(super) struct ClickCounterActor {
+    /// Channel receiver for incoming click events
+    pub(super) receiver: mpsc::Receiver<ActorMessage>,
+    /// Lock-free HashMap buffer (Layer 1) - single-threaded access only
+    pub(super) buffer: HashMap<String, PendingClicks>,
+    /// Shared DashMap for concurrent reads (Layer 2)
+    pub(super) read_view: Arc<DashMap<String, PendingClicks>>,
+    /// Underlying storage for persistence (Layer 3)
+    pub(super) storage: Arc<dyn Storage>,
+    /// Fast flush interval (Layer 1 → Layer 2)
+    pub(super) fast_flush_interval: Duration,
+    /// Slow flush interval (Layer 2 → Layer 3)
+    pub(super) slow_flush_interval: Duration,
+    /// Jitter and coalescing applied to the slow flush
+    pub(super) flush_config: FlushConfig,
+    /// Where the slow flush reports queue depth and failed flushes
+    pub(super) alerts: Option<Arc<OperatorAlerts>>,
+    /// Consecutive click batches that failed to persist
+    pub(super) failure_streak: Arc<AtomicU32>,
+}
+
+impl ClickCounterActor {
+    pub(super) async fn run(mut self) {
+        let mut fast_flush_ticker = time::interval(self.fast_flush_interval);
+        let mut slow_flush_ticker =
+            FlushTicker::new(self.slow_flush_interval, self.flush_config.jitter_percent);
+        let mut coalescer = FlushCoalescer::new(&self.flush_config);
+        let mut backoff = FlushBackoff::new();
+        let mut flush_tasks = Vec::new();
+
+        // Skip the first tick which fires immediately
+        fast_flush_ticker.tick().await;
+
+        loop {
+            tokio::select! {
+                // Handle incoming click events
+                Some(msg) = self.receiver.recv() => {
+                    match msg {
+                        ActorMessage::BatchIncrement(short_code, count) => {
+                            // Fast local increment in Layer 1 (no locks!). Only
+                            // a new bucket reads the clock.
+                            self.buffer
+                                .entry(short_code)
+                                .and_modify(|pending| pending.count += count)
+                                .or_insert_with(|| PendingClicks::new(count, Instant::now()));
+                        }
+                        ActorMessage::Flush(done) => {
+                            self.flush_everything(&mut flush_tasks).await;
+                            // The requester may have stopped waiting; nothing to report.
+                            let _ = done.send(());
+                        }
+                        ActorMessage::Discard(short_codes, done) => {
+                            for short_code in &short_codes {
+                                self.buffer.remove(short_code);
+                                self.read_view.remove(short_code);
+                            }
+                            let _ = done.send(());
+                        }
+                        ActorMessage::Shutdown => {
+                            tracing::info!("Actor received shutdown signal, flushing all data...");
+                            self.flush_everything(&mut flush_tasks).await;
+                            tracing::info!("All data flushed successfully on shutdown");
+                            break;
+                        }
+                    }
+                }
+                // Fast flush: Layer 1 → Layer 2 (100ms default)
+                _ = fast_flush_ticker.tick() => {
+                    self.flush_buffer_to_read_view();
+                }
+                // Slow flush: Layer 2 → Layer 3 (5s default, jittered)
+                _ = slow_flush_ticker.tick() => {
+                    // Spawns background task, doesn't block the actor. While
+                    // batches keep failing, wait longer between attempts.
+                    // While the previous batch is still being written, clicks
+                    // keep accumulating for the next one: a hot code is never
+                    // updated by two flushes at once.
+                    reap_finished_flush_tasks(&mut flush_tasks).await;
+                    let failure_streak = self.failure_streak.load(Ordering::Relaxed);
+                    if flush_tasks.is_empty()
+                        && backoff.should_attempt(failure_streak)
+                        && coalescer.should_flush(self.read_view.len())
+                    {
+                        if let Some(handle) = self.flush_read_view_to_storage() {
+                            flush_tasks.push(handle);
+                        }
+                    }
+                    if let Some(alerts) = &self.alerts {
+                        alerts.check_queue_depth(
+                            AlertCondition::ClickQueueDepth,
+                            self.receiver.len(),
+                            self.receiver.max_capacity(),
+                        );
+                        alerts.check_flush_failures(
+                            AlertCondition::ClickFlushFailures,
+                            self.failure_streak.load(Ordering::Relaxed),
+                        );
+                    }
+                }
+                // Channel closed without shutdown message
+                else => {
+                    tracing::warn!("Actor channel closed unexpectedly, flushing data...");
+                    self.flush_everything(&mut flush_tasks).await;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Flush every layer to storage and wait for it. Writes already in
+    /// flight finish first, so clicks they fail to persist are requeued and
+    /// go out with the final batch.
+    async fn flush_everything(&mut self, flush_tasks: &mut Vec<JoinHandle<()>>) {
+        finish_flush_tasks(flush_tasks).await;
+        // Flush Layer 1 → Layer 2
+        self.flush_buffer_to_read_view();
+        // Flush Layer 2 → Layer 3 and await the write.
+        if let Some(handle) = self.flush_read_view_to_storage() {
+            flush_tasks.push(handle);
+        }
+        finish_flush_tasks(flush_tasks).await;
+    }
+
+    /// Flush Layer 1 (buffer) → Layer 2 (read_view DashMap)
+    /// This is fast and non-blocking
+    fn flush_buffer_to_read_view(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        for (short_code, pending) in self.buffer.drain() {
+            merge_pending(&self.read_view, short_code, pending);
+        }
+    }
+
+    /// Flush Layer 2 (read_view) → Layer 3 (database)
+    /// This can be slow but doesn't block Layer 1 ingestion
+    /// Returns a JoinHandle to the background flush task
+    fn flush_read_view_to_storage(&self) -> Option<tokio::task::JoinHandle<()>> {
+        // Atomically collect and zero out counts from DashMap
+        // This is fast and happens synchronously to maintain data consistency
+        let started = Instant::now();
+        let mut oldest = started;
+        let pending_updates: Vec<ClickIncrement> = self
+            .read_view
+            .iter_mut()
+            .filter_map(|mut entry| {
+                let PendingClicks { count, since } = *entry.value();
+                if count == 0 {
+                    return None;
+                }
+                oldest = oldest.min(since);
+                // Atomically zero the entry - any new increments will be added to 0
+                entry.value_mut().count = 0;
+                Some(ClickIncrement::new(
+                    entry.key().clone(),
+                    NonZeroU64::new(count).expect("zero counts were filtered"),
+                ))
+            })
+            .collect();
+
+        // Remove zero entries (fast operation)
+        self.read_view.retain(|_, pending| pending.count > 0);
+
+        // Skip spawning if there's nothing to flush
+        if pending_updates.is_empty() {
+            return None;
+        }
+
+        // Spawn the slow database writes in a separate task
+        // This doesn't block the actor from processing new clicks
+        // Return the JoinHandle so callers can optionally wait for completion
+        let storage = Arc::clone(&self.storage);
+        let read_view = Arc::clone(&self.read_view);
+        let failure_streak = Arc::clone(&self.failure_streak);
+        let mut report = FlushReport {
+            stage: "clicks",
+            entries: pending_updates.len(),
+            bytes: pending_updates
+                .iter()
+                .map(|increment| increment.short_code().len() + std::mem::size_of::<u64>())
+                .sum(),
+            duration: Duration::ZERO,
+            lag: started - oldest,
+            succeeded: true,
+        };
+        Some(tokio::spawn(async move {
+            let result = storage.increment_clicks_batch(&pending_updates).await;
+            report.duration = started.elapsed();
+            report.succeeded = result.is_ok();
+            report.log();
+            if let Err(error) = result {
+                tracing::error!(%error, "failed to persist click batch; requeueing it");
+                failure_streak.fetch_add(1, Ordering::Relaxed);
+                // Requeued clicks keep the batch's age, so lag keeps growing.
+                for increment in pending_updates {
+                    let (short_code, amount) = increment.into_parts();
+                    merge_pending(
+                        &read_view,
+                        short_code,
+                        PendingClicks::new(amount.get(), oldest),
+                    );
+                }
+            } else {
+                failure_streak.store(0, Ordering::Relaxed);
+            }
+        }))
+    }
+}
+
+async fn reap_finished_flush_tasks(flush_tasks: &mut Vec<JoinHandle<()>>) {
+    while let Some(index) = flush_tasks.iter().position(JoinHandle::is_finished) {
+        let handle = flush_tasks.swap_remove(index);
+        if let Err(error) = handle.await {
+            tracing::error!(%error, "background click flush task panicked");
+        }
+    }
+}
+
+async fn finish_flush_tasks(flush_tasks: &mut Vec<JoinHandle<()>>) {
+    for handle in flush_tasks.drain(..) {
+        if let Err(error) = handle.await {
+            tracing::error!(%error, "background click flush task panicked during shutdown");
+        }
+    }
+}
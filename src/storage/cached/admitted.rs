@@ -0,0 +1,71 @@
+//! Click counts for cached links with a `max_clicks`, taken as redirects are
+//! let through so concurrent ones cannot overshoot the limit.
+
+use super::entry::{CachedUrl, RedirectTarget};
+use super::CachedStorage;
+
+/// Approximate bookkeeping a `DashMap` keeps for each entry, in bytes.
+const ADMITTED_OVERHEAD_BYTES: usize = 32;
+
+/// Approximate memory of the click count a read cache entry may hold in
+/// `admitted`: one for every link with a `max_clicks`.
+pub(super) fn admitted_weight(cached: Option<&CachedUrl>) -> u32 {
+    let bytes = match cached {
+        Some(cached) if cached.url.options.max_clicks.is_some() => {
+            ADMITTED_OVERHEAD_BYTES
+                + std::mem::size_of::<(String, i64)>()
+                + cached.url.short_code.len()
+        }
+        _ => 0,
+    };
+    u32::try_from(bytes).unwrap_or(u32::MAX)
+}
+
+impl CachedStorage {
+    /// Take one of `target`'s remaining clicks, returning false when its
+    /// `max_clicks` are used up. Links without a limit always get one.
+    ///
+    /// The count starts at the stored clicks plus those buffered, is raised
+    /// to the stored clicks whenever the database is ahead (other instances
+    /// redirecting the same link), and counts each click as it is let
+    /// through, so concurrent redirects cannot overshoot the limit while
+    /// their increments wait for a flush. It is kept only while the link is
+    /// in the read cache; a link served from elsewhere, such as the stale
+    /// snapshot, is judged by its stored and buffered clicks alone.
+    pub fn admit_click(&self, target: &RedirectTarget) -> bool {
+        let cached = &target.cached;
+        let url = &cached.url;
+        let Some(max_clicks) = url.options.max_clicks else {
+            return true;
+        };
+        let counted = url
+            .clicks
+            .saturating_add(self.get_buffered_clicks(&url.short_code) as i64);
+        let looked_up = cached.alias_used.as_deref().unwrap_or(&url.short_code);
+        if !self.read_cache.contains_key(looked_up) {
+            return counted.max(url.clicks) < max_clicks;
+        }
+        let mut admitted = self
+            .admitted
+            .entry(url.short_code.clone())
+            .or_insert(counted);
+        *admitted = (*admitted).max(url.clicks);
+        if *admitted >= max_clicks {
+            return false;
+        }
+        *admitted += 1;
+        true
+    }
+
+    /// Whether `target` has used up its `max_clicks`, without taking one.
+    pub fn clicks_exhausted(&self, target: &RedirectTarget) -> bool {
+        let url = &target.cached.url;
+        let admitted = self
+            .admitted
+            .get(&url.short_code)
+            .map_or(url.clicks, |n| *n);
+        url.options
+            .max_clicks
+            .is_some_and(|max_clicks| admitted.max(url.clicks) >= max_clicks)
+    }
+}
@@ -0,0 +1,177 @@
+//! What the read cache holds for a link, and the redirect view handed out
+//! from it.
+
+use crate::destination::{location_header, requires_interstitial};
+use crate::models::ShortenedUrl;
+use crate::storage::{LookupMetadata, Storage};
+use anyhow::Result;
+use axum::http::HeaderValue;
+use std::sync::Arc;
+
+/// A lookup error shared by every caller coalesced onto the same cache load.
+///
+/// Keeps the original error reachable through `source()` so callers can still
+/// recognize causes such as pool timeouts.
+#[derive(Debug)]
+pub(super) struct SharedLookupError(pub(super) Arc<anyhow::Error>);
+
+impl std::fmt::Display for SharedLookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for SharedLookupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        let inner: &(dyn std::error::Error + Send + Sync + 'static) = (*self.0).as_ref();
+        Some(inner)
+    }
+}
+
+pub(super) struct CachedUrl {
+    pub(super) url: Arc<ShortenedUrl>,
+    pub(super) location: Option<HeaderValue>,
+    pub(super) interstitial: bool,
+    /// The link is flagged for the countdown page
+    pub(super) countdown: bool,
+    pub(super) analytics_code: Arc<str>,
+    /// The alias this entry was looked up by, when `url` is its canonical link
+    pub(super) alias_used: Option<Arc<str>>,
+}
+
+impl CachedUrl {
+    /// Approximate memory held by the entry: its structs plus the strings
+    /// the link and the prepared redirect keep.
+    pub(super) fn size_in_bytes(&self) -> usize {
+        let url = &self.url;
+        let optional = |value: &Option<String>| value.as_ref().map_or(0, String::len);
+        std::mem::size_of::<Self>()
+            + std::mem::size_of::<ShortenedUrl>()
+            + url.short_code.len()
+            + url.original_url.len()
+            + optional(&url.created_by)
+            + optional(&url.created_by_auth_method)
+            + optional(&url.alias_of)
+            + optional(&url.title)
+            + self.location.as_ref().map_or(0, HeaderValue::len)
+            + self.analytics_code.len()
+            + self.alias_used.as_ref().map_or(0, |alias| alias.len())
+    }
+
+    pub(super) fn new(url: Arc<ShortenedUrl>) -> Arc<Self> {
+        Self::build(url, None)
+    }
+
+    /// Entry for `alias_code`, which redirects to its canonical link `url`.
+    fn via_alias(url: Arc<ShortenedUrl>, alias_code: &str) -> Arc<Self> {
+        Self::build(url, Some(Arc::from(alias_code)))
+    }
+
+    fn build(url: Arc<ShortenedUrl>, alias_used: Option<Arc<str>>) -> Arc<Self> {
+        Arc::new(Self {
+            location: location_header(&url.original_url),
+            interstitial: requires_interstitial(&url.original_url),
+            countdown: url.options.shows_interstitial(),
+            analytics_code: Arc::from(url.short_code.as_str()),
+            alias_used,
+            url,
+        })
+    }
+}
+
+/// Load `short_code` for the cache. An alias is resolved to its canonical
+/// link here, at fill time, so a redirect through an alias is still one cache
+/// lookup and its clicks and analytics go to the canonical code. A removed
+/// (deactivated) alias is cached as itself and answers as an inactive link.
+pub(super) async fn load_cached(
+    inner: &dyn Storage,
+    short_code: &str,
+) -> Result<Option<Arc<CachedUrl>>> {
+    let Some(url) = inner.get(short_code).await? else {
+        return Ok(None);
+    };
+    match &url.alias_of {
+        Some(canonical) if url.is_active => Ok(inner
+            .get(canonical)
+            .await?
+            .map(|canonical| CachedUrl::via_alias(canonical, short_code))),
+        _ => Ok(Some(CachedUrl::new(url))),
+    }
+}
+
+/// An immutable redirect projection retained directly from the cache.
+///
+/// Holding the cache entry avoids separately cloning its URL model and
+/// analytics-code allocations for every successful redirect. The response still
+/// receives an owned header value because Axum must own response headers.
+pub struct RedirectTarget {
+    pub(super) cached: Arc<CachedUrl>,
+    expired: bool,
+}
+
+impl RedirectTarget {
+    /// The target for `cached`, looked up at `now` in milliseconds since the
+    /// Unix epoch. Expiry is judged on every lookup, so a cached entry stops
+    /// redirecting the moment its link expires.
+    pub(super) fn new(cached: Arc<CachedUrl>, now: i64) -> Self {
+        let expired = cached.url.is_expired(now);
+        Self { cached, expired }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.cached.url.is_active
+    }
+
+    /// Whether the link redirected to had expired when it was looked up.
+    pub fn is_expired(&self) -> bool {
+        self.expired
+    }
+
+    /// Whether the code is reserved and has no destination to redirect to.
+    pub fn is_reserved(&self) -> bool {
+        self.cached.url.is_reserved()
+    }
+
+    pub fn short_code(&self) -> &str {
+        &self.cached.url.short_code
+    }
+
+    pub fn original_url(&self) -> &str {
+        &self.cached.url.original_url
+    }
+
+    /// Creation time of the link redirected to, in milliseconds since the epoch
+    pub fn created_at(&self) -> i64 {
+        self.cached.url.created_at
+    }
+
+    pub fn location(&self) -> Option<HeaderValue> {
+        self.cached.location.clone()
+    }
+
+    /// Whether the destination is served through an interstitial page
+    /// instead of a `Location` redirect (non-web schemes such as `mailto:`).
+    pub fn requires_interstitial(&self) -> bool {
+        self.cached.interstitial
+    }
+
+    /// Whether the link is flagged to show the countdown page before its
+    /// destination (the `interstitial` option).
+    pub fn flagged_for_countdown(&self) -> bool {
+        self.cached.countdown
+    }
+
+    pub fn analytics_code(&self) -> Arc<str> {
+        Arc::clone(&self.cached.analytics_code)
+    }
+
+    /// The alias the redirect came through, if it was not the short code itself
+    pub fn alias_used(&self) -> Option<Arc<str>> {
+        self.cached.alias_used.clone()
+    }
+}
+
+pub struct RedirectLookup {
+    pub target: Option<RedirectTarget>,
+    pub metadata: LookupMetadata,
+}
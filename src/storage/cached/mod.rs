@@ -0,0 +1,489 @@
+//! Read caching and click buffering in front of a [`Storage`] backend.
+//!
+//! [`CachedStorage`] answers link lookups from a moka cache sized by a
+//! [`CachePolicy`] and counts clicks through an actor that batches them into
+//! the database. The cache's sizing and eviction live in `policy`, its
+//! counters in `stats`, and the click counts kept for links with a
+//! `max_clicks` in `admitted`.
+
+mod actor;
+mod admitted;
+mod entry;
+mod policy;
+mod stats;
+mod storage;
+#[cfg(all(test, feature = "sqlite"))]
+mod tests;
+
+pub use entry::{RedirectLookup, RedirectTarget};
+pub use policy::CachePolicy;
+pub use stats::{CacheStats, LookupStats, StaleStats};
+
+use actor::{enqueue_click_increment, ActorMessage, ClickCounterActor, PendingClicks};
+use entry::{load_cached, CachedUrl, SharedLookupError};
+use policy::{NegativeCache, StaleSnapshot};
+use stats::LookupCounters;
+
+use crate::alerts::OperatorAlerts;
+use crate::clock::{system_clock, Clock};
+use crate::config::FlushConfig;
+use crate::models::ShortenedUrl;
+use crate::storage::{LookupMetadata, OwnedClickError, Storage};
+use anyhow::Result;
+use dashmap::DashMap;
+use moka::future::Cache;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Cached storage wrapper that implements read caching and write buffering
+pub struct CachedStorage {
+    /// Underlying storage implementation
+    inner: Arc<dyn Storage>,
+    /// Read cache for URL lookups (Moka cache)
+    read_cache: Cache<String, Option<Arc<CachedUrl>>>,
+    /// Where lookups of codes that do not exist are remembered
+    negative_cache: NegativeCache,
+    /// Recently loaded links to redirect from while the database is down
+    stale: Option<StaleSnapshot>,
+    policy: CachePolicy,
+    lookups: LookupCounters,
+    /// Shared read view for real-time click statistics (Layer 2)
+    read_view: Arc<DashMap<String, PendingClicks>>,
+    /// Actor message sender
+    actor_tx: mpsc::Sender<ActorMessage>,
+    /// Long-lived actor task, joined during graceful shutdown.
+    actor_handle: Mutex<Option<JoinHandle<()>>>,
+    /// Decides whether a cached link has expired when it is looked up
+    clock: Arc<dyn Clock>,
+    /// Clicks let through so far for cached links with a `max_clicks`, by
+    /// canonical code. Unlike the stored count it includes clicks still on
+    /// their way to the buffer. A count lives only as long as the read cache
+    /// entry it was taken through, which is weighed to include it.
+    admitted: Arc<DashMap<String, i64>>,
+}
+
+impl CachedStorage {
+    pub fn new(
+        inner: Arc<dyn Storage>,
+        max_cache_entries: u64,
+        flush_interval_secs: u64,
+        actor_buffer_size: usize,
+        actor_flush_interval_ms: u64,
+    ) -> Self {
+        Self::new_with_flush_config(
+            inner,
+            max_cache_entries,
+            flush_interval_secs,
+            actor_buffer_size,
+            actor_flush_interval_ms,
+            FlushConfig::default(),
+        )
+    }
+
+    /// Create a cached storage whose database flushes follow `flush_config`.
+    pub fn new_with_flush_config(
+        inner: Arc<dyn Storage>,
+        max_cache_entries: u64,
+        flush_interval_secs: u64,
+        actor_buffer_size: usize,
+        actor_flush_interval_ms: u64,
+        flush_config: FlushConfig,
+    ) -> Self {
+        Self::new_with_cache_policy(
+            inner,
+            CachePolicy::with_max_entries(max_cache_entries),
+            flush_interval_secs,
+            actor_buffer_size,
+            actor_flush_interval_ms,
+            flush_config,
+        )
+    }
+
+    /// Create a cached storage whose read cache is sized by `policy`.
+    pub fn new_with_cache_policy(
+        inner: Arc<dyn Storage>,
+        policy: CachePolicy,
+        flush_interval_secs: u64,
+        actor_buffer_size: usize,
+        actor_flush_interval_ms: u64,
+        flush_config: FlushConfig,
+    ) -> Self {
+        Self::new_with_alerts(
+            inner,
+            policy,
+            flush_interval_secs,
+            actor_buffer_size,
+            actor_flush_interval_ms,
+            flush_config,
+            None,
+        )
+    }
+
+    /// Create a cached storage whose click flushes report to `alerts`.
+    pub fn new_with_alerts(
+        inner: Arc<dyn Storage>,
+        policy: CachePolicy,
+        flush_interval_secs: u64,
+        actor_buffer_size: usize,
+        actor_flush_interval_ms: u64,
+        flush_config: FlushConfig,
+        alerts: Option<Arc<OperatorAlerts>>,
+    ) -> Self {
+        let admitted = Arc::new(DashMap::new());
+        let read_cache = policy.build_read_cache(Arc::clone(&admitted));
+        let negative_cache = match policy.negative_max_entries {
+            None => NegativeCache::Shared,
+            Some(0) => NegativeCache::Disabled,
+            Some(max_entries) => NegativeCache::Separate(policy.build(max_entries)),
+        };
+        let stale = policy
+            .stale_max_age
+            .map(|max_age| StaleSnapshot::new(&policy, max_age));
+        let read_view = Arc::new(DashMap::new());
+
+        // Create actor channel with large buffer to prevent message loss
+        let (actor_tx, actor_rx) = mpsc::channel(actor_buffer_size);
+
+        // Spawn the click counter actor
+        let actor = ClickCounterActor {
+            receiver: actor_rx,
+            buffer: HashMap::new(),
+            read_view: Arc::clone(&read_view),
+            storage: Arc::clone(&inner),
+            fast_flush_interval: Duration::from_millis(actor_flush_interval_ms),
+            slow_flush_interval: Duration::from_secs(flush_interval_secs),
+            flush_config,
+            alerts,
+            failure_streak: Arc::new(AtomicU32::new(0)),
+        };
+
+        let actor_handle = tokio::spawn(async move {
+            actor.run().await;
+        });
+
+        Self {
+            inner,
+            read_cache,
+            negative_cache,
+            stale,
+            policy,
+            lookups: LookupCounters::default(),
+            read_view,
+            actor_tx,
+            actor_handle: Mutex::new(Some(actor_handle)),
+            clock: system_clock(),
+            admitted,
+        }
+    }
+
+    /// Judge link expiry by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Flush buffered clicks and wait for the long-lived actor to stop.
+    pub async fn shutdown(&self) {
+        let actor_handle = self
+            .actor_handle
+            .lock()
+            .expect("click actor handle mutex poisoned")
+            .take();
+        let Some(actor_handle) = actor_handle else {
+            return;
+        };
+
+        if let Err(error) = self.actor_tx.send(ActorMessage::Shutdown).await {
+            tracing::warn!(%error, "click counter actor stopped before shutdown signal");
+        }
+        if let Err(error) = actor_handle.await {
+            tracing::error!(%error, "click counter actor panicked during shutdown");
+        }
+    }
+
+    /// Persist every click accepted so far and wait for the database writes.
+    ///
+    /// Clicks enqueued before this call are included; clicks from concurrent
+    /// callers may land in this flush or the next one. Batches that fail to
+    /// persist are requeued for the next flush rather than reported here.
+    pub async fn flush(&self) -> Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.actor_tx
+            .send(ActorMessage::Flush(done_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("click counter actor channel closed"))?;
+        done_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("click counter actor stopped before flushing"))
+    }
+
+    /// Drop the clicks buffered for `short_codes` without writing them.
+    async fn discard_clicks(&self, short_codes: Vec<String>) -> Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.actor_tx
+            .send(ActorMessage::Discard(short_codes, done_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("click counter actor channel closed"))?;
+        done_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("click counter actor stopped before discarding"))
+    }
+
+    /// Enqueue a click without awaiting channel capacity.
+    ///
+    /// The bounded actor queue is the uncontended fast path. Saturated queues
+    /// merge counts into the actor's existing shared flush layer, preserving
+    /// clicks without creating a task per redirect or growing an unbounded queue.
+    pub fn buffer_click_owned(
+        &self,
+        short_code: String,
+        amount: u64,
+    ) -> Result<(), OwnedClickError> {
+        enqueue_click_increment(&self.actor_tx, &self.read_view, short_code, amount)
+    }
+
+    async fn get_cached(&self, short_code: &str) -> Result<Option<Arc<CachedUrl>>> {
+        if let NegativeCache::Separate(negative) = &self.negative_cache {
+            if negative.contains_key(short_code) {
+                return Ok(None);
+            }
+        }
+
+        let cached = match self.read_cache.get(short_code).await {
+            Some(cached) => cached,
+            None => self.load_coalesced(short_code).await?,
+        };
+
+        // The coalesced load always lands in the read cache; move an absence
+        // out of it unless missing codes share that cache.
+        if cached.is_none() && !matches!(self.negative_cache, NegativeCache::Shared) {
+            self.cache_missing(short_code).await;
+        }
+        Ok(cached)
+    }
+
+    /// Load a missed code into the read cache. Moka runs one load per code
+    /// at a time; misses that arrive while it is in flight wait for it and
+    /// share its result, including its failure. A load that outlives the
+    /// lookup timeout fails, so one stuck query cannot hold every waiter.
+    async fn load_coalesced(&self, short_code: &str) -> Result<Option<Arc<CachedUrl>>> {
+        let loaded = AtomicBool::new(false);
+        let inner = Arc::clone(&self.inner);
+        let stale = self.stale.as_ref();
+        let lookups = &self.lookups;
+        let timeout = self.policy.lookup_timeout;
+        let loaded_here = &loaded;
+        let cached = self
+            .read_cache
+            .try_get_with_by_ref(short_code, async move {
+                loaded_here.store(true, Ordering::Relaxed);
+                lookups.loads.fetch_add(1, Ordering::Relaxed);
+                let load = load_cached(inner.as_ref(), short_code);
+                let cached = match timeout {
+                    Some(timeout) => time::timeout(timeout, load).await.map_err(|_| {
+                        lookups.timed_out.fetch_add(1, Ordering::Relaxed);
+                        anyhow::anyhow!(
+                            "lookup of {short_code} timed out after {} ms",
+                            timeout.as_millis()
+                        )
+                    })??,
+                    None => load.await?,
+                };
+                if let (Some(stale), Some(cached)) = (stale, &cached) {
+                    stale
+                        .entries
+                        .insert(short_code.to_string(), Arc::clone(cached))
+                        .await;
+                }
+                Ok(cached)
+            })
+            .await
+            .map_err(|error| anyhow::Error::new(SharedLookupError(error)));
+        if !loaded.load(Ordering::Relaxed) {
+            self.lookups.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+        cached
+    }
+
+    /// Cached outcome of a previous lookup, if any: `Some(None)` is a code
+    /// known not to exist.
+    async fn cache_lookup(&self, short_code: &str) -> Option<Option<Arc<CachedUrl>>> {
+        if let Some(cached) = self.read_cache.get(short_code).await {
+            return Some(cached);
+        }
+        match &self.negative_cache {
+            NegativeCache::Separate(negative) if negative.contains_key(short_code) => Some(None),
+            _ => None,
+        }
+    }
+
+    /// Remember a link that exists, replacing any cached absence.
+    async fn cache_found(&self, short_code: &str, cached: Arc<CachedUrl>) {
+        if let NegativeCache::Separate(negative) = &self.negative_cache {
+            negative.invalidate(short_code).await;
+        }
+        if let Some(stale) = &self.stale {
+            stale
+                .entries
+                .insert(short_code.to_string(), Arc::clone(&cached))
+                .await;
+        }
+        self.read_cache
+            .insert(short_code.to_string(), Some(cached))
+            .await;
+    }
+
+    /// Remember that a code does not exist, as far as the negative cache allows.
+    async fn cache_missing(&self, short_code: &str) {
+        match &self.negative_cache {
+            NegativeCache::Shared => {
+                self.read_cache.insert(short_code.to_string(), None).await;
+            }
+            NegativeCache::Separate(negative) => {
+                self.read_cache.invalidate(short_code).await;
+                negative.insert(short_code.to_string(), ()).await;
+            }
+            NegativeCache::Disabled => {
+                self.read_cache.invalidate(short_code).await;
+            }
+        }
+    }
+
+    /// Drop every cached lookup outcome.
+    async fn invalidate_all_cached(&self) {
+        self.read_cache.invalidate_all();
+        self.read_cache.run_pending_tasks().await;
+        if let NegativeCache::Separate(negative) = &self.negative_cache {
+            negative.invalidate_all();
+            negative.run_pending_tasks().await;
+        }
+        if let Some(stale) = &self.stale {
+            stale.entries.invalidate_all();
+            stale.entries.run_pending_tasks().await;
+        }
+    }
+
+    /// Look up a redirect, falling back to the stale snapshot when the
+    /// database cannot be reached. Only redirects degrade this way; API
+    /// lookups report the error.
+    async fn get_redirect_cached(&self, short_code: &str) -> Result<Option<Arc<CachedUrl>>> {
+        let error = match self.get_cached(short_code).await {
+            Ok(cached) => return Ok(cached),
+            Err(error) => error,
+        };
+        let Some(stale) = &self.stale else {
+            return Err(error);
+        };
+        let Some(cached) = stale.entries.get(short_code).await else {
+            return Err(error);
+        };
+        stale.served.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(%error, short_code, "lookup failed; serving the stale redirect");
+        Ok(Some(cached))
+    }
+
+    pub async fn get_redirect(&self, short_code: &str) -> Result<Option<RedirectTarget>> {
+        let now = self.clock.now_epoch_ms();
+        Ok(self
+            .get_redirect_cached(short_code)
+            .await?
+            .map(|cached| RedirectTarget::new(cached, now)))
+    }
+
+    pub async fn get_redirect_with_metadata(&self, short_code: &str) -> Result<RedirectLookup> {
+        let now = self.clock.now_epoch_ms();
+        let cache_start = Instant::now();
+        if let Some(cached) = self.cache_lookup(short_code).await {
+            return Ok(RedirectLookup {
+                target: cached.map(|cached| RedirectTarget::new(cached, now)),
+                metadata: LookupMetadata {
+                    cache_hit: true,
+                    cache_duration: Some(cache_start.elapsed()),
+                    db_duration: None,
+                },
+            });
+        }
+        let cache_duration = cache_start.elapsed();
+        let db_start = Instant::now();
+        let target = self
+            .get_redirect_cached(short_code)
+            .await?
+            .map(|cached| RedirectTarget::new(cached, now));
+
+        Ok(RedirectLookup {
+            target,
+            metadata: LookupMetadata {
+                cache_hit: false,
+                cache_duration: Some(cache_duration),
+                db_duration: Some(db_start.elapsed()),
+            },
+        })
+    }
+
+    /// Get buffered click count for a short code from Layer 2 (read_view)
+    fn get_buffered_clicks(&self, short_code: &str) -> u64 {
+        self.read_view
+            .get(short_code)
+            .map(|entry| entry.value().count)
+            .unwrap_or(0)
+    }
+
+    /// Add buffered clicks to a URL read from the database. Inactive links
+    /// get none: the backends drop increments for them when the buffer flushes.
+    fn add_buffered_clicks(&self, url: &mut Arc<ShortenedUrl>) {
+        if !url.is_active {
+            return;
+        }
+        let buffered = self.get_buffered_clicks(&url.short_code);
+        if buffered > 0 {
+            Arc::make_mut(url).clicks += buffered as i64;
+        }
+    }
+
+    /// Invalidate cache entry for a specific short code
+    async fn invalidate_cache(&self, short_code: &str) {
+        self.read_cache.invalidate(short_code).await;
+        self.admitted.remove(short_code);
+        if let NegativeCache::Separate(negative) = &self.negative_cache {
+            negative.invalidate(short_code).await;
+        }
+        if let Some(stale) = &self.stale {
+            stale.entries.invalidate(short_code).await;
+        }
+    }
+
+    /// Invalidate `short_code` and every alias of it, whose cache entries hold
+    /// a copy of its link. Drops the whole cache if the aliases can't be read.
+    async fn invalidate_with_aliases(&self, short_code: &str) {
+        self.invalidate_cache(short_code).await;
+        match self.inner.get_aliases(&[short_code.to_owned()]).await {
+            Ok(aliases) => {
+                for alias in aliases {
+                    self.invalidate_cache(&alias.short_code).await;
+                }
+            }
+            Err(error) => {
+                tracing::warn!(%error, "failed to load aliases; clearing the lookup cache");
+                self.invalidate_all_cached().await;
+            }
+        }
+    }
+
+    /// The stored row for `short_code` given its cache entry. Entries for
+    /// aliases hold their canonical link, so those are read from `inner`.
+    async fn stored_row(
+        &self,
+        short_code: &str,
+        cached: Option<Arc<CachedUrl>>,
+    ) -> Result<Option<Arc<ShortenedUrl>>> {
+        match cached {
+            Some(cached) if cached.url.short_code != short_code => self.inner.get(short_code).await,
+            cached => Ok(cached.map(|cached| Arc::clone(&cached.url))),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Sizing and eviction of the read cache, the negative cache and the stale
+//! snapshot.
+
+use crate::config::{CacheConfig, CacheEvictionPolicy};
+use dashmap::DashMap;
+use moka::future::{Cache, CacheBuilder};
+use moka::policy::EvictionPolicy;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::admitted::admitted_weight;
+use super::entry::CachedUrl;
+
+/// Sizing and eviction of the read cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Cap for found links, and for missing codes too unless they have their own cap
+    pub max_entries: u64,
+    /// See [`CacheConfig::max_bytes`]; replaces `max_entries` when set
+    pub max_bytes: Option<u64>,
+    /// See [`CacheConfig::negative_max_entries`]
+    pub negative_max_entries: Option<u64>,
+    pub eviction_policy: CacheEvictionPolicy,
+    /// See [`CacheConfig::stale_max_age_secs`]
+    pub stale_max_age: Option<Duration>,
+    /// See [`CacheConfig::entry_ttl_secs`]
+    pub entry_ttl: Option<Duration>,
+    /// See [`CacheConfig::lookup_timeout_ms`]
+    pub lookup_timeout: Option<Duration>,
+}
+
+impl CachePolicy {
+    pub fn from_config(config: &CacheConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            max_bytes: config.max_bytes,
+            negative_max_entries: config.negative_max_entries,
+            eviction_policy: config.eviction_policy,
+            stale_max_age: config
+                .stale_max_age_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            entry_ttl: config
+                .entry_ttl_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            lookup_timeout: (config.lookup_timeout_ms > 0)
+                .then(|| Duration::from_millis(config.lookup_timeout_ms)),
+        }
+    }
+
+    /// A single cache of `max_entries` shared by found and missing codes.
+    pub fn with_max_entries(max_entries: u64) -> Self {
+        Self {
+            max_entries,
+            max_bytes: None,
+            negative_max_entries: None,
+            eviction_policy: CacheEvictionPolicy::default(),
+            stale_max_age: None,
+            entry_ttl: None,
+            lookup_timeout: Some(Duration::from_millis(
+                CacheConfig::default_lookup_timeout_ms(),
+            )),
+        }
+    }
+
+    /// A single cache of about `max_bytes` shared by found and missing codes.
+    pub fn with_max_bytes(max_bytes: u64) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..Self::with_max_entries(CacheConfig::default_max_entries())
+        }
+    }
+
+    pub(super) fn build<V>(&self, max_capacity: u64) -> Cache<String, V>
+    where
+        V: Clone + Send + Sync + 'static,
+    {
+        self.builder().max_capacity(max_capacity).build()
+    }
+
+    /// The read cache, capped by entries or, with `max_bytes`, by the
+    /// approximate size of what its entries hold. Whenever a link's own
+    /// entry leaves, evicted, expired, invalidated or replaced, its click
+    /// count in `admitted` goes with it. Entries for aliases hold the
+    /// canonical link too, but leave its count alone: the canonical entry
+    /// may still be cached and counting.
+    pub(super) fn build_read_cache(
+        &self,
+        admitted: Arc<DashMap<String, i64>>,
+    ) -> Cache<String, Option<Arc<CachedUrl>>> {
+        let builder = self.builder().eviction_listener(
+            move |short_code: Arc<String>, cached: Option<Arc<CachedUrl>>, _| {
+                if let Some(cached) = cached.filter(|cached| *short_code == cached.url.short_code) {
+                    admitted.remove(&cached.url.short_code);
+                }
+            },
+        );
+        let Some(max_bytes) = self.max_bytes else {
+            return builder.max_capacity(self.max_entries).build();
+        };
+        builder
+            .max_capacity(max_bytes)
+            .weigher(|short_code: &String, cached: &Option<Arc<CachedUrl>>| {
+                entry_weight(short_code, cached.as_deref())
+                    .saturating_add(admitted_weight(cached.as_deref()))
+            })
+            .build()
+    }
+
+    /// A builder with the eviction policy and, when set, the entry TTL. An
+    /// expired entry reads as a miss, so the next lookup reloads it.
+    fn builder<V>(&self) -> CacheBuilder<String, V, Cache<String, V>>
+    where
+        V: Clone + Send + Sync + 'static,
+    {
+        let builder = Cache::builder().eviction_policy(self.moka_eviction_policy());
+        match self.entry_ttl {
+            Some(ttl) => builder.time_to_live(ttl),
+            None => builder,
+        }
+    }
+
+    fn moka_eviction_policy(&self) -> EvictionPolicy {
+        match self.eviction_policy {
+            CacheEvictionPolicy::TinyLfu => EvictionPolicy::tiny_lfu(),
+            CacheEvictionPolicy::Lru => EvictionPolicy::lru(),
+        }
+    }
+}
+
+/// Approximate bookkeeping moka keeps for each entry, in bytes.
+const ENTRY_OVERHEAD_BYTES: usize = 128;
+
+/// Approximate memory held by a cache entry for `short_code`: the fixed size
+/// of the structs it keeps plus the length of every string in them. A cached
+/// absence weighs its key alone.
+pub(super) fn entry_weight(short_code: &str, cached: Option<&CachedUrl>) -> u32 {
+    let bytes = ENTRY_OVERHEAD_BYTES
+        + std::mem::size_of::<String>()
+        + short_code.len()
+        + cached.map_or(0, CachedUrl::size_in_bytes);
+    u32::try_from(bytes).unwrap_or(u32::MAX)
+}
+
+/// Where lookups of codes that do not exist are cached.
+pub(super) enum NegativeCache {
+    /// As `None` entries in the read cache, competing with found links
+    Shared,
+    /// In a cache of their own, capped separately
+    Separate(Cache<String, ()>),
+    /// Not at all: every lookup of a missing code reaches the database
+    Disabled,
+}
+
+/// The last copy of each recently loaded link, kept for at most `max_age`.
+///
+/// Redirects fall back to it when a cache miss cannot reach the database, so
+/// links that were served recently keep working through a short outage.
+/// Entries are dropped whenever the link changes, so a stale redirect never
+/// resurrects a destination or a deactivated link.
+pub(super) struct StaleSnapshot {
+    pub(super) entries: Cache<String, Arc<CachedUrl>>,
+    pub(super) max_age: Duration,
+    /// Redirects answered from the snapshot
+    pub(super) served: AtomicU64,
+}
+
+impl StaleSnapshot {
+    /// A snapshot capped like the read cache of `policy`.
+    pub(super) fn new(policy: &CachePolicy, max_age: Duration) -> Self {
+        let builder = Cache::builder()
+            .time_to_live(max_age)
+            .eviction_policy(EvictionPolicy::lru());
+        let entries = match policy.max_bytes {
+            Some(max_bytes) => builder
+                .max_capacity(max_bytes)
+                .weigher(|short_code: &String, cached: &Arc<CachedUrl>| {
+                    entry_weight(short_code, Some(cached))
+                })
+                .build(),
+            None => builder.max_capacity(policy.max_entries).build(),
+        };
+        Self {
+            entries,
+            max_age,
+            served: AtomicU64::new(0),
+        }
+    }
+}
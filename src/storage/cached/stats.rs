@@ -0,0 +1,120 @@
+//! Sizes and counters of the caches, as reported by `cache_stats`.
+
+use crate::config::CacheEvictionPolicy;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::policy::NegativeCache;
+use super::CachedStorage;
+
+/// Current size of the read cache, split by lookup outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub eviction_policy: CacheEvictionPolicy,
+    pub max_entries: u64,
+    /// Byte budget of the read cache, when it is sized in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+    /// Current size of the read cache in the unit of its cap: approximate
+    /// bytes under `max_bytes`, else entries
+    pub weighted_size: u64,
+    /// Separate cap for missing codes; absent when they share `max_entries`
+    pub negative_max_entries: Option<u64>,
+    /// Seconds after which a cached lookup is reloaded from the database;
+    /// `None` when entries live until evicted or changed
+    pub entry_ttl_secs: Option<u64>,
+    /// Cached links that exist (active or not)
+    pub positive_entries: u64,
+    /// Cached lookups of codes that do not exist
+    pub negative_entries: u64,
+    /// Stale serving, when enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale: Option<StaleStats>,
+    /// How cache misses reached the database
+    pub lookups: LookupStats,
+}
+
+/// Cache misses since startup, by how each was answered.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LookupStats {
+    /// Misses that queried the database
+    pub loads: u64,
+    /// Misses that joined a query already in flight for the same code
+    pub coalesced: u64,
+    /// Queries abandoned after the lookup timeout, failing every miss that
+    /// waited on them
+    pub timed_out: u64,
+}
+
+/// Counters behind [`LookupStats`].
+#[derive(Default)]
+pub(super) struct LookupCounters {
+    pub(super) loads: AtomicU64,
+    pub(super) coalesced: AtomicU64,
+    pub(super) timed_out: AtomicU64,
+}
+
+impl LookupCounters {
+    fn snapshot(&self) -> LookupStats {
+        LookupStats {
+            loads: self.loads.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// State of the snapshot redirects fall back to while the database is down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleStats {
+    pub max_age_secs: u64,
+    /// Links that could currently be served stale
+    pub entries: u64,
+    /// Redirects served stale since startup
+    pub served: u64,
+}
+
+impl CachedStorage {
+    /// Count the read cache's entries and snapshot its counters. Pending
+    /// evictions run first, so the counts only include live entries.
+    pub(super) async fn stats(&self) -> CacheStats {
+        self.read_cache.run_pending_tasks().await;
+        let mut positive_entries = 0;
+        let mut negative_entries = 0;
+        for (_, cached) in self.read_cache.iter() {
+            if cached.is_some() {
+                positive_entries += 1;
+            } else {
+                negative_entries += 1;
+            }
+        }
+        if let NegativeCache::Separate(negative) = &self.negative_cache {
+            negative.run_pending_tasks().await;
+            negative_entries += negative.entry_count();
+        }
+        let stale = match &self.stale {
+            Some(stale) => {
+                stale.entries.run_pending_tasks().await;
+                Some(StaleStats {
+                    max_age_secs: stale.max_age.as_secs(),
+                    entries: stale.entries.entry_count(),
+                    served: stale.served.load(Ordering::Relaxed),
+                })
+            }
+            None => None,
+        };
+
+        CacheStats {
+            eviction_policy: self.policy.eviction_policy,
+            max_entries: self.policy.max_entries,
+            max_bytes: self.policy.max_bytes,
+            weighted_size: self.read_cache.weighted_size(),
+            negative_max_entries: self.policy.negative_max_entries,
+            entry_ttl_secs: self.policy.entry_ttl.map(|ttl| ttl.as_secs()),
+            positive_entries,
+            negative_entries,
+            stale,
+            lookups: self.lookups.snapshot(),
+        }
+    }
+}
@@ -0,0 +1,596 @@
+use super::policy::entry_weight;
+use super::*;
+use crate::config::CacheEvictionPolicy;
+use crate::models::{CreatedVia, LinkOptions};
+use crate::storage::{SqliteStorage, StorageError};
+use crate::testing::Fixture;
+
+#[tokio::test]
+async fn owned_click_recovers_code_when_actor_is_closed() {
+    let (actor_tx, actor_rx) = mpsc::channel(1);
+    drop(actor_rx);
+    let read_view = DashMap::new();
+
+    let error =
+        enqueue_click_increment(&actor_tx, &read_view, "recover-me".to_owned(), 1).unwrap_err();
+
+    assert_eq!(error.short_code(), "recover-me");
+}
+
+#[tokio::test]
+async fn zero_owned_click_does_not_require_a_live_actor() {
+    let (actor_tx, actor_rx) = mpsc::channel(1);
+    drop(actor_rx);
+    let read_view = DashMap::new();
+
+    enqueue_click_increment(&actor_tx, &read_view, "no-op".to_owned(), 0).unwrap();
+}
+
+#[tokio::test]
+async fn full_actor_queue_merges_click_into_flush_layer() {
+    let (actor_tx, _actor_rx) = mpsc::channel(1);
+    let read_view = DashMap::new();
+    actor_tx
+        .try_send(ActorMessage::BatchIncrement("queued".to_owned(), 1))
+        .unwrap();
+
+    enqueue_click_increment(&actor_tx, &read_view, "overflow".to_owned(), 7).unwrap();
+
+    assert_eq!(
+        read_view.get("overflow").map(|pending| pending.count),
+        Some(7)
+    );
+}
+
+#[tokio::test]
+async fn concurrent_full_queue_merges_every_click() {
+    let (actor_tx, _actor_rx) = mpsc::channel(1);
+    actor_tx
+        .try_send(ActorMessage::BatchIncrement("queued".to_owned(), 1))
+        .unwrap();
+    let actor_tx = Arc::new(actor_tx);
+    let read_view = Arc::new(DashMap::new());
+    let mut workers = tokio::task::JoinSet::new();
+
+    for _ in 0..100 {
+        let actor_tx = Arc::clone(&actor_tx);
+        let read_view = Arc::clone(&read_view);
+        workers.spawn(async move {
+            enqueue_click_increment(&actor_tx, &read_view, "overflow".to_owned(), 1).unwrap();
+        });
+    }
+    while let Some(result) = workers.join_next().await {
+        result.unwrap();
+    }
+
+    assert_eq!(
+        read_view.get("overflow").map(|pending| pending.count),
+        Some(100)
+    );
+}
+
+#[tokio::test]
+async fn graceful_shutdown_persists_queued_and_overflow_clicks() {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    inner
+        .create_with_code("durable", "https://example.com", None)
+        .await
+        .unwrap();
+    let storage = CachedStorage::new(inner.clone(), 10, 3_600, 1, 3_600_000);
+
+    storage.buffer_click_owned("durable".to_owned(), 1).unwrap();
+    storage.buffer_click_owned("durable".to_owned(), 7).unwrap();
+    storage.shutdown().await;
+
+    let url = inner.get_authoritative("durable").await.unwrap().unwrap();
+    assert_eq!(url.clicks, 8);
+}
+
+#[tokio::test]
+async fn clicks_buffered_before_deactivation_are_not_counted() {
+    let (inner, storage) = sqlite_backed_storage().await;
+    storage
+        .create_with_code("paused", "https://example.com", None)
+        .await
+        .unwrap();
+
+    // A click accepted just before the link was disabled reaches the
+    // database only after it is inactive.
+    storage.buffer_click_owned("paused".to_owned(), 5).unwrap();
+    storage.deactivate("paused").await.unwrap();
+    let url = storage.get_authoritative("paused").await.unwrap().unwrap();
+    assert_eq!(url.clicks, 0);
+    storage.flush().await.unwrap();
+    let url = inner.get_authoritative("paused").await.unwrap().unwrap();
+    assert_eq!(url.clicks, 0);
+
+    storage.reactivate("paused").await.unwrap();
+    storage.buffer_click_owned("paused".to_owned(), 2).unwrap();
+    storage.flush().await.unwrap();
+    let url = inner.get_authoritative("paused").await.unwrap().unwrap();
+    assert_eq!(url.clicks, 2);
+}
+
+#[tokio::test]
+async fn click_flush_reports_entries_bytes_and_lag() {
+    let (_inner, storage) = sqlite_backed_storage().await;
+    storage
+        .create_with_code("logged", "https://example.com", None)
+        .await
+        .unwrap();
+    let (events, _guard) = crate::flush::capture::flush_events();
+
+    storage.buffer_click_owned("logged".to_owned(), 3).unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    storage.flush().await.unwrap();
+
+    let events = events.lock().unwrap();
+    let event = events
+        .iter()
+        .find(|event| event["stage"] == "clicks")
+        .expect("click flush event");
+    assert_eq!(event["entries"], "1");
+    assert_eq!(event["bytes"], ("logged".len() + 8).to_string());
+    assert_eq!(event["succeeded"], "true");
+    assert!(event.contains_key("duration_ms"));
+    assert!(event["lag_ms"].parse::<u64>().unwrap() >= 20);
+}
+
+async fn sqlite_backed_storage() -> (Arc<SqliteStorage>, CachedStorage) {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    let storage = CachedStorage::new(inner.clone(), 10, 3_600, 16, 3_600_000);
+    (inner, storage)
+}
+
+#[tokio::test]
+async fn created_link_is_served_from_cache_without_database_lookup() {
+    let (inner, storage) = sqlite_backed_storage().await;
+    storage
+        .create_with_code("fresh", "https://example.com/original", None)
+        .await
+        .unwrap();
+    // Change the row behind the cache: only a database read could see it.
+    inner
+        .update_url("fresh", "https://example.com/changed", None)
+        .await
+        .unwrap();
+
+    let lookup = storage.get_with_metadata("fresh").await.unwrap();
+    assert!(lookup.metadata.cache_hit);
+    assert!(lookup.metadata.cache_duration.is_some());
+    assert_eq!(lookup.metadata.db_duration, None);
+    assert_eq!(
+        lookup.url.unwrap().original_url,
+        "https://example.com/original"
+    );
+
+    let redirect = storage.get_redirect_with_metadata("fresh").await.unwrap();
+    assert!(redirect.metadata.cache_hit);
+    assert_eq!(redirect.metadata.db_duration, None);
+    assert_eq!(
+        redirect.target.unwrap().original_url(),
+        "https://example.com/original"
+    );
+}
+
+#[tokio::test]
+async fn cache_miss_with_database_hit_reports_both_durations() {
+    let (inner, storage) = sqlite_backed_storage().await;
+    inner
+        .create_with_code("cold", "https://example.com", None)
+        .await
+        .unwrap();
+
+    let lookup = storage.get_with_metadata("cold").await.unwrap();
+    assert!(!lookup.metadata.cache_hit);
+    assert!(lookup.metadata.cache_duration.is_some());
+    assert!(lookup.metadata.db_duration.is_some());
+    assert!(lookup.url.is_some());
+
+    let repeat = storage.get_with_metadata("cold").await.unwrap();
+    assert!(repeat.metadata.cache_hit);
+    assert_eq!(repeat.metadata.db_duration, None);
+}
+
+#[tokio::test]
+async fn missing_code_reports_database_lookup_then_cached_absence() {
+    let (_inner, storage) = sqlite_backed_storage().await;
+
+    let lookup = storage.get_with_metadata("absent").await.unwrap();
+    assert!(!lookup.metadata.cache_hit);
+    assert!(lookup.metadata.db_duration.is_some());
+    assert!(lookup.url.is_none());
+
+    let repeat = storage.get_with_metadata("absent").await.unwrap();
+    assert!(repeat.metadata.cache_hit);
+    assert_eq!(repeat.metadata.db_duration, None);
+    assert!(repeat.url.is_none());
+}
+
+#[tokio::test]
+async fn creating_a_previously_missing_code_replaces_cached_absence() {
+    let (_inner, storage) = sqlite_backed_storage().await;
+    assert!(storage.get("late").await.unwrap().is_none());
+
+    storage
+        .create_with_code("late", "https://example.com/late", None)
+        .await
+        .unwrap();
+
+    let lookup = storage.get_with_metadata("late").await.unwrap();
+    assert!(lookup.metadata.cache_hit);
+    assert_eq!(lookup.url.unwrap().original_url, "https://example.com/late");
+}
+
+async fn storage_with_policy(negative_max_entries: Option<u64>) -> CachedStorage {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    let policy = CachePolicy {
+        max_entries: 10,
+        max_bytes: None,
+        negative_max_entries,
+        eviction_policy: CacheEvictionPolicy::Lru,
+        stale_max_age: None,
+        entry_ttl: None,
+        lookup_timeout: None,
+    };
+    CachedStorage::new_with_cache_policy(
+        inner,
+        policy,
+        3_600,
+        16,
+        3_600_000,
+        FlushConfig::default(),
+    )
+}
+
+#[tokio::test]
+async fn shared_cache_counts_found_and_missing_codes() {
+    let (_inner, storage) = sqlite_backed_storage().await;
+    storage
+        .create_with_code("found", "https://example.com", None)
+        .await
+        .unwrap();
+    assert!(storage.get("missing").await.unwrap().is_none());
+
+    let stats = storage.cache_stats().await.unwrap();
+    assert_eq!(stats.eviction_policy, CacheEvictionPolicy::TinyLfu);
+    assert_eq!(stats.max_entries, 10);
+    assert_eq!(stats.negative_max_entries, None);
+    assert_eq!(stats.entry_ttl_secs, None);
+    assert_eq!(stats.positive_entries, 1);
+    assert_eq!(stats.negative_entries, 1);
+}
+
+#[tokio::test]
+async fn separate_negative_cache_keeps_missing_codes_out_of_the_read_cache() {
+    let storage = storage_with_policy(Some(2)).await;
+    storage
+        .create_with_code("found", "https://example.com", None)
+        .await
+        .unwrap();
+    for code in ["scan-1", "scan-2", "scan-3", "scan-4"] {
+        assert!(storage.get(code).await.unwrap().is_none());
+    }
+
+    let repeat = storage.get_with_metadata("scan-4").await.unwrap();
+    assert!(repeat.metadata.cache_hit);
+    assert!(repeat.url.is_none());
+
+    let stats = storage.cache_stats().await.unwrap();
+    assert_eq!(stats.eviction_policy, CacheEvictionPolicy::Lru);
+    assert_eq!(stats.negative_max_entries, Some(2));
+    assert_eq!(stats.positive_entries, 1);
+    assert_eq!(stats.negative_entries, 2);
+    assert!(
+        storage
+            .get_with_metadata("found")
+            .await
+            .unwrap()
+            .metadata
+            .cache_hit
+    );
+}
+
+#[tokio::test]
+async fn creating_a_code_clears_its_separate_cached_absence() {
+    let storage = storage_with_policy(Some(10)).await;
+    assert!(storage.get("late").await.unwrap().is_none());
+
+    storage
+        .create_with_code("late", "https://example.com/late", None)
+        .await
+        .unwrap();
+
+    let lookup = storage.get_with_metadata("late").await.unwrap();
+    assert!(lookup.metadata.cache_hit);
+    assert_eq!(lookup.url.unwrap().original_url, "https://example.com/late");
+    assert_eq!(storage.cache_stats().await.unwrap().negative_entries, 0);
+}
+
+#[tokio::test]
+async fn disabled_negative_cache_always_asks_the_database() {
+    let storage = storage_with_policy(Some(0)).await;
+    assert!(storage.get("absent").await.unwrap().is_none());
+    assert!(storage.get_authoritative("absent").await.unwrap().is_none());
+
+    let repeat = storage.get_with_metadata("absent").await.unwrap();
+    assert!(!repeat.metadata.cache_hit);
+    assert!(repeat.metadata.db_duration.is_some());
+    assert_eq!(storage.cache_stats().await.unwrap().negative_entries, 0);
+}
+
+#[tokio::test]
+async fn byte_budget_evicts_by_size_not_count() {
+    const MAX_BYTES: u64 = 32 * 1024;
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    let policy = CachePolicy {
+        eviction_policy: CacheEvictionPolicy::Lru,
+        ..CachePolicy::with_max_bytes(MAX_BYTES)
+    };
+    let storage = CachedStorage::new_with_cache_policy(
+        inner,
+        policy,
+        3_600,
+        16,
+        3_600_000,
+        FlushConfig::default(),
+    );
+
+    let small: Vec<String> = (0..20).map(|i| format!("small-{i}")).collect();
+    for code in &small {
+        storage
+            .create_with_code(code, "https://example.com/", None)
+            .await
+            .unwrap();
+        storage.get(code).await.unwrap().unwrap();
+    }
+    let stats = storage.cache_stats().await.unwrap();
+    assert_eq!(stats.max_bytes, Some(MAX_BYTES));
+    assert_eq!(stats.positive_entries, 20);
+    assert!(stats.weighted_size <= MAX_BYTES);
+
+    // Each of these outweighs all the small links together
+    let long_url = format!("https://example.com/{}", "x".repeat(6 * 1024));
+    for i in 0..6 {
+        let code = format!("large-{i}");
+        storage
+            .create_with_code(&code, &long_url, None)
+            .await
+            .unwrap();
+        let large = storage.get(&code).await.unwrap().unwrap();
+        let weight = entry_weight(&code, Some(&CachedUrl::new(large)));
+        assert!(u64::from(weight) > stats.weighted_size);
+    }
+    let stats = storage.cache_stats().await.unwrap();
+    assert!(stats.weighted_size <= MAX_BYTES);
+    assert!(stats.positive_entries < 6);
+}
+
+async fn storage_with_stale_serving(
+    stale_max_age: Option<Duration>,
+) -> (Arc<SqliteStorage>, CachedStorage) {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    let policy = CachePolicy {
+        stale_max_age,
+        ..CachePolicy::with_max_entries(10)
+    };
+    let storage = CachedStorage::new_with_cache_policy(
+        inner.clone(),
+        policy,
+        3_600,
+        16,
+        3_600_000,
+        FlushConfig::default(),
+    );
+    (inner, storage)
+}
+
+/// Fail every later query, as an unreachable database would.
+async fn take_database_down(inner: &SqliteStorage) {
+    inner.pool.close().await;
+    inner.read_pool.close().await;
+}
+
+#[tokio::test]
+async fn evicted_links_redirect_from_the_stale_snapshot_while_the_database_is_down() {
+    let (inner, storage) = storage_with_stale_serving(Some(Duration::from_secs(60))).await;
+    inner
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    assert!(storage.get_redirect("docs").await.unwrap().is_some());
+    storage.read_cache.invalidate("docs").await;
+    take_database_down(&inner).await;
+
+    let target = storage.get_redirect("docs").await.unwrap().unwrap();
+    assert_eq!(target.original_url(), "https://example.com/docs");
+    let lookup = storage.get_redirect_with_metadata("docs").await.unwrap();
+    assert!(!lookup.metadata.cache_hit);
+    assert!(lookup.target.is_some());
+
+    // Codes never loaded have nothing to fall back to, and API lookups
+    // report the outage instead of answering from the snapshot.
+    assert!(storage.get_redirect("other").await.is_err());
+    let error = storage.get("docs").await.unwrap_err();
+    assert!(matches!(
+        StorageError::from(error),
+        StorageError::Unavailable(_)
+    ));
+
+    let stats = storage.cache_stats().await.unwrap();
+    assert_eq!(
+        stats.stale,
+        Some(StaleStats {
+            max_age_secs: 60,
+            entries: 1,
+            served: 2,
+        })
+    );
+}
+
+#[tokio::test]
+async fn changed_links_are_never_served_stale() {
+    let (inner, storage) = storage_with_stale_serving(Some(Duration::from_secs(60))).await;
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    assert!(storage.get_redirect("docs").await.unwrap().is_some());
+    assert!(storage.deactivate("docs").await.unwrap());
+    take_database_down(&inner).await;
+
+    assert!(storage.get_redirect("docs").await.is_err());
+}
+
+#[tokio::test]
+async fn stale_serving_is_off_unless_configured() {
+    let (inner, storage) = storage_with_stale_serving(None).await;
+    inner
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    assert!(storage.get_redirect("docs").await.unwrap().is_some());
+    storage.read_cache.invalidate("docs").await;
+    take_database_down(&inner).await;
+
+    assert!(storage.get_redirect("docs").await.is_err());
+    assert_eq!(storage.cache_stats().await.unwrap().stale, None);
+}
+
+#[tokio::test]
+async fn entry_ttl_bounds_how_long_changes_elsewhere_go_unseen() {
+    const TTL: Duration = Duration::from_millis(300);
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    inner.init().await.unwrap();
+    let storage = CachedStorage::new_with_cache_policy(
+        inner.clone(),
+        CachePolicy {
+            entry_ttl: Some(TTL),
+            ..CachePolicy::with_max_entries(10)
+        },
+        3_600,
+        16,
+        3_600_000,
+        FlushConfig::default(),
+    );
+    Fixture::new(inner.clone()).seed().await.unwrap();
+    assert!(storage.get("user1-0").await.unwrap().unwrap().is_active);
+
+    // Another instance deactivates the link in the shared database
+    assert!(inner.deactivate("user1-0").await.unwrap());
+    assert!(
+        storage
+            .get_with_metadata("user1-0")
+            .await
+            .unwrap()
+            .metadata
+            .cache_hit
+    );
+    assert!(storage.get("user1-0").await.unwrap().unwrap().is_active);
+
+    tokio::time::sleep(TTL + Duration::from_millis(100)).await;
+    let lookup = storage.get_with_metadata("user1-0").await.unwrap();
+    assert!(!lookup.metadata.cache_hit);
+    assert!(!lookup.url.unwrap().is_active);
+}
+
+#[tokio::test]
+async fn click_counts_leave_with_their_cache_entries() {
+    let storage = storage_with_policy(None).await;
+    let limited = |max_clicks| LinkOptions {
+        max_clicks: Some(max_clicks),
+        ..LinkOptions::default()
+    };
+    storage
+        .create_with_code_via(
+            "limited",
+            "https://example.com",
+            None,
+            None,
+            CreatedVia::Api,
+            &limited(2),
+        )
+        .await
+        .unwrap();
+    let admit = || async {
+        let target = storage.get_redirect("limited").await.unwrap().unwrap();
+        storage.admit_click(&target)
+    };
+
+    assert!(admit().await);
+    assert_eq!(storage.admitted.get("limited").map(|n| *n), Some(1));
+
+    // Changing the limit, or anything else about the link, drops its count
+    assert!(storage
+        .set_link_options("limited", &limited(5))
+        .await
+        .unwrap());
+    assert!(!storage.admitted.contains_key("limited"));
+    assert!(admit().await);
+    assert!(storage.deactivate("limited").await.unwrap());
+    assert!(storage.reactivate("limited").await.unwrap());
+    assert!(!storage.admitted.contains_key("limited"));
+
+    // So does evicting the link from the read cache
+    assert!(admit().await);
+    for n in 0..20 {
+        let code = format!("other{n}");
+        storage
+            .create_with_code(&code, "https://example.com", None)
+            .await
+            .unwrap();
+        storage.get_redirect(&code).await.unwrap();
+    }
+    storage.read_cache.run_pending_tasks().await;
+    assert!(!storage.read_cache.contains_key("limited"));
+    assert!(storage.admitted.is_empty());
+}
+
+#[tokio::test]
+async fn evicting_an_alias_keeps_the_canonical_click_count() {
+    let storage = storage_with_policy(None).await;
+    let limited = LinkOptions {
+        max_clicks: Some(3),
+        ..LinkOptions::default()
+    };
+    storage
+        .create_with_code_via(
+            "limited",
+            "https://example.com",
+            None,
+            None,
+            CreatedVia::Api,
+            &limited,
+        )
+        .await
+        .unwrap();
+    storage
+        .add_alias("limited", "alias", None)
+        .await
+        .unwrap()
+        .unwrap();
+    let admit = |short_code| {
+        let storage = &storage;
+        async move {
+            let target = storage.get_redirect(short_code).await.unwrap().unwrap();
+            storage.admit_click(&target)
+        }
+    };
+
+    assert!(admit("limited").await);
+    assert!(admit("alias").await);
+    assert_eq!(storage.admitted.get("limited").map(|n| *n), Some(2));
+
+    // The canonical entry is still cached and keeps counting
+    storage.read_cache.invalidate("alias").await;
+    storage.read_cache.run_pending_tasks().await;
+    assert_eq!(storage.admitted.get("limited").map(|n| *n), Some(2));
+    assert!(admit("limited").await);
+    assert!(!admit("limited").await);
+    assert!(!admit("alias").await);
+}
@@ -0,0 +1,11 @@
+//! Campaigns: named groups of links whose stats roll up together.
+//!
+//! A link belongs to at most one campaign through `urls.campaign_id`.
+//! Deleting a campaign unassigns its links and keeps them. Each backend's
+//! queries live in a submodule here, and the backends' `Storage` campaign
+//! methods call into it.
+
+#[cfg(feature = "postgres")]
+pub(crate) mod postgres;
+#[cfg(feature = "sqlite")]
+pub(crate) mod sqlite;
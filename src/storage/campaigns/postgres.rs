@@ -0,0 +1,204 @@
+//! Campaign queries for `PostgresStorage`.
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+use crate::analytics::AnalyticsAggregate;
+use crate::models::{Campaign, CampaignLinkStats, CampaignStats};
+
+/// Create the `campaigns` table and the `urls.campaign_id` column pointing
+/// into it.
+pub(crate) async fn create_schema(pool: &PgPool) -> Result<()> {
+    // Deleting a campaign unassigns its links; `delete` does that itself,
+    // and the foreign key backs it up.
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS campaigns (
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            owner TEXT,
+            created_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "ALTER TABLE urls ADD COLUMN IF NOT EXISTS campaign_id BIGINT REFERENCES campaigns(id) ON DELETE SET NULL",
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_campaign_id ON urls(campaign_id) WHERE campaign_id IS NOT NULL",
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn create(
+    pool: &PgPool,
+    name: &str,
+    owner: Option<&str>,
+    created_at: i64,
+) -> Result<Campaign> {
+    let campaign = sqlx::query_as::<_, Campaign>(
+        "INSERT INTO campaigns (name, owner, created_at) VALUES ($1, $2, $3) RETURNING id, name, owner, created_at",
+    )
+    .bind(name)
+    .bind(owner)
+    .bind(created_at)
+    .fetch_one(pool)
+    .await?;
+    Ok(campaign)
+}
+
+pub(crate) async fn get(pool: &PgPool, id: i64) -> Result<Option<Campaign>> {
+    let campaign = sqlx::query_as::<_, Campaign>(
+        "SELECT id, name, owner, created_at FROM campaigns WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(campaign)
+}
+
+pub(crate) async fn list(pool: &PgPool, owner: Option<&str>) -> Result<Vec<Campaign>> {
+    let campaigns = sqlx::query_as::<_, Campaign>(
+        r#"
+        SELECT id, name, owner, created_at
+        FROM campaigns
+        WHERE $1::TEXT IS NULL OR owner = $1
+        ORDER BY created_at DESC, id DESC
+        "#,
+    )
+    .bind(owner)
+    .fetch_all(pool)
+    .await?;
+    Ok(campaigns)
+}
+
+pub(crate) async fn rename(pool: &PgPool, id: i64, name: &str) -> Result<Option<Campaign>> {
+    let campaign = sqlx::query_as::<_, Campaign>(
+        "UPDATE campaigns SET name = $1 WHERE id = $2 RETURNING id, name, owner, created_at",
+    )
+    .bind(name)
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(campaign)
+}
+
+/// Delete campaign `id` and unassign its links, returning their codes, or
+/// `None` when there is no such campaign.
+pub(crate) async fn delete(pool: &PgPool, id: i64) -> Result<Option<Vec<String>>> {
+    let mut tx = pool.begin().await?;
+    let unassigned: Vec<String> = sqlx::query_scalar(
+        "UPDATE urls SET campaign_id = NULL WHERE campaign_id = $1 RETURNING short_code",
+    )
+    .bind(id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let deleted = sqlx::query("DELETE FROM campaigns WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+        > 0;
+    tx.commit().await?;
+    Ok(deleted.then_some(unassigned))
+}
+
+pub(crate) async fn set_link_campaign(
+    pool: &PgPool,
+    short_code: &str,
+    campaign_id: Option<i64>,
+) -> Result<bool> {
+    let result = sqlx::query("UPDATE urls SET campaign_id = $1 WHERE short_code = $2")
+        .bind(campaign_id)
+        .bind(short_code)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn stats(
+    pool: &PgPool,
+    id: i64,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<CampaignStats> {
+    let start_time = start_time.unwrap_or(i64::MIN);
+    let end_time = end_time.unwrap_or(i64::MAX);
+    let links = sqlx::query_as::<_, CampaignLinkStats>(
+        r#"
+        SELECT u.short_code, u.clicks,
+               COALESCE(SUM(a.visit_count), 0)::BIGINT AS visits
+        FROM urls u
+        LEFT JOIN analytics a
+          ON a.short_code = u.short_code AND a.time_bucket >= $1 AND a.time_bucket <= $2
+        WHERE u.campaign_id = $3
+        GROUP BY u.id
+        ORDER BY u.clicks DESC, u.short_code
+        "#,
+    )
+    .bind(start_time)
+    .bind(end_time)
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    let countries = sqlx::query_as::<_, AnalyticsAggregate>(
+        r#"
+        SELECT a.country_code AS dimension, SUM(a.visit_count)::BIGINT AS visit_count
+        FROM analytics a
+        JOIN urls u ON u.short_code = a.short_code
+        WHERE u.campaign_id = $1 AND a.time_bucket >= $2 AND a.time_bucket <= $3
+          AND a.country_code IS NOT NULL
+        GROUP BY a.country_code
+        ORDER BY visit_count DESC, dimension
+        "#,
+    )
+    .bind(id)
+    .bind(start_time)
+    .bind(end_time)
+    .fetch_all(pool)
+    .await?;
+    Ok(CampaignStats::new(links, countries))
+}
+
+pub(crate) async fn export(pool: &PgPool) -> Result<Vec<Campaign>> {
+    let campaigns = sqlx::query_as::<_, Campaign>(
+        "SELECT id, name, owner, created_at FROM campaigns ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(campaigns)
+}
+
+/// Insert `campaigns` with their ids, skipping ids already taken.
+pub(crate) async fn import(pool: &PgPool, campaigns: &[Campaign]) -> Result<u64> {
+    let mut inserted = 0;
+    let mut tx = pool.begin().await?;
+    for campaign in campaigns {
+        inserted += sqlx::query(
+            "INSERT INTO campaigns (id, name, owner, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(campaign.id)
+        .bind(&campaign.name)
+        .bind(&campaign.owner)
+        .bind(campaign.created_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    if inserted > 0 {
+        // BIGSERIAL does not see explicit ids; later inserts would collide.
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('campaigns', 'id'), (SELECT MAX(id) FROM campaigns))",
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(inserted)
+}
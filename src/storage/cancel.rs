@@ -0,0 +1,127 @@
+//! Interrupting SQLite statements whose caller has given up.
+//!
+//! Dropping a sqlx future stops waiting for a statement but not the statement
+//! itself: SQLite keeps stepping it on the connection's worker thread, and
+//! the connection only goes back to the pool once it finishes. A future run
+//! through [`with_query_cancel`] carries a [`QueryCancel`]; SQLite connections
+//! acquired inside it get a progress handler that aborts their statement once
+//! the token is cancelled, so the connection is free again within
+//! milliseconds. Postgres statements still run to completion on the server.
+
+#[cfg(feature = "sqlite")]
+use sqlx::sqlite::{SqliteConnection, SqlitePoolOptions};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// SQLite virtual machine instructions between two cancellation checks.
+#[cfg(feature = "sqlite")]
+const PROGRESS_CHECK_OPS: i32 = 1_000;
+
+tokio::task_local! {
+    static CURRENT: QueryCancel;
+}
+
+/// Shared flag that aborts the statements started under it.
+#[derive(Debug, Clone, Default)]
+pub struct QueryCancel(Arc<AtomicBool>);
+
+impl QueryCancel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort statements still running under this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Run `future` with `cancel` governing the statements it starts.
+pub async fn with_query_cancel<F: Future>(cancel: QueryCancel, future: F) -> F::Output {
+    CURRENT.scope(cancel, future).await
+}
+
+/// Point each connection's progress handler at the acquiring task's token.
+/// Connections acquired outside [`with_query_cancel`] run without one, so a
+/// token cancelled after its connection was released cannot abort the next
+/// user's statement.
+#[cfg(feature = "sqlite")]
+pub(crate) fn interruptible(options: SqlitePoolOptions) -> SqlitePoolOptions {
+    options
+        .after_connect(|conn, _| Box::pin(watch_current(conn)))
+        .before_acquire(|conn, _| Box::pin(async move { watch_current(conn).await.map(|()| true) }))
+}
+
+#[cfg(feature = "sqlite")]
+async fn watch_current(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let cancel = CURRENT.try_with(QueryCancel::clone).ok();
+    let mut handle = conn.lock_handle().await?;
+    match cancel {
+        Some(cancel) => {
+            handle.set_progress_handler(PROGRESS_CHECK_OPS, move || !cancel.is_cancelled())
+        }
+        None => handle.remove_progress_handler(),
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    const SLOW_QUERY: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100000000) SELECT count(*) FROM n";
+
+    async fn single_connection_pool() -> sqlx::SqlitePool {
+        interruptible(SqlitePoolOptions::new().max_connections(1))
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn cancelling_interrupts_the_running_statement() {
+        let pool = single_connection_pool().await;
+        let cancel = QueryCancel::new();
+        let query = tokio::spawn(with_query_cancel(cancel.clone(), {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, i64>(SLOW_QUERY)
+                    .fetch_one(&pool)
+                    .await
+            }
+        }));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        cancel.cancel();
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), query)
+            .await
+            .expect("statement kept running after cancel")
+            .unwrap();
+        assert!(result.unwrap_err().to_string().contains("interrupt"));
+    }
+
+    #[tokio::test]
+    async fn a_token_does_not_outlive_its_scope() {
+        let pool = single_connection_pool().await;
+        let cancel = QueryCancel::new();
+        with_query_cancel(cancel.clone(), async {
+            sqlx::query("SELECT 1").execute(&pool).await.unwrap();
+        })
+        .await;
+        cancel.cancel();
+
+        // The same connection, acquired outside any scope, runs to completion
+        let rows: i64 = sqlx::query_scalar(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000) SELECT count(*) FROM n",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows, 10_000);
+    }
+}
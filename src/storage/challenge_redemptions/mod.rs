@@ -0,0 +1,11 @@
+//! Proof-of-work nonces already spent on a link.
+//!
+//! Nonces are signed with the shared `CREATION_CHALLENGE_SECRET`, so any
+//! instance can check one another issued. Each redemption is a row in
+//! `challenge_redemptions` until the nonce expires, which is what stops the
+//! same answer from buying a link on every instance behind a load balancer.
+
+#[cfg(feature = "postgres")]
+pub(crate) mod postgres;
+#[cfg(feature = "sqlite")]
+pub(crate) mod sqlite;
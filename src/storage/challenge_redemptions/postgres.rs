@@ -0,0 +1,42 @@
+//! Challenge redemption queries for `PostgresStorage`.
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+pub(crate) async fn create_schema(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS challenge_redemptions (
+            nonce TEXT PRIMARY KEY,
+            expires_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_challenge_redemptions_expires_at ON challenge_redemptions(expires_at)",
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn redeem(pool: &PgPool, nonce: &str, expires_at: i64, now: i64) -> Result<bool> {
+    sqlx::query("DELETE FROM challenge_redemptions WHERE expires_at <= $1")
+        .bind(now)
+        .execute(pool)
+        .await?;
+    let result = sqlx::query(
+        r#"
+        INSERT INTO challenge_redemptions (nonce, expires_at)
+        VALUES ($1, $2)
+        ON CONFLICT (nonce) DO NOTHING
+        "#,
+    )
+    .bind(nonce)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,47 @@
+//! Challenge redemption queries for `SqliteStorage`.
+
+use anyhow::Result;
+use sqlx::{SqliteConnection, SqlitePool};
+
+pub(crate) async fn create_schema(connection: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS challenge_redemptions (
+            nonce TEXT PRIMARY KEY,
+            expires_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_challenge_redemptions_expires_at ON challenge_redemptions(expires_at)",
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn redeem(
+    pool: &SqlitePool,
+    nonce: &str,
+    expires_at: i64,
+    now: i64,
+) -> Result<bool> {
+    sqlx::query("DELETE FROM challenge_redemptions WHERE expires_at <= ?")
+        .bind(now)
+        .execute(pool)
+        .await?;
+    let result = sqlx::query(
+        r#"
+        INSERT INTO challenge_redemptions (nonce, expires_at)
+        VALUES (?, ?)
+        ON CONFLICT (nonce) DO NOTHING
+        "#,
+    )
+    .bind(nonce)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
@@ -0,0 +1,290 @@
+//! Bulk copy between storage backends (`lynx db copy`).
+//!
+//! Seeds a new backend, typically the secondary of a [`MirrorStorage`], from
+//! the current one. Campaigns, links, users, manual admins and visitor
+//! analytics are read in keyset-ordered batches and written in one
+//! transaction per batch. Links keep their clicks, state and timestamps, and their row ids when
+//! asked to (`lynx db migrate-to`); codes that already exist in the target
+//! are skipped, so an interrupted copy can simply be run again. Analytics rows have no key to skip on, so only the
+//! hours before the earliest one the target already has are copied: a
+//! mirror that started writing first keeps its rows, and a target without
+//! analytics gets all of them.
+//!
+//! [`MirrorStorage`]: super::MirrorStorage
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::Storage;
+
+/// Rows read and written per batch unless the caller picks another size.
+pub const DEFAULT_COPY_BATCH_SIZE: i64 = 1000;
+
+/// A `users` row as copied between backends.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct UserRecord {
+    pub user_id: String,
+    pub auth_method: String,
+    pub email: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_seen_at: i64,
+}
+
+/// An `admin_users` row as copied between backends.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct AdminRecord {
+    pub user_id: String,
+    pub auth_method: String,
+    pub promoted_at: i64,
+}
+
+/// Rows in each table a copy covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct RowCounts {
+    pub urls: i64,
+    pub users: i64,
+    pub admins: i64,
+    pub campaigns: i64,
+    pub analytics: i64,
+}
+
+impl RowCounts {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Tables whose counts differ, as `(table, self, other)`
+    pub fn differences(&self, other: &RowCounts) -> Vec<(&'static str, i64, i64)> {
+        [
+            ("urls", self.urls, other.urls),
+            ("users", self.users, other.users),
+            ("admin_users", self.admins, other.admins),
+            ("campaigns", self.campaigns, other.campaigns),
+            ("analytics", self.analytics, other.analytics),
+        ]
+        .into_iter()
+        .filter(|(_, ours, theirs)| ours != theirs)
+        .collect()
+    }
+}
+
+/// Rows a copy read from the source and inserted into the target, per table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CopyReport {
+    pub urls_read: u64,
+    pub urls_inserted: u64,
+    pub users_read: u64,
+    pub users_inserted: u64,
+    pub admins_read: u64,
+    pub admins_inserted: u64,
+    pub campaigns_read: u64,
+    pub campaigns_inserted: u64,
+    pub analytics_read: u64,
+    pub analytics_inserted: u64,
+    /// Earliest analytics hour the target already had; rows from it on
+    /// were not copied
+    pub analytics_before: Option<i64>,
+}
+
+/// Copy campaigns, links, users, admins and analytics from `source` to
+/// `target` in batches of `batch_size`, calling `on_batch` with the table
+/// name and the report so far after every batch. With `keep_ids`, links keep
+/// their row ids (see [`Storage::import_urls`]); campaigns always keep
+/// theirs, so links stay in their campaigns. Both storages must be
+/// initialized.
+pub async fn copy_storage(
+    source: &dyn Storage,
+    target: &dyn Storage,
+    batch_size: i64,
+    keep_ids: bool,
+    on_batch: &mut (dyn FnMut(&str, &CopyReport) + Send),
+) -> Result<CopyReport> {
+    anyhow::ensure!(batch_size > 0, "batch size must be positive");
+    let mut report = CopyReport::default();
+
+    // Links refer to their campaign, so campaigns go first. There are few
+    // enough for one batch.
+    let campaigns = source.export_campaigns().await?;
+    report.campaigns_read = campaigns.len() as u64;
+    report.campaigns_inserted = target.import_campaigns(&campaigns).await?;
+    on_batch("campaigns", &report);
+
+    let mut after_id = 0;
+    loop {
+        let urls = source.export_urls(after_id, batch_size).await?;
+        let Some(last) = urls.last() else { break };
+        after_id = last.id;
+        report.urls_read += urls.len() as u64;
+        report.urls_inserted += target.import_urls(&urls, keep_ids).await?;
+        on_batch("urls", &report);
+    }
+
+    let mut after = None;
+    loop {
+        let users = source.export_users(after.as_ref(), batch_size).await?;
+        let Some(last) = users.last() else { break };
+        after = Some((last.user_id.clone(), last.auth_method.clone()));
+        report.users_read += users.len() as u64;
+        report.users_inserted += target.import_users(&users).await?;
+        on_batch("users", &report);
+    }
+
+    // Manual admins are a handful of rows; one batch holds them all.
+    let admins = source.export_admins().await?;
+    report.admins_read = admins.len() as u64;
+    report.admins_inserted = target.import_admins(&admins).await?;
+    on_batch("admins", &report);
+
+    report.analytics_before = target
+        .export_analytics(None, 1)
+        .await?
+        .first()
+        .map(|row| row.time_bucket);
+    let cutoff = report.analytics_before.unwrap_or(i64::MAX);
+    let mut after = None;
+    loop {
+        let mut rows = source.export_analytics(after, batch_size).await?;
+        let Some(last) = rows.last() else { break };
+        after = Some((last.time_bucket, last.id));
+        let complete = last.time_bucket >= cutoff;
+        rows.retain(|row| row.time_bucket < cutoff);
+        report.analytics_read += rows.len() as u64;
+        report.analytics_inserted += target.import_analytics(&rows).await?;
+        on_batch("analytics", &report);
+        if complete {
+            break;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::analytics::{AnalyticsRollup, IpVersion};
+    use crate::storage::SqliteStorage;
+
+    async fn sqlite() -> SqliteStorage {
+        let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+        storage.init().await.unwrap();
+        storage
+    }
+
+    fn rollup(short_code: &str, time_bucket: i64) -> AnalyticsRollup {
+        AnalyticsRollup {
+            short_code: short_code.to_string(),
+            time_bucket,
+            country_code: Some("NL".to_string()),
+            region: None,
+            city: None,
+            asn: None,
+            ip_version: IpVersion::V4,
+            visit_count: 2,
+            estimated_visits: 0,
+            alias_used: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn copies_every_table_and_can_run_again() {
+        let source = sqlite().await;
+        for code in ["a", "b", "c"] {
+            source
+                .create_with_code(code, "https://example.com", Some("alice"))
+                .await
+                .unwrap();
+        }
+        source.increment_clicks("b", 4).await.unwrap();
+        source.deactivate("c").await.unwrap();
+        source
+            .upsert_user("alice", Some("alice@example.com"), "oauth")
+            .await
+            .unwrap();
+        source.promote_to_admin("alice", "oauth").await.unwrap();
+        source
+            .upsert_analytics_batch(vec![rollup("a", 3600), rollup("b", 7200)])
+            .await
+            .unwrap();
+
+        // A mirror that already wrote newer analytics keeps them.
+        let target = sqlite().await;
+        target
+            .upsert_analytics_batch(vec![rollup("b", 7200)])
+            .await
+            .unwrap();
+
+        let mut batches = 0;
+        let report = copy_storage(&source, &target, 2, false, &mut |_, _| batches += 1)
+            .await
+            .unwrap();
+        assert_eq!((report.urls_read, report.urls_inserted), (3, 3));
+        assert_eq!((report.users_inserted, report.admins_inserted), (1, 1));
+        assert_eq!(report.analytics_before, Some(7200));
+        assert_eq!((report.analytics_read, report.analytics_inserted), (1, 1));
+        assert!(batches >= 4);
+
+        let b = target.get_authoritative("b").await.unwrap().unwrap();
+        assert_eq!((b.clicks, b.created_by.as_deref()), (4, Some("alice")));
+        assert!(
+            !target
+                .get_authoritative("c")
+                .await
+                .unwrap()
+                .unwrap()
+                .is_active
+        );
+        assert!(target.is_manual_admin("alice", "oauth").await.unwrap());
+        assert_eq!(target.export_analytics(None, 10).await.unwrap().len(), 2);
+
+        let again = copy_storage(&source, &target, 2, false, &mut |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(again.urls_read, 3);
+        assert_eq!(
+            (
+                again.urls_inserted,
+                again.users_inserted,
+                again.admins_inserted
+            ),
+            (0, 0, 0)
+        );
+        assert_eq!(again.analytics_inserted, 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_ids_and_later_links_do_not_collide() {
+        let source = sqlite().await;
+        for code in ["a", "b", "c", "d"] {
+            source
+                .create_with_code(code, "https://example.com", None)
+                .await
+                .unwrap();
+        }
+
+        let target = sqlite().await;
+        copy_storage(&source, &target, 10, true, &mut |_, _| {})
+            .await
+            .unwrap();
+        for code in ["a", "b", "c", "d"] {
+            assert_eq!(
+                target.get_authoritative(code).await.unwrap().unwrap().id,
+                source.get_authoritative(code).await.unwrap().unwrap().id
+            );
+        }
+        assert!(source
+            .count_rows()
+            .await
+            .unwrap()
+            .differences(&target.count_rows().await.unwrap())
+            .is_empty());
+
+        let d = target.get_authoritative("d").await.unwrap().unwrap();
+        let e = target
+            .create_with_code("e", "https://example.com", None)
+            .await
+            .unwrap();
+        assert!(e.id > d.id);
+    }
+}
@@ -0,0 +1,42 @@
+//! Daily instance statistics.
+//!
+//! Once a day a background task records instance-wide totals for the last
+//! complete UTC day in `instance_stats_daily`, for the admin stats history.
+//! Recording a day again replaces its row, so restarts do not duplicate it.
+//! Days that end while the server is down are not filled in later.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::Storage;
+use crate::analytics::daily::{day_start, DAY_SECS};
+use crate::clock::Clock;
+
+/// Time between recordings.
+const RECORD_INTERVAL: Duration = Duration::from_secs(DAY_SECS as u64);
+
+/// Record yesterday's stats in `storage` once a day, starting now.
+pub fn spawn_instance_stats_recorder(
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RECORD_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let yesterday = day_start(clock.now_epoch_secs()) - DAY_SECS;
+            match storage.record_instance_stats(yesterday).await {
+                Ok(stats) => tracing::info!(
+                    day = stats.day,
+                    total_links = stats.total_links,
+                    links_created = stats.links_created,
+                    clicks = stats.clicks,
+                    "Recorded daily instance stats"
+                ),
+                Err(error) => tracing::warn!(%error, "Failed to record daily instance stats"),
+            }
+        }
+    })
+}
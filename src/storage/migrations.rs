@@ -0,0 +1,59 @@
+//! Numbered one-time migrations.
+//!
+//! Schema that can be declared idempotently (`CREATE ... IF NOT EXISTS`, a
+//! guarded `ADD COLUMN`) stays in each backend's `ensure_schema`. Changes
+//! that rewrite existing data or can only happen once, such as merging rows
+//! or dropping a constraint or column, are listed here instead. Each backend
+//! applies the ones a database has not had, in order, after its idempotent
+//! schema, and records each in `schema_migrations` in the transaction that
+//! finishes it, so later starts skip it after one lookup.
+//!
+//! Versions are never reused or reordered, and every backend implements
+//! every migration.
+
+use std::collections::HashSet;
+
+/// A one-time schema change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+}
+
+/// Every migration, oldest first.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "analytics_null_dimension_key",
+    },
+    Migration {
+        version: 2,
+        name: "urls_created_at_millis",
+    },
+];
+
+/// Migrations missing from `applied`, oldest first.
+pub fn pending(applied: &HashSet<i64>) -> impl Iterator<Item = &'static Migration> + '_ {
+    MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_count_up_from_one() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1, "{}", migration.name);
+        }
+    }
+
+    #[test]
+    fn pending_skips_applied_versions() {
+        let all: Vec<i64> = pending(&HashSet::new()).map(|m| m.version).collect();
+        assert_eq!(all.len(), MIGRATIONS.len());
+        assert_eq!(pending(&HashSet::from([1])).count(), MIGRATIONS.len() - 1);
+    }
+}
@@ -0,0 +1,321 @@
+//! Dark launch of a new database backend (`DATABASE_MIRROR_URL`).
+//!
+//! [`MirrorStorage`] serves every read from the primary and applies every
+//! write to the primary first and then, if it succeeded, queues it for the
+//! secondary. Queued writes are applied in order by a background task, so a
+//! slow or unreachable mirror adds no latency to users; its failures are
+//! logged and counted, and writes arriving while the queue is full are
+//! dropped and counted. A sample of link lookups is repeated on the
+//! secondary in the background and differences are logged, which shows
+//! whether the new backend would have answered the same.
+//!
+//! Row ids differ between the two databases. Writes that name a row by id
+//! are translated where the meaning survives (restoring a history entry
+//! sets the same destination on the secondary) and kept to the primary
+//! otherwise (one batch of the malformed `created_by` patch).
+
+mod queue;
+mod storage;
+
+pub use queue::MIRROR_QUEUE_CAPACITY;
+
+use serde::Serialize;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::models::ShortenedUrl;
+use crate::storage::Storage;
+use queue::MirrorJob;
+
+/// Counters of what the mirror did besides serving the primary.
+#[derive(Debug, Default)]
+struct MirrorCounters {
+    secondary_failures: AtomicU64,
+    dropped_writes: AtomicU64,
+    comparisons: AtomicU64,
+    mismatches: AtomicU64,
+}
+
+/// Snapshot of [`MirrorStorage`] activity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MirrorStats {
+    /// Writes (and comparison reads) the secondary failed
+    pub secondary_failures: u64,
+    /// Writes never applied to the secondary because its queue was full
+    pub dropped_writes: u64,
+    /// Sampled lookups compared against the secondary
+    pub comparisons: u64,
+    /// Compared lookups whose answers differed
+    pub mismatches: u64,
+}
+
+pub struct MirrorStorage {
+    primary: Arc<dyn Storage>,
+    secondary: Arc<dyn Storage>,
+    compare_sample_rate: f64,
+    counters: Arc<MirrorCounters>,
+    writes: mpsc::Sender<MirrorJob>,
+}
+
+impl MirrorStorage {
+    /// Mirror writes of `primary` to `secondary`, comparing the share
+    /// `compare_sample_rate` (0.0 to 1.0) of link lookups. Must be called
+    /// within a Tokio runtime, which runs the secondary's write queue.
+    pub fn new(
+        primary: Arc<dyn Storage>,
+        secondary: Arc<dyn Storage>,
+        compare_sample_rate: f64,
+    ) -> Self {
+        let counters = Arc::<MirrorCounters>::default();
+        Self {
+            primary,
+            secondary,
+            compare_sample_rate: compare_sample_rate.clamp(0.0, 1.0),
+            writes: queue::spawn_writer(Arc::clone(&counters)),
+            counters,
+        }
+    }
+
+    pub fn stats(&self) -> MirrorStats {
+        MirrorStats {
+            secondary_failures: self.counters.secondary_failures.load(Ordering::Relaxed),
+            dropped_writes: self.counters.dropped_writes.load(Ordering::Relaxed),
+            comparisons: self.counters.comparisons.load(Ordering::Relaxed),
+            mismatches: self.counters.mismatches.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Wait until every write queued so far has been applied to (or has
+    /// failed on) the secondary. Call before exiting so none are lost.
+    pub async fn drain(&self) {
+        let (done, drained) = oneshot::channel();
+        if self.writes.send(MirrorJob::Drained(done)).await.is_ok() {
+            let _ = drained.await;
+        }
+    }
+
+    /// Queue the secondary's copy of a write the primary accepted. `write`
+    /// runs later, on the write queue, so it must own its arguments.
+    fn mirror<F, Fut, T, E>(&self, operation: &'static str, write: F)
+    where
+        F: FnOnce(Arc<dyn Storage>) -> Fut,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+        E: Display,
+    {
+        let job = MirrorJob::write(operation, write(Arc::clone(&self.secondary)));
+        match self.writes.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.counters.dropped_writes.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    operation,
+                    capacity = MIRROR_QUEUE_CAPACITY,
+                    "Mirror write queue is full; the secondary database misses this write"
+                );
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.record_secondary_failure(operation, &"mirror write queue stopped");
+            }
+        }
+    }
+
+    /// Log and count a failed secondary operation; the caller's result stands.
+    fn record_secondary_failure(&self, operation: &'static str, error: &dyn Display) {
+        self.counters
+            .secondary_failures
+            .fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(operation, %error, "Mirror write failed on the secondary database");
+    }
+
+    /// Look `short_code` up on the secondary in the background, if sampled,
+    /// and log when it differs from what the primary returned.
+    fn compare_link(&self, short_code: &str, primary: Option<&Arc<ShortenedUrl>>) {
+        if self.compare_sample_rate <= 0.0 || rand::random::<f64>() >= self.compare_sample_rate {
+            return;
+        }
+        let secondary = Arc::clone(&self.secondary);
+        let counters = Arc::clone(&self.counters);
+        let short_code = short_code.to_string();
+        let primary = primary.cloned();
+        tokio::spawn(async move {
+            counters.comparisons.fetch_add(1, Ordering::Relaxed);
+            match secondary.get_authoritative(&short_code).await {
+                Ok(mirrored) => {
+                    if let Some(field) = link_difference(primary.as_deref(), mirrored.as_deref()) {
+                        counters.mismatches.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            short_code,
+                            field,
+                            "Mirror lookup differs from the primary database"
+                        );
+                    }
+                }
+                Err(error) => {
+                    counters.secondary_failures.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(short_code, %error, "Mirror lookup failed on the secondary database");
+                }
+            }
+        });
+    }
+}
+
+/// The first field in which the secondary's copy of a link differs from the
+/// primary's, or `None` when they agree. Ids, creation times and click
+/// counts are not compared: they differ by design or lag behind the flush.
+pub fn link_difference(
+    primary: Option<&ShortenedUrl>,
+    secondary: Option<&ShortenedUrl>,
+) -> Option<&'static str> {
+    let (primary, secondary) = match (primary, secondary) {
+        (None, None) => return None,
+        (Some(primary), Some(secondary)) => (primary, secondary),
+        _ => return Some("existence"),
+    };
+    if primary.original_url != secondary.original_url {
+        Some("original_url")
+    } else if primary.is_active != secondary.is_active {
+        Some("is_active")
+    } else if primary.created_by != secondary.created_by {
+        Some("created_by")
+    } else if primary.created_by_auth_method != secondary.created_by_auth_method {
+        Some("created_by_auth_method")
+    } else if primary.alias_of != secondary.alias_of {
+        Some("alias_of")
+    } else if primary.reserved_until != secondary.reserved_until {
+        Some("reserved_until")
+    } else if primary.options != secondary.options {
+        Some("options")
+    } else {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::models::{CreatedVia, LinkOptions};
+    use crate::storage::SqliteStorage;
+
+    async fn sqlite() -> Arc<SqliteStorage> {
+        let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+        storage.init().await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn writes_reach_both_and_reads_only_the_primary() {
+        let primary = sqlite().await;
+        let secondary = sqlite().await;
+        let mirror = MirrorStorage::new(primary.clone(), secondary.clone(), 0.0);
+
+        mirror
+            .create_with_code("docs", "https://example.com/docs", Some("alice"))
+            .await
+            .unwrap();
+        mirror.increment_clicks("docs", 3).await.unwrap();
+        mirror.deactivate("docs").await.unwrap();
+        mirror.drain().await;
+        for storage in [&primary, &secondary] {
+            let url = storage.get_authoritative("docs").await.unwrap().unwrap();
+            assert_eq!((url.clicks, url.is_active), (3, false));
+        }
+
+        secondary
+            .create_with_code("only-secondary", "https://example.com", None)
+            .await
+            .unwrap();
+        assert!(mirror.get("only-secondary").await.unwrap().is_none());
+        assert_eq!(mirror.stats(), MirrorStats::default());
+    }
+
+    #[tokio::test]
+    async fn secondary_failures_are_counted_not_returned() {
+        let primary = sqlite().await;
+        let secondary = sqlite().await;
+        secondary.pool.close().await;
+        let mirror = MirrorStorage::new(primary.clone(), secondary, 0.0);
+
+        mirror
+            .create_with_code("docs", "https://example.com/docs", None)
+            .await
+            .unwrap();
+        mirror
+            .update_url("docs", "https://example.com/v2", None)
+            .await
+            .unwrap();
+        mirror.drain().await;
+
+        assert_eq!(mirror.stats().secondary_failures, 2);
+        let url = primary.get_authoritative("docs").await.unwrap().unwrap();
+        assert_eq!(url.original_url, "https://example.com/v2");
+    }
+
+    #[tokio::test]
+    async fn a_stalled_secondary_does_not_delay_writes() {
+        let primary = sqlite().await;
+        let secondary = sqlite().await;
+        let mirror = MirrorStorage::new(primary.clone(), secondary.clone(), 0.0);
+
+        // Hold the secondary's only write connection
+        let held = secondary.pool.acquire().await.unwrap();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            mirror.create_with_code("docs", "https://example.com/docs", None),
+        )
+        .await
+        .expect("write waited for the secondary")
+        .unwrap();
+        assert!(primary.get_authoritative("docs").await.unwrap().is_some());
+
+        drop(held);
+        mirror.drain().await;
+        assert!(secondary.get_authoritative("docs").await.unwrap().is_some());
+        assert_eq!(mirror.stats(), MirrorStats::default());
+    }
+
+    #[test]
+    fn links_are_compared_on_what_a_redirect_uses() {
+        let link = |original_url: &str, clicks| ShortenedUrl {
+            id: clicks,
+            short_code: "docs".to_string(),
+            original_url: original_url.to_string(),
+            created_at: 0,
+            created_by: None,
+            created_by_auth_method: None,
+            clicks,
+            is_active: true,
+            reserved_until: None,
+            alias_of: None,
+            title: None,
+            created_via: CreatedVia::Api,
+            last_visited_at: None,
+            updated_at: 0,
+            options: LinkOptions::default(),
+            campaign_id: None,
+        };
+        let primary = link("https://example.com", 1);
+
+        assert_eq!(link_difference(None, None), None);
+        assert_eq!(link_difference(Some(&primary), None), Some("existence"));
+        assert_eq!(
+            link_difference(Some(&primary), Some(&link("https://example.com", 7))),
+            None
+        );
+        assert_eq!(
+            link_difference(Some(&primary), Some(&link("https://example.org", 1))),
+            Some("original_url")
+        );
+        let inactive = ShortenedUrl {
+            is_active: false,
+            ..link("https://example.com", 1)
+        };
+        assert_eq!(
+            link_difference(Some(&primary), Some(&inactive)),
+            Some("is_active")
+        );
+    }
+}
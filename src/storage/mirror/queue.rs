@@ -0,0 +1,67 @@
+//! Ordered background application of mirrored writes.
+//!
+//! Writes are queued once the primary has accepted them and applied to the
+//! secondary one at a time, in the order they were queued, by a single task.
+//! The queue is bounded: while a slow or unreachable secondary keeps it full,
+//! further writes are dropped and counted rather than held in memory.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+use super::MirrorCounters;
+
+/// Mirrored writes that may wait for the secondary before new ones are dropped.
+pub const MIRROR_QUEUE_CAPACITY: usize = 10_000;
+
+type SecondaryWrite = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+pub(super) enum MirrorJob {
+    Write {
+        operation: &'static str,
+        write: SecondaryWrite,
+    },
+    /// Answered once every job queued before it has been applied.
+    Drained(oneshot::Sender<()>),
+}
+
+impl MirrorJob {
+    pub(super) fn write<T, E: std::fmt::Display>(
+        operation: &'static str,
+        write: impl Future<Output = Result<T, E>> + Send + 'static,
+    ) -> Self {
+        Self::Write {
+            operation,
+            write: Box::pin(
+                async move { write.await.map(|_| ()).map_err(|error| error.to_string()) },
+            ),
+        }
+    }
+}
+
+/// Spawn the task that applies queued writes to the secondary.
+pub(super) fn spawn_writer(counters: Arc<MirrorCounters>) -> mpsc::Sender<MirrorJob> {
+    let (sender, mut receiver) = mpsc::channel(MIRROR_QUEUE_CAPACITY);
+    tokio::spawn(async move {
+        while let Some(job) = receiver.recv().await {
+            match job {
+                MirrorJob::Write { operation, write } => {
+                    if let Err(error) = write.await {
+                        counters.secondary_failures.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(
+                            operation,
+                            %error,
+                            "Mirror write failed on the secondary database"
+                        );
+                    }
+                }
+                MirrorJob::Drained(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    });
+    sender
+}
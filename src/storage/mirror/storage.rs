@@ -0,0 +1,1010 @@
+//! [`Storage`] for [`MirrorStorage`]: reads from the primary, writes to the
+//! primary and then, through the write queue, to the secondary.
+//!
+//! Queued writes own their arguments because they run after the call that
+//! made them has returned.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::MirrorStorage;
+use crate::analytics::{
+    AnalyticsAggregate, AnalyticsEntry, AnalyticsExportScope, AnalyticsGroupBy, AnalyticsRollup,
+};
+use crate::models::{
+    ActivityDay, AuditEntry, Campaign, CampaignStats, ClickHistoryEntry, CreatedVia,
+    DestinationHostLink, DestinationHostSummary, InstanceStatsDay, LinkOptions, ModerationEntry,
+    ModerationStatus, ShortenedUrl, UrlHistoryEntry, UserAccount, UserLinkCounts, UserUsageHour,
+    UserUsageTotal,
+};
+use crate::storage::cached::CacheStats;
+use crate::storage::copy::{AdminRecord, RowCounts, UserRecord};
+use crate::storage::{
+    ClickIncrement, HardDeleteReport, MalformedPatchBatch, OrphanCounts, PoolStats, SearchParams,
+    SearchResult, ServerLease, Storage, StorageResult, VerifyReport,
+};
+
+#[async_trait]
+impl Storage for MirrorStorage {
+    /// Awaited on both: the secondary's tables must exist before writes are
+    /// queued for it. A secondary failure is still only logged.
+    async fn ensure_schema(&self) -> Result<()> {
+        self.primary.ensure_schema().await?;
+        if let Err(error) = self.secondary.ensure_schema().await {
+            self.record_secondary_failure("ensure_schema", &error);
+        }
+        Ok(())
+    }
+
+    /// The primary only: the secondary never answers reads.
+    async fn verify_schema(&self) -> Result<()> {
+        self.primary.verify_schema().await
+    }
+
+    async fn create_with_code_via(
+        &self,
+        short_code: &str,
+        original_url: &str,
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        created_via: CreatedVia,
+        options: &LinkOptions,
+    ) -> StorageResult<Arc<ShortenedUrl>> {
+        let created = self
+            .primary
+            .create_with_code_via(
+                short_code,
+                original_url,
+                created_by,
+                created_by_auth_method,
+                created_via,
+                options,
+            )
+            .await?;
+        let (code, url) = (short_code.to_owned(), original_url.to_owned());
+        let created_by = created_by.map(str::to_owned);
+        let auth_method = created_by_auth_method.map(str::to_owned);
+        let options = options.clone();
+        self.mirror("create_with_code_via", move |secondary| async move {
+            secondary
+                .create_with_code_via(
+                    &code,
+                    &url,
+                    created_by.as_deref(),
+                    auth_method.as_deref(),
+                    created_via,
+                    &options,
+                )
+                .await
+        });
+        Ok(created)
+    }
+
+    async fn get(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        let url = self.primary.get(short_code).await?;
+        self.compare_link(short_code, url.as_ref());
+        Ok(url)
+    }
+
+    async fn get_authoritative(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        let url = self.primary.get_authoritative(short_code).await?;
+        self.compare_link(short_code, url.as_ref());
+        Ok(url)
+    }
+
+    async fn get_many(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary.get_many(short_codes).await
+    }
+
+    async fn deactivate(&self, short_code: &str) -> Result<bool> {
+        let deactivated = self.primary.deactivate(short_code).await?;
+        let code = short_code.to_owned();
+        self.mirror("deactivate", move |secondary| async move {
+            secondary.deactivate(&code).await
+        });
+        Ok(deactivated)
+    }
+
+    async fn set_title_if_missing(&self, short_code: &str, title: &str) -> Result<bool> {
+        let written = self.primary.set_title_if_missing(short_code, title).await?;
+        let (code, title) = (short_code.to_owned(), title.to_owned());
+        self.mirror("set_title_if_missing", move |secondary| async move {
+            secondary.set_title_if_missing(&code, &title).await
+        });
+        Ok(written)
+    }
+
+    async fn set_link_options(&self, short_code: &str, options: &LinkOptions) -> Result<bool> {
+        let written = self.primary.set_link_options(short_code, options).await?;
+        let (code, options) = (short_code.to_owned(), options.clone());
+        self.mirror("set_link_options", move |secondary| async move {
+            secondary.set_link_options(&code, &options).await
+        });
+        Ok(written)
+    }
+
+    async fn reactivate(&self, short_code: &str) -> Result<bool> {
+        let reactivated = self.primary.reactivate(short_code).await?;
+        let code = short_code.to_owned();
+        self.mirror("reactivate", move |secondary| async move {
+            secondary.reactivate(&code).await
+        });
+        Ok(reactivated)
+    }
+
+    async fn update_url(
+        &self,
+        short_code: &str,
+        new_url: &str,
+        updated_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        let updated = self
+            .primary
+            .update_url(short_code, new_url, updated_by)
+            .await?;
+        let (code, url) = (short_code.to_owned(), new_url.to_owned());
+        let updated_by = updated_by.map(str::to_owned);
+        self.mirror("update_url", move |secondary| async move {
+            secondary
+                .update_url(&code, &url, updated_by.as_deref())
+                .await
+        });
+        Ok(updated)
+    }
+
+    async fn reserve_codes(
+        &self,
+        short_codes: &[String],
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        reserved_until: i64,
+    ) -> StorageResult<Vec<Arc<ShortenedUrl>>> {
+        let reserved = self
+            .primary
+            .reserve_codes(
+                short_codes,
+                created_by,
+                created_by_auth_method,
+                reserved_until,
+            )
+            .await?;
+        let codes = short_codes.to_vec();
+        let created_by = created_by.map(str::to_owned);
+        let auth_method = created_by_auth_method.map(str::to_owned);
+        self.mirror("reserve_codes", move |secondary| async move {
+            secondary
+                .reserve_codes(
+                    &codes,
+                    created_by.as_deref(),
+                    auth_method.as_deref(),
+                    reserved_until,
+                )
+                .await
+        });
+        Ok(reserved)
+    }
+
+    async fn expire_reservations(&self, now: i64) -> Result<i64> {
+        let expired = self.primary.expire_reservations(now).await?;
+        self.mirror("expire_reservations", move |secondary| async move {
+            secondary.expire_reservations(now).await
+        });
+        Ok(expired)
+    }
+
+    async fn get_url_history(&self, short_code: &str) -> Result<Vec<UrlHistoryEntry>> {
+        self.primary.get_url_history(short_code).await
+    }
+
+    async fn restore_url(
+        &self,
+        short_code: &str,
+        history_id: i64,
+        restored_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        let restored = self
+            .primary
+            .restore_url(short_code, history_id, restored_by)
+            .await?;
+        // History ids are the primary's; set the restored destination instead.
+        if let Some(url) = &restored {
+            let (code, url) = (short_code.to_owned(), url.original_url.clone());
+            let restored_by = restored_by.map(str::to_owned);
+            self.mirror("restore_url", move |secondary| async move {
+                secondary
+                    .update_url(&code, &url, restored_by.as_deref())
+                    .await
+            });
+        }
+        Ok(restored)
+    }
+
+    async fn rename_code(
+        &self,
+        short_code: &str,
+        new_code: &str,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        let renamed = self.primary.rename_code(short_code, new_code).await?;
+        let (code, new_code) = (short_code.to_owned(), new_code.to_owned());
+        self.mirror("rename_code", move |secondary| async move {
+            secondary.rename_code(&code, &new_code).await
+        });
+        Ok(renamed)
+    }
+
+    async fn hard_delete(
+        &self,
+        short_code: &str,
+        actor: Option<&str>,
+    ) -> StorageResult<Option<HardDeleteReport>> {
+        let deleted = self.primary.hard_delete(short_code, actor).await?;
+        let (code, actor) = (short_code.to_owned(), actor.map(str::to_owned));
+        self.mirror("hard_delete", move |secondary| async move {
+            secondary.hard_delete(&code, actor.as_deref()).await
+        });
+        Ok(deleted)
+    }
+
+    async fn add_alias(
+        &self,
+        short_code: &str,
+        alias_code: &str,
+        created_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        let alias = self
+            .primary
+            .add_alias(short_code, alias_code, created_by)
+            .await?;
+        let (code, alias_code) = (short_code.to_owned(), alias_code.to_owned());
+        let created_by = created_by.map(str::to_owned);
+        self.mirror("add_alias", move |secondary| async move {
+            secondary
+                .add_alias(&code, &alias_code, created_by.as_deref())
+                .await
+        });
+        Ok(alias)
+    }
+
+    async fn get_aliases(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary.get_aliases(short_codes).await
+    }
+
+    async fn increment_clicks(&self, short_code: &str, amount: u64) -> Result<()> {
+        self.primary.increment_clicks(short_code, amount).await?;
+        let code = short_code.to_owned();
+        self.mirror("increment_clicks", move |secondary| async move {
+            secondary.increment_clicks(&code, amount).await
+        });
+        Ok(())
+    }
+
+    async fn increment_clicks_batch(&self, increments: &[ClickIncrement]) -> Result<()> {
+        self.primary.increment_clicks_batch(increments).await?;
+        let increments = increments.to_vec();
+        self.mirror("increment_clicks_batch", move |secondary| async move {
+            secondary.increment_clicks_batch(&increments).await
+        });
+        Ok(())
+    }
+
+    async fn list_with_cursor(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary
+            .list_with_cursor(limit, cursor, is_admin, user_id)
+            .await
+    }
+
+    async fn list_by_last_visit(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+        unused_since: Option<i64>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary
+            .list_by_last_visit(limit, cursor, unused_since, is_admin, user_id)
+            .await
+    }
+
+    async fn upsert_user(
+        &self,
+        user_id: &str,
+        email: Option<&str>,
+        auth_method: &str,
+    ) -> Result<()> {
+        self.primary
+            .upsert_user(user_id, email, auth_method)
+            .await?;
+        let (user_id, auth_method) = (user_id.to_owned(), auth_method.to_owned());
+        let email = email.map(str::to_owned);
+        self.mirror("upsert_user", move |secondary| async move {
+            secondary
+                .upsert_user(&user_id, email.as_deref(), &auth_method)
+                .await
+        });
+        Ok(())
+    }
+
+    async fn is_manual_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        self.primary.is_manual_admin(user_id, auth_method).await
+    }
+
+    async fn promote_to_admin(&self, user_id: &str, auth_method: &str) -> Result<()> {
+        self.primary.promote_to_admin(user_id, auth_method).await?;
+        let (user_id, auth_method) = (user_id.to_owned(), auth_method.to_owned());
+        self.mirror("promote_to_admin", move |secondary| async move {
+            secondary.promote_to_admin(&user_id, &auth_method).await
+        });
+        Ok(())
+    }
+
+    async fn demote_from_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        let demoted = self.primary.demote_from_admin(user_id, auth_method).await?;
+        let (user_id, auth_method) = (user_id.to_owned(), auth_method.to_owned());
+        self.mirror("demote_from_admin", move |secondary| async move {
+            secondary.demote_from_admin(&user_id, &auth_method).await
+        });
+        Ok(demoted)
+    }
+
+    async fn list_manual_admins(&self) -> Result<Vec<(String, String, String)>> {
+        self.primary.list_manual_admins().await
+    }
+
+    async fn patch_created_by(
+        &self,
+        short_code: &str,
+        new_created_by: &str,
+        auth_method: Option<&str>,
+    ) -> Result<bool> {
+        let patched = self
+            .primary
+            .patch_created_by(short_code, new_created_by, auth_method)
+            .await?;
+        let (code, new_created_by) = (short_code.to_owned(), new_created_by.to_owned());
+        let auth_method = auth_method.map(str::to_owned);
+        self.mirror("patch_created_by", move |secondary| async move {
+            secondary
+                .patch_created_by(&code, &new_created_by, auth_method.as_deref())
+                .await
+        });
+        Ok(patched)
+    }
+
+    async fn patch_all_malformed_created_by_with_progress(
+        &self,
+        new_created_by: &str,
+        on_batch: &mut (dyn FnMut(i64) + Send),
+    ) -> Result<i64> {
+        let patched = self
+            .primary
+            .patch_all_malformed_created_by_with_progress(new_created_by, on_batch)
+            .await?;
+        let new_created_by = new_created_by.to_owned();
+        self.mirror(
+            "patch_all_malformed_created_by",
+            move |secondary| async move {
+                secondary
+                    .patch_all_malformed_created_by(&new_created_by)
+                    .await
+            },
+        );
+        Ok(patched)
+    }
+
+    /// Primary only: `after_id` is a primary row id. Patching everything
+    /// through `patch_all_malformed_created_by` patches the secondary too.
+    async fn patch_malformed_created_by_batch(
+        &self,
+        new_created_by: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Option<MalformedPatchBatch>> {
+        self.primary
+            .patch_malformed_created_by_batch(new_created_by, after_id, limit)
+            .await
+    }
+
+    async fn count_malformed_created_by(&self) -> Result<i64> {
+        self.primary.count_malformed_created_by().await
+    }
+
+    async fn sample_malformed_created_by(&self, limit: i64) -> Result<Vec<String>> {
+        self.primary.sample_malformed_created_by(limit).await
+    }
+
+    async fn user_exists(&self, user_id: &str) -> Result<bool> {
+        self.primary.user_exists(user_id).await
+    }
+
+    async fn record_audit_entry(
+        &self,
+        action: &str,
+        short_code: &str,
+        actor: Option<&str>,
+        effective_user: &str,
+    ) -> Result<()> {
+        self.primary
+            .record_audit_entry(action, short_code, actor, effective_user)
+            .await?;
+        let (action, code) = (action.to_owned(), short_code.to_owned());
+        let (actor, effective_user) = (actor.map(str::to_owned), effective_user.to_owned());
+        self.mirror("record_audit_entry", move |secondary| async move {
+            secondary
+                .record_audit_entry(&action, &code, actor.as_deref(), &effective_user)
+                .await
+        });
+        Ok(())
+    }
+
+    async fn get_audit_log(&self, short_code: &str) -> Result<Vec<AuditEntry>> {
+        self.primary.get_audit_log(short_code).await
+    }
+
+    async fn submit_for_moderation(
+        &self,
+        short_code: &str,
+        status: ModerationStatus,
+    ) -> Result<()> {
+        self.primary
+            .submit_for_moderation(short_code, status)
+            .await?;
+        let code = short_code.to_owned();
+        self.mirror("submit_for_moderation", move |secondary| async move {
+            secondary.submit_for_moderation(&code, status).await
+        });
+        Ok(())
+    }
+
+    async fn get_moderation(&self, short_code: &str) -> Result<Option<ModerationEntry>> {
+        self.primary.get_moderation(short_code).await
+    }
+
+    async fn list_moderation(
+        &self,
+        status: ModerationStatus,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ModerationEntry>> {
+        self.primary.list_moderation(status, limit, offset).await
+    }
+
+    async fn approve_link(&self, short_code: &str, decided_by: Option<&str>) -> Result<bool> {
+        let approved = self.primary.approve_link(short_code, decided_by).await?;
+        let (code, decided_by) = (short_code.to_owned(), decided_by.map(str::to_owned));
+        self.mirror("approve_link", move |secondary| async move {
+            secondary.approve_link(&code, decided_by.as_deref()).await
+        });
+        Ok(approved)
+    }
+
+    async fn reject_link(
+        &self,
+        short_code: &str,
+        reason: &str,
+        decided_by: Option<&str>,
+    ) -> Result<bool> {
+        let rejected = self
+            .primary
+            .reject_link(short_code, reason, decided_by)
+            .await?;
+        let (code, reason) = (short_code.to_owned(), reason.to_owned());
+        let decided_by = decided_by.map(str::to_owned);
+        self.mirror("reject_link", move |secondary| async move {
+            secondary
+                .reject_link(&code, &reason, decided_by.as_deref())
+                .await
+        });
+        Ok(rejected)
+    }
+
+    /// The secondary gets the campaign with the primary's id, so links
+    /// assigned to it later point at the same campaign on both.
+    async fn create_campaign(&self, name: &str, owner: Option<&str>) -> Result<Campaign> {
+        let campaign = self.primary.create_campaign(name, owner).await?;
+        let copy = campaign.clone();
+        self.mirror("create_campaign", move |secondary| async move {
+            secondary.import_campaigns(&[copy]).await
+        });
+        Ok(campaign)
+    }
+
+    async fn get_campaign(&self, id: i64) -> Result<Option<Campaign>> {
+        self.primary.get_campaign(id).await
+    }
+
+    async fn list_campaigns(&self, owner: Option<&str>) -> Result<Vec<Campaign>> {
+        self.primary.list_campaigns(owner).await
+    }
+
+    async fn rename_campaign(&self, id: i64, name: &str) -> Result<Option<Campaign>> {
+        let renamed = self.primary.rename_campaign(id, name).await?;
+        let name = name.to_owned();
+        self.mirror("rename_campaign", move |secondary| async move {
+            secondary.rename_campaign(id, &name).await
+        });
+        Ok(renamed)
+    }
+
+    async fn delete_campaign(&self, id: i64) -> Result<Option<Vec<String>>> {
+        let unassigned = self.primary.delete_campaign(id).await?;
+        self.mirror("delete_campaign", move |secondary| async move {
+            secondary.delete_campaign(id).await
+        });
+        Ok(unassigned)
+    }
+
+    async fn set_link_campaign(&self, short_code: &str, campaign_id: Option<i64>) -> Result<bool> {
+        let written = self
+            .primary
+            .set_link_campaign(short_code, campaign_id)
+            .await?;
+        let code = short_code.to_owned();
+        self.mirror("set_link_campaign", move |secondary| async move {
+            secondary.set_link_campaign(&code, campaign_id).await
+        });
+        Ok(written)
+    }
+
+    async fn campaign_stats(
+        &self,
+        id: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<CampaignStats> {
+        self.primary.campaign_stats(id, start_time, end_time).await
+    }
+
+    async fn list_all_users(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, String, String)>,
+        inactive_since: Option<i64>,
+    ) -> Result<Vec<UserAccount>> {
+        self.primary
+            .list_all_users(limit, cursor, inactive_since)
+            .await
+    }
+
+    async fn user_emails(
+        &self,
+        users: &[(String, String)],
+    ) -> Result<Vec<(String, String, String)>> {
+        self.primary.user_emails(users).await
+    }
+
+    async fn links_by_destination_host(
+        &self,
+        hosts: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        self.primary
+            .links_by_destination_host(hosts, limit, offset)
+            .await
+    }
+
+    async fn links_by_destination(
+        &self,
+        url: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        self.primary.links_by_destination(url, limit, offset).await
+    }
+
+    async fn destination_host_summary(
+        &self,
+        hosts: &[String],
+        min_links: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostSummary>> {
+        self.primary
+            .destination_host_summary(hosts, min_links, limit, offset)
+            .await
+    }
+
+    async fn list_user_links(
+        &self,
+        user_id: &str,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary.list_user_links(user_id, limit, cursor).await
+    }
+
+    async fn find_active_by_destination(
+        &self,
+        original_url: &str,
+        created_by: Option<&str>,
+    ) -> Result<Option<Arc<ShortenedUrl>>> {
+        self.primary
+            .find_active_by_destination(original_url, created_by)
+            .await
+    }
+
+    async fn bulk_deactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        let deactivated = self.primary.bulk_deactivate_user_links(user_id).await?;
+        let user_id = user_id.to_owned();
+        self.mirror("bulk_deactivate_user_links", move |secondary| async move {
+            secondary.bulk_deactivate_user_links(&user_id).await
+        });
+        Ok(deactivated)
+    }
+
+    async fn bulk_reactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        let reactivated = self.primary.bulk_reactivate_user_links(user_id).await?;
+        let user_id = user_id.to_owned();
+        self.mirror("bulk_reactivate_user_links", move |secondary| async move {
+            secondary.bulk_reactivate_user_links(&user_id).await
+        });
+        Ok(reactivated)
+    }
+
+    async fn user_accounts(&self, user_id: &str) -> Result<Vec<UserAccount>> {
+        self.primary.user_accounts(user_id).await
+    }
+
+    async fn user_link_counts(&self, user_id: &str) -> Result<UserLinkCounts> {
+        self.primary.user_link_counts(user_id).await
+    }
+
+    async fn count_user_links_by_state(&self, user_id: &str, is_active: bool) -> Result<i64> {
+        self.primary
+            .count_user_links_by_state(user_id, is_active)
+            .await
+    }
+
+    async fn sample_user_links_by_state(
+        &self,
+        user_id: &str,
+        is_active: bool,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        self.primary
+            .sample_user_links_by_state(user_id, is_active, limit)
+            .await
+    }
+
+    async fn upsert_analytics_batch(&self, records: Vec<AnalyticsRollup>) -> Result<()> {
+        self.primary.upsert_analytics_batch(records.clone()).await?;
+        self.mirror("upsert_analytics_batch", move |secondary| async move {
+            secondary.upsert_analytics_batch(records).await
+        });
+        Ok(())
+    }
+
+    async fn upsert_known_analytics_batch(&self, records: Vec<AnalyticsRollup>) -> Result<u64> {
+        let skipped = self
+            .primary
+            .upsert_known_analytics_batch(records.clone())
+            .await?;
+        self.mirror(
+            "upsert_known_analytics_batch",
+            move |secondary| async move { secondary.upsert_known_analytics_batch(records).await },
+        );
+        Ok(skipped)
+    }
+
+    async fn get_analytics(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AnalyticsEntry>> {
+        self.primary
+            .get_analytics(short_code, start_time, end_time, limit)
+            .await
+    }
+
+    async fn get_estimated_visits(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<i64> {
+        self.primary
+            .get_estimated_visits(short_code, start_time, end_time)
+            .await
+    }
+
+    async fn get_analytics_export_page(
+        &self,
+        scope: AnalyticsExportScope<'_>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<AnalyticsEntry>> {
+        self.primary
+            .get_analytics_export_page(scope, start_time, end_time, after, limit)
+            .await
+    }
+
+    async fn get_analytics_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        group_by: AnalyticsGroupBy,
+        limit: i64,
+    ) -> Result<Vec<AnalyticsAggregate>> {
+        self.primary
+            .get_analytics_aggregate(short_code, start_time, end_time, group_by, limit)
+            .await
+    }
+
+    async fn get_instance_ip_version_split(
+        &self,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<AnalyticsAggregate>> {
+        self.primary
+            .get_instance_ip_version_split(start_time, end_time)
+            .await
+    }
+
+    async fn get_analytics_daily_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        time_zone: chrono_tz::Tz,
+        limit: i64,
+    ) -> Result<Vec<AnalyticsAggregate>> {
+        self.primary
+            .get_analytics_daily_aggregate(short_code, start_time, end_time, time_zone, limit)
+            .await
+    }
+
+    async fn prune_analytics(
+        &self,
+        retention_days: i64,
+        drop_dimensions: &[String],
+    ) -> Result<(i64, i64)> {
+        let pruned = self
+            .primary
+            .prune_analytics(retention_days, drop_dimensions)
+            .await?;
+        let drop_dimensions = drop_dimensions.to_vec();
+        self.mirror("prune_analytics", move |secondary| async move {
+            secondary
+                .prune_analytics(retention_days, &drop_dimensions)
+                .await
+        });
+        Ok(pruned)
+    }
+
+    async fn analytics_complete_since(&self) -> Result<Option<i64>> {
+        self.primary.analytics_complete_since().await
+    }
+
+    async fn rollup_analytics_daily(&self, from: Option<i64>, until: i64) -> Result<i64> {
+        let rows = self.primary.rollup_analytics_daily(from, until).await?;
+        self.mirror("rollup_analytics_daily", move |secondary| async move {
+            secondary.rollup_analytics_daily(from, until).await
+        });
+        Ok(rows)
+    }
+
+    async fn analytics_daily_rolled_until(&self) -> Result<Option<i64>> {
+        self.primary.analytics_daily_rolled_until().await
+    }
+
+    async fn record_instance_stats(&self, day: i64) -> Result<InstanceStatsDay> {
+        let stats = self.primary.record_instance_stats(day).await?;
+        self.mirror("record_instance_stats", move |secondary| async move {
+            secondary.record_instance_stats(day).await
+        });
+        Ok(stats)
+    }
+
+    async fn instance_stats_history(&self, since: i64) -> Result<Vec<InstanceStatsDay>> {
+        self.primary.instance_stats_history(since).await
+    }
+
+    async fn activity_report(&self, since: i64, until: i64, top: i64) -> Result<Vec<ActivityDay>> {
+        self.primary.activity_report(since, until, top).await
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        self.primary.get_setting(key).await
+    }
+
+    async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.primary.set_setting(key, value).await?;
+        let (key, value) = (key.to_owned(), value.to_owned());
+        self.mirror("set_setting", move |secondary| async move {
+            secondary.set_setting(&key, &value).await
+        });
+        Ok(())
+    }
+
+    // Leases describe the servers on the primary; the mirror has none.
+    async fn renew_server_lease(&self, lease: &ServerLease) -> Result<()> {
+        self.primary.renew_server_lease(lease).await
+    }
+
+    async fn release_server_lease(&self, instance_id: &str) -> Result<()> {
+        self.primary.release_server_lease(instance_id).await
+    }
+
+    async fn active_server_leases(&self) -> Result<Vec<ServerLease>> {
+        self.primary.active_server_leases().await
+    }
+
+    // Nonces are redeemed where they are checked, on the primary.
+    async fn redeem_challenge_nonce(&self, nonce: &str, expires_at: i64) -> Result<bool> {
+        self.primary.redeem_challenge_nonce(nonce, expires_at).await
+    }
+
+    async fn record_user_usage(&self, hours: &[UserUsageHour]) -> Result<()> {
+        self.primary.record_user_usage(hours).await?;
+        let hours = hours.to_vec();
+        self.mirror("record_user_usage", move |secondary| async move {
+            secondary.record_user_usage(&hours).await
+        });
+        Ok(())
+    }
+
+    async fn user_usage(&self, user_id: &str, since: i64) -> Result<Vec<UserUsageHour>> {
+        self.primary.user_usage(user_id, since).await
+    }
+
+    async fn user_usage_totals(
+        &self,
+        user_ids: &[String],
+        since: i64,
+    ) -> Result<Vec<UserUsageTotal>> {
+        self.primary.user_usage_totals(user_ids, since).await
+    }
+
+    async fn get_click_history(
+        &self,
+        short_code: &str,
+        since: i64,
+        time_zone: chrono_tz::Tz,
+    ) -> Result<Vec<ClickHistoryEntry>> {
+        self.primary
+            .get_click_history(short_code, since, time_zone)
+            .await
+    }
+
+    async fn compact_click_history(&self, retention_days: i64) -> Result<(i64, i64)> {
+        let compacted = self.primary.compact_click_history(retention_days).await?;
+        self.mirror("compact_click_history", move |secondary| async move {
+            secondary.compact_click_history(retention_days).await
+        });
+        Ok(compacted)
+    }
+
+    async fn count_orphan_analytics(&self) -> Result<OrphanCounts> {
+        self.primary.count_orphan_analytics().await
+    }
+
+    async fn delete_orphan_analytics(&self) -> Result<OrphanCounts> {
+        let deleted = self.primary.delete_orphan_analytics().await?;
+        self.mirror("delete_orphan_analytics", |secondary| async move {
+            secondary.delete_orphan_analytics().await
+        });
+        Ok(deleted)
+    }
+
+    /// Reports on the primary; with `fix`, the secondary is repaired too.
+    async fn verify_database(&self, fix: bool) -> Result<VerifyReport> {
+        let report = self.primary.verify_database(fix).await?;
+        if fix {
+            self.mirror("verify_database", |secondary| async move {
+                secondary.verify_database(true).await
+            });
+        }
+        Ok(report)
+    }
+
+    async fn search(
+        &self,
+        params: &SearchParams,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<SearchResult> {
+        self.primary.search(params, is_admin, user_id).await
+    }
+
+    async fn export_urls(&self, after_id: i64, limit: i64) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.primary.export_urls(after_id, limit).await
+    }
+
+    async fn import_urls(&self, urls: &[Arc<ShortenedUrl>], keep_ids: bool) -> Result<u64> {
+        let inserted = self.primary.import_urls(urls, keep_ids).await?;
+        let urls = urls.to_vec();
+        self.mirror("import_urls", move |secondary| async move {
+            secondary.import_urls(&urls, keep_ids).await
+        });
+        Ok(inserted)
+    }
+
+    async fn export_users(
+        &self,
+        after: Option<&(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<UserRecord>> {
+        self.primary.export_users(after, limit).await
+    }
+
+    async fn import_users(&self, users: &[UserRecord]) -> Result<u64> {
+        let inserted = self.primary.import_users(users).await?;
+        let users = users.to_vec();
+        self.mirror("import_users", move |secondary| async move {
+            secondary.import_users(&users).await
+        });
+        Ok(inserted)
+    }
+
+    async fn export_admins(&self) -> Result<Vec<AdminRecord>> {
+        self.primary.export_admins().await
+    }
+
+    async fn import_admins(&self, admins: &[AdminRecord]) -> Result<u64> {
+        let inserted = self.primary.import_admins(admins).await?;
+        let admins = admins.to_vec();
+        self.mirror("import_admins", move |secondary| async move {
+            secondary.import_admins(&admins).await
+        });
+        Ok(inserted)
+    }
+
+    async fn export_campaigns(&self) -> Result<Vec<Campaign>> {
+        self.primary.export_campaigns().await
+    }
+
+    async fn import_campaigns(&self, campaigns: &[Campaign]) -> Result<u64> {
+        let inserted = self.primary.import_campaigns(campaigns).await?;
+        let campaigns = campaigns.to_vec();
+        self.mirror("import_campaigns", move |secondary| async move {
+            secondary.import_campaigns(&campaigns).await
+        });
+        Ok(inserted)
+    }
+
+    async fn export_analytics(
+        &self,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<AnalyticsEntry>> {
+        self.primary.export_analytics(after, limit).await
+    }
+
+    async fn import_analytics(&self, rows: &[AnalyticsEntry]) -> Result<u64> {
+        let inserted = self.primary.import_analytics(rows).await?;
+        let rows = rows.to_vec();
+        self.mirror("import_analytics", move |secondary| async move {
+            secondary.import_analytics(&rows).await
+        });
+        Ok(inserted)
+    }
+
+    async fn count_rows(&self) -> Result<RowCounts> {
+        self.primary.count_rows().await
+    }
+
+    fn pool_stats(&self) -> Option<PoolStats> {
+        self.primary.pool_stats()
+    }
+
+    async fn probe_pool(&self) {
+        self.primary.probe_pool().await;
+    }
+
+    async fn cache_stats(&self) -> Option<CacheStats> {
+        self.primary.cache_stats().await
+    }
+}
@@ -1,12 +1,49 @@
 pub mod cached;
+mod campaigns;
+pub mod cancel;
+mod challenge_redemptions;
+pub mod copy;
+pub mod instance_stats;
+pub mod migrations;
+pub mod mirror;
+pub mod pool;
+#[cfg(feature = "postgres")]
 pub mod postgres;
+pub mod preview;
+pub mod relevance;
+pub mod reservations;
+mod server_leases;
+#[cfg(feature = "sqlite")]
 pub mod sqlite;
 pub mod trait_def;
+pub mod verify;
 
-pub use cached::{CachedStorage, RedirectLookup, RedirectTarget};
+pub use cached::{
+    CachePolicy, CacheStats, CachedStorage, LookupStats, RedirectLookup, RedirectTarget, StaleStats,
+};
+pub use cancel::{with_query_cancel, QueryCancel};
+pub use copy::{
+    copy_storage, AdminRecord, CopyReport, RowCounts, UserRecord, DEFAULT_COPY_BATCH_SIZE,
+};
+pub use instance_stats::spawn_instance_stats_recorder;
+pub use mirror::{MirrorStats, MirrorStorage};
+pub use pool::{
+    is_pool_timeout, spawn_pool_probe, PoolMonitor, PoolSettings, PoolStats, PoolUsage,
+};
+#[cfg(feature = "postgres")]
 pub use postgres::PostgresStorage;
+pub use preview::{BulkPreview, DRY_RUN_SAMPLE_SIZE};
+pub use reservations::spawn_reservation_sweep;
+pub use server_leases::{spawn_server_lease, ServerLease, LEASE_RENEW_INTERVAL, LEASE_TTL};
+#[cfg(feature = "sqlite")]
 pub use sqlite::SqliteStorage;
 pub use trait_def::{
-    ClickIncrement, LookupMetadata, LookupResult, OwnedClickError, SearchParams, SearchResult,
-    Storage, StorageError, StorageResult,
+    ClickIncrement, HardDeleteReport, LookupMetadata, LookupResult, MalformedPatchBatch,
+    OwnedClickError, SearchParams, SearchResult, SearchSort, Storage, StorageError, StorageResult,
+    MALFORMED_CREATED_BY, MALFORMED_PATCH_BATCH_SIZE, RELEVANCE_MAX_OFFSET, SEARCH_EXTRA_ROWS,
 };
+pub use verify::{CheckStatus, OrphanCounts, VerifyCheck, VerifyReport};
+
+/// Columns of `urls` that make up a `ShortenedUrl`, in its field order; the
+/// select list of every query on either backend that reads whole links.
+pub(crate) const URL_COLUMNS: &str = "id, short_code, original_url, created_at, created_by, clicks, is_active, reserved_until, alias_of, title, created_via, last_visited_at, updated_at, created_by_auth_method, options, campaign_id";
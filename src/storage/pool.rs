@@ -0,0 +1,304 @@
+//! Connection pool health shared by the SQL backends.
+//!
+//! sqlx reports how many connections are open and idle, but not how long
+//! callers wait to get one. [`PoolMonitor`] fills that gap by timing a probe
+//! acquisition every [`POOL_PROBE_INTERVAL`]: the probe waits in the same queue
+//! as requests, so its wait approximates what a request would have seen at that
+//! moment. Probes slower than the configured threshold are counted, and a
+//! warning is logged at most once per [`SLOW_ACQUIRE_WARN_INTERVAL`].
+
+use serde::Serialize;
+use sqlx::pool::PoolOptions;
+use sqlx::{Database, Pool};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use super::Storage;
+use crate::config::DatabaseConfig;
+
+/// How often [`spawn_pool_probe`] times a connection acquisition.
+pub const POOL_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Minimum time between two slow-acquire warnings.
+pub const SLOW_ACQUIRE_WARN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Sentinel stored before the first probe completes.
+const NO_PROBE: u64 = u64::MAX;
+
+/// Acquisition limits applied to a backend's connection pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSettings {
+    /// How long a caller may wait for a connection before failing with a pool timeout
+    pub acquire_timeout: Duration,
+    /// Waits above this are counted as slow and logged (rate-limited)
+    pub slow_acquire_threshold: Duration,
+}
+
+impl PoolSettings {
+    pub fn from_config(config: &DatabaseConfig) -> Self {
+        Self {
+            acquire_timeout: Duration::from_secs(config.acquire_timeout_secs),
+            slow_acquire_threshold: Duration::from_millis(config.slow_acquire_threshold_ms),
+        }
+    }
+
+    /// Apply the acquire timeout to sqlx pool options.
+    pub fn apply<DB: Database>(&self, options: PoolOptions<DB>) -> PoolOptions<DB> {
+        options
+            .acquire_timeout(self.acquire_timeout)
+            // sqlx logs every slow acquisition on its own. Raising its threshold
+            // to the timeout silences that in favor of the rate-limited warning.
+            .acquire_slow_threshold(self.acquire_timeout)
+    }
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            acquire_timeout: Duration::from_secs(DatabaseConfig::default_acquire_timeout_secs()),
+            slow_acquire_threshold: Duration::from_millis(
+                DatabaseConfig::default_slow_acquire_threshold_ms(),
+            ),
+        }
+    }
+}
+
+/// Connection counts of one pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PoolUsage {
+    pub max_connections: u32,
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+}
+
+impl PoolUsage {
+    pub fn of<DB: Database>(pool: &Pool<DB>) -> Self {
+        let size = pool.size();
+        let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX).min(size);
+        Self {
+            max_connections: pool.options().get_max_connections(),
+            size,
+            idle,
+            in_use: size - idle,
+        }
+    }
+}
+
+/// Point-in-time view of a connection pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PoolStats {
+    /// Connections of the pool serving reads (the only pool on most backends)
+    #[serde(flatten)]
+    pub usage: PoolUsage,
+    /// Separate single-connection pool for writes (SQLite only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_pool: Option<PoolUsage>,
+    pub acquire_timeout_ms: u64,
+    pub slow_acquire_threshold_ms: u64,
+    /// Probe acquisitions performed so far
+    pub probes: u64,
+    /// Probes that waited longer than the slow threshold (including timeouts)
+    pub slow_acquires: u64,
+    /// Probes that gave up after the acquire timeout
+    pub acquire_timeouts: u64,
+    /// Wait of the most recent successful probe, absent before the first one
+    pub last_acquire_wait_ms: Option<u64>,
+    pub max_acquire_wait_ms: u64,
+}
+
+/// Probe counters for one connection pool.
+#[derive(Debug)]
+pub struct PoolMonitor {
+    settings: PoolSettings,
+    probes: AtomicU64,
+    slow_acquires: AtomicU64,
+    acquire_timeouts: AtomicU64,
+    last_wait_ms: AtomicU64,
+    max_wait_ms: AtomicU64,
+    last_warning: Mutex<Option<Instant>>,
+}
+
+impl PoolMonitor {
+    pub fn new(settings: PoolSettings) -> Self {
+        Self {
+            settings,
+            probes: AtomicU64::new(0),
+            slow_acquires: AtomicU64::new(0),
+            acquire_timeouts: AtomicU64::new(0),
+            last_wait_ms: AtomicU64::new(NO_PROBE),
+            max_wait_ms: AtomicU64::new(0),
+            last_warning: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> PoolSettings {
+        self.settings
+    }
+
+    /// Time one acquisition from `pool` and record it.
+    pub async fn probe<DB: Database>(&self, pool: &Pool<DB>) {
+        let started = Instant::now();
+        match pool.acquire().await {
+            Ok(_connection) => self.record_probe(Some(started.elapsed())),
+            Err(sqlx::Error::PoolTimedOut) => self.record_probe(None),
+            Err(error) => tracing::warn!(%error, "database pool probe failed"),
+        }
+    }
+
+    /// Record one probe; `None` means the acquisition timed out.
+    pub fn record_probe(&self, wait: Option<Duration>) {
+        self.probes.fetch_add(1, Ordering::Relaxed);
+
+        let Some(wait) = wait else {
+            self.slow_acquires.fetch_add(1, Ordering::Relaxed);
+            self.acquire_timeouts.fetch_add(1, Ordering::Relaxed);
+            if self.warning_due(Instant::now()) {
+                tracing::warn!(
+                    timeout_ms = self.settings.acquire_timeout.as_millis() as u64,
+                    "Database pool exhausted: no connection became available before the acquire timeout"
+                );
+            }
+            return;
+        };
+
+        let wait_ms = wait.as_millis() as u64;
+        self.last_wait_ms.store(wait_ms, Ordering::Relaxed);
+        self.max_wait_ms.fetch_max(wait_ms, Ordering::Relaxed);
+
+        if wait > self.settings.slow_acquire_threshold {
+            self.slow_acquires.fetch_add(1, Ordering::Relaxed);
+            if self.warning_due(Instant::now()) {
+                tracing::warn!(
+                    wait_ms,
+                    threshold_ms = self.settings.slow_acquire_threshold.as_millis() as u64,
+                    "Slow database pool acquisition; consider raising DATABASE_MAX_CONNECTIONS"
+                );
+            }
+        }
+    }
+
+    /// Whether a warning may be logged at `now`, claiming the slot if so.
+    fn warning_due(&self, now: Instant) -> bool {
+        let mut last = self
+            .last_warning
+            .lock()
+            .expect("pool warning lock poisoned");
+        match *last {
+            Some(at) if now.duration_since(at) < SLOW_ACQUIRE_WARN_INTERVAL => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Combine the live state of `pool` with the probe counters.
+    pub fn stats<DB: Database>(&self, pool: &Pool<DB>) -> PoolStats {
+        let last_wait_ms = self.last_wait_ms.load(Ordering::Relaxed);
+
+        PoolStats {
+            usage: PoolUsage::of(pool),
+            write_pool: None,
+            acquire_timeout_ms: self.settings.acquire_timeout.as_millis() as u64,
+            slow_acquire_threshold_ms: self.settings.slow_acquire_threshold.as_millis() as u64,
+            probes: self.probes.load(Ordering::Relaxed),
+            slow_acquires: self.slow_acquires.load(Ordering::Relaxed),
+            acquire_timeouts: self.acquire_timeouts.load(Ordering::Relaxed),
+            last_acquire_wait_ms: (last_wait_ms != NO_PROBE).then_some(last_wait_ms),
+            max_acquire_wait_ms: self.max_wait_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Whether `error` was caused by the pool failing to hand out a connection
+/// within the acquire timeout.
+pub fn is_pool_timeout(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::PoolTimedOut)
+        )
+    })
+}
+
+/// Probe `storage`'s connection pool every [`POOL_PROBE_INTERVAL`].
+pub fn spawn_pool_probe(storage: Arc<dyn Storage>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(POOL_PROBE_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            storage.probe_pool().await;
+        }
+    })
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    fn monitor() -> PoolMonitor {
+        PoolMonitor::new(PoolSettings {
+            acquire_timeout: Duration::from_secs(1),
+            slow_acquire_threshold: Duration::from_millis(100),
+        })
+    }
+
+    #[tokio::test]
+    async fn probes_are_reflected_in_stats() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let monitor = monitor();
+        assert_eq!(monitor.stats(&pool).last_acquire_wait_ms, None);
+
+        monitor.probe(&pool).await;
+        // The probe's connection goes back to the pool on a background task
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while PoolUsage::of(&pool).in_use > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("probe connection was never released");
+        monitor.record_probe(Some(Duration::from_millis(250)));
+        monitor.record_probe(Some(Duration::from_millis(20)));
+        monitor.record_probe(None);
+
+        let stats = monitor.stats(&pool);
+        assert_eq!(stats.usage.max_connections, 2);
+        assert_eq!(stats.usage.in_use, 0);
+        assert_eq!(stats.write_pool, None);
+        assert_eq!(stats.probes, 4);
+        assert_eq!(stats.slow_acquires, 2);
+        assert_eq!(stats.acquire_timeouts, 1);
+        assert_eq!(stats.last_acquire_wait_ms, Some(20));
+        assert_eq!(stats.max_acquire_wait_ms, 250);
+    }
+
+    #[test]
+    fn warnings_are_rate_limited() {
+        let monitor = monitor();
+        let start = Instant::now();
+        assert!(monitor.warning_due(start));
+        assert!(!monitor.warning_due(start + Duration::from_secs(1)));
+        assert!(!monitor.warning_due(start + SLOW_ACQUIRE_WARN_INTERVAL / 2));
+        assert!(monitor.warning_due(start + SLOW_ACQUIRE_WARN_INTERVAL));
+    }
+
+    #[test]
+    fn pool_timeouts_are_detected_through_context() {
+        let error = anyhow::Error::new(sqlx::Error::PoolTimedOut).context("listing URLs");
+        assert!(is_pool_timeout(&error));
+        assert!(!is_pool_timeout(&anyhow::Error::new(
+            sqlx::Error::PoolClosed
+        )));
+        assert!(!is_pool_timeout(&anyhow::anyhow!("database is locked")));
+    }
+}
@@ -0,0 +1,229 @@
+//! Analytics aggregates by dimension for `PostgresStorage`, stitched from the
+//! daily rollup where it covers the range.
+
+use crate::analytics::daily::RollupSplit;
+use crate::analytics::AnalyticsGroupBy;
+use crate::storage::Storage;
+use anyhow::Result;
+use chrono_tz::Tz;
+
+use super::PostgresStorage;
+
+/// Build one analytics aggregate query at compile time. Both arguments must
+/// be string literals: `concat!` rejects anything else, so no runtime value
+/// can reach the SQL text. Values from the request are bound as parameters.
+macro_rules! aggregate_sql {
+    ($dimension:literal, $table:literal) => {
+        concat!(
+            "SELECT ",
+            $dimension,
+            " as dimension, CAST(SUM(visit_count) AS BIGINT) as visit_count FROM ",
+            $table,
+            " WHERE short_code = $1 AND time_bucket >= $2 AND time_bucket <= $3 AND ",
+            $dimension,
+            " IS NOT NULL GROUP BY ",
+            $dimension,
+            " ORDER BY visit_count DESC LIMIT $4"
+        )
+    };
+}
+
+const AGGREGATE_BY_COUNTRY: &str = aggregate_sql!("country_code", "analytics");
+
+/// `<dropped>` regions and cities are shown as-is rather than formatted.
+const AGGREGATE_BY_REGION: &str = aggregate_sql!(
+    "CASE WHEN region = '<dropped>' THEN region ELSE CONCAT(COALESCE(region, 'Unknown'), ', ', COALESCE(country_code, 'Unknown')) END",
+    "analytics"
+);
+
+const AGGREGATE_BY_CITY: &str = aggregate_sql!(
+    "CASE WHEN city = '<dropped>' THEN city ELSE CONCAT(COALESCE(city, 'Unknown'), ', ', COALESCE(region, 'Unknown'), ', ', COALESCE(country_code, 'Unknown')) END",
+    "analytics"
+);
+
+const AGGREGATE_BY_ASN: &str = aggregate_sql!("CAST(asn AS TEXT)", "analytics");
+
+const AGGREGATE_BY_HOUR: &str = aggregate_sql!("CAST(time_bucket AS TEXT)", "analytics");
+
+const AGGREGATE_BY_DAY: &str =
+    aggregate_sql!("CAST((time_bucket / 86400) * 86400 AS TEXT)", "analytics");
+
+/// Alias hits are counted in their own table, not per visitor dimension.
+const AGGREGATE_BY_ALIAS_USED: &str = aggregate_sql!("alias_code", "alias_analytics");
+
+/// Versions other than 4 and 6 (pruned rows) are reported as unknown.
+const AGGREGATE_BY_IP_VERSION: &str = aggregate_sql!(
+    "CASE ip_version WHEN 4 THEN 'IPv4' WHEN 6 THEN 'IPv6' ELSE 'unknown' END",
+    "analytics"
+);
+
+/// Visits to every link by IP version, for the instance-wide split.
+const INSTANCE_IP_VERSION_SPLIT: &str = "SELECT CASE ip_version WHEN 4 THEN 'IPv4' WHEN 6 THEN 'IPv6' ELSE 'unknown' END as dimension, CAST(SUM(visit_count) AS BIGINT) as visit_count FROM analytics WHERE time_bucket >= $1 AND time_bucket <= $2 GROUP BY 1 ORDER BY visit_count DESC";
+
+/// Like `aggregate_sql!`, but whole days inside the rollup watermark come
+/// from `analytics_daily` and the rest of the range from hourly analytics.
+/// Binds the short code, range start, `daily_from`, `daily_until`, range end
+/// and limit; the rollup part reuses the code and its day range.
+macro_rules! stitched_aggregate_sql {
+    ($hourly:literal, $daily:literal) => {
+        concat!(
+            "SELECT dimension, CAST(SUM(visit_count) AS BIGINT) as visit_count FROM (SELECT ",
+            $hourly,
+            " as dimension, visit_count FROM analytics WHERE short_code = $1 AND ((time_bucket >= $2 AND time_bucket < $3) OR (time_bucket >= $4 AND time_bucket <= $5)) UNION ALL SELECT ",
+            $daily,
+            " as dimension, visit_count FROM analytics_daily WHERE short_code = $1 AND day >= $3 AND day < $4) t WHERE dimension IS NOT NULL GROUP BY dimension ORDER BY visit_count DESC LIMIT $6"
+        )
+    };
+}
+
+const STITCHED_BY_COUNTRY: &str = stitched_aggregate_sql!("country_code", "country_code");
+
+const STITCHED_BY_DAY: &str = stitched_aggregate_sql!(
+    "CAST((time_bucket / 86400) * 86400 AS TEXT)",
+    "CAST(day AS TEXT)"
+);
+
+/// The stitched aggregate query for `group_by`, for the dimensions the daily
+/// rollup keeps.
+fn stitched_aggregate_query(group_by: AnalyticsGroupBy) -> Option<&'static str> {
+    match group_by {
+        AnalyticsGroupBy::Country => Some(STITCHED_BY_COUNTRY),
+        AnalyticsGroupBy::Day => Some(STITCHED_BY_DAY),
+        _ => None,
+    }
+}
+
+/// The aggregate query for `group_by`.
+///
+/// This is the only way `get_analytics_aggregate` gets its SQL: the
+/// dimension selects one of the constants above, and the `'static` return
+/// type keeps runtime strings out. New dimensions get a new constant here.
+pub(super) fn aggregate_query(group_by: AnalyticsGroupBy) -> &'static str {
+    match group_by {
+        AnalyticsGroupBy::Country => AGGREGATE_BY_COUNTRY,
+        AnalyticsGroupBy::Region => AGGREGATE_BY_REGION,
+        AnalyticsGroupBy::City => AGGREGATE_BY_CITY,
+        AnalyticsGroupBy::Asn => AGGREGATE_BY_ASN,
+        AnalyticsGroupBy::Hour => AGGREGATE_BY_HOUR,
+        AnalyticsGroupBy::Day => AGGREGATE_BY_DAY,
+        AnalyticsGroupBy::AliasUsed => AGGREGATE_BY_ALIAS_USED,
+        AnalyticsGroupBy::IpVersion => AGGREGATE_BY_IP_VERSION,
+    }
+}
+
+/// Run a `stitched_aggregate_sql!` query over `[start_time, end_time]`,
+/// or return `None` when the range holds no whole rolled-up day and the
+/// hourly query alone answers it.
+async fn stitched_aggregate(
+    storage: &PostgresStorage,
+    query: &'static str,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: i64,
+) -> Result<Option<Vec<crate::analytics::AnalyticsAggregate>>> {
+    let rolled_until = storage.analytics_daily_rolled_until().await?;
+    let Some(split) = RollupSplit::of(start_time, end_time, rolled_until) else {
+        return Ok(None);
+    };
+    let results = sqlx::query_as::<_, crate::analytics::AnalyticsAggregate>(query)
+        .bind(short_code)
+        .bind(start_time.unwrap_or(i64::MIN))
+        .bind(split.daily_from)
+        .bind(split.daily_until)
+        .bind(end_time.unwrap_or(i64::MAX))
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?;
+    Ok(Some(results))
+}
+
+pub(super) async fn get_analytics_aggregate(
+    storage: &PostgresStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    group_by: AnalyticsGroupBy,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+    if let Some(query) = stitched_aggregate_query(group_by) {
+        if let Some(results) =
+            stitched_aggregate(storage, query, short_code, start_time, end_time, limit).await?
+        {
+            return Ok(results);
+        }
+    }
+
+    // Open-ended ranges bind the extremes so one query covers every case.
+    let results =
+        sqlx::query_as::<_, crate::analytics::AnalyticsAggregate>(aggregate_query(group_by))
+            .bind(short_code)
+            .bind(start_time.unwrap_or(i64::MIN))
+            .bind(end_time.unwrap_or(i64::MAX))
+            .bind(limit)
+            .fetch_all(storage.pool.as_ref())
+            .await?;
+
+    Ok(results)
+}
+
+pub(super) async fn get_instance_ip_version_split(
+    storage: &PostgresStorage,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+    let results = sqlx::query_as(INSTANCE_IP_VERSION_SPLIT)
+        .bind(start_time.unwrap_or(i64::MIN))
+        .bind(end_time.unwrap_or(i64::MAX))
+        .fetch_all(storage.pool.as_ref())
+        .await?;
+    Ok(results)
+}
+
+pub(super) async fn get_analytics_daily_aggregate(
+    storage: &PostgresStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    time_zone: Tz,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+    // The rollup's days are UTC days
+    if time_zone == Tz::UTC {
+        if let Some(results) = stitched_aggregate(
+            storage,
+            STITCHED_BY_DAY,
+            short_code,
+            start_time,
+            end_time,
+            limit,
+        )
+        .await?
+        {
+            return Ok(results);
+        }
+    }
+
+    // Truncate in local time and convert the local midnight back, so DST
+    // transition days span 23 or 25 hours.
+    let results = sqlx::query_as::<_, crate::analytics::AnalyticsAggregate>(
+        r#"
+        SELECT CAST(EXTRACT(EPOCH FROM date_trunc('day', to_timestamp(time_bucket) AT TIME ZONE $4) AT TIME ZONE $4) AS BIGINT)::TEXT as dimension,
+               CAST(SUM(visit_count) AS BIGINT) as visit_count
+        FROM analytics
+        WHERE short_code = $1 AND time_bucket >= $2 AND time_bucket <= $3
+        GROUP BY 1
+        ORDER BY visit_count DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(short_code)
+    .bind(start_time.unwrap_or(i64::MIN))
+    .bind(end_time.unwrap_or(i64::MAX))
+    .bind(time_zone.name())
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(results)
+}
@@ -0,0 +1,161 @@
+//! Renames and aliases for `PostgresStorage`.
+
+use crate::destination::destination_host;
+use crate::models::ShortenedUrl;
+use crate::storage::{StorageError, StorageResult, URL_COLUMNS};
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+pub(super) async fn rename_code(
+    storage: &PostgresStorage,
+    short_code: &str,
+    new_code: &str,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    let mut tx = storage.pool.begin().await?;
+
+    // Lock the row so a concurrent rename of the same code waits.
+    let old = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = $1
+        FOR UPDATE
+        "#
+    ))
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(old) = old else {
+        return Ok(None);
+    };
+    if let Some(canonical) = &old.alias_of {
+        return Err(StorageError::InvalidInput(format!(
+            "'{}' is an alias of '{}'; rename '{}' instead",
+            short_code, canonical, canonical
+        )));
+    }
+
+    let renamed = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        INSERT INTO urls (short_code, original_url, created_at, updated_at, created_by, created_by_auth_method, is_active, reserved_until, created_via, options, campaign_id, dest_host, normalized_url)
+        VALUES ($1, $2, $3, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        ON CONFLICT (short_code) DO NOTHING
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(new_code)
+    .bind(&old.original_url)
+    .bind(created_at)
+    .bind(&old.created_by)
+    .bind(&old.created_by_auth_method)
+    .bind(old.is_active)
+    .bind(old.reserved_until)
+    .bind(old.created_via.as_str())
+    .bind(old.options.to_json())
+    .bind(old.campaign_id)
+    .bind(destination_host(&old.original_url))
+    .bind(storage.normalized_url(&old.original_url))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(StorageError::Conflict)?;
+
+    // Re-point earlier aliases too, so no alias ends up behind another.
+    sqlx::query(
+        "UPDATE urls SET alias_of = $1, updated_at = $3 WHERE short_code = $2 OR alias_of = $2",
+    )
+    .bind(new_code)
+    .bind(short_code)
+    .bind(created_at)
+    .execute(&mut *tx)
+    .await?;
+
+    // The old code now follows the new one's state, so a deactivated
+    // link's alias comes back with it when it is reactivated.
+    sqlx::query("UPDATE urls SET is_active = true WHERE short_code = $1")
+        .bind(short_code)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(Arc::new(renamed)))
+}
+
+pub(super) async fn add_alias(
+    storage: &PostgresStorage,
+    short_code: &str,
+    alias_code: &str,
+    created_by: Option<&str>,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    let mut tx = storage.pool.begin().await?;
+
+    // Lock the link so a concurrent rename cannot turn it into an alias
+    // before the new alias points at it.
+    let canonical = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = $1
+        FOR SHARE
+        "#
+    ))
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(canonical) = canonical else {
+        return Ok(None);
+    };
+    if let Some(target) = &canonical.alias_of {
+        return Err(StorageError::InvalidInput(format!(
+            "'{}' is an alias of '{}'; add the alias to '{}' instead",
+            short_code, target, target
+        )));
+    }
+
+    let alias = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        INSERT INTO urls (short_code, original_url, created_at, updated_at, created_by, is_active, alias_of, dest_host, normalized_url)
+        VALUES ($1, $2, $3, $3, $4, true, $5, $6, $7)
+        ON CONFLICT (short_code) DO NOTHING
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(alias_code)
+    .bind(&canonical.original_url)
+    .bind(created_at)
+    .bind(created_by)
+    .bind(short_code)
+    .bind(destination_host(&canonical.original_url))
+    .bind(storage.normalized_url(&canonical.original_url))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(StorageError::Conflict)?;
+
+    tx.commit().await?;
+
+    Ok(Some(Arc::new(alias)))
+}
+
+pub(super) async fn get_aliases(
+    storage: &PostgresStorage,
+    short_codes: &[String],
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let aliases = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE alias_of = ANY($1)
+        "#
+    ))
+    .bind(short_codes)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(aliases.into_iter().map(Arc::new).collect())
+}
@@ -0,0 +1,421 @@
+//! Analytics writes and reads for `PostgresStorage`.
+
+use crate::analytics::{AliasRollup, AnalyticsEstimate, AnalyticsExportScope, AnalyticsRollup};
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the hourly `analytics` table and the per-alias and estimated
+/// visit tables kept beside it.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Create analytics table for visitor IP analytics
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics (
+            id BIGSERIAL PRIMARY KEY,
+            short_code TEXT NOT NULL,
+            time_bucket BIGINT NOT NULL,
+            country_code TEXT,
+            region TEXT,
+            city TEXT,
+            asn BIGINT,
+            ip_version INTEGER NOT NULL,
+            visit_count BIGINT NOT NULL DEFAULT 0,
+            created_at BIGINT NOT NULL,
+            updated_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Index for analytics queries by short code
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_analytics_short_code ON analytics(short_code)")
+        .execute(pool)
+        .await?;
+
+    // Index for analytics queries by time bucket
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_analytics_time_bucket ON analytics(time_bucket DESC)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Composite index for short code and time range queries
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_analytics_short_code_time ON analytics(short_code, time_bucket DESC)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Visits that came through an alias, per alias and hour, for the
+    // `alias_used` analytics dimension
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alias_analytics (
+            id BIGSERIAL PRIMARY KEY,
+            short_code TEXT NOT NULL,
+            alias_code TEXT NOT NULL,
+            time_bucket BIGINT NOT NULL,
+            visit_count BIGINT NOT NULL DEFAULT 0,
+            created_at BIGINT NOT NULL,
+            updated_at BIGINT NOT NULL,
+            UNIQUE(short_code, alias_code, time_bucket)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Visits extrapolated from events kept by load sampling, per code
+    // and hour; they are also in `analytics.visit_count`
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics_estimates (
+            id BIGSERIAL PRIMARY KEY,
+            short_code TEXT NOT NULL,
+            time_bucket BIGINT NOT NULL,
+            estimated_visits BIGINT NOT NULL DEFAULT 0,
+            created_at BIGINT NOT NULL,
+            updated_at BIGINT NOT NULL,
+            UNIQUE(short_code, time_bucket)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Export pages are keyset-paginated on `(time_bucket, id)` so each page is an
+/// index range scan however deep the export goes.
+const EXPORT_PAGE_BY_LINK: &str = "SELECT a.id, a.short_code, a.time_bucket, a.country_code, a.region, a.city, a.asn, a.ip_version, a.visit_count, a.created_at, a.updated_at FROM analytics a WHERE a.short_code = $1 AND a.time_bucket >= $2 AND a.time_bucket <= $3 AND (a.time_bucket > $4 OR (a.time_bucket = $4 AND a.id > $5)) ORDER BY a.time_bucket, a.id LIMIT $6";
+
+const EXPORT_PAGE_BY_OWNER: &str = "SELECT a.id, a.short_code, a.time_bucket, a.country_code, a.region, a.city, a.asn, a.ip_version, a.visit_count, a.created_at, a.updated_at FROM analytics a JOIN urls u ON u.short_code = a.short_code WHERE u.created_by = $1 AND a.time_bucket >= $2 AND a.time_bucket <= $3 AND (a.time_bucket > $4 OR (a.time_bucket = $4 AND a.id > $5)) ORDER BY a.time_bucket, a.id LIMIT $6";
+
+/// Add per-alias visit counts split off an analytics batch. Counts for codes
+/// that no longer exist are dropped.
+async fn upsert_alias_rollups(
+    connection: &mut sqlx::PgConnection,
+    aliases: Vec<AliasRollup>,
+    now: i64,
+) -> Result<()> {
+    if aliases.is_empty() {
+        return Ok(());
+    }
+
+    let mut short_codes = Vec::with_capacity(aliases.len());
+    let mut alias_codes = Vec::with_capacity(aliases.len());
+    let mut time_buckets = Vec::with_capacity(aliases.len());
+    let mut visit_counts = Vec::with_capacity(aliases.len());
+    for alias in aliases {
+        short_codes.push(alias.short_code);
+        alias_codes.push(alias.alias_code);
+        time_buckets.push(alias.time_bucket);
+        visit_counts.push(alias.visit_count);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO alias_analytics (
+            short_code, alias_code, time_bucket, visit_count, created_at, updated_at
+        )
+        SELECT batch.*, $5, $5
+        FROM UNNEST($1::text[], $2::text[], $3::bigint[], $4::bigint[])
+            AS batch(short_code, alias_code, time_bucket, visit_count)
+        WHERE EXISTS (SELECT 1 FROM urls u WHERE u.short_code = batch.short_code)
+        ON CONFLICT(short_code, alias_code, time_bucket)
+        DO UPDATE SET
+            visit_count = alias_analytics.visit_count + EXCLUDED.visit_count,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(short_codes)
+    .bind(alias_codes)
+    .bind(time_buckets)
+    .bind(visit_counts)
+    .bind(now)
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+/// Add the estimated visits of an analytics batch. Estimates for codes that
+/// no longer exist are dropped.
+async fn upsert_analytics_estimates(
+    connection: &mut sqlx::PgConnection,
+    estimates: Vec<AnalyticsEstimate>,
+    now: i64,
+) -> Result<()> {
+    if estimates.is_empty() {
+        return Ok(());
+    }
+
+    let mut short_codes = Vec::with_capacity(estimates.len());
+    let mut time_buckets = Vec::with_capacity(estimates.len());
+    let mut estimated_visits = Vec::with_capacity(estimates.len());
+    for estimate in estimates {
+        short_codes.push(estimate.short_code);
+        time_buckets.push(estimate.time_bucket);
+        estimated_visits.push(estimate.estimated_visits);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO analytics_estimates (
+            short_code, time_bucket, estimated_visits, created_at, updated_at
+        )
+        SELECT batch.*, $4, $4
+        FROM UNNEST($1::text[], $2::bigint[], $3::bigint[])
+            AS batch(short_code, time_bucket, estimated_visits)
+        WHERE EXISTS (SELECT 1 FROM urls u WHERE u.short_code = batch.short_code)
+        ON CONFLICT(short_code, time_bucket)
+        DO UPDATE SET
+            estimated_visits = analytics_estimates.estimated_visits + EXCLUDED.estimated_visits,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(short_codes)
+    .bind(time_buckets)
+    .bind(estimated_visits)
+    .bind(now)
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn upsert_analytics_batch(
+    storage: &PostgresStorage,
+    records: Vec<AnalyticsRollup>,
+) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let now = storage.clock.now_epoch_secs();
+
+    let estimates = AnalyticsRollup::estimates(&records);
+    let (records, aliases) = AnalyticsRollup::split_aliases(records);
+    let mut short_codes = Vec::with_capacity(records.len());
+    let mut time_buckets = Vec::with_capacity(records.len());
+    let mut country_codes = Vec::with_capacity(records.len());
+    let mut regions = Vec::with_capacity(records.len());
+    let mut cities = Vec::with_capacity(records.len());
+    let mut asns = Vec::with_capacity(records.len());
+    let mut ip_versions = Vec::with_capacity(records.len());
+    let mut visit_counts = Vec::with_capacity(records.len());
+    for record in records {
+        short_codes.push(record.short_code);
+        time_buckets.push(record.time_bucket);
+        country_codes.push(record.country_code);
+        regions.push(record.region);
+        cities.push(record.city);
+        asns.push(record.asn);
+        ip_versions.push(record.ip_version.as_i32());
+        visit_counts.push(record.visit_count);
+    }
+
+    let mut transaction = storage.pool.begin().await?;
+    sqlx::query(
+        r#"
+        INSERT INTO analytics (
+            short_code, time_bucket, country_code, region, city, asn,
+            ip_version, visit_count, created_at, updated_at
+        )
+        SELECT batch.*, $9, $9
+        FROM UNNEST(
+            $1::text[], $2::bigint[], $3::text[], $4::text[],
+            $5::text[], $6::bigint[], $7::integer[], $8::bigint[]
+        ) AS batch(
+            short_code, time_bucket, country_code, region, city, asn,
+            ip_version, visit_count
+        )
+        ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+        DO UPDATE SET
+            visit_count = analytics.visit_count + EXCLUDED.visit_count,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(short_codes)
+    .bind(time_buckets)
+    .bind(country_codes)
+    .bind(regions)
+    .bind(cities)
+    .bind(asns)
+    .bind(ip_versions)
+    .bind(visit_counts)
+    .bind(now)
+    .execute(&mut *transaction)
+    .await?;
+    upsert_alias_rollups(&mut transaction, aliases, now).await?;
+    upsert_analytics_estimates(&mut transaction, estimates, now).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub(super) async fn upsert_known_analytics_batch(
+    storage: &PostgresStorage,
+    records: Vec<AnalyticsRollup>,
+) -> Result<u64> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let now = storage.clock.now_epoch_secs();
+
+    let estimates = AnalyticsRollup::estimates(&records);
+    let (records, aliases) = AnalyticsRollup::split_aliases(records);
+    let record_count = records.len() as u64;
+    let mut short_codes = Vec::with_capacity(records.len());
+    let mut time_buckets = Vec::with_capacity(records.len());
+    let mut country_codes = Vec::with_capacity(records.len());
+    let mut regions = Vec::with_capacity(records.len());
+    let mut cities = Vec::with_capacity(records.len());
+    let mut asns = Vec::with_capacity(records.len());
+    let mut ip_versions = Vec::with_capacity(records.len());
+    let mut visit_counts = Vec::with_capacity(records.len());
+    for record in records {
+        short_codes.push(record.short_code);
+        time_buckets.push(record.time_bucket);
+        country_codes.push(record.country_code);
+        regions.push(record.region);
+        cities.push(record.city);
+        asns.push(record.asn);
+        ip_versions.push(record.ip_version.as_i32());
+        visit_counts.push(record.visit_count);
+    }
+
+    let mut transaction = storage.pool.begin().await?;
+    let result = sqlx::query(
+        r#"
+        INSERT INTO analytics (
+            short_code, time_bucket, country_code, region, city, asn,
+            ip_version, visit_count, created_at, updated_at
+        )
+        SELECT batch.*, $9, $9
+        FROM UNNEST(
+            $1::text[], $2::bigint[], $3::text[], $4::text[],
+            $5::text[], $6::bigint[], $7::integer[], $8::bigint[]
+        ) AS batch(
+            short_code, time_bucket, country_code, region, city, asn,
+            ip_version, visit_count
+        )
+        WHERE EXISTS (SELECT 1 FROM urls u WHERE u.short_code = batch.short_code)
+        ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+        DO UPDATE SET
+            visit_count = analytics.visit_count + EXCLUDED.visit_count,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(short_codes)
+    .bind(time_buckets)
+    .bind(country_codes)
+    .bind(regions)
+    .bind(cities)
+    .bind(asns)
+    .bind(ip_versions)
+    .bind(visit_counts)
+    .bind(now)
+    .execute(&mut *transaction)
+    .await?;
+    upsert_alias_rollups(&mut transaction, aliases, now).await?;
+    upsert_analytics_estimates(&mut transaction, estimates, now).await?;
+    transaction.commit().await?;
+
+    Ok(record_count.saturating_sub(result.rows_affected()))
+}
+
+pub(super) async fn get_analytics(
+    storage: &PostgresStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+    let results = if let (Some(start), Some(end)) = (start_time, end_time) {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = $1 AND time_bucket >= $2 AND time_bucket <= $3 ORDER BY time_bucket DESC LIMIT $4"
+        )
+        .bind(short_code)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else if let Some(start) = start_time {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = $1 AND time_bucket >= $2 ORDER BY time_bucket DESC LIMIT $3"
+        )
+        .bind(short_code)
+        .bind(start)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else if let Some(end) = end_time {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = $1 AND time_bucket <= $2 ORDER BY time_bucket DESC LIMIT $3"
+        )
+        .bind(short_code)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = $1 ORDER BY time_bucket DESC LIMIT $2"
+        )
+        .bind(short_code)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    };
+
+    Ok(results)
+}
+
+pub(super) async fn get_estimated_visits(
+    storage: &PostgresStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<i64> {
+    let (estimated,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(estimated_visits), 0)::BIGINT FROM analytics_estimates WHERE short_code = $1 AND time_bucket >= $2 AND time_bucket <= $3",
+    )
+    .bind(short_code)
+    .bind(start_time.unwrap_or(i64::MIN))
+    .bind(end_time.unwrap_or(i64::MAX))
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+    Ok(estimated)
+}
+
+pub(super) async fn get_analytics_export_page(
+    storage: &PostgresStorage,
+    scope: AnalyticsExportScope<'_>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    after: Option<(i64, i64)>,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+    let (query, key) = match scope {
+        AnalyticsExportScope::Link(short_code) => (EXPORT_PAGE_BY_LINK, short_code),
+        AnalyticsExportScope::Owner(user_id) => (EXPORT_PAGE_BY_OWNER, user_id),
+    };
+    // The first page starts before every possible key.
+    let (after_bucket, after_id) = after.unwrap_or((i64::MIN, i64::MIN));
+    let results = sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(query)
+        .bind(key)
+        .bind(start_time.unwrap_or(i64::MIN))
+        .bind(end_time.unwrap_or(i64::MAX))
+        .bind(after_bucket)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?;
+
+    Ok(results)
+}
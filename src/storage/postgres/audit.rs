@@ -0,0 +1,77 @@
+//! The admin audit log for `PostgresStorage`.
+
+use crate::models::AuditEntry;
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the `audit_log` table.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Actions admins take on behalf of other users
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id BIGSERIAL PRIMARY KEY,
+            action TEXT NOT NULL,
+            short_code TEXT NOT NULL,
+            actor TEXT,
+            effective_user TEXT NOT NULL,
+            created_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_audit_log_short_code ON audit_log(short_code, created_at DESC, id DESC)",
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn record_audit_entry(
+    storage: &PostgresStorage,
+    action: &str,
+    short_code: &str,
+    actor: Option<&str>,
+    effective_user: &str,
+) -> Result<()> {
+    let created_at = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (action, short_code, actor, effective_user, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(action)
+    .bind(short_code)
+    .bind(actor)
+    .bind(effective_user)
+    .bind(created_at)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn get_audit_log(
+    storage: &PostgresStorage,
+    short_code: &str,
+) -> Result<Vec<AuditEntry>> {
+    let entries = sqlx::query_as::<_, AuditEntry>(
+        r#"
+        SELECT id, action, short_code, actor, effective_user, created_at
+        FROM audit_log
+        WHERE short_code = $1
+        ORDER BY created_at DESC, id DESC
+        "#,
+    )
+    .bind(short_code)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(entries)
+}
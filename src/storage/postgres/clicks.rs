@@ -0,0 +1,188 @@
+//! Click counts and hourly click history for `PostgresStorage`.
+
+use crate::models::ClickHistoryEntry;
+use crate::storage::ClickIncrement;
+use crate::timezone::hour_start;
+use anyhow::{anyhow, Result};
+use chrono_tz::Tz;
+use std::convert::TryFrom;
+
+use super::PostgresStorage;
+
+/// Create the `click_history` table.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Click counts per UTC hour, kept alongside the lifetime counter in
+    // urls.clicks so they can be grouped into days of any time zone
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS click_history (
+            short_code TEXT NOT NULL,
+            hour BIGINT NOT NULL,
+            clicks BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (short_code, hour)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Instance-wide days (activity report, daily stats) read every code's hours
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_click_history_hour ON click_history(hour)")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn increment_clicks(
+    storage: &PostgresStorage,
+    short_code: &str,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let amount = i64::try_from(amount).map_err(|_| anyhow!("increment amount exceeds i64"))?;
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        WITH updated AS (
+            UPDATE urls
+            SET clicks = clicks + $2, last_visited_at = $4
+            WHERE short_code = $1 AND is_active = true
+            RETURNING short_code
+        )
+        INSERT INTO click_history (short_code, hour, clicks)
+        SELECT short_code, $3, $2 FROM updated
+        ON CONFLICT (short_code, hour)
+        DO UPDATE SET clicks = click_history.clicks + EXCLUDED.clicks
+        "#,
+    )
+    .bind(short_code)
+    .bind(amount)
+    .bind(hour_start(now))
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn increment_clicks_batch(
+    storage: &PostgresStorage,
+    increments: &[ClickIncrement],
+) -> Result<()> {
+    if increments.is_empty() {
+        return Ok(());
+    }
+
+    let short_codes: Vec<&str> = increments.iter().map(ClickIncrement::short_code).collect();
+    let amounts: Vec<i64> = increments
+        .iter()
+        .map(|increment| {
+            i64::try_from(increment.amount().get())
+                .map_err(|_| anyhow!("increment amount exceeds i64"))
+        })
+        .collect::<Result<_>>()?;
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        WITH updated AS (
+            UPDATE urls AS url
+            SET clicks = url.clicks + increment.amount, last_visited_at = $4
+            FROM UNNEST($1::text[], $2::bigint[]) AS increment(short_code, amount)
+            WHERE url.short_code = increment.short_code AND url.is_active = true
+            RETURNING url.short_code, increment.amount
+        )
+        INSERT INTO click_history (short_code, hour, clicks)
+        SELECT short_code, $3, amount FROM updated
+        ON CONFLICT (short_code, hour)
+        DO UPDATE SET clicks = click_history.clicks + EXCLUDED.clicks
+        "#,
+    )
+    .bind(short_codes)
+    .bind(amounts)
+    .bind(hour_start(now))
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn get_click_history(
+    storage: &PostgresStorage,
+    short_code: &str,
+    since: i64,
+    time_zone: Tz,
+) -> Result<Vec<ClickHistoryEntry>> {
+    let history = sqlx::query_as::<_, ClickHistoryEntry>(
+        r#"
+        SELECT EXTRACT(EPOCH FROM date_trunc('day', to_timestamp(hour) AT TIME ZONE $3) AT TIME ZONE $3)::BIGINT AS day,
+               SUM(clicks)::BIGINT AS clicks
+        FROM click_history
+        WHERE short_code = $1 AND hour >= $2
+        GROUP BY 1
+        ORDER BY 1 ASC
+        "#,
+    )
+    .bind(short_code)
+    .bind(since)
+    .bind(time_zone.name())
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(history)
+}
+
+pub(super) async fn compact_click_history(
+    storage: &PostgresStorage,
+    retention_days: i64,
+) -> Result<(i64, i64)> {
+    let cutoff = hour_start(storage.clock.now_epoch_secs() - retention_days.max(0) * 86400);
+
+    // Rows already dated the first of their month are left alone, so
+    // compacted rows are never merged again.
+    let mut tx = storage.pool.begin().await?;
+    let merged = sqlx::query(
+        r#"
+        WITH hourly AS (
+            SELECT short_code,
+                   hour,
+                   clicks,
+                   EXTRACT(EPOCH FROM date_trunc('month', to_timestamp(hour) AT TIME ZONE 'UTC'))::BIGINT AS month
+            FROM click_history
+            WHERE hour < $1
+        )
+        INSERT INTO click_history (short_code, hour, clicks)
+        SELECT short_code, month, SUM(clicks)::BIGINT
+        FROM hourly
+        WHERE hour <> month
+        GROUP BY short_code, month
+        ON CONFLICT (short_code, hour)
+        DO UPDATE SET clicks = click_history.clicks + EXCLUDED.clicks
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM click_history
+        WHERE hour < $1
+          AND hour <> EXTRACT(EPOCH FROM date_trunc('month', to_timestamp(hour) AT TIME ZONE 'UTC'))::BIGINT
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((
+        removed.rows_affected() as i64,
+        merged.rows_affected() as i64,
+    ))
+}
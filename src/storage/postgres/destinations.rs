@@ -0,0 +1,116 @@
+//! Lookups of links by destination for `PostgresStorage`.
+
+use crate::models::{DestinationHostLink, DestinationHostSummary, ShortenedUrl};
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+pub(super) async fn links_by_destination_host(
+    storage: &PostgresStorage,
+    hosts: &[String],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostLink>> {
+    let links = sqlx::query_as::<_, DestinationHostLink>(
+        r#"
+        SELECT u.dest_host, u.short_code, u.original_url, u.created_by, o.email AS owner_email, u.clicks, u.created_at
+        FROM urls u
+        LEFT JOIN users o
+          ON o.user_id = u.created_by AND o.auth_method = u.created_by_auth_method
+        WHERE u.dest_host = ANY($1) AND u.is_active = true AND u.alias_of IS NULL
+        ORDER BY u.dest_host, u.clicks DESC, u.id
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(hosts)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(links)
+}
+
+pub(super) async fn links_by_destination(
+    storage: &PostgresStorage,
+    url: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostLink>> {
+    let Some(normalized) = storage.normalized_url(url) else {
+        return Ok(Vec::new());
+    };
+    let links = sqlx::query_as::<_, DestinationHostLink>(
+        r#"
+        SELECT COALESCE(u.dest_host, '') AS dest_host, u.short_code, u.original_url, u.created_by, o.email AS owner_email, u.clicks, u.created_at
+        FROM urls u
+        LEFT JOIN users o
+          ON o.user_id = u.created_by AND o.auth_method = u.created_by_auth_method
+        WHERE u.normalized_url = $1 AND u.is_active = true AND u.alias_of IS NULL
+        ORDER BY u.clicks DESC, u.id
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(normalized)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(links)
+}
+
+pub(super) async fn destination_host_summary(
+    storage: &PostgresStorage,
+    hosts: &[String],
+    min_links: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostSummary>> {
+    // An empty array means every host
+    let summary = sqlx::query_as::<_, DestinationHostSummary>(
+        r#"
+        SELECT dest_host, COUNT(*) AS links, COALESCE(SUM(clicks), 0)::BIGINT AS clicks,
+               COUNT(DISTINCT created_by) AS owners
+        FROM urls
+        WHERE dest_host IS NOT NULL AND is_active = true AND alias_of IS NULL
+          AND (cardinality($1::TEXT[]) = 0 OR dest_host = ANY($1))
+        GROUP BY dest_host
+        HAVING COUNT(*) >= $2
+        ORDER BY links DESC, dest_host
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(hosts)
+    .bind(min_links)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(summary)
+}
+
+pub(super) async fn find_active_by_destination(
+    storage: &PostgresStorage,
+    original_url: &str,
+    created_by: Option<&str>,
+) -> Result<Option<Arc<ShortenedUrl>>> {
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE original_url = $1 AND created_by IS NOT DISTINCT FROM $2 AND is_active = true
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+        "#
+    ))
+    .bind(original_url)
+    .bind(created_by)
+    .fetch_optional(storage.pool.as_ref())
+    .await?;
+
+    Ok(url.map(Arc::new))
+}
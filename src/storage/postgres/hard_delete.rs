@@ -0,0 +1,121 @@
+//! Permanent deletion of a link and its data for `PostgresStorage`.
+
+use crate::storage::verify::{any_column, LINK_ANALYTICS_TABLES};
+use crate::storage::{HardDeleteReport, StorageError, StorageResult};
+
+use super::PostgresStorage;
+
+/// Longest `hard_delete` waits for its lock on `urls`. Redirects queue
+/// behind a waiting `ALTER TABLE`, so it gives up well before they time out.
+const HARD_DELETE_LOCK_TIMEOUT: &str = "5s";
+
+pub(super) async fn hard_delete(
+    storage: &PostgresStorage,
+    short_code: &str,
+    actor: Option<&str>,
+) -> StorageResult<Option<HardDeleteReport>> {
+    if !storage.allow_hard_delete {
+        return Err(StorageError::PermissionDenied);
+    }
+
+    // Check before locking urls for a code that is not there
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM urls WHERE short_code = $1)")
+            .bind(short_code)
+            .fetch_one(storage.pool.as_ref())
+            .await?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let mut tx = storage.pool.begin().await?;
+    sqlx::query(&format!(
+        "SET LOCAL lock_timeout = '{HARD_DELETE_LOCK_TIMEOUT}'"
+    ))
+    .execute(&mut *tx)
+    .await?;
+    // Disabling the trigger locks urls until commit, so no other session
+    // ever sees it off, and a failure anywhere rolls it back on.
+    sqlx::query("ALTER TABLE urls DISABLE TRIGGER prevent_urls_delete_trigger")
+        .execute(&mut *tx)
+        .await?;
+    // `init` revokes DELETE from the owner too; grant it back for this
+    // transaction only.
+    let can_delete: bool = sqlx::query_scalar("SELECT has_table_privilege('urls', 'DELETE')")
+        .fetch_one(&mut *tx)
+        .await?;
+    if !can_delete {
+        sqlx::query("GRANT DELETE ON urls TO CURRENT_USER")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let codes: Vec<String> = sqlx::query_scalar(
+        "SELECT short_code FROM urls WHERE short_code = $1 OR alias_of = $1 ORDER BY id",
+    )
+    .bind(short_code)
+    .fetch_all(&mut *tx)
+    .await?;
+    if !codes.iter().any(|code| code == short_code) {
+        return Ok(None);
+    }
+
+    // Audited for the owner while the row is still there to name them
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (action, short_code, actor, effective_user, created_at)
+        SELECT 'hard_delete', short_code, $1::TEXT, COALESCE(created_by, $1::TEXT, ''), $2
+        FROM urls WHERE short_code = $3
+        "#,
+    )
+    .bind(actor)
+    .bind(storage.clock.now_epoch_secs())
+    .bind(short_code)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut analytics_rows = 0;
+    for (table, columns) in LINK_ANALYTICS_TABLES {
+        let condition = any_column(columns, |column| format!("{column} = ANY($1)"));
+        analytics_rows += sqlx::query(&format!("DELETE FROM {table} WHERE {condition}"))
+            .bind(&codes)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+    }
+    let click_history_rows = sqlx::query("DELETE FROM click_history WHERE short_code = ANY($1)")
+        .bind(&codes)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as i64;
+    let url_history_rows = sqlx::query("DELETE FROM url_history WHERE short_code = ANY($1)")
+        .bind(&codes)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as i64;
+    sqlx::query("DELETE FROM link_moderation WHERE short_code = ANY($1)")
+        .bind(&codes)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM urls WHERE short_code = ANY($1)")
+        .bind(&codes)
+        .execute(&mut *tx)
+        .await?;
+
+    if !can_delete {
+        sqlx::query("REVOKE DELETE ON urls FROM CURRENT_USER")
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("ALTER TABLE urls ENABLE TRIGGER prevent_urls_delete_trigger")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Some(HardDeleteReport {
+        codes,
+        analytics_rows,
+        click_history_rows,
+        url_history_rows,
+    }))
+}
@@ -0,0 +1,385 @@
+//! Link creation, lookup and updates for `PostgresStorage`.
+
+use crate::destination::destination_host;
+use crate::models::{CreatedVia, LinkOptions, ShortenedUrl, UrlHistoryEntry};
+use crate::storage::{StorageError, StorageResult, URL_COLUMNS};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+/// Create `url_history`, the previous destinations of each link.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Create url_history table to record previous destinations on update/restore
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS url_history (
+            id BIGSERIAL PRIMARY KEY,
+            short_code TEXT NOT NULL,
+            historic_url TEXT NOT NULL,
+            changed_at BIGINT NOT NULL,
+            changed_by TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_url_history_short_code ON url_history(short_code, changed_at DESC, id DESC)",
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn create_with_code_via(
+    storage: &PostgresStorage,
+    short_code: &str,
+    original_url: &str,
+    created_by: Option<&str>,
+    created_by_auth_method: Option<&str>,
+    created_via: CreatedVia,
+    options: &LinkOptions,
+) -> StorageResult<Arc<ShortenedUrl>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    // RETURNING yields no row when the code exists, so one statement both
+    // detects the conflict and reads back the stored row.
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        INSERT INTO urls (short_code, original_url, created_at, updated_at, created_by, created_by_auth_method, is_active, created_via, dest_host, normalized_url, options)
+        VALUES ($1, $2, $3, $3, $4, $5, true, $6, $7, $8, $9)
+        ON CONFLICT (short_code) DO NOTHING
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(short_code)
+    .bind(original_url)
+    .bind(created_at)
+    .bind(created_by)
+    .bind(created_by_auth_method)
+    .bind(created_via.as_str())
+    .bind(destination_host(original_url))
+    .bind(storage.normalized_url(original_url))
+    .bind(options.to_json())
+    .fetch_optional(storage.pool.as_ref())
+    .await?
+    .ok_or(StorageError::Conflict)?;
+
+    Ok(Arc::new(url))
+}
+
+pub(super) async fn get_authoritative(
+    storage: &PostgresStorage,
+    short_code: &str,
+) -> Result<Option<Arc<ShortenedUrl>>> {
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = $1
+        "#
+    ))
+    .bind(short_code)
+    .fetch_optional(storage.pool.as_ref())
+    .await?;
+
+    Ok(url.map(Arc::new))
+}
+
+pub(super) async fn get_many(
+    storage: &PostgresStorage,
+    short_codes: &[String],
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let urls = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = ANY($1)
+        "#
+    ))
+    .bind(short_codes)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
+
+pub(super) async fn deactivate(storage: &PostgresStorage, short_code: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = false, updated_at = CASE WHEN is_active THEN $2 ELSE updated_at END
+        WHERE short_code = $1
+        "#,
+    )
+    .bind(short_code)
+    .bind(storage.clock.now_epoch_ms())
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn set_title_if_missing(
+    storage: &PostgresStorage,
+    short_code: &str,
+    title: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET title = $1
+        WHERE short_code = $2 AND title IS NULL
+        "#,
+    )
+    .bind(title)
+    .bind(short_code)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn set_link_options(
+    storage: &PostgresStorage,
+    short_code: &str,
+    options: &LinkOptions,
+) -> Result<bool> {
+    let result = sqlx::query("UPDATE urls SET options = $1, updated_at = $3 WHERE short_code = $2")
+        .bind(options.to_json())
+        .bind(short_code)
+        .bind(storage.clock.now_epoch_ms())
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn reactivate(storage: &PostgresStorage, short_code: &str) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = true, updated_at = CASE WHEN is_active THEN updated_at ELSE $2 END
+        WHERE short_code = $1
+        "#,
+    )
+    .bind(short_code)
+    .bind(storage.clock.now_epoch_ms())
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn update_url(
+    storage: &PostgresStorage,
+    short_code: &str,
+    new_url: &str,
+    updated_by: Option<&str>,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let changed_at = storage.clock.now_epoch_secs();
+
+    let mut tx = storage.pool.begin().await.map_err(|e| anyhow!(e))?;
+
+    // Read the current (soon-to-be-previous) destination.
+    let old: Option<(String, Option<i64>)> =
+        sqlx::query_as("SELECT original_url, reserved_until FROM urls WHERE short_code = $1")
+            .bind(short_code)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+    let Some((old_url, reserved_until)) = old else {
+        return Ok(None);
+    };
+
+    // Record the previous destination in history. A reserved code has
+    // only a placeholder, so there is nothing to record.
+    if reserved_until.is_none() {
+        sqlx::query(
+            r#"
+            INSERT INTO url_history (short_code, historic_url, changed_at, changed_by)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(short_code)
+        .bind(&old_url)
+        .bind(changed_at)
+        .bind(updated_by)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| anyhow!(e))?;
+    }
+
+    // Point the active record at the new destination, ending any reservation.
+    let updated = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        UPDATE urls
+        SET original_url = $2, dest_host = $3, normalized_url = $4, reserved_until = NULL,
+            updated_at = $5
+        WHERE short_code = $1
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(short_code)
+    .bind(new_url)
+    .bind(destination_host(new_url))
+    .bind(storage.normalized_url(new_url))
+    .bind(storage.clock.now_epoch_ms())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    tx.commit().await.map_err(|e| anyhow!(e))?;
+
+    Ok(Some(Arc::new(updated)))
+}
+
+pub(super) async fn reserve_codes(
+    storage: &PostgresStorage,
+    short_codes: &[String],
+    created_by: Option<&str>,
+    created_by_auth_method: Option<&str>,
+    reserved_until: i64,
+) -> StorageResult<Vec<Arc<ShortenedUrl>>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    // Dropping the transaction on conflict rolls back the codes inserted so far.
+    let mut tx = storage.pool.begin().await.map_err(|e| anyhow!(e))?;
+    let mut reserved = Vec::with_capacity(short_codes.len());
+    for short_code in short_codes {
+        let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            INSERT INTO urls (short_code, original_url, created_at, updated_at, created_by, created_by_auth_method, is_active, reserved_until)
+            VALUES ($1, $2, $3, $3, $4, $5, true, $6)
+            ON CONFLICT (short_code) DO NOTHING
+            RETURNING {URL_COLUMNS}
+            "#
+        ))
+        .bind(short_code)
+        .bind(ShortenedUrl::RESERVED_DESTINATION)
+        .bind(created_at)
+        .bind(created_by)
+        .bind(created_by_auth_method)
+        .bind(reserved_until)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| anyhow!(e))?;
+
+        match url {
+            Some(url) => reserved.push(Arc::new(url)),
+            None => return Err(StorageError::Conflict),
+        }
+    }
+
+    tx.commit().await.map_err(|e| anyhow!(e))?;
+
+    Ok(reserved)
+}
+
+pub(super) async fn expire_reservations(storage: &PostgresStorage, now: i64) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = false, updated_at = $2
+        WHERE reserved_until IS NOT NULL AND reserved_until <= $1 AND is_active = true
+        "#,
+    )
+    .bind(now)
+    .bind(storage.clock.now_epoch_ms())
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+pub(super) async fn get_url_history(
+    storage: &PostgresStorage,
+    short_code: &str,
+) -> Result<Vec<UrlHistoryEntry>> {
+    let history = sqlx::query_as::<_, UrlHistoryEntry>(
+        r#"
+        SELECT id, short_code, historic_url, changed_at, changed_by
+        FROM url_history
+        WHERE short_code = $1
+        ORDER BY changed_at DESC, id DESC
+        "#,
+    )
+    .bind(short_code)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(history)
+}
+
+pub(super) async fn restore_url(
+    storage: &PostgresStorage,
+    short_code: &str,
+    history_id: i64,
+    restored_by: Option<&str>,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let changed_at = storage.clock.now_epoch_secs();
+
+    let mut tx = storage.pool.begin().await.map_err(|e| anyhow!(e))?;
+
+    // Resolve the destination to restore to.
+    let historic_url: Option<String> = sqlx::query_scalar(
+        "SELECT historic_url FROM url_history WHERE id = $1 AND short_code = $2",
+    )
+    .bind(history_id)
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    let historic_url = historic_url.ok_or(StorageError::NotFound)?;
+
+    // Read the current destination so it is preserved in history.
+    let old_url: Option<String> =
+        sqlx::query_scalar("SELECT original_url FROM urls WHERE short_code = $1")
+            .bind(short_code)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| anyhow!(e))?;
+
+    let Some(old_url) = old_url else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO url_history (short_code, historic_url, changed_at, changed_by)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(short_code)
+    .bind(&old_url)
+    .bind(changed_at)
+    .bind(restored_by)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    let updated = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        UPDATE urls
+        SET original_url = $2, dest_host = $3, normalized_url = $4, updated_at = $5
+        WHERE short_code = $1
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(short_code)
+    .bind(&historic_url)
+    .bind(destination_host(&historic_url))
+    .bind(storage.normalized_url(&historic_url))
+    .bind(storage.clock.now_epoch_ms())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| anyhow!(e))?;
+
+    tx.commit().await.map_err(|e| anyhow!(e))?;
+
+    Ok(Some(Arc::new(updated)))
+}
@@ -0,0 +1,137 @@
+//! Paginated link listings for `PostgresStorage`, newest first or by last visit.
+
+use crate::models::ShortenedUrl;
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+pub(super) async fn list_with_cursor(
+    storage: &PostgresStorage,
+    limit: i64,
+    cursor: Option<(i64, i64)>,
+    is_admin: bool,
+    user_id: Option<&str>,
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let urls = if is_admin || user_id.is_none() {
+        // Admin sees all URLs, or when auth is disabled (no user_id), show all
+        if let Some((cursor_created_at, cursor_id)) = cursor {
+            sqlx::query_as::<_, ShortenedUrl>(&format!(
+                r#"
+                SELECT {URL_COLUMNS}
+                FROM urls
+                WHERE (created_at, id) < ($1, $2)
+                ORDER BY created_at DESC, id DESC
+                LIMIT $3
+                "#
+            ))
+            .bind(cursor_created_at)
+            .bind(cursor_id)
+            .bind(limit)
+            .fetch_all(storage.pool.as_ref())
+            .await?
+        } else {
+            sqlx::query_as::<_, ShortenedUrl>(&format!(
+                r#"
+                SELECT {URL_COLUMNS}
+                FROM urls
+                ORDER BY created_at DESC, id DESC
+                LIMIT $1
+                "#
+            ))
+            .bind(limit)
+            .fetch_all(storage.pool.as_ref())
+            .await?
+        }
+    } else if let Some(uid) = user_id {
+        // Regular user sees only their own URLs
+        if let Some((cursor_created_at, cursor_id)) = cursor {
+            sqlx::query_as::<_, ShortenedUrl>(&format!(
+                r#"
+                SELECT {URL_COLUMNS}
+                FROM urls
+                WHERE created_by = $1 AND (created_at, id) < ($2, $3)
+                ORDER BY created_at DESC, id DESC
+                LIMIT $4
+                "#
+            ))
+            .bind(uid)
+            .bind(cursor_created_at)
+            .bind(cursor_id)
+            .bind(limit)
+            .fetch_all(storage.pool.as_ref())
+            .await?
+        } else {
+            sqlx::query_as::<_, ShortenedUrl>(&format!(
+                r#"
+                SELECT {URL_COLUMNS}
+                FROM urls
+                WHERE created_by = $1
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2
+                "#
+            ))
+            .bind(uid)
+            .bind(limit)
+            .fetch_all(storage.pool.as_ref())
+            .await?
+        }
+    } else {
+        // Should not reach here, but return empty list
+        vec![]
+    };
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
+
+pub(super) async fn list_by_last_visit(
+    storage: &PostgresStorage,
+    limit: i64,
+    cursor: Option<(i64, i64)>,
+    unused_since: Option<i64>,
+    is_admin: bool,
+    user_id: Option<&str>,
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    // Admins see every link, and so does everyone when auth is disabled
+    let created_by = if is_admin { None } else { user_id };
+    let urls = if let Some((cursor_visited_at, cursor_id)) = cursor {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE ($1::TEXT IS NULL OR created_by = $1)
+              AND ($2::BIGINT IS NULL OR COALESCE(last_visited_at, 0) < $2)
+              AND (COALESCE(last_visited_at, 0) > $3
+                   OR (COALESCE(last_visited_at, 0) = $3 AND id > $4))
+            ORDER BY COALESCE(last_visited_at, 0) ASC, id ASC
+            LIMIT $5
+            "#
+        ))
+        .bind(created_by)
+        .bind(unused_since)
+        .bind(cursor_visited_at)
+        .bind(cursor_id)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE ($1::TEXT IS NULL OR created_by = $1)
+              AND ($2::BIGINT IS NULL OR COALESCE(last_visited_at, 0) < $2)
+            ORDER BY COALESCE(last_visited_at, 0) ASC, id ASC
+            LIMIT $3
+            "#
+        ))
+        .bind(created_by)
+        .bind(unused_since)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    };
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
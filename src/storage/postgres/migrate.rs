@@ -0,0 +1,246 @@
+//! The numbered migrations `init()` applies for `PostgresStorage`, one instance
+//! at a time under an advisory lock.
+
+use crate::storage::migrations;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+use super::PostgresStorage;
+
+/// Short codes merged per transaction while migrating analytics rows.
+const ANALYTICS_MERGE_BATCH: usize = 500;
+
+/// Longest a migration waits for a table lock before failing startup,
+/// rather than queueing every other statement on the table behind it.
+const MIGRATION_LOCK_TIMEOUT: &str = "30s";
+
+/// Advisory lock key held while numbered migrations run, so instances
+/// starting together apply each one once.
+const MIGRATION_LOCK_KEY: i64 = 0x6c79_6e78_6d69_6772;
+
+/// Short codes with analytics rows that share a key once NULL dimensions
+/// compare equal.
+async fn duplicate_analytics_codes(connection: &mut sqlx::PgConnection) -> Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT short_code FROM (
+            SELECT short_code
+            FROM analytics
+            GROUP BY short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version
+            HAVING COUNT(*) > 1
+        ) AS duplicates
+        "#,
+    )
+    .fetch_all(&mut *connection)
+    .await?)
+}
+
+/// Merge the analytics rows of `codes` that share a key once NULL dimensions
+/// compare equal, keeping the oldest row of each group with the summed visit
+/// count. Rows upserted before `idx_analytics_key` never conflicted on a
+/// NULL dimension, so each flush of such a key added another row. Returns
+/// the rows removed.
+async fn merge_duplicate_analytics(
+    connection: &mut sqlx::PgConnection,
+    codes: &[String],
+) -> Result<u64> {
+    sqlx::query(
+        r#"
+        UPDATE analytics
+        SET visit_count = merged.visit_count, updated_at = merged.updated_at
+        FROM (
+            SELECT MIN(id) AS id, SUM(visit_count)::BIGINT AS visit_count, MAX(updated_at) AS updated_at
+            FROM analytics
+            WHERE short_code = ANY($1)
+            GROUP BY short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version
+            HAVING COUNT(*) > 1
+        ) AS merged
+        WHERE analytics.id = merged.id
+        "#,
+    )
+    .bind(codes)
+    .execute(&mut *connection)
+    .await?;
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM analytics
+        WHERE short_code = ANY($1)
+          AND id NOT IN (
+            SELECT MIN(id)
+            FROM analytics
+            WHERE short_code = ANY($1)
+            GROUP BY short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version
+          )
+        "#,
+    )
+    .bind(codes)
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+    Ok(removed)
+}
+
+/// Migration 1: one analytics row per key with NULL dimensions compared as
+/// equal, which a plain UNIQUE constraint does not do, so upserts of a visit
+/// without a region or ASN add to the existing row. NULL text maps to '' and
+/// a NULL ASN to -1; writers store neither.
+///
+/// Existing duplicates are merged a batch of codes per transaction while
+/// flushes carry on; only the rows written since, the constraint swap and
+/// the index build run under a table lock, bounded by
+/// `MIGRATION_LOCK_TIMEOUT`. The index is built in the locked transaction so
+/// no flush can add a duplicate between the merge and the index.
+async fn migrate_analytics_key(
+    connection: &mut sqlx::PgConnection,
+    migration: &migrations::Migration,
+) -> Result<()> {
+    let mut merged = 0;
+    let codes = duplicate_analytics_codes(&mut *connection).await?;
+    for batch in codes.chunks(ANALYTICS_MERGE_BATCH) {
+        let mut tx = sqlx::Connection::begin(&mut *connection).await?;
+        merged += merge_duplicate_analytics(&mut tx, batch).await?;
+        tx.commit().await?;
+    }
+
+    let mut tx = sqlx::Connection::begin(&mut *connection).await?;
+    sqlx::query(&format!(
+        "SET LOCAL lock_timeout = '{MIGRATION_LOCK_TIMEOUT}'"
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("LOCK TABLE analytics IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+    let codes = duplicate_analytics_codes(&mut tx).await?;
+    merged += merge_duplicate_analytics(&mut tx, &codes).await?;
+    let constraints: Vec<String> = sqlx::query_scalar(
+        "SELECT conname::TEXT FROM pg_constraint WHERE conrelid = 'analytics'::regclass AND contype = 'u'",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for constraint in constraints {
+        sqlx::query(&format!(
+            "ALTER TABLE analytics DROP CONSTRAINT \"{}\"",
+            constraint.replace('"', "\"\"")
+        ))
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_analytics_key ON analytics(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)",
+    )
+    .execute(&mut *tx)
+    .await?;
+    record_migration(&mut tx, migration).await?;
+    tx.commit().await?;
+
+    if merged > 0 {
+        tracing::info!(rows = merged, "Merged duplicate analytics rows");
+    }
+    Ok(())
+}
+
+/// Migration 2: link creation times in milliseconds instead of seconds,
+/// with `updated_at` starting out equal to them. One locked transaction, so
+/// no link is created in seconds after the rewrite.
+async fn migrate_created_at_millis(
+    connection: &mut sqlx::PgConnection,
+    migration: &migrations::Migration,
+) -> Result<()> {
+    let mut tx = sqlx::Connection::begin(&mut *connection).await?;
+    sqlx::query(&format!(
+        "SET LOCAL lock_timeout = '{MIGRATION_LOCK_TIMEOUT}'"
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("LOCK TABLE urls IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE urls SET created_at = created_at * 1000, updated_at = created_at * 1000")
+        .execute(&mut *tx)
+        .await?;
+    record_migration(&mut tx, migration).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Record `migration` as applied, in the transaction that finished it.
+async fn record_migration(
+    connection: &mut sqlx::PgConnection,
+    migration: &migrations::Migration,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, EXTRACT(EPOCH FROM now())::BIGINT)",
+    )
+    .bind(migration.version)
+    .bind(migration.name)
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+/// Versions listed in `schema_migrations`.
+pub(super) async fn applied_migrations(
+    connection: &mut sqlx::PgConnection,
+) -> Result<HashSet<i64>> {
+    Ok(sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+/// Apply the numbered migrations the database has not had. An advisory
+/// lock held on one connection keeps instances starting together from
+/// running the same one twice; each migration records itself in
+/// `schema_migrations` in the transaction that finishes it.
+pub(super) async fn apply_migrations(storage: &PostgresStorage) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    let mut connection = storage.pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *connection)
+        .await?;
+    let applied = apply_pending_migrations(&mut connection).await;
+    let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *connection)
+        .await;
+    if unlocked.is_err() {
+        // Never hand a connection still holding the lock back to the pool
+        connection.detach();
+    }
+    applied?;
+    unlocked?;
+    Ok(())
+}
+
+async fn apply_pending_migrations(connection: &mut sqlx::PgConnection) -> Result<()> {
+    // Read under the lock, so a migration another instance finished
+    // while this one waited is skipped
+    let applied = applied_migrations(&mut *connection).await?;
+    for migration in migrations::pending(&applied) {
+        match migration.version {
+            1 => migrate_analytics_key(&mut *connection, migration).await?,
+            2 => migrate_created_at_millis(&mut *connection, migration).await?,
+            version => return Err(anyhow!("no Postgres implementation of migration {version}")),
+        }
+        tracing::info!(
+            version = migration.version,
+            name = migration.name,
+            "Applied schema migration"
+        );
+    }
+    Ok(())
+}
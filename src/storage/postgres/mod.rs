@@ -0,0 +1,805 @@
+use crate::analytics::{AnalyticsExportScope, AnalyticsGroupBy, AnalyticsRollup};
+use crate::clock::{system_clock, Clock};
+use crate::config::UrlNormalizationConfig;
+use crate::destination::normalize_url;
+use crate::models::{
+    ActivityDay, AuditEntry, Campaign, CampaignStats, ClickHistoryEntry, CreatedVia,
+    DestinationHostLink, DestinationHostSummary, InstanceStatsDay, LinkOptions, ModerationEntry,
+    ModerationStatus, ShortenedUrl, UrlHistoryEntry, UserAccount, UserLinkCounts, UserUsageHour,
+    UserUsageTotal,
+};
+use crate::storage::campaigns;
+use crate::storage::challenge_redemptions;
+use crate::storage::copy::{AdminRecord, RowCounts, UserRecord};
+use crate::storage::server_leases::{self, ServerLease};
+use crate::storage::verify::{check_schema_current, EXPECTED_TABLES};
+use crate::storage::{
+    ClickIncrement, HardDeleteReport, MalformedPatchBatch, OrphanCounts, PoolMonitor, PoolSettings,
+    PoolStats, SearchParams, SearchResult, Storage, StorageResult, VerifyReport,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono_tz::Tz;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+mod aggregates;
+mod aliases;
+mod analytics;
+mod audit;
+mod clicks;
+mod destinations;
+mod hard_delete;
+mod links;
+mod listing;
+mod migrate;
+mod moderation;
+mod patch;
+mod reports;
+mod rollups;
+mod schema;
+mod search;
+mod transfer;
+mod usage;
+mod users;
+mod verify;
+
+/// Trigram search indexes, created by `init()` when `pg_trgm` is available.
+const TRIGRAM_INDEXES: &[&str] = &["idx_urls_short_code_trgm", "idx_urls_original_url_trgm"];
+
+pub struct PostgresStorage {
+    pub pool: Arc<PgPool>,
+    monitor: PoolMonitor,
+    /// Schema every connection resolves unqualified names in (`DATABASE_SCHEMA`)
+    schema: Option<String>,
+    /// Source of stored timestamps, the system clock unless a test injects one
+    clock: Arc<dyn Clock>,
+    /// Steps that derive `urls.normalized_url` from each destination
+    url_normalization: UrlNormalizationConfig,
+    /// Whether `hard_delete` may run (`ALLOW_HARD_DELETE`)
+    allow_hard_delete: bool,
+}
+
+/// Reject schema names that would need quoting: they are spliced into
+/// `CREATE SCHEMA` and the connection's `search_path`.
+pub fn validate_schema_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_');
+    let valid_rest = chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid_start || !valid_rest || name.len() > 63 || name.starts_with("pg_") {
+        return Err(anyhow!(
+            "Invalid DATABASE_SCHEMA '{}': use 1-63 lowercase letters, digits and underscores, not starting with a digit or 'pg_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+impl PostgresStorage {
+    pub async fn new(database_url: &str, max_connections: u32) -> Result<Self> {
+        Self::new_with_pool_settings(database_url, max_connections, PoolSettings::default()).await
+    }
+
+    pub async fn new_with_pool_settings(
+        database_url: &str,
+        max_connections: u32,
+        settings: PoolSettings,
+    ) -> Result<Self> {
+        Self::new_in_schema(database_url, max_connections, settings, None).await
+    }
+
+    /// Connect with every connection's `search_path` set to `schema` first, so
+    /// tables, indexes and the delete-protection trigger functions all live in
+    /// that schema. `public` stays on the path for extensions such as pg_trgm
+    /// that were installed there. `init` creates the schema if it is missing.
+    pub async fn new_in_schema(
+        database_url: &str,
+        max_connections: u32,
+        settings: PoolSettings,
+        schema: Option<&str>,
+    ) -> Result<Self> {
+        let mut options = PgConnectOptions::from_str(database_url)?;
+        if let Some(schema) = schema {
+            validate_schema_name(schema)?;
+            // No space after the comma: startup options are split on whitespace
+            options = options.options([("search_path", format!("{},public", schema))]);
+        }
+        let pool = settings
+            .apply(PgPoolOptions::new().max_connections(max_connections))
+            .connect_with(options)
+            .await?;
+        Ok(Self {
+            pool: Arc::new(pool),
+            monitor: PoolMonitor::new(settings),
+            schema: schema.map(str::to_string),
+            clock: system_clock(),
+            url_normalization: UrlNormalizationConfig::default(),
+            allow_hard_delete: false,
+        })
+    }
+
+    /// Stamp rows with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Derive `urls.normalized_url` with `config` instead of the defaults.
+    pub fn with_url_normalization(mut self, config: UrlNormalizationConfig) -> Self {
+        self.url_normalization = config;
+        self
+    }
+
+    /// Let `hard_delete` run; without this it always fails.
+    pub fn with_hard_delete(mut self, allow: bool) -> Self {
+        self.allow_hard_delete = allow;
+        self
+    }
+
+    fn normalized_url(&self, url: &str) -> Option<String> {
+        normalize_url(url, &self.url_normalization)
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn ensure_schema(&self) -> Result<()> {
+        schema::ensure_schema(self).await
+    }
+
+    async fn verify_schema(&self) -> Result<()> {
+        let tables = verify::schema_objects(self).await?;
+        let columns = verify::schema_columns(self).await?;
+        let applied = if tables.contains("schema_migrations") {
+            let mut connection = self.pool.acquire().await?;
+            migrate::applied_migrations(&mut connection).await?
+        } else {
+            HashSet::new()
+        };
+        check_schema_current(&[EXPECTED_TABLES], &tables, &columns, &applied)
+    }
+
+    async fn create_with_code_via(
+        &self,
+        short_code: &str,
+        original_url: &str,
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        created_via: CreatedVia,
+        options: &LinkOptions,
+    ) -> StorageResult<Arc<ShortenedUrl>> {
+        links::create_with_code_via(
+            self,
+            short_code,
+            original_url,
+            created_by,
+            created_by_auth_method,
+            created_via,
+            options,
+        )
+        .await
+    }
+
+    async fn get(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        self.get_authoritative(short_code).await
+    }
+
+    async fn get_authoritative(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        links::get_authoritative(self, short_code).await
+    }
+
+    async fn get_many(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        links::get_many(self, short_codes).await
+    }
+
+    async fn deactivate(&self, short_code: &str) -> Result<bool> {
+        links::deactivate(self, short_code).await
+    }
+
+    async fn set_title_if_missing(&self, short_code: &str, title: &str) -> Result<bool> {
+        links::set_title_if_missing(self, short_code, title).await
+    }
+
+    async fn set_link_options(&self, short_code: &str, options: &LinkOptions) -> Result<bool> {
+        links::set_link_options(self, short_code, options).await
+    }
+
+    async fn reactivate(&self, short_code: &str) -> Result<bool> {
+        links::reactivate(self, short_code).await
+    }
+
+    async fn update_url(
+        &self,
+        short_code: &str,
+        new_url: &str,
+        updated_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        links::update_url(self, short_code, new_url, updated_by).await
+    }
+
+    async fn reserve_codes(
+        &self,
+        short_codes: &[String],
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        reserved_until: i64,
+    ) -> StorageResult<Vec<Arc<ShortenedUrl>>> {
+        links::reserve_codes(
+            self,
+            short_codes,
+            created_by,
+            created_by_auth_method,
+            reserved_until,
+        )
+        .await
+    }
+
+    async fn rename_code(
+        &self,
+        short_code: &str,
+        new_code: &str,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        aliases::rename_code(self, short_code, new_code).await
+    }
+
+    async fn add_alias(
+        &self,
+        short_code: &str,
+        alias_code: &str,
+        created_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        aliases::add_alias(self, short_code, alias_code, created_by).await
+    }
+
+    async fn get_aliases(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        aliases::get_aliases(self, short_codes).await
+    }
+
+    async fn hard_delete(
+        &self,
+        short_code: &str,
+        actor: Option<&str>,
+    ) -> StorageResult<Option<HardDeleteReport>> {
+        hard_delete::hard_delete(self, short_code, actor).await
+    }
+
+    async fn expire_reservations(&self, now: i64) -> Result<i64> {
+        links::expire_reservations(self, now).await
+    }
+
+    async fn get_url_history(&self, short_code: &str) -> Result<Vec<UrlHistoryEntry>> {
+        links::get_url_history(self, short_code).await
+    }
+
+    async fn restore_url(
+        &self,
+        short_code: &str,
+        history_id: i64,
+        restored_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        links::restore_url(self, short_code, history_id, restored_by).await
+    }
+
+    async fn increment_clicks(&self, short_code: &str, amount: u64) -> Result<()> {
+        clicks::increment_clicks(self, short_code, amount).await
+    }
+
+    async fn increment_clicks_batch(&self, increments: &[ClickIncrement]) -> Result<()> {
+        clicks::increment_clicks_batch(self, increments).await
+    }
+
+    async fn list_with_cursor(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        listing::list_with_cursor(self, limit, cursor, is_admin, user_id).await
+    }
+
+    async fn list_by_last_visit(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+        unused_since: Option<i64>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        listing::list_by_last_visit(self, limit, cursor, unused_since, is_admin, user_id).await
+    }
+
+    async fn upsert_user(
+        &self,
+        user_id: &str,
+        email: Option<&str>,
+        auth_method: &str,
+    ) -> Result<()> {
+        users::upsert_user(self, user_id, email, auth_method).await
+    }
+
+    async fn is_manual_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        users::is_manual_admin(self, user_id, auth_method).await
+    }
+
+    async fn promote_to_admin(&self, user_id: &str, auth_method: &str) -> Result<()> {
+        users::promote_to_admin(self, user_id, auth_method).await
+    }
+
+    async fn demote_from_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        users::demote_from_admin(self, user_id, auth_method).await
+    }
+
+    async fn list_manual_admins(&self) -> Result<Vec<(String, String, String)>> {
+        users::list_manual_admins(self).await
+    }
+
+    async fn patch_created_by(
+        &self,
+        short_code: &str,
+        new_created_by: &str,
+        auth_method: Option<&str>,
+    ) -> Result<bool> {
+        patch::patch_created_by(self, short_code, new_created_by, auth_method).await
+    }
+
+    async fn patch_malformed_created_by_batch(
+        &self,
+        new_created_by: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Option<MalformedPatchBatch>> {
+        patch::patch_malformed_created_by_batch(self, new_created_by, after_id, limit).await
+    }
+
+    async fn count_malformed_created_by(&self) -> Result<i64> {
+        patch::count_malformed_created_by(self).await
+    }
+
+    async fn sample_malformed_created_by(&self, limit: i64) -> Result<Vec<String>> {
+        patch::sample_malformed_created_by(self, limit).await
+    }
+
+    async fn user_exists(&self, user_id: &str) -> Result<bool> {
+        users::user_exists(self, user_id).await
+    }
+
+    async fn record_audit_entry(
+        &self,
+        action: &str,
+        short_code: &str,
+        actor: Option<&str>,
+        effective_user: &str,
+    ) -> Result<()> {
+        audit::record_audit_entry(self, action, short_code, actor, effective_user).await
+    }
+
+    async fn get_audit_log(&self, short_code: &str) -> Result<Vec<AuditEntry>> {
+        audit::get_audit_log(self, short_code).await
+    }
+
+    async fn submit_for_moderation(
+        &self,
+        short_code: &str,
+        status: ModerationStatus,
+    ) -> Result<()> {
+        moderation::submit_for_moderation(self, short_code, status).await
+    }
+
+    async fn get_moderation(&self, short_code: &str) -> Result<Option<ModerationEntry>> {
+        moderation::get_moderation(self, short_code).await
+    }
+
+    async fn list_moderation(
+        &self,
+        status: ModerationStatus,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ModerationEntry>> {
+        moderation::list_moderation(self, status, limit, offset).await
+    }
+
+    async fn approve_link(&self, short_code: &str, decided_by: Option<&str>) -> Result<bool> {
+        moderation::approve_link(self, short_code, decided_by).await
+    }
+
+    async fn reject_link(
+        &self,
+        short_code: &str,
+        reason: &str,
+        decided_by: Option<&str>,
+    ) -> Result<bool> {
+        moderation::reject_link(self, short_code, reason, decided_by).await
+    }
+
+    async fn create_campaign(&self, name: &str, owner: Option<&str>) -> Result<Campaign> {
+        campaigns::postgres::create(self.pool.as_ref(), name, owner, self.clock.now_epoch_ms())
+            .await
+    }
+
+    async fn get_campaign(&self, id: i64) -> Result<Option<Campaign>> {
+        campaigns::postgres::get(self.pool.as_ref(), id).await
+    }
+
+    async fn list_campaigns(&self, owner: Option<&str>) -> Result<Vec<Campaign>> {
+        campaigns::postgres::list(self.pool.as_ref(), owner).await
+    }
+
+    async fn rename_campaign(&self, id: i64, name: &str) -> Result<Option<Campaign>> {
+        campaigns::postgres::rename(self.pool.as_ref(), id, name).await
+    }
+
+    async fn delete_campaign(&self, id: i64) -> Result<Option<Vec<String>>> {
+        campaigns::postgres::delete(self.pool.as_ref(), id).await
+    }
+
+    async fn set_link_campaign(&self, short_code: &str, campaign_id: Option<i64>) -> Result<bool> {
+        campaigns::postgres::set_link_campaign(self.pool.as_ref(), short_code, campaign_id).await
+    }
+
+    async fn campaign_stats(
+        &self,
+        id: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<CampaignStats> {
+        campaigns::postgres::stats(self.pool.as_ref(), id, start_time, end_time).await
+    }
+
+    async fn list_all_users(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, String, String)>,
+        inactive_since: Option<i64>,
+    ) -> Result<Vec<UserAccount>> {
+        users::list_all_users(self, limit, cursor, inactive_since).await
+    }
+
+    async fn user_emails(
+        &self,
+        users: &[(String, String)],
+    ) -> Result<Vec<(String, String, String)>> {
+        users::user_emails(self, users).await
+    }
+
+    async fn links_by_destination_host(
+        &self,
+        hosts: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        destinations::links_by_destination_host(self, hosts, limit, offset).await
+    }
+
+    async fn links_by_destination(
+        &self,
+        url: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        destinations::links_by_destination(self, url, limit, offset).await
+    }
+
+    async fn destination_host_summary(
+        &self,
+        hosts: &[String],
+        min_links: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostSummary>> {
+        destinations::destination_host_summary(self, hosts, min_links, limit, offset).await
+    }
+
+    async fn user_accounts(&self, user_id: &str) -> Result<Vec<UserAccount>> {
+        users::user_accounts(self, user_id).await
+    }
+
+    async fn user_link_counts(&self, user_id: &str) -> Result<UserLinkCounts> {
+        users::user_link_counts(self, user_id).await
+    }
+
+    async fn list_user_links(
+        &self,
+        user_id: &str,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        users::list_user_links(self, user_id, limit, cursor).await
+    }
+
+    async fn find_active_by_destination(
+        &self,
+        original_url: &str,
+        created_by: Option<&str>,
+    ) -> Result<Option<Arc<ShortenedUrl>>> {
+        destinations::find_active_by_destination(self, original_url, created_by).await
+    }
+
+    async fn bulk_deactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        users::bulk_deactivate_user_links(self, user_id).await
+    }
+
+    async fn bulk_reactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        users::bulk_reactivate_user_links(self, user_id).await
+    }
+
+    async fn count_user_links_by_state(&self, user_id: &str, is_active: bool) -> Result<i64> {
+        users::count_user_links_by_state(self, user_id, is_active).await
+    }
+
+    async fn sample_user_links_by_state(
+        &self,
+        user_id: &str,
+        is_active: bool,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        users::sample_user_links_by_state(self, user_id, is_active, limit).await
+    }
+
+    async fn upsert_analytics_batch(&self, records: Vec<AnalyticsRollup>) -> Result<()> {
+        analytics::upsert_analytics_batch(self, records).await
+    }
+
+    async fn upsert_known_analytics_batch(&self, records: Vec<AnalyticsRollup>) -> Result<u64> {
+        analytics::upsert_known_analytics_batch(self, records).await
+    }
+
+    async fn get_analytics(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+        analytics::get_analytics(self, short_code, start_time, end_time, limit).await
+    }
+
+    async fn get_estimated_visits(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<i64> {
+        analytics::get_estimated_visits(self, short_code, start_time, end_time).await
+    }
+
+    async fn get_analytics_export_page(
+        &self,
+        scope: AnalyticsExportScope<'_>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+        analytics::get_analytics_export_page(self, scope, start_time, end_time, after, limit).await
+    }
+
+    async fn get_analytics_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        group_by: AnalyticsGroupBy,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+        aggregates::get_analytics_aggregate(self, short_code, start_time, end_time, group_by, limit)
+            .await
+    }
+
+    async fn get_instance_ip_version_split(
+        &self,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+        aggregates::get_instance_ip_version_split(self, start_time, end_time).await
+    }
+
+    async fn get_analytics_daily_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        time_zone: Tz,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsAggregate>> {
+        aggregates::get_analytics_daily_aggregate(
+            self, short_code, start_time, end_time, time_zone, limit,
+        )
+        .await
+    }
+
+    async fn prune_analytics(
+        &self,
+        retention_days: i64,
+        drop_dimensions: &[String],
+    ) -> Result<(i64, i64)> {
+        rollups::prune_analytics(self, retention_days, drop_dimensions).await
+    }
+
+    async fn analytics_complete_since(&self) -> Result<Option<i64>> {
+        rollups::analytics_complete_since(self).await
+    }
+
+    async fn rollup_analytics_daily(&self, from: Option<i64>, until: i64) -> Result<i64> {
+        rollups::rollup_analytics_daily(self, from, until).await
+    }
+
+    async fn analytics_daily_rolled_until(&self) -> Result<Option<i64>> {
+        rollups::analytics_daily_rolled_until(self).await
+    }
+
+    async fn record_instance_stats(&self, day: i64) -> Result<InstanceStatsDay> {
+        reports::record_instance_stats(self, day).await
+    }
+
+    async fn instance_stats_history(&self, since: i64) -> Result<Vec<InstanceStatsDay>> {
+        reports::instance_stats_history(self, since).await
+    }
+
+    async fn activity_report(&self, since: i64, until: i64, top: i64) -> Result<Vec<ActivityDay>> {
+        reports::activity_report(self, since, until, top).await
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let value: Option<(String,)> = sqlx::query_as("SELECT value FROM settings WHERE key = $1")
+            .bind(key)
+            .fetch_optional(self.pool.as_ref())
+            .await?;
+        Ok(value.map(|(value,)| value))
+    }
+
+    async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO settings (key, value, updated_at) VALUES ($1, $2, $3)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .bind(self.clock.now_epoch_secs())
+        .execute(self.pool.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    async fn renew_server_lease(&self, lease: &ServerLease) -> Result<()> {
+        server_leases::postgres::renew(self.pool.as_ref(), lease).await
+    }
+
+    async fn release_server_lease(&self, instance_id: &str) -> Result<()> {
+        server_leases::postgres::release(self.pool.as_ref(), instance_id).await
+    }
+
+    async fn active_server_leases(&self) -> Result<Vec<ServerLease>> {
+        server_leases::postgres::active(self.pool.as_ref(), self.clock.now_epoch_ms()).await
+    }
+
+    async fn redeem_challenge_nonce(&self, nonce: &str, expires_at: i64) -> Result<bool> {
+        challenge_redemptions::postgres::redeem(
+            self.pool.as_ref(),
+            nonce,
+            expires_at,
+            self.clock.now_epoch_secs(),
+        )
+        .await
+    }
+
+    async fn record_user_usage(&self, hours: &[UserUsageHour]) -> Result<()> {
+        usage::record_user_usage(self, hours).await
+    }
+
+    async fn user_usage(&self, user_id: &str, since: i64) -> Result<Vec<UserUsageHour>> {
+        usage::user_usage(self, user_id, since).await
+    }
+
+    async fn user_usage_totals(
+        &self,
+        user_ids: &[String],
+        since: i64,
+    ) -> Result<Vec<UserUsageTotal>> {
+        usage::user_usage_totals(self, user_ids, since).await
+    }
+
+    async fn get_click_history(
+        &self,
+        short_code: &str,
+        since: i64,
+        time_zone: Tz,
+    ) -> Result<Vec<ClickHistoryEntry>> {
+        clicks::get_click_history(self, short_code, since, time_zone).await
+    }
+
+    async fn compact_click_history(&self, retention_days: i64) -> Result<(i64, i64)> {
+        clicks::compact_click_history(self, retention_days).await
+    }
+
+    async fn count_orphan_analytics(&self) -> Result<OrphanCounts> {
+        verify::count_orphan_analytics(self).await
+    }
+
+    async fn delete_orphan_analytics(&self) -> Result<OrphanCounts> {
+        verify::delete_orphan_analytics(self).await
+    }
+
+    async fn verify_database(&self, fix: bool) -> Result<VerifyReport> {
+        verify::verify_database(self, fix).await
+    }
+
+    async fn search(
+        &self,
+        params: &SearchParams,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<SearchResult> {
+        search::search(self, params, is_admin, user_id).await
+    }
+
+    async fn export_urls(&self, after_id: i64, limit: i64) -> Result<Vec<Arc<ShortenedUrl>>> {
+        transfer::export_urls(self, after_id, limit).await
+    }
+
+    async fn import_urls(&self, urls: &[Arc<ShortenedUrl>], keep_ids: bool) -> Result<u64> {
+        transfer::import_urls(self, urls, keep_ids).await
+    }
+
+    async fn export_users(
+        &self,
+        after: Option<&(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<UserRecord>> {
+        transfer::export_users(self, after, limit).await
+    }
+
+    async fn import_users(&self, users: &[UserRecord]) -> Result<u64> {
+        transfer::import_users(self, users).await
+    }
+
+    async fn export_admins(&self) -> Result<Vec<AdminRecord>> {
+        transfer::export_admins(self).await
+    }
+
+    async fn import_admins(&self, admins: &[AdminRecord]) -> Result<u64> {
+        transfer::import_admins(self, admins).await
+    }
+
+    async fn export_campaigns(&self) -> Result<Vec<Campaign>> {
+        campaigns::postgres::export(self.pool.as_ref()).await
+    }
+
+    async fn import_campaigns(&self, campaigns: &[Campaign]) -> Result<u64> {
+        campaigns::postgres::import(self.pool.as_ref(), campaigns).await
+    }
+
+    async fn export_analytics(
+        &self,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+        transfer::export_analytics(self, after, limit).await
+    }
+
+    async fn import_analytics(&self, rows: &[crate::analytics::AnalyticsEntry]) -> Result<u64> {
+        transfer::import_analytics(self, rows).await
+    }
+
+    async fn count_rows(&self) -> Result<RowCounts> {
+        transfer::count_rows(self).await
+    }
+
+    fn pool_stats(&self) -> Option<PoolStats> {
+        Some(self.monitor.stats(self.pool.as_ref()))
+    }
+
+    async fn probe_pool(&self) {
+        self.monitor.probe(self.pool.as_ref()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,176 @@
+//! The moderation queue for anonymously created links, for `PostgresStorage`.
+
+use crate::models::{ModerationEntry, ModerationStatus};
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the `link_moderation` table.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Review state of links created without signing in
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS link_moderation (
+            short_code TEXT PRIMARY KEY,
+            status TEXT NOT NULL CHECK (status IN ('pending', 'approved', 'rejected')),
+            reason TEXT,
+            decided_at BIGINT,
+            decided_by TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_link_moderation_status ON link_moderation(status)")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(super) async fn submit_for_moderation(
+    storage: &PostgresStorage,
+    short_code: &str,
+    status: ModerationStatus,
+) -> Result<()> {
+    let mut tx = storage.pool.begin().await?;
+
+    sqlx::query("INSERT INTO link_moderation (short_code, status) VALUES ($1, $2)")
+        .bind(short_code)
+        .bind(status.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+    if status == ModerationStatus::Pending {
+        sqlx::query(
+            "UPDATE urls SET is_active = false, updated_at = CASE WHEN is_active THEN $2 ELSE updated_at END WHERE short_code = $1",
+        )
+        .bind(short_code)
+        .bind(storage.clock.now_epoch_ms())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub(super) async fn get_moderation(
+    storage: &PostgresStorage,
+    short_code: &str,
+) -> Result<Option<ModerationEntry>> {
+    let entry = sqlx::query_as::<_, ModerationEntry>(
+        r#"
+        SELECT u.short_code, u.original_url, u.created_at, m.status, m.reason, m.decided_at, m.decided_by
+        FROM link_moderation m
+        JOIN urls u ON u.short_code = m.short_code
+        WHERE m.short_code = $1
+        "#,
+    )
+    .bind(short_code)
+    .fetch_optional(storage.pool.as_ref())
+    .await?;
+
+    Ok(entry)
+}
+
+pub(super) async fn list_moderation(
+    storage: &PostgresStorage,
+    status: ModerationStatus,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ModerationEntry>> {
+    let entries = sqlx::query_as::<_, ModerationEntry>(
+        r#"
+        SELECT u.short_code, u.original_url, u.created_at, m.status, m.reason, m.decided_at, m.decided_by
+        FROM link_moderation m
+        JOIN urls u ON u.short_code = m.short_code
+        WHERE m.status = $1
+        ORDER BY u.created_at ASC, u.id ASC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(status.as_str())
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(entries)
+}
+
+pub(super) async fn approve_link(
+    storage: &PostgresStorage,
+    short_code: &str,
+    decided_by: Option<&str>,
+) -> Result<bool> {
+    let decided_at = storage.clock.now_epoch_secs();
+
+    let mut tx = storage.pool.begin().await?;
+    let approved = sqlx::query(
+        r#"
+        UPDATE link_moderation
+        SET status = 'approved', reason = NULL, decided_at = $1, decided_by = $2
+        WHERE short_code = $3 AND status = 'pending'
+        "#,
+    )
+    .bind(decided_at)
+    .bind(decided_by)
+    .bind(short_code)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if approved {
+        sqlx::query(
+            "UPDATE urls SET is_active = true, updated_at = CASE WHEN is_active THEN updated_at ELSE $2 END WHERE short_code = $1",
+        )
+        .bind(short_code)
+        .bind(storage.clock.now_epoch_ms())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(approved)
+}
+
+pub(super) async fn reject_link(
+    storage: &PostgresStorage,
+    short_code: &str,
+    reason: &str,
+    decided_by: Option<&str>,
+) -> Result<bool> {
+    let decided_at = storage.clock.now_epoch_secs();
+
+    let mut tx = storage.pool.begin().await?;
+    let rejected = sqlx::query(
+        r#"
+        UPDATE link_moderation
+        SET status = 'rejected', reason = $1, decided_at = $2, decided_by = $3
+        WHERE short_code = $4 AND status IN ('pending', 'approved')
+        "#,
+    )
+    .bind(reason)
+    .bind(decided_at)
+    .bind(decided_by)
+    .bind(short_code)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if rejected {
+        sqlx::query(
+            "UPDATE urls SET is_active = false, updated_at = CASE WHEN is_active THEN $2 ELSE updated_at END WHERE short_code = $1",
+        )
+        .bind(short_code)
+        .bind(storage.clock.now_epoch_ms())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(rejected)
+}
@@ -0,0 +1,171 @@
+//! Instance statistics and activity reports for `PostgresStorage`.
+
+use crate::analytics::daily::{day_start, RollupSplit, DAY_SECS};
+use crate::models::{activity_series, ActivityDay, DailyRanking, InstanceStatsDay};
+use crate::storage::Storage;
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the `instance_stats_daily` table.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // One row per recorded UTC day of instance-wide totals
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS instance_stats_daily (
+            day BIGINT PRIMARY KEY,
+            total_links BIGINT NOT NULL,
+            active_links BIGINT NOT NULL,
+            links_created BIGINT NOT NULL,
+            clicks BIGINT NOT NULL,
+            active_users BIGINT NOT NULL,
+            recorded_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn record_instance_stats(
+    storage: &PostgresStorage,
+    day: i64,
+) -> Result<InstanceStatsDay> {
+    let day = day_start(day);
+    let day_end = day + DAY_SECS;
+    // Link timestamps are in milliseconds, click history hours in seconds
+    let (day_start_ms, day_end_ms) = (day * 1000, day_end * 1000);
+    let stats = sqlx::query_as::<_, InstanceStatsDay>(
+        r#"
+        INSERT INTO instance_stats_daily (day, total_links, active_links, links_created, clicks, active_users, recorded_at)
+        SELECT
+            $1,
+            COUNT(*),
+            COUNT(*) FILTER (WHERE is_active),
+            COUNT(*) FILTER (WHERE created_at >= $2),
+            (SELECT COALESCE(SUM(clicks), 0)::BIGINT FROM click_history WHERE hour >= $1 AND hour < $3),
+            COUNT(DISTINCT created_by) FILTER (WHERE created_at >= $2),
+            $4
+        FROM urls
+        WHERE alias_of IS NULL AND reserved_until IS NULL AND created_at < $5
+        ON CONFLICT (day) DO UPDATE SET
+            total_links = EXCLUDED.total_links,
+            active_links = EXCLUDED.active_links,
+            links_created = EXCLUDED.links_created,
+            clicks = EXCLUDED.clicks,
+            active_users = EXCLUDED.active_users,
+            recorded_at = EXCLUDED.recorded_at
+        RETURNING day, total_links, active_links, links_created, clicks, active_users
+        "#,
+    )
+    .bind(day)
+    .bind(day_start_ms)
+    .bind(day_end)
+    .bind(storage.clock.now_epoch_secs())
+    .bind(day_end_ms)
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(stats)
+}
+
+pub(super) async fn instance_stats_history(
+    storage: &PostgresStorage,
+    since: i64,
+) -> Result<Vec<InstanceStatsDay>> {
+    let history = sqlx::query_as::<_, InstanceStatsDay>(
+        "SELECT day, total_links, active_links, links_created, clicks, active_users FROM instance_stats_daily WHERE day >= $1 ORDER BY day",
+    )
+    .bind(since)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(history)
+}
+
+pub(super) async fn activity_report(
+    storage: &PostgresStorage,
+    since: i64,
+    until: i64,
+    top: i64,
+) -> Result<Vec<ActivityDay>> {
+    // Link timestamps are in milliseconds; the rest in seconds. Links
+    // without a creator count toward the day but rank last, and are
+    // dropped from the top list when they make it in.
+    let creators = sqlx::query_as::<_, DailyRanking>(
+        r#"
+        SELECT day, created_by AS key, n AS count, total FROM (
+            SELECT day, created_by, n,
+                   SUM(n) OVER (PARTITION BY day)::BIGINT AS total,
+                   ROW_NUMBER() OVER (PARTITION BY day ORDER BY created_by IS NULL, n DESC, created_by) AS place
+            FROM (
+                SELECT (created_at / 86400000) * 86400 AS day, created_by, COUNT(*) AS n
+                FROM urls
+                WHERE alias_of IS NULL AND reserved_until IS NULL AND created_at >= $1 AND created_at < $2
+                GROUP BY 1, 2
+            ) per_user
+        ) ranked
+        WHERE place <= $3
+        ORDER BY day, place
+        "#,
+    )
+    .bind(since * 1000)
+    .bind(until * 1000)
+    .bind(top.max(1))
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    let links = sqlx::query_as::<_, DailyRanking>(
+        r#"
+        SELECT day, short_code AS key, n AS count, total FROM (
+            SELECT day, short_code, n,
+                   SUM(n) OVER (PARTITION BY day)::BIGINT AS total,
+                   ROW_NUMBER() OVER (PARTITION BY day ORDER BY n DESC, short_code) AS place
+            FROM (
+                SELECT (hour / 86400) * 86400 AS day, short_code, SUM(clicks)::BIGINT AS n
+                FROM click_history
+                WHERE hour >= $1 AND hour < $2
+                GROUP BY 1, 2
+            ) per_link
+        ) ranked
+        WHERE place <= $3
+        ORDER BY day, place
+        "#,
+    )
+    .bind(since)
+    .bind(until)
+    .bind(top.max(1))
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    // Whole days inside the rollup watermark come from analytics_daily
+    let rolled_until = storage.analytics_daily_rolled_until().await?;
+    let (daily_from, daily_until) = RollupSplit::of(Some(since), Some(until - 1), rolled_until)
+        .map_or((until, until), |split| {
+            (split.daily_from, split.daily_until)
+        });
+    let visits: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT day, SUM(visit_count)::BIGINT FROM (
+            SELECT (time_bucket / 86400) * 86400 AS day, visit_count FROM analytics
+            WHERE (time_bucket >= $1 AND time_bucket < $2) OR (time_bucket >= $3 AND time_bucket < $4)
+            UNION ALL
+            SELECT day, visit_count FROM analytics_daily WHERE day >= $5 AND day < $6
+        ) visits
+        GROUP BY day
+        "#,
+    )
+    .bind(since)
+    .bind(daily_from)
+    .bind(daily_until)
+    .bind(until)
+    .bind(daily_from)
+    .bind(daily_until)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(activity_series(
+        since, until, DAY_SECS, creators, links, visits,
+    ))
+}
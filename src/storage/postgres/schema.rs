@@ -0,0 +1,377 @@
+//! Schema setup for `PostgresStorage`: the `urls` table and its delete
+//! protection, and the order `init()` creates each feature's tables in.
+
+use crate::config::UrlNormalizationConfig;
+use crate::destination::{destination_host, normalize_url};
+use crate::storage::campaigns;
+use crate::storage::challenge_redemptions;
+use crate::storage::server_leases;
+use anyhow::Result;
+
+use super::{
+    analytics, audit, clicks, links, migrate, moderation, reports, rollups, search, usage, users,
+    PostgresStorage,
+};
+
+/// Rows read per batch while filling `urls.dest_host` or
+/// `urls.normalized_url` for existing links.
+const DEST_HOST_BACKFILL_BATCH: i64 = 1_000;
+
+/// Fill `urls.dest_host` from `original_url` for every existing row, a
+/// batch of ids at a time.
+async fn backfill_dest_host(connection: &mut sqlx::PgConnection) -> Result<()> {
+    let mut after = 0_i64;
+    loop {
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, original_url FROM urls WHERE id > $1 ORDER BY id LIMIT $2")
+                .bind(after)
+                .bind(DEST_HOST_BACKFILL_BATCH)
+                .fetch_all(&mut *connection)
+                .await?;
+        let Some((last, _)) = rows.last() else {
+            return Ok(());
+        };
+        after = *last;
+        let (ids, hosts): (Vec<i64>, Vec<String>) = rows
+            .iter()
+            .filter_map(|(id, original_url)| Some((*id, destination_host(original_url)?)))
+            .unzip();
+        sqlx::query(
+            r#"
+            UPDATE urls SET dest_host = v.host
+            FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS v(id, host)
+            WHERE urls.id = v.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&hosts)
+        .execute(&mut *connection)
+        .await?;
+    }
+}
+
+/// Fill `urls.normalized_url` from `original_url` for every existing row, a
+/// batch of ids at a time.
+async fn backfill_normalized_url(
+    connection: &mut sqlx::PgConnection,
+    normalization: &UrlNormalizationConfig,
+) -> Result<()> {
+    let mut after = 0_i64;
+    loop {
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, original_url FROM urls WHERE id > $1 ORDER BY id LIMIT $2")
+                .bind(after)
+                .bind(DEST_HOST_BACKFILL_BATCH)
+                .fetch_all(&mut *connection)
+                .await?;
+        let Some((last, _)) = rows.last() else {
+            return Ok(());
+        };
+        after = *last;
+        let (ids, normalized): (Vec<i64>, Vec<String>) = rows
+            .iter()
+            .filter_map(|(id, original_url)| {
+                Some((*id, normalize_url(original_url, normalization)?))
+            })
+            .unzip();
+        sqlx::query(
+            r#"
+            UPDATE urls SET normalized_url = v.normalized
+            FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS v(id, normalized)
+            WHERE urls.id = v.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&normalized)
+        .execute(&mut *connection)
+        .await?;
+    }
+}
+
+pub(super) async fn ensure_schema(storage: &PostgresStorage) -> Result<()> {
+    if let Some(schema) = &storage.schema {
+        // The name was validated on connect, so it needs no quoting.
+        sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {}", schema))
+            .execute(storage.pool.as_ref())
+            .await?;
+    }
+
+    // Create URLs table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS urls (
+            id BIGSERIAL PRIMARY KEY,
+            short_code TEXT NOT NULL UNIQUE,
+            original_url TEXT NOT NULL,
+            created_at BIGINT NOT NULL,
+            created_by TEXT,
+            clicks BIGINT NOT NULL DEFAULT 0,
+            is_active BOOLEAN NOT NULL DEFAULT true
+        )
+        "#,
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_short_code ON urls(short_code)")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_created_by ON urls(created_by)")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // Reserved codes hold a placeholder destination until `reserved_until`
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS reserved_until BIGINT")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_reserved_until ON urls(reserved_until) WHERE reserved_until IS NOT NULL",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    // A renamed code keeps redirecting as an alias of its new code
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS alias_of TEXT")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_alias_of ON urls(alias_of) WHERE alias_of IS NOT NULL",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    // Destination page title, fetched in the background after creation
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS title TEXT")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // How each link was created; rows from before the column are unknown
+    sqlx::query(
+        "ALTER TABLE urls ADD COLUMN IF NOT EXISTS created_via TEXT NOT NULL DEFAULT 'unknown'",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    // Set by the click flush; links never visited since the column exists are NULL
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS last_visited_at BIGINT")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // Recorded from the creator's claims; links created before are NULL
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS created_by_auth_method TEXT")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // Per-link options as one JSON object (see `LinkOptions`)
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS options TEXT NOT NULL DEFAULT '{}'")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // Last change to what a link does; existing rows get theirs from
+    // migration 2
+    sqlx::query("ALTER TABLE urls ADD COLUMN IF NOT EXISTS updated_at BIGINT NOT NULL DEFAULT 0")
+        .execute(storage.pool.as_ref())
+        .await?;
+
+    // Destination host for reports by domain, derived in Rust from
+    // original_url on every write. Existing rows are filled when the
+    // column is added, under a table lock so a concurrent init waits and
+    // then finds the column.
+    let mut tx = storage.pool.begin().await?;
+    sqlx::query("LOCK TABLE urls IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+    let has_dest_host: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema()
+              AND table_name = 'urls'
+              AND column_name = 'dest_host'
+        )
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !has_dest_host {
+        sqlx::query("ALTER TABLE urls ADD COLUMN dest_host TEXT")
+            .execute(&mut *tx)
+            .await?;
+        backfill_dest_host(&mut tx).await?;
+    }
+    // Canonical destination for dedupe, derived the same way with the
+    // configured URL_NORMALIZE_STEPS
+    let has_normalized_url: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema()
+              AND table_name = 'urls'
+              AND column_name = 'normalized_url'
+        )
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !has_normalized_url {
+        sqlx::query("ALTER TABLE urls ADD COLUMN normalized_url TEXT")
+            .execute(&mut *tx)
+            .await?;
+        backfill_normalized_url(&mut tx, &storage.url_normalization).await?;
+    }
+    tx.commit().await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_dest_host ON urls(dest_host) WHERE dest_host IS NOT NULL",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_normalized_url ON urls(normalized_url) WHERE normalized_url IS NOT NULL",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    campaigns::postgres::create_schema(storage.pool.as_ref()).await?;
+    server_leases::postgres::create_schema(storage.pool.as_ref()).await?;
+    challenge_redemptions::postgres::create_schema(storage.pool.as_ref()).await?;
+
+    // Index for cursor-based pagination (created_at DESC, id DESC)
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_created_at_id ON urls(created_at DESC, id DESC)",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    // Index for user-specific cursor pagination
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_created_by_created_at_id ON urls(created_by, created_at DESC, id DESC)",
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    users::create_schema(storage.pool.as_ref()).await?;
+    analytics::create_schema(storage.pool.as_ref()).await?;
+    audit::create_schema(storage.pool.as_ref()).await?;
+    moderation::create_schema(storage.pool.as_ref()).await?;
+    rollups::create_schema(storage.pool.as_ref()).await?;
+
+    // Runtime settings admins change without a redeploy
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    usage::create_schema(storage.pool.as_ref()).await?;
+    reports::create_schema(storage.pool.as_ref()).await?;
+    links::create_schema(storage.pool.as_ref()).await?;
+    clicks::create_schema(storage.pool.as_ref()).await?;
+
+    // Security: Set up delete protection in a transaction to ensure consistency
+    // This prevents race conditions when multiple init() calls happen concurrently
+    let mut tx = storage.pool.begin().await?;
+
+    // Create function to prevent DELETE operations on urls table
+    sqlx::query(
+        r#"
+        CREATE OR REPLACE FUNCTION prevent_urls_delete()
+        RETURNS TRIGGER AS $$
+        BEGIN
+            RAISE EXCEPTION 'DELETE operations are not allowed on the urls table. Use deactivation instead.';
+            RETURN NULL;
+        END;
+        $$ LANGUAGE plpgsql
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Create trigger to prevent DELETE operations. The lookup is scoped to
+    // this schema's urls table: another schema may already have one.
+    sqlx::query(
+        r#"
+        DO $$
+        BEGIN
+            IF NOT EXISTS (
+                SELECT 1 FROM pg_trigger
+                WHERE tgname = 'prevent_urls_delete_trigger'
+                  AND tgrelid = 'urls'::regclass
+            ) THEN
+                CREATE TRIGGER prevent_urls_delete_trigger
+                BEFORE DELETE ON urls
+                FOR EACH ROW
+                EXECUTE FUNCTION prevent_urls_delete();
+            END IF;
+        END
+        $$
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Create function to prevent TRUNCATE operations on urls table
+    sqlx::query(
+        r#"
+        CREATE OR REPLACE FUNCTION prevent_urls_truncate()
+        RETURNS TRIGGER AS $$
+        BEGIN
+            RAISE EXCEPTION 'TRUNCATE operations are not allowed on the urls table. Use deactivation instead.';
+            RETURN NULL;
+        END;
+        $$ LANGUAGE plpgsql
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Create trigger to prevent TRUNCATE operations
+    sqlx::query(
+        r#"
+        DO $$
+        BEGIN
+            IF NOT EXISTS (
+                SELECT 1 FROM pg_trigger
+                WHERE tgname = 'prevent_urls_truncate_trigger'
+                  AND tgrelid = 'urls'::regclass
+            ) THEN
+                CREATE TRIGGER prevent_urls_truncate_trigger
+                BEFORE TRUNCATE ON urls
+                FOR EACH STATEMENT
+                EXECUTE FUNCTION prevent_urls_truncate();
+            END IF;
+        END
+        $$
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Attempt to revoke DELETE permission on urls table
+    // Note: This may fail if we don't have permission to REVOKE,
+    // which is acceptable as the triggers provide the primary protection
+    let _ = sqlx::query("REVOKE DELETE ON urls FROM PUBLIC")
+        .execute(&mut *tx)
+        .await;
+
+    // Also try to revoke from the current user
+    let _ = sqlx::query("REVOKE DELETE ON urls FROM CURRENT_USER")
+        .execute(&mut *tx)
+        .await;
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    search::create_schema(storage.pool.as_ref()).await?;
+
+    migrate::apply_migrations(storage).await
+}
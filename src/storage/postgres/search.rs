@@ -0,0 +1,234 @@
+//! Link search for `PostgresStorage`: exact codes first, then trigram matches.
+
+use crate::models::{CreatedVia, ShortenedUrl};
+use crate::storage::relevance::rank_by_relevance;
+use crate::storage::{
+    SearchParams, SearchResult, SearchSort, RELEVANCE_MAX_OFFSET, SEARCH_EXTRA_ROWS, URL_COLUMNS,
+};
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the `pg_trgm` extension and the trigram indexes search uses,
+/// where the database allows it.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Enable pg_trgm extension for trigram substring search
+    // This may fail if the extension is not available, which is acceptable
+    let _ = sqlx::query("CREATE EXTENSION IF NOT EXISTS pg_trgm")
+        .execute(pool)
+        .await;
+
+    // Create trigram indexes for substring search
+    // GIN index on short_code for case-sensitive LIKE searches
+    let _ = sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_short_code_trgm ON urls USING GIN (short_code gin_trgm_ops)",
+    )
+    .execute(pool)
+    .await;
+
+    // GIN index on lower(original_url) for case-insensitive LIKE searches
+    let _ = sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_original_url_trgm ON urls USING GIN (lower(original_url) gin_trgm_ops)",
+    )
+    .execute(pool)
+    .await;
+    Ok(())
+}
+
+/// The link whose short code is exactly the search query, if it passes
+/// the search filters. One unique-index lookup, so it is cheap next to
+/// the substring match.
+async fn pg_search_exact_code(
+    storage: &PostgresStorage,
+    params: &SearchParams,
+    created_by: Option<&str>,
+) -> Result<Option<ShortenedUrl>> {
+    let created_via = params.created_via.map(CreatedVia::as_str);
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls u
+        WHERE u.short_code = $1
+          AND ($2::TEXT IS NULL OR ($2 = '__null__' AND u.created_by IS NULL) OR u.created_by = $2)
+          AND ($3::BIGINT IS NULL OR u.created_at >= $3)
+          AND ($4::BIGINT IS NULL OR u.created_at < $4)
+          AND ($5::BOOLEAN IS NULL OR u.is_active = $5)
+          AND ($6::TEXT IS NULL OR u.created_via = $6)
+          AND ($7::BIGINT IS NULL OR COALESCE(u.last_visited_at, 0) < $7)
+          AND ($8::BIGINT IS NULL OR u.campaign_id = $8)
+        "#
+    ))
+    .bind(&params.q)
+    .bind(created_by)
+    .bind(params.created_from)
+    .bind(params.created_to)
+    .bind(params.is_active)
+    .bind(created_via)
+    .bind(params.unused_since)
+    .bind(params.campaign_id)
+    .fetch_optional(storage.pool.as_ref())
+    .await?;
+    Ok(url)
+}
+
+/// Substring matches other than the exact code, best match first by
+/// pg_trgm similarity of code and destination combined, then newest.
+async fn pg_search_by_relevance(
+    storage: &PostgresStorage,
+    params: &SearchParams,
+    like_pattern: &str,
+    created_by: Option<&str>,
+    offset: i64,
+) -> Result<Vec<ShortenedUrl>, sqlx::Error> {
+    let created_via = params.created_via.map(CreatedVia::as_str);
+    sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls u
+        WHERE (u.short_code LIKE $1 OR lower(u.original_url) LIKE lower($1))
+          AND u.short_code <> $2
+          AND ($3::TEXT IS NULL OR ($3 = '__null__' AND u.created_by IS NULL) OR u.created_by = $3)
+          AND ($4::BIGINT IS NULL OR u.created_at >= $4)
+          AND ($5::BIGINT IS NULL OR u.created_at < $5)
+          AND ($6::BOOLEAN IS NULL OR u.is_active = $6)
+          AND ($7::TEXT IS NULL OR u.created_via = $7)
+          AND ($8::BIGINT IS NULL OR COALESCE(u.last_visited_at, 0) < $8)
+          AND ($11::BIGINT IS NULL OR u.campaign_id = $11)
+        ORDER BY similarity(u.short_code, $2) + similarity(u.original_url, $2) DESC,
+                 u.created_at DESC, u.id DESC
+        LIMIT $9 OFFSET $10
+        "#
+    ))
+    .bind(like_pattern)
+    .bind(&params.q)
+    .bind(created_by)
+    .bind(params.created_from)
+    .bind(params.created_to)
+    .bind(params.is_active)
+    .bind(created_via)
+    .bind(params.unused_since)
+    .bind(params.limit + 1)
+    .bind(offset)
+    .bind(params.campaign_id)
+    .fetch_all(storage.pool.as_ref())
+    .await
+}
+
+/// Links whose code or destination contains `like_pattern` and pass
+/// the search filters, newest first, after `params.cursor` when it is
+/// set.
+async fn pg_search_matches(
+    storage: &PostgresStorage,
+    params: &SearchParams,
+    like_pattern: &str,
+    created_by: Option<&str>,
+    fetch_limit: i64,
+) -> Result<Vec<ShortenedUrl>> {
+    let (cursor_created_at, cursor_id) = params.cursor.unzip();
+    let urls = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE (short_code LIKE $1 OR lower(original_url) LIKE lower($1))
+          AND ($2::TEXT IS NULL OR ($2 = '__null__' AND created_by IS NULL) OR created_by = $2)
+          AND ($3::BIGINT IS NULL OR created_at >= $3)
+          AND ($4::BIGINT IS NULL OR created_at < $4)
+          AND ($5::BOOLEAN IS NULL OR is_active = $5)
+          AND ($6::TEXT IS NULL OR created_via = $6)
+          AND ($7::BIGINT IS NULL OR COALESCE(last_visited_at, 0) < $7)
+          AND ($8::BIGINT IS NULL OR campaign_id = $8)
+          AND ($9::BIGINT IS NULL OR (created_at, id) < ($9, $10))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $11
+        "#
+    ))
+    .bind(like_pattern)
+    .bind(created_by)
+    .bind(params.created_from)
+    .bind(params.created_to)
+    .bind(params.is_active)
+    .bind(params.created_via.map(CreatedVia::as_str))
+    .bind(params.unused_since)
+    .bind(params.campaign_id)
+    .bind(cursor_created_at)
+    .bind(cursor_id)
+    .bind(fetch_limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+    Ok(urls)
+}
+
+pub(super) async fn search(
+    storage: &PostgresStorage,
+    params: &SearchParams,
+    is_admin: bool,
+    user_id: Option<&str>,
+) -> Result<SearchResult> {
+    // Build the LIKE pattern for substring search
+    // Escape backslashes first, then special LIKE characters
+    let like_pattern = format!(
+        "%{}%",
+        params
+            .q
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    // Build the query based on user permissions
+    // Non-admin users can only search their own URLs
+    let effective_created_by = if is_admin {
+        params.created_by.clone()
+    } else {
+        // Non-admin must search only their own URLs
+        user_id.map(|s| s.to_string())
+    };
+
+    let exact = pg_search_exact_code(storage, params, effective_created_by.as_deref()).await?;
+
+    // A keyset cursor under relevance sort continues a search that fell
+    // back to ranking each page below
+    if params.sort == SearchSort::Relevance && params.cursor.is_none() {
+        let offset = params.offset.unwrap_or(0).clamp(0, RELEVANCE_MAX_OFFSET);
+        match pg_search_by_relevance(
+            storage,
+            params,
+            &like_pattern,
+            effective_created_by.as_deref(),
+            offset,
+        )
+        .await
+        {
+            Ok(rows) => {
+                return Ok(SearchResult::from_offset_rows(
+                    rows,
+                    exact,
+                    params.limit,
+                    offset,
+                ))
+            }
+            // Without pg_trgm there is no similarity(): rank each
+            // recency-ordered page instead, as SQLite does
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42883") => {
+                tracing::debug!("pg_trgm unavailable, ranking search pages in process");
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let urls = pg_search_matches(
+        storage,
+        params,
+        &like_pattern,
+        effective_created_by.as_deref(),
+        params.limit + SEARCH_EXTRA_ROWS,
+    )
+    .await?;
+
+    let mut result = SearchResult::from_rows(urls, exact, params.limit, params.cursor.is_none());
+    if params.sort == SearchSort::Relevance {
+        let pinned = usize::from(result.exact_match);
+        rank_by_relevance(&mut result.items[pinned..], &params.q);
+    }
+    Ok(result)
+}
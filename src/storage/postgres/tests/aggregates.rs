@@ -0,0 +1,144 @@
+use super::super::aggregates::aggregate_query;
+use super::*;
+use crate::analytics::AnalyticsGroupBy;
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_country() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    // Create a short code
+    storage
+        .create_with_code("multi", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert analytics from multiple countries
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup(
+            "multi",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            Some(15169),
+            10,
+        ),
+        rollup(
+            "multi",
+            time_bucket,
+            Some("GB"),
+            Some("England"),
+            Some("London"),
+            Some(16509),
+            5,
+        ),
+        rollup(
+            "multi",
+            time_bucket,
+            Some("US"),
+            Some("NY"),
+            Some("NYC"),
+            Some(15169),
+            3,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by country
+    let aggregates = storage
+        .get_analytics_aggregate("multi", None, None, AnalyticsGroupBy::Country, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 2); // US and GB
+
+    // Verify totals
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 18);
+
+    // Find US aggregate (should be 13 = 10 + 3)
+    let us_agg = aggregates.iter().find(|a| a.dimension == "US").unwrap();
+    assert_eq!(us_agg.visit_count, 13);
+
+    // Find GB aggregate (should be 5)
+    let gb_agg = aggregates.iter().find(|a| a.dimension == "GB").unwrap();
+    assert_eq!(gb_agg.visit_count, 5);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_asn() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    storage
+        .create_with_code("test_agg_asn", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup(
+            "test_agg_asn",
+            time_bucket,
+            Some("US"),
+            None,
+            None,
+            Some(15169),
+            8,
+        ),
+        rollup(
+            "test_agg_asn",
+            time_bucket,
+            Some("US"),
+            None,
+            None,
+            Some(16509),
+            3,
+        ),
+        rollup(
+            "test_agg_asn",
+            time_bucket,
+            Some("GB"),
+            None,
+            None,
+            Some(15169),
+            2,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by ASN
+    let aggregates = storage
+        .get_analytics_aggregate("test_agg_asn", None, None, AnalyticsGroupBy::Asn, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 2);
+
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 13);
+
+    // ASN 15169 should have 10 visits (8 + 2)
+    let asn_agg = aggregates.iter().find(|a| a.dimension == "15169").unwrap();
+    assert_eq!(asn_agg.visit_count, 10);
+}
+
+#[test]
+fn test_aggregate_queries_bind_every_request_value() {
+    for group_by in AnalyticsGroupBy::ALL {
+        let sql = aggregate_query(group_by);
+        // short_code, start, end and limit are all placeholders
+        for placeholder in ["$1", "$2", "$3", "$4"] {
+            assert!(sql.contains(placeholder), "{sql}");
+        }
+        assert!(!sql.contains("$5") && !sql.contains('{'), "{sql}");
+    }
+}
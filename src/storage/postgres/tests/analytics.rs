@@ -0,0 +1,171 @@
+use super::*;
+
+#[tokio::test]
+async fn test_analytics_upsert_and_retrieval() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    // Create a short code
+    storage
+        .create_with_code("test123", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert analytics records
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup(
+            "test123",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("San Francisco"),
+            Some(15169),
+            5,
+        ),
+        rollup(
+            "test123",
+            time_bucket,
+            Some("GB"),
+            Some("England"),
+            Some("London"),
+            Some(16509),
+            3,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Retrieve analytics
+    let analytics = storage
+        .get_analytics("test123", None, None, 100)
+        .await
+        .unwrap();
+
+    assert_eq!(analytics.len(), 2);
+
+    // Verify visit counts
+    let total_visits: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total_visits, 8);
+}
+
+#[tokio::test]
+async fn test_analytics_upserts_with_null_dimensions_share_a_row() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    storage
+        .create_with_code("nulldims", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Separate batches, so the second can only land on the first's row
+    // through the conflict target
+    let time_bucket = 1698768000;
+    for (country, visits) in [(None, 2), (None, 3), (Some("US"), 4), (Some("US"), 1)] {
+        storage
+            .upsert_analytics_batch(vec![rollup(
+                "nulldims",
+                time_bucket,
+                country,
+                None,
+                None,
+                None,
+                visits,
+            )])
+            .await
+            .unwrap();
+    }
+
+    let mut counts: Vec<i64> = storage
+        .get_analytics("nulldims", None, None, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|a| a.visit_count)
+        .collect();
+    counts.sort();
+    assert_eq!(counts, [5, 5]);
+}
+
+#[tokio::test]
+async fn test_analytics_time_range_filtering() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    storage
+        .create_with_code("test_time_range", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert records with different time buckets
+    let records = vec![
+        rollup("test_time_range", 1000, Some("US"), None, None, None, 5),
+        rollup("test_time_range", 2000, Some("US"), None, None, None, 3),
+        rollup("test_time_range", 3000, Some("US"), None, None, None, 7),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Query with start and end time
+    let analytics = storage
+        .get_analytics("test_time_range", Some(1500), Some(2500), 100)
+        .await
+        .unwrap();
+    let total: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 3); // Only record from 2000
+}
+
+#[tokio::test]
+async fn test_analytics_upsert_increments_existing() {
+    let Some(storage) = setup_postgres().await else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+
+    storage
+        .create_with_code("test_upsert_incr", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+
+    // Insert initial record - all fields need to match for the UNIQUE constraint to trigger
+    let records = vec![rollup(
+        "test_upsert_incr",
+        time_bucket,
+        Some("US"),
+        Some("CA"),
+        Some("SF"),
+        Some(15169),
+        5,
+    )];
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Upsert same key with more visits - all fields must match
+    let records = vec![rollup(
+        "test_upsert_incr",
+        time_bucket,
+        Some("US"),
+        Some("CA"),
+        Some("SF"),
+        Some(15169),
+        3,
+    )];
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Should have incremented, not replaced
+    let analytics = storage
+        .get_analytics("test_upsert_incr", None, None, 100)
+        .await
+        .unwrap();
+
+    assert_eq!(analytics.len(), 1);
+    assert_eq!(analytics[0].visit_count, 8); // 5 + 3
+}
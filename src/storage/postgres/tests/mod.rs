@@ -0,0 +1,59 @@
+//! Tests for `PostgresStorage`, grouped by feature. Those that need a
+//! database skip themselves when `DATABASE_URL` is not set.
+
+use super::*;
+use crate::analytics::AnalyticsRollup;
+use crate::analytics::IpVersion;
+use crate::storage::Storage;
+use std::sync::Arc;
+
+mod aggregates;
+mod analytics;
+
+/// Build an analytics rollup row for tests. `ip_version` is fixed to IPv4,
+/// which is what every fixture below exercises.
+fn rollup(
+    short_code: &str,
+    time_bucket: i64,
+    country_code: Option<&str>,
+    region: Option<&str>,
+    city: Option<&str>,
+    asn: Option<i64>,
+    visit_count: i64,
+) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket,
+        country_code: country_code.map(str::to_string),
+        region: region.map(str::to_string),
+        city: city.map(str::to_string),
+        asn,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+// Helper to set up a test PostgreSQL database
+// Note: These tests require a running PostgreSQL instance
+// Set DATABASE_URL environment variable to run these tests
+async fn setup_postgres() -> Option<Arc<dyn Storage>> {
+    let db_url = std::env::var("DATABASE_URL").ok()?;
+    let storage = PostgresStorage::new(&db_url, 5).await.ok()?;
+    storage.init().await.ok()?;
+    Some(Arc::new(storage))
+}
+
+#[test]
+fn test_schema_names_that_need_quoting_are_rejected() {
+    for valid in ["lynx", "_lynx", "lynx_2", &"a".repeat(63)] {
+        assert!(validate_schema_name(valid).is_ok(), "{valid}");
+    }
+    let too_long = "a".repeat(64);
+    for invalid in [
+        "", "Lynx", "2lynx", "ly-nx", "ly nx", "lynx;", "pg_lynx", &too_long,
+    ] {
+        assert!(validate_schema_name(invalid).is_err(), "{invalid}");
+    }
+}
@@ -0,0 +1,247 @@
+//! Export and import of every table, for copying between databases, for `PostgresStorage`.
+
+use crate::destination::destination_host;
+use crate::models::ShortenedUrl;
+use crate::storage::copy::{AdminRecord, RowCounts, UserRecord};
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+pub(super) async fn export_urls(
+    storage: &PostgresStorage,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let urls = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE id > $1
+        ORDER BY id
+        LIMIT $2
+        "#
+    ))
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
+
+pub(super) async fn import_urls(
+    storage: &PostgresStorage,
+    urls: &[Arc<ShortenedUrl>],
+    keep_ids: bool,
+) -> Result<u64> {
+    let mut inserted = 0;
+    let mut tx = storage.pool.begin().await?;
+    for url in urls {
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO urls (id, short_code, original_url, created_at, created_by, clicks, is_active, reserved_until, alias_of, title, created_via, last_visited_at, updated_at, created_by_auth_method, options, campaign_id, dest_host, normalized_url)
+            VALUES (COALESCE($1, nextval(pg_get_serial_sequence('urls', 'id'))), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(keep_ids.then_some(url.id))
+        .bind(&url.short_code)
+        .bind(&url.original_url)
+        .bind(url.created_at)
+        .bind(&url.created_by)
+        .bind(url.clicks)
+        .bind(url.is_active)
+        .bind(url.reserved_until)
+        .bind(&url.alias_of)
+        .bind(&url.title)
+        .bind(url.created_via.as_str())
+        .bind(url.last_visited_at)
+        .bind(url.updated_at)
+        .bind(&url.created_by_auth_method)
+        .bind(url.options.to_json())
+        .bind(url.campaign_id)
+        .bind(destination_host(&url.original_url))
+        .bind(storage.normalized_url(&url.original_url))
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    if keep_ids && inserted > 0 {
+        // BIGSERIAL does not see explicit ids; later inserts would collide.
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('urls', 'id'), (SELECT MAX(id) FROM urls))",
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+pub(super) async fn export_users(
+    storage: &PostgresStorage,
+    after: Option<&(String, String)>,
+    limit: i64,
+) -> Result<Vec<UserRecord>> {
+    let (after_user, after_method) = after.map_or((None, None), |(user_id, auth_method)| {
+        (Some(user_id.as_str()), Some(auth_method.as_str()))
+    });
+    let users = sqlx::query_as::<_, UserRecord>(
+        r#"
+        SELECT user_id, auth_method, email, created_at, updated_at, last_seen_at
+        FROM users
+        WHERE $1::TEXT IS NULL OR (user_id, auth_method) > ($1, $2::TEXT)
+        ORDER BY user_id, auth_method
+        LIMIT $3
+        "#,
+    )
+    .bind(after_user)
+    .bind(after_method)
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(users)
+}
+
+pub(super) async fn import_users(storage: &PostgresStorage, users: &[UserRecord]) -> Result<u64> {
+    let mut inserted = 0;
+    let mut tx = storage.pool.begin().await?;
+    for user in users {
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO users (user_id, auth_method, email, created_at, updated_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id, auth_method) DO NOTHING
+            "#,
+        )
+        .bind(&user.user_id)
+        .bind(&user.auth_method)
+        .bind(&user.email)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.last_seen_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+pub(super) async fn export_admins(storage: &PostgresStorage) -> Result<Vec<AdminRecord>> {
+    let admins = sqlx::query_as::<_, AdminRecord>(
+        "SELECT user_id, auth_method, promoted_at FROM admin_users ORDER BY user_id, auth_method",
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(admins)
+}
+
+pub(super) async fn import_admins(
+    storage: &PostgresStorage,
+    admins: &[AdminRecord],
+) -> Result<u64> {
+    let mut inserted = 0;
+    let mut tx = storage.pool.begin().await?;
+    for admin in admins {
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO admin_users (user_id, auth_method, promoted_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, auth_method) DO NOTHING
+            "#,
+        )
+        .bind(&admin.user_id)
+        .bind(&admin.auth_method)
+        .bind(admin.promoted_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+pub(super) async fn export_analytics(
+    storage: &PostgresStorage,
+    after: Option<(i64, i64)>,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+    let (after_bucket, after_id) = after.unwrap_or((i64::MIN, i64::MIN));
+    let rows = sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+        r#"
+        SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at
+        FROM analytics
+        WHERE (time_bucket, id) > ($1, $2)
+        ORDER BY time_bucket, id
+        LIMIT $3
+        "#,
+    )
+    .bind(after_bucket)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(rows)
+}
+
+pub(super) async fn import_analytics(
+    storage: &PostgresStorage,
+    rows: &[crate::analytics::AnalyticsEntry],
+) -> Result<u64> {
+    let mut inserted = 0;
+    let mut tx = storage.pool.begin().await?;
+    for row in rows {
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO analytics (short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+            DO UPDATE SET
+                visit_count = analytics.visit_count + EXCLUDED.visit_count,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(&row.short_code)
+        .bind(row.time_bucket)
+        .bind(&row.country_code)
+        .bind(&row.region)
+        .bind(&row.city)
+        .bind(row.asn)
+        .bind(row.ip_version)
+        .bind(row.visit_count)
+        .bind(row.created_at)
+        .bind(row.updated_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+pub(super) async fn count_rows(storage: &PostgresStorage) -> Result<RowCounts> {
+    let counts = sqlx::query_as::<_, RowCounts>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM urls) AS urls,
+            (SELECT COUNT(*) FROM users) AS users,
+            (SELECT COUNT(*) FROM admin_users) AS admins,
+            (SELECT COUNT(*) FROM campaigns) AS campaigns,
+            (SELECT COUNT(*) FROM analytics) AS analytics
+        "#,
+    )
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(counts)
+}
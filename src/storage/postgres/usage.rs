@@ -0,0 +1,123 @@
+//! Hourly API usage per user for `PostgresStorage`.
+
+use crate::models::{UserUsageHour, UserUsageTotal};
+use anyhow::Result;
+
+use super::PostgresStorage;
+
+/// Create the `user_usage` table.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // API requests per user and UTC hour, flushed from the auth
+    // middleware's in-memory counters
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_usage (
+            user_id TEXT NOT NULL,
+            hour BIGINT NOT NULL,
+            create_requests BIGINT NOT NULL DEFAULT 0,
+            read_requests BIGINT NOT NULL DEFAULT 0,
+            search_requests BIGINT NOT NULL DEFAULT 0,
+            analytics_requests BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, hour)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn record_user_usage(
+    storage: &PostgresStorage,
+    hours: &[UserUsageHour],
+) -> Result<()> {
+    if hours.is_empty() {
+        return Ok(());
+    }
+    let mut user_ids = Vec::with_capacity(hours.len());
+    let mut starts = Vec::with_capacity(hours.len());
+    let mut creates = Vec::with_capacity(hours.len());
+    let mut reads = Vec::with_capacity(hours.len());
+    let mut searches = Vec::with_capacity(hours.len());
+    let mut analytics = Vec::with_capacity(hours.len());
+    for hour in hours {
+        user_ids.push(hour.user_id.as_str());
+        starts.push(hour.hour);
+        creates.push(hour.counts.create_requests);
+        reads.push(hour.counts.read_requests);
+        searches.push(hour.counts.search_requests);
+        analytics.push(hour.counts.analytics_requests);
+    }
+
+    // A batch never repeats a (user, hour), which ON CONFLICT requires
+    sqlx::query(
+        r#"
+        INSERT INTO user_usage (
+            user_id, hour, create_requests, read_requests, search_requests, analytics_requests
+        )
+        SELECT *
+        FROM UNNEST($1::text[], $2::bigint[], $3::bigint[], $4::bigint[], $5::bigint[], $6::bigint[])
+        ON CONFLICT(user_id, hour) DO UPDATE SET
+            create_requests = user_usage.create_requests + EXCLUDED.create_requests,
+            read_requests = user_usage.read_requests + EXCLUDED.read_requests,
+            search_requests = user_usage.search_requests + EXCLUDED.search_requests,
+            analytics_requests = user_usage.analytics_requests + EXCLUDED.analytics_requests
+        "#,
+    )
+    .bind(user_ids)
+    .bind(starts)
+    .bind(creates)
+    .bind(reads)
+    .bind(searches)
+    .bind(analytics)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn user_usage(
+    storage: &PostgresStorage,
+    user_id: &str,
+    since: i64,
+) -> Result<Vec<UserUsageHour>> {
+    let hours = sqlx::query_as::<_, UserUsageHour>(
+        r#"
+        SELECT user_id, hour, create_requests, read_requests, search_requests, analytics_requests
+        FROM user_usage
+        WHERE user_id = $1 AND hour >= $2
+        ORDER BY hour
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(hours)
+}
+
+pub(super) async fn user_usage_totals(
+    storage: &PostgresStorage,
+    user_ids: &[String],
+    since: i64,
+) -> Result<Vec<UserUsageTotal>> {
+    let totals = sqlx::query_as::<_, UserUsageTotal>(
+        r#"
+        SELECT user_id,
+               SUM(create_requests)::BIGINT AS create_requests,
+               SUM(read_requests)::BIGINT AS read_requests,
+               SUM(search_requests)::BIGINT AS search_requests,
+               SUM(analytics_requests)::BIGINT AS analytics_requests
+        FROM user_usage
+        WHERE hour >= $1 AND user_id = ANY($2)
+        GROUP BY user_id
+        "#,
+    )
+    .bind(since)
+    .bind(user_ids)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(totals)
+}
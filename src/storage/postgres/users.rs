@@ -0,0 +1,440 @@
+//! Users, admins and per-user link queries for `PostgresStorage`.
+
+use crate::models::{ShortenedUrl, UserAccount, UserLinkCounts};
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::PostgresStorage;
+
+/// Create the `users` and `admin_users` tables.
+pub(super) async fn create_schema(pool: &sqlx::PgPool) -> Result<()> {
+    // Create users table to track user metadata
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS users (
+            user_id TEXT NOT NULL,
+            auth_method TEXT NOT NULL,
+            email TEXT,
+            created_at BIGINT NOT NULL,
+            updated_at BIGINT NOT NULL,
+            last_seen_at BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, auth_method)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Bumped when a request authenticates as the user; accounts from
+    // before the column start from their last metadata update
+    let mut tx = pool.begin().await?;
+    let has_last_seen_at: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema()
+              AND table_name = 'users'
+              AND column_name = 'last_seen_at'
+        )
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if !has_last_seen_at {
+        sqlx::query("ALTER TABLE users ADD COLUMN last_seen_at BIGINT NOT NULL DEFAULT 0")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE users SET last_seen_at = updated_at")
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    // Index for cursor-based user listing
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at DESC, user_id DESC, auth_method DESC)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Create admin_users table for manually promoted admins
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS admin_users (
+            user_id TEXT NOT NULL,
+            auth_method TEXT NOT NULL,
+            promoted_at BIGINT NOT NULL,
+            PRIMARY KEY (user_id, auth_method)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn upsert_user(
+    storage: &PostgresStorage,
+    user_id: &str,
+    email: Option<&str>,
+    auth_method: &str,
+) -> Result<()> {
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        INSERT INTO users (user_id, auth_method, email, created_at, updated_at, last_seen_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, auth_method) DO UPDATE SET
+            email = COALESCE(EXCLUDED.email, users.email),
+            updated_at = EXCLUDED.updated_at,
+            last_seen_at = EXCLUDED.last_seen_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .bind(email)
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn is_manual_admin(
+    storage: &PostgresStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<bool> {
+    let result = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM admin_users
+        WHERE user_id = $1 AND auth_method = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(result > 0)
+}
+
+pub(super) async fn promote_to_admin(
+    storage: &PostgresStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<()> {
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_users (user_id, auth_method, promoted_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, auth_method) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn demote_from_admin(
+    storage: &PostgresStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM admin_users
+        WHERE user_id = $1 AND auth_method = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn list_manual_admins(
+    storage: &PostgresStorage,
+) -> Result<Vec<(String, String, String)>> {
+    let admins = sqlx::query_as::<_, (String, String, Option<String>)>(
+        r#"
+        SELECT a.user_id, a.auth_method, u.email
+        FROM admin_users a
+        LEFT JOIN users u ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+        ORDER BY a.promoted_at DESC
+        "#,
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?
+    .into_iter()
+    .map(|(user_id, auth_method, email)| {
+        (
+            user_id,
+            auth_method,
+            email.unwrap_or_else(|| "N/A".to_string()),
+        )
+    })
+    .collect();
+
+    Ok(admins)
+}
+
+pub(super) async fn user_exists(storage: &PostgresStorage, user_id: &str) -> Result<bool> {
+    let exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_id = $1)")
+            .bind(user_id)
+            .fetch_one(storage.pool.as_ref())
+            .await?;
+
+    Ok(exists)
+}
+
+pub(super) async fn list_all_users(
+    storage: &PostgresStorage,
+    limit: i64,
+    cursor: Option<(i64, String, String)>,
+    inactive_since: Option<i64>,
+) -> Result<Vec<UserAccount>> {
+    let users = if let Some((cursor_created_at, cursor_user_id, cursor_auth_method)) = cursor {
+        sqlx::query_as::<_, UserAccount>(
+            r#"
+            SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+                   a.user_id IS NOT NULL AS is_manual_admin
+            FROM users u
+            LEFT JOIN admin_users a
+              ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+            WHERE (u.created_at, u.user_id, u.auth_method) < ($1, $2, $3)
+              AND ($4::BIGINT IS NULL OR u.last_seen_at < $4)
+            ORDER BY u.created_at DESC, u.user_id DESC, u.auth_method DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(cursor_created_at)
+        .bind(cursor_user_id)
+        .bind(cursor_auth_method)
+        .bind(inactive_since)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, UserAccount>(
+            r#"
+            SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+                   a.user_id IS NOT NULL AS is_manual_admin
+            FROM users u
+            LEFT JOIN admin_users a
+              ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+            WHERE ($1::BIGINT IS NULL OR u.last_seen_at < $1)
+            ORDER BY u.created_at DESC, u.user_id DESC, u.auth_method DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(inactive_since)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    };
+
+    Ok(users)
+}
+
+pub(super) async fn user_emails(
+    storage: &PostgresStorage,
+    users: &[(String, String)],
+) -> Result<Vec<(String, String, String)>> {
+    let (user_ids, auth_methods): (Vec<&str>, Vec<&str>) = users
+        .iter()
+        .map(|(user_id, auth_method)| (user_id.as_str(), auth_method.as_str()))
+        .unzip();
+    let emails = sqlx::query_as::<_, (String, String, String)>(
+        r#"
+        SELECT u.user_id, u.auth_method, u.email
+        FROM users u
+        JOIN UNNEST($1::text[], $2::text[]) AS wanted(user_id, auth_method)
+          ON u.user_id = wanted.user_id AND u.auth_method = wanted.auth_method
+        WHERE u.email IS NOT NULL
+        "#,
+    )
+    .bind(user_ids)
+    .bind(auth_methods)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(emails)
+}
+
+pub(super) async fn user_accounts(
+    storage: &PostgresStorage,
+    user_id: &str,
+) -> Result<Vec<UserAccount>> {
+    let accounts = sqlx::query_as::<_, UserAccount>(
+        r#"
+        SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+               a.user_id IS NOT NULL AS is_manual_admin
+        FROM users u
+        LEFT JOIN admin_users a
+          ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+        WHERE u.user_id = $1
+        ORDER BY u.created_at, u.auth_method
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(accounts)
+}
+
+pub(super) async fn user_link_counts(
+    storage: &PostgresStorage,
+    user_id: &str,
+) -> Result<UserLinkCounts> {
+    let counts = sqlx::query_as::<_, UserLinkCounts>(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE alias_of IS NULL AND is_active AND reserved_until IS NULL) AS active,
+            COUNT(*) FILTER (WHERE alias_of IS NULL AND NOT is_active) AS inactive,
+            COUNT(*) FILTER (WHERE alias_of IS NULL AND is_active AND reserved_until IS NOT NULL) AS reserved,
+            COUNT(*) FILTER (WHERE alias_of IS NOT NULL) AS aliases,
+            COALESCE(SUM(clicks), 0)::BIGINT AS total_clicks
+        FROM urls
+        WHERE created_by = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(counts)
+}
+
+pub(super) async fn list_user_links(
+    storage: &PostgresStorage,
+    user_id: &str,
+    limit: i64,
+    cursor: Option<(i64, i64)>,
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let urls = if let Some((cursor_created_at, cursor_id)) = cursor {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE created_by = $1 AND (created_at, id) < ($2, $3)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
+            "#
+        ))
+        .bind(user_id)
+        .bind(cursor_created_at)
+        .bind(cursor_id)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE created_by = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#
+        ))
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(storage.pool.as_ref())
+        .await?
+    };
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
+
+pub(super) async fn bulk_deactivate_user_links(
+    storage: &PostgresStorage,
+    user_id: &str,
+) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = false, updated_at = $2
+        WHERE created_by = $1 AND is_active = true
+        "#,
+    )
+    .bind(user_id)
+    .bind(storage.clock.now_epoch_ms())
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+pub(super) async fn bulk_reactivate_user_links(
+    storage: &PostgresStorage,
+    user_id: &str,
+) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = true, updated_at = $2
+        WHERE created_by = $1 AND is_active = false
+        "#,
+    )
+    .bind(user_id)
+    .bind(storage.clock.now_epoch_ms())
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+pub(super) async fn count_user_links_by_state(
+    storage: &PostgresStorage,
+    user_id: &str,
+    is_active: bool,
+) -> Result<i64> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM urls WHERE created_by = $1 AND is_active = $2",
+    )
+    .bind(user_id)
+    .bind(is_active)
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(count)
+}
+
+pub(super) async fn sample_user_links_by_state(
+    storage: &PostgresStorage,
+    user_id: &str,
+    is_active: bool,
+    limit: i64,
+) -> Result<Vec<String>> {
+    let codes = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT short_code
+        FROM urls
+        WHERE created_by = $1 AND is_active = $2
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(is_active)
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(codes)
+}
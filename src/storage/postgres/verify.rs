@@ -0,0 +1,165 @@
+//! Database health checks and orphaned analytics for `PostgresStorage`.
+
+use crate::storage::verify::{
+    is_schema_incomplete, orphan_condition, ANALYTICS_TABLES, EXPECTED_INDEXES, EXPECTED_TABLES,
+    LINK_ANALYTICS_TABLES, ORPHAN_DELETE_BATCH_SIZE,
+};
+use crate::storage::{CheckStatus, OrphanCounts, Storage, VerifyReport};
+use anyhow::Result;
+use std::collections::HashSet;
+
+use super::{PostgresStorage, TRIGRAM_INDEXES};
+
+/// Rows of `table` naming a short code in `columns` that is not in `urls`.
+async fn count_orphans(storage: &PostgresStorage, table: &str, columns: &[&str]) -> Result<i64> {
+    let condition = orphan_condition(columns);
+    let count = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} t WHERE {condition}"))
+        .fetch_one(storage.pool.as_ref())
+        .await?;
+    Ok(count)
+}
+
+/// Delete the rows `count_orphans` counts, in batches.
+async fn delete_orphans(storage: &PostgresStorage, table: &str, columns: &[&str]) -> Result<i64> {
+    let statement = format!(
+        "DELETE FROM {table} WHERE ctid = ANY(ARRAY(SELECT ctid FROM {table} t WHERE {} LIMIT $1))",
+        orphan_condition(columns)
+    );
+    let mut deleted = 0;
+    loop {
+        // Each batch commits on its own, releasing its row locks.
+        let removed = sqlx::query(&statement)
+            .bind(ORPHAN_DELETE_BATCH_SIZE)
+            .execute(storage.pool.as_ref())
+            .await?
+            .rows_affected() as i64;
+        deleted += removed;
+        if removed < ORPHAN_DELETE_BATCH_SIZE {
+            return Ok(deleted);
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Names of the tables and indexes in the current schema.
+pub(super) async fn schema_objects(storage: &PostgresStorage) -> Result<HashSet<String>> {
+    let names: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT tablename::TEXT FROM pg_tables WHERE schemaname = current_schema()
+        UNION ALL
+        SELECT indexname::TEXT FROM pg_indexes WHERE schemaname = current_schema()
+        "#,
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+    Ok(names.into_iter().collect())
+}
+
+/// `(table, column)` pairs of every table currently in the database.
+pub(super) async fn schema_columns(storage: &PostgresStorage) -> Result<HashSet<(String, String)>> {
+    let pairs: Vec<(String, String)> = sqlx::query_as(
+        "SELECT table_name::TEXT, column_name::TEXT FROM information_schema.columns WHERE table_schema = current_schema()",
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+    Ok(pairs.into_iter().collect())
+}
+
+pub(super) async fn count_orphan_analytics(storage: &PostgresStorage) -> Result<OrphanCounts> {
+    let mut orphans = OrphanCounts::default();
+    for (table, columns) in LINK_ANALYTICS_TABLES {
+        orphans.analytics += count_orphans(storage, table, columns).await?;
+    }
+    orphans.click_history = count_orphans(storage, "click_history", &["short_code"]).await?;
+    Ok(orphans)
+}
+
+pub(super) async fn delete_orphan_analytics(storage: &PostgresStorage) -> Result<OrphanCounts> {
+    let mut deleted = OrphanCounts::default();
+    for (table, columns) in LINK_ANALYTICS_TABLES {
+        deleted.analytics += delete_orphans(storage, table, columns).await?;
+    }
+    deleted.click_history = delete_orphans(storage, "click_history", &["short_code"]).await?;
+    Ok(deleted)
+}
+
+pub(super) async fn verify_database(storage: &PostgresStorage, fix: bool) -> Result<VerifyReport> {
+    let mut report = VerifyReport::new("postgres");
+
+    // Indexes left behind by failed concurrent builds are never used.
+    let invalid_indexes: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT c.relname::TEXT
+        FROM pg_index i
+        JOIN pg_class c ON c.oid = i.indexrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = current_schema() AND NOT (i.indisvalid AND i.indisready)
+        "#,
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+    if invalid_indexes.is_empty() {
+        report.push("invalid indexes", CheckStatus::Ok, "none");
+    } else {
+        report.push(
+            "invalid indexes",
+            CheckStatus::Problem,
+            invalid_indexes.join(", "),
+        );
+    }
+
+    let unvalidated_foreign_keys: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT c.conname::TEXT
+        FROM pg_constraint c
+        JOIN pg_namespace n ON n.oid = c.connamespace
+        WHERE n.nspname = current_schema() AND c.contype = 'f' AND NOT c.convalidated
+        "#,
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+    if unvalidated_foreign_keys.is_empty() {
+        report.push("unvalidated foreign keys", CheckStatus::Ok, "none");
+    } else {
+        report.push(
+            "unvalidated foreign keys",
+            CheckStatus::Problem,
+            unvalidated_foreign_keys.join(", "),
+        );
+    }
+
+    let has_trigram: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_trgm')")
+            .fetch_one(storage.pool.as_ref())
+            .await?;
+    let trigram_indexes: &[&str] = if has_trigram { TRIGRAM_INDEXES } else { &[] };
+
+    let before = schema_objects(storage).await?;
+    let after = if fix
+        && is_schema_incomplete(
+            &[EXPECTED_TABLES, EXPECTED_INDEXES, trigram_indexes],
+            &before,
+        ) {
+        // init() only creates what is missing.
+        storage.init().await?;
+        schema_objects(storage).await?
+    } else {
+        before.clone()
+    };
+    report.record_schema_objects("table", EXPECTED_TABLES, &before, &after);
+    report.record_schema_objects("index", EXPECTED_INDEXES, &before, &after);
+    report.record_schema_objects("index", trigram_indexes, &before, &after);
+
+    // Orphans can only be counted once urls and the analytics tables exist.
+    if is_schema_incomplete(&[&["urls"], ANALYTICS_TABLES], &after) {
+        return Ok(report);
+    }
+    let orphans = storage.count_orphan_analytics().await?;
+    let deleted = fix && orphans.total() > 0;
+    if deleted {
+        storage.delete_orphan_analytics().await?;
+    }
+    report.record_orphans(orphans, deleted);
+
+    Ok(report)
+}
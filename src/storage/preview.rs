@@ -0,0 +1,94 @@
+//! Dry runs of the bulk CLI commands.
+//!
+//! `lynx user deactivate-links`, `lynx user reactivate-links` and
+//! `lynx patch fix-all` rewrite every matching link at once. With `--dry-run`
+//! they build a [`BulkPreview`] instead: the number of links the command would
+//! change and a few of their codes, read with a count and a short select so
+//! previewing a large account stays cheap. Nothing is written.
+
+use anyhow::Result;
+
+use super::Storage;
+
+/// Short codes listed by a dry run.
+pub const DRY_RUN_SAMPLE_SIZE: i64 = 10;
+
+/// What a bulk command would change, without changing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkPreview {
+    /// Links the command would change
+    pub affected: i64,
+    /// Up to [`DRY_RUN_SAMPLE_SIZE`] of their codes, newest first
+    pub sample: Vec<String>,
+}
+
+impl BulkPreview {
+    /// Links of `user_id` that deactivating (`is_active`) or reactivating
+    /// (`!is_active`) them would change.
+    pub async fn user_links(storage: &dyn Storage, user_id: &str, is_active: bool) -> Result<Self> {
+        Ok(Self {
+            affected: storage
+                .count_user_links_by_state(user_id, is_active)
+                .await?,
+            sample: storage
+                .sample_user_links_by_state(user_id, is_active, DRY_RUN_SAMPLE_SIZE)
+                .await?,
+        })
+    }
+
+    /// Links whose malformed `created_by` the fix-all patch would replace.
+    pub async fn malformed_created_by(storage: &dyn Storage) -> Result<Self> {
+        Ok(Self {
+            affected: storage.count_malformed_created_by().await?,
+            sample: storage
+                .sample_malformed_created_by(DRY_RUN_SAMPLE_SIZE)
+                .await?,
+        })
+    }
+
+    /// The dry-run report: how many links would be `action`, then the sample.
+    pub fn render(&self, action: &str) -> String {
+        let mut report = format!("Dry run: {} link(s) would be {}", self.affected, action);
+        if !self.sample.is_empty() {
+            let shown = self.sample.len() as i64;
+            if shown < self.affected {
+                report.push_str(&format!("\nFirst {} of them:", shown));
+            } else {
+                report.push_str("\nLinks:");
+            }
+            for code in &self.sample {
+                report.push_str(&format!("\n  {}", code));
+            }
+        }
+        report.push_str("\nNo changes were made.");
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lists_the_sample_and_says_how_much_is_hidden() {
+        let preview = BulkPreview {
+            affected: 12,
+            sample: vec!["newest".to_string(), "older".to_string()],
+        };
+        assert_eq!(
+            preview.render("deactivated"),
+            "Dry run: 12 link(s) would be deactivated\n\
+             First 2 of them:\n  newest\n  older\n\
+             No changes were made."
+        );
+
+        let empty = BulkPreview {
+            affected: 0,
+            sample: Vec::new(),
+        };
+        assert_eq!(
+            empty.render("reactivated"),
+            "Dry run: 0 link(s) would be reactivated\nNo changes were made."
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Search relevance scoring.
+//!
+//! Postgres ranks `sort=relevance` searches in SQL with pg_trgm's
+//! `similarity`. SQLite has no trigram similarity, so it reorders each page of
+//! recency-ordered matches with [`similarity`] here, which follows the same
+//! definition: the share of distinct trigrams two strings have in common,
+//! taken over lowercased alphanumeric words padded with two leading blanks and
+//! one trailing blank.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::models::ShortenedUrl;
+
+fn trigrams(value: &str) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for word in value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let padded: Vec<char> = "  "
+            .chars()
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(std::iter::once(' '))
+            .collect();
+        trigrams.extend(padded.windows(3).map(|w| [w[0], w[1], w[2]]));
+    }
+    trigrams
+}
+
+/// pg_trgm `similarity(a, b)`: from 0 for nothing shared to 1 for the same
+/// trigrams.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.intersection(&b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        return 0.0;
+    }
+    shared as f32 / total as f32
+}
+
+/// How well `url` matches the search query `q`: the similarity of its code
+/// plus that of its destination, as the Postgres relevance sort computes it.
+pub fn search_score(url: &ShortenedUrl, q: &str) -> f32 {
+    similarity(&url.short_code, q) + similarity(&url.original_url, q)
+}
+
+/// Order `items` best match first, keeping recency order between equal scores.
+pub(crate) fn rank_by_relevance(items: &mut [Arc<ShortenedUrl>], q: &str) {
+    items.sort_by(|a, b| search_score(b, q).total_cmp(&search_score(a, q)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_matches_pg_trgm() {
+        // SELECT similarity('word', 'two words') is 0.36363637 in pg_trgm
+        assert!((similarity("word", "two words") - 0.363_636_37).abs() < 1e-6);
+        assert_eq!(similarity("Docs", "docs"), 1.0);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+        assert_eq!(similarity("", "--"), 0.0);
+    }
+
+    #[test]
+    fn closer_matches_rank_first() {
+        let url = |code: &str, destination: &str| {
+            Arc::new(ShortenedUrl {
+                id: 0,
+                short_code: code.to_string(),
+                original_url: destination.to_string(),
+                created_at: 0,
+                created_by: None,
+                created_by_auth_method: None,
+                clicks: 0,
+                is_active: true,
+                reserved_until: None,
+                alias_of: None,
+                title: None,
+                created_via: Default::default(),
+                last_visited_at: None,
+                updated_at: 0,
+                options: Default::default(),
+                campaign_id: None,
+            })
+        };
+        let mut items = vec![
+            url("q3-report", "https://example.com/docs-archive/2019/misc"),
+            url("docs", "https://docs.example.com/"),
+            url("guide", "https://example.com/docs"),
+        ];
+        rank_by_relevance(&mut items, "docs");
+        let codes: Vec<&str> = items.iter().map(|u| u.short_code.as_str()).collect();
+        assert_eq!(codes, vec!["docs", "guide", "q3-report"]);
+    }
+}
@@ -0,0 +1,33 @@
+//! Expiry of reserved short codes.
+//!
+//! Codes reserved through `POST /api/links/reserve` carry a `reserved_until`
+//! timestamp. A background sweep deactivates the ones that never received a
+//! destination; like every URL, the rows themselves are kept.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::Storage;
+use crate::clock::Clock;
+
+/// Deactivate reservations in `storage` that lapsed by `clock` every
+/// `interval`.
+pub fn spawn_reservation_sweep(
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match storage.expire_reservations(clock.now_epoch_secs()).await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!(expired, "Deactivated expired code reservations"),
+                Err(error) => tracing::warn!(%error, "Failed to expire code reservations"),
+            }
+        }
+    })
+}
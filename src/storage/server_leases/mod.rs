@@ -0,0 +1,81 @@
+//! Leases running servers hold in the database.
+//!
+//! Every server keeps a row in `server_leases` while it runs, renewing it
+//! every [`LEASE_RENEW_INTERVAL`] for [`LEASE_TTL`] ahead and deleting it
+//! on a clean shutdown. Commands that must not run beside a server, such as
+//! `lynx link hard-delete`, check for leases that have not lapsed, so they
+//! see every instance sharing the database wherever it runs. A server that
+//! crashed holds its lease until it lapses.
+
+use serde::Serialize;
+use sqlx::FromRow;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::Storage;
+use crate::clock::Clock;
+
+#[cfg(feature = "postgres")]
+pub(crate) mod postgres;
+#[cfg(feature = "sqlite")]
+pub(crate) mod sqlite;
+
+/// How long a lease lasts without renewal.
+pub const LEASE_TTL: Duration = Duration::from_secs(60);
+
+/// Time between renewals; several fit in a lease, so a slow write or two
+/// does not let it lapse.
+pub const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(20);
+
+/// A running server's claim on the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow)]
+pub struct ServerLease {
+    /// Random id the server picked at startup
+    pub instance_id: String,
+    /// Host name the server runs on, for telling instances apart
+    pub host: String,
+    /// When the server started, in milliseconds since the Unix epoch
+    pub started_at: i64,
+    /// When the lease lapses unless renewed, in milliseconds since the Unix
+    /// epoch
+    pub expires_at: i64,
+}
+
+impl ServerLease {
+    /// A lease for a server starting now on this host.
+    pub fn starting(clock: &dyn Clock) -> Self {
+        let host = std::env::var("HOSTNAME")
+            .ok()
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "unknown".to_string());
+        let now = clock.now_epoch_ms();
+        Self {
+            instance_id: format!("{:016x}", rand::random::<u64>()),
+            host,
+            started_at: now,
+            expires_at: now,
+        }
+    }
+}
+
+/// Hold `lease` in `storage` until the task is aborted, renewing it from
+/// now on. Release it with `Storage::release_server_lease` on shutdown.
+pub fn spawn_server_lease(
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    mut lease: ServerLease,
+) -> JoinHandle<()> {
+    let ttl = i64::try_from(LEASE_TTL.as_millis()).unwrap_or(i64::MAX);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(LEASE_RENEW_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            lease.expires_at = clock.now_epoch_ms().saturating_add(ttl);
+            if let Err(error) = storage.renew_server_lease(&lease).await {
+                tracing::warn!(%error, "Failed to renew the server lease");
+            }
+        }
+    })
+}
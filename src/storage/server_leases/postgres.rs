@@ -0,0 +1,62 @@
+//! Server lease queries for `PostgresStorage`.
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+use super::ServerLease;
+
+pub(crate) async fn create_schema(pool: &PgPool) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS server_leases (
+            instance_id TEXT PRIMARY KEY,
+            host TEXT NOT NULL,
+            started_at BIGINT NOT NULL,
+            expires_at BIGINT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn renew(pool: &PgPool, lease: &ServerLease) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO server_leases (instance_id, host, started_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (instance_id) DO UPDATE SET expires_at = EXCLUDED.expires_at
+        "#,
+    )
+    .bind(&lease.instance_id)
+    .bind(&lease.host)
+    .bind(lease.started_at)
+    .bind(lease.expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn release(pool: &PgPool, instance_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM server_leases WHERE instance_id = $1")
+        .bind(instance_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn active(pool: &PgPool, now: i64) -> Result<Vec<ServerLease>> {
+    let leases = sqlx::query_as::<_, ServerLease>(
+        r#"
+        SELECT instance_id, host, started_at, expires_at
+        FROM server_leases
+        WHERE expires_at > $1
+        ORDER BY started_at, instance_id
+        "#,
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+    Ok(leases)
+}
@@ -0,0 +1,62 @@
+//! Server lease queries for `SqliteStorage`.
+
+use anyhow::Result;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use super::ServerLease;
+
+pub(crate) async fn create_schema(connection: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS server_leases (
+            instance_id TEXT PRIMARY KEY,
+            host TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn renew(pool: &SqlitePool, lease: &ServerLease) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO server_leases (instance_id, host, started_at, expires_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (instance_id) DO UPDATE SET expires_at = excluded.expires_at
+        "#,
+    )
+    .bind(&lease.instance_id)
+    .bind(&lease.host)
+    .bind(lease.started_at)
+    .bind(lease.expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn release(pool: &SqlitePool, instance_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM server_leases WHERE instance_id = ?")
+        .bind(instance_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn active(read_pool: &SqlitePool, now: i64) -> Result<Vec<ServerLease>> {
+    let leases = sqlx::query_as::<_, ServerLease>(
+        r#"
+        SELECT instance_id, host, started_at, expires_at
+        FROM server_leases
+        WHERE expires_at > ?
+        ORDER BY started_at, instance_id
+        "#,
+    )
+    .bind(now)
+    .fetch_all(read_pool)
+    .await?;
+    Ok(leases)
+}
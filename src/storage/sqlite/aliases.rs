@@ -0,0 +1,171 @@
+//! Renames and aliases for `SqliteStorage`.
+
+use crate::destination::destination_host;
+use crate::models::ShortenedUrl;
+use crate::storage::{StorageError, StorageResult, URL_COLUMNS};
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::{SqliteStorage, GET_MANY_CHUNK_SIZE};
+
+pub(super) async fn rename_code(
+    storage: &SqliteStorage,
+    short_code: &str,
+    new_code: &str,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    let mut tx = storage.pool.begin().await?;
+
+    let old = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = ?
+        "#
+    ))
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(old) = old else {
+        return Ok(None);
+    };
+    if let Some(canonical) = &old.alias_of {
+        return Err(StorageError::InvalidInput(format!(
+            "'{}' is an alias of '{}'; rename '{}' instead",
+            short_code, canonical, canonical
+        )));
+    }
+
+    let renamed = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        INSERT INTO urls (short_code, original_url, dest_host, normalized_url, created_at, updated_at, created_by, created_by_auth_method, is_active, reserved_until, created_via, options, campaign_id)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(short_code) DO NOTHING
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(new_code)
+    .bind(&old.original_url)
+    .bind(destination_host(&old.original_url))
+    .bind(storage.normalized_url(&old.original_url))
+    .bind(created_at)
+    .bind(created_at)
+    .bind(&old.created_by)
+    .bind(&old.created_by_auth_method)
+    .bind(old.is_active)
+    .bind(old.reserved_until)
+    .bind(old.created_via.as_str())
+    .bind(old.options.to_json())
+    .bind(old.campaign_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(StorageError::Conflict)?;
+
+    // Re-point earlier aliases too, so no alias ends up behind another.
+    sqlx::query(
+        "UPDATE urls SET alias_of = ?, updated_at = ? WHERE short_code = ? OR alias_of = ?",
+    )
+    .bind(new_code)
+    .bind(created_at)
+    .bind(short_code)
+    .bind(short_code)
+    .execute(&mut *tx)
+    .await?;
+
+    // The old code now follows the new one's state, so a deactivated
+    // link's alias comes back with it when it is reactivated.
+    sqlx::query("UPDATE urls SET is_active = 1 WHERE short_code = ?")
+        .bind(short_code)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(Arc::new(renamed)))
+}
+
+pub(super) async fn add_alias(
+    storage: &SqliteStorage,
+    short_code: &str,
+    alias_code: &str,
+    created_by: Option<&str>,
+) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+    let created_at = storage.clock.now_epoch_ms();
+
+    let mut tx = storage.pool.begin().await?;
+
+    let canonical = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE short_code = ?
+        "#
+    ))
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(canonical) = canonical else {
+        return Ok(None);
+    };
+    if let Some(target) = &canonical.alias_of {
+        return Err(StorageError::InvalidInput(format!(
+            "'{}' is an alias of '{}'; add the alias to '{}' instead",
+            short_code, target, target
+        )));
+    }
+
+    let alias = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        INSERT INTO urls (short_code, original_url, dest_host, normalized_url, created_at, updated_at, created_by, is_active, alias_of)
+        VALUES (?, ?, ?, ?, ?, ?, ?, 1, ?)
+        ON CONFLICT(short_code) DO NOTHING
+        RETURNING {URL_COLUMNS}
+        "#
+    ))
+    .bind(alias_code)
+    .bind(&canonical.original_url)
+    .bind(destination_host(&canonical.original_url))
+    .bind(storage.normalized_url(&canonical.original_url))
+    .bind(created_at)
+    .bind(created_at)
+    .bind(created_by)
+    .bind(short_code)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(StorageError::Conflict)?;
+
+    tx.commit().await?;
+
+    Ok(Some(Arc::new(alias)))
+}
+
+pub(super) async fn get_aliases(
+    storage: &SqliteStorage,
+    short_codes: &[String],
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let mut aliases = Vec::new();
+    for chunk in short_codes.chunks(GET_MANY_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE alias_of IN ({placeholders})
+            "#
+        );
+        let mut query = sqlx::query_as::<_, ShortenedUrl>(&sql);
+        for short_code in chunk {
+            query = query.bind(short_code);
+        }
+        aliases.extend(
+            query
+                .fetch_all(storage.read_pool.as_ref())
+                .await?
+                .into_iter()
+                .map(Arc::new),
+        );
+    }
+
+    Ok(aliases)
+}
@@ -0,0 +1,343 @@
+//! Analytics writes and reads for `SqliteStorage`.
+
+use crate::analytics::{AliasRollup, AnalyticsEstimate, AnalyticsExportScope, AnalyticsRollup};
+use anyhow::Result;
+
+use super::SqliteStorage;
+
+/// Create the hourly `analytics` table and the per-alias and estimated
+/// visit tables kept beside it.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // Create analytics table for visitor IP analytics
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            short_code TEXT NOT NULL,
+            time_bucket INTEGER NOT NULL,
+            country_code TEXT,
+            region TEXT,
+            city TEXT,
+            asn INTEGER,
+            ip_version INTEGER NOT NULL,
+            visit_count INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Index for analytics queries by short code
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_analytics_short_code ON analytics(short_code)")
+        .execute(&mut *connection)
+        .await?;
+
+    // Index for analytics queries by time bucket
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_analytics_time_bucket ON analytics(time_bucket DESC)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Composite index for short code and time range queries
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_analytics_short_code_time ON analytics(short_code, time_bucket DESC)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Visits that came through an alias, per alias and hour, for the
+    // `alias_used` analytics dimension
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS alias_analytics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            short_code TEXT NOT NULL,
+            alias_code TEXT NOT NULL,
+            time_bucket INTEGER NOT NULL,
+            visit_count INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(short_code, alias_code, time_bucket)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Visits extrapolated from events kept by load sampling, per code and
+    // hour; they are also in `analytics.visit_count`
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics_estimates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            short_code TEXT NOT NULL,
+            time_bucket INTEGER NOT NULL,
+            estimated_visits INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(short_code, time_bucket)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+/// Export pages are keyset-paginated on `(time_bucket, id)` so each page is an
+/// index range scan however deep the export goes.
+const EXPORT_PAGE_BY_LINK: &str = "SELECT a.id, a.short_code, a.time_bucket, a.country_code, a.region, a.city, a.asn, a.ip_version, a.visit_count, a.created_at, a.updated_at FROM analytics a WHERE a.short_code = ? AND a.time_bucket >= ? AND a.time_bucket <= ? AND (a.time_bucket > ? OR (a.time_bucket = ? AND a.id > ?)) ORDER BY a.time_bucket, a.id LIMIT ?";
+
+const EXPORT_PAGE_BY_OWNER: &str = "SELECT a.id, a.short_code, a.time_bucket, a.country_code, a.region, a.city, a.asn, a.ip_version, a.visit_count, a.created_at, a.updated_at FROM analytics a JOIN urls u ON u.short_code = a.short_code WHERE u.created_by = ? AND a.time_bucket >= ? AND a.time_bucket <= ? AND (a.time_bucket > ? OR (a.time_bucket = ? AND a.id > ?)) ORDER BY a.time_bucket, a.id LIMIT ?";
+
+/// Add per-alias visit counts split off an analytics batch. Counts for codes
+/// that no longer exist are dropped.
+async fn upsert_alias_rollups(
+    connection: &mut sqlx::SqliteConnection,
+    aliases: Vec<AliasRollup>,
+    now: i64,
+) -> Result<()> {
+    for alias in aliases {
+        sqlx::query(
+            r#"
+            INSERT INTO alias_analytics (short_code, alias_code, time_bucket, visit_count, created_at, updated_at)
+            SELECT ?, ?, ?, ?, ?, ?
+            WHERE EXISTS (SELECT 1 FROM urls WHERE short_code = ?)
+            ON CONFLICT(short_code, alias_code, time_bucket)
+            DO UPDATE SET visit_count = visit_count + excluded.visit_count, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&alias.short_code)
+        .bind(&alias.alias_code)
+        .bind(alias.time_bucket)
+        .bind(alias.visit_count)
+        .bind(now)
+        .bind(now)
+        .bind(&alias.short_code)
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Add the estimated visits of an analytics batch. Estimates for codes that
+/// no longer exist are dropped.
+async fn upsert_analytics_estimates(
+    connection: &mut sqlx::SqliteConnection,
+    estimates: Vec<AnalyticsEstimate>,
+    now: i64,
+) -> Result<()> {
+    for estimate in estimates {
+        sqlx::query(
+            r#"
+            INSERT INTO analytics_estimates (short_code, time_bucket, estimated_visits, created_at, updated_at)
+            SELECT ?, ?, ?, ?, ?
+            WHERE EXISTS (SELECT 1 FROM urls WHERE short_code = ?)
+            ON CONFLICT(short_code, time_bucket)
+            DO UPDATE SET estimated_visits = estimated_visits + excluded.estimated_visits, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&estimate.short_code)
+        .bind(estimate.time_bucket)
+        .bind(estimate.estimated_visits)
+        .bind(now)
+        .bind(now)
+        .bind(&estimate.short_code)
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub(super) async fn upsert_analytics_batch(
+    storage: &SqliteStorage,
+    records: Vec<AnalyticsRollup>,
+) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let now = storage.clock.now_epoch_secs();
+
+    let estimates = AnalyticsRollup::estimates(&records);
+    let (records, aliases) = AnalyticsRollup::split_aliases(records);
+    let mut transaction = storage.pool.begin().await?;
+    for record in records {
+        sqlx::query(
+            r#"
+            INSERT INTO analytics (short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+            DO UPDATE SET visit_count = visit_count + ?, updated_at = ?
+            "#,
+        )
+        .bind(&record.short_code)
+        .bind(record.time_bucket)
+        .bind(&record.country_code)
+        .bind(&record.region)
+        .bind(&record.city)
+        .bind(record.asn)
+        .bind(record.ip_version.as_i32())
+        .bind(record.visit_count)
+        .bind(now)
+        .bind(now)
+        .bind(record.visit_count)
+        .bind(now)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    upsert_alias_rollups(&mut transaction, aliases, now).await?;
+    upsert_analytics_estimates(&mut transaction, estimates, now).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub(super) async fn upsert_known_analytics_batch(
+    storage: &SqliteStorage,
+    records: Vec<AnalyticsRollup>,
+) -> Result<u64> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let now = storage.clock.now_epoch_secs();
+
+    let estimates = AnalyticsRollup::estimates(&records);
+    let (records, aliases) = AnalyticsRollup::split_aliases(records);
+    let mut skipped = 0;
+    let mut transaction = storage.pool.begin().await?;
+    for record in records {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO analytics (short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at)
+            SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
+            WHERE EXISTS (SELECT 1 FROM urls WHERE short_code = ?)
+            ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+            DO UPDATE SET visit_count = visit_count + ?, updated_at = ?
+            "#,
+        )
+        .bind(&record.short_code)
+        .bind(record.time_bucket)
+        .bind(&record.country_code)
+        .bind(&record.region)
+        .bind(&record.city)
+        .bind(record.asn)
+        .bind(record.ip_version.as_i32())
+        .bind(record.visit_count)
+        .bind(now)
+        .bind(now)
+        .bind(&record.short_code)
+        .bind(record.visit_count)
+        .bind(now)
+        .execute(&mut *transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            skipped += 1;
+        }
+    }
+    upsert_alias_rollups(&mut transaction, aliases, now).await?;
+    upsert_analytics_estimates(&mut transaction, estimates, now).await?;
+    transaction.commit().await?;
+
+    Ok(skipped)
+}
+
+pub(super) async fn get_analytics(
+    storage: &SqliteStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+    // Simplified query building
+    let results = if let (Some(start), Some(end)) = (start_time, end_time) {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = ? AND time_bucket >= ? AND time_bucket <= ? ORDER BY time_bucket DESC LIMIT ?"
+        )
+        .bind(short_code)
+        .bind(start)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    } else if let Some(start) = start_time {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = ? AND time_bucket >= ? ORDER BY time_bucket DESC LIMIT ?"
+        )
+        .bind(short_code)
+        .bind(start)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    } else if let Some(end) = end_time {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = ? AND time_bucket <= ? ORDER BY time_bucket DESC LIMIT ?"
+        )
+        .bind(short_code)
+        .bind(end)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(
+            "SELECT id, short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at FROM analytics WHERE short_code = ? ORDER BY time_bucket DESC LIMIT ?"
+        )
+        .bind(short_code)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    };
+
+    Ok(results)
+}
+
+pub(super) async fn get_estimated_visits(
+    storage: &SqliteStorage,
+    short_code: &str,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+) -> Result<i64> {
+    let (estimated,): (i64,) = sqlx::query_as(
+        "SELECT COALESCE(SUM(estimated_visits), 0) FROM analytics_estimates WHERE short_code = ? AND time_bucket >= ? AND time_bucket <= ?",
+    )
+    .bind(short_code)
+    .bind(start_time.unwrap_or(i64::MIN))
+    .bind(end_time.unwrap_or(i64::MAX))
+    .fetch_one(storage.read_pool.as_ref())
+    .await?;
+    Ok(estimated)
+}
+
+pub(super) async fn get_analytics_export_page(
+    storage: &SqliteStorage,
+    scope: AnalyticsExportScope<'_>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    after: Option<(i64, i64)>,
+    limit: i64,
+) -> Result<Vec<crate::analytics::AnalyticsEntry>> {
+    let (query, key) = match scope {
+        AnalyticsExportScope::Link(short_code) => (EXPORT_PAGE_BY_LINK, short_code),
+        AnalyticsExportScope::Owner(user_id) => (EXPORT_PAGE_BY_OWNER, user_id),
+    };
+    // The first page starts before every possible key.
+    let (after_bucket, after_id) = after.unwrap_or((i64::MIN, i64::MIN));
+    let results = sqlx::query_as::<_, crate::analytics::AnalyticsEntry>(query)
+        .bind(key)
+        .bind(start_time.unwrap_or(i64::MIN))
+        .bind(end_time.unwrap_or(i64::MAX))
+        .bind(after_bucket)
+        .bind(after_bucket)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?;
+
+    Ok(results)
+}
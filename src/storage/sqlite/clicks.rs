@@ -0,0 +1,190 @@
+//! Click counts and hourly click history for `SqliteStorage`.
+
+use crate::models::ClickHistoryEntry;
+use crate::storage::ClickIncrement;
+use crate::timezone::{hour_start, sum_by_local_day};
+use anyhow::{anyhow, Result};
+use chrono_tz::Tz;
+use std::convert::TryFrom;
+
+use super::SqliteStorage;
+
+/// Create the `click_history` table.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // Click counts per UTC hour, kept alongside the lifetime counter in
+    // urls.clicks so they can be grouped into days of any time zone
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS click_history (
+            short_code TEXT NOT NULL,
+            hour INTEGER NOT NULL,
+            clicks INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (short_code, hour)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Instance-wide days (activity report, daily stats) read every code's hours
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_click_history_hour ON click_history(hour)")
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
+
+/// Add `amount` to the lifetime counter of `short_code`, mark it visited at
+/// `now`, and add to its click history row for the hour containing `now`.
+/// Unknown and inactive codes are ignored, so clicks buffered before a
+/// deactivation are dropped rather than counted.
+async fn add_clicks(
+    connection: &mut sqlx::SqliteConnection,
+    short_code: &str,
+    amount: i64,
+    now: i64,
+) -> Result<()> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE urls
+        SET clicks = clicks + ?, last_visited_at = ?
+        WHERE short_code = ? AND is_active = 1
+        "#,
+    )
+    .bind(amount)
+    .bind(now)
+    .bind(short_code)
+    .execute(&mut *connection)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO click_history (short_code, hour, clicks)
+        VALUES (?, ?, ?)
+        ON CONFLICT(short_code, hour) DO UPDATE SET clicks = clicks + excluded.clicks
+        "#,
+    )
+    .bind(short_code)
+    .bind(hour_start(now))
+    .bind(amount)
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn increment_clicks(
+    storage: &SqliteStorage,
+    short_code: &str,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let amount = i64::try_from(amount).map_err(|_| anyhow!("increment amount exceeds i64"))?;
+
+    let mut transaction = storage.pool.begin().await?;
+    add_clicks(
+        &mut transaction,
+        short_code,
+        amount,
+        storage.clock.now_epoch_secs(),
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub(super) async fn increment_clicks_batch(
+    storage: &SqliteStorage,
+    increments: &[ClickIncrement],
+) -> Result<()> {
+    if increments.is_empty() {
+        return Ok(());
+    }
+
+    let now = storage.clock.now_epoch_secs();
+    let mut transaction = storage.pool.begin().await?;
+    for increment in increments {
+        let amount = i64::try_from(increment.amount().get())
+            .map_err(|_| anyhow!("increment amount exceeds i64"))?;
+        add_clicks(&mut transaction, increment.short_code(), amount, now).await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub(super) async fn get_click_history(
+    storage: &SqliteStorage,
+    short_code: &str,
+    since: i64,
+    time_zone: Tz,
+) -> Result<Vec<ClickHistoryEntry>> {
+    // SQLite has no time zone database, so hours are grouped into local
+    // days in Rust.
+    let hours: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT hour, clicks
+        FROM click_history
+        WHERE short_code = ? AND hour >= ?
+        "#,
+    )
+    .bind(short_code)
+    .bind(since)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(sum_by_local_day(hours, time_zone)
+        .into_iter()
+        .map(|(day, clicks)| ClickHistoryEntry { day, clicks })
+        .collect())
+}
+
+pub(super) async fn compact_click_history(
+    storage: &SqliteStorage,
+    retention_days: i64,
+) -> Result<(i64, i64)> {
+    let cutoff = hour_start(storage.clock.now_epoch_secs() - retention_days.max(0) * 86400);
+
+    // Rows already dated the first of their month are left alone, so
+    // compacted rows are never merged again.
+    let mut tx = storage.pool.begin().await?;
+    let merged = sqlx::query(
+        r#"
+        INSERT INTO click_history (short_code, hour, clicks)
+        SELECT short_code,
+               CAST(strftime('%s', hour, 'unixepoch', 'start of month') AS INTEGER) AS month,
+               SUM(clicks)
+        FROM click_history
+        WHERE hour < ?
+          AND hour != CAST(strftime('%s', hour, 'unixepoch', 'start of month') AS INTEGER)
+        GROUP BY short_code, month
+        ON CONFLICT(short_code, hour) DO UPDATE SET clicks = clicks + excluded.clicks
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM click_history
+        WHERE hour < ?
+          AND hour != CAST(strftime('%s', hour, 'unixepoch', 'start of month') AS INTEGER)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok((
+        removed.rows_affected() as i64,
+        merged.rows_affected() as i64,
+    ))
+}
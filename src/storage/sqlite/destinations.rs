@@ -0,0 +1,131 @@
+//! Lookups of links by destination for `SqliteStorage`.
+
+use crate::models::{DestinationHostLink, DestinationHostSummary, ShortenedUrl};
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::SqliteStorage;
+
+pub(super) async fn links_by_destination_host(
+    storage: &SqliteStorage,
+    hosts: &[String],
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostLink>> {
+    if hosts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; hosts.len()].join(", ");
+    let sql = format!(
+        r#"
+        SELECT u.dest_host, u.short_code, u.original_url, u.created_by, o.email AS owner_email, u.clicks, u.created_at
+        FROM urls u
+        LEFT JOIN users o
+          ON o.user_id = u.created_by AND o.auth_method = u.created_by_auth_method
+        WHERE u.dest_host IN ({placeholders}) AND u.is_active = 1 AND u.alias_of IS NULL
+        ORDER BY u.dest_host, u.clicks DESC, u.id
+        LIMIT ? OFFSET ?
+        "#
+    );
+    let mut query = sqlx::query_as::<_, DestinationHostLink>(&sql);
+    for host in hosts {
+        query = query.bind(host);
+    }
+    let links = query
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?;
+
+    Ok(links)
+}
+
+pub(super) async fn links_by_destination(
+    storage: &SqliteStorage,
+    url: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostLink>> {
+    let Some(normalized) = storage.normalized_url(url) else {
+        return Ok(Vec::new());
+    };
+    let links = sqlx::query_as::<_, DestinationHostLink>(
+        r#"
+        SELECT COALESCE(u.dest_host, '') AS dest_host, u.short_code, u.original_url, u.created_by, o.email AS owner_email, u.clicks, u.created_at
+        FROM urls u
+        LEFT JOIN users o
+          ON o.user_id = u.created_by AND o.auth_method = u.created_by_auth_method
+        WHERE u.normalized_url = ? AND u.is_active = 1 AND u.alias_of IS NULL
+        ORDER BY u.clicks DESC, u.id
+        LIMIT ? OFFSET ?
+        "#,
+    )
+    .bind(normalized)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(links)
+}
+
+pub(super) async fn destination_host_summary(
+    storage: &SqliteStorage,
+    hosts: &[String],
+    min_links: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<DestinationHostSummary>> {
+    let host_filter = if hosts.is_empty() {
+        String::new()
+    } else {
+        format!("AND dest_host IN ({})", vec!["?"; hosts.len()].join(", "))
+    };
+    let sql = format!(
+        r#"
+        SELECT dest_host, COUNT(*) AS links, COALESCE(SUM(clicks), 0) AS clicks,
+               COUNT(DISTINCT created_by) AS owners
+        FROM urls
+        WHERE dest_host IS NOT NULL AND is_active = 1 AND alias_of IS NULL {host_filter}
+        GROUP BY dest_host
+        HAVING COUNT(*) >= ?
+        ORDER BY links DESC, dest_host
+        LIMIT ? OFFSET ?
+        "#
+    );
+    let mut query = sqlx::query_as::<_, DestinationHostSummary>(&sql);
+    for host in hosts {
+        query = query.bind(host);
+    }
+    let summary = query
+        .bind(min_links)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?;
+
+    Ok(summary)
+}
+
+pub(super) async fn find_active_by_destination(
+    storage: &SqliteStorage,
+    original_url: &str,
+    created_by: Option<&str>,
+) -> Result<Option<Arc<ShortenedUrl>>> {
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls
+        WHERE original_url = ? AND created_by IS ? AND is_active = 1
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+        "#
+    ))
+    .bind(original_url)
+    .bind(created_by)
+    .fetch_optional(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(url.map(Arc::new))
+}
@@ -0,0 +1,101 @@
+//! Permanent deletion of a link and its data for `SqliteStorage`.
+
+use crate::storage::verify::{any_column, LINK_ANALYTICS_TABLES};
+use crate::storage::{HardDeleteReport, StorageError, StorageResult};
+
+use super::{SqliteStorage, PREVENT_URLS_DELETE_TRIGGER};
+
+pub(super) async fn hard_delete(
+    storage: &SqliteStorage,
+    short_code: &str,
+    actor: Option<&str>,
+) -> StorageResult<Option<HardDeleteReport>> {
+    if !storage.allow_hard_delete {
+        return Err(StorageError::PermissionDenied);
+    }
+
+    let mut tx = storage.pool.begin().await?;
+    let rows: Vec<(i64, String, String)> = sqlx::query_as(
+        "SELECT id, short_code, original_url FROM urls WHERE short_code = ?1 OR alias_of = ?1 ORDER BY id",
+    )
+    .bind(short_code)
+    .fetch_all(&mut *tx)
+    .await?;
+    if !rows.iter().any(|(_, code, _)| code == short_code) {
+        return Ok(None);
+    }
+
+    // Audited for the owner while the row is still there to name them
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (action, short_code, actor, effective_user, created_at)
+        SELECT 'hard_delete', short_code, ?1, COALESCE(created_by, ?1, ''), ?2
+        FROM urls WHERE short_code = ?3
+        "#,
+    )
+    .bind(actor)
+    .bind(storage.clock.now_epoch_secs())
+    .bind(short_code)
+    .execute(&mut *tx)
+    .await?;
+
+    // DDL is transactional: the trigger is back before anyone else writes
+    sqlx::query("DROP TRIGGER IF EXISTS prevent_urls_delete")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut report = HardDeleteReport::default();
+    for (id, code, original_url) in rows {
+        // The search tables read their text from urls and have no delete
+        // trigger, so their entries go first, while the row still matches.
+        sqlx::query(
+            "INSERT INTO urls_fts_code(urls_fts_code, rowid, short_code) VALUES('delete', ?, ?)",
+        )
+        .bind(id)
+        .bind(&code)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO urls_fts_url(urls_fts_url, rowid, original_url) VALUES('delete', ?, ?)",
+        )
+        .bind(id)
+        .bind(&original_url)
+        .execute(&mut *tx)
+        .await?;
+
+        for (table, columns) in LINK_ANALYTICS_TABLES {
+            let condition = any_column(columns, |column| format!("{column} = ?1"));
+            report.analytics_rows += sqlx::query(&format!("DELETE FROM {table} WHERE {condition}"))
+                .bind(&code)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+        }
+        report.click_history_rows += sqlx::query("DELETE FROM click_history WHERE short_code = ?")
+            .bind(&code)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+        report.url_history_rows += sqlx::query("DELETE FROM url_history WHERE short_code = ?")
+            .bind(&code)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+        sqlx::query("DELETE FROM link_moderation WHERE short_code = ?")
+            .bind(&code)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM urls WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        report.codes.push(code);
+    }
+
+    sqlx::query(PREVENT_URLS_DELETE_TRIGGER)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(Some(report))
+}
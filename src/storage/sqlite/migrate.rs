@@ -0,0 +1,113 @@
+//! The numbered migrations `init()` applies for `SqliteStorage`, inside the
+//! schema transaction that already holds the write lock.
+
+use crate::storage::migrations;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+/// Merge analytics rows that share a key once NULL dimensions compare equal,
+/// keeping the oldest row of each group with the summed visit count. Rows
+/// upserted before `idx_analytics_key` never conflicted on a NULL dimension,
+/// so each flush of such a key added another row. Returns the rows removed.
+async fn merge_duplicate_analytics(connection: &mut sqlx::SqliteConnection) -> Result<u64> {
+    sqlx::query(
+        r#"
+        UPDATE analytics
+        SET visit_count = merged.visit_count, updated_at = merged.updated_at
+        FROM (
+            SELECT MIN(id) AS id, SUM(visit_count) AS visit_count, MAX(updated_at) AS updated_at
+            FROM analytics
+            GROUP BY short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version
+            HAVING COUNT(*) > 1
+        ) AS merged
+        WHERE analytics.id = merged.id
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM analytics
+        WHERE id NOT IN (
+            SELECT MIN(id)
+            FROM analytics
+            GROUP BY short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?
+    .rows_affected();
+    Ok(removed)
+}
+
+/// Apply the numbered migrations the database has not had, each recorded
+/// in `schema_migrations`. Runs inside `create_schema`'s transaction, which
+/// already holds the write lock.
+pub(super) async fn apply_migrations(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    let applied: HashSet<i64> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *connection)
+        .await?
+        .into_iter()
+        .collect();
+
+    for migration in migrations::pending(&applied) {
+        match migration.version {
+            1 => migrate_analytics_key(&mut *connection).await?,
+            2 => migrate_created_at_millis(&mut *connection).await?,
+            version => return Err(anyhow!("no SQLite implementation of migration {version}")),
+        }
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .execute(&mut *connection)
+        .await?;
+        tracing::info!(
+            version = migration.version,
+            name = migration.name,
+            "Applied schema migration"
+        );
+    }
+    Ok(())
+}
+
+/// Migration 1: one analytics row per key with NULL dimensions compared as
+/// equal, which a plain UNIQUE constraint does not do, so upserts of a visit
+/// without a region or ASN add to the existing row. NULL text maps to '' and
+/// a NULL ASN to -1; writers store neither. Rows written before the index
+/// are merged first. The old table constraint stays, as SQLite cannot drop
+/// it, and is implied by the index.
+async fn migrate_analytics_key(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    let merged = merge_duplicate_analytics(&mut *connection).await?;
+    if merged > 0 {
+        tracing::info!(rows = merged, "Merged duplicate analytics rows");
+    }
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_analytics_key ON analytics(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)",
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+/// Migration 2: link creation times in milliseconds instead of seconds,
+/// with `updated_at` starting out equal to them.
+async fn migrate_created_at_millis(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    sqlx::query("UPDATE urls SET created_at = created_at * 1000, updated_at = created_at * 1000")
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
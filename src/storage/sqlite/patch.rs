@@ -0,0 +1,103 @@
+//! Repair of malformed `created_by` values for `SqliteStorage`.
+
+use crate::storage::MalformedPatchBatch;
+use anyhow::Result;
+
+use super::SqliteStorage;
+
+pub(super) async fn patch_created_by(
+    storage: &SqliteStorage,
+    short_code: &str,
+    new_created_by: &str,
+    auth_method: Option<&str>,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET created_by = ?, created_by_auth_method = ?, updated_at = ?
+        WHERE short_code = ?
+        "#,
+    )
+    .bind(new_created_by)
+    .bind(auth_method)
+    .bind(storage.clock.now_epoch_ms())
+    .bind(short_code)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn patch_malformed_created_by_batch(
+    storage: &SqliteStorage,
+    new_created_by: &str,
+    after_id: i64,
+    limit: i64,
+) -> Result<Option<MalformedPatchBatch>> {
+    let ids = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE urls
+        SET created_by = ?, updated_at = ?
+        WHERE id IN (
+            SELECT id
+            FROM urls
+            WHERE id > ?
+              AND (created_by IS NULL
+                   OR created_by = ''
+                   OR created_by = '00000000-0000-0000-0000-000000000000')
+            ORDER BY id
+            LIMIT ?
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(new_created_by)
+    .bind(storage.clock.now_epoch_ms())
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(storage.pool.as_ref())
+    .await?;
+
+    Ok(ids.iter().max().map(|&last_id| MalformedPatchBatch {
+        patched: ids.len() as i64,
+        last_id,
+    }))
+}
+
+pub(super) async fn count_malformed_created_by(storage: &SqliteStorage) -> Result<i64> {
+    let count = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM urls
+        WHERE created_by IS NULL
+           OR created_by = ''
+           OR created_by = '00000000-0000-0000-0000-000000000000'
+        "#,
+    )
+    .fetch_one(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(count)
+}
+
+pub(super) async fn sample_malformed_created_by(
+    storage: &SqliteStorage,
+    limit: i64,
+) -> Result<Vec<String>> {
+    let codes = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT short_code
+        FROM urls
+        WHERE created_by IS NULL
+           OR created_by = ''
+           OR created_by = '00000000-0000-0000-0000-000000000000'
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(codes)
+}
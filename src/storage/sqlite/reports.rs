@@ -0,0 +1,173 @@
+//! Instance statistics and activity reports for `SqliteStorage`.
+
+use crate::analytics::daily::{day_start, RollupSplit, DAY_SECS};
+use crate::models::{activity_series, ActivityDay, DailyRanking, InstanceStatsDay};
+use crate::storage::Storage;
+use anyhow::Result;
+
+use super::SqliteStorage;
+
+/// Create the `instance_stats_daily` table.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // One row per recorded UTC day of instance-wide totals
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS instance_stats_daily (
+            day INTEGER PRIMARY KEY,
+            total_links INTEGER NOT NULL,
+            active_links INTEGER NOT NULL,
+            links_created INTEGER NOT NULL,
+            clicks INTEGER NOT NULL,
+            active_users INTEGER NOT NULL,
+            recorded_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn record_instance_stats(
+    storage: &SqliteStorage,
+    day: i64,
+) -> Result<InstanceStatsDay> {
+    let day = day_start(day);
+    let day_end = day + DAY_SECS;
+    // Link timestamps are in milliseconds, click history hours in seconds
+    let (day_start_ms, day_end_ms) = (day * 1000, day_end * 1000);
+    let stats = sqlx::query_as::<_, InstanceStatsDay>(
+        r#"
+        INSERT INTO instance_stats_daily (day, total_links, active_links, links_created, clicks, active_users, recorded_at)
+        SELECT
+            ?,
+            COUNT(*),
+            COALESCE(SUM(is_active = 1), 0),
+            COALESCE(SUM(created_at >= ?), 0),
+            (SELECT COALESCE(SUM(clicks), 0) FROM click_history WHERE hour >= ? AND hour < ?),
+            COUNT(DISTINCT CASE WHEN created_at >= ? THEN created_by END),
+            ?
+        FROM urls
+        WHERE alias_of IS NULL AND reserved_until IS NULL AND created_at < ?
+        ON CONFLICT(day) DO UPDATE SET
+            total_links = excluded.total_links,
+            active_links = excluded.active_links,
+            links_created = excluded.links_created,
+            clicks = excluded.clicks,
+            active_users = excluded.active_users,
+            recorded_at = excluded.recorded_at
+        RETURNING day, total_links, active_links, links_created, clicks, active_users
+        "#,
+    )
+    .bind(day)
+    .bind(day_start_ms)
+    .bind(day)
+    .bind(day_end)
+    .bind(day_start_ms)
+    .bind(storage.clock.now_epoch_secs())
+    .bind(day_end_ms)
+    .fetch_one(storage.pool.as_ref())
+    .await?;
+
+    Ok(stats)
+}
+
+pub(super) async fn instance_stats_history(
+    storage: &SqliteStorage,
+    since: i64,
+) -> Result<Vec<InstanceStatsDay>> {
+    let history = sqlx::query_as::<_, InstanceStatsDay>(
+        "SELECT day, total_links, active_links, links_created, clicks, active_users FROM instance_stats_daily WHERE day >= ? ORDER BY day",
+    )
+    .bind(since)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(history)
+}
+
+pub(super) async fn activity_report(
+    storage: &SqliteStorage,
+    since: i64,
+    until: i64,
+    top: i64,
+) -> Result<Vec<ActivityDay>> {
+    // Link timestamps are in milliseconds; the rest in seconds. Links
+    // without a creator count toward the day but rank last, and are
+    // dropped from the top list when they make it in.
+    let creators = sqlx::query_as::<_, DailyRanking>(
+        r#"
+        SELECT day, created_by AS key, n AS count, total FROM (
+            SELECT day, created_by, n,
+                   SUM(n) OVER (PARTITION BY day) AS total,
+                   ROW_NUMBER() OVER (PARTITION BY day ORDER BY created_by IS NULL, n DESC, created_by) AS place
+            FROM (
+                SELECT (created_at / 86400000) * 86400 AS day, created_by, COUNT(*) AS n
+                FROM urls
+                WHERE alias_of IS NULL AND reserved_until IS NULL AND created_at >= ? AND created_at < ?
+                GROUP BY 1, 2
+            ) per_user
+        ) ranked
+        WHERE place <= ?
+        ORDER BY day, place
+        "#,
+    )
+    .bind(since * 1000)
+    .bind(until * 1000)
+    .bind(top.max(1))
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    let links = sqlx::query_as::<_, DailyRanking>(
+        r#"
+        SELECT day, short_code AS key, n AS count, total FROM (
+            SELECT day, short_code, n,
+                   SUM(n) OVER (PARTITION BY day) AS total,
+                   ROW_NUMBER() OVER (PARTITION BY day ORDER BY n DESC, short_code) AS place
+            FROM (
+                SELECT (hour / 86400) * 86400 AS day, short_code, SUM(clicks) AS n
+                FROM click_history
+                WHERE hour >= ? AND hour < ?
+                GROUP BY 1, 2
+            ) per_link
+        ) ranked
+        WHERE place <= ?
+        ORDER BY day, place
+        "#,
+    )
+    .bind(since)
+    .bind(until)
+    .bind(top.max(1))
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    // Whole days inside the rollup watermark come from analytics_daily
+    let rolled_until = storage.analytics_daily_rolled_until().await?;
+    let (daily_from, daily_until) = RollupSplit::of(Some(since), Some(until - 1), rolled_until)
+        .map_or((until, until), |split| {
+            (split.daily_from, split.daily_until)
+        });
+    let visits: Vec<(i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT day, CAST(SUM(visit_count) AS INTEGER) FROM (
+            SELECT (time_bucket / 86400) * 86400 AS day, visit_count FROM analytics
+            WHERE (time_bucket >= ? AND time_bucket < ?) OR (time_bucket >= ? AND time_bucket < ?)
+            UNION ALL
+            SELECT day, visit_count FROM analytics_daily WHERE day >= ? AND day < ?
+        ) visits
+        GROUP BY day
+        "#,
+    )
+    .bind(since)
+    .bind(daily_from)
+    .bind(daily_until)
+    .bind(until)
+    .bind(daily_from)
+    .bind(daily_until)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(activity_series(
+        since, until, DAY_SECS, creators, links, visits,
+    ))
+}
@@ -0,0 +1,278 @@
+//! Analytics pruning and the daily rollup for `SqliteStorage`.
+
+use crate::analytics::daily::{day_start, rollup_start};
+use crate::analytics::{DROPPED_DIMENSION_MARKER, DROPPED_IP_VERSION};
+use anyhow::Result;
+
+use super::SqliteStorage;
+
+/// Create the prune log, `analytics_daily` and `analytics_daily_runs`.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // One row per `lynx analytics prune`, so responses can say from when
+    // analytics are still at full detail
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics_prune_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ran_at INTEGER NOT NULL,
+            cutoff INTEGER NOT NULL,
+            retention_days INTEGER NOT NULL,
+            dropped_dimensions TEXT NOT NULL,
+            deleted_count INTEGER NOT NULL,
+            inserted_count INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Visits per code, UTC day and country, summed from hourly analytics by
+    // the daily rollup; see `crate::analytics::daily`
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics_daily (
+            short_code TEXT NOT NULL,
+            day INTEGER NOT NULL,
+            country_code TEXT,
+            visit_count INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_analytics_daily_short_code_day ON analytics_daily(short_code, day)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // One row per rollup; the latest `until_day` is the watermark below which
+    // every day is in analytics_daily
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS analytics_daily_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ran_at INTEGER NOT NULL,
+            from_day INTEGER,
+            until_day INTEGER NOT NULL,
+            row_count INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+/// Record one analytics prune in `analytics_prune_runs`; `counts` are the
+/// rows deleted and inserted.
+async fn record_prune_run<'e>(
+    executor: impl sqlx::SqliteExecutor<'e>,
+    ran_at: i64,
+    cutoff: i64,
+    retention_days: i64,
+    drop_dimensions: &[String],
+    (deleted_count, inserted_count): (i64, i64),
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO analytics_prune_runs (ran_at, cutoff, retention_days, dropped_dimensions, deleted_count, inserted_count) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(ran_at)
+    .bind(cutoff)
+    .bind(retention_days)
+    .bind(drop_dimensions.join(","))
+    .bind(deleted_count)
+    .bind(inserted_count)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn prune_analytics(
+    storage: &SqliteStorage,
+    retention_days: i64,
+    drop_dimensions: &[String],
+) -> Result<(i64, i64)> {
+    // Compute cutoff_time and round to the start of an hour
+    // This ensures time_bucket is always a valid hourly boundary
+    let raw_cutoff_time = storage.clock.now_epoch_secs() - (retention_days * 86400);
+    let cutoff_time = (raw_cutoff_time / 3600) * 3600;
+
+    // Count old entries before pruning
+    let count_query = "SELECT COUNT(*) FROM analytics WHERE time_bucket < ?";
+    let old_count: (i64,) = sqlx::query_as(count_query)
+        .bind(cutoff_time)
+        .fetch_one(storage.pool.as_ref())
+        .await?;
+    let deleted_count = old_count.0;
+
+    // If no old entries, record the run and return early
+    if deleted_count == 0 {
+        record_prune_run(
+            storage.pool.as_ref(),
+            storage.clock.now_epoch_secs(),
+            cutoff_time,
+            retention_days,
+            drop_dimensions,
+            (0, 0),
+        )
+        .await?;
+        return Ok((0, 0));
+    }
+
+    // Build the SELECT clause with dropped dimensions replaced
+    let mut select_fields = vec!["short_code".to_string()];
+
+    // Pre-compute dimension checks for efficiency
+    let drop_country = drop_dimensions.contains(&"country_code".to_string())
+        || drop_dimensions.contains(&"country".to_string());
+
+    // Always set time_bucket to cutoff_time for aggregated entries
+    // This ensures they won't be immediately deleted and simplifies logic
+    select_fields.push(format!("CAST({} AS INTEGER) as time_bucket", cutoff_time));
+
+    // Add dimension fields with conditional dropping
+    for field in &["country_code", "region", "city", "asn", "ip_version"] {
+        if drop_dimensions.contains(&field.to_string())
+            || (field == &"country_code" && drop_country)
+        {
+            if field == &"asn" {
+                select_fields.push(format!("NULL as {}", field));
+            } else if field == &"ip_version" {
+                // A bare integer in GROUP BY would name a column position
+                select_fields.push(format!(
+                    "CAST({} AS INTEGER) as {}",
+                    DROPPED_IP_VERSION, field
+                ));
+            } else {
+                select_fields.push(format!("'{}' as {}", DROPPED_DIMENSION_MARKER, field));
+            }
+        } else {
+            select_fields.push(field.to_string());
+        }
+    }
+
+    // Build SELECT clause
+    let select_clause = select_fields.join(", ");
+
+    // Build GROUP BY clause - use expressions without "as alias" parts
+    let group_by_expressions: Vec<String> = select_fields
+        .iter()
+        .map(|f| {
+            // Extract expression before "as" if present, otherwise use the whole field
+            if f.contains(" as ") {
+                f.split(" as ").next().unwrap().to_string()
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+    let group_by_clause = group_by_expressions.join(", ");
+
+    let now = storage.clock.now_epoch_secs();
+    let mut tx = storage.pool.begin().await?;
+
+    // Create aggregated entries with time_bucket set to cutoff_time
+    // Note: We don't exclude entries at cutoff_time since all old entries
+    // should be aggregated together with their new time_bucket value.
+    // A row already in the cutoff hour with the same key absorbs its
+    // aggregate.
+    let aggregate_query = format!(
+        "INSERT INTO analytics (short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at)
+         SELECT {}, SUM(visit_count) as visit_count, {} as created_at, {} as updated_at
+         FROM analytics
+         WHERE time_bucket < ?
+         GROUP BY {}
+         ON CONFLICT(short_code, time_bucket, COALESCE(country_code, ''), COALESCE(region, ''), COALESCE(city, ''), COALESCE(asn, -1), ip_version)
+         DO UPDATE SET visit_count = visit_count + excluded.visit_count, updated_at = excluded.updated_at",
+        select_clause, now, now, group_by_clause
+    );
+
+    let insert_result = sqlx::query(&aggregate_query)
+        .bind(cutoff_time)
+        .execute(&mut *tx)
+        .await?;
+
+    let inserted_count = insert_result.rows_affected() as i64;
+
+    // Delete old entries
+    let delete_query = "DELETE FROM analytics WHERE time_bucket < ?";
+    sqlx::query(delete_query)
+        .bind(cutoff_time)
+        .execute(&mut *tx)
+        .await?;
+
+    record_prune_run(
+        &mut *tx,
+        now,
+        cutoff_time,
+        retention_days,
+        drop_dimensions,
+        (deleted_count, inserted_count),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((deleted_count, inserted_count))
+}
+
+pub(super) async fn analytics_complete_since(storage: &SqliteStorage) -> Result<Option<i64>> {
+    let (cutoff,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(cutoff) FROM analytics_prune_runs WHERE deleted_count > 0")
+            .fetch_one(storage.read_pool.as_ref())
+            .await?;
+    Ok(cutoff)
+}
+
+pub(super) async fn rollup_analytics_daily(
+    storage: &SqliteStorage,
+    from: Option<i64>,
+    until: i64,
+) -> Result<i64> {
+    let until = day_start(until);
+    let mut tx = storage.pool.begin().await?;
+    let (rolled_until,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(until_day) FROM analytics_daily_runs")
+            .fetch_one(&mut *tx)
+            .await?;
+    let from = rollup_start(from, rolled_until);
+    if from.is_some_and(|from| from >= until) {
+        return Ok(0);
+    }
+
+    // Rebuilt from scratch, so a day rolled up twice is not counted twice
+    let range = (from.unwrap_or(i64::MIN), until);
+    sqlx::query("DELETE FROM analytics_daily WHERE day >= ? AND day < ?")
+        .bind(range.0)
+        .bind(range.1)
+        .execute(&mut *tx)
+        .await?;
+    let rows = sqlx::query(
+        "INSERT INTO analytics_daily (short_code, day, country_code, visit_count) SELECT short_code, (time_bucket / 86400) * 86400, country_code, SUM(visit_count) FROM analytics WHERE time_bucket >= ? AND time_bucket < ? GROUP BY short_code, (time_bucket / 86400) * 86400, country_code",
+    )
+    .bind(range.0)
+    .bind(range.1)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() as i64;
+    sqlx::query("INSERT INTO analytics_daily_runs (ran_at, from_day, until_day, row_count) VALUES (?, ?, ?, ?)")
+        .bind(storage.clock.now_epoch_secs())
+        .bind(from)
+        .bind(until)
+        .bind(rows)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(rows)
+}
+
+pub(super) async fn analytics_daily_rolled_until(storage: &SqliteStorage) -> Result<Option<i64>> {
+    let (rolled_until,): (Option<i64>,) =
+        sqlx::query_as("SELECT MAX(until_day) FROM analytics_daily_runs")
+            .fetch_one(storage.read_pool.as_ref())
+            .await?;
+    Ok(rolled_until)
+}
@@ -0,0 +1,305 @@
+//! Schema setup for `SqliteStorage`: the `urls` table and the order `init()`
+//! creates each feature's tables in.
+
+use crate::config::UrlNormalizationConfig;
+use crate::destination::{destination_host, normalize_url};
+use crate::storage::campaigns;
+use crate::storage::challenge_redemptions;
+use crate::storage::server_leases;
+use anyhow::Result;
+
+use super::{
+    analytics, audit, clicks, links, migrate, moderation, reports, rollups, search, usage, users,
+    PREVENT_URLS_DELETE_TRIGGER,
+};
+
+/// Rows read per batch while filling `urls.dest_host` or
+/// `urls.normalized_url` for existing links.
+const DEST_HOST_BACKFILL_BATCH: i64 = 1_000;
+
+/// Fill `urls.dest_host` from `original_url` for every existing row, a
+/// batch of ids at a time.
+async fn backfill_dest_host(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    let mut after = 0_i64;
+    loop {
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, original_url FROM urls WHERE id > ? ORDER BY id LIMIT ?")
+                .bind(after)
+                .bind(DEST_HOST_BACKFILL_BATCH)
+                .fetch_all(&mut *connection)
+                .await?;
+        let Some((last, _)) = rows.last() else {
+            return Ok(());
+        };
+        after = *last;
+        for (id, original_url) in &rows {
+            if let Some(host) = destination_host(original_url) {
+                sqlx::query("UPDATE urls SET dest_host = ? WHERE id = ?")
+                    .bind(host)
+                    .bind(id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+        }
+    }
+}
+
+/// Fill `urls.normalized_url` from `original_url` for every existing row, a
+/// batch of ids at a time.
+async fn backfill_normalized_url(
+    connection: &mut sqlx::SqliteConnection,
+    normalization: &UrlNormalizationConfig,
+) -> Result<()> {
+    let mut after = 0_i64;
+    loop {
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, original_url FROM urls WHERE id > ? ORDER BY id LIMIT ?")
+                .bind(after)
+                .bind(DEST_HOST_BACKFILL_BATCH)
+                .fetch_all(&mut *connection)
+                .await?;
+        let Some((last, _)) = rows.last() else {
+            return Ok(());
+        };
+        after = *last;
+        for (id, original_url) in &rows {
+            if let Some(normalized) = normalize_url(original_url, normalization) {
+                sqlx::query("UPDATE urls SET normalized_url = ? WHERE id = ?")
+                    .bind(normalized)
+                    .bind(id)
+                    .execute(&mut *connection)
+                    .await?;
+            }
+        }
+    }
+}
+
+/// Create or upgrade the schema. Every statement is idempotent; `init` runs
+/// them all in one transaction so concurrent callers never interleave.
+pub(super) async fn create_schema(
+    connection: &mut sqlx::SqliteConnection,
+    normalization: &UrlNormalizationConfig,
+) -> Result<()> {
+    // Create URLs table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS urls (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            short_code TEXT NOT NULL UNIQUE,
+            original_url TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            created_by TEXT,
+            clicks INTEGER NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_short_code ON urls(short_code)")
+        .execute(&mut *connection)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_created_by ON urls(created_by)")
+        .execute(&mut *connection)
+        .await?;
+
+    // Reserved codes hold a placeholder destination until `reserved_until`.
+    // SQLite has no ADD COLUMN IF NOT EXISTS, so check the table first.
+    let has_reserved_until: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'reserved_until'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_reserved_until == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN reserved_until INTEGER")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_reserved_until ON urls(reserved_until) WHERE reserved_until IS NOT NULL",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // A renamed code keeps redirecting as an alias of its new code.
+    let has_alias_of: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'alias_of'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_alias_of == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN alias_of TEXT")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_alias_of ON urls(alias_of) WHERE alias_of IS NOT NULL",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Destination page title, fetched in the background after creation
+    let has_title: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'title'")
+            .fetch_one(&mut *connection)
+            .await?;
+    if has_title == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN title TEXT")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // How each link was created; rows from before the column are unknown
+    let has_created_via: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'created_via'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_created_via == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN created_via TEXT NOT NULL DEFAULT 'unknown'")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Set by the click flush; links never visited since the column exists are NULL
+    let has_last_visited_at: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'last_visited_at'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_last_visited_at == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN last_visited_at INTEGER")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Recorded from the creator's claims; links created before are NULL
+    let has_created_by_auth_method: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'created_by_auth_method'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_created_by_auth_method == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN created_by_auth_method TEXT")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Per-link options as one JSON object (see `LinkOptions`)
+    let has_options: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'options'")
+            .fetch_one(&mut *connection)
+            .await?;
+    if has_options == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN options TEXT NOT NULL DEFAULT '{}'")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Last change to what a link does; existing rows get theirs from
+    // migration 2
+    let has_updated_at: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'updated_at'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_updated_at == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Destination host for reports by domain, derived in Rust from
+    // original_url on every write; existing rows are filled when it is added
+    let has_dest_host: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'dest_host'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_dest_host == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN dest_host TEXT")
+            .execute(&mut *connection)
+            .await?;
+        backfill_dest_host(&mut *connection).await?;
+    }
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_dest_host ON urls(dest_host) WHERE dest_host IS NOT NULL",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Canonical destination for dedupe, derived the same way with the
+    // configured URL_NORMALIZE_STEPS
+    let has_normalized_url: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('urls') WHERE name = 'normalized_url'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_normalized_url == 0 {
+        sqlx::query("ALTER TABLE urls ADD COLUMN normalized_url TEXT")
+            .execute(&mut *connection)
+            .await?;
+        backfill_normalized_url(&mut *connection, normalization).await?;
+    }
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_normalized_url ON urls(normalized_url) WHERE normalized_url IS NOT NULL",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    campaigns::sqlite::create_schema(&mut *connection).await?;
+    server_leases::sqlite::create_schema(&mut *connection).await?;
+    challenge_redemptions::sqlite::create_schema(&mut *connection).await?;
+
+    // Index for cursor-based pagination (created_at DESC, id DESC)
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_created_at_id ON urls(created_at DESC, id DESC)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Index for user-specific cursor pagination
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_urls_created_by_created_at_id ON urls(created_by, created_at DESC, id DESC)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    users::create_schema(&mut *connection).await?;
+    analytics::create_schema(&mut *connection).await?;
+    audit::create_schema(&mut *connection).await?;
+    moderation::create_schema(&mut *connection).await?;
+    rollups::create_schema(&mut *connection).await?;
+
+    // Runtime settings admins change without a redeploy
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    usage::create_schema(&mut *connection).await?;
+    reports::create_schema(&mut *connection).await?;
+    links::create_schema(&mut *connection).await?;
+    clicks::create_schema(&mut *connection).await?;
+
+    // Security: Create trigger to prevent DELETE operations on urls table
+    // This ensures URLs can only be deactivated, never deleted
+    sqlx::query(PREVENT_URLS_DELETE_TRIGGER)
+        .execute(&mut *connection)
+        .await?;
+
+    search::create_schema(&mut *connection).await?;
+
+    migrate::apply_migrations(&mut *connection).await
+}
@@ -0,0 +1,196 @@
+//! Link search for `SqliteStorage`: exact codes first, then FTS5 matches.
+
+use crate::models::{CreatedVia, ShortenedUrl};
+use crate::storage::relevance::rank_by_relevance;
+use crate::storage::{SearchParams, SearchResult, SearchSort, SEARCH_EXTRA_ROWS, URL_COLUMNS};
+use anyhow::Result;
+
+use super::SqliteStorage;
+
+/// Create the FTS5 tables search reads and the triggers that keep them
+/// in sync with `urls`, then reindex existing links.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // FTS5 trigram search tables for efficient substring matching
+    // Table for case-sensitive short_code search
+    sqlx::query(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS urls_fts_code USING fts5(
+            short_code,
+            tokenize = 'trigram case_sensitive 1',
+            content = 'urls',
+            content_rowid = 'id'
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Table for case-insensitive original_url search
+    sqlx::query(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS urls_fts_url USING fts5(
+            original_url,
+            tokenize = 'trigram',
+            content = 'urls',
+            content_rowid = 'id'
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Triggers to keep FTS tables in sync with urls table
+    // Insert trigger
+    sqlx::query(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS urls_fts_insert AFTER INSERT ON urls BEGIN
+            INSERT INTO urls_fts_code(rowid, short_code) VALUES (new.id, new.short_code);
+            INSERT INTO urls_fts_url(rowid, original_url) VALUES (new.id, new.original_url);
+        END
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Update trigger
+    sqlx::query(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS urls_fts_update AFTER UPDATE ON urls BEGIN
+            INSERT INTO urls_fts_code(urls_fts_code, rowid, short_code) VALUES('delete', old.id, old.short_code);
+            INSERT INTO urls_fts_url(urls_fts_url, rowid, original_url) VALUES('delete', old.id, old.original_url);
+            INSERT INTO urls_fts_code(rowid, short_code) VALUES (new.id, new.short_code);
+            INSERT INTO urls_fts_url(rowid, original_url) VALUES (new.id, new.original_url);
+        END
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Rebuild FTS indexes to ensure existing data is indexed
+    // This is idempotent and safe to run on every init
+    sqlx::query("INSERT INTO urls_fts_code(urls_fts_code) VALUES('rebuild')")
+        .execute(&mut *connection)
+        .await?;
+    sqlx::query("INSERT INTO urls_fts_url(urls_fts_url) VALUES('rebuild')")
+        .execute(&mut *connection)
+        .await?;
+    Ok(())
+}
+
+/// The link whose short code is exactly the search query, if it passes
+/// the search filters. One unique-index lookup, so it is cheap next to
+/// the substring match.
+async fn search_exact_code(
+    storage: &SqliteStorage,
+    params: &SearchParams,
+    created_by: Option<&str>,
+) -> Result<Option<ShortenedUrl>> {
+    let created_via = params.created_via.map(CreatedVia::as_str);
+    let url = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        SELECT {URL_COLUMNS}
+        FROM urls u
+        WHERE u.short_code = ?1
+          AND (?2 IS NULL OR (?2 = '__null__' AND u.created_by IS NULL) OR u.created_by = ?2)
+          AND (?3 IS NULL OR u.created_at >= ?3)
+          AND (?4 IS NULL OR u.created_at < ?4)
+          AND (?5 IS NULL OR u.is_active = ?5)
+          AND (?6 IS NULL OR u.created_via = ?6)
+          AND (?7 IS NULL OR COALESCE(u.last_visited_at, 0) < ?7)
+          AND (?8 IS NULL OR u.campaign_id = ?8)
+        "#
+    ))
+    .bind(&params.q)
+    .bind(created_by)
+    .bind(params.created_from)
+    .bind(params.created_to)
+    .bind(params.is_active)
+    .bind(created_via)
+    .bind(params.unused_since)
+    .bind(params.campaign_id)
+    .fetch_optional(storage.read_pool.as_ref())
+    .await?;
+    Ok(url)
+}
+
+/// Links whose code or destination contains the search query and pass
+/// its filters, newest first, after `params.cursor` when it is set.
+async fn search_matches(
+    storage: &SqliteStorage,
+    params: &SearchParams,
+    created_by: Option<&str>,
+    fetch_limit: i64,
+) -> Result<Vec<ShortenedUrl>> {
+    // Escape the query for FTS5 MATCH - treat as literal by wrapping in quotes
+    // Double any quotes in the query to escape them
+    let fts_query = format!("\"{}\"", params.q.replace('"', "\"\""));
+    let (cursor_created_at, cursor_id) = params.cursor.unzip();
+    let urls = sqlx::query_as::<_, ShortenedUrl>(&format!(
+        r#"
+        WITH matched(fts_rowid) AS (
+            SELECT rowid FROM urls_fts_code WHERE urls_fts_code MATCH ?1
+            UNION
+            SELECT rowid FROM urls_fts_url WHERE urls_fts_url MATCH ?1
+        )
+        SELECT {URL_COLUMNS}
+        FROM urls
+        JOIN matched ON fts_rowid = urls.id
+        WHERE (?2 IS NULL OR (?2 = '__null__' AND created_by IS NULL) OR created_by = ?2)
+          AND (?3 IS NULL OR created_at >= ?3)
+          AND (?4 IS NULL OR created_at < ?4)
+          AND (?5 IS NULL OR is_active = ?5)
+          AND (?6 IS NULL OR created_via = ?6)
+          AND (?7 IS NULL OR COALESCE(last_visited_at, 0) < ?7)
+          AND (?8 IS NULL OR campaign_id = ?8)
+          AND (?9 IS NULL OR created_at < ?9 OR (created_at = ?9 AND id < ?10))
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?11
+        "#
+    ))
+    .bind(fts_query)
+    .bind(created_by)
+    .bind(params.created_from)
+    .bind(params.created_to)
+    .bind(params.is_active)
+    .bind(params.created_via.map(CreatedVia::as_str))
+    .bind(params.unused_since)
+    .bind(params.campaign_id)
+    .bind(cursor_created_at)
+    .bind(cursor_id)
+    .bind(fetch_limit)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+    Ok(urls)
+}
+
+pub(super) async fn search(
+    storage: &SqliteStorage,
+    params: &SearchParams,
+    is_admin: bool,
+    user_id: Option<&str>,
+) -> Result<SearchResult> {
+    // Build the query based on user permissions
+    // Non-admin users can only search their own URLs
+    let effective_created_by = if is_admin {
+        params.created_by.clone()
+    } else {
+        // Non-admin must search only their own URLs
+        user_id.map(|s| s.to_string())
+    };
+
+    let exact = search_exact_code(storage, params, effective_created_by.as_deref()).await?;
+    let urls = search_matches(
+        storage,
+        params,
+        effective_created_by.as_deref(),
+        params.limit + SEARCH_EXTRA_ROWS,
+    )
+    .await?;
+
+    let mut result = SearchResult::from_rows(urls, exact, params.limit, params.cursor.is_none());
+    if params.sort == SearchSort::Relevance {
+        let pinned = usize::from(result.exact_match);
+        rank_by_relevance(&mut result.items[pinned..], &params.q);
+    }
+    Ok(result)
+}
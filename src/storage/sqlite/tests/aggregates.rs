@@ -0,0 +1,492 @@
+use super::super::aggregates::aggregate_query;
+use super::*;
+use crate::analytics::AnalyticsGroupBy;
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_country() {
+    let storage = setup_sqlite().await;
+
+    // Create a short code
+    storage
+        .create_with_code("multi", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert analytics from multiple countries
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup(
+            "multi",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            Some(15169),
+            10,
+        ),
+        rollup(
+            "multi",
+            time_bucket,
+            Some("GB"),
+            Some("England"),
+            Some("London"),
+            Some(16509),
+            5,
+        ),
+        rollup(
+            "multi",
+            time_bucket,
+            Some("US"),
+            Some("NY"),
+            Some("NYC"),
+            Some(15169),
+            3,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by country
+    let aggregates = storage
+        .get_analytics_aggregate("multi", None, None, AnalyticsGroupBy::Country, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 2); // US and GB
+
+    // Verify totals
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 18);
+
+    // Find US aggregate (should be 13 = 10 + 3)
+    let us_agg = aggregates.iter().find(|a| a.dimension == "US").unwrap();
+    assert_eq!(us_agg.visit_count, 13);
+
+    // Find GB aggregate (should be 5)
+    let gb_agg = aggregates.iter().find(|a| a.dimension == "GB").unwrap();
+    assert_eq!(gb_agg.visit_count, 5);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_region() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("LA"),
+            None,
+            7,
+        ),
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            Some("NY"),
+            Some("NYC"),
+            None,
+            4,
+        ),
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            None,
+            2,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by region
+    let aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::Region, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 2); // CA and NY
+
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 13);
+
+    // CA should have 9 visits (7 + 2), formatted as "CA, US"
+    let ca_agg = aggregates.iter().find(|a| a.dimension == "CA, US").unwrap();
+    assert_eq!(ca_agg.visit_count, 9);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_asn() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        rollup("test", time_bucket, Some("US"), None, None, Some(15169), 8),
+        rollup("test", time_bucket, Some("US"), None, None, Some(16509), 3),
+        rollup("test", time_bucket, Some("GB"), None, None, Some(15169), 2),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by ASN
+    let aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::Asn, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 2);
+
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 13);
+
+    // ASN 15169 should have 10 visits (8 + 2)
+    let asn_agg = aggregates.iter().find(|a| a.dimension == "15169").unwrap();
+    assert_eq!(asn_agg.visit_count, 10);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_with_time_range() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert records with different time buckets
+    let records = vec![
+        rollup("test", 1000, Some("US"), None, None, None, 5),
+        rollup("test", 2000, Some("GB"), None, None, None, 3),
+        rollup("test", 3000, Some("US"), None, None, None, 7),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate with time range that includes only middle record
+    let aggregates = storage
+        .get_analytics_aggregate(
+            "test",
+            Some(1500),
+            Some(2500),
+            AnalyticsGroupBy::Country,
+            10,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 1);
+    assert_eq!(aggregates[0].dimension, "GB");
+    assert_eq!(aggregates[0].visit_count, 3);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_city_formatting() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        // Full geo data: city, region, country
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Toronto"),
+            None,
+            5,
+        ),
+        // Same city, different count
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Toronto"),
+            None,
+            3,
+        ),
+        // Different city, same region and country
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Ottawa"),
+            None,
+            2,
+        ),
+        // Missing region
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            None,
+            Some("Portland"),
+            None,
+            1,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by city
+    let aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::City, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 3);
+
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 11);
+
+    // Toronto should be "Toronto, Ontario, CA" with 8 visits (5 + 3)
+    let toronto_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Toronto, Ontario, CA")
+        .unwrap();
+    assert_eq!(toronto_agg.visit_count, 8);
+
+    // Ottawa should be "Ottawa, Ontario, CA" with 2 visits
+    let ottawa_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Ottawa, Ontario, CA")
+        .unwrap();
+    assert_eq!(ottawa_agg.visit_count, 2);
+
+    // Portland should be "Portland, Unknown, US" (missing region)
+    let portland_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Portland, Unknown, US")
+        .unwrap();
+    assert_eq!(portland_agg.visit_count, 1);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_by_region_formatting() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        // Full data: country and region
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Toronto"),
+            None,
+            5,
+        ),
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Ottawa"),
+            None,
+            3,
+        ),
+        // Different region, same country
+        rollup(
+            "test",
+            time_bucket,
+            Some("CA"),
+            Some("Quebec"),
+            Some("Montreal"),
+            None,
+            2,
+        ),
+        // Missing country (edge case)
+        rollup(
+            "test",
+            time_bucket,
+            None,
+            Some("Texas"),
+            Some("Austin"),
+            None,
+            1,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by region
+    let aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::Region, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(aggregates.len(), 3);
+
+    let total: i64 = aggregates.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 11);
+
+    // Ontario should be "Ontario, CA" with 8 visits (5 + 3)
+    let ontario_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Ontario, CA")
+        .unwrap();
+    assert_eq!(ontario_agg.visit_count, 8);
+
+    // Quebec should be "Quebec, CA" with 2 visits
+    let quebec_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Quebec, CA")
+        .unwrap();
+    assert_eq!(quebec_agg.visit_count, 2);
+
+    // Texas should be "Texas, Unknown" (missing country)
+    let texas_agg = aggregates
+        .iter()
+        .find(|a| a.dimension == "Texas, Unknown")
+        .unwrap();
+    assert_eq!(texas_agg.visit_count, 1);
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_handles_dropped_markers() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+    let records = vec![
+        // Normal entry
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            None,
+            5,
+        ),
+        // Pruned entry with <dropped> markers
+        rollup(
+            "test",
+            time_bucket,
+            Some("US"),
+            Some("<dropped>"),
+            Some("<dropped>"),
+            None,
+            3,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Aggregate by city
+    let city_aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::City, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(city_aggregates.len(), 2);
+
+    // Normal entry should be formatted as "SF, CA, US"
+    let sf_agg = city_aggregates.iter().find(|a| a.dimension == "SF, CA, US");
+    assert!(sf_agg.is_some(), "Should have SF, CA, US entry");
+    assert_eq!(sf_agg.unwrap().visit_count, 5);
+
+    // Dropped entry should remain as "<dropped>"
+    let dropped_agg = city_aggregates.iter().find(|a| a.dimension == "<dropped>");
+    assert!(dropped_agg.is_some(), "Should have <dropped> entry");
+    assert_eq!(dropped_agg.unwrap().visit_count, 3);
+
+    // Aggregate by region
+    let region_aggregates = storage
+        .get_analytics_aggregate("test", None, None, AnalyticsGroupBy::Region, 10)
+        .await
+        .unwrap();
+
+    assert_eq!(region_aggregates.len(), 2);
+
+    // Normal entry should be formatted as "CA, US"
+    let ca_agg = region_aggregates.iter().find(|a| a.dimension == "CA, US");
+    assert!(ca_agg.is_some(), "Should have CA, US entry");
+
+    // Dropped entry should remain as "<dropped>"
+    let dropped_region_agg = region_aggregates
+        .iter()
+        .find(|a| a.dimension == "<dropped>");
+    assert!(
+        dropped_region_agg.is_some(),
+        "Should have <dropped> entry for region"
+    );
+}
+
+#[test]
+fn test_aggregate_queries_bind_every_request_value() {
+    for group_by in AnalyticsGroupBy::ALL {
+        let sql = aggregate_query(group_by);
+        // short_code, start, end and limit are all placeholders
+        assert_eq!(sql.matches('?').count(), 4, "{sql}");
+        assert!(!sql.contains('{'), "{sql}");
+    }
+}
+
+#[tokio::test]
+async fn test_analytics_aggregate_treats_short_code_as_data() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code("safe", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![rollup(
+            "safe",
+            1698768000,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            Some(15169),
+            2,
+        )])
+        .await
+        .unwrap();
+
+    let hostile = "safe' OR '1'='1'; DROP TABLE analytics; --";
+    for group_by in AnalyticsGroupBy::ALL {
+        let aggregates = storage
+            .get_analytics_aggregate(hostile, None, None, group_by, 10)
+            .await
+            .unwrap();
+        assert!(aggregates.is_empty(), "{group_by:?}");
+    }
+
+    let aggregates = storage
+        .get_analytics_aggregate("safe", None, None, AnalyticsGroupBy::Country, 10)
+        .await
+        .unwrap();
+    assert_eq!(aggregates.len(), 1);
+    assert_eq!(aggregates[0].visit_count, 2);
+}
@@ -0,0 +1,102 @@
+use super::*;
+use crate::storage::{Storage, StorageError};
+
+#[tokio::test]
+async fn test_rename_code_keeps_old_codes_as_aliases_of_the_canonical_link() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("first", "https://example.com/docs", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("taken", "https://example.com/other", None)
+        .await
+        .unwrap();
+
+    let second = storage
+        .rename_code("first", "second")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.original_url, "https://example.com/docs");
+    assert_eq!(second.created_by.as_deref(), Some("user1"));
+    assert!(second.alias_of.is_none());
+
+    let third = storage
+        .rename_code("second", "third")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(third.short_code, "third");
+    let mut aliases: Vec<(String, Option<String>)> = storage
+        .get_aliases(&["third".to_string()])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|url| (url.short_code.clone(), url.alias_of.clone()))
+        .collect();
+    aliases.sort();
+    assert_eq!(
+        aliases,
+        vec![
+            ("first".to_string(), Some("third".to_string())),
+            ("second".to_string(), Some("third".to_string())),
+        ]
+    );
+
+    // Renaming an alias or onto an existing code (including an alias of
+    // the same link) would create a chain or a cycle.
+    assert!(matches!(
+        storage.rename_code("first", "fourth").await,
+        Err(StorageError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        storage.rename_code("third", "second").await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(matches!(
+        storage.rename_code("third", "taken").await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(storage
+        .rename_code("missing", "fifth")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage.get_authoritative("fourth").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_add_alias_points_at_the_canonical_link() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("docs", "https://example.com/docs", Some("user1"))
+        .await
+        .unwrap();
+
+    let alias = storage
+        .add_alias("docs", "manual", Some("user2"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(alias.alias_of.as_deref(), Some("docs"));
+    assert_eq!(alias.original_url, "https://example.com/docs");
+    assert_eq!(alias.created_by.as_deref(), Some("user2"));
+    assert!(alias.is_active);
+
+    assert!(matches!(
+        storage.add_alias("manual", "chained", None).await,
+        Err(StorageError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        storage.add_alias("docs", "docs", None).await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(storage
+        .add_alias("missing", "other", None)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,349 @@
+use super::*;
+use crate::analytics::{AnalyticsExportScope, AnalyticsGroupBy, AnalyticsRollup};
+use crate::storage::{OrphanCounts, Storage};
+
+#[tokio::test]
+async fn test_analytics_upsert_and_retrieval() {
+    let storage = setup_sqlite().await;
+
+    // Create a short code
+    storage
+        .create_with_code("test123", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert analytics records
+    let time_bucket = 1698768000; // Some timestamp
+    let records = vec![
+        rollup(
+            "test123",
+            time_bucket,
+            Some("US"),
+            Some("CA"),
+            Some("San Francisco"),
+            Some(15169),
+            5,
+        ),
+        rollup(
+            "test123",
+            time_bucket,
+            Some("GB"),
+            Some("England"),
+            Some("London"),
+            Some(16509),
+            3,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Retrieve analytics
+    let analytics = storage
+        .get_analytics("test123", None, None, 100)
+        .await
+        .unwrap();
+
+    assert_eq!(analytics.len(), 2);
+
+    // Verify visit counts
+    let total_visits: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total_visits, 8);
+}
+
+#[tokio::test]
+async fn test_analytics_time_range_filtering() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Insert records with different time buckets
+    let records = vec![
+        rollup("test", 1000, Some("US"), None, None, None, 5),
+        rollup("test", 2000, Some("US"), None, None, None, 3),
+        rollup("test", 3000, Some("US"), None, None, None, 7),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Query with start time
+    let analytics = storage
+        .get_analytics("test", Some(1500), None, 100)
+        .await
+        .unwrap();
+    let total: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 10); // Only records from 2000 and 3000
+
+    // Query with end time
+    let analytics = storage
+        .get_analytics("test", None, Some(2500), 100)
+        .await
+        .unwrap();
+    let total: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 8); // Only records from 1000 and 2000
+
+    // Query with both start and end time
+    let analytics = storage
+        .get_analytics("test", Some(1500), Some(2500), 100)
+        .await
+        .unwrap();
+    let total: i64 = analytics.iter().map(|a| a.visit_count).sum();
+    assert_eq!(total, 3); // Only record from 2000
+}
+
+#[tokio::test]
+async fn test_analytics_upsert_increments_existing() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    let time_bucket = 1698768000;
+
+    // Insert initial record - all fields need to match for the UNIQUE constraint to trigger
+    let records = vec![rollup(
+        "test",
+        time_bucket,
+        Some("US"),
+        Some("CA"),
+        Some("SF"),
+        Some(15169),
+        5,
+    )];
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Upsert same key with more visits - all fields must match
+    let records = vec![rollup(
+        "test",
+        time_bucket,
+        Some("US"),
+        Some("CA"),
+        Some("SF"),
+        Some(15169),
+        3,
+    )];
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Should have incremented, not replaced
+    let analytics = storage
+        .get_analytics("test", None, None, 100)
+        .await
+        .unwrap();
+
+    assert_eq!(analytics.len(), 1);
+    assert_eq!(analytics[0].visit_count, 8); // 5 + 3
+}
+
+/// Analytics reference links by short code without a foreign key; the
+/// flush path must never be what creates orphans.
+#[tokio::test]
+async fn test_known_analytics_upsert_skips_unknown_codes() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("kept", "https://example.com", None)
+        .await
+        .unwrap();
+
+    let keyed = |code: &str, count| {
+        rollup(
+            code,
+            3600,
+            Some("US"),
+            Some("CA"),
+            Some("LA"),
+            Some(7922),
+            count,
+        )
+    };
+    let batch = || vec![keyed("kept", 2), keyed("wp-login.php", 9)];
+    assert_eq!(
+        storage.upsert_known_analytics_batch(batch()).await.unwrap(),
+        1
+    );
+    assert_eq!(
+        storage.upsert_known_analytics_batch(batch()).await.unwrap(),
+        1
+    );
+
+    assert_eq!(
+        storage.count_orphan_analytics().await.unwrap(),
+        OrphanCounts::default()
+    );
+    let kept = storage.get_analytics("kept", None, None, 10).await.unwrap();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].visit_count, 4);
+}
+
+#[tokio::test]
+async fn test_alias_visits_are_merged_into_analytics_and_counted_per_alias() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+
+    let via = |alias_used: Option<&str>, visit_count: i64| AnalyticsRollup {
+        alias_used: alias_used.map(str::to_string),
+        ..rollup(
+            "docs",
+            3_600,
+            Some("US"),
+            Some("CA"),
+            Some("LA"),
+            Some(7922),
+            visit_count,
+        )
+    };
+    let batch = vec![via(None, 1), via(Some("manual"), 2), via(Some("guide"), 4)];
+    storage.upsert_analytics_batch(batch.clone()).await.unwrap();
+    assert_eq!(
+        storage.upsert_known_analytics_batch(batch).await.unwrap(),
+        0
+    );
+
+    // One analytics row: the alias is not one of its dimensions.
+    let rows = storage
+        .get_analytics("docs", None, None, 100)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].visit_count, 14);
+
+    let by_alias = storage
+        .get_analytics_aggregate("docs", None, None, AnalyticsGroupBy::AliasUsed, 10)
+        .await
+        .unwrap();
+    let by_alias: Vec<(&str, i64)> = by_alias
+        .iter()
+        .map(|aggregate| (aggregate.dimension.as_str(), aggregate.visit_count))
+        .collect();
+    assert_eq!(by_alias, vec![("guide", 8), ("manual", 4)]);
+}
+
+#[tokio::test]
+async fn test_sampled_visits_are_counted_and_flagged_as_estimated() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+
+    let sampled = |time_bucket: i64, country: &str, estimated_visits: i64| AnalyticsRollup {
+        estimated_visits,
+        ..rollup(
+            "docs",
+            time_bucket,
+            Some(country),
+            None,
+            None,
+            None,
+            estimated_visits + 1,
+        )
+    };
+    storage
+        .upsert_analytics_batch(vec![
+            sampled(3_600, "US", 8),
+            sampled(3_600, "CA", 4),
+            sampled(7_200, "US", 0),
+            AnalyticsRollup {
+                short_code: "gone".to_string(),
+                ..sampled(3_600, "US", 4)
+            },
+        ])
+        .await
+        .unwrap();
+    storage
+        .upsert_known_analytics_batch(vec![sampled(7_200, "US", 10)])
+        .await
+        .unwrap();
+
+    let visits: i64 = storage
+        .get_analytics("docs", None, None, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.visit_count)
+        .sum();
+    assert_eq!(visits, 26);
+    let estimated = |start, end| storage.get_estimated_visits("docs", start, end);
+    assert_eq!(estimated(None, None).await.unwrap(), 22);
+    assert_eq!(estimated(Some(7_200), None).await.unwrap(), 10);
+    assert_eq!(estimated(None, Some(3_600)).await.unwrap(), 12);
+    // Estimates of codes that do not exist are dropped like their visits
+    assert_eq!(
+        storage
+            .get_estimated_visits("gone", None, None)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_analytics_export_pages_through_ties_and_owners() {
+    let storage = setup_sqlite().await;
+    for (code, owner) in [
+        ("mine", "user1"),
+        ("also-mine", "user1"),
+        ("theirs", "user2"),
+    ] {
+        storage
+            .create_with_code(code, "https://example.com", Some(owner))
+            .await
+            .unwrap();
+    }
+    // Three rows share the 1000 bucket, so pages must break ties on id.
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("mine", 1000, Some("US"), None, None, None, 1),
+            rollup("mine", 1000, Some("GB"), None, None, None, 1),
+            rollup("mine", 1000, Some("FR"), None, None, None, 1),
+            rollup("mine", 4600, Some("US"), None, None, None, 1),
+            rollup("also-mine", 2000, Some("US"), None, None, None, 1),
+            rollup("theirs", 3000, Some("US"), None, None, None, 1),
+        ])
+        .await
+        .unwrap();
+
+    let export = |scope, start, end| {
+        let storage = &storage;
+        async move {
+            let mut rows = Vec::new();
+            let mut after = None;
+            loop {
+                let page = storage
+                    .get_analytics_export_page(scope, start, end, after, 2)
+                    .await
+                    .unwrap();
+                after = page.last().map(|row| (row.time_bucket, row.id));
+                let done = page.len() < 2;
+                rows.extend(page);
+                if done {
+                    return rows;
+                }
+            }
+        }
+    };
+
+    let rows = export(AnalyticsExportScope::Link("mine"), None, None).await;
+    assert_eq!(rows.len(), 4);
+    // Strictly increasing keys: no row repeated or skipped across pages.
+    assert!(rows
+        .windows(2)
+        .all(|pair| (pair[0].time_bucket, pair[0].id) < (pair[1].time_bucket, pair[1].id)));
+
+    let rows = export(AnalyticsExportScope::Owner("user1"), None, None).await;
+    let codes: Vec<&str> = rows.iter().map(|row| row.short_code.as_str()).collect();
+    assert_eq!(codes, ["mine", "mine", "mine", "also-mine", "mine"]);
+
+    let rows = export(AnalyticsExportScope::Owner("user1"), Some(1500), Some(4600)).await;
+    let buckets: Vec<i64> = rows.iter().map(|row| row.time_bucket).collect();
+    assert_eq!(buckets, [2000, 4600]);
+}
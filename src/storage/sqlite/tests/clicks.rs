@@ -0,0 +1,211 @@
+use super::*;
+use crate::models::{ClickHistoryEntry, ShortenedUrl};
+use crate::storage::{ClickIncrement, SearchParams, SearchSort, Storage};
+use crate::timezone::hour_start;
+use chrono_tz::Tz;
+use std::num::NonZeroU64;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_click_increments_are_recorded_per_hour() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code("daily", "https://example.com/daily", None)
+        .await
+        .unwrap();
+
+    storage.increment_clicks("daily", 2).await.unwrap();
+    let batch = [
+        ClickIncrement::new("daily".to_string(), NonZeroU64::new(3).unwrap()),
+        ClickIncrement::new("missing".to_string(), NonZeroU64::new(4).unwrap()),
+    ];
+    storage.increment_clicks_batch(&batch).await.unwrap();
+
+    let now = chrono::Utc::now().timestamp();
+    let today = crate::timezone::local_day_start(now, Tz::UTC);
+    let history = storage
+        .get_click_history("daily", today, Tz::UTC)
+        .await
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![ClickHistoryEntry {
+            day: today,
+            clicks: 5
+        }]
+    );
+    assert!(storage
+        .get_click_history("missing", 0, Tz::UTC)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(storage
+        .get_click_history("daily", hour_start(now) + 3600, Tz::UTC)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_click_history_days_follow_dst_transitions() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+
+    // One click in every UTC hour from 2024-03-09T05:00Z to 2024-03-12T04:00Z,
+    // i.e. three New York days around the spring-forward transition.
+    let first_hour = 1_709_960_400;
+    for hour in 0..71 {
+        sqlx::query("INSERT INTO click_history (short_code, hour, clicks) VALUES ('c', ?, 1)")
+            .bind(first_hour + hour * 3600)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+
+    let history = storage
+        .get_click_history("c", 0, Tz::America__New_York)
+        .await
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![
+            ClickHistoryEntry {
+                day: first_hour,
+                clicks: 24
+            },
+            ClickHistoryEntry {
+                day: first_hour + 24 * 3600,
+                clicks: 23
+            },
+            ClickHistoryEntry {
+                day: first_hour + 47 * 3600,
+                clicks: 24
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_click_history_compacts_old_hours_into_months() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+
+    // 2024-01-01T00Z, 2024-01-15T03Z, 2024-01-31T23Z and 2024-02-10T12Z,
+    // plus the current hour
+    let day = 86_400;
+    let january = 1_704_067_200;
+    let february = january + 31 * day;
+    let current_hour = hour_start(chrono::Utc::now().timestamp());
+    for (hour, clicks) in [
+        (january, 1),
+        (january + 14 * day + 3 * 3600, 2),
+        (january + 30 * day + 23 * 3600, 3),
+        (february + 9 * day + 12 * 3600, 4),
+        (current_hour, 5),
+    ] {
+        sqlx::query("INSERT INTO click_history (short_code, hour, clicks) VALUES ('c', ?, ?)")
+            .bind(hour)
+            .bind(clicks)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+
+    let (merged, months) = storage.compact_click_history(30).await.unwrap();
+    assert_eq!((merged, months), (3, 2));
+
+    let history = storage.get_click_history("c", 0, Tz::UTC).await.unwrap();
+    assert_eq!(
+        history,
+        vec![
+            ClickHistoryEntry {
+                day: january,
+                clicks: 6
+            },
+            ClickHistoryEntry {
+                day: february,
+                clicks: 4
+            },
+            ClickHistoryEntry {
+                day: crate::timezone::local_day_start(current_hour, Tz::UTC),
+                clicks: 5
+            },
+        ]
+    );
+
+    // Compacted rows are not merged again.
+    assert_eq!(storage.compact_click_history(30).await.unwrap(), (0, 0));
+}
+
+#[tokio::test]
+async fn test_last_visit_is_tracked_and_listed() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    for code in ["visited", "idle", "stale"] {
+        storage
+            .create_with_code(code, "https://example.com/docs", None)
+            .await
+            .unwrap();
+    }
+    storage.increment_clicks("visited", 2).await.unwrap();
+    sqlx::query("UPDATE urls SET last_visited_at = 1000 WHERE short_code = 'stale'")
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+
+    let visited = storage.get("visited").await.unwrap().unwrap();
+    let visited_at = visited.last_visited_at.unwrap();
+    assert!(visited_at > 1000);
+    assert_eq!(
+        storage.get("idle").await.unwrap().unwrap().last_visited_at,
+        None
+    );
+
+    // Never visited first, then least recently visited.
+    let codes = |urls: Vec<Arc<ShortenedUrl>>| -> Vec<String> {
+        urls.iter().map(|url| url.short_code.clone()).collect()
+    };
+    let all = storage
+        .list_by_last_visit(10, None, None, true, None)
+        .await
+        .unwrap();
+    assert_eq!(codes(all.clone()), vec!["idle", "stale", "visited"]);
+    let page = storage
+        .list_by_last_visit(10, Some((0, all[0].id)), None, true, None)
+        .await
+        .unwrap();
+    assert_eq!(codes(page), vec!["stale", "visited"]);
+    let unused = storage
+        .list_by_last_visit(10, None, Some(visited_at), true, None)
+        .await
+        .unwrap();
+    assert_eq!(codes(unused), vec!["idle", "stale"]);
+    let owned = storage
+        .list_by_last_visit(10, None, None, false, Some("someone-else"))
+        .await
+        .unwrap();
+    assert!(owned.is_empty());
+
+    let params = SearchParams {
+        q: "docs".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: Some(1001),
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+    let result = storage.search(&params, true, None).await.unwrap();
+    let mut found: Vec<_> = result
+        .items
+        .iter()
+        .map(|url| url.short_code.clone())
+        .collect();
+    found.sort();
+    assert_eq!(found, vec!["idle", "stale"]);
+}
@@ -0,0 +1,314 @@
+use super::*;
+use crate::models::{CreatedVia, LinkOptions, ShortenedUrl};
+use crate::storage::{Storage, StorageError};
+use anyhow::anyhow;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_reserved_codes_are_activated_or_expired() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code("taken", "https://example.com/taken", None)
+        .await
+        .unwrap();
+
+    let codes = ["spring-a".to_string(), "spring-b".to_string()];
+    let reserved = storage
+        .reserve_codes(&codes, Some("marketing"), None, 1_000)
+        .await
+        .unwrap();
+    assert_eq!(reserved.len(), 2);
+    assert!(reserved
+        .iter()
+        .all(|url| url.is_reserved() && url.is_active));
+    assert_eq!(reserved[0].original_url, ShortenedUrl::RESERVED_DESTINATION);
+
+    // A conflict anywhere in the batch reserves nothing.
+    let clash = ["spring-c".to_string(), "taken".to_string()];
+    assert!(matches!(
+        storage
+            .reserve_codes(&clash, Some("marketing"), None, 1_000)
+            .await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(storage.get("spring-c").await.unwrap().is_none());
+
+    // Setting a destination ends the reservation without recording the placeholder.
+    let activated = storage
+        .update_url("spring-a", "https://example.com/spring", Some("marketing"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!activated.is_reserved());
+    assert!(storage
+        .get_url_history("spring-a")
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert_eq!(storage.expire_reservations(999).await.unwrap(), 0);
+    assert_eq!(storage.expire_reservations(1_000).await.unwrap(), 1);
+    assert_eq!(storage.expire_reservations(1_000).await.unwrap(), 0);
+
+    let expired = storage
+        .get_authoritative("spring-b")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(expired.is_reserved());
+    assert!(!expired.is_active);
+    let active = storage
+        .get_authoritative("spring-a")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(active.is_active);
+}
+
+#[tokio::test]
+async fn test_create_with_code_returns_inserted_row_and_detects_conflicts() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+
+    let created = storage
+        .create_with_code("fresh", "https://example.com/first", Some("user1"))
+        .await
+        .unwrap();
+    assert!(created.id > 0);
+    assert_eq!(created.original_url, "https://example.com/first");
+    assert_eq!(created.created_by.as_deref(), Some("user1"));
+    assert_eq!((created.clicks, created.is_active), (0, true));
+    assert!(created.reserved_until.is_none());
+
+    assert!(matches!(
+        storage
+            .create_with_code("fresh", "https://example.com/second", None)
+            .await,
+        Err(StorageError::Conflict)
+    ));
+    let stored = storage.get_authoritative("fresh").await.unwrap().unwrap();
+    assert_eq!(stored.id, created.id);
+    assert_eq!(stored.original_url, "https://example.com/first");
+}
+
+#[tokio::test]
+async fn test_renamed_link_keeps_its_created_via() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code_via(
+            "before",
+            "https://example.com",
+            None,
+            None,
+            CreatedVia::Integration,
+            &LinkOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    let renamed = storage
+        .rename_code("before", "after")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(renamed.created_via, CreatedVia::Integration);
+}
+
+#[tokio::test]
+async fn test_link_records_creator_auth_method() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code_via(
+            "owned",
+            "https://example.com",
+            Some("alice"),
+            Some("oauth"),
+            CreatedVia::Api,
+            &LinkOptions::default(),
+        )
+        .await
+        .unwrap();
+    storage
+        .upsert_user("alice", Some("alice@oauth.example"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user("alice", Some("alice@cf.example"), "cloudflare")
+        .await
+        .unwrap();
+
+    let url = storage.get("owned").await.unwrap().unwrap();
+    assert_eq!(url.created_by_auth_method.as_deref(), Some("oauth"));
+    let emails = storage
+        .user_emails(&[("alice".to_string(), "oauth".to_string())])
+        .await
+        .unwrap();
+    assert_eq!(
+        emails,
+        vec![(
+            "alice".to_string(),
+            "oauth".to_string(),
+            "alice@oauth.example".to_string()
+        )]
+    );
+
+    let renamed = storage
+        .rename_code("owned", "moved")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(renamed.created_by_auth_method.as_deref(), Some("oauth"));
+
+    storage
+        .patch_created_by("moved", "bob", Some("cloudflare"))
+        .await
+        .unwrap();
+    let patched = storage.get("moved").await.unwrap().unwrap();
+    assert_eq!(patched.created_by.as_deref(), Some("bob"));
+    assert_eq!(
+        patched.created_by_auth_method.as_deref(),
+        Some("cloudflare")
+    );
+}
+
+#[tokio::test]
+async fn test_sqlx_errors_are_classified() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("taken", "https://example.com", None)
+        .await
+        .unwrap();
+
+    let insert = |short_code: &'static str, original_url: Option<&'static str>| {
+        sqlx::query(
+            "INSERT INTO urls (short_code, original_url, created_at, is_active) VALUES (?, ?, 0, 1)",
+        )
+        .bind(short_code)
+        .bind(original_url)
+        .execute(storage.pool.as_ref())
+    };
+    let duplicate = insert("taken", Some("https://example.com"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        StorageError::from(duplicate),
+        StorageError::Conflict
+    ));
+    let missing_url = insert("fresh", None).await.unwrap_err();
+    assert!(matches!(
+        StorageError::from(missing_url),
+        StorageError::InvalidInput(_)
+    ));
+
+    assert!(matches!(
+        storage.restore_url("taken", 999, None).await,
+        Err(StorageError::NotFound)
+    ));
+
+    storage.pool.close().await;
+    let closed = StorageError::from(anyhow!(insert("closed", None).await.unwrap_err()));
+    assert!(
+        closed.is_retryable(),
+        "unexpected classification: {closed:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_created_at_orders_links_within_a_second() {
+    let storage = setup_sqlite().await;
+    let first = storage
+        .create_with_code("first", "https://example.com/1", None)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let second = storage
+        .create_with_code("second", "https://example.com/2", None)
+        .await
+        .unwrap();
+
+    assert!(second.created_at > first.created_at);
+    assert!(first.created_at > 1_000_000_000_000);
+    assert_eq!(first.updated_at, first.created_at);
+}
+
+#[tokio::test]
+async fn test_updated_at_follows_destination_and_state_not_clicks() {
+    let clock = Arc::new(crate::clock::FakeClock::at_epoch_ms(1_700_000_000_000));
+    let storage = SqliteStorage::new("sqlite::memory:", 1)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    let created = storage
+        .create_with_code("moving", "https://example.com/a", None)
+        .await
+        .unwrap();
+    assert_eq!(created.updated_at, 1_700_000_000_000);
+    let current = || async {
+        let url = storage.get_authoritative("moving").await.unwrap().unwrap();
+        url.updated_at
+    };
+
+    clock.set_epoch_ms(1_700_000_001_000);
+    storage.increment_clicks("moving", 3).await.unwrap();
+    storage
+        .set_title_if_missing("moving", "Example")
+        .await
+        .unwrap();
+    assert_eq!(current().await, created.updated_at);
+
+    clock.set_epoch_ms(1_700_000_002_000);
+    let moved = storage
+        .update_url("moving", "https://example.com/b", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(moved.updated_at, 1_700_000_002_000);
+    assert_eq!(current().await, moved.updated_at);
+    assert_eq!(moved.created_at, created.created_at);
+
+    clock.set_epoch_ms(1_700_000_003_000);
+    storage.deactivate("moving").await.unwrap();
+    assert_eq!(current().await, 1_700_000_003_000);
+
+    // Deactivating again changes nothing, so neither does updated_at
+    clock.set_epoch_ms(1_700_000_004_000);
+    storage.deactivate("moving").await.unwrap();
+    assert_eq!(current().await, 1_700_000_003_000);
+
+    storage
+        .patch_created_by("moving", "owner-2", None)
+        .await
+        .unwrap();
+    assert_eq!(current().await, 1_700_000_004_000);
+}
+
+#[tokio::test]
+async fn test_corrupt_link_options_fail_the_read() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let limited = LinkOptions {
+        max_clicks: Some(1),
+        ..LinkOptions::default()
+    };
+    storage
+        .create_with_code_via(
+            "spent",
+            "https://example.com",
+            None,
+            None,
+            CreatedVia::Api,
+            &limited,
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE urls SET options = '{\"max_clicks\":' WHERE short_code = 'spent'")
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+
+    // Read as the defaults, the link would redirect past its limit
+    assert!(storage.get("spent").await.is_err());
+    assert!(storage.get_authoritative("spent").await.is_err());
+}
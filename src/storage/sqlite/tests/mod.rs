@@ -0,0 +1,97 @@
+//! Tests for `SqliteStorage`, grouped by feature.
+
+use super::*;
+use crate::analytics::AnalyticsRollup;
+use crate::analytics::IpVersion;
+use crate::storage::Storage;
+use std::sync::Arc;
+
+mod aggregates;
+mod aliases;
+mod analytics;
+mod clicks;
+mod links;
+mod moderation;
+mod patch;
+mod rollups;
+mod schema;
+mod search;
+mod users;
+mod verify;
+
+/// Build an analytics rollup row for tests. `ip_version` is fixed to IPv4,
+/// which is what every fixture below exercises.
+fn rollup(
+    short_code: &str,
+    time_bucket: i64,
+    country_code: Option<&str>,
+    region: Option<&str>,
+    city: Option<&str>,
+    asn: Option<i64>,
+    visit_count: i64,
+) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket,
+        country_code: country_code.map(str::to_string),
+        region: region.map(str::to_string),
+        city: city.map(str::to_string),
+        asn,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+async fn setup_sqlite() -> Arc<dyn Storage> {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    Arc::new(storage)
+}
+
+async fn create_test_urls(storage: &Arc<dyn Storage>) {
+    // Create URL with normal user
+    storage
+        .create_with_code("normal1", "https://example.com/1", Some("user123"))
+        .await
+        .unwrap();
+
+    // Create URL with all-zero UUID (malformed)
+    storage
+        .create_with_code(
+            "malformed1",
+            "https://example.com/2",
+            Some("00000000-0000-0000-0000-000000000000"),
+        )
+        .await
+        .unwrap();
+
+    // Create URL with empty string (malformed)
+    storage
+        .create_with_code("malformed2", "https://example.com/3", Some(""))
+        .await
+        .unwrap();
+
+    // Create URL with null created_by (malformed)
+    storage
+        .create_with_code("malformed3", "https://example.com/4", None)
+        .await
+        .unwrap();
+
+    // Create another normal URL
+    storage
+        .create_with_code("normal2", "https://example.com/5", Some("user456"))
+        .await
+        .unwrap();
+
+    // Create another all-zero UUID URL
+    storage
+        .create_with_code(
+            "malformed4",
+            "https://example.com/6",
+            Some("00000000-0000-0000-0000-000000000000"),
+        )
+        .await
+        .unwrap();
+}
@@ -0,0 +1,103 @@
+use super::*;
+use crate::models::ModerationStatus;
+use crate::storage::Storage;
+
+#[tokio::test]
+async fn test_audit_log_records_actor_and_effective_user_newest_first() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .upsert_user("bob", Some("bob@example.com"), "oauth")
+        .await
+        .unwrap();
+    assert!(storage.user_exists("bob").await.unwrap());
+    assert!(!storage.user_exists("nobody").await.unwrap());
+
+    storage
+        .record_audit_entry("create", "docs", Some("admin"), "bob")
+        .await
+        .unwrap();
+    storage
+        .record_audit_entry("update", "docs", None, "bob")
+        .await
+        .unwrap();
+    storage
+        .record_audit_entry("create", "other", Some("admin"), "carol")
+        .await
+        .unwrap();
+
+    let entries = storage.get_audit_log("docs").await.unwrap();
+    let entries: Vec<(&str, Option<&str>, &str)> = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry.action.as_str(),
+                entry.actor.as_deref(),
+                entry.effective_user.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        vec![("update", None, "bob"), ("create", Some("admin"), "bob")]
+    );
+}
+
+#[tokio::test]
+async fn test_moderation_holds_pending_links_until_approved() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    for code in ["held", "accepted"] {
+        storage
+            .create_with_code(code, "https://example.com", Some("anonymous"))
+            .await
+            .unwrap();
+    }
+
+    storage
+        .submit_for_moderation("held", ModerationStatus::Pending)
+        .await
+        .unwrap();
+    storage
+        .submit_for_moderation("accepted", ModerationStatus::Approved)
+        .await
+        .unwrap();
+    assert!(!storage.get("held").await.unwrap().unwrap().is_active);
+    assert!(storage.get("accepted").await.unwrap().unwrap().is_active);
+
+    let pending = storage
+        .list_moderation(ModerationStatus::Pending, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].short_code, "held");
+
+    // Only pending links can be approved.
+    assert!(!storage
+        .approve_link("accepted", Some("admin"))
+        .await
+        .unwrap());
+    assert!(storage.approve_link("held", Some("admin")).await.unwrap());
+    assert!(!storage.approve_link("held", Some("admin")).await.unwrap());
+    let held = storage.get_moderation("held").await.unwrap().unwrap();
+    assert_eq!(held.status, ModerationStatus::Approved);
+    assert_eq!(held.decided_by.as_deref(), Some("admin"));
+    assert!(storage.get("held").await.unwrap().unwrap().is_active);
+
+    assert!(storage
+        .reject_link("accepted", "spam", Some("admin"))
+        .await
+        .unwrap());
+    assert!(!storage
+        .reject_link("accepted", "spam", Some("admin"))
+        .await
+        .unwrap());
+    let accepted = storage.get_moderation("accepted").await.unwrap().unwrap();
+    assert_eq!(accepted.status, ModerationStatus::Rejected);
+    assert_eq!(accepted.reason.as_deref(), Some("spam"));
+    assert!(!storage.get("accepted").await.unwrap().unwrap().is_active);
+
+    // Links that never went through moderation are left alone.
+    assert!(storage.get_moderation("missing").await.unwrap().is_none());
+    assert!(!storage.approve_link("missing", None).await.unwrap());
+}
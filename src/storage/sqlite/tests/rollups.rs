@@ -0,0 +1,256 @@
+use super::*;
+use crate::storage::Storage;
+use chrono_tz::Tz;
+
+#[tokio::test]
+async fn test_analytics_prune_drops_dimensions() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Create analytics data older than 30 days
+    let old_time = chrono::Utc::now().timestamp() - (40 * 86400); // 40 days ago
+    let recent_time = chrono::Utc::now().timestamp() - (10 * 86400); // 10 days ago
+
+    let records = vec![
+        // Old records (will be pruned)
+        rollup(
+            "test",
+            old_time,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            Some(15169),
+            5,
+        ),
+        rollup(
+            "test",
+            old_time + 3600,
+            Some("US"),
+            Some("CA"),
+            Some("LA"),
+            Some(15169),
+            3,
+        ),
+        rollup(
+            "test",
+            old_time,
+            Some("GB"),
+            Some("England"),
+            Some("London"),
+            Some(16509),
+            2,
+        ),
+        // Recent records (will not be pruned)
+        rollup(
+            "test",
+            recent_time,
+            Some("CA"),
+            Some("Ontario"),
+            Some("Toronto"),
+            None,
+            4,
+        ),
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Prune with dropping city and region dimensions
+    let (deleted, _inserted) = storage
+        .prune_analytics(30, &["city".to_string(), "region".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(deleted, 3, "Should have deleted 3 old entries");
+    // Aggregated entries with current timestamp are created and should remain
+    // (they won't be deleted because their time_bucket is current time > cutoff)
+}
+
+#[tokio::test]
+async fn test_analytics_prune_sets_time_bucket_to_cutoff() {
+    let storage = setup_sqlite().await;
+
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+
+    // Create analytics data with different hours
+    let old_time = chrono::Utc::now().timestamp() - (40 * 86400); // 40 days ago
+
+    let records = vec![
+        rollup(
+            "test",
+            old_time,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            None,
+            5,
+        ),
+        rollup(
+            "test",
+            old_time + 3600,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            None,
+            3,
+        ), // Different hour
+        rollup(
+            "test",
+            old_time + 7200,
+            Some("US"),
+            Some("CA"),
+            Some("SF"),
+            None,
+            2,
+        ), // Another hour
+    ];
+
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    // Prune (time_bucket is always set to cutoff_time now)
+    let (deleted, inserted) = storage.prune_analytics(30, &[]).await.unwrap();
+
+    assert_eq!(deleted, 3, "Should have deleted 3 old entries");
+    assert_eq!(
+        inserted, 1,
+        "Should have created 1 aggregated entry with all data"
+    );
+
+    // Verify the aggregated entry has time_bucket set to cutoff_time
+    let analytics = storage
+        .get_analytics("test", None, None, 100)
+        .await
+        .unwrap();
+    assert_eq!(analytics.len(), 1);
+    assert_eq!(analytics[0].visit_count, 10); // 5 + 3 + 2
+
+    // The time_bucket should be at the cutoff_time (rounded to hour start)
+    let raw_cutoff = chrono::Utc::now().timestamp() - (30 * 86400);
+    let expected_cutoff = (raw_cutoff / 3600) * 3600;
+    assert_eq!(analytics[0].time_bucket, expected_cutoff);
+}
+
+#[tokio::test]
+async fn test_analytics_complete_since_follows_prunes_that_aggregated_rows() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("test", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    assert_eq!(storage.analytics_complete_since().await.unwrap(), None);
+
+    // A prune with nothing old enough is recorded but aggregated nothing
+    let now = chrono::Utc::now().timestamp();
+    storage
+        .upsert_analytics_batch(vec![rollup("test", now, Some("US"), None, None, None, 1)])
+        .await
+        .unwrap();
+    assert_eq!(storage.prune_analytics(30, &[]).await.unwrap(), (0, 0));
+    assert_eq!(storage.analytics_complete_since().await.unwrap(), None);
+
+    storage
+        .upsert_analytics_batch(vec![rollup(
+            "test",
+            now - 40 * 86400,
+            Some("US"),
+            Some("CA"),
+            None,
+            None,
+            1,
+        )])
+        .await
+        .unwrap();
+    storage
+        .prune_analytics(30, &["region".to_string()])
+        .await
+        .unwrap();
+    let expected_cutoff = ((now - 30 * 86400) / 3600) * 3600;
+    let since = storage.analytics_complete_since().await.unwrap().unwrap();
+    // The clock may cross an hour boundary between `now` and the prune
+    assert!(since == expected_cutoff || since == expected_cutoff + 3600);
+
+    let runs: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT dropped_dimensions, deleted_count FROM analytics_prune_runs ORDER BY id",
+    )
+    .fetch_all(storage.pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(runs, [(String::new(), 0), ("region".to_string(), 1)]);
+}
+
+#[tokio::test]
+async fn test_analytics_daily_aggregate_follows_dst_transitions() {
+    let storage = setup_sqlite().await;
+    storage
+        .create_with_code("test", "https://example.com", None)
+        .await
+        .unwrap();
+
+    // 2024-11-03 in New York repeats 01:00, so its day spans 25 UTC hours
+    // from 04:00Z to 05:00Z the next day.
+    let fall_back_day = 1_730_606_400;
+    let records = (0..26)
+        .map(|hour| {
+            rollup(
+                "test",
+                fall_back_day + hour * 3600,
+                Some("US"),
+                None,
+                None,
+                None,
+                1,
+            )
+        })
+        .chain([rollup(
+            "test",
+            fall_back_day - 3600,
+            Some("US"),
+            None,
+            None,
+            None,
+            1,
+        )])
+        .collect();
+    storage.upsert_analytics_batch(records).await.unwrap();
+
+    let days = storage
+        .get_analytics_daily_aggregate("test", None, None, Tz::America__New_York, 10)
+        .await
+        .unwrap();
+    let days: Vec<(String, i64)> = days
+        .into_iter()
+        .map(|aggregate| (aggregate.dimension, aggregate.visit_count))
+        .collect();
+    assert_eq!(
+        days,
+        vec![
+            (fall_back_day.to_string(), 25),
+            ((fall_back_day - 24 * 3600).to_string(), 1),
+            ((fall_back_day + 25 * 3600).to_string(), 1),
+        ]
+    );
+
+    // In UTC the same hours fall on 2024-11-02, -03 and -04.
+    let utc_total: i64 = storage
+        .get_analytics_daily_aggregate("test", None, None, Tz::UTC, 10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|aggregate| aggregate.visit_count)
+        .sum();
+    assert_eq!(utc_total, 27);
+
+    let limited = storage
+        .get_analytics_daily_aggregate("test", Some(fall_back_day), None, Tz::America__New_York, 1)
+        .await
+        .unwrap();
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].visit_count, 25);
+}
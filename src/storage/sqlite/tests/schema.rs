@@ -0,0 +1,101 @@
+use super::*;
+use crate::storage::Storage;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_init_on_one_file_all_succeed() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "lynx-concurrent-init-{}-{nanos}.db",
+        std::process::id()
+    ));
+    let url = format!("sqlite://{}", path.display());
+
+    // Separate storages stand in for separate processes: each has its own
+    // pools and only the database file is shared.
+    let mut storages = Vec::new();
+    for _ in 0..6 {
+        storages.push(SqliteStorage::new(&url, 2).await.unwrap());
+    }
+    let inits = storages.into_iter().map(|storage| {
+        tokio::spawn(async move {
+            storage.init().await?;
+            anyhow::Ok(storage)
+        })
+    });
+    let mut initialized = Vec::new();
+    for init in inits.collect::<Vec<_>>() {
+        initialized.push(init.await.unwrap().expect("every concurrent init succeeds"));
+    }
+
+    let storage = &initialized[0];
+    storage
+        .create_with_code("after-init", "https://example.com", None)
+        .await
+        .unwrap();
+    assert!(storage
+        .get_authoritative("after-init")
+        .await
+        .unwrap()
+        .is_some());
+    let report = storage.verify_database(false).await.unwrap();
+    assert_eq!(report.problems(), 0, "{report:?}");
+
+    drop(initialized);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[tokio::test]
+async fn test_created_at_migrates_to_milliseconds_once() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("early", "https://example.com", None)
+        .await
+        .unwrap();
+
+    // Back to the schema from before millisecond timestamps
+    for statement in [
+        "ALTER TABLE urls DROP COLUMN updated_at",
+        "UPDATE urls SET created_at = 1700000000",
+        "DELETE FROM schema_migrations WHERE version = 2",
+    ] {
+        sqlx::query(statement)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+
+    storage.init().await.unwrap();
+    storage.init().await.unwrap();
+    let early = storage.get_authoritative("early").await.unwrap().unwrap();
+    assert_eq!(early.created_at, 1_700_000_000_000);
+    assert_eq!(early.updated_at, early.created_at);
+}
+
+#[tokio::test]
+async fn test_last_seen_at_migrates_from_updated_at() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+    storage.upsert_user("old", None, "oauth").await.unwrap();
+
+    // Back to the schema from before last_seen_at
+    for statement in [
+        "ALTER TABLE users DROP COLUMN last_seen_at",
+        "UPDATE users SET updated_at = 1700000000",
+    ] {
+        sqlx::query(statement)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+
+    storage.init().await.unwrap();
+    storage.init().await.unwrap();
+    let accounts = storage.user_accounts("old").await.unwrap();
+    assert_eq!(accounts[0].last_seen_at, 1_700_000_000);
+}
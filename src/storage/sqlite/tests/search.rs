@@ -0,0 +1,348 @@
+use super::*;
+use crate::models::{CreatedVia, LinkOptions};
+use crate::storage::{SearchParams, SearchResult, SearchSort, Storage};
+
+// Search functionality tests
+#[tokio::test]
+async fn test_search_by_short_code() {
+    let storage = setup_sqlite().await;
+
+    // Create test URLs
+    storage
+        .create_with_code("abc123", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("xyz789", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("abc456", "https://different.com/3", Some("user2"))
+        .await
+        .unwrap();
+
+    // Search for "abc" - should find abc123 and abc456
+    let params = SearchParams {
+        q: "abc".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 2);
+    assert!(result.items.iter().any(|u| u.short_code == "abc123"));
+    assert!(result.items.iter().any(|u| u.short_code == "abc456"));
+}
+
+#[tokio::test]
+async fn test_search_by_original_url() {
+    let storage = setup_sqlite().await;
+
+    // Create test URLs
+    storage
+        .create_with_code("link1", "https://github.com/project", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link2", "https://GITHUB.com/other", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link3", "https://example.com/test", Some("user1"))
+        .await
+        .unwrap();
+
+    // Search for "github" - should find both github URLs (case-insensitive)
+    let params = SearchParams {
+        q: "github".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 2);
+}
+
+#[tokio::test]
+async fn test_search_user_isolation() {
+    let storage = setup_sqlite().await;
+
+    // Create URLs for different users
+    storage
+        .create_with_code("user1link", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("user2link", "https://example.com/2", Some("user2"))
+        .await
+        .unwrap();
+
+    // Non-admin user1 searching should only see their own URLs
+    let params = SearchParams {
+        q: "link".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    // Non-admin user1 should only see user1link
+    let result = storage.search(&params, false, Some("user1")).await.unwrap();
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.items[0].short_code, "user1link");
+
+    // Admin should see all links
+    let result = storage.search(&params, true, Some("admin")).await.unwrap();
+    assert_eq!(result.items.len(), 2);
+}
+
+#[tokio::test]
+async fn test_search_with_filters() {
+    let storage = setup_sqlite().await;
+
+    // Create test URLs
+    storage
+        .create_with_code("active1", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+
+    // Deactivate one URL
+    storage.deactivate("active1").await.unwrap();
+
+    storage
+        .create_with_code("active2", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+
+    // Search for active URLs only
+    let params = SearchParams {
+        q: "example".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: Some(true),
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.items[0].short_code, "active2");
+
+    // Search for inactive URLs only
+    let params = SearchParams {
+        q: "example".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: Some(false),
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.items[0].short_code, "active1");
+}
+
+#[tokio::test]
+async fn test_search_pagination() {
+    let storage = setup_sqlite().await;
+
+    // Create multiple URLs
+    for i in 0..5 {
+        storage
+            .create_with_code(
+                &format!("test{}", i),
+                &format!("https://example.com/{}", i),
+                Some("user1"),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Search with limit 2
+    let params = SearchParams {
+        q: "test".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 2,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 2);
+    assert!(result.has_more);
+    assert!(result.next_cursor.is_some());
+
+    // Fetch next page
+    let params = SearchParams {
+        q: "test".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 2,
+        cursor: result.next_cursor,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result2 = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result2.items.len(), 2);
+    assert!(result2.has_more);
+}
+
+#[tokio::test]
+async fn test_search_short_code_case_sensitive() {
+    let storage = setup_sqlite().await;
+
+    // Create URLs with mixed case short codes
+    storage
+        .create_with_code("AbCdEf", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("abcdef", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+
+    // Search for "AbC" (uppercase) - should only find AbCdEf
+    let params = SearchParams {
+        q: "AbC".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+
+    let result = storage.search(&params, true, None).await.unwrap();
+    // Short code search is case-sensitive, so only AbCdEf should match
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.items[0].short_code, "AbCdEf");
+}
+
+#[tokio::test]
+async fn test_created_via_is_stored_and_filters_search() {
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+    storage.init().await.unwrap();
+
+    storage
+        .create_with_code_via(
+            "docs-api",
+            "https://example.com/a",
+            None,
+            None,
+            CreatedVia::Api,
+            &LinkOptions::default(),
+        )
+        .await
+        .unwrap();
+    let quick = storage
+        .create_with_code_via(
+            "docs-quick",
+            "https://example.com/b",
+            None,
+            None,
+            CreatedVia::Bookmarklet,
+            &LinkOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(quick.created_via, CreatedVia::Bookmarklet);
+    // Rows written without a source, like those from before the column
+    sqlx::query(
+        "INSERT INTO urls (short_code, original_url, created_at, is_active) VALUES ('docs-old', 'https://example.com/c', 0, 1)",
+    )
+    .execute(storage.pool.as_ref())
+    .await
+    .unwrap();
+    let old = storage.get("docs-old").await.unwrap().unwrap();
+    assert_eq!(old.created_via, CreatedVia::Unknown);
+
+    let mut params = SearchParams {
+        q: "docs".to_string(),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: Some(CreatedVia::Bookmarklet),
+        unused_since: None,
+        campaign_id: None,
+        limit: 50,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+    let codes = |result: SearchResult| -> Vec<String> {
+        result
+            .items
+            .iter()
+            .map(|url| url.short_code.clone())
+            .collect()
+    };
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(codes(result), vec!["docs-quick"]);
+
+    params.created_via = Some(CreatedVia::Unknown);
+    params.created_by = Some("__null__".to_string());
+    params.cursor = Some((i64::MAX, i64::MAX));
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(codes(result), vec!["docs-old"]);
+
+    params.created_via = None;
+    let result = storage.search(&params, true, None).await.unwrap();
+    assert_eq!(result.items.len(), 3);
+}
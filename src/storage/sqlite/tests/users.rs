@@ -0,0 +1,185 @@
+use super::*;
+use crate::clock::Clock;
+use crate::storage::Storage;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_list_all_users() {
+    let storage = setup_sqlite().await;
+
+    // Create test users
+    storage
+        .upsert_user("user1", Some("user1@example.com"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user("user2", Some("user2@example.com"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user("user3", None, "cloudflare")
+        .await
+        .unwrap();
+    storage.upsert_user("user3", None, "oauth").await.unwrap();
+
+    // List all users
+    let users = storage.list_all_users(10, None, None).await.unwrap();
+    assert_eq!(users.len(), 4);
+
+    // Test pagination: users created in the same second still page once each
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = storage.list_all_users(3, cursor, None).await.unwrap();
+        let Some(last) = page.last().cloned() else {
+            break;
+        };
+        seen.extend(
+            page.into_iter()
+                .map(|user| (user.user_id, user.auth_method)),
+        );
+        cursor = Some((last.created_at, last.user_id, last.auth_method));
+    }
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec![
+            ("user1".to_string(), "oauth".to_string()),
+            ("user2".to_string(), "oauth".to_string()),
+            ("user3".to_string(), "cloudflare".to_string()),
+            ("user3".to_string(), "oauth".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_list_user_links() {
+    let storage = setup_sqlite().await;
+
+    // Create test URLs for different users
+    storage
+        .create_with_code("link1", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link2", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link3", "https://example.com/3", Some("user2"))
+        .await
+        .unwrap();
+
+    // List links for user1
+    let links = storage.list_user_links("user1", 10, None).await.unwrap();
+    assert_eq!(links.len(), 2);
+
+    // List links for user2
+    let links = storage.list_user_links("user2", 10, None).await.unwrap();
+    assert_eq!(links.len(), 1);
+
+    // List links for non-existent user
+    let links = storage
+        .list_user_links("nonexistent", 10, None)
+        .await
+        .unwrap();
+    assert_eq!(links.len(), 0);
+}
+
+#[tokio::test]
+async fn test_bulk_deactivate_user_links() {
+    let storage = setup_sqlite().await;
+
+    // Create test URLs for a user
+    storage
+        .create_with_code("link1", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link2", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link3", "https://example.com/3", Some("user2"))
+        .await
+        .unwrap();
+
+    // Deactivate all links for user1
+    let count = storage.bulk_deactivate_user_links("user1").await.unwrap();
+    assert_eq!(count, 2);
+
+    // Verify user1's links are deactivated
+    let link1 = storage.get_authoritative("link1").await.unwrap().unwrap();
+    assert!(!link1.is_active);
+
+    let link2 = storage.get_authoritative("link2").await.unwrap().unwrap();
+    assert!(!link2.is_active);
+
+    // Verify user2's link is still active
+    let link3 = storage.get_authoritative("link3").await.unwrap().unwrap();
+    assert!(link3.is_active);
+
+    // Try to deactivate again (should return 0)
+    let count = storage.bulk_deactivate_user_links("user1").await.unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn test_bulk_reactivate_user_links() {
+    let storage = setup_sqlite().await;
+
+    // Create and deactivate test URLs for a user
+    storage
+        .create_with_code("link1", "https://example.com/1", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("link2", "https://example.com/2", Some("user1"))
+        .await
+        .unwrap();
+    storage.deactivate("link1").await.unwrap();
+    storage.deactivate("link2").await.unwrap();
+
+    // Reactivate all links for user1
+    let count = storage.bulk_reactivate_user_links("user1").await.unwrap();
+    assert_eq!(count, 2);
+
+    // Verify user1's links are reactivated
+    let link1 = storage.get_authoritative("link1").await.unwrap().unwrap();
+    assert!(link1.is_active);
+
+    let link2 = storage.get_authoritative("link2").await.unwrap().unwrap();
+    assert!(link2.is_active);
+
+    // Try to reactivate again (should return 0)
+    let count = storage.bulk_reactivate_user_links("user1").await.unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn test_list_users_filters_by_last_seen() {
+    let clock = Arc::new(crate::clock::FakeClock::at_epoch_ms(1_700_000_000_000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    storage.upsert_user("dormant", None, "oauth").await.unwrap();
+    storage.upsert_user("busy", None, "oauth").await.unwrap();
+    clock.advance(Duration::from_secs(200 * 86_400));
+    storage.upsert_user("busy", None, "oauth").await.unwrap();
+
+    let cutoff = clock.now_epoch_secs() - 180 * 86_400;
+    let inactive = storage
+        .list_all_users(10, None, Some(cutoff))
+        .await
+        .unwrap();
+    let ids: Vec<_> = inactive.iter().map(|user| user.user_id.as_str()).collect();
+    assert_eq!(ids, ["dormant"]);
+    assert_eq!(inactive[0].last_seen_at, 1_700_000_000);
+    assert_eq!(
+        storage.list_all_users(10, None, None).await.unwrap().len(),
+        2
+    );
+}
@@ -0,0 +1,174 @@
+use super::*;
+use crate::storage::verify::ORPHAN_DELETE_BATCH_SIZE;
+use crate::storage::{CheckStatus, OrphanCounts, Storage};
+use chrono_tz::Tz;
+
+#[tokio::test]
+async fn test_verify_database_recreates_indexes_and_deletes_orphans() {
+    // A file, as in production: the shared cache behind `sqlite::memory:`
+    // takes table locks that can leave the schema checks below waiting on
+    // an idle read connection after the index is recreated.
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("lynx-verify-{}-{nanos}.db", std::process::id()));
+    let storage = SqliteStorage::new(&format!("sqlite://{}", path.display()), 5)
+        .await
+        .unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("kept", "https://example.com", None)
+        .await
+        .unwrap();
+
+    let report = storage.verify_database(false).await.unwrap();
+    assert_eq!(report.problems(), 0);
+    assert!(report
+        .checks
+        .iter()
+        .all(|check| check.status == CheckStatus::Ok));
+
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("kept", 3600, Some("US"), None, None, None, 2),
+            rollup("gone", 3600, Some("US"), None, None, None, 5),
+        ])
+        .await
+        .unwrap();
+    sqlx::query("DROP INDEX idx_analytics_short_code")
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+
+    let report = storage.verify_database(false).await.unwrap();
+    assert_eq!(report.problems(), 2);
+
+    let report = storage.verify_database(true).await.unwrap();
+    assert_eq!(report.problems(), 0);
+    let status = |name: &str| {
+        report
+            .checks
+            .iter()
+            .find(|check| check.check == name)
+            .map(|check| check.status)
+    };
+    assert_eq!(
+        status("index idx_analytics_short_code"),
+        Some(CheckStatus::Fixed)
+    );
+    assert_eq!(status("orphaned analytics rows"), Some(CheckStatus::Fixed));
+
+    let remaining = storage.get_analytics("kept", None, None, 10).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(storage.verify_database(false).await.unwrap().problems(), 0);
+    assert!(storage.get_authoritative("kept").await.unwrap().is_some());
+
+    drop(storage);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[tokio::test]
+async fn test_orphan_analytics_are_counted_and_deleted_in_batches() {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("kept", "https://example.com", None)
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("kept", 3600, Some("US"), None, None, None, 2),
+            rollup("gone", 3600, Some("US"), None, None, None, 5),
+        ])
+        .await
+        .unwrap();
+    storage.increment_clicks("kept", 1).await.unwrap();
+    storage
+        .create_with_code("kept_alias", "https://example.com", None)
+        .await
+        .unwrap();
+    // Alias visits are orphaned when either code is gone.
+    for (short_code, alias_code) in [
+        ("kept", "kept_alias"),
+        ("kept", "gone_alias"),
+        ("gone", "kept_alias"),
+    ] {
+        sqlx::query(
+            "INSERT INTO alias_analytics (short_code, alias_code, time_bucket, visit_count, created_at, updated_at) VALUES (?, ?, 3600, 1, 0, 0)",
+        )
+        .bind(short_code)
+        .bind(alias_code)
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+    }
+    for code in ["kept", "gone"] {
+        sqlx::query(
+            "INSERT INTO analytics_estimates (short_code, time_bucket, estimated_visits, created_at, updated_at) VALUES (?, 3600, 1, 0, 0)",
+        )
+        .bind(code)
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO analytics_daily (short_code, day, country_code, visit_count) VALUES (?, 0, 'US', 1)",
+        )
+        .bind(code)
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+    }
+
+    // More orphaned click history than fits in one delete batch.
+    let orphaned_hours = ORPHAN_DELETE_BATCH_SIZE * 2 + 500;
+    sqlx::query(
+        r#"
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?)
+        INSERT INTO click_history (short_code, hour, clicks)
+        SELECT 'gone', i * 3600, 1 FROM n
+        "#,
+    )
+    .bind(orphaned_hours)
+    .execute(storage.pool.as_ref())
+    .await
+    .unwrap();
+
+    let expected = OrphanCounts {
+        analytics: 5,
+        click_history: orphaned_hours,
+    };
+    assert_eq!(storage.count_orphan_analytics().await.unwrap(), expected);
+    assert_eq!(storage.delete_orphan_analytics().await.unwrap(), expected);
+    assert_eq!(
+        storage.count_orphan_analytics().await.unwrap(),
+        OrphanCounts::default()
+    );
+
+    assert_eq!(
+        storage
+            .get_analytics("kept", None, None, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        storage
+            .get_click_history("kept", 0, Tz::UTC)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    for table in ["alias_analytics", "analytics_estimates", "analytics_daily"] {
+        let codes: Vec<String> =
+            sqlx::query_scalar(&format!("SELECT DISTINCT short_code FROM {table}"))
+                .fetch_all(storage.pool.as_ref())
+                .await
+                .unwrap();
+        assert_eq!(codes, ["kept"], "{table}");
+    }
+}
@@ -0,0 +1,115 @@
+//! Hourly API usage per user for `SqliteStorage`.
+
+use crate::models::{UserUsageHour, UserUsageTotal};
+use anyhow::Result;
+
+use super::{SqliteStorage, GET_MANY_CHUNK_SIZE};
+
+/// Create the `user_usage` table.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // API requests per user and UTC hour, flushed from the auth middleware's
+    // in-memory counters
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_usage (
+            user_id TEXT NOT NULL,
+            hour INTEGER NOT NULL,
+            create_requests INTEGER NOT NULL DEFAULT 0,
+            read_requests INTEGER NOT NULL DEFAULT 0,
+            search_requests INTEGER NOT NULL DEFAULT 0,
+            analytics_requests INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, hour)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn record_user_usage(
+    storage: &SqliteStorage,
+    hours: &[UserUsageHour],
+) -> Result<()> {
+    if hours.is_empty() {
+        return Ok(());
+    }
+    let mut transaction = storage.pool.begin().await?;
+    for hour in hours {
+        sqlx::query(
+            r#"
+            INSERT INTO user_usage (
+                user_id, hour, create_requests, read_requests, search_requests, analytics_requests
+            )
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, hour) DO UPDATE SET
+                create_requests = user_usage.create_requests + excluded.create_requests,
+                read_requests = user_usage.read_requests + excluded.read_requests,
+                search_requests = user_usage.search_requests + excluded.search_requests,
+                analytics_requests = user_usage.analytics_requests + excluded.analytics_requests
+            "#,
+        )
+        .bind(&hour.user_id)
+        .bind(hour.hour)
+        .bind(hour.counts.create_requests)
+        .bind(hour.counts.read_requests)
+        .bind(hour.counts.search_requests)
+        .bind(hour.counts.analytics_requests)
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub(super) async fn user_usage(
+    storage: &SqliteStorage,
+    user_id: &str,
+    since: i64,
+) -> Result<Vec<UserUsageHour>> {
+    let hours = sqlx::query_as::<_, UserUsageHour>(
+        r#"
+        SELECT user_id, hour, create_requests, read_requests, search_requests, analytics_requests
+        FROM user_usage
+        WHERE user_id = ? AND hour >= ?
+        ORDER BY hour
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(hours)
+}
+
+pub(super) async fn user_usage_totals(
+    storage: &SqliteStorage,
+    user_ids: &[String],
+    since: i64,
+) -> Result<Vec<UserUsageTotal>> {
+    let mut totals = Vec::new();
+    for chunk in user_ids.chunks(GET_MANY_CHUNK_SIZE) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT user_id,
+                   SUM(create_requests) AS create_requests,
+                   SUM(read_requests) AS read_requests,
+                   SUM(search_requests) AS search_requests,
+                   SUM(analytics_requests) AS analytics_requests
+            FROM user_usage
+            WHERE hour >= ? AND user_id IN ({placeholders})
+            GROUP BY user_id
+            "#
+        );
+        let mut query = sqlx::query_as::<_, UserUsageTotal>(&sql).bind(since);
+        for user_id in chunk {
+            query = query.bind(user_id);
+        }
+        totals.extend(query.fetch_all(storage.read_pool.as_ref()).await?);
+    }
+
+    Ok(totals)
+}
@@ -0,0 +1,434 @@
+//! Users, admins and per-user link queries for `SqliteStorage`.
+
+use crate::models::{ShortenedUrl, UserAccount, UserLinkCounts};
+use crate::storage::URL_COLUMNS;
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::{SqliteStorage, GET_MANY_CHUNK_SIZE};
+
+/// Create the `users` and `admin_users` tables.
+pub(super) async fn create_schema(connection: &mut sqlx::SqliteConnection) -> Result<()> {
+    // Create users table to track user metadata
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS users (
+            user_id TEXT NOT NULL,
+            auth_method TEXT NOT NULL,
+            email TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            last_seen_at INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, auth_method)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Bumped when a request authenticates as the user; accounts from before
+    // the column start from their last metadata update
+    let has_last_seen_at: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM pragma_table_info('users') WHERE name = 'last_seen_at'",
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    if has_last_seen_at == 0 {
+        sqlx::query("ALTER TABLE users ADD COLUMN last_seen_at INTEGER NOT NULL DEFAULT 0")
+            .execute(&mut *connection)
+            .await?;
+        sqlx::query("UPDATE users SET last_seen_at = updated_at")
+            .execute(&mut *connection)
+            .await?;
+    }
+
+    // Index for cursor-based user listing
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at DESC, user_id DESC, auth_method DESC)",
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    // Create admin_users table for manually promoted admins
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS admin_users (
+            user_id TEXT NOT NULL,
+            auth_method TEXT NOT NULL,
+            promoted_at INTEGER NOT NULL,
+            PRIMARY KEY (user_id, auth_method)
+        )
+        "#,
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn upsert_user(
+    storage: &SqliteStorage,
+    user_id: &str,
+    email: Option<&str>,
+    auth_method: &str,
+) -> Result<()> {
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        INSERT INTO users (user_id, auth_method, email, created_at, updated_at, last_seen_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (user_id, auth_method) DO UPDATE SET
+            email = COALESCE(excluded.email, users.email),
+            updated_at = excluded.updated_at,
+            last_seen_at = excluded.last_seen_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .bind(email)
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn is_manual_admin(
+    storage: &SqliteStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<bool> {
+    let result = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM admin_users
+        WHERE user_id = ? AND auth_method = ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .fetch_one(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(result > 0)
+}
+
+pub(super) async fn promote_to_admin(
+    storage: &SqliteStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<()> {
+    let now = storage.clock.now_epoch_secs();
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_users (user_id, auth_method, promoted_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT (user_id, auth_method) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .bind(now)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn demote_from_admin(
+    storage: &SqliteStorage,
+    user_id: &str,
+    auth_method: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM admin_users
+        WHERE user_id = ? AND auth_method = ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(auth_method)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub(super) async fn list_manual_admins(
+    storage: &SqliteStorage,
+) -> Result<Vec<(String, String, String)>> {
+    let admins = sqlx::query_as::<_, (String, String, Option<String>)>(
+        r#"
+        SELECT a.user_id, a.auth_method, u.email
+        FROM admin_users a
+        LEFT JOIN users u ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+        ORDER BY a.promoted_at DESC
+        "#,
+    )
+    .fetch_all(storage.read_pool.as_ref())
+    .await?
+    .into_iter()
+    .map(|(user_id, auth_method, email)| {
+        (
+            user_id,
+            auth_method,
+            email.unwrap_or_else(|| "N/A".to_string()),
+        )
+    })
+    .collect();
+
+    Ok(admins)
+}
+
+pub(super) async fn user_exists(storage: &SqliteStorage, user_id: &str) -> Result<bool> {
+    let exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_id = ?)")
+            .bind(user_id)
+            .fetch_one(storage.read_pool.as_ref())
+            .await?;
+
+    Ok(exists)
+}
+
+pub(super) async fn list_all_users(
+    storage: &SqliteStorage,
+    limit: i64,
+    cursor: Option<(i64, String, String)>,
+    inactive_since: Option<i64>,
+) -> Result<Vec<UserAccount>> {
+    let users = if let Some((cursor_created_at, cursor_user_id, cursor_auth_method)) = cursor {
+        sqlx::query_as::<_, UserAccount>(
+            r#"
+            SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+                   a.user_id IS NOT NULL AS is_manual_admin
+            FROM users u
+            LEFT JOIN admin_users a
+              ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+            WHERE (u.created_at, u.user_id, u.auth_method) < (?, ?, ?)
+              AND (? IS NULL OR u.last_seen_at < ?)
+            ORDER BY u.created_at DESC, u.user_id DESC, u.auth_method DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(cursor_created_at)
+        .bind(cursor_user_id)
+        .bind(cursor_auth_method)
+        .bind(inactive_since)
+        .bind(inactive_since)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, UserAccount>(
+            r#"
+            SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+                   a.user_id IS NOT NULL AS is_manual_admin
+            FROM users u
+            LEFT JOIN admin_users a
+              ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+            WHERE (? IS NULL OR u.last_seen_at < ?)
+            ORDER BY u.created_at DESC, u.user_id DESC, u.auth_method DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(inactive_since)
+        .bind(inactive_since)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    };
+
+    Ok(users)
+}
+
+pub(super) async fn user_emails(
+    storage: &SqliteStorage,
+    users: &[(String, String)],
+) -> Result<Vec<(String, String, String)>> {
+    let mut emails = Vec::with_capacity(users.len());
+    // Two parameters per user
+    for chunk in users.chunks(GET_MANY_CHUNK_SIZE / 2) {
+        let pairs = vec!["(?, ?)"; chunk.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT user_id, auth_method, email
+            FROM users
+            WHERE email IS NOT NULL AND (user_id, auth_method) IN (VALUES {pairs})
+            "#
+        );
+        let mut query = sqlx::query_as::<_, (String, String, String)>(&sql);
+        for (user_id, auth_method) in chunk {
+            query = query.bind(user_id).bind(auth_method);
+        }
+        emails.extend(query.fetch_all(storage.read_pool.as_ref()).await?);
+    }
+
+    Ok(emails)
+}
+
+pub(super) async fn user_accounts(
+    storage: &SqliteStorage,
+    user_id: &str,
+) -> Result<Vec<UserAccount>> {
+    let accounts = sqlx::query_as::<_, UserAccount>(
+        r#"
+        SELECT u.user_id, u.auth_method, u.email, u.created_at, u.last_seen_at,
+               a.user_id IS NOT NULL AS is_manual_admin
+        FROM users u
+        LEFT JOIN admin_users a
+          ON a.user_id = u.user_id AND a.auth_method = u.auth_method
+        WHERE u.user_id = ?
+        ORDER BY u.created_at, u.auth_method
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(accounts)
+}
+
+pub(super) async fn user_link_counts(
+    storage: &SqliteStorage,
+    user_id: &str,
+) -> Result<UserLinkCounts> {
+    let counts = sqlx::query_as::<_, UserLinkCounts>(
+        r#"
+        SELECT
+            COALESCE(SUM(alias_of IS NULL AND is_active = 1 AND reserved_until IS NULL), 0) AS active,
+            COALESCE(SUM(alias_of IS NULL AND is_active = 0), 0) AS inactive,
+            COALESCE(SUM(alias_of IS NULL AND is_active = 1 AND reserved_until IS NOT NULL), 0) AS reserved,
+            COALESCE(SUM(alias_of IS NOT NULL), 0) AS aliases,
+            COALESCE(SUM(clicks), 0) AS total_clicks
+        FROM urls
+        WHERE created_by = ?
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(counts)
+}
+
+pub(super) async fn list_user_links(
+    storage: &SqliteStorage,
+    user_id: &str,
+    limit: i64,
+    cursor: Option<(i64, i64)>,
+) -> Result<Vec<Arc<ShortenedUrl>>> {
+    let urls = if let Some((cursor_created_at, cursor_id)) = cursor {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE created_by = ? AND ((created_at < ?) OR (created_at = ? AND id < ?))
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?
+            "#
+        ))
+        .bind(user_id)
+        .bind(cursor_created_at)
+        .bind(cursor_created_at)
+        .bind(cursor_id)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    } else {
+        sqlx::query_as::<_, ShortenedUrl>(&format!(
+            r#"
+            SELECT {URL_COLUMNS}
+            FROM urls
+            WHERE created_by = ?
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?
+            "#
+        ))
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+    };
+
+    Ok(urls.into_iter().map(Arc::new).collect())
+}
+
+pub(super) async fn bulk_deactivate_user_links(
+    storage: &SqliteStorage,
+    user_id: &str,
+) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = 0, updated_at = ?
+        WHERE created_by = ? AND is_active = 1
+        "#,
+    )
+    .bind(storage.clock.now_epoch_ms())
+    .bind(user_id)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+pub(super) async fn bulk_reactivate_user_links(
+    storage: &SqliteStorage,
+    user_id: &str,
+) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE urls
+        SET is_active = 1, updated_at = ?
+        WHERE created_by = ? AND is_active = 0
+        "#,
+    )
+    .bind(storage.clock.now_epoch_ms())
+    .bind(user_id)
+    .execute(storage.pool.as_ref())
+    .await?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+pub(super) async fn count_user_links_by_state(
+    storage: &SqliteStorage,
+    user_id: &str,
+    is_active: bool,
+) -> Result<i64> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM urls WHERE created_by = ? AND is_active = ?",
+    )
+    .bind(user_id)
+    .bind(is_active)
+    .fetch_one(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(count)
+}
+
+pub(super) async fn sample_user_links_by_state(
+    storage: &SqliteStorage,
+    user_id: &str,
+    is_active: bool,
+    limit: i64,
+) -> Result<Vec<String>> {
+    let codes = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT short_code
+        FROM urls
+        WHERE created_by = ? AND is_active = ?
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(user_id)
+    .bind(is_active)
+    .bind(limit)
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+
+    Ok(codes)
+}
@@ -0,0 +1,138 @@
+//! Database health checks and orphaned analytics for `SqliteStorage`.
+
+use crate::storage::verify::{
+    is_schema_incomplete, orphan_condition, ANALYTICS_TABLES, EXPECTED_INDEXES, EXPECTED_TABLES,
+    LINK_ANALYTICS_TABLES, ORPHAN_DELETE_BATCH_SIZE,
+};
+use crate::storage::{CheckStatus, OrphanCounts, Storage, VerifyReport};
+use anyhow::Result;
+use std::collections::HashSet;
+
+use super::{SqliteStorage, SEARCH_TABLES};
+
+/// Rows of `table` naming a short code in `columns` that is not in `urls`.
+async fn count_orphans(storage: &SqliteStorage, table: &str, columns: &[&str]) -> Result<i64> {
+    let condition = orphan_condition(columns);
+    let count = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} t WHERE {condition}"))
+        .fetch_one(storage.read_pool.as_ref())
+        .await?;
+    Ok(count)
+}
+
+/// Delete the rows `count_orphans` counts, in batches.
+async fn delete_orphans(storage: &SqliteStorage, table: &str, columns: &[&str]) -> Result<i64> {
+    let statement = format!(
+        "DELETE FROM {table} WHERE rowid IN (SELECT rowid FROM {table} t WHERE {} LIMIT ?)",
+        orphan_condition(columns)
+    );
+    let mut deleted = 0;
+    loop {
+        // Each batch is its own statement, so queued click and
+        // analytics flushes get the writer in between.
+        let removed = sqlx::query(&statement)
+            .bind(ORPHAN_DELETE_BATCH_SIZE)
+            .execute(storage.pool.as_ref())
+            .await?
+            .rows_affected() as i64;
+        deleted += removed;
+        if removed < ORPHAN_DELETE_BATCH_SIZE {
+            return Ok(deleted);
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Names of the tables and indexes currently in the database.
+pub(super) async fn schema_objects(storage: &SqliteStorage) -> Result<HashSet<String>> {
+    let names: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type IN ('table', 'index')")
+            .fetch_all(storage.read_pool.as_ref())
+            .await?;
+    Ok(names.into_iter().collect())
+}
+
+/// `(table, column)` pairs of every table currently in the database.
+pub(super) async fn schema_columns(storage: &SqliteStorage) -> Result<HashSet<(String, String)>> {
+    let pairs: Vec<(String, String)> = sqlx::query_as(
+        "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p WHERE m.type = 'table'",
+    )
+    .fetch_all(storage.read_pool.as_ref())
+    .await?;
+    Ok(pairs.into_iter().collect())
+}
+
+pub(super) async fn count_orphan_analytics(storage: &SqliteStorage) -> Result<OrphanCounts> {
+    let mut orphans = OrphanCounts::default();
+    for (table, columns) in LINK_ANALYTICS_TABLES {
+        orphans.analytics += count_orphans(storage, table, columns).await?;
+    }
+    orphans.click_history = count_orphans(storage, "click_history", &["short_code"]).await?;
+    Ok(orphans)
+}
+
+pub(super) async fn delete_orphan_analytics(storage: &SqliteStorage) -> Result<OrphanCounts> {
+    let mut deleted = OrphanCounts::default();
+    for (table, columns) in LINK_ANALYTICS_TABLES {
+        deleted.analytics += delete_orphans(storage, table, columns).await?;
+    }
+    deleted.click_history = delete_orphans(storage, "click_history", &["short_code"]).await?;
+    Ok(deleted)
+}
+
+pub(super) async fn verify_database(storage: &SqliteStorage, fix: bool) -> Result<VerifyReport> {
+    let mut report = VerifyReport::new("sqlite");
+
+    let integrity: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(storage.read_pool.as_ref())
+        .await?;
+    if integrity == ["ok"] {
+        report.push("integrity_check", CheckStatus::Ok, "ok");
+    } else {
+        report.push(
+            "integrity_check",
+            CheckStatus::Problem,
+            integrity.join("; "),
+        );
+    }
+
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(storage.read_pool.as_ref())
+        .await?
+        .len();
+    if violations == 0 {
+        report.push("foreign_key_check", CheckStatus::Ok, "ok");
+    } else {
+        report.push(
+            "foreign_key_check",
+            CheckStatus::Problem,
+            format!("{violations} rows violate foreign keys"),
+        );
+    }
+
+    let before = schema_objects(storage).await?;
+    let after = if fix
+        && is_schema_incomplete(&[EXPECTED_TABLES, SEARCH_TABLES, EXPECTED_INDEXES], &before)
+    {
+        // init() only creates what is missing.
+        storage.init().await?;
+        schema_objects(storage).await?
+    } else {
+        before.clone()
+    };
+    report.record_schema_objects("table", EXPECTED_TABLES, &before, &after);
+    report.record_schema_objects("table", SEARCH_TABLES, &before, &after);
+    report.record_schema_objects("index", EXPECTED_INDEXES, &before, &after);
+
+    // Orphans can only be counted once urls and the analytics tables exist.
+    if is_schema_incomplete(&[&["urls"], ANALYTICS_TABLES], &after) {
+        return Ok(report);
+    }
+    let orphans = storage.count_orphan_analytics().await?;
+    let deleted = fix && orphans.total() > 0;
+    if deleted {
+        storage.delete_orphan_analytics().await?;
+    }
+    report.record_orphans(orphans, deleted);
+
+    Ok(report)
+}
@@ -1,4 +1,14 @@
-use crate::models::{ShortenedUrl, UrlHistoryEntry};
+use super::cached::CacheStats;
+use super::copy::{AdminRecord, RowCounts, UserRecord};
+use super::pool::PoolStats;
+use super::server_leases::ServerLease;
+use super::verify::{OrphanCounts, VerifyReport};
+use crate::models::{
+    ActivityDay, AuditEntry, Campaign, CampaignStats, ClickHistoryEntry, CreatedVia,
+    DestinationHostLink, DestinationHostSummary, InstanceStatsDay, LinkOptions, ModerationEntry,
+    ModerationStatus, ShortenedUrl, UrlHistoryEntry, UserAccount, UserLinkCounts, UserUsageHour,
+    UserUsageTotal,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -7,12 +17,133 @@ use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 
+/// Storage failures classified by what a caller can do about them.
+///
+/// `From<sqlx::Error>` and `From<anyhow::Error>` classify driver errors from
+/// either backend, so `?` inside a `StorageResult` method picks the variant.
 #[derive(Debug, Error)]
 pub enum StorageError {
+    /// A unique constraint rejected the write, usually an existing short code
     #[error("short code already exists")]
     Conflict,
+    /// The row the operation needs does not exist
+    #[error("not found")]
+    NotFound,
+    /// The database rejected a value (check, not-null or foreign key constraint)
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    /// The database could not serve the request right now; retrying may succeed
+    #[error("database unavailable: {0}")]
+    Unavailable(#[source] anyhow::Error),
+    /// The database refused the operation for lack of privileges
+    #[error("permission denied")]
+    PermissionDenied,
     #[error(transparent)]
-    Other(#[from] anyhow::Error),
+    Other(anyhow::Error),
+}
+
+impl StorageError {
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, StorageError::Unavailable(_))
+    }
+}
+
+impl From<anyhow::Error> for StorageError {
+    fn from(error: anyhow::Error) -> Self {
+        let Some(source) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<sqlx::Error>())
+        else {
+            return StorageError::Other(error);
+        };
+        if let Some(classified) = classify_sqlx_error(source) {
+            return classified;
+        }
+        if is_unavailable(source) {
+            StorageError::Unavailable(error)
+        } else {
+            StorageError::Other(error)
+        }
+    }
+}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(error: sqlx::Error) -> Self {
+        anyhow::Error::from(error).into()
+    }
+}
+
+/// SQLite primary result codes (the low byte of the extended code).
+const SQLITE_PERM: i32 = 3;
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+const SQLITE_READONLY: i32 = 8;
+const SQLITE_AUTH: i32 = 23;
+
+/// Classify the sqlx errors that map to a variant without a source.
+fn classify_sqlx_error(error: &sqlx::Error) -> Option<StorageError> {
+    use sqlx::error::ErrorKind;
+
+    match error {
+        sqlx::Error::RowNotFound => Some(StorageError::NotFound),
+        sqlx::Error::Database(database) => match database.kind() {
+            ErrorKind::UniqueViolation => Some(StorageError::Conflict),
+            ErrorKind::ForeignKeyViolation
+            | ErrorKind::NotNullViolation
+            | ErrorKind::CheckViolation => {
+                Some(StorageError::InvalidInput(database.message().to_owned()))
+            }
+            _ => is_permission_denied(database.as_ref()).then_some(StorageError::PermissionDenied),
+        },
+        _ => None,
+    }
+}
+
+/// Connection-level failures and lock contention that a retry can outlast.
+fn is_unavailable(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(database) => match sqlite_primary_code(database.as_ref()) {
+            Some(code) => matches!(code, SQLITE_BUSY | SQLITE_LOCKED),
+            // Postgres: connection exceptions, insufficient resources and
+            // server shutdown or startup.
+            None => database.code().is_some_and(|code| {
+                code.starts_with("08")
+                    || code.starts_with("53")
+                    || matches!(&*code, "57P01" | "57P02" | "57P03")
+            }),
+        },
+        _ => false,
+    }
+}
+
+fn is_permission_denied(error: &dyn sqlx::error::DatabaseError) -> bool {
+    match sqlite_primary_code(error) {
+        Some(code) => matches!(code, SQLITE_PERM | SQLITE_READONLY | SQLITE_AUTH),
+        // Postgres: insufficient_privilege and invalid authorization.
+        None => error
+            .code()
+            .is_some_and(|code| code == "42501" || code.starts_with("28")),
+    }
+}
+
+/// The primary result code of a SQLite error, or `None` for other backends.
+#[cfg(feature = "sqlite")]
+fn sqlite_primary_code(error: &dyn sqlx::error::DatabaseError) -> Option<i32> {
+    error.try_downcast_ref::<sqlx::sqlite::SqliteError>()?;
+    let code: i32 = error.code()?.parse().ok()?;
+    Some(code & 0xff)
+}
+
+/// Builds without SQLite only see Postgres errors.
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_primary_code(_error: &dyn sqlx::error::DatabaseError) -> Option<i32> {
+    None
 }
 
 pub type StorageResult<T> = Result<T, StorageError>;
@@ -79,6 +210,23 @@ pub struct LookupResult {
     pub metadata: LookupMetadata,
 }
 
+/// Order of search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    /// Newest first, paged by `(created_at, id)` cursor
+    #[default]
+    CreatedAt,
+    /// Best match first. Postgres ranks all matches with pg_trgm similarity
+    /// and pages by offset, up to [`RELEVANCE_MAX_OFFSET`]; SQLite pages by
+    /// recency and ranks the matches within each page.
+    Relevance,
+}
+
+/// Matches a relevance-sorted search pages through before it stops, since
+/// every page re-ranks and skips all the matches before it
+pub const RELEVANCE_MAX_OFFSET: i64 = 1000;
+
 /// Parameters for search queries
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchParams {
@@ -86,16 +234,31 @@ pub struct SearchParams {
     pub q: String,
     /// Filter by creator (use "__null__" for NULL created_by)
     pub created_by: Option<String>,
-    /// Filter by created_at >= this value (inclusive)
+    /// Filter by created_at >= this value, in epoch milliseconds (inclusive)
     pub created_from: Option<i64>,
-    /// Filter by created_at < this value (exclusive)
+    /// Filter by created_at < this value, in epoch milliseconds (exclusive)
     pub created_to: Option<i64>,
     /// Filter by is_active status
     pub is_active: Option<bool>,
+    /// Filter by the entry point links were created through
+    #[serde(default)]
+    pub created_via: Option<CreatedVia>,
+    /// Only links not visited since this Unix timestamp (never visited included)
+    #[serde(default)]
+    pub unused_since: Option<i64>,
+    /// Only links in this campaign
+    #[serde(default)]
+    pub campaign_id: Option<i64>,
     /// Maximum number of results to return
     pub limit: i64,
     /// Cursor for pagination (created_at, id)
     pub cursor: Option<(i64, i64)>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    /// Matches to skip, for searches paged by offset (see
+    /// [`SearchResult::next_offset`])
+    #[serde(default)]
+    pub offset: Option<i64>,
 }
 
 /// Result of a search operation
@@ -107,21 +270,169 @@ pub struct SearchResult {
     pub next_cursor: Option<(i64, i64)>,
     /// Whether there are more results
     pub has_more: bool,
+    /// Whether the first item is the link whose short code is exactly the
+    /// query, pinned ahead of the substring matches
+    #[serde(default)]
+    pub exact_match: bool,
+    /// Set instead of `next_cursor` when the backend pages this search by
+    /// offset: the `offset` to request the next page with
+    #[serde(default)]
+    pub next_offset: Option<i64>,
+}
+
+/// Substring matches fetched past the page size: one to tell whether
+/// another page follows and one to stand in for the exact match, which is
+/// taken out of the substring matches on every page.
+pub const SEARCH_EXTRA_ROWS: i64 = 2;
+
+impl SearchResult {
+    /// Build a page from `rows`, substring matches in cursor order fetched
+    /// with [`SEARCH_EXTRA_ROWS`] more than `limit`, and `exact`, the link
+    /// whose code equals the query if it passes the same filters. The exact
+    /// match leads the first page and is left out of every page's substring
+    /// matches; the cursor always follows the substring matches, so later
+    /// pages are unaffected.
+    pub(crate) fn from_rows(
+        rows: Vec<ShortenedUrl>,
+        exact: Option<ShortenedUrl>,
+        limit: i64,
+        first_page: bool,
+    ) -> Self {
+        let exact_id = exact.as_ref().map(|url| url.id);
+        let mut rows: Vec<ShortenedUrl> = rows
+            .into_iter()
+            .filter(|url| Some(url.id) != exact_id)
+            .collect();
+        let pinned = exact.filter(|_| first_page && limit > 0);
+        let capacity = limit.max(0) as usize - usize::from(pinned.is_some());
+        let has_more = rows.len() > capacity;
+        rows.truncate(capacity);
+
+        // With a page of one the exact match fills it, and the next page
+        // starts from the newest substring match
+        let next_cursor = has_more.then(|| {
+            rows.last()
+                .map_or((i64::MAX, i64::MAX), |last| (last.created_at, last.id))
+        });
+        let exact_match = pinned.is_some();
+        Self {
+            items: pinned.into_iter().chain(rows).map(Arc::new).collect(),
+            next_cursor,
+            has_more,
+            exact_match,
+            next_offset: None,
+        }
+    }
+
+    /// Build a page from `rows`, matches fetched from `offset` with one more
+    /// than the page holds, which leave the exact match out. `exact` leads
+    /// the first page as in [`SearchResult::from_rows`], and the next page
+    /// starts where this one's matches end, while that is within
+    /// [`RELEVANCE_MAX_OFFSET`].
+    #[cfg(feature = "postgres")]
+    pub(crate) fn from_offset_rows(
+        mut rows: Vec<ShortenedUrl>,
+        exact: Option<ShortenedUrl>,
+        limit: i64,
+        offset: i64,
+    ) -> Self {
+        let pinned = exact.filter(|_| offset == 0 && limit > 0);
+        let capacity = limit.max(0) as usize - usize::from(pinned.is_some());
+        let more_rows = rows.len() > capacity;
+        rows.truncate(capacity);
+
+        let next = offset + rows.len() as i64;
+        let next_offset = (more_rows && next < RELEVANCE_MAX_OFFSET).then_some(next);
+        Self {
+            exact_match: pinned.is_some(),
+            items: pinned.into_iter().chain(rows).map(Arc::new).collect(),
+            next_cursor: None,
+            has_more: next_offset.is_some(),
+            next_offset,
+        }
+    }
+}
+
+/// Rows `patch_all_malformed_created_by` rewrites per statement, so a large
+/// patch never holds the write lock (SQLite) or row locks (Postgres) for long.
+pub const MALFORMED_PATCH_BATCH_SIZE: i64 = 1000;
+
+/// Values of `created_by` that `patch_all_malformed_created_by` replaces,
+/// besides NULL.
+pub const MALFORMED_CREATED_BY: &[&str] = &["", "00000000-0000-0000-0000-000000000000"];
+
+/// One batch of `patch_malformed_created_by_batch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MalformedPatchBatch {
+    /// Rows patched by this batch
+    pub patched: i64,
+    /// Highest id patched; the next batch starts after it
+    pub last_id: i64,
+}
+
+/// What `hard_delete` removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HardDeleteReport {
+    /// Codes deleted: the one asked for and, for a canonical link, its aliases
+    pub codes: Vec<String>,
+    /// Analytics rows deleted: detailed, per alias, estimated and daily
+    pub analytics_rows: i64,
+    /// Hourly and monthly click history rows deleted
+    pub click_history_rows: i64,
+    /// Previous destinations deleted
+    pub url_history_rows: i64,
 }
 
 #[async_trait]
 pub trait Storage: Send + Sync {
-    /// Initialize the storage (run migrations, etc.)
-    async fn init(&self) -> Result<()>;
+    /// Create the schema or migrate it to the current version: tables,
+    /// columns, indexes, triggers and extensions. Needs DDL privileges; the
+    /// server and `lynx db migrate` run it.
+    async fn ensure_schema(&self) -> Result<()>;
 
-    /// Create a new shortened URL with a caller-provided code (used for custom codes)
-    async fn create_with_code(
+    /// Check, without writing, that the schema exists and is current. Fails
+    /// with a hint to run the server or `lynx db migrate` otherwise; CLI
+    /// commands run it instead of `ensure_schema`.
+    async fn verify_schema(&self) -> Result<()>;
+
+    /// Initialize the storage; the same as [`Storage::ensure_schema`].
+    async fn init(&self) -> Result<()> {
+        self.ensure_schema().await
+    }
+
+    /// Create a new shortened URL with a caller-provided code, recording the
+    /// auth method of `created_by` and the entry point it was created through.
+    /// The link is stored with `options` in the same statement, so it never
+    /// redirects without them.
+    async fn create_with_code_via(
         &self,
         short_code: &str,
         original_url: &str,
         created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        created_via: CreatedVia,
+        options: &LinkOptions,
     ) -> StorageResult<Arc<ShortenedUrl>>;
 
+    /// Create a new shortened URL with a caller-provided code (used for custom
+    /// codes) without recording where it came from
+    async fn create_with_code(
+        &self,
+        short_code: &str,
+        original_url: &str,
+        created_by: Option<&str>,
+    ) -> StorageResult<Arc<ShortenedUrl>> {
+        self.create_with_code_via(
+            short_code,
+            original_url,
+            created_by,
+            None,
+            CreatedVia::Unknown,
+            &LinkOptions::default(),
+        )
+        .await
+    }
+
     // Additional helper methods may be added for automatic code generation if storage-backed.
 
     /// Get a shortened URL by short code without observability metadata.
@@ -144,15 +455,29 @@ pub trait Storage: Send + Sync {
     /// Get a shortened URL by short code with authoritative statistics
     async fn get_authoritative(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>>;
 
+    /// Look up many short codes at once. Returns the links that exist, in no
+    /// particular order; codes without a link are simply absent.
+    async fn get_many(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>>;
+
     /// Deactivate a shortened URL (soft delete)
     async fn deactivate(&self, short_code: &str) -> Result<bool>;
 
+    /// Store the destination page title of `short_code` unless it already
+    /// has one. Returns whether a title was written.
+    async fn set_title_if_missing(&self, short_code: &str, title: &str) -> Result<bool>;
+
+    /// Replace the per-link options of `short_code`, moving its `updated_at`
+    /// to now. Returns whether the link exists.
+    async fn set_link_options(&self, short_code: &str, options: &LinkOptions) -> Result<bool>;
+
     /// Reactivate a shortened URL
     async fn reactivate(&self, short_code: &str) -> Result<bool>;
 
     /// Update the destination of an existing shortened URL.
     /// Records the previous destination in the history table within a single
-    /// transaction. Returns the updated URL, or `None` if the code does not exist.
+    /// transaction. Setting the destination of a reserved code ends the
+    /// reservation instead; the placeholder is not recorded.
+    /// Returns the updated URL, or `None` if the code does not exist.
     async fn update_url(
         &self,
         short_code: &str,
@@ -160,12 +485,31 @@ pub trait Storage: Send + Sync {
         updated_by: Option<&str>,
     ) -> StorageResult<Option<Arc<ShortenedUrl>>>;
 
+    /// Reserve `short_codes` for `created_by` before their destination is known.
+    /// Reserved rows hold `ShortenedUrl::RESERVED_DESTINATION` until `update_url`
+    /// sets a destination, which ends the reservation. The codes are inserted in
+    /// one transaction: if any of them exists, nothing is reserved and
+    /// `StorageError::Conflict` is returned.
+    async fn reserve_codes(
+        &self,
+        short_codes: &[String],
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        reserved_until: i64,
+    ) -> StorageResult<Vec<Arc<ShortenedUrl>>>;
+
+    /// Deactivate reserved codes whose `reserved_until` is at or before `now`.
+    /// The rows are kept, like every other URL. Returns the number expired.
+    async fn expire_reservations(&self, now: i64) -> Result<i64>;
+
     /// Get the history of destinations for a short code, ordered by changed_at DESC
     async fn get_url_history(&self, short_code: &str) -> Result<Vec<UrlHistoryEntry>>;
 
     /// Restore a URL to a historical destination.
     /// Records the current destination in the history table within a single
     /// transaction. Returns the updated URL, or `None` if the code does not exist.
+    /// Fails with `StorageError::NotFound` when the history entry does not
+    /// belong to the code.
     async fn restore_url(
         &self,
         short_code: &str,
@@ -173,7 +517,76 @@ pub trait Storage: Send + Sync {
         restored_by: Option<&str>,
     ) -> StorageResult<Option<Arc<ShortenedUrl>>>;
 
-    /// Increment click count by the provided amount
+    /// Rename `short_code` to `new_code` without breaking the old code.
+    ///
+    /// In one transaction, `new_code` is created with the old code's
+    /// destination, owner and state, and the old code, along with any alias
+    /// that pointed at it, becomes an alias of `new_code`. Aliases therefore
+    /// never point at aliases and cannot form cycles. Returns the new link, or
+    /// `None` if `short_code` does not exist. Fails with
+    /// `StorageError::Conflict` when `new_code` exists and
+    /// `StorageError::InvalidInput` when `short_code` is itself an alias.
+    async fn rename_code(
+        &self,
+        short_code: &str,
+        new_code: &str,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>>;
+
+    /// Attach `alias_code` to the link at `short_code` as another code that
+    /// redirects to its destination.
+    ///
+    /// The alias row records `short_code` in `alias_of`; redirects, clicks and
+    /// analytics through it go to `short_code`. Returns the alias, or `None` if
+    /// `short_code` does not exist. Fails with `StorageError::Conflict` when
+    /// `alias_code` exists and `StorageError::InvalidInput` when `short_code`
+    /// is itself an alias.
+    async fn add_alias(
+        &self,
+        short_code: &str,
+        alias_code: &str,
+        created_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>>;
+
+    /// Links whose `alias_of` is one of `short_codes`, in no particular order.
+    async fn get_aliases(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>>;
+
+    /// Permanently delete `short_code` with its analytics, click history,
+    /// destination history and moderation state, for links that must not be
+    /// kept at all. Deleting a canonical link deletes its aliases too;
+    /// deleting an alias leaves its link alone. The audit log is kept, and
+    /// gains a `hard_delete` entry for `actor` (`None` from the command
+    /// line) on behalf of the link's owner.
+    ///
+    /// Everything, the audit entry included, goes in one transaction that
+    /// lifts the delete protection on `urls` for itself only, so no delete
+    /// is ever left unaudited. Fails with `StorageError::PermissionDenied`
+    /// unless the storage was built with hard delete allowed
+    /// (`ALLOW_HARD_DELETE`). Returns `None` if `short_code` does not exist.
+    async fn hard_delete(
+        &self,
+        short_code: &str,
+        actor: Option<&str>,
+    ) -> StorageResult<Option<HardDeleteReport>>;
+
+    /// Increment click count by the provided amount.
+    ///
+    /// This is the canonical click write: `increment_click`,
+    /// `increment_click_owned` and `increment_clicks_owned` are provided
+    /// wrappers around it, and implementations that override a wrapper (for
+    /// example to avoid a clone) must keep its semantics identical. A zero
+    /// amount is a no-op and never fails. Increments for unknown or inactive
+    /// codes are ignored when they reach the database.
+    ///
+    /// Consistency model:
+    /// - Database backends apply the increment atomically before returning, so
+    ///   concurrent increments are never lost and `get_authoritative` observes
+    ///   them immediately. The same transaction adds the amount to the current
+    ///   UTC hour of the click history (see `get_click_history`).
+    /// - `CachedStorage` only enqueues the increment. Buffered clicks become
+    ///   visible to `get_authoritative` and listings within the actor's fast
+    ///   flush interval, and reach the database on the slow flush,
+    ///   `CachedStorage::flush` or shutdown. Plain `get` may serve a cached
+    ///   count that is older than either.
     async fn increment_clicks(&self, short_code: &str, amount: u64) -> Result<()>;
 
     /// Atomically persist a batch of nonzero click increments.
@@ -193,7 +606,7 @@ pub trait Storage: Send + Sync {
         }
     }
 
-    /// Increment click count by 1 (convenience helper)
+    /// Increment click count by 1 (convenience wrapper over `increment_clicks`)
     async fn increment_click(&self, short_code: &str) -> Result<()> {
         self.increment_clicks(short_code, 1).await
     }
@@ -215,6 +628,19 @@ pub trait Storage: Send + Sync {
         user_id: Option<&str>,
     ) -> Result<Vec<Arc<ShortenedUrl>>>;
 
+    /// List URLs least recently visited first, with never visited links
+    /// leading. With `unused_since`, only links not visited since that Unix
+    /// timestamp are returned. Ordered by `COALESCE(last_visited_at, 0)`, id;
+    /// the cursor is that pair from the last row of the previous page.
+    async fn list_by_last_visit(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>, // (last_visited_at or 0, id)
+        unused_since: Option<i64>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>>;
+
     /// Register or update user metadata
     async fn upsert_user(
         &self,
@@ -235,31 +661,227 @@ pub trait Storage: Send + Sync {
     /// List all manually promoted admins
     async fn list_manual_admins(&self) -> Result<Vec<(String, String, String)>>; // (user_id, auth_method, email)
 
-    /// Patch created_by for a specific short code
-    async fn patch_created_by(&self, short_code: &str, new_created_by: &str) -> Result<bool>;
+    /// Patch created_by for a specific short code, together with the auth
+    /// method it signs in with (`None` when unknown)
+    async fn patch_created_by(
+        &self,
+        short_code: &str,
+        new_created_by: &str,
+        auth_method: Option<&str>,
+    ) -> Result<bool>;
 
     /// Patch all malformed created_by values (all-zero UUID or null) to a new value
     /// Returns the number of rows updated
-    async fn patch_all_malformed_created_by(&self, new_created_by: &str) -> Result<i64>;
+    async fn patch_all_malformed_created_by(&self, new_created_by: &str) -> Result<i64> {
+        self.patch_all_malformed_created_by_with_progress(new_created_by, &mut |_| {})
+            .await
+    }
+
+    /// [`Storage::patch_all_malformed_created_by`] in batches of
+    /// [`MALFORMED_PATCH_BATCH_SIZE`], calling `on_batch` with the rows each
+    /// batch patched. Every batch commits on its own, so an interrupted run
+    /// keeps what it patched and running it again patches the rest.
+    async fn patch_all_malformed_created_by_with_progress(
+        &self,
+        new_created_by: &str,
+        on_batch: &mut (dyn FnMut(i64) + Send),
+    ) -> Result<i64> {
+        if MALFORMED_CREATED_BY.contains(&new_created_by) {
+            anyhow::bail!("'{}' is itself a malformed created_by", new_created_by);
+        }
+
+        let mut total = 0;
+        let mut after_id = 0;
+        while let Some(batch) = self
+            .patch_malformed_created_by_batch(new_created_by, after_id, MALFORMED_PATCH_BATCH_SIZE)
+            .await?
+        {
+            total += batch.patched;
+            after_id = batch.last_id;
+            on_batch(batch.patched);
+            if batch.patched < MALFORMED_PATCH_BATCH_SIZE {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        Ok(total)
+    }
+
+    /// Patch up to `limit` malformed created_by values with ids above
+    /// `after_id`, lowest ids first. `None` when there were none left.
+    async fn patch_malformed_created_by_batch(
+        &self,
+        new_created_by: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Option<MalformedPatchBatch>>;
+
+    /// Number of links `patch_all_malformed_created_by` would patch
+    async fn count_malformed_created_by(&self) -> Result<i64>;
+
+    /// Up to `limit` short codes of those links, newest first
+    async fn sample_malformed_created_by(&self, limit: i64) -> Result<Vec<String>>;
+
+    /// Whether `user_id` has signed in with any auth method
+    async fn user_exists(&self, user_id: &str) -> Result<bool>;
+
+    /// Record that `actor` took `action` on `short_code` on behalf of
+    /// `effective_user`
+    async fn record_audit_entry(
+        &self,
+        action: &str,
+        short_code: &str,
+        actor: Option<&str>,
+        effective_user: &str,
+    ) -> Result<()>;
+
+    /// Audit entries for a short code, newest first
+    async fn get_audit_log(&self, short_code: &str) -> Result<Vec<AuditEntry>>;
+
+    /// Put a link created without signing in under moderation in `status`.
+    /// A `Pending` link is deactivated in the same transaction and stays
+    /// inactive until `approve_link`.
+    async fn submit_for_moderation(&self, short_code: &str, status: ModerationStatus)
+        -> Result<()>;
+
+    /// The moderation entry of `short_code`, if it was ever submitted
+    async fn get_moderation(&self, short_code: &str) -> Result<Option<ModerationEntry>>;
+
+    /// Moderation entries in `status`, oldest link first
+    async fn list_moderation(
+        &self,
+        status: ModerationStatus,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ModerationEntry>>;
+
+    /// Approve a pending link and activate it. Returns whether a pending
+    /// entry was approved.
+    async fn approve_link(&self, short_code: &str, decided_by: Option<&str>) -> Result<bool>;
+
+    /// Reject a pending or approved link and deactivate it, recording
+    /// `reason`. Returns whether an entry was rejected; the row is kept.
+    async fn reject_link(
+        &self,
+        short_code: &str,
+        reason: &str,
+        decided_by: Option<&str>,
+    ) -> Result<bool>;
+
+    /// Create a campaign named `name` owned by `owner`
+    async fn create_campaign(&self, name: &str, owner: Option<&str>) -> Result<Campaign>;
 
-    /// List all users with pagination support
-    /// Returns users ordered by created_at DESC
-    /// Returns up to limit results
+    /// The campaign with id `id`, if it exists
+    async fn get_campaign(&self, id: i64) -> Result<Option<Campaign>>;
+
+    /// Campaigns owned by `owner`, or every campaign when `None`, newest
+    /// first
+    async fn list_campaigns(&self, owner: Option<&str>) -> Result<Vec<Campaign>>;
+
+    /// Rename a campaign. Returns it as renamed, or `None` if it does not
+    /// exist.
+    async fn rename_campaign(&self, id: i64, name: &str) -> Result<Option<Campaign>>;
+
+    /// Delete a campaign and take its links out of it, in one transaction.
+    /// The links are otherwise untouched: `updated_at` does not move.
+    /// Returns the codes of the links it held, or `None` if it does not
+    /// exist.
+    async fn delete_campaign(&self, id: i64) -> Result<Option<Vec<String>>>;
+
+    /// Put a link in a campaign, or take it out of its campaign with
+    /// `None`. Returns whether the link exists.
+    async fn set_link_campaign(&self, short_code: &str, campaign_id: Option<i64>) -> Result<bool>;
+
+    /// Clicks of every link in a campaign, with their visits and visits by
+    /// country between the optional Unix timestamps
+    async fn campaign_stats(
+        &self,
+        id: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<CampaignStats>;
+
+    /// List all users with cursor-based pagination
+    /// Returns users ordered by created_at DESC, user_id DESC, auth_method DESC
+    /// The cursor is the (created_at, user_id, auth_method) of the last user
+    /// of the previous page. With `inactive_since`, only users last seen
+    /// before that Unix timestamp are listed.
     async fn list_all_users(
         &self,
         limit: i64,
+        cursor: Option<(i64, String, String)>,
+        inactive_since: Option<i64>,
+    ) -> Result<Vec<UserAccount>>;
+
+    /// Emails of the given `(user_id, auth_method)` users, as
+    /// `(user_id, auth_method, email)`. Users without an email are left out.
+    async fn user_emails(
+        &self,
+        users: &[(String, String)],
+    ) -> Result<Vec<(String, String, String)>>;
+
+    /// Active links (aliases left out) whose destination host is one of
+    /// `hosts`, ordered by host, then most clicked first, with the emails of
+    /// their creators. Hosts are matched lowercased, as [`destination_host`]
+    /// stores them.
+    ///
+    /// [`destination_host`]: crate::destination::destination_host
+    async fn links_by_destination_host(
+        &self,
+        hosts: &[String],
+        limit: i64,
         offset: i64,
-    ) -> Result<Vec<(String, String, String, i64)>>; // (user_id, auth_method, email, created_at)
+    ) -> Result<Vec<DestinationHostLink>>;
 
-    /// List all links created by a specific user with pagination
-    /// Returns links ordered by created_at DESC
+    /// Active links (aliases left out) whose normalized destination matches
+    /// that of `url`, most clicked first, with the emails of their creators:
+    /// the links a new one to `url` would duplicate. Both sides are
+    /// normalized with this storage's steps, as [`normalize_url`] does.
+    ///
+    /// [`normalize_url`]: crate::destination::normalize_url
+    async fn links_by_destination(
+        &self,
+        url: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>>;
+
+    /// Active links (aliases left out) grouped by destination host, most
+    /// links first, for hosts with at least `min_links` of them. A non-empty
+    /// `hosts` restricts the report to those hosts.
+    async fn destination_host_summary(
+        &self,
+        hosts: &[String],
+        min_links: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostSummary>>;
+
+    /// Every auth method `user_id` has signed in with, oldest first, with
+    /// whether each is a manually promoted admin
+    async fn user_accounts(&self, user_id: &str) -> Result<Vec<UserAccount>>;
+
+    /// Links created by `user_id` counted by state, with their total clicks
+    async fn user_link_counts(&self, user_id: &str) -> Result<UserLinkCounts>;
+
+    /// List all links created by a specific user with cursor-based pagination
+    /// Returns links ordered by created_at DESC, id DESC
+    /// The cursor is the (created_at, id) of the last link of the previous page
     async fn list_user_links(
         &self,
         user_id: &str,
         limit: i64,
-        offset: i64,
+        cursor: Option<(i64, i64)>,
     ) -> Result<Vec<Arc<ShortenedUrl>>>;
 
+    /// Most recent active link created by `created_by` (or anonymously when
+    /// `None`) that points to exactly `original_url`
+    async fn find_active_by_destination(
+        &self,
+        original_url: &str,
+        created_by: Option<&str>,
+    ) -> Result<Option<Arc<ShortenedUrl>>>;
+
     /// Deactivate all links created by a specific user
     /// Returns the number of links deactivated
     async fn bulk_deactivate_user_links(&self, user_id: &str) -> Result<i64>;
@@ -268,6 +890,18 @@ pub trait Storage: Send + Sync {
     /// Returns the number of links reactivated
     async fn bulk_reactivate_user_links(&self, user_id: &str) -> Result<i64>;
 
+    /// Number of links created by `user_id` that are active (or inactive),
+    /// i.e. what the bulk deactivation (or reactivation) would change
+    async fn count_user_links_by_state(&self, user_id: &str, is_active: bool) -> Result<i64>;
+
+    /// Up to `limit` short codes of those links, newest first
+    async fn sample_user_links_by_state(
+        &self,
+        user_id: &str,
+        is_active: bool,
+        limit: i64,
+    ) -> Result<Vec<String>>;
+
     /// Batch insert or update analytics records
     /// Uses UPSERT to increment visit counts for existing records
     async fn upsert_analytics_batch(
@@ -275,6 +909,15 @@ pub trait Storage: Send + Sync {
         records: Vec<crate::analytics::AnalyticsRollup>,
     ) -> Result<()>;
 
+    /// Like `upsert_analytics_batch`, but skips records whose short code is
+    /// not in `urls` and returns how many were skipped. Analytics reference
+    /// links by short code without a foreign key; this keeps the flush path
+    /// from creating rows for codes that never existed.
+    async fn upsert_known_analytics_batch(
+        &self,
+        records: Vec<crate::analytics::AnalyticsRollup>,
+    ) -> Result<u64>;
+
     /// Get analytics for a specific short code
     async fn get_analytics(
         &self,
@@ -284,6 +927,28 @@ pub trait Storage: Send + Sync {
         limit: i64,
     ) -> Result<Vec<crate::analytics::AnalyticsEntry>>;
 
+    /// Visits to `short_code` between the given hours that were
+    /// extrapolated from events kept by load sampling. They are included in
+    /// the analytics visit counts; this says how much of those is estimated.
+    async fn get_estimated_visits(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<i64>;
+
+    /// Get one page of raw analytics rows for an export, ordered by
+    /// `(time_bucket, id)` and starting after the `after` key of the
+    /// previous page's last row
+    async fn get_analytics_export_page(
+        &self,
+        scope: crate::analytics::AnalyticsExportScope<'_>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsEntry>>;
+
     /// Get aggregated analytics grouped by a dimension
     async fn get_analytics_aggregate(
         &self,
@@ -294,6 +959,26 @@ pub trait Storage: Send + Sync {
         limit: i64,
     ) -> Result<Vec<crate::analytics::AnalyticsAggregate>>;
 
+    /// Visits to every link with `time_bucket` in `[start_time, end_time]`
+    /// (open ends unbounded) split by IP version, labelled and ordered like
+    /// `get_analytics_aggregate` with `group_by=ip_version`
+    async fn get_instance_ip_version_split(
+        &self,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<crate::analytics::AnalyticsAggregate>>;
+
+    /// Get analytics visits per day in `time_zone`, keyed by the start of the
+    /// day (Unix timestamp as text) and ordered like `get_analytics_aggregate`
+    async fn get_analytics_daily_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        time_zone: chrono_tz::Tz,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsAggregate>>;
+
     /// Prune old analytics data by aggregating entries and dropping specified dimensions
     /// Returns the number of rows affected (deleted old rows + inserted aggregated rows)
     async fn prune_analytics(
@@ -302,9 +987,109 @@ pub trait Storage: Send + Sync {
         drop_dimensions: &[String],
     ) -> Result<(i64, i64)>; // (deleted_count, inserted_count)
 
+    /// Start of full-detail analytics: the cutoff of the latest prune that
+    /// aggregated rows, or `None` if no analytics were ever pruned. Rows before
+    /// it were merged into one bucket at the cutoff, with dropped dimensions
+    /// replaced by the dropped-dimension marker.
+    async fn analytics_complete_since(&self) -> Result<Option<i64>>;
+
+    /// Sum hourly analytics into `analytics_daily` for every whole UTC day
+    /// before `until`, continuing from the previous rollup's watermark, or
+    /// rebuilding from `from` (see `analytics::daily::rollup_start`). Country
+    /// and day aggregates then read those days from the rollup. Returns the
+    /// rollup rows written.
+    async fn rollup_analytics_daily(&self, from: Option<i64>, until: i64) -> Result<i64>;
+
+    /// The rollup watermark: every UTC day before it is in `analytics_daily`.
+    /// `None` until the first rollup.
+    async fn analytics_daily_rolled_until(&self) -> Result<Option<i64>>;
+
+    /// Compute instance-wide totals for the UTC day starting at `day` and
+    /// store them in `instance_stats_daily`, replacing an earlier record of
+    /// the same day. Returns what was stored.
+    async fn record_instance_stats(&self, day: i64) -> Result<InstanceStatsDay>;
+
+    /// Recorded instance stats for days from `since` onwards, oldest first.
+    async fn instance_stats_history(&self, since: i64) -> Result<Vec<InstanceStatsDay>>;
+
+    /// Activity of every UTC day from `since` up to `until` (day starts),
+    /// oldest first and including quiet days: links created, clicks from the
+    /// click history, analytics visits (rolled-up days from
+    /// `analytics_daily`), and each day's `top` creators and links.
+    async fn activity_report(&self, since: i64, until: i64, top: i64) -> Result<Vec<ActivityDay>>;
+
+    /// Value of a runtime setting an admin changed, or `None` if it was
+    /// never set.
+    async fn get_setting(&self, key: &str) -> Result<Option<String>>;
+
+    /// Store a runtime setting in `settings`, replacing its earlier value.
+    async fn set_setting(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Record `lease` in `server_leases`, or move its expiry if the server
+    /// already holds one.
+    async fn renew_server_lease(&self, lease: &ServerLease) -> Result<()>;
+
+    /// Drop the lease of server `instance_id` as it shuts down.
+    async fn release_server_lease(&self, instance_id: &str) -> Result<()>;
+
+    /// Leases that have not lapsed: the servers running against this
+    /// database, oldest first.
+    async fn active_server_leases(&self) -> Result<Vec<ServerLease>>;
+
+    /// Record proof-of-work `nonce` in `challenge_redemptions` until
+    /// `expires_at` (Unix seconds), dropping redemptions that have lapsed.
+    /// `false` if the nonce was already redeemed, by this server or another.
+    async fn redeem_challenge_nonce(&self, nonce: &str, expires_at: i64) -> Result<bool>;
+
+    /// Add hourly API request counts to `user_usage` in one transaction,
+    /// summing with counts already stored for the same user and hour.
+    async fn record_user_usage(&self, hours: &[UserUsageHour]) -> Result<()>;
+
+    /// A user's stored API request counts for hours from `since` onwards,
+    /// oldest first.
+    async fn user_usage(&self, user_id: &str, since: i64) -> Result<Vec<UserUsageHour>>;
+
+    /// Stored API request counts of each of `user_ids` summed over hours
+    /// from `since` onwards. Users without requests are omitted.
+    async fn user_usage_totals(
+        &self,
+        user_ids: &[String],
+        since: i64,
+    ) -> Result<Vec<UserUsageTotal>>;
+
+    /// Clicks of a short code per day in `time_zone`, counting hours from
+    /// `since` (a Unix timestamp) onwards, oldest first. Days without clicks
+    /// are omitted.
+    async fn get_click_history(
+        &self,
+        short_code: &str,
+        since: i64,
+        time_zone: chrono_tz::Tz,
+    ) -> Result<Vec<ClickHistoryEntry>>;
+
+    /// Merge hourly click history rows older than `retention_days` into one
+    /// row per code and month, dated the first hour of the month (UTC).
+    /// Running it again only touches rows added since the previous run.
+    async fn compact_click_history(&self, retention_days: i64) -> Result<(i64, i64)>; // (merged_hourly_rows, monthly_rows)
+
+    /// Count analytics and click history rows whose short code is not in `urls`.
+    async fn count_orphan_analytics(&self) -> Result<OrphanCounts>;
+
+    /// Delete the rows counted by `count_orphan_analytics`, in batches of
+    /// `ORPHAN_DELETE_BATCH_SIZE`, and return how many were removed.
+    async fn delete_orphan_analytics(&self) -> Result<OrphanCounts>;
+
+    /// Check database integrity, the tables and indexes created by `init()`
+    /// and analytics rows whose short code no longer exists. With `fix`,
+    /// missing tables and indexes are recreated and orphaned analytics rows
+    /// are deleted; URL rows are never touched.
+    async fn verify_database(&self, fix: bool) -> Result<VerifyReport>;
+
     /// Search for URLs matching a query string with optional filters
     /// - Matches short_code (case-sensitive) or original_url (case-insensitive)
-    /// - Applies filters: created_by, created_from, created_to, is_active
+    /// - A link whose short_code equals the query leads the first page
+    /// - Applies filters: created_by, created_from, created_to, is_active,
+    ///   created_via, unused_since, campaign_id
     /// - Uses cursor-based pagination ordered by created_at DESC, id DESC
     /// - Non-admin users can only search their own URLs
     async fn search(
@@ -313,4 +1098,68 @@ pub trait Storage: Send + Sync {
         is_admin: bool,
         user_id: Option<&str>,
     ) -> Result<SearchResult>;
+
+    /// Up to `limit` links with ids above `after_id`, lowest id first, for
+    /// copying to another backend (see `copy_storage`)
+    async fn export_urls(&self, after_id: i64, limit: i64) -> Result<Vec<Arc<ShortenedUrl>>>;
+
+    /// Insert links copied from another backend with their clicks, state and
+    /// timestamps, in one transaction. Links that conflict with an existing
+    /// row are skipped. With `keep_ids` they keep their row ids and the id
+    /// sequence is moved past them; otherwise the target assigns new ids.
+    /// Returns how many were inserted.
+    async fn import_urls(&self, urls: &[Arc<ShortenedUrl>], keep_ids: bool) -> Result<u64>;
+
+    /// Up to `limit` users after the `(user_id, auth_method)` key, in key order
+    async fn export_users(
+        &self,
+        after: Option<&(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<UserRecord>>;
+
+    /// Insert copied users, skipping those that exist. Returns how many
+    /// were inserted.
+    async fn import_users(&self, users: &[UserRecord]) -> Result<u64>;
+
+    /// Every manually promoted admin
+    async fn export_admins(&self) -> Result<Vec<AdminRecord>>;
+
+    /// Insert copied admins, skipping those that exist. Returns how many
+    /// were inserted.
+    async fn import_admins(&self, admins: &[AdminRecord]) -> Result<u64>;
+
+    /// Every campaign, lowest id first
+    async fn export_campaigns(&self) -> Result<Vec<Campaign>>;
+
+    /// Insert copied campaigns with their ids, skipping ids that exist, and
+    /// move the id sequence past them. Returns how many were inserted.
+    async fn import_campaigns(&self, campaigns: &[Campaign]) -> Result<u64>;
+
+    /// Up to `limit` analytics rows after the `(time_bucket, id)` key, in
+    /// key order
+    async fn export_analytics(
+        &self,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<crate::analytics::AnalyticsEntry>>;
+
+    /// Insert copied analytics rows as they are, in one transaction. Rows
+    /// are not merged with existing ones. Returns how many were inserted.
+    async fn import_analytics(&self, rows: &[crate::analytics::AnalyticsEntry]) -> Result<u64>;
+
+    /// Rows in each table `copy_storage` copies
+    async fn count_rows(&self) -> Result<RowCounts>;
+
+    /// Connection pool health, for backends that pool database connections
+    fn pool_stats(&self) -> Option<PoolStats> {
+        None
+    }
+
+    /// Time one connection acquisition and record it in [`Storage::pool_stats`]
+    async fn probe_pool(&self) {}
+
+    /// Size of the in-process read cache, for storages that keep one
+    async fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
 }
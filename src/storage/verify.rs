@@ -0,0 +1,411 @@
+//! Database health checks behind `lynx db verify`.
+//!
+//! Each backend runs its own integrity and foreign key checks, then looks for
+//! the tables and indexes `init()` creates and for analytics rows whose short
+//! code no longer exists. With `fix`, missing schema objects are recreated by
+//! running `init()` again and orphaned analytics rows are deleted.
+//!
+//! `Storage::verify_schema` is the quick read-only variant CLI commands run
+//! before touching the database: every expected table, every column a
+//! migration added and every numbered migration must be there.
+
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::migrations::MIGRATIONS;
+
+/// Tables created by `init()` on every backend.
+pub const EXPECTED_TABLES: &[&str] = &[
+    "urls",
+    "campaigns",
+    "users",
+    "admin_users",
+    "analytics",
+    "alias_analytics",
+    "analytics_estimates",
+    "url_history",
+    "click_history",
+    "audit_log",
+    "analytics_prune_runs",
+    "analytics_daily",
+    "analytics_daily_runs",
+    "instance_stats_daily",
+    "link_moderation",
+    "settings",
+    "user_usage",
+    "server_leases",
+    "challenge_redemptions",
+    "schema_migrations",
+];
+
+/// Indexes created by `init()` on every backend.
+pub const EXPECTED_INDEXES: &[&str] = &[
+    "idx_short_code",
+    "idx_created_by",
+    "idx_urls_reserved_until",
+    "idx_urls_alias_of",
+    "idx_urls_created_at_id",
+    "idx_urls_created_by_created_at_id",
+    "idx_urls_dest_host",
+    "idx_urls_normalized_url",
+    "idx_urls_campaign_id",
+    "idx_analytics_key",
+    "idx_analytics_short_code",
+    "idx_analytics_time_bucket",
+    "idx_analytics_short_code_time",
+    "idx_analytics_daily_short_code_day",
+    "idx_url_history_short_code",
+    "idx_click_history_hour",
+    "idx_audit_log_short_code",
+    "idx_link_moderation_status",
+    "idx_challenge_redemptions_expires_at",
+];
+
+/// Columns that migrations in `init()` add to existing tables, as
+/// `(table, column)`. A database missing one predates that migration.
+pub const MIGRATED_COLUMNS: &[(&str, &str)] = &[
+    ("urls", "reserved_until"),
+    ("urls", "alias_of"),
+    ("urls", "title"),
+    ("urls", "created_via"),
+    ("urls", "last_visited_at"),
+    ("urls", "created_by_auth_method"),
+    ("urls", "options"),
+    ("urls", "updated_at"),
+    ("urls", "dest_host"),
+    ("urls", "normalized_url"),
+    ("urls", "campaign_id"),
+    ("users", "last_seen_at"),
+];
+
+/// Schema objects listed in `verify_schema` errors before the rest are
+/// summarized as a count.
+const MAX_LISTED_MISSING: usize = 5;
+
+/// Tables holding per-link analytics that may outlive a missing `urls` row.
+pub const ANALYTICS_TABLES: &[&str] = &[
+    "analytics",
+    "alias_analytics",
+    "analytics_estimates",
+    "analytics_daily",
+    "click_history",
+];
+
+/// Analytics tables with the columns that name a link, as `(table,
+/// columns)`. `hard_delete` clears the rows naming a deleted code and
+/// `delete_orphan_analytics` the rows naming a code missing from `urls`;
+/// both count them as analytics rows. `click_history` is counted apart.
+pub const LINK_ANALYTICS_TABLES: &[(&str, &[&str])] = &[
+    ("analytics", &["short_code"]),
+    ("alias_analytics", &["short_code", "alias_code"]),
+    ("analytics_estimates", &["short_code"]),
+    ("analytics_daily", &["short_code"]),
+];
+
+/// `condition` for each of `columns`, joined with `OR`.
+pub fn any_column(columns: &[&str], condition: impl Fn(&str) -> String) -> String {
+    columns
+        .iter()
+        .map(|column| condition(column))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Rows of a table aliased `t` naming a short code missing from `urls`.
+pub fn orphan_condition(columns: &[&str]) -> String {
+    any_column(columns, |column| {
+        format!("NOT EXISTS (SELECT 1 FROM urls u WHERE u.short_code = t.{column})")
+    })
+}
+
+/// Rows removed per statement when deleting orphaned analytics, so a large
+/// cleanup never holds a table lock for long.
+pub const ORPHAN_DELETE_BATCH_SIZE: i64 = 1000;
+
+/// Analytics rows whose short code is not in `urls`: `analytics` sums the
+/// [`LINK_ANALYTICS_TABLES`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OrphanCounts {
+    pub analytics: i64,
+    pub click_history: i64,
+}
+
+impl OrphanCounts {
+    pub fn total(&self) -> i64 {
+        self.analytics + self.click_history
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Problem,
+    Fixed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyCheck {
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Outcome of `Storage::verify_database`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub backend: &'static str,
+    pub checks: Vec<VerifyCheck>,
+}
+
+impl VerifyReport {
+    pub fn new(backend: &'static str) -> Self {
+        Self {
+            backend,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        check: impl Into<String>,
+        status: CheckStatus,
+        detail: impl Into<String>,
+    ) {
+        self.checks.push(VerifyCheck {
+            check: check.into(),
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// Number of checks that found a problem that is still there.
+    pub fn problems(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Problem)
+            .count()
+    }
+
+    /// Record whether each `expected` object of `kind` exists. `before` holds
+    /// the names present when verification started and `after` those present
+    /// once any fix ran.
+    pub fn record_schema_objects(
+        &mut self,
+        kind: &str,
+        expected: &[&str],
+        before: &HashSet<String>,
+        after: &HashSet<String>,
+    ) {
+        for name in expected {
+            let check = format!("{kind} {name}");
+            if before.contains(*name) {
+                self.push(check, CheckStatus::Ok, "present");
+            } else if after.contains(*name) {
+                self.push(check, CheckStatus::Fixed, "recreated");
+            } else {
+                self.push(check, CheckStatus::Problem, "missing");
+            }
+        }
+    }
+
+    /// Record the orphaned analytics rows found, and whether they were deleted.
+    pub fn record_orphans(&mut self, orphans: OrphanCounts, deleted: bool) {
+        self.record_orphan_table("analytics", orphans.analytics, deleted);
+        self.record_orphan_table("click_history", orphans.click_history, deleted);
+    }
+
+    fn record_orphan_table(&mut self, table: &str, orphans: i64, deleted: bool) {
+        let check = format!("orphaned {table} rows");
+        match (orphans, deleted) {
+            (0, _) => self.push(check, CheckStatus::Ok, "none"),
+            (n, true) => self.push(check, CheckStatus::Fixed, format!("{n} deleted")),
+            (n, false) => self.push(
+                check,
+                CheckStatus::Problem,
+                format!("{n} rows reference missing short codes"),
+            ),
+        }
+    }
+}
+
+/// Whether any name in `expected` is absent from `present`.
+pub fn is_schema_incomplete(expected: &[&[&str]], present: &HashSet<String>) -> bool {
+    expected
+        .iter()
+        .flat_map(|names| names.iter())
+        .any(|name| !present.contains(*name))
+}
+
+/// Fail unless every table in `expected_tables` is in `tables`, every
+/// [`MIGRATED_COLUMNS`] entry is in `columns` and every numbered migration
+/// is in `applied`.
+pub fn check_schema_current(
+    expected_tables: &[&[&str]],
+    tables: &HashSet<String>,
+    columns: &HashSet<(String, String)>,
+    applied: &HashSet<i64>,
+) -> anyhow::Result<()> {
+    let missing_tables = expected_tables
+        .iter()
+        .flat_map(|names| names.iter())
+        .filter(|name| !tables.contains(**name))
+        .map(|name| format!("table {name}"));
+    let missing_columns = MIGRATED_COLUMNS
+        .iter()
+        .filter(|(table, column)| {
+            tables.contains(*table) && !columns.contains(&(table.to_string(), column.to_string()))
+        })
+        .map(|(table, column)| format!("column {table}.{column}"));
+    let missing_migrations = MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| format!("migration {} ({})", migration.version, migration.name));
+    let missing: Vec<String> = missing_tables
+        .chain(missing_columns)
+        .chain(missing_migrations)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let mut listed = missing[..missing.len().min(MAX_LISTED_MISSING)].join(", ");
+    if missing.len() > MAX_LISTED_MISSING {
+        listed.push_str(&format!(" and {} more", missing.len() - MAX_LISTED_MISSING));
+    }
+    bail!(
+        "database schema is missing or outdated ({listed}); \
+         run the server or `lynx db migrate` first"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn schema_objects_report_present_recreated_and_missing() {
+        let mut report = VerifyReport::new("sqlite");
+        report.record_schema_objects(
+            "index",
+            &["idx_a", "idx_b", "idx_c"],
+            &names(&["idx_a"]),
+            &names(&["idx_a", "idx_b"]),
+        );
+
+        let statuses: Vec<CheckStatus> = report.checks.iter().map(|check| check.status).collect();
+        assert_eq!(
+            statuses,
+            vec![CheckStatus::Ok, CheckStatus::Fixed, CheckStatus::Problem]
+        );
+        assert_eq!(report.checks[2].check, "index idx_c");
+        assert_eq!(report.problems(), 1);
+    }
+
+    #[test]
+    fn deleted_orphans_are_not_problems() {
+        let mut report = VerifyReport::new("postgres");
+        report.record_orphans(
+            OrphanCounts {
+                analytics: 0,
+                click_history: 4,
+            },
+            true,
+        );
+        assert_eq!(report.problems(), 0);
+        assert_eq!(report.checks[1].status, CheckStatus::Fixed);
+
+        report.record_orphans(
+            OrphanCounts {
+                analytics: 2,
+                click_history: 0,
+            },
+            false,
+        );
+        assert_eq!(report.problems(), 1);
+    }
+
+    fn columns(pairs: &[(&str, &str)]) -> HashSet<(String, String)> {
+        pairs
+            .iter()
+            .map(|(table, column)| (table.to_string(), column.to_string()))
+            .collect()
+    }
+
+    fn all_applied() -> HashSet<i64> {
+        MIGRATIONS
+            .iter()
+            .map(|migration| migration.version)
+            .collect()
+    }
+
+    #[test]
+    fn current_schema_needs_every_table_and_migrated_column() {
+        let tables = names(&["urls", "users"]);
+        assert!(check_schema_current(
+            &[&["urls", "users"]],
+            &tables,
+            &columns(MIGRATED_COLUMNS),
+            &all_applied()
+        )
+        .is_ok());
+
+        let error = check_schema_current(
+            &[&["urls", "users", "audit_log"]],
+            &tables,
+            &columns(&[]),
+            &all_applied(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("database schema is missing or outdated (table audit_log, column urls.reserved_until,"), "{error}");
+        assert!(
+            error.contains(&format!(
+                "and {} more",
+                MIGRATED_COLUMNS.len() + 1 - MAX_LISTED_MISSING
+            )),
+            "{error}"
+        );
+        assert!(
+            error.ends_with("run the server or `lynx db migrate` first"),
+            "{error}"
+        );
+
+        // Columns of a missing table are reported as the table alone
+        let error = check_schema_current(&[&["urls"]], &names(&[]), &columns(&[]), &all_applied())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("(table urls)"), "{error}");
+    }
+
+    #[test]
+    fn current_schema_needs_every_numbered_migration() {
+        let tables = names(&["urls", "users"]);
+        let error = check_schema_current(
+            &[&["urls", "users"]],
+            &tables,
+            &columns(MIGRATED_COLUMNS),
+            &HashSet::new(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.contains("migration 1 (analytics_null_dimension_key)"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn schema_is_incomplete_when_any_name_is_missing() {
+        let present = names(&["urls", "idx_short_code"]);
+        assert!(!is_schema_incomplete(
+            &[&["urls"], &["idx_short_code"]],
+            &present
+        ));
+        assert!(is_schema_incomplete(&[&["urls", "users"]], &present));
+    }
+}
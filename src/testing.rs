@@ -0,0 +1,469 @@
+//! Seeded storage states for tests.
+//!
+//! Tests that need "some users with some links and some traffic" describe
+//! the state with a [`Fixture`] instead of looping over `create_with_code`
+//! and `upsert_analytics_batch` by hand:
+//!
+//! ```ignore
+//! let seeded = Fixture::new(storage.clone())
+//!     .users(3)
+//!     .links_per_user(10)
+//!     .with_clicks(0..=50)
+//!     .with_analytics_days(30)
+//!     .seed()
+//!     .await?;
+//! ```
+//!
+//! Everything the fixture writes is a function of its settings: user ids and
+//! short codes follow a fixed pattern, and click and visit counts come from
+//! a generator seeded with [`Fixture::rng_seed`]. Hand in the storage's
+//! [`FakeClock`] with [`Fixture::clock`] to also fix creation times and the
+//! days the analytics land on.
+//!
+//! Links that need more than the defaults are named by their code:
+//!
+//! ```ignore
+//! let seeded = Fixture::new(storage.clone())
+//!     .links_per_user(3)
+//!     .link_options("user1-0", LinkOptions { max_clicks: Some(10), ..LinkOptions::default() })
+//!     .campaign("Spring launch", ["user1-1", "user1-2"])
+//!     .seed()
+//!     .await?;
+//! ```
+//!
+//! Compiled for unit tests and, through the `test-util` feature, for the
+//! integration tests; release builds never contain it.
+
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use rand::distr::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::analytics::{AnalyticsRollup, IpVersion};
+use crate::clock::{Clock, FakeClock, SystemClock};
+use crate::models::{Campaign, CreatedVia, LinkOptions, ShortenedUrl};
+use crate::storage::{ClickIncrement, Storage};
+
+/// Countries the seeded analytics rotate through, one per day.
+pub const COUNTRIES: [&str; 8] = ["US", "DE", "JP", "BR", "IN", "FR", "GB", "CA"];
+
+/// Auth method of the seeded users, also recorded on their links so owner
+/// lookups find them.
+pub const AUTH_METHOD: &str = "oauth";
+
+/// Visits recorded per link and day when analytics are seeded.
+const VISITS_PER_DAY: RangeInclusive<i64> = 1..=20;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Builder for a seeded storage state.
+pub struct Fixture {
+    storage: Arc<dyn Storage>,
+    users: usize,
+    links_per_user: usize,
+    clicks: RangeInclusive<u64>,
+    analytics_days: u32,
+    prefix: String,
+    clock: Option<Arc<FakeClock>>,
+    rng_seed: u64,
+    link_options: HashMap<String, LinkOptions>,
+    campaigns: Vec<(String, Vec<String>)>,
+}
+
+/// What [`Fixture::seed`] created, in creation order.
+#[derive(Debug, Clone)]
+pub struct Seeded {
+    pub users: Vec<SeededUser>,
+    pub campaigns: Vec<Campaign>,
+}
+
+/// A seeded user and the links they own.
+#[derive(Debug, Clone)]
+pub struct SeededUser {
+    pub user_id: String,
+    pub email: String,
+    pub links: Vec<SeededLink>,
+}
+
+/// A seeded link and the traffic recorded for it.
+#[derive(Debug, Clone)]
+pub struct SeededLink {
+    /// The link as storage returned it on creation, put in its campaign but
+    /// before any clicks
+    pub url: Arc<ShortenedUrl>,
+    pub clicks: u64,
+    /// Visits across all seeded analytics rows
+    pub visits: i64,
+}
+
+impl SeededLink {
+    pub fn short_code(&self) -> &str {
+        &self.url.short_code
+    }
+}
+
+impl Seeded {
+    /// Every seeded link, user by user.
+    pub fn links(&self) -> impl DoubleEndedIterator<Item = &SeededLink> {
+        self.users.iter().flat_map(|user| &user.links)
+    }
+
+    /// The seeded link with `short_code`.
+    pub fn link(&self, short_code: &str) -> Option<&SeededLink> {
+        self.links().find(|link| link.short_code() == short_code)
+    }
+
+    /// The seeded campaign named `name`.
+    pub fn campaign(&self, name: &str) -> Option<&Campaign> {
+        self.campaigns.iter().find(|campaign| campaign.name == name)
+    }
+
+    pub fn total_clicks(&self) -> u64 {
+        self.links().map(|link| link.clicks).sum()
+    }
+
+    pub fn total_visits(&self) -> i64 {
+        self.links().map(|link| link.visits).sum()
+    }
+}
+
+impl Fixture {
+    /// One user with one link, no clicks and no analytics.
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            users: 1,
+            links_per_user: 1,
+            clicks: 0..=0,
+            analytics_days: 0,
+            prefix: String::new(),
+            clock: None,
+            rng_seed: 0,
+            link_options: HashMap::new(),
+            campaigns: Vec::new(),
+        }
+    }
+
+    /// Create `count` users, `user1` through `user{count}`.
+    pub fn users(mut self, count: usize) -> Self {
+        self.users = count;
+        self
+    }
+
+    /// Give every user `count` links, `{user}-0` through `{user}-{count - 1}`.
+    pub fn links_per_user(mut self, count: usize) -> Self {
+        self.links_per_user = count;
+        self
+    }
+
+    /// Record a click count drawn from `clicks` on every link.
+    pub fn with_clicks(mut self, clicks: RangeInclusive<u64>) -> Self {
+        self.clicks = clicks;
+        self
+    }
+
+    /// Record visits on each of the last `days` days, today included, for
+    /// every link. Day `n` back is attributed to `COUNTRIES[n % 8]`.
+    pub fn with_analytics_days(mut self, days: u32) -> Self {
+        self.analytics_days = days;
+        self
+    }
+
+    /// Prefix user ids (and so short codes), for several fixtures in one
+    /// database.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// The clock the storage was built with. The fixture advances it by a
+    /// millisecond after each link, so creation order is the seeding order,
+    /// and dates the analytics from its reading. Without one, links may
+    /// share a timestamp and analytics end on the real today.
+    pub fn clock(mut self, clock: Arc<FakeClock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Seed for the click and visit counts; the same seed gives the same
+    /// counts on every run.
+    pub fn rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = seed;
+        self
+    }
+
+    /// Create the link `short_code` with `options` instead of the defaults.
+    pub fn link_options(mut self, short_code: impl Into<String>, options: LinkOptions) -> Self {
+        self.link_options.insert(short_code.into(), options);
+        self
+    }
+
+    /// Create a campaign named `name` holding the links `short_codes`,
+    /// owned by the user of the first of them. A link in several campaigns
+    /// ends up in the last.
+    pub fn campaign<S: Into<String>>(
+        mut self,
+        name: impl Into<String>,
+        short_codes: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let short_codes = short_codes.into_iter().map(Into::into).collect();
+        self.campaigns.push((name.into(), short_codes));
+        self
+    }
+
+    /// Write the described state to storage.
+    pub async fn seed(self) -> Result<Seeded> {
+        // Every link named must be one the fixture creates, before anything
+        // is written.
+        let owners: HashMap<String, String> = (1..=self.users)
+            .flat_map(|n| {
+                let user_id = format!("{}user{n}", self.prefix);
+                (0..self.links_per_user).map(move |i| (format!("{user_id}-{i}"), user_id.clone()))
+            })
+            .collect();
+        let named = self.link_options.keys();
+        for code in named.chain(self.campaigns.iter().flat_map(|(_, codes)| codes)) {
+            if !owners.contains_key(code) {
+                bail!("{code} is not a link the fixture seeds");
+            }
+        }
+        let mut campaigns = Vec::with_capacity(self.campaigns.len());
+        let mut campaign_of = HashMap::new();
+        for (name, codes) in &self.campaigns {
+            let owner = codes.first().map(|code| owners[code].as_str());
+            let campaign = self.storage.create_campaign(name, owner).await?;
+            for code in codes {
+                campaign_of.insert(code.as_str(), campaign.id);
+            }
+            campaigns.push(campaign);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.rng_seed);
+        let clicks = Uniform::new_inclusive(*self.clicks.start(), *self.clicks.end())?;
+        let visits = Uniform::new_inclusive(*VISITS_PER_DAY.start(), *VISITS_PER_DAY.end())?;
+        let today = match &self.clock {
+            Some(clock) => clock.now_epoch_secs(),
+            None => SystemClock.now_epoch_secs(),
+        }
+        .div_euclid(SECS_PER_DAY)
+            * SECS_PER_DAY;
+
+        let mut users = Vec::with_capacity(self.users);
+        let mut increments = Vec::new();
+        let mut rollups = Vec::new();
+        for n in 1..=self.users {
+            let user_id = format!("{}user{n}", self.prefix);
+            let email = format!("{user_id}@example.com");
+            self.storage
+                .upsert_user(&user_id, Some(&email), AUTH_METHOD)
+                .await?;
+
+            let mut links = Vec::with_capacity(self.links_per_user);
+            for i in 0..self.links_per_user {
+                let code = format!("{user_id}-{i}");
+                let mut url = self
+                    .storage
+                    .create_with_code_via(
+                        &code,
+                        &format!("https://example.com/{user_id}/{i}"),
+                        Some(&user_id),
+                        Some(AUTH_METHOD),
+                        CreatedVia::Unknown,
+                        &self.link_options.get(&code).cloned().unwrap_or_default(),
+                    )
+                    .await?;
+                if let Some(&campaign_id) = campaign_of.get(code.as_str()) {
+                    self.storage
+                        .set_link_campaign(&code, Some(campaign_id))
+                        .await?;
+                    url = Arc::new(ShortenedUrl {
+                        campaign_id: Some(campaign_id),
+                        ..(*url).clone()
+                    });
+                }
+                if let Some(clock) = &self.clock {
+                    clock.advance(Duration::from_millis(1));
+                }
+
+                let link_clicks = clicks.sample(&mut rng);
+                if let Some(amount) = NonZeroU64::new(link_clicks) {
+                    increments.push(ClickIncrement::new(code.clone(), amount));
+                }
+                let mut link_visits = 0;
+                for day in 0..self.analytics_days {
+                    let visit_count = visits.sample(&mut rng);
+                    link_visits += visit_count;
+                    rollups.push(AnalyticsRollup {
+                        short_code: code.clone(),
+                        time_bucket: today - i64::from(day) * SECS_PER_DAY,
+                        country_code: Some(COUNTRIES[day as usize % COUNTRIES.len()].to_string()),
+                        region: None,
+                        city: None,
+                        asn: None,
+                        ip_version: IpVersion::V4,
+                        visit_count,
+                        estimated_visits: 0,
+                        alias_used: None,
+                    });
+                }
+                links.push(SeededLink {
+                    url,
+                    clicks: link_clicks,
+                    visits: link_visits,
+                });
+            }
+            users.push(SeededUser {
+                user_id,
+                email,
+                links,
+            });
+        }
+
+        if !increments.is_empty() {
+            self.storage.increment_clicks_batch(&increments).await?;
+        }
+        if !rollups.is_empty() {
+            self.storage.upsert_analytics_batch(rollups).await?;
+        }
+        Ok(Seeded { users, campaigns })
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+
+    async fn storage(clock: &Arc<FakeClock>) -> Arc<dyn Storage> {
+        let storage = SqliteStorage::new("sqlite::memory:", 1)
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        storage.init().await.unwrap();
+        Arc::new(storage)
+    }
+
+    async fn seed(seed: u64) -> (Arc<dyn Storage>, Seeded) {
+        let clock = Arc::new(FakeClock::at_epoch_ms(1_700_000_000_000));
+        let storage = storage(&clock).await;
+        let seeded = Fixture::new(storage.clone())
+            .users(2)
+            .links_per_user(3)
+            .with_clicks(1..=100)
+            .with_analytics_days(4)
+            .clock(clock)
+            .rng_seed(seed)
+            .seed()
+            .await
+            .unwrap();
+        (storage, seeded)
+    }
+
+    #[tokio::test]
+    async fn seeds_users_links_clicks_and_analytics() {
+        let (storage, seeded) = seed(7).await;
+
+        let codes: Vec<&str> = seeded.links().map(SeededLink::short_code).collect();
+        assert_eq!(
+            codes,
+            ["user1-0", "user1-1", "user1-2", "user2-0", "user2-1", "user2-2"]
+        );
+        for user in &seeded.users {
+            assert!(storage.user_exists(&user.user_id).await.unwrap());
+        }
+        for link in seeded.links() {
+            let stored = storage.get(link.short_code()).await.unwrap().unwrap();
+            assert_eq!(stored.clicks, link.clicks as i64);
+            let analytics = storage
+                .get_analytics(link.short_code(), None, None, 100)
+                .await
+                .unwrap();
+            assert_eq!(analytics.len(), 4);
+            let visits: i64 = analytics.iter().map(|row| row.visit_count).sum();
+            assert_eq!(visits, link.visits);
+        }
+    }
+
+    #[tokio::test]
+    async fn same_seed_same_state() {
+        let ((_, a), (_, b), (_, c)) = (seed(7).await, seed(7).await, seed(8).await);
+        let counts = |seeded: &Seeded| -> Vec<(u64, i64, i64)> {
+            seeded
+                .links()
+                .map(|link| (link.clicks, link.visits, link.url.created_at))
+                .collect()
+        };
+        assert_eq!(counts(&a), counts(&b));
+        assert_ne!(counts(&a), counts(&c));
+    }
+
+    #[tokio::test]
+    async fn named_links_get_their_options_and_campaigns() {
+        let clock = Arc::new(FakeClock::at_epoch_ms(1_700_000_000_000));
+        let storage = storage(&clock).await;
+        let limited = LinkOptions {
+            max_clicks: Some(10),
+            ..LinkOptions::default()
+        };
+        let seeded = Fixture::new(storage.clone())
+            .users(2)
+            .links_per_user(2)
+            .link_options("user1-0", limited.clone())
+            .campaign("Launch", ["user2-1", "user1-1"])
+            .seed()
+            .await
+            .unwrap();
+
+        let launch = seeded.campaign("Launch").unwrap();
+        assert_eq!(launch.owner.as_deref(), Some("user2"));
+        for link in seeded.links() {
+            let stored = storage.get(link.short_code()).await.unwrap().unwrap();
+            assert_eq!(stored.options, link.url.options);
+            assert_eq!(stored.campaign_id, link.url.campaign_id);
+        }
+        let options: Vec<bool> = seeded
+            .links()
+            .map(|link| link.url.options == limited)
+            .collect();
+        assert_eq!(options, [true, false, false, false]);
+        let in_launch: Vec<&str> = seeded
+            .links()
+            .filter(|link| link.url.campaign_id == Some(launch.id))
+            .map(SeededLink::short_code)
+            .collect();
+        assert_eq!(in_launch, ["user1-1", "user2-1"]);
+
+        let unknown = Fixture::new(storage.clone())
+            .prefix("other-")
+            .link_options("user1-0", limited)
+            .seed()
+            .await;
+        assert!(unknown.is_err());
+        assert!(!storage.user_exists("other-user1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fake_clock_orders_links_and_dates_analytics() {
+        let (storage, seeded) = seed(1).await;
+
+        let created: Vec<i64> = seeded.links().map(|link| link.url.created_at).collect();
+        assert!(created.windows(2).all(|pair| pair[0] < pair[1]));
+        let buckets: Vec<i64> = storage
+            .get_analytics("user1-0", None, None, 100)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.time_bucket)
+            .collect();
+        let today = 1_700_000_000 / SECS_PER_DAY * SECS_PER_DAY;
+        for day in 0..4 {
+            assert!(buckets.contains(&(today - day * SECS_PER_DAY)));
+        }
+    }
+}
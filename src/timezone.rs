@@ -0,0 +1,148 @@
+//! Calendar days in a caller-chosen time zone.
+//!
+//! Analytics and click history are stored in UTC hour buckets. Grouping them
+//! by day used to divide by 86400, which yields UTC days; reports evaluated in
+//! another zone need the zone's own midnights instead. Those are not 24 hours
+//! apart on daylight saving transitions, so day boundaries are always derived
+//! from the local calendar date rather than by adding fixed offsets.
+
+use chrono::{Days, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+pub const SECONDS_PER_HOUR: i64 = 3_600;
+
+/// Parse an IANA time zone name such as `America/New_York`.
+pub fn parse_time_zone(name: &str) -> Option<Tz> {
+    name.trim().parse().ok()
+}
+
+/// Start of the UTC hour containing `timestamp`.
+pub fn hour_start(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(SECONDS_PER_HOUR)
+}
+
+/// Start (Unix timestamp) of the day in `tz` that contains `timestamp`.
+pub fn local_day_start(timestamp: i64, tz: Tz) -> i64 {
+    tz.timestamp_opt(timestamp, 0)
+        .single()
+        .and_then(|local| start_of_date(local.date_naive(), tz))
+        .unwrap_or(timestamp)
+}
+
+/// Start of the day in `tz` that began `days_ago` local days before the one
+/// containing `now`.
+pub fn local_day_start_days_ago(now: i64, days_ago: u64, tz: Tz) -> i64 {
+    tz.timestamp_opt(now, 0)
+        .single()
+        .and_then(|local| local.date_naive().checked_sub_days(Days::new(days_ago)))
+        .and_then(|date| start_of_date(date, tz))
+        .unwrap_or_else(|| local_day_start(now, tz))
+}
+
+/// First instant of `date` in `tz`. A few zones have skipped midnight on a
+/// transition day, so the earliest existing hour is used then.
+fn start_of_date(date: NaiveDate, tz: Tz) -> Option<i64> {
+    (0..24).find_map(|hour| {
+        let local = date.and_hms_opt(hour, 0, 0)?;
+        tz.from_local_datetime(&local)
+            .earliest()
+            .map(|start| start.timestamp())
+    })
+}
+
+/// Sum `(bucket_start, count)` pairs per day in `tz`, keyed by day start.
+pub fn sum_by_local_day(
+    buckets: impl IntoIterator<Item = (i64, i64)>,
+    tz: Tz,
+) -> BTreeMap<i64, i64> {
+    let mut days = BTreeMap::new();
+    for (bucket, count) in buckets {
+        *days.entry(local_day_start(bucket, tz)).or_insert(0) += count;
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> i64 {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap().timestamp()
+    }
+
+    fn hours(from: i64, count: i64) -> impl Iterator<Item = (i64, i64)> {
+        (0..count).map(move |i| (from + i * SECONDS_PER_HOUR, 1))
+    }
+
+    #[test]
+    fn time_zone_names_are_validated() {
+        assert_eq!(
+            parse_time_zone("America/New_York"),
+            Some(Tz::America__New_York)
+        );
+        assert_eq!(parse_time_zone("UTC"), Some(Tz::UTC));
+        assert_eq!(parse_time_zone("Mars/Olympus_Mons"), None);
+        assert_eq!(parse_time_zone(""), None);
+    }
+
+    #[test]
+    fn utc_days_match_epoch_division() {
+        let timestamp = utc(2024, 6, 1, 13) + 59;
+        assert_eq!(
+            local_day_start(timestamp, Tz::UTC),
+            (timestamp / 86_400) * 86_400
+        );
+    }
+
+    #[test]
+    fn spring_forward_day_has_23_hours() {
+        let ny = Tz::America__New_York;
+        // 2024-03-10 starts at 05:00 UTC (EST) and ends at 04:00 UTC (EDT).
+        let start = utc(2024, 3, 10, 5);
+        assert_eq!(local_day_start(start, ny), start);
+        assert_eq!(local_day_start(start - 1, ny), utc(2024, 3, 9, 5));
+        assert_eq!(
+            local_day_start(utc(2024, 3, 11, 4), ny),
+            utc(2024, 3, 11, 4)
+        );
+
+        let days = sum_by_local_day(hours(utc(2024, 3, 9, 5), 24 + 23 + 24), ny);
+        assert_eq!(
+            days.into_iter().collect::<Vec<_>>(),
+            vec![
+                (utc(2024, 3, 9, 5), 24),
+                (start, 23),
+                (utc(2024, 3, 11, 4), 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn fall_back_day_has_25_hours() {
+        let ny = Tz::America__New_York;
+        // 2024-11-03 starts at 04:00 UTC (EDT) and ends at 05:00 UTC (EST).
+        let start = utc(2024, 11, 3, 4);
+        let days = sum_by_local_day(hours(start, 25 + 24), ny);
+        assert_eq!(
+            days.into_iter().collect::<Vec<_>>(),
+            vec![(start, 25), (utc(2024, 11, 4, 5), 24)]
+        );
+    }
+
+    #[test]
+    fn days_ago_steps_over_transitions_by_calendar_date() {
+        let ny = Tz::America__New_York;
+        let now = utc(2024, 3, 11, 16);
+        assert_eq!(local_day_start_days_ago(now, 0, ny), utc(2024, 3, 11, 4));
+        assert_eq!(local_day_start_days_ago(now, 1, ny), utc(2024, 3, 10, 5));
+        assert_eq!(local_day_start_days_ago(now, 2, ny), utc(2024, 3, 9, 5));
+    }
+
+    #[test]
+    fn hours_are_truncated() {
+        assert_eq!(hour_start(utc(2024, 1, 1, 7) + 1_234), utc(2024, 1, 1, 7));
+        assert_eq!(hour_start(-1), -SECONDS_PER_HOUR);
+    }
+}
@@ -0,0 +1,420 @@
+//! Background fetch of a new link's page title.
+//!
+//! With `TITLE_FETCH_ENABLED` set, creating a link spawns a task that loads
+//! the destination and stores its `<title>`, so listings read as more than a
+//! column of URLs. The request is answered before the fetch starts, and a
+//! fetch that fails for any reason leaves the title empty without reporting
+//! anything to the caller.
+//!
+//! Fetching arbitrary user-supplied URLs from the server is bounded on every
+//! axis: only http(s), [`FETCH_TIMEOUT`] for the whole exchange, at most
+//! [`MAX_BODY_BYTES`] read, at most [`MAX_REDIRECTS`] redirects, and no
+//! private, loopback or link-local addresses (checked on every resolved
+//! address, so DNS names pointing inward are refused too) unless the
+//! instance opts in with `TITLE_FETCH_ALLOW_PRIVATE_ADDRESSES`.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::config::TitleFetchConfig;
+use crate::storage::Storage;
+
+/// Upper bound for the whole fetch, including reading the body.
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Bytes of the page read while looking for its title.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Redirects followed before giving up.
+pub const MAX_REDIRECTS: usize = 2;
+
+/// Characters of a title kept; longer titles are cut.
+pub const MAX_TITLE_CHARS: usize = 200;
+
+/// Loads destination pages and extracts their titles.
+#[derive(Debug, Clone)]
+pub struct TitleFetcher {
+    client: reqwest::Client,
+    allow_private_addresses: bool,
+}
+
+impl TitleFetcher {
+    /// Build a fetcher from configuration, or `None` when fetching is off.
+    pub fn from_config(config: &TitleFetchConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        match Self::new(config.allow_private_addresses) {
+            Ok(fetcher) => Some(fetcher),
+            Err(error) => {
+                tracing::warn!(error = %error, "Title fetching disabled: failed to build HTTP client");
+                None
+            }
+        }
+    }
+
+    pub fn new(allow_private_addresses: bool) -> reqwest::Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            // Destinations are fetched directly, never through
+            // `OUTBOUND_PROXY_URL`: the private-address guard below must
+            // resolve them itself
+            .no_proxy()
+            .timeout(FETCH_TIMEOUT)
+            .redirect(redirect_policy(allow_private_addresses))
+            .user_agent(concat!(
+                "Lynx/",
+                env!("CARGO_PKG_VERSION"),
+                " (title fetch)"
+            ));
+        if !allow_private_addresses {
+            builder = builder.dns_resolver(Arc::new(PublicAddressResolver));
+        }
+        Ok(Self {
+            client: builder.build()?,
+            allow_private_addresses,
+        })
+    }
+
+    /// Fetch the title of `short_code`'s destination in the background and
+    /// store it if the link still has none.
+    pub fn spawn_fill(&self, storage: Arc<dyn Storage>, short_code: String, destination: String) {
+        let fetcher = self.clone();
+        tokio::spawn(async move {
+            let Some(title) = fetcher.fetch_title(&destination).await else {
+                return;
+            };
+            if let Err(error) = storage.set_title_if_missing(&short_code, &title).await {
+                tracing::debug!(short_code = %short_code, error = %error, "failed to store fetched title");
+            }
+        });
+    }
+
+    /// The `<title>` of the page at `destination`, or `None` when the URL may
+    /// not be fetched, the fetch fails, or the page has no usable title.
+    pub async fn fetch_title(&self, destination: &str) -> Option<String> {
+        let url = Url::parse(destination).ok()?;
+        if !self.may_fetch(&url) {
+            tracing::debug!(url = %destination, "title fetch refused");
+            return None;
+        }
+
+        match self.read_head(url).await {
+            Ok(html) => extract_title(&html),
+            Err(error) => {
+                tracing::debug!(url = %destination, error = %error, "title fetch failed");
+                None
+            }
+        }
+    }
+
+    fn may_fetch(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+            && (self.allow_private_addresses || !has_private_ip_host(url))
+    }
+
+    /// The start of an HTML response body, at most [`MAX_BODY_BYTES`] long.
+    async fn read_head(&self, url: Url) -> reqwest::Result<String> {
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        let is_html = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_none_or(|value| value.to_ascii_lowercase().contains("html"));
+        if !is_html {
+            return Ok(String::new());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            let room = MAX_BODY_BYTES - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            if body.len() >= MAX_BODY_BYTES {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+fn redirect_policy(allow_private_addresses: bool) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if !matches!(attempt.url().scheme(), "http" | "https") {
+            attempt.error("redirect to a non-web scheme")
+        } else if !allow_private_addresses && has_private_ip_host(attempt.url()) {
+            attempt.error("redirect to a private address")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Whether the URL names a private address literally. Host names are
+/// checked when they resolve, by [`PublicAddressResolver`].
+fn has_private_ip_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Ipv4(ip)) => !is_public_address(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => !is_public_address(IpAddr::V6(ip)),
+        Some(Host::Domain(_)) => false,
+        None => true,
+    }
+}
+
+/// Resolves host names and drops every address that is not publicly
+/// routable, failing when none is left.
+struct PublicAddressResolver;
+
+impl Resolve for PublicAddressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether `ip` is publicly routable: not private, loopback, link-local,
+/// shared, documentation, multicast or otherwise reserved.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// The text of the first `<title>` element, with entities decoded and
+/// whitespace collapsed, cut to [`MAX_TITLE_CHARS`].
+pub fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Decode the character references that commonly appear in titles.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest
+            .find(';')
+            .filter(|&semi| semi <= 10)
+            .map(|semi| (&rest[1..semi], semi));
+        let replacement = entity.and_then(|(name, semi)| {
+            let c = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "#39" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| name.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, semi))
+        });
+        match replacement {
+            Some((c, semi)) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::header,
+        response::{IntoResponse, Redirect},
+        routing::get,
+        Router,
+    };
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn page(title: &str) -> impl IntoResponse {
+        (
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            format!("<html><head><TITLE lang=\"en\">{title}</TITLE></head><body></body></html>"),
+        )
+    }
+
+    async fn test_server() -> String {
+        let app = Router::new()
+            .route("/", get(|| async { page("  Spring   sale &amp; more\n") }))
+            .route("/hop1", get(|| async { Redirect::temporary("/") }))
+            .route("/hop2", get(|| async { Redirect::temporary("/hop1") }))
+            .route("/hop3", get(|| async { Redirect::temporary("/hop2") }))
+            .route(
+                "/huge",
+                get(|| async {
+                    let padding = "x".repeat(MAX_BODY_BYTES);
+                    page(&padding).into_response()
+                }),
+            )
+            .route(
+                "/json",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "application/json")],
+                        "<title>not a page</title>",
+                    )
+                }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(FETCH_TIMEOUT + Duration::from_secs(1)).await;
+                    page("too late")
+                }),
+            );
+        serve(app).await
+    }
+
+    #[tokio::test]
+    async fn titles_are_fetched_within_the_redirect_budget() {
+        let base = test_server().await;
+        let fetcher = TitleFetcher::new(true).unwrap();
+
+        for path in ["/", "/hop1", "/hop2"] {
+            assert_eq!(
+                fetcher
+                    .fetch_title(&format!("{base}{path}"))
+                    .await
+                    .as_deref(),
+                Some("Spring sale & more"),
+                "{path}"
+            );
+        }
+        assert_eq!(fetcher.fetch_title(&format!("{base}/hop3")).await, None);
+    }
+
+    #[tokio::test]
+    async fn oversized_slow_and_non_html_responses_yield_nothing() {
+        let base = test_server().await;
+        let fetcher = TitleFetcher::new(true).unwrap();
+
+        // The closing tag lies beyond the read cap.
+        assert_eq!(fetcher.fetch_title(&format!("{base}/huge")).await, None);
+        assert_eq!(fetcher.fetch_title(&format!("{base}/json")).await, None);
+        assert_eq!(fetcher.fetch_title(&format!("{base}/slow")).await, None);
+        assert_eq!(fetcher.fetch_title(&format!("{base}/missing")).await, None);
+    }
+
+    #[tokio::test]
+    async fn private_addresses_are_refused_by_default() {
+        let base = test_server().await;
+        let fetcher = TitleFetcher::new(false).unwrap();
+
+        assert_eq!(fetcher.fetch_title(&format!("{base}/")).await, None);
+        let by_name = base.replace("127.0.0.1", "localhost");
+        assert_eq!(fetcher.fetch_title(&format!("{by_name}/")).await, None);
+        assert_eq!(fetcher.fetch_title("ftp://example.com/").await, None);
+    }
+
+    #[test]
+    fn public_addresses_are_told_apart() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1", "8.8.8.8"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn titles_are_extracted_and_cleaned() {
+        assert_eq!(
+            extract_title("<head><title>Caf&#233; &lt;3 &#x27;menu&#x27;</title>").as_deref(),
+            Some("Café <3 'menu'")
+        );
+        assert_eq!(
+            extract_title("<title>AT&T &unknown; a&b</title>").as_deref(),
+            Some("AT&T &unknown; a&b")
+        );
+        assert_eq!(extract_title("<title>   </title>"), None);
+        assert_eq!(extract_title("<title>unterminated"), None);
+        assert_eq!(extract_title("<html>no title</html>"), None);
+
+        let long = format!("<title>{}</title>", "a".repeat(MAX_TITLE_CHARS + 50));
+        assert_eq!(
+            extract_title(&long).unwrap().chars().count(),
+            MAX_TITLE_CHARS
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Integration tests for the admin activity report: per-day totals and top
+//! lists over a seeded fixture, the same numbers once analytics days are
+//! rolled up, and the admin-only JSON and CSV endpoint.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    extract::{Query, State},
+    response::Response,
+    Extension,
+};
+use lynx::analytics::daily::DAY_SECS;
+use lynx::analytics::{AnalyticsRollup, IpVersion};
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::AppState,
+    quick::QuickRateLimiter,
+    reports::{activity_report, ActivityReportQuery, ACTIVITY_CSV_HEADER},
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::FakeClock;
+use lynx::models::{ActivityCreator, ActivityDay, ActivityLink};
+use lynx::storage::{SqliteStorage, Storage};
+use lynx::testing::{Fixture, Seeded};
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+/// 2023-03-10T00:00:00Z
+const DAY: i64 = 1_678_406_400;
+
+fn visits(short_code: &str, time_bucket: i64, visit_count: i64) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket,
+        country_code: Some("US".to_string()),
+        region: None,
+        city: None,
+        asn: None,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+/// Yesterday: two links by `early`, one without a creator, an alias, and
+/// some clicks and visits; two days ago only visits. Today: the fixture's
+/// six users with a link each, two more links for `user1`, random clicks
+/// and a day of visits.
+async fn seeded_storage() -> (Arc<dyn Storage>, Arc<FakeClock>, Seeded) {
+    let clock = Arc::new(FakeClock::at_epoch_ms((DAY - DAY_SECS + 3600) * 1000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+
+    for (code, creator) in [
+        ("early-0", Some("early")),
+        ("early-1", Some("early")),
+        ("anon", None),
+    ] {
+        storage
+            .create_with_code(code, "https://example.com/early", creator)
+            .await
+            .unwrap();
+    }
+    storage
+        .add_alias("early-0", "early-alias", None)
+        .await
+        .unwrap();
+    storage.increment_clicks("early-0", 4).await.unwrap();
+    storage.increment_clicks("anon", 1).await.unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            visits("early-0", DAY - 2 * DAY_SECS + 5 * 3600, 7),
+            visits("early-0", DAY - DAY_SECS + 3600, 3),
+            visits("anon", DAY - DAY_SECS + 23 * 3600, 2),
+        ])
+        .await
+        .unwrap();
+
+    clock.set_epoch_ms((DAY + 10 * 3600) * 1000);
+    let seeded = Fixture::new(Arc::clone(&storage))
+        .users(6)
+        .links_per_user(1)
+        .with_clicks(1..=100)
+        .with_analytics_days(1)
+        .clock(Arc::clone(&clock))
+        .rng_seed(7)
+        .seed()
+        .await
+        .unwrap();
+    for code in ["user1-x", "user1-y"] {
+        storage
+            .create_with_code(code, "https://example.com/more", Some("user1"))
+            .await
+            .unwrap();
+    }
+
+    (storage, clock, seeded)
+}
+
+fn creator(user_id: &str, links_created: i64) -> ActivityCreator {
+    ActivityCreator {
+        user_id: user_id.to_string(),
+        links_created,
+    }
+}
+
+fn link(short_code: &str, clicks: i64) -> ActivityLink {
+    ActivityLink {
+        short_code: short_code.to_string(),
+        clicks,
+    }
+}
+
+fn expected(seeded: &Seeded) -> Vec<ActivityDay> {
+    let mut today_links: Vec<ActivityLink> = seeded
+        .links()
+        .map(|seeded| link(seeded.short_code(), seeded.clicks as i64))
+        .collect();
+    today_links.sort_by(|a, b| {
+        b.clicks
+            .cmp(&a.clicks)
+            .then(a.short_code.cmp(&b.short_code))
+    });
+    today_links.truncate(5);
+
+    let quiet = |day| ActivityDay {
+        day,
+        links_created: 0,
+        clicks: 0,
+        visits: 0,
+        top_creators: Vec::new(),
+        top_links: Vec::new(),
+    };
+    vec![
+        quiet(DAY - 3 * DAY_SECS),
+        ActivityDay {
+            visits: 7,
+            ..quiet(DAY - 2 * DAY_SECS)
+        },
+        ActivityDay {
+            day: DAY - DAY_SECS,
+            links_created: 3,
+            clicks: 5,
+            visits: 5,
+            top_creators: vec![creator("early", 2)],
+            top_links: vec![link("early-0", 4), link("anon", 1)],
+        },
+        ActivityDay {
+            day: DAY,
+            links_created: 8,
+            clicks: seeded.total_clicks() as i64,
+            visits: seeded.total_visits(),
+            top_creators: vec![
+                creator("user1", 3),
+                creator("user2", 1),
+                creator("user3", 1),
+                creator("user4", 1),
+                creator("user5", 1),
+            ],
+            top_links: today_links,
+        },
+    ]
+}
+
+#[tokio::test]
+async fn test_activity_report_adds_up_each_day() {
+    let (storage, _clock, seeded) = seeded_storage().await;
+    let since = DAY - 3 * DAY_SECS;
+    let until = DAY + DAY_SECS;
+
+    let report = storage.activity_report(since, until, 5).await.unwrap();
+    assert_eq!(report, expected(&seeded));
+
+    // Rolled-up days read from analytics_daily with the same result
+    storage.rollup_analytics_daily(None, DAY).await.unwrap();
+    assert_eq!(
+        storage.analytics_daily_rolled_until().await.unwrap(),
+        Some(DAY)
+    );
+    assert_eq!(
+        storage.activity_report(since, until, 5).await.unwrap(),
+        report
+    );
+
+    // Narrower windows and shorter top lists cut from the same numbers
+    let today = storage.activity_report(DAY, until, 2).await.unwrap();
+    assert_eq!(today.len(), 1);
+    assert_eq!(today[0].links_created, 8);
+    assert_eq!(today[0].top_creators, report[3].top_creators[..2]);
+    assert_eq!(today[0].top_links, report[3].top_links[..2]);
+}
+
+async fn get_report(
+    state: &Arc<AppState>,
+    is_admin: bool,
+    days: Option<i64>,
+    format: Option<&str>,
+) -> Result<Response, axum::http::StatusCode> {
+    activity_report(
+        State(Arc::clone(state)),
+        Extension(Some(AuthClaims(Arc::new(
+            json!({ "sub": "root", "is_admin": is_admin }),
+        )))),
+        Query(ActivityReportQuery {
+            days,
+            format: format.map(str::to_string),
+        }),
+    )
+    .await
+    .map_err(|error| error.status_code())
+}
+
+async fn body(response: Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_activity_report_endpoint_is_admin_only_with_csv() {
+    let (storage, clock, seeded) = seeded_storage().await;
+    let config = Arc::new(common::test_config());
+    let state = Arc::new(AppState {
+        storage,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: clock.clone(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+
+    assert_eq!(
+        get_report(&state, false, None, None).await.unwrap_err(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        get_report(&state, true, None, Some("xml"))
+            .await
+            .unwrap_err(),
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    // The window ends with today, which is still running
+    let response = get_report(&state, true, Some(4), None).await.unwrap();
+    let report: serde_json::Value = serde_json::from_str(&body(response).await).unwrap();
+    assert_eq!(report["days"], 4);
+    assert_eq!(report["top"], 5);
+    assert_eq!(
+        serde_json::from_value::<Vec<ActivityDay>>(report["activity"].clone()).unwrap(),
+        expected(&seeded)
+    );
+
+    let response = get_report(&state, true, None, None).await.unwrap();
+    let report: serde_json::Value = serde_json::from_str(&body(response).await).unwrap();
+    assert_eq!(report["days"], 30);
+    let activity = report["activity"].as_array().unwrap();
+    assert_eq!(activity.len(), 30);
+    assert_eq!(activity[29]["day"], DAY);
+
+    let response = get_report(&state, true, Some(2), Some("csv"))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let csv = body(response).await;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), ACTIVITY_CSV_HEADER.lines().next());
+    assert_eq!(
+        lines.next(),
+        Some("1678320000,2023-03-09T00:00:00Z,3,5,5,early:2,early-0:4;anon:1")
+    );
+    assert!(lines.next().unwrap().starts_with(&format!(
+        "{DAY},2023-03-10T00:00:00Z,8,{},{},user1:3;user2:1;",
+        seeded.total_clicks(),
+        seeded.total_visits()
+    )));
+    assert_eq!(lines.next(), None);
+}
@@ -0,0 +1,279 @@
+//! Integration tests for link aliases: `POST /api/links/{code}/aliases` and
+//! `DELETE /api/links/{code}/aliases/{alias}`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::analytics::{AnalyticsAggregator, AnalyticsGroupBy, AnalyticsRollup};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::redirect::{self, RedirectAnalytics};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10))
+}
+
+async fn create_test_app(storage: &Arc<CachedStorage>) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    )
+}
+
+async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    let body = match body {
+        Some(body) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn encode(code: &str) -> String {
+    URL_SAFE_NO_PAD.encode(code)
+}
+
+async fn add_alias(app: &Router, code: &str, alias: &str) -> StatusCode {
+    call(
+        app,
+        "POST",
+        &format!("/api/links/{}/aliases", encode(code)),
+        Some(json!({ "code": alias })),
+    )
+    .await
+    .0
+}
+
+async fn redirect_status(redirects: &Router, code: &str) -> StatusCode {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    redirects.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_alias_visits_count_toward_the_link_and_break_down_by_alias() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    assert_eq!(add_alias(&app, "docs", "manual").await, StatusCode::CREATED);
+    assert_eq!(add_alias(&app, "docs", "guide").await, StatusCode::CREATED);
+
+    let aggregator = Arc::new(AnalyticsAggregator::new());
+    let flush_storage = Arc::clone(&storage);
+    let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
+        let storage = Arc::clone(&flush_storage);
+        Box::pin(async move {
+            let records = entries
+                .into_iter()
+                .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                .collect();
+            storage.upsert_known_analytics_batch(records).await?;
+            Ok(())
+        })
+    });
+    let analytics = RedirectAnalytics::from_enabled(
+        lynx::config::AnalyticsConfig {
+            enabled: true,
+            ..lynx::config::AnalyticsConfig::default()
+        },
+        Arc::clone(&aggregator),
+    )
+    .unwrap();
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        Some(analytics),
+        false,
+        StatusCode::FOUND,
+    );
+
+    for code in ["docs", "manual", "manual", "guide", "guide", "guide"] {
+        assert_eq!(redirect_status(&redirects, code).await, StatusCode::FOUND);
+    }
+
+    let by_alias = |aggregates: Vec<(String, i64)>| {
+        let mut aggregates = aggregates;
+        aggregates.sort();
+        aggregates
+    };
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+    storage.flush().await.unwrap();
+
+    let docs = storage.get_authoritative("docs").await.unwrap().unwrap();
+    assert_eq!(docs.clicks, 6);
+    let visits: i64 = storage
+        .get_analytics("docs", None, None, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|entry| entry.visit_count)
+        .sum();
+    assert_eq!(visits, 6);
+    assert!(storage
+        .get_analytics("manual", None, None, 100)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let aggregates = storage
+        .get_analytics_aggregate("docs", None, None, AnalyticsGroupBy::AliasUsed, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        by_alias(
+            aggregates
+                .into_iter()
+                .map(|aggregate| (aggregate.dimension, aggregate.visit_count))
+                .collect()
+        ),
+        vec![("guide".to_string(), 3), ("manual".to_string(), 2)]
+    );
+}
+
+#[tokio::test]
+async fn test_removing_an_alias_or_deactivating_the_link() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        None,
+        false,
+        StatusCode::FOUND,
+    );
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    for alias in ["one", "two"] {
+        assert_eq!(add_alias(&app, "docs", alias).await, StatusCode::CREATED);
+        assert_eq!(redirect_status(&redirects, alias).await, StatusCode::FOUND);
+    }
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/api/links/{}/aliases/{}", encode("docs"), encode("one")),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(redirect_status(&redirects, "one").await, StatusCode::GONE);
+    assert_eq!(redirect_status(&redirects, "two").await, StatusCode::FOUND);
+    assert_eq!(redirect_status(&redirects, "docs").await, StatusCode::FOUND);
+
+    // The removed alias is gone from the listing; its code stays taken.
+    let (_, json) = call(&app, "GET", "/api/urls", None).await;
+    let urls = json["urls"].as_array().unwrap();
+    assert_eq!(urls.len(), 1);
+    let aliases: Vec<&str> = urls[0]["aliases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|alias| alias["short_code"].as_str().unwrap())
+        .collect();
+    assert_eq!(aliases, vec!["two"]);
+    assert_eq!(add_alias(&app, "docs", "one").await, StatusCode::CONFLICT);
+
+    let (status, _) = call(
+        &app,
+        "PUT",
+        &format!("/api/urls/{}/deactivate", encode("docs")),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(redirect_status(&redirects, "two").await, StatusCode::GONE);
+    assert_eq!(redirect_status(&redirects, "docs").await, StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_aliases_cannot_chain_and_must_belong_to_the_link() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    for code in ["docs", "blog"] {
+        storage
+            .create_with_code(code, &format!("https://example.com/{code}"), None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(add_alias(&app, "docs", "manual").await, StatusCode::CREATED);
+
+    assert_eq!(
+        add_alias(&app, "manual", "chained").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(add_alias(&app, "docs", "blog").await, StatusCode::CONFLICT);
+    assert_eq!(
+        add_alias(&app, "missing", "other").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(add_alias(&app, "docs", "").await, StatusCode::BAD_REQUEST);
+
+    let (status, _) = call(
+        &app,
+        "DELETE",
+        &format!("/api/links/{}/aliases/{}", encode("blog"), encode("manual")),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Searching for the alias finds the link it belongs to.
+    let (status, json) = call(&app, "GET", "/api/urls/search?q=manual", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let items = json["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["short_code"], "docs");
+    assert_eq!(items[0]["aliases"][0]["short_code"], "manual");
+}
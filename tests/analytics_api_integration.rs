@@ -3,6 +3,7 @@
 //! These tests verify that the analytics API endpoints work correctly end-to-end,
 //! including the near real-time analytics feature that combines database and
 //! in-memory data.
+#![cfg(feature = "sqlite")]
 
 use axum::{
     body::Body,
@@ -13,10 +14,13 @@ use lynx::analytics::{AnalyticsAggregator, AnalyticsGroupBy, AnalyticsRollup, Ip
 use lynx::auth::AuthService;
 use lynx::config::{AuthConfig, AuthMode, Config};
 use lynx::storage::{SqliteStorage, Storage};
+use lynx::testing::Fixture;
 use serde_json::Value;
 use std::sync::Arc;
 use tower::ServiceExt;
 
+mod common;
+
 /// Build an analytics rollup row for tests. `ip_version` is fixed to IPv4,
 /// which is what every fixture below exercises.
 fn rollup(
@@ -37,6 +41,8 @@ fn rollup(
         asn,
         ip_version: IpVersion::V4,
         visit_count,
+        estimated_visits: 0,
+        alias_used: None,
     }
 }
 
@@ -52,36 +58,6 @@ fn create_test_config() -> Arc<Config> {
     use lynx::config::*;
 
     Arc::new(Config {
-        database: DatabaseConfig {
-            backend: DatabaseBackend::Sqlite,
-            url: "sqlite::memory:".to_string(),
-            max_connections: 5,
-        },
-        api_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 8080,
-        },
-        redirect_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 3000,
-        },
-        redirect_base_url: "http://localhost:3000".to_string(),
-        auth: AuthConfig {
-            mode: AuthMode::None,
-            oauth: None,
-            cloudflare: None,
-        },
-        frontend: FrontendConfig { static_dir: None },
-        cache: CacheConfig {
-            max_entries: 10000,
-            flush_interval_secs: 5,
-            actor_buffer_size: 100000,
-            actor_flush_interval_ms: 100,
-        },
-        pagination: PaginationConfig {
-            cursor_hmac_secret: None,
-        },
-        short_code_max_length: 50,
         analytics: AnalyticsConfig {
             enabled: false,
             geoip_city_db_path: None,
@@ -91,8 +67,10 @@ fn create_test_config() -> Arc<Config> {
             trusted_proxies: vec![],
             num_trusted_proxies: None,
             flush_interval_secs: 30,
+            daily_rollup_after_days: 30,
+            sampling: Default::default(),
         },
-        redirect_status: RedirectMode::default(),
+        ..common::test_config()
     })
 }
 
@@ -375,6 +353,8 @@ async fn test_analytics_aggregate_with_unknown_pending_events() {
             short_code: "pending".into(),
             timestamp: chrono::Utc::now().timestamp(),
             client_ip: "8.8.8.8".parse::<IpAddr>().unwrap(),
+            alias_used: None,
+            weight: 1,
         };
         aggregator.record_event(event);
     }
@@ -580,3 +560,329 @@ async fn test_analytics_aggregate_group_by_region() {
         .unwrap();
     assert_eq!(ny_agg["visit_count"], 4);
 }
+
+#[tokio::test]
+async fn test_analytics_aggregate_group_by_values() {
+    let storage = create_test_storage().await;
+    let auth_service = create_test_auth_service().await;
+    let config = create_test_config();
+
+    storage
+        .create_with_code("grouped", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![rollup(
+            "grouped",
+            1698768000,
+            Some("US"),
+            Some("CA"),
+            Some("LA"),
+            Some(15169),
+            3,
+        )])
+        .await
+        .unwrap();
+
+    let app = lynx::api::create_api_router(Arc::clone(&storage), auth_service, config, None);
+
+    for group_by in AnalyticsGroupBy::ALL {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/api/analytics/{}/aggregate?group_by={}",
+                        encoded_code("grouped"),
+                        group_by.as_str()
+                    ))
+                    .header(header::AUTHORIZATION, "Bearer test-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "group_by={:?}", group_by);
+    }
+
+    // Unknown dimensions are rejected rather than silently grouped by country
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/analytics/{}/aggregate?group_by=regoin",
+                    encoded_code("grouped")
+                ))
+                .header(header::AUTHORIZATION, "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].as_str().unwrap().contains("regoin"));
+}
+
+#[tokio::test]
+async fn test_analytics_export_streams_csv_per_link_and_per_user() {
+    let storage = create_test_storage().await;
+    let auth_service = create_test_auth_service().await;
+    let config = create_test_config();
+
+    // AUTH_MODE=none signs every request in as this user
+    let caller = "00000000-0000-0000-0000-000000000000";
+    storage
+        .create_with_code("exported", "https://example.com", Some(caller))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("elsewhere", "https://example.com", Some("user2"))
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup(
+                "exported",
+                1698768000,
+                Some("US"),
+                Some("DC"),
+                Some("Washington, D.C."),
+                Some(15169),
+                5,
+            ),
+            rollup("exported", 1698771600, Some("GB"), None, None, None, 2),
+            rollup("elsewhere", 1698768000, Some("FR"), None, None, None, 1),
+        ])
+        .await
+        .unwrap();
+
+    let app = lynx::api::create_api_router(Arc::clone(&storage), auth_service, config, None);
+    let get = |uri: String, gzip: bool| {
+        let app = app.clone();
+        async move {
+            let mut request = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, "Bearer test-token");
+            if gzip {
+                request = request.header(header::ACCEPT_ENCODING, "gzip");
+            }
+            app.oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+        }
+    };
+
+    let response = get(
+        format!(
+            "/api/links/{}/analytics/export?format=csv",
+            encoded_code("exported")
+        ),
+        false,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"exported-analytics.csv\""
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let csv = String::from_utf8(body.to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3, "{csv}");
+    assert_eq!(
+        lines[0],
+        "short_code,time_bucket,time_bucket_iso,country_code,region,city,asn,ip_version,visit_count,created_at,created_at_iso,updated_at,updated_at_iso"
+    );
+    assert!(lines[1].starts_with(
+        "exported,1698768000,2023-10-31T16:00:00Z,US,DC,\"Washington, D.C.\",15169,4,5,"
+    ));
+    assert!(lines[2].starts_with("exported,1698771600,2023-10-31T17:00:00Z,GB,,,,4,2,"));
+
+    // The caller's export covers their links only
+    let response = get(
+        "/api/me/analytics/export?start=1698768000&end=1698768000".to_string(),
+        false,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let csv = String::from_utf8(body.to_vec()).unwrap();
+    let codes: Vec<&str> = csv
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap())
+        .collect();
+    assert_eq!(codes, ["exported"]);
+
+    // Gzipped on request
+    let response = get("/api/me/analytics/export".to_string(), true).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..2], &[0x1f, 0x8b]);
+
+    let response = get(
+        format!(
+            "/api/links/{}/analytics/export?format=xlsx",
+            encoded_code("exported")
+        ),
+        false,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_analytics_responses_flag_pruned_data() {
+    let storage = create_test_storage().await;
+    let auth_service = create_test_auth_service().await;
+    let config = create_test_config();
+
+    storage
+        .create_with_code("pruned", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    let now = chrono::Utc::now().timestamp();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("pruned", now - 40 * 86400, Some("US"), None, None, None, 4),
+            rollup("pruned", now, Some("GB"), None, None, None, 1),
+        ])
+        .await
+        .unwrap();
+
+    let app = lynx::api::create_api_router(Arc::clone(&storage), auth_service, config, None);
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header(header::AUTHORIZATION, "Bearer test-token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        }
+    };
+    let aggregate_uri = format!("/api/analytics/{}/aggregate", encoded_code("pruned"));
+
+    // Nothing pruned yet: every row is at full detail
+    let json = get(aggregate_uri.clone()).await;
+    assert!(json["data_complete_since"].is_null());
+    assert!(json["aggregates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|row| row["aggregated"] == false));
+
+    storage
+        .prune_analytics(30, &["country".to_string()])
+        .await
+        .unwrap();
+
+    let json = get(aggregate_uri).await;
+    let since = json["data_complete_since"].as_i64().unwrap();
+    assert!(since > now - 31 * 86400 && since <= now - 30 * 86400 + 3600);
+    let rows = json["aggregates"].as_array().unwrap();
+    let dropped = rows
+        .iter()
+        .find(|row| row["dimension"] == "<dropped>")
+        .unwrap();
+    assert_eq!(dropped["aggregated"], true);
+    assert_eq!(dropped["visit_count"], 4);
+    let detailed = rows.iter().find(|row| row["dimension"] == "GB").unwrap();
+    assert_eq!(detailed["aggregated"], false);
+
+    let json = get(format!("/api/analytics/{}", encoded_code("pruned"))).await;
+    assert_eq!(json["data_complete_since"], since);
+}
+
+#[tokio::test]
+async fn test_analytics_responses_report_estimated_visits() {
+    let storage = create_test_storage().await;
+    let auth_service = create_test_auth_service().await;
+    let config = create_test_config();
+
+    Fixture::new(Arc::clone(&storage)).seed().await.unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            AnalyticsRollup {
+                estimated_visits: 8,
+                ..rollup("user1-0", 3_600, Some("US"), None, None, None, 9)
+            },
+            rollup("user1-0", 7_200, Some("GB"), None, None, None, 2),
+        ])
+        .await
+        .unwrap();
+
+    let aggregator = Arc::new(AnalyticsAggregator::new().with_sampling(
+        &lynx::config::AnalyticsSamplingConfig {
+            floor_rate: Some(0.1),
+            ..Default::default()
+        },
+        lynx::clock::system_clock(),
+    ));
+    let app =
+        lynx::api::create_api_router(Arc::clone(&storage), auth_service, config, Some(aggregator));
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header(header::AUTHORIZATION, "Bearer test-token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        }
+    };
+
+    let json = get(format!("/api/analytics/{}", encoded_code("user1-0"))).await;
+    assert_eq!(json["estimated_visits"], 8);
+    let json = get(format!(
+        "/api/analytics/{}/aggregate?start_time=7200",
+        encoded_code("user1-0")
+    ))
+    .await;
+    assert_eq!(json["estimated_visits"], 0);
+
+    // An idle queue is not being sampled
+    let json = get("/api/stats/analytics".to_string()).await;
+    assert_eq!(json["queue_capacity"], 100_000);
+    assert_eq!(json["sampling"]["enabled"], true);
+    assert_eq!(json["sampling"]["active"], false);
+    assert_eq!(json["sampling"]["one_in"], 10);
+    assert_eq!(json["sampling"]["high_water"], 80_000);
+    assert_eq!(json["sampling"]["low_water"], 50_000);
+}
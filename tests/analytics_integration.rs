@@ -3,6 +3,7 @@
 //! These tests verify the analytics system works end-to-end with real GeoIP databases
 //! when available. Tests will be skipped if databases cannot be downloaded (e.g., in
 //! restricted CI environments).
+#![cfg(all(feature = "sqlite", feature = "analytics"))]
 
 use lynx::analytics::{AnalyticsAggregator, AnalyticsGroupBy, AnalyticsRollup, GeoIpService};
 use lynx::storage::{SqliteStorage, Storage};
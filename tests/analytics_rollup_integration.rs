@@ -0,0 +1,159 @@
+//! Integration tests for the daily analytics rollup: country and day
+//! aggregates read whole rolled-up days from `analytics_daily` and must add
+//! up to exactly what the hourly rows alone give.
+//!
+//! Like `storage_integration_test`, `DATABASE_BACKEND` picks the backend and
+//! the Postgres variant needs `DATABASE_URL`.
+#![cfg(feature = "sqlite")]
+
+use lynx::analytics::daily::DAY_SECS;
+use lynx::analytics::{AnalyticsAggregate, AnalyticsGroupBy, AnalyticsRollup, IpVersion};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// 2024-01-01T00:00:00Z. The rollup watermark is shared by every link in a
+/// database, so the data sits on fixed days that reruns roll up the same way.
+const DAY_0: i64 = 1_704_067_200;
+const DAYS: i64 = 10;
+const ROLLED_DAYS: i64 = 6;
+
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(selected) => selected == backend,
+        Err(_) => true,
+    }
+}
+
+/// Hourly rows over `DAYS` days, a few hours a day in three countries
+fn synthetic_rows(short_code: &str) -> Vec<AnalyticsRollup> {
+    let countries = [Some("US"), Some("DE"), None];
+    (0..DAYS * 24)
+        .step_by(5)
+        .enumerate()
+        .map(|(i, hour)| AnalyticsRollup {
+            short_code: short_code.to_string(),
+            time_bucket: DAY_0 + hour * 3600,
+            country_code: countries[i % countries.len()].map(str::to_string),
+            region: None,
+            city: None,
+            asn: None,
+            ip_version: IpVersion::V4,
+            visit_count: 1 + (i as i64 * 7) % 11,
+            estimated_visits: 0,
+            alias_used: None,
+        })
+        .collect()
+}
+
+fn totals(aggregates: Vec<AnalyticsAggregate>) -> BTreeMap<String, i64> {
+    aggregates
+        .into_iter()
+        .map(|aggregate| (aggregate.dimension, aggregate.visit_count))
+        .collect()
+}
+
+/// Country totals, day totals and UTC daily totals for each range
+async fn snapshot(
+    storage: &dyn Storage,
+    short_code: &str,
+    ranges: &[(Option<i64>, Option<i64>)],
+) -> Vec<BTreeMap<String, i64>> {
+    let mut snapshot = Vec::new();
+    for &(start, end) in ranges {
+        for group_by in [AnalyticsGroupBy::Country, AnalyticsGroupBy::Day] {
+            let aggregates = storage
+                .get_analytics_aggregate(short_code, start, end, group_by, 1000)
+                .await
+                .unwrap();
+            snapshot.push(totals(aggregates));
+        }
+        let daily = storage
+            .get_analytics_daily_aggregate(short_code, start, end, chrono_tz::UTC, 1000)
+            .await
+            .unwrap();
+        snapshot.push(totals(daily));
+    }
+    snapshot
+}
+
+async fn assert_rollup_matches_hourly_totals(storage: Arc<dyn Storage>, prefix: &str) {
+    let code = format!("{prefix}_rollup");
+    storage
+        .create_with_code(&code, "https://example.com/rollup", Some("owner"))
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(synthetic_rows(&code))
+        .await
+        .unwrap();
+
+    let hour = 3600;
+    let ranges = [
+        (None, None),
+        // Partial days at both ends, across the watermark
+        (
+            Some(DAY_0 + 5 * hour),
+            Some(DAY_0 + 7 * DAY_SECS + 3 * hour),
+        ),
+        // Whole rolled-up days only
+        (Some(DAY_0 + 2 * DAY_SECS), Some(DAY_0 + 5 * DAY_SECS - 1)),
+        // Within one day, and after the watermark
+        (
+            Some(DAY_0 + DAY_SECS + hour),
+            Some(DAY_0 + DAY_SECS + 20 * hour),
+        ),
+        (Some(DAY_0 + 8 * DAY_SECS), None),
+    ];
+    let hourly = snapshot(storage.as_ref(), &code, &ranges).await;
+    assert!(hourly[0].values().sum::<i64>() > 0);
+
+    // Rebuild from the first day so reruns on a shared database start over
+    let watermark = DAY_0 + ROLLED_DAYS * DAY_SECS;
+    let rows = storage
+        .rollup_analytics_daily(Some(DAY_0), watermark + hour)
+        .await
+        .unwrap();
+    assert!(rows > 0);
+    assert!(storage.analytics_daily_rolled_until().await.unwrap() >= Some(watermark));
+    assert_eq!(snapshot(storage.as_ref(), &code, &ranges).await, hourly);
+
+    // Rolling the same days up again does not count them twice
+    storage
+        .rollup_analytics_daily(Some(DAY_0 + 2 * DAY_SECS), watermark)
+        .await
+        .unwrap();
+    assert_eq!(snapshot(storage.as_ref(), &code, &ranges).await, hourly);
+}
+
+#[tokio::test]
+async fn test_rollup_matches_hourly_totals_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    assert_eq!(storage.analytics_daily_rolled_until().await.unwrap(), None);
+    assert_rollup_matches_hourly_totals(Arc::new(storage), "lite").await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_rollup_matches_hourly_totals_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let storage = PostgresStorage::new(&db_url, 5).await.unwrap();
+    storage.init().await.unwrap();
+
+    let prefix = format!("pg_rollup_{}", std::process::id());
+    assert_rollup_matches_hourly_totals(Arc::new(storage), &prefix).await;
+}
@@ -3,12 +3,15 @@
 //! These tests ensure the analytics system maintains data consistency and doesn't lose data
 //! under various challenging conditions including concurrent operations, rapid flush cycles,
 //! and edge cases.
+#![cfg(feature = "sqlite")]
 
 use lynx::analytics::{
     AnalyticsAggregator, AnalyticsEvent, AnalyticsGroupBy, AnalyticsRecord, AnalyticsRollup,
     GeoLocation, IpVersion,
 };
-use lynx::storage::{PostgresStorage, SqliteStorage, Storage};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 
@@ -32,6 +35,8 @@ fn rollup(
         asn,
         ip_version: IpVersion::V4,
         visit_count,
+        estimated_visits: 0,
+        alias_used: None,
     }
 }
 
@@ -43,15 +48,15 @@ async fn create_test_storage() -> Arc<dyn Storage> {
 }
 
 async fn create_backend_storage() -> Arc<dyn Storage> {
+    #[cfg(feature = "postgres")]
     if std::env::var("DATABASE_BACKEND").as_deref() == Ok("postgres") {
         let url = std::env::var("DATABASE_URL")
             .expect("DATABASE_URL must be set for PostgreSQL integration tests");
         let storage = PostgresStorage::new(&url, 5).await.unwrap();
         storage.init().await.unwrap();
-        Arc::new(storage)
-    } else {
-        create_test_storage().await
+        return Arc::new(storage);
     }
+    create_test_storage().await
 }
 
 #[tokio::test]
@@ -81,6 +86,8 @@ async fn test_graceful_shutdown_persists_saturated_analytics_exactly() {
             short_code: code.as_str().into(),
             timestamp: 1_000_000,
             client_ip: "127.0.0.1".parse().unwrap(),
+            alias_used: None,
+            weight: 1,
         });
     }
     aggregator.shutdown().await;
@@ -108,7 +115,9 @@ async fn test_concurrent_event_recording() {
                 let event = AnalyticsEvent {
                     short_code: format!("code{}", task_id % 3).into(),
                     client_ip: "192.168.1.1".parse().unwrap(),
+                    alias_used: None,
                     timestamp: 1000000 + i,
+                    weight: 1,
                 };
                 agg_clone.record_event(event);
             }
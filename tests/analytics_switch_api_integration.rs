@@ -0,0 +1,277 @@
+//! Integration tests for `POST /api/admin/analytics/enabled`: switching
+//! analytics off stops recording at once while redirects keep working, the
+//! flush task writes out what was pending, the switch is saved in `settings`
+//! and reported at `GET /api/admin/info`, and switching back on resumes.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::analytics::{
+    AnalyticsAggregator, AnalyticsGroupBy, AnalyticsRollup, ANALYTICS_ENABLED_SETTING,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AnalyticsConfig, AuthConfig, AuthMode, Config};
+use lynx::redirect::{self, RedirectAnalytics};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+mod common;
+
+const CODE: &str = "docs";
+
+fn analytics_config() -> Config {
+    Config {
+        analytics: AnalyticsConfig {
+            enabled: true,
+            ..AnalyticsConfig::default()
+        },
+        ..common::test_config()
+    }
+}
+
+async fn create_storage() -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let storage = Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10));
+    storage
+        .create_with_code(CODE, "https://example.com/", None)
+        .await
+        .unwrap();
+    storage
+}
+
+async fn create_api(
+    storage: &Arc<CachedStorage>,
+    aggregator: Option<Arc<AnalyticsAggregator>>,
+) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        Arc::new(analytics_config()),
+        aggregator,
+    )
+}
+
+/// Start an aggregator whose flush task writes to `storage` only when
+/// drained, so every stored visit was written by a drain.
+fn start_aggregator(
+    storage: &Arc<CachedStorage>,
+    enabled: bool,
+) -> (Arc<AnalyticsAggregator>, tokio::task::JoinHandle<()>) {
+    let aggregator = Arc::new(AnalyticsAggregator::new());
+    aggregator.set_enabled(enabled);
+    let flush_storage = Arc::clone(storage);
+    let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
+        let storage = Arc::clone(&flush_storage);
+        Box::pin(async move {
+            let records = entries
+                .into_iter()
+                .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                .collect();
+            storage.upsert_known_analytics_batch(records).await?;
+            Ok(())
+        })
+    });
+    (aggregator, flush_handle)
+}
+
+async fn visit(redirects: &Router) {
+    let mut request = Request::builder()
+        .uri(format!("/{CODE}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 40_000))));
+    let status = redirects.clone().oneshot(request).await.unwrap().status();
+    assert_eq!(status, StatusCode::FOUND);
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn switch(app: &Router, enabled: bool) {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/api/admin/analytics/enabled",
+        Some(json!({ "enabled": enabled })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "enabled": enabled, "persisted": true }));
+}
+
+async fn stored_visits(storage: &CachedStorage) -> i64 {
+    storage
+        .get_analytics_aggregate(CODE, None, None, AnalyticsGroupBy::Hour, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|aggregate| aggregate.visit_count)
+        .sum()
+}
+
+fn pending_visits(aggregator: &AnalyticsAggregator) -> i64 {
+    aggregator
+        .get_in_memory_aggregate(CODE, AnalyticsGroupBy::Hour)
+        .iter()
+        .map(|(_, count)| count)
+        .sum()
+}
+
+#[tokio::test]
+async fn test_switching_analytics_off_mid_traffic_records_nothing_until_back_on() {
+    let storage = create_storage().await;
+    let (aggregator, flush_handle) = start_aggregator(&storage, true);
+    let app = create_api(&storage, Some(Arc::clone(&aggregator))).await;
+    let analytics =
+        RedirectAnalytics::from_enabled(analytics_config().analytics, Arc::clone(&aggregator))
+            .unwrap();
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        Some(analytics),
+        false,
+        StatusCode::FOUND,
+    );
+
+    // Steady traffic across the switch
+    let stop = Arc::new(AtomicBool::new(false));
+    let served = Arc::new(AtomicU64::new(0));
+    let traffic = tokio::spawn({
+        let (redirects, stop, served) = (redirects.clone(), Arc::clone(&stop), Arc::clone(&served));
+        async move {
+            while !stop.load(Ordering::Relaxed) {
+                visit(&redirects).await;
+                served.fetch_add(1, Ordering::Relaxed);
+                tokio::task::yield_now().await;
+            }
+        }
+    });
+    while served.load(Ordering::Relaxed) < 50 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    // Long enough for the actor to hand the first visits to the flush task
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    switch(&app, false).await;
+    let (_, info) = send(&app, "GET", "/api/admin/info", None).await;
+    assert_eq!(info["analytics"]["enabled"], json!(true));
+    assert_eq!(info["analytics"]["recording"], json!(false));
+    assert_eq!(
+        storage
+            .get_setting(ANALYTICS_ENABLED_SETTING)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("false")
+    );
+
+    // Visits already queued reach the aggregator, and switching off wrote
+    // them out without waiting for the hour-long flush interval
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let recorded_before_off = stored_visits(&storage).await + pending_visits(&aggregator);
+    assert!(recorded_before_off >= 50, "recorded {recorded_before_off}");
+    assert!(stored_visits(&storage).await > 0);
+
+    let served_while_off = served.load(Ordering::Relaxed);
+    while served.load(Ordering::Relaxed) < served_while_off + 100 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    stop.store(true, Ordering::Relaxed);
+    traffic.await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(
+        stored_visits(&storage).await + pending_visits(&aggregator),
+        recorded_before_off,
+        "no visit may be recorded while analytics is off"
+    );
+
+    switch(&app, true).await;
+    let (_, info) = send(&app, "GET", "/api/admin/info", None).await;
+    assert_eq!(info["analytics"]["recording"], json!(true));
+    for _ in 0..5 {
+        visit(&redirects).await;
+    }
+
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+    assert_eq!(stored_visits(&storage).await, recorded_before_off + 5);
+}
+
+#[tokio::test]
+async fn test_switching_analytics_needs_analytics_enabled() {
+    let storage = create_storage().await;
+    let app = create_api(&storage, None).await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/api/admin/analytics/enabled",
+        Some(json!({ "enabled": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        storage
+            .get_setting(ANALYTICS_ENABLED_SETTING)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_flush_task_started_while_off_waits_for_the_switch() {
+    let storage = create_storage().await;
+    // As after a restart with the switch saved off
+    let (aggregator, flush_handle) = start_aggregator(&storage, false);
+    let app = create_api(&storage, Some(Arc::clone(&aggregator))).await;
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        RedirectAnalytics::from_enabled(analytics_config().analytics, Arc::clone(&aggregator)),
+        false,
+        StatusCode::FOUND,
+    );
+
+    visit(&redirects).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(pending_visits(&aggregator), 0);
+
+    switch(&app, true).await;
+    visit(&redirects).await;
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+    assert_eq!(stored_visits(&storage).await, 1);
+}
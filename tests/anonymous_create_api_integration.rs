@@ -0,0 +1,359 @@
+//! Integration tests for anonymous link creation and the moderation queue
+//!
+//! `POST /api/public/urls` only exists with `ALLOW_ANONYMOUS_CREATE`, never
+//! accepts custom codes and is limited per client IP. With
+//! `ANONYMOUS_CREATE_REQUIRE_APPROVAL` new links stay inactive until an admin
+//! approves them under `/api/moderation/links`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use base64::prelude::*;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AnonymousCreateConfig, Config};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tower::ServiceExt;
+
+mod common;
+
+const SECRET: &[u8] = b"anonymous-create-test-secret";
+const KID: &str = "test-key";
+const ISSUER: &str = "https://issuer.example.com";
+const AUDIENCE: &str = "lynx";
+
+/// Serve a JWKS document holding the HMAC test key; returns its URL.
+async fn spawn_jwks_server() -> String {
+    let jwks = json!({
+        "keys": [{ "kid": KID, "kty": "oct", "alg": "HS256", "k": BASE64_STANDARD.encode(SECRET) }]
+    });
+    let app = Router::new().route("/jwks", get(move || async move { Json(jwks) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/jwks", addr)
+}
+
+/// Helper to create test config
+fn create_test_config(jwks_url: String, anonymous_create: AnonymousCreateConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        auth: AuthConfig {
+            mode: AuthMode::Oauth,
+            oauth: Some(OAuthConfig {
+                issuer_url: ISSUER.to_string(),
+                audience: AUDIENCE.to_string(),
+                client_id: AUDIENCE.to_string(),
+                scopes: "openid".to_string(),
+                redirect_uri: "http://localhost:8080/auth/callback".to_string(),
+                jwks_url: Some(jwks_url),
+                jwks_cache_ttl_secs: 300,
+                clock_skew_secs: 30,
+                allow_anonymous_subject: false,
+            }),
+            cloudflare: None,
+        },
+        anonymous_create,
+        ..common::test_config()
+    })
+}
+
+async fn create_test_api(anonymous_create: AnonymousCreateConfig) -> (Router, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let config = create_test_config(spawn_jwks_server().await, anonymous_create);
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    let app = api::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        config,
+        None,
+    );
+    (app, storage)
+}
+
+fn enabled(rate_limit_per_minute: u32, require_approval: bool) -> AnonymousCreateConfig {
+    AnonymousCreateConfig {
+        enabled: true,
+        rate_limit_per_minute,
+        require_approval,
+    }
+}
+
+/// A correctly signed, unexpired token for `sub`, optionally an admin.
+fn token(sub: &str, admin: bool) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let roles: Vec<&str> = if admin { vec!["admin"] } else { vec![] };
+    let body = json!({
+        "iss": ISSUER,
+        "aud": AUDIENCE,
+        "iat": now,
+        "exp": now + 300,
+        "sub": sub,
+        "roles": roles,
+    });
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(KID.to_string());
+    encode(&header, &body, &EncodingKey::from_secret(SECRET)).unwrap()
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    (status, json)
+}
+
+/// `POST /api/public/urls` from `peer` without credentials
+async fn create_anonymous(app: &Router, peer: &str, body: Value) -> (StatusCode, Value) {
+    let peer: SocketAddr = format!("{}:40000", peer).parse().unwrap();
+    send(
+        app,
+        Request::builder()
+            .method("POST")
+            .uri("/api/public/urls")
+            .header(header::CONTENT_TYPE, "application/json")
+            .extension(ConnectInfo(peer))
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await
+}
+
+async fn moderate(
+    app: &Router,
+    token: &str,
+    method: &str,
+    path: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let builder = Request::builder()
+        .method(method)
+        .uri(format!("/api/moderation/links{}", path))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    };
+    send(app, request.unwrap()).await
+}
+
+fn encoded(code: &str) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(code)
+}
+
+#[tokio::test]
+async fn test_anonymous_create_is_off_by_default() {
+    let (app, storage) = create_test_api(AnonymousCreateConfig::default()).await;
+
+    let (status, _) =
+        create_anonymous(&app, "192.0.2.1", json!({ "url": "https://example.com" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(storage
+        .list_user_links("anonymous", 10, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_anonymous_links_get_generated_codes_only() {
+    let (app, storage) = create_test_api(enabled(0, false)).await;
+
+    let (status, _) = create_anonymous(
+        &app,
+        "192.0.2.1",
+        json!({ "url": "https://example.com", "custom_code": "mine" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(storage.get_authoritative("mine").await.unwrap().is_none());
+
+    let (status, body) =
+        create_anonymous(&app, "192.0.2.1", json!({ "url": "https://example.com" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["moderation_status"], "approved");
+    assert_eq!(body["is_active"], true);
+
+    let code = body["short_code"].as_str().unwrap();
+    let link = storage.get_authoritative(code).await.unwrap().unwrap();
+    assert_eq!(link.created_by.as_deref(), Some("anonymous"));
+    assert!(link.is_active);
+}
+
+#[tokio::test]
+async fn test_anonymous_create_is_rate_limited_per_ip() {
+    let (app, _storage) = create_test_api(enabled(2, false)).await;
+    let body = json!({ "url": "https://example.com" });
+
+    for _ in 0..2 {
+        let (status, _) = create_anonymous(&app, "192.0.2.1", body.clone()).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    let (status, _) = create_anonymous(&app, "192.0.2.1", body.clone()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Another client has its own window.
+    let (status, _) = create_anonymous(&app, "192.0.2.2", body).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_pending_links_wait_for_an_admin() {
+    let (app, storage) = create_test_api(enabled(0, true)).await;
+    let admin = token("root", true);
+    let user = token("alice", false);
+
+    let (status, body) =
+        create_anonymous(&app, "192.0.2.1", json!({ "url": "https://example.com/a" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["moderation_status"], "pending");
+    assert_eq!(body["is_active"], false);
+    let code = body["short_code"].as_str().unwrap().to_string();
+    assert!(
+        !storage
+            .get_authoritative(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_active,
+        "pending links must not redirect"
+    );
+
+    // Only admins see and decide the queue.
+    for (method, path, body) in [
+        ("GET", String::new(), None),
+        ("POST", format!("/{}/approve", encoded(&code)), None),
+        (
+            "POST",
+            format!("/{}/reject", encoded(&code)),
+            Some(json!({ "reason": "spam" })),
+        ),
+    ] {
+        let (status, _) = moderate(&app, &user, method, &path, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{method} {path}");
+    }
+
+    let (status, body) = moderate(&app, &admin, "GET", "", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "pending");
+    let pending: Vec<&str> = body["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["short_code"].as_str().unwrap())
+        .collect();
+    assert_eq!(pending, vec![code.as_str()]);
+
+    let approve = format!("/{}/approve", encoded(&code));
+    let (status, body) = moderate(&app, &admin, "POST", &approve, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "approved");
+    assert_eq!(body["decided_by"], "root");
+    assert!(
+        storage
+            .get_authoritative(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_active
+    );
+    let (status, _) = moderate(&app, &admin, "POST", &approve, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = moderate(&app, &admin, "GET", "", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["links"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rejected_links_are_deactivated_with_reason() {
+    let (app, storage) = create_test_api(enabled(0, true)).await;
+    let admin = token("root", true);
+
+    let (_, body) =
+        create_anonymous(&app, "192.0.2.1", json!({ "url": "https://example.com/b" })).await;
+    let code = body["short_code"].as_str().unwrap().to_string();
+    let reject = format!("/{}/reject", encoded(&code));
+
+    let (status, _) = moderate(
+        &app,
+        &admin,
+        "POST",
+        &reject,
+        Some(json!({ "reason": " " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = moderate(
+        &app,
+        &admin,
+        "POST",
+        &reject,
+        Some(json!({ "reason": "phishing" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "rejected");
+    assert_eq!(body["reason"], "phishing");
+
+    // The row is kept, inactive, and can no longer be approved.
+    let link = storage.get_authoritative(&code).await.unwrap().unwrap();
+    assert!(!link.is_active);
+    let approve = format!("/{}/approve", encoded(&code));
+    let (status, _) = moderate(&app, &admin, "POST", &approve, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = moderate(&app, &admin, "GET", "?status=rejected", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["links"][0]["short_code"], code.as_str());
+    assert_eq!(body["links"][0]["reason"], "phishing");
+
+    let (status, _) = moderate(&app, &admin, "GET", "?status=bogus", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Links created by signed-in users are not under moderation.
+    storage
+        .create_with_code("regular", "https://example.com", Some("alice"))
+        .await
+        .unwrap();
+    let (status, _) = moderate(
+        &app,
+        &admin,
+        "POST",
+        &format!("/{}/reject", encoded("regular")),
+        Some(json!({ "reason": "spam" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(
+        storage
+            .get_authoritative("regular")
+            .await
+            .unwrap()
+            .unwrap()
+            .is_active
+    );
+}
@@ -0,0 +1,132 @@
+//! Concurrent cache misses for one short code share a single database query,
+//! and its click counts are written by one flush at a time
+#![cfg(feature = "sqlite")]
+
+#[path = "cache_singleflight/slow_storage.rs"]
+mod slow_storage;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use lynx::config::{CacheEvictionPolicy, FlushConfig};
+use lynx::storage::{CachePolicy, CachedStorage, LookupStats, SqliteStorage, Storage};
+use slow_storage::SlowStorage;
+use tokio::task::JoinSet;
+
+const CONCURRENT_GETS: u64 = 500;
+
+async fn slow_cached_storage(
+    delay: Duration,
+    lookup_timeout: Option<Duration>,
+) -> (Arc<SlowStorage>, Arc<CachedStorage>) {
+    let sqlite = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    sqlite.init().await.unwrap();
+    sqlite
+        .create_with_code("hot", "https://example.com/hot", None)
+        .await
+        .unwrap();
+    let slow = Arc::new(SlowStorage::new(sqlite, delay));
+    let policy = CachePolicy {
+        max_entries: 100,
+        max_bytes: None,
+        negative_max_entries: None,
+        eviction_policy: CacheEvictionPolicy::Lru,
+        stale_max_age: None,
+        entry_ttl: None,
+        lookup_timeout,
+    };
+    let cached = CachedStorage::new_with_cache_policy(
+        slow.clone(),
+        policy,
+        3_600,
+        16,
+        3_600_000,
+        FlushConfig::default(),
+    );
+    (slow, Arc::new(cached))
+}
+
+async fn get_concurrently(storage: &Arc<CachedStorage>, code: &str) -> Vec<anyhow::Result<bool>> {
+    let mut gets = JoinSet::new();
+    for _ in 0..CONCURRENT_GETS {
+        let storage = Arc::clone(storage);
+        let code = code.to_string();
+        gets.spawn(async move { storage.get(&code).await.map(|url| url.is_some()) });
+    }
+    gets.join_all().await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_misses_share_one_fetch() {
+    let (slow, storage) = slow_cached_storage(Duration::from_millis(200), None).await;
+
+    let results = get_concurrently(&storage, "hot").await;
+    assert!(results.into_iter().all(|found| found.unwrap()));
+    assert_eq!(slow.gets(), 1);
+    assert_eq!(
+        storage.cache_stats().await.unwrap().lookups,
+        LookupStats {
+            loads: 1,
+            coalesced: CONCURRENT_GETS - 1,
+            timed_out: 0,
+        }
+    );
+
+    // The shared result is cached for later lookups
+    assert!(storage.get("hot").await.unwrap().is_some());
+    assert_eq!(slow.gets(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_misses_of_a_missing_code_share_one_fetch() {
+    let (slow, storage) = slow_cached_storage(Duration::from_millis(200), None).await;
+
+    let results = get_concurrently(&storage, "missing").await;
+    assert!(results.into_iter().all(|found| !found.unwrap()));
+    assert_eq!(slow.gets(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_stuck_fetch_times_out_for_every_waiter() {
+    let (slow, storage) =
+        slow_cached_storage(Duration::from_secs(60), Some(Duration::from_millis(100))).await;
+
+    let results = get_concurrently(&storage, "hot").await;
+    assert!(results.iter().all(Result::is_err));
+    assert_eq!(slow.gets(), 1);
+    let lookups = storage.cache_stats().await.unwrap().lookups;
+    assert_eq!(lookups.loads, 1);
+    assert_eq!(lookups.timed_out, 1);
+
+    // The failure is not cached: the next miss queries again
+    assert!(storage.get("hot").await.is_err());
+    assert_eq!(slow.gets(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_slow_click_flushes_never_overlap() {
+    let sqlite = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    sqlite.init().await.unwrap();
+    sqlite
+        .create_with_code("hot", "https://example.com/hot", None)
+        .await
+        .unwrap();
+    // Each batch takes longer than the one-second flush interval
+    let slow = Arc::new(
+        SlowStorage::new(sqlite.clone(), Duration::ZERO)
+            .with_flush_delay(Duration::from_millis(1_500)),
+    );
+    let storage = CachedStorage::new(slow.clone(), 100, 1, 1_000, 10);
+
+    let mut clicks = 0;
+    for _ in 0..70 {
+        storage.buffer_click_owned("hot".to_string(), 1).unwrap();
+        clicks += 1;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    storage.shutdown().await;
+
+    assert_eq!(slow.max_flushes_in_flight(), 1);
+    let url = sqlite.get_authoritative("hot").await.unwrap().unwrap();
+    assert_eq!(url.clicks, clicks);
+}
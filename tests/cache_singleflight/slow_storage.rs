@@ -0,0 +1,737 @@
+//! A storage that answers `get` slowly and counts the calls, so tests can
+//! hold many cache misses in flight at once. Click batches can be slowed
+//! down too, recording how many were ever written at once. Every other
+//! method passes straight through to the wrapped storage.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use lynx::models::{
+    ActivityDay, AuditEntry, Campaign, CampaignStats, ClickHistoryEntry, CreatedVia,
+    DestinationHostLink, DestinationHostSummary, InstanceStatsDay, LinkOptions, ModerationEntry,
+    ModerationStatus, ShortenedUrl, UrlHistoryEntry, UserAccount, UserLinkCounts, UserUsageHour,
+    UserUsageTotal,
+};
+use lynx::storage::{
+    AdminRecord, ClickIncrement, HardDeleteReport, MalformedPatchBatch, OrphanCounts, RowCounts,
+    SearchParams, SearchResult, ServerLease, Storage, StorageResult, UserRecord, VerifyReport,
+};
+
+pub struct SlowStorage {
+    inner: Arc<dyn Storage>,
+    delay: Duration,
+    gets: AtomicU64,
+    flush_delay: Duration,
+    flushes_in_flight: AtomicU64,
+    max_flushes_in_flight: AtomicU64,
+}
+
+impl SlowStorage {
+    pub fn new(inner: Arc<dyn Storage>, delay: Duration) -> Self {
+        Self {
+            inner,
+            delay,
+            gets: AtomicU64::new(0),
+            flush_delay: Duration::ZERO,
+            flushes_in_flight: AtomicU64::new(0),
+            max_flushes_in_flight: AtomicU64::new(0),
+        }
+    }
+
+    /// Take `flush_delay` to write each click batch
+    pub fn with_flush_delay(mut self, flush_delay: Duration) -> Self {
+        self.flush_delay = flush_delay;
+        self
+    }
+
+    /// Calls to `get` so far
+    pub fn gets(&self) -> u64 {
+        self.gets.load(Ordering::SeqCst)
+    }
+
+    /// Most click batches ever written at the same time
+    pub fn max_flushes_in_flight(&self) -> u64 {
+        self.max_flushes_in_flight.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Storage for SlowStorage {
+    async fn ensure_schema(&self) -> Result<()> {
+        self.inner.ensure_schema().await
+    }
+
+    async fn verify_schema(&self) -> Result<()> {
+        self.inner.verify_schema().await
+    }
+
+    async fn create_with_code_via(
+        &self,
+        short_code: &str,
+        original_url: &str,
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        created_via: CreatedVia,
+        options: &LinkOptions,
+    ) -> StorageResult<Arc<ShortenedUrl>> {
+        self.inner
+            .create_with_code_via(
+                short_code,
+                original_url,
+                created_by,
+                created_by_auth_method,
+                created_via,
+                options,
+            )
+            .await
+    }
+
+    async fn get(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        self.gets.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.inner.get(short_code).await
+    }
+
+    async fn get_authoritative(&self, short_code: &str) -> Result<Option<Arc<ShortenedUrl>>> {
+        self.inner.get_authoritative(short_code).await
+    }
+
+    async fn get_many(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner.get_many(short_codes).await
+    }
+
+    async fn deactivate(&self, short_code: &str) -> Result<bool> {
+        self.inner.deactivate(short_code).await
+    }
+
+    async fn set_title_if_missing(&self, short_code: &str, title: &str) -> Result<bool> {
+        self.inner.set_title_if_missing(short_code, title).await
+    }
+
+    async fn set_link_options(&self, short_code: &str, options: &LinkOptions) -> Result<bool> {
+        self.inner.set_link_options(short_code, options).await
+    }
+
+    async fn reactivate(&self, short_code: &str) -> Result<bool> {
+        self.inner.reactivate(short_code).await
+    }
+
+    async fn update_url(
+        &self,
+        short_code: &str,
+        new_url: &str,
+        updated_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        self.inner.update_url(short_code, new_url, updated_by).await
+    }
+
+    async fn reserve_codes(
+        &self,
+        short_codes: &[String],
+        created_by: Option<&str>,
+        created_by_auth_method: Option<&str>,
+        reserved_until: i64,
+    ) -> StorageResult<Vec<Arc<ShortenedUrl>>> {
+        self.inner
+            .reserve_codes(
+                short_codes,
+                created_by,
+                created_by_auth_method,
+                reserved_until,
+            )
+            .await
+    }
+
+    async fn expire_reservations(&self, now: i64) -> Result<i64> {
+        self.inner.expire_reservations(now).await
+    }
+
+    async fn get_url_history(&self, short_code: &str) -> Result<Vec<UrlHistoryEntry>> {
+        self.inner.get_url_history(short_code).await
+    }
+
+    async fn restore_url(
+        &self,
+        short_code: &str,
+        history_id: i64,
+        restored_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        self.inner
+            .restore_url(short_code, history_id, restored_by)
+            .await
+    }
+
+    async fn rename_code(
+        &self,
+        short_code: &str,
+        new_code: &str,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        self.inner.rename_code(short_code, new_code).await
+    }
+
+    async fn hard_delete(
+        &self,
+        short_code: &str,
+        actor: Option<&str>,
+    ) -> StorageResult<Option<HardDeleteReport>> {
+        self.inner.hard_delete(short_code, actor).await
+    }
+
+    async fn add_alias(
+        &self,
+        short_code: &str,
+        alias_code: &str,
+        created_by: Option<&str>,
+    ) -> StorageResult<Option<Arc<ShortenedUrl>>> {
+        self.inner
+            .add_alias(short_code, alias_code, created_by)
+            .await
+    }
+
+    async fn get_aliases(&self, short_codes: &[String]) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner.get_aliases(short_codes).await
+    }
+
+    async fn increment_clicks(&self, short_code: &str, amount: u64) -> Result<()> {
+        self.inner.increment_clicks(short_code, amount).await
+    }
+
+    async fn increment_clicks_batch(&self, increments: &[ClickIncrement]) -> Result<()> {
+        let in_flight = self.flushes_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_flushes_in_flight
+            .fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(self.flush_delay).await;
+        let result = self.inner.increment_clicks_batch(increments).await;
+        self.flushes_in_flight.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn list_with_cursor(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>, // (created_at, id)
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner
+            .list_with_cursor(
+                limit, cursor, // (created_at, id)
+                is_admin, user_id,
+            )
+            .await
+    }
+
+    async fn list_by_last_visit(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, i64)>, // (last_visited_at or 0, id)
+        unused_since: Option<i64>,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner
+            .list_by_last_visit(
+                limit,
+                cursor, // (last_visited_at or 0, id)
+                unused_since,
+                is_admin,
+                user_id,
+            )
+            .await
+    }
+
+    async fn upsert_user(
+        &self,
+        user_id: &str,
+        email: Option<&str>,
+        auth_method: &str,
+    ) -> Result<()> {
+        self.inner.upsert_user(user_id, email, auth_method).await
+    }
+
+    async fn is_manual_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        self.inner.is_manual_admin(user_id, auth_method).await
+    }
+
+    async fn promote_to_admin(&self, user_id: &str, auth_method: &str) -> Result<()> {
+        self.inner.promote_to_admin(user_id, auth_method).await
+    }
+
+    async fn demote_from_admin(&self, user_id: &str, auth_method: &str) -> Result<bool> {
+        self.inner.demote_from_admin(user_id, auth_method).await
+    }
+
+    async fn list_manual_admins(&self) -> Result<Vec<(String, String, String)>> {
+        self.inner.list_manual_admins().await
+    }
+
+    async fn patch_created_by(
+        &self,
+        short_code: &str,
+        new_created_by: &str,
+        auth_method: Option<&str>,
+    ) -> Result<bool> {
+        self.inner
+            .patch_created_by(short_code, new_created_by, auth_method)
+            .await
+    }
+
+    async fn patch_malformed_created_by_batch(
+        &self,
+        new_created_by: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Option<MalformedPatchBatch>> {
+        self.inner
+            .patch_malformed_created_by_batch(new_created_by, after_id, limit)
+            .await
+    }
+
+    async fn count_malformed_created_by(&self) -> Result<i64> {
+        self.inner.count_malformed_created_by().await
+    }
+
+    async fn sample_malformed_created_by(&self, limit: i64) -> Result<Vec<String>> {
+        self.inner.sample_malformed_created_by(limit).await
+    }
+
+    async fn user_exists(&self, user_id: &str) -> Result<bool> {
+        self.inner.user_exists(user_id).await
+    }
+
+    async fn record_audit_entry(
+        &self,
+        action: &str,
+        short_code: &str,
+        actor: Option<&str>,
+        effective_user: &str,
+    ) -> Result<()> {
+        self.inner
+            .record_audit_entry(action, short_code, actor, effective_user)
+            .await
+    }
+
+    async fn get_audit_log(&self, short_code: &str) -> Result<Vec<AuditEntry>> {
+        self.inner.get_audit_log(short_code).await
+    }
+
+    async fn submit_for_moderation(
+        &self,
+        short_code: &str,
+        status: ModerationStatus,
+    ) -> Result<()> {
+        self.inner.submit_for_moderation(short_code, status).await
+    }
+
+    async fn get_moderation(&self, short_code: &str) -> Result<Option<ModerationEntry>> {
+        self.inner.get_moderation(short_code).await
+    }
+
+    async fn list_moderation(
+        &self,
+        status: ModerationStatus,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ModerationEntry>> {
+        self.inner.list_moderation(status, limit, offset).await
+    }
+
+    async fn approve_link(&self, short_code: &str, decided_by: Option<&str>) -> Result<bool> {
+        self.inner.approve_link(short_code, decided_by).await
+    }
+
+    async fn reject_link(
+        &self,
+        short_code: &str,
+        reason: &str,
+        decided_by: Option<&str>,
+    ) -> Result<bool> {
+        self.inner.reject_link(short_code, reason, decided_by).await
+    }
+
+    async fn create_campaign(&self, name: &str, owner: Option<&str>) -> Result<Campaign> {
+        self.inner.create_campaign(name, owner).await
+    }
+
+    async fn get_campaign(&self, id: i64) -> Result<Option<Campaign>> {
+        self.inner.get_campaign(id).await
+    }
+
+    async fn list_campaigns(&self, owner: Option<&str>) -> Result<Vec<Campaign>> {
+        self.inner.list_campaigns(owner).await
+    }
+
+    async fn rename_campaign(&self, id: i64, name: &str) -> Result<Option<Campaign>> {
+        self.inner.rename_campaign(id, name).await
+    }
+
+    async fn delete_campaign(&self, id: i64) -> Result<Option<Vec<String>>> {
+        self.inner.delete_campaign(id).await
+    }
+
+    async fn set_link_campaign(&self, short_code: &str, campaign_id: Option<i64>) -> Result<bool> {
+        self.inner.set_link_campaign(short_code, campaign_id).await
+    }
+
+    async fn campaign_stats(
+        &self,
+        id: i64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<CampaignStats> {
+        self.inner.campaign_stats(id, start_time, end_time).await
+    }
+
+    async fn list_all_users(
+        &self,
+        limit: i64,
+        cursor: Option<(i64, String, String)>,
+        inactive_since: Option<i64>,
+    ) -> Result<Vec<UserAccount>> {
+        self.inner
+            .list_all_users(limit, cursor, inactive_since)
+            .await
+    }
+
+    async fn user_emails(
+        &self,
+        users: &[(String, String)],
+    ) -> Result<Vec<(String, String, String)>> {
+        self.inner.user_emails(users).await
+    }
+
+    async fn links_by_destination_host(
+        &self,
+        hosts: &[String],
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        self.inner
+            .links_by_destination_host(hosts, limit, offset)
+            .await
+    }
+
+    async fn links_by_destination(
+        &self,
+        url: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostLink>> {
+        self.inner.links_by_destination(url, limit, offset).await
+    }
+
+    async fn destination_host_summary(
+        &self,
+        hosts: &[String],
+        min_links: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<DestinationHostSummary>> {
+        self.inner
+            .destination_host_summary(hosts, min_links, limit, offset)
+            .await
+    }
+
+    async fn user_accounts(&self, user_id: &str) -> Result<Vec<UserAccount>> {
+        self.inner.user_accounts(user_id).await
+    }
+
+    async fn user_link_counts(&self, user_id: &str) -> Result<UserLinkCounts> {
+        self.inner.user_link_counts(user_id).await
+    }
+
+    async fn list_user_links(
+        &self,
+        user_id: &str,
+        limit: i64,
+        cursor: Option<(i64, i64)>,
+    ) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner.list_user_links(user_id, limit, cursor).await
+    }
+
+    async fn find_active_by_destination(
+        &self,
+        original_url: &str,
+        created_by: Option<&str>,
+    ) -> Result<Option<Arc<ShortenedUrl>>> {
+        self.inner
+            .find_active_by_destination(original_url, created_by)
+            .await
+    }
+
+    async fn bulk_deactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        self.inner.bulk_deactivate_user_links(user_id).await
+    }
+
+    async fn bulk_reactivate_user_links(&self, user_id: &str) -> Result<i64> {
+        self.inner.bulk_reactivate_user_links(user_id).await
+    }
+
+    async fn count_user_links_by_state(&self, user_id: &str, is_active: bool) -> Result<i64> {
+        self.inner
+            .count_user_links_by_state(user_id, is_active)
+            .await
+    }
+
+    async fn sample_user_links_by_state(
+        &self,
+        user_id: &str,
+        is_active: bool,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        self.inner
+            .sample_user_links_by_state(user_id, is_active, limit)
+            .await
+    }
+
+    async fn upsert_analytics_batch(
+        &self,
+        records: Vec<lynx::analytics::AnalyticsRollup>,
+    ) -> Result<()> {
+        self.inner.upsert_analytics_batch(records).await
+    }
+
+    async fn upsert_known_analytics_batch(
+        &self,
+        records: Vec<lynx::analytics::AnalyticsRollup>,
+    ) -> Result<u64> {
+        self.inner.upsert_known_analytics_batch(records).await
+    }
+
+    async fn get_analytics(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<lynx::analytics::AnalyticsEntry>> {
+        self.inner
+            .get_analytics(short_code, start_time, end_time, limit)
+            .await
+    }
+
+    async fn get_estimated_visits(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<i64> {
+        self.inner
+            .get_estimated_visits(short_code, start_time, end_time)
+            .await
+    }
+
+    async fn get_analytics_export_page(
+        &self,
+        scope: lynx::analytics::AnalyticsExportScope<'_>,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<lynx::analytics::AnalyticsEntry>> {
+        self.inner
+            .get_analytics_export_page(scope, start_time, end_time, after, limit)
+            .await
+    }
+
+    async fn get_analytics_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        group_by: lynx::analytics::AnalyticsGroupBy,
+        limit: i64,
+    ) -> Result<Vec<lynx::analytics::AnalyticsAggregate>> {
+        self.inner
+            .get_analytics_aggregate(short_code, start_time, end_time, group_by, limit)
+            .await
+    }
+
+    async fn get_instance_ip_version_split(
+        &self,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<lynx::analytics::AnalyticsAggregate>> {
+        self.inner
+            .get_instance_ip_version_split(start_time, end_time)
+            .await
+    }
+
+    async fn get_analytics_daily_aggregate(
+        &self,
+        short_code: &str,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+        time_zone: chrono_tz::Tz,
+        limit: i64,
+    ) -> Result<Vec<lynx::analytics::AnalyticsAggregate>> {
+        self.inner
+            .get_analytics_daily_aggregate(short_code, start_time, end_time, time_zone, limit)
+            .await
+    }
+
+    async fn prune_analytics(
+        &self,
+        retention_days: i64,
+        drop_dimensions: &[String],
+    ) -> Result<(i64, i64)> {
+        self.inner
+            .prune_analytics(retention_days, drop_dimensions)
+            .await
+    }
+
+    async fn analytics_complete_since(&self) -> Result<Option<i64>> {
+        self.inner.analytics_complete_since().await
+    }
+
+    async fn rollup_analytics_daily(&self, from: Option<i64>, until: i64) -> Result<i64> {
+        self.inner.rollup_analytics_daily(from, until).await
+    }
+
+    async fn analytics_daily_rolled_until(&self) -> Result<Option<i64>> {
+        self.inner.analytics_daily_rolled_until().await
+    }
+
+    async fn record_instance_stats(&self, day: i64) -> Result<InstanceStatsDay> {
+        self.inner.record_instance_stats(day).await
+    }
+
+    async fn instance_stats_history(&self, since: i64) -> Result<Vec<InstanceStatsDay>> {
+        self.inner.instance_stats_history(since).await
+    }
+
+    async fn activity_report(&self, since: i64, until: i64, top: i64) -> Result<Vec<ActivityDay>> {
+        self.inner.activity_report(since, until, top).await
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        self.inner.get_setting(key).await
+    }
+
+    async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.inner.set_setting(key, value).await
+    }
+
+    async fn renew_server_lease(&self, lease: &ServerLease) -> Result<()> {
+        self.inner.renew_server_lease(lease).await
+    }
+
+    async fn release_server_lease(&self, instance_id: &str) -> Result<()> {
+        self.inner.release_server_lease(instance_id).await
+    }
+
+    async fn active_server_leases(&self) -> Result<Vec<ServerLease>> {
+        self.inner.active_server_leases().await
+    }
+
+    async fn redeem_challenge_nonce(&self, nonce: &str, expires_at: i64) -> Result<bool> {
+        self.inner.redeem_challenge_nonce(nonce, expires_at).await
+    }
+
+    async fn record_user_usage(&self, hours: &[UserUsageHour]) -> Result<()> {
+        self.inner.record_user_usage(hours).await
+    }
+
+    async fn user_usage(&self, user_id: &str, since: i64) -> Result<Vec<UserUsageHour>> {
+        self.inner.user_usage(user_id, since).await
+    }
+
+    async fn user_usage_totals(
+        &self,
+        user_ids: &[String],
+        since: i64,
+    ) -> Result<Vec<UserUsageTotal>> {
+        self.inner.user_usage_totals(user_ids, since).await
+    }
+
+    async fn get_click_history(
+        &self,
+        short_code: &str,
+        since: i64,
+        time_zone: chrono_tz::Tz,
+    ) -> Result<Vec<ClickHistoryEntry>> {
+        self.inner
+            .get_click_history(short_code, since, time_zone)
+            .await
+    }
+
+    async fn compact_click_history(&self, retention_days: i64) -> Result<(i64, i64)> {
+        self.inner.compact_click_history(retention_days).await
+    }
+
+    async fn count_orphan_analytics(&self) -> Result<OrphanCounts> {
+        self.inner.count_orphan_analytics().await
+    }
+
+    async fn delete_orphan_analytics(&self) -> Result<OrphanCounts> {
+        self.inner.delete_orphan_analytics().await
+    }
+
+    async fn verify_database(&self, fix: bool) -> Result<VerifyReport> {
+        self.inner.verify_database(fix).await
+    }
+
+    async fn search(
+        &self,
+        params: &SearchParams,
+        is_admin: bool,
+        user_id: Option<&str>,
+    ) -> Result<SearchResult> {
+        self.inner.search(params, is_admin, user_id).await
+    }
+
+    async fn export_urls(&self, after_id: i64, limit: i64) -> Result<Vec<Arc<ShortenedUrl>>> {
+        self.inner.export_urls(after_id, limit).await
+    }
+
+    async fn import_urls(&self, urls: &[Arc<ShortenedUrl>], keep_ids: bool) -> Result<u64> {
+        self.inner.import_urls(urls, keep_ids).await
+    }
+
+    async fn export_users(
+        &self,
+        after: Option<&(String, String)>,
+        limit: i64,
+    ) -> Result<Vec<UserRecord>> {
+        self.inner.export_users(after, limit).await
+    }
+
+    async fn import_users(&self, users: &[UserRecord]) -> Result<u64> {
+        self.inner.import_users(users).await
+    }
+
+    async fn export_admins(&self) -> Result<Vec<AdminRecord>> {
+        self.inner.export_admins().await
+    }
+
+    async fn import_admins(&self, admins: &[AdminRecord]) -> Result<u64> {
+        self.inner.import_admins(admins).await
+    }
+
+    async fn export_campaigns(&self) -> Result<Vec<Campaign>> {
+        self.inner.export_campaigns().await
+    }
+
+    async fn import_campaigns(&self, campaigns: &[Campaign]) -> Result<u64> {
+        self.inner.import_campaigns(campaigns).await
+    }
+
+    async fn export_analytics(
+        &self,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<lynx::analytics::AnalyticsEntry>> {
+        self.inner.export_analytics(after, limit).await
+    }
+
+    async fn import_analytics(&self, rows: &[lynx::analytics::AnalyticsEntry]) -> Result<u64> {
+        self.inner.import_analytics(rows).await
+    }
+
+    async fn count_rows(&self) -> Result<RowCounts> {
+        self.inner.count_rows().await
+    }
+}
@@ -0,0 +1,108 @@
+//! Integration tests for the admin read cache statistics endpoint
+//!
+//! `GET /api/stats/cache` reports the configured caps and how many cached
+//! lookups found a link versus a missing code.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, CacheEvictionPolicy, Config, FlushConfig};
+use lynx::storage::{CachePolicy, CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_storage() -> Arc<dyn Storage> {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    Arc::new(storage)
+}
+
+async fn create_test_api(storage: Arc<dyn Storage>) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::routes::create_api_router(storage, auth_service, create_test_config(), None)
+}
+
+async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_cache_stats_split_found_and_missing_entries() {
+    let cached = CachedStorage::new_with_cache_policy(
+        create_test_storage().await,
+        CachePolicy {
+            max_entries: 100,
+            max_bytes: None,
+            negative_max_entries: Some(10),
+            eviction_policy: CacheEvictionPolicy::Lru,
+            stale_max_age: None,
+            entry_ttl: None,
+            lookup_timeout: None,
+        },
+        5,
+        1_000,
+        10,
+        FlushConfig::default(),
+    );
+    cached
+        .create_with_code("spring-sale", "https://example.com/sale", None)
+        .await
+        .unwrap();
+    for code in ["wp-admin", "wp-login.php", ".env"] {
+        assert!(cached.get(code).await.unwrap().is_none());
+    }
+    let app = create_test_api(Arc::new(cached)).await;
+
+    let (status, json) = get_json(&app, "/api/stats/cache").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json,
+        json!({
+            "eviction_policy": "lru",
+            "max_entries": 100,
+            "weighted_size": 1,
+            "negative_max_entries": 10,
+            "entry_ttl_secs": null,
+            "positive_entries": 1,
+            "negative_entries": 3,
+            "lookups": { "loads": 3, "coalesced": 0, "timed_out": 0 },
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_cache_stats_without_read_cache_returns_not_found() {
+    let app = create_test_api(create_test_storage().await).await;
+    let (status, _) = get_json(&app, "/api/stats/cache").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
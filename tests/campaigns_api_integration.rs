@@ -0,0 +1,254 @@
+//! Integration tests for campaigns: links join one through `campaign_id`
+//! on create and update, `GET /api/campaigns/{id}/stats` sums their clicks
+//! and visits, search filters by campaign, and deleting a campaign takes
+//! its links out of it without changing them otherwise.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::analytics::{AnalyticsRollup, IpVersion};
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode};
+use lynx::storage::{SqliteStorage, Storage};
+use lynx::testing::Fixture;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+async fn create_app() -> (Router, Arc<dyn Storage>) {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let config = Arc::new(common::test_config());
+    let app = lynx::api::create_api_router(Arc::clone(&storage), auth_service, config, None);
+    (app, storage)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn rollup(short_code: &str, country_code: &str, visit_count: i64) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket: 1_698_768_000,
+        country_code: Some(country_code.to_string()),
+        region: None,
+        city: None,
+        asn: None,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+async fn create_campaign(app: &Router, name: &str) -> i64 {
+    let (status, campaign) =
+        send(app, "POST", "/api/campaigns", Some(json!({ "name": name }))).await;
+    assert_eq!(status, StatusCode::CREATED, "{campaign}");
+    assert_eq!(campaign["name"], name);
+    campaign["id"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn test_campaign_stats_sum_clicks_and_visits_over_member_links() {
+    let (app, storage) = create_app().await;
+    // `user1-2` is not in the campaign, so left out of its stats
+    let seeded = Fixture::new(Arc::clone(&storage))
+        .links_per_user(3)
+        .campaign("Spring launch", ["user1-0", "user1-1"])
+        .seed()
+        .await
+        .unwrap();
+    let id = seeded.campaign("Spring launch").unwrap().id;
+
+    storage.increment_clicks("user1-0", 7).await.unwrap();
+    storage.increment_clicks("user1-1", 2).await.unwrap();
+    storage.increment_clicks("user1-2", 50).await.unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("user1-0", "US", 4),
+            rollup("user1-0", "GB", 1),
+            rollup("user1-1", "US", 2),
+            rollup("user1-2", "FR", 40),
+        ])
+        .await
+        .unwrap();
+
+    let (status, stats) = send(&app, "GET", &format!("/api/campaigns/{id}/stats"), None).await;
+    assert_eq!(status, StatusCode::OK, "{stats}");
+    assert_eq!(stats["campaign"]["name"], "Spring launch");
+    assert_eq!(stats["total_clicks"], 9);
+    assert_eq!(stats["total_visits"], 7);
+    assert_eq!(
+        stats["links"],
+        json!([
+            { "short_code": "user1-0", "clicks": 7, "visits": 5 },
+            { "short_code": "user1-1", "clicks": 2, "visits": 2 },
+        ])
+    );
+    assert_eq!(
+        stats["countries"],
+        json!([
+            { "dimension": "US", "visit_count": 6, "aggregated": false },
+            { "dimension": "GB", "visit_count": 1, "aggregated": false },
+        ])
+    );
+
+    // A range that misses every bucket keeps the clicks and drops the visits
+    let (_, stats) = send(
+        &app,
+        "GET",
+        &format!("/api/campaigns/{id}/stats?start_time=1800000000"),
+        None,
+    )
+    .await;
+    assert_eq!(stats["total_clicks"], 9);
+    assert_eq!(stats["total_visits"], 0);
+    assert_eq!(stats["countries"], json!([]));
+
+    let (status, found) = send(
+        &app,
+        "GET",
+        &format!("/api/urls/search?q=example&campaign_id={id}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{found}");
+    let mut codes: Vec<&str> = found["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["short_code"].as_str().unwrap())
+        .collect();
+    codes.sort_unstable();
+    assert_eq!(codes, ["user1-0", "user1-1"]);
+}
+
+#[tokio::test]
+async fn test_links_move_between_campaigns_on_update() {
+    let (app, storage) = create_app().await;
+    let first = create_campaign(&app, "First").await;
+    let second = create_campaign(&app, "Second").await;
+    send(
+        &app,
+        "POST",
+        "/api/urls",
+        Some(
+            json!({ "url": "https://example.com/", "custom_code": "mover", "campaign_id": first }),
+        ),
+    )
+    .await;
+
+    // `bW92ZXI` is `mover` in base64url
+    let (status, link) = send(
+        &app,
+        "PATCH",
+        "/api/urls/bW92ZXI",
+        Some(json!({ "url": "https://example.com/", "campaign_id": second })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{link}");
+    assert_eq!(link["campaign_id"], second);
+
+    // Leaving `campaign_id` out keeps the campaign; null removes it
+    let (_, link) = send(
+        &app,
+        "PATCH",
+        "/api/urls/bW92ZXI",
+        Some(json!({ "url": "https://example.com/new" })),
+    )
+    .await;
+    assert_eq!(link["campaign_id"], second);
+    let (_, link) = send(
+        &app,
+        "PATCH",
+        "/api/urls/bW92ZXI",
+        Some(json!({ "url": "https://example.com/new", "campaign_id": null })),
+    )
+    .await;
+    assert_eq!(link["campaign_id"], Value::Null);
+    assert_eq!(
+        storage.get("mover").await.unwrap().unwrap().campaign_id,
+        None
+    );
+
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        "/api/urls/bW92ZXI",
+        Some(json!({ "url": "https://example.com/", "campaign_id": 999 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_deleting_a_campaign_unassigns_its_links() {
+    let (app, storage) = create_app().await;
+    let seeded = Fixture::new(Arc::clone(&storage))
+        .campaign("Short-lived", ["user1-0"])
+        .seed()
+        .await
+        .unwrap();
+    let id = seeded.campaign("Short-lived").unwrap().id;
+    let before = storage.get("user1-0").await.unwrap().unwrap();
+    assert_eq!(before.campaign_id, Some(id));
+
+    let (status, renamed) = send(
+        &app,
+        "PATCH",
+        &format!("/api/campaigns/{id}"),
+        Some(json!({ "name": "  Renamed  " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(renamed["name"], "Renamed");
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        &format!("/api/campaigns/{id}"),
+        Some(json!({ "name": " " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, list) = send(&app, "GET", "/api/campaigns", None).await;
+    assert_eq!(list["campaigns"].as_array().unwrap().len(), 1);
+
+    let (status, _) = send(&app, "DELETE", &format!("/api/campaigns/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "GET", &format!("/api/campaigns/{id}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let after = storage.get("user1-0").await.unwrap().unwrap();
+    assert_eq!(after.campaign_id, None);
+    assert_eq!(after.original_url, before.original_url);
+    assert_eq!(after.updated_at, before.updated_at);
+}
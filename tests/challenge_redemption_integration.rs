@@ -0,0 +1,89 @@
+//! Integration tests for the proof-of-work redemptions kept in the
+//! database, which let every instance accept a nonce only once.
+//!
+//! Like `storage_integration_test`, `DATABASE_BACKEND` picks the backend and
+//! the Postgres variant needs `DATABASE_URL`. The Postgres database may be
+//! shared, so each run redeems nonces of its own.
+#![cfg(feature = "sqlite")]
+
+use lynx::clock::{Clock, FakeClock};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 2023-03-10T00:00:00Z
+const DAY_MS: i64 = 1_678_406_400_000;
+
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(selected) => selected == backend,
+        Err(_) => true,
+    }
+}
+
+async fn assert_nonces_redeem_once_until_they_lapse(storage: &dyn Storage, clock: &FakeClock) {
+    let run = format!("{:016x}", rand::random::<u64>());
+    let nonce = format!("{run}.first");
+    let expires_at = clock.now_epoch_secs() + 300;
+
+    assert!(storage
+        .redeem_challenge_nonce(&nonce, expires_at)
+        .await
+        .unwrap());
+    assert!(!storage
+        .redeem_challenge_nonce(&nonce, expires_at)
+        .await
+        .unwrap());
+    assert!(storage
+        .redeem_challenge_nonce(&format!("{run}.second"), expires_at)
+        .await
+        .unwrap());
+
+    // Lapsed redemptions are dropped by the next one
+    clock.advance(Duration::from_secs(300));
+    assert!(storage
+        .redeem_challenge_nonce(&format!("{run}.third"), clock.now_epoch_secs() + 300)
+        .await
+        .unwrap());
+    assert!(storage
+        .redeem_challenge_nonce(&nonce, expires_at + 600)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_challenge_redemptions_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY_MS));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    assert_nonces_redeem_once_until_they_lapse(&storage, &clock).await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_challenge_redemptions_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY_MS));
+    let storage = PostgresStorage::new(&db_url, 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    assert_nonces_redeem_once_until_they_lapse(&storage, &clock).await;
+}
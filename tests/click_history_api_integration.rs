@@ -0,0 +1,173 @@
+//! Integration tests for the daily click history endpoint
+//! `GET /api/links/{code}/clicks/history`
+//!
+//! Clicks are written straight to storage, the way the click flush writes
+//! them, and read back through the API.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono_tz::Tz;
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::storage::{SqliteStorage, Storage};
+use lynx::timezone::local_day_start;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_app() -> (Router, Arc<SqliteStorage>) {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let storage = Arc::new(storage);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let app = api::routes::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    );
+    (app, storage)
+}
+
+async fn history(app: &Router, code: &str, query: &str) -> (StatusCode, Value) {
+    let uri = format!(
+        "/api/links/{}/clicks/history{query}",
+        URL_SAFE_NO_PAD.encode(code)
+    );
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_click_history_returns_daily_counts_within_the_window() {
+    let (app, storage) = create_test_app().await;
+    storage
+        .create_with_code("promo", "https://example.com/promo", None)
+        .await
+        .unwrap();
+
+    let today = local_day_start(chrono::Utc::now().timestamp(), Tz::UTC);
+    let day = 86_400;
+    for (offset, clicks) in [(100, 7), (10, 3)] {
+        sqlx::query("INSERT INTO click_history (short_code, hour, clicks) VALUES ('promo', ?, ?)")
+            .bind(today - offset * day)
+            .bind(clicks)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+    storage.increment_clicks("promo", 2).await.unwrap();
+
+    let (status, json) = history(&app, "promo", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["short_code"], "promo");
+    assert_eq!(json["days"], 90);
+    assert_eq!(json["tz"], "UTC");
+    assert_eq!(json["total"], 5);
+    assert_eq!(json["clicks"], 2);
+    assert_eq!(
+        json["history"],
+        json!([
+            {"day": today - 10 * day, "clicks": 3},
+            {"day": today, "clicks": 2},
+        ])
+    );
+
+    let (_, json) = history(&app, "promo", "?days=365").await;
+    assert_eq!(json["total"], 12);
+
+    let (_, json) = history(&app, "promo", "?days=0").await;
+    assert_eq!(json["days"], 1);
+    assert_eq!(json["history"], json!([{"day": today, "clicks": 2}]));
+}
+
+#[tokio::test]
+async fn test_click_history_groups_days_in_the_requested_time_zone() {
+    let (app, storage) = create_test_app().await;
+    storage
+        .create_with_code("dst", "https://example.com/dst", None)
+        .await
+        .unwrap();
+
+    // New York switched to daylight saving time on 2024-03-10, whose local
+    // day runs from 05:00 UTC to 04:00 UTC the next day.
+    let march_9_23h_est = 1_710_043_200; // 2024-03-10T04:00:00Z
+    let march_10_23h_edt = 1_710_126_000; // 2024-03-11T03:00:00Z
+    for (hour, clicks) in [(march_9_23h_est, 1), (march_10_23h_edt, 2)] {
+        sqlx::query("INSERT INTO click_history (short_code, hour, clicks) VALUES ('dst', ?, ?)")
+            .bind(hour)
+            .bind(clicks)
+            .execute(storage.pool.as_ref())
+            .await
+            .unwrap();
+    }
+
+    let (status, json) = history(&app, "dst", "?days=3660").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json["history"],
+        json!([
+            {"day": 1_710_028_800, "clicks": 1},
+            {"day": 1_710_115_200, "clicks": 2},
+        ])
+    );
+
+    let (status, json) = history(&app, "dst", "?days=3660&tz=America/New_York").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["tz"], "America/New_York");
+    assert_eq!(
+        json["history"],
+        json!([
+            {"day": 1_709_960_400, "clicks": 1},
+            {"day": 1_710_046_800, "clicks": 2},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_click_history_rejects_unknown_time_zones() {
+    let (app, storage) = create_test_app().await;
+    storage
+        .create_with_code("promo", "https://example.com/promo", None)
+        .await
+        .unwrap();
+
+    let (status, _) = history(&app, "promo", "?tz=Mars/Olympus_Mons").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_click_history_for_unknown_code_is_not_found() {
+    let (app, _) = create_test_app().await;
+    let (status, _) = history(&app, "missing", "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
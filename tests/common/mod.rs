@@ -0,0 +1,89 @@
+//! Shared setup for the integration test targets.
+//!
+//! Each target declares `mod common;` and builds its configuration from
+//! [`test_config`], overriding only the fields its tests are about:
+//!
+//! ```ignore
+//! Arc::new(Config {
+//!     pagination,
+//!     ..common::test_config()
+//! })
+//! ```
+
+use lynx::config::*;
+
+/// In-memory SQLite, no authentication and every optional feature at its
+/// default, which for most features means off.
+pub fn test_config() -> Config {
+    Config {
+        database: DatabaseConfig {
+            backend: DatabaseBackend::Sqlite,
+            url: "sqlite::memory:".to_string(),
+            max_connections: 5,
+            acquire_timeout_secs: 5,
+            slow_acquire_threshold_ms: 500,
+            schema: None,
+            allow_hard_delete: false,
+        },
+        api_server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+        },
+        redirect_server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 3000,
+        },
+        redirect_base_url: "http://localhost:3000".to_string(),
+        redirect_path_prefix: None,
+        auth: AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        },
+        frontend: FrontendConfig {
+            enabled: true,
+            static_dir: None,
+        },
+        cache: CacheConfig {
+            max_entries: 10000,
+            max_bytes: None,
+            flush_interval_secs: 5,
+            actor_buffer_size: 100000,
+            actor_flush_interval_ms: 100,
+            negative_max_entries: None,
+            eviction_policy: CacheEvictionPolicy::default(),
+            stale_max_age_secs: None,
+            entry_ttl_secs: None,
+            lookup_timeout_ms: 5_000,
+        },
+        pagination: PaginationConfig::default(),
+        short_code_max_length: 50,
+        analytics: AnalyticsConfig::default(),
+        redirect_status: RedirectMode::default(),
+        flush: FlushConfig::default(),
+        redirect_stats: RedirectStatsConfig::default(),
+        code_normalization: CodeNormalizationConfig::default(),
+        destination: DestinationConfig::default(),
+        url_normalization: UrlNormalizationConfig::default(),
+        quick_link: QuickLinkConfig::default(),
+        title_fetch: TitleFetchConfig::default(),
+        slack: None,
+        smtp: None,
+        live_visits: LiveVisitsConfig::default(),
+        click_history: ClickHistoryConfig::default(),
+        reservations: ReservationConfig::default(),
+        alerts: AlertConfig::default(),
+        anonymous_create: AnonymousCreateConfig::default(),
+        creation_challenge: CreationChallengeConfig::default(),
+        redirect_landing: RedirectLandingConfig::default(),
+        redirect_interstitial: RedirectInterstitialConfig::default(),
+        link_info: LinkInfoConfig::default(),
+        link_quota: LinkQuotaConfig::default(),
+        database_mirror: None,
+        public_url: PublicUrlConfig::default(),
+        request_timeout: RequestTimeoutConfig::default(),
+        stats_privacy: StatsPrivacyConfig::default(),
+        outbound_http: OutboundHttpConfig::default(),
+        code_generation: CodeGenerationConfig::default(),
+    }
+}
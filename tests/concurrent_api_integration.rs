@@ -2,6 +2,7 @@
 //!
 //! These tests verify that the API correctly handles concurrent operations,
 //! particularly for short code creation which is a critical operation.
+#![cfg(feature = "sqlite")]
 
 use axum::{
     body::Body,
@@ -17,6 +18,8 @@ use serde_json::Value;
 use std::sync::Arc;
 use tower::ServiceExt;
 
+mod common;
+
 /// Helper to create test storage
 async fn create_test_storage() -> Arc<dyn Storage> {
     let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
@@ -29,35 +32,6 @@ fn create_test_config(short_code_max_length: usize) -> Arc<Config> {
     use lynx::config::*;
 
     Arc::new(Config {
-        database: DatabaseConfig {
-            backend: DatabaseBackend::Sqlite,
-            url: "sqlite::memory:".to_string(),
-            max_connections: 5,
-        },
-        api_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 8080,
-        },
-        redirect_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 3000,
-        },
-        redirect_base_url: "http://localhost:3000".to_string(),
-        auth: AuthConfig {
-            mode: AuthMode::None,
-            oauth: None,
-            cloudflare: None,
-        },
-        frontend: FrontendConfig { static_dir: None },
-        cache: CacheConfig {
-            max_entries: 10000,
-            flush_interval_secs: 5,
-            actor_buffer_size: 100000,
-            actor_flush_interval_ms: 100,
-        },
-        pagination: PaginationConfig {
-            cursor_hmac_secret: None,
-        },
         short_code_max_length,
         analytics: AnalyticsConfig {
             enabled: false,
@@ -68,8 +42,10 @@ fn create_test_config(short_code_max_length: usize) -> Arc<Config> {
             trusted_proxies: vec![],
             num_trusted_proxies: None,
             flush_interval_secs: 30,
+            daily_rollup_after_days: 30,
+            sampling: Default::default(),
         },
-        redirect_status: RedirectMode::default(),
+        ..common::test_config()
     })
 }
 
@@ -161,7 +137,7 @@ async fn test_concurrent_different_short_codes() {
         let handle = tokio::spawn(async move {
             let request = Request::builder()
                 .method("POST")
-                .uri("/api/shorten")
+                .uri("/api/urls")
                 .header("content-type", "application/json")
                 .body(Body::from(format!(
                     r#"{{"url": "https://example.com", "custom_code": "test_{:03}"}}"#,
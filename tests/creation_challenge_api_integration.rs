@@ -0,0 +1,312 @@
+//! Integration tests for the captcha / proof-of-work hook on link creation
+//!
+//! A mock [`CreationChallenge`] stands in for Turnstile and hCaptcha: it
+//! accepts the answer `pass`, rejects anything else and reports the provider
+//! as down for `down`. The proof of work is exercised end to end.
+#![cfg(feature = "sqlite")]
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use lynx::api::{
+    self,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthService;
+use lynx::challenge::{
+    leading_zero_bits, ChallengeError, CreationChallenge, ProofOfWork, CHALLENGE_HEADER,
+};
+use lynx::config::{ChallengeEndpoint, ChallengeProvider, Config, CreationChallengeConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+mod common;
+
+/// Accepts `pass`, fails everything else, and is unreachable for `down`.
+#[derive(Default)]
+struct MockVerifier {
+    remote_ips: Mutex<Vec<Option<IpAddr>>>,
+}
+
+#[async_trait]
+impl CreationChallenge for MockVerifier {
+    fn provider(&self) -> ChallengeProvider {
+        ChallengeProvider::Turnstile
+    }
+
+    async fn verify(&self, answer: &str, remote_ip: Option<IpAddr>) -> Result<(), ChallengeError> {
+        self.remote_ips.lock().unwrap().push(remote_ip);
+        match answer {
+            "pass" => Ok(()),
+            "down" => Err(ChallengeError::Unavailable(
+                "connection refused".to_string(),
+            )),
+            _ => Err(ChallengeError::Failed),
+        }
+    }
+}
+
+/// Helper to create test config requiring a challenge on `endpoints`
+fn create_test_config(
+    provider: ChallengeProvider,
+    endpoints: Vec<ChallengeEndpoint>,
+) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        anonymous_create: AnonymousCreateConfig {
+            enabled: true,
+            rate_limit_per_minute: 0,
+            require_approval: false,
+        },
+        creation_challenge: CreationChallengeConfig {
+            provider,
+            secret: None,
+            endpoints,
+            pow_difficulty: 4,
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_api(
+    config: Arc<Config>,
+    challenge: Option<Arc<dyn CreationChallenge>>,
+) -> (Router, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let app = create_test_api_with_storage(config, Arc::clone(&storage), challenge).await;
+    (app, storage)
+}
+
+async fn create_test_api_with_storage(
+    config: Arc<Config>,
+    storage: Arc<SqliteStorage>,
+    challenge: Option<Arc<dyn CreationChallenge>>,
+) -> Router {
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    let server_info = Arc::new(ServerInfo::new(&config, &RuntimeFacts::default()));
+    api::create_api_router_with_creation_challenge(
+        storage as Arc<dyn Storage>,
+        auth_service,
+        config,
+        None,
+        None,
+        None,
+        server_info,
+        challenge,
+    )
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    (status, json)
+}
+
+/// POST `body` to `uri`, answering the challenge with `answer` if given
+async fn post(app: &Router, uri: &str, body: Value, answer: Option<&str>) -> (StatusCode, Value) {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .extension(ConnectInfo(SocketAddr::from(([192, 0, 2, 7], 40000))));
+    if let Some(answer) = answer {
+        builder = builder.header(CHALLENGE_HEADER, answer);
+    }
+    send(app, builder.body(Body::from(body.to_string())).unwrap()).await
+}
+
+async fn quick(app: &Router, answer: Option<&str>) -> StatusCode {
+    let mut builder = Request::builder()
+        .uri("/api/quick?url=https%3A%2F%2Fexample.com%2Fquick")
+        .header(header::ACCEPT, "application/json");
+    if let Some(answer) = answer {
+        builder = builder.header(CHALLENGE_HEADER, answer);
+    }
+    send(app, builder.body(Body::empty()).unwrap()).await.0
+}
+
+async fn link_count(storage: &SqliteStorage) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM urls")
+        .fetch_one(storage.pool.as_ref())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_public_create_requires_a_passed_challenge() {
+    let verifier = Arc::new(MockVerifier::default());
+    let config = create_test_config(
+        ChallengeProvider::Turnstile,
+        CreationChallengeConfig::default_endpoints(),
+    );
+    let (app, storage) = create_test_api(config, Some(verifier.clone())).await;
+    let body = json!({ "url": "https://example.com" });
+
+    let (status, _) = post(&app, "/api/public/urls", body.clone(), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = post(&app, "/api/public/urls", body.clone(), Some("wrong")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = post(&app, "/api/public/urls", body.clone(), Some("down")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(link_count(&storage).await, 0);
+
+    let (status, _) = post(&app, "/api/public/urls", body, Some("pass")).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(link_count(&storage).await, 1);
+
+    // The provider is told who is answering.
+    let remote_ips = verifier.remote_ips.lock().unwrap();
+    assert!(remote_ips
+        .iter()
+        .all(|ip| *ip == Some(IpAddr::from([192, 0, 2, 7]))));
+}
+
+#[tokio::test]
+async fn test_challenge_is_checked_before_validation() {
+    let config = create_test_config(
+        ChallengeProvider::Turnstile,
+        CreationChallengeConfig::default_endpoints(),
+    );
+    let (app, _storage) = create_test_api(config, Some(Arc::new(MockVerifier::default()))).await;
+
+    let (status, _) = post(
+        &app,
+        "/api/public/urls",
+        json!({ "url": "not a url" }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = post(
+        &app,
+        "/api/public/urls",
+        json!({ "url": "not a url" }),
+        Some("pass"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_only_listed_endpoints_require_a_challenge() {
+    let config = create_test_config(
+        ChallengeProvider::Turnstile,
+        CreationChallengeConfig::default_endpoints(),
+    );
+    let (app, _storage) = create_test_api(config, Some(Arc::new(MockVerifier::default()))).await;
+    let (status, _) = post(
+        &app,
+        "/api/urls",
+        json!({ "url": "https://example.com" }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(quick(&app, None).await, StatusCode::CREATED);
+
+    let config = create_test_config(
+        ChallengeProvider::Turnstile,
+        vec![ChallengeEndpoint::Api, ChallengeEndpoint::Quick],
+    );
+    let (app, _storage) = create_test_api(config, Some(Arc::new(MockVerifier::default()))).await;
+    let body = json!({ "url": "https://example.com", "custom_code": "checked" });
+    let (status, _) = post(&app, "/api/urls", body.clone(), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = post(&app, "/api/urls", body, Some("pass")).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(quick(&app, Some("wrong")).await, StatusCode::FORBIDDEN);
+    assert_eq!(quick(&app, Some("pass")).await, StatusCode::CREATED);
+
+    // Not listed now, so anonymous creation needs no answer.
+    let (status, _) = post(
+        &app,
+        "/api/public/urls",
+        json!({ "url": "https://example.com" }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_proof_of_work_round_trip() {
+    let config = create_test_config(
+        ChallengeProvider::Pow,
+        CreationChallengeConfig::default_endpoints(),
+    );
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let pow = ProofOfWork::new(
+        b"challenge secret",
+        4,
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        lynx::clock::system_clock(),
+    );
+    let app = create_test_api_with_storage(config, Arc::clone(&storage), Some(Arc::new(pow))).await;
+
+    let (status, issued) = send(
+        &app,
+        Request::builder()
+            .uri("/api/public/challenge")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(issued["provider"], "pow");
+    assert_eq!(issued["difficulty"], 4);
+    let nonce = issued["nonce"].as_str().unwrap();
+
+    let answer = (0u64..)
+        .map(|solution| format!("{}:{}", nonce, solution))
+        .find(|answer| leading_zero_bits(&Sha256::digest(answer.as_bytes())) >= 4)
+        .unwrap();
+    let body = json!({ "url": "https://example.com" });
+    let (status, _) = post(&app, "/api/public/urls", body.clone(), Some(&answer)).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Each nonce buys one link.
+    let (status, _) = post(&app, "/api/public/urls", body, Some(&answer)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_challenge_endpoint_is_absent_without_a_provider() {
+    let config = create_test_config(
+        ChallengeProvider::None,
+        CreationChallengeConfig::default_endpoints(),
+    );
+    let (app, _storage) = create_test_api(config, None).await;
+
+    let (status, _) = send(
+        &app,
+        Request::builder()
+            .uri("/api/public/challenge")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = post(
+        &app,
+        "/api/public/urls",
+        json!({ "url": "https://example.com" }),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+}
@@ -0,0 +1,390 @@
+//! Integration tests for `GET /api/admin/reports/destinations`
+//!
+//! The report reads `urls.dest_host` and `urls.normalized_url`, which every
+//! write keeps in step with the destination and `ensure_schema` fills for
+//! rows that predate them. It lists active links to the requested hosts or
+//! to one normalized destination with their owners, or groups all active
+//! links by host, as JSON or CSV.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
+use lynx::testing::{Fixture, Seeded};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Get the database backend to test from environment variable
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(val) => val.eq_ignore_ascii_case(backend),
+        Err(_) => true,
+    }
+}
+
+/// Two users with three links each, then repointed so that `a.com` has two
+/// active links of user1 (plus an alias and a deactivated link of user2),
+/// `b.com` one of user2 and `example.com` the remaining two.
+async fn seed(storage: &Arc<dyn Storage>) -> Seeded {
+    let seeded = Fixture::new(Arc::clone(storage))
+        .users(2)
+        .links_per_user(3)
+        .with_clicks(1..=100)
+        .rng_seed(1237)
+        .seed()
+        .await
+        .unwrap();
+    for (code, url) in [
+        ("user1-0", "https://a.com/1"),
+        ("user1-1", "https://A.com./2"),
+        ("user2-0", "https://b.com/"),
+        ("user2-1", "https://a.com/3"),
+    ] {
+        storage.update_url(code, url, None).await.unwrap().unwrap();
+    }
+    storage.deactivate("user2-1").await.unwrap();
+    storage
+        .add_alias("user1-0", "user1-0-alias", Some("user1"))
+        .await
+        .unwrap()
+        .unwrap();
+    seeded
+}
+
+async fn sqlite_storage() -> Arc<dyn Storage> {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    Arc::new(storage)
+}
+
+async fn create_test_api(storage: Arc<dyn Storage>) -> Router {
+    let config = Arc::new(common::test_config());
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    api::create_api_router(storage, auth_service, config, None)
+}
+
+async fn get(app: &Router, query: &str) -> (StatusCode, Option<String>, String) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/admin/reports/destinations{query}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+async fn get_json(app: &Router, query: &str) -> Value {
+    let (status, _, body) = get(app, query).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_str(&body).unwrap()
+}
+
+fn clicks(seeded: &Seeded, code: &str) -> i64 {
+    seeded.link(code).unwrap().clicks as i64
+}
+
+#[tokio::test]
+async fn test_report_on_hosts_lists_active_links_with_owners() {
+    let storage = sqlite_storage().await;
+    let seeded = seed(&storage).await;
+    let app = create_test_api(storage).await;
+
+    let report = get_json(&app, "?host=b.com&host=A.COM&host=gone.example").await;
+
+    let a_clicks = clicks(&seeded, "user1-0") + clicks(&seeded, "user1-1");
+    assert_eq!(
+        report["hosts"],
+        json!([
+            { "dest_host": "b.com", "links": 1, "clicks": clicks(&seeded, "user2-0"), "owners": 1 },
+            { "dest_host": "a.com", "links": 2, "clicks": a_clicks, "owners": 1 },
+            { "dest_host": "gone.example", "links": 0, "clicks": 0, "owners": 0 },
+        ])
+    );
+    let links = report["links"].as_array().unwrap();
+    let rows: Vec<(&str, &str, &str)> = links
+        .iter()
+        .map(|link| {
+            (
+                link["dest_host"].as_str().unwrap(),
+                link["short_code"].as_str().unwrap(),
+                link["owner_email"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    let mut a_links = ["user1-0", "user1-1"];
+    a_links.sort_by_key(|code| -clicks(&seeded, code));
+    assert_eq!(
+        rows,
+        [
+            ("a.com", a_links[0], "user1@example.com"),
+            ("a.com", a_links[1], "user1@example.com"),
+            ("b.com", "user2-0", "user2@example.com"),
+        ]
+    );
+    assert!(report.get("min_links").is_none());
+
+    let second_page = get_json(&app, "?host=a.com&host=b.com&limit=2&page=2").await;
+    assert_eq!(second_page["links"][0]["short_code"], "user2-0");
+    assert_eq!(second_page["links"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_report_on_url_lists_links_to_the_same_destination() {
+    let storage = sqlite_storage().await;
+    let seeded = seed(&storage).await;
+    for (code, url) in [
+        (
+            "dup-tracked",
+            "https://A.com/1?utm_source=mail&utm_medium=x#top",
+        ),
+        ("dup-slash", "https://a.com:443/1/"),
+        ("other-query", "https://a.com/1?page=2"),
+    ] {
+        storage
+            .create_with_code(code, url, Some("user2"))
+            .await
+            .unwrap();
+    }
+    let app = create_test_api(storage).await;
+
+    let report = get_json(&app, "?url=http%3A%2F%2Fa.com%2F1").await;
+    assert_eq!(report["normalized_url"], "http://a.com/1");
+    assert_eq!(report["links"], json!([]));
+
+    let report = get_json(&app, "?url=https%3A%2F%2Fa.com%2F1%3Futm_campaign%3Dspring").await;
+    assert_eq!(report["normalized_url"], "https://a.com/1");
+    let codes: Vec<&str> = report["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|link| link["short_code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, ["user1-0", "dup-tracked", "dup-slash"]);
+    assert_eq!(
+        report["links"][1]["original_url"],
+        "https://A.com/1?utm_source=mail&utm_medium=x#top"
+    );
+    assert_eq!(report["hosts"][0]["dest_host"], "a.com");
+    assert_eq!(report["hosts"][0]["links"], 5);
+    assert_eq!(report["links"][0]["clicks"], clicks(&seeded, "user1-0"));
+}
+
+#[tokio::test]
+async fn test_report_without_hosts_groups_by_host() {
+    let storage = sqlite_storage().await;
+    let seeded = seed(&storage).await;
+    let app = create_test_api(storage).await;
+
+    let report = get_json(&app, "?min_links=2").await;
+    assert_eq!(report["min_links"], 2);
+    assert!(report.get("links").is_none());
+    let hosts: Vec<(&str, i64, i64)> = report["hosts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|host| {
+            (
+                host["dest_host"].as_str().unwrap(),
+                host["links"].as_i64().unwrap(),
+                host["owners"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(hosts, [("a.com", 2, 1), ("example.com", 2, 2)]);
+    assert_eq!(
+        report["hosts"][1]["clicks"],
+        clicks(&seeded, "user1-2") + clicks(&seeded, "user2-2")
+    );
+
+    let all = get_json(&app, "").await;
+    assert_eq!(all["min_links"], 1);
+    assert_eq!(all["hosts"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_report_as_csv() {
+    let storage = sqlite_storage().await;
+    seed(&storage).await;
+    let app = create_test_api(storage).await;
+
+    let (status, content_type, body) = get(&app, "?host=b.com&format=csv").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("text/csv; charset=utf-8"));
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(
+        lines[0],
+        "dest_host,short_code,original_url,created_by,owner_email,clicks,created_at,created_at_iso"
+    );
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[1].starts_with("b.com,user2-0,https://b.com/,user2,user2@example.com,"),
+        "{body}"
+    );
+
+    let (_, _, body) = get(&app, "?min_links=2&format=csv").await;
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines[0], "dest_host,links,clicks,owners");
+    assert!(lines[1].starts_with("a.com,2,"), "{body}");
+    assert!(lines[2].starts_with("example.com,2,"), "{body}");
+}
+
+#[tokio::test]
+async fn test_report_rejects_invalid_queries() {
+    let app = create_test_api(sqlite_storage().await).await;
+
+    for query in [
+        "?host=a.com&min_links=3",
+        "?min_links=0",
+        "?format=xlsx",
+        "?url=https%3A%2F%2Fa.com%2F&host=a.com",
+    ] {
+        let (status, _, _) = get(&app, query).await;
+        assert!(status.is_client_error(), "{query}: {status}");
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_dest_host_and_normalized_url_are_filled_for_existing_links() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("lynx-dest-host-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("lynx.db").display());
+
+    let storage = SqliteStorage::new(&url, 1).await.unwrap();
+    storage.ensure_schema().await.unwrap();
+    storage
+        .create_with_code("old", "https://Legacy.example/x#intro", None)
+        .await
+        .unwrap();
+    // Undo the migrations, as if the links predated them
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    for statement in [
+        "DROP INDEX idx_urls_dest_host",
+        "ALTER TABLE urls DROP COLUMN dest_host",
+        "DROP INDEX idx_urls_normalized_url",
+        "ALTER TABLE urls DROP COLUMN normalized_url",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+
+    storage.ensure_schema().await.unwrap();
+    let summary = storage
+        .destination_host_summary(&["legacy.example".to_string()], 1, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].links, 1);
+    let links = storage
+        .links_by_destination("https://legacy.example/x", 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].short_code, "old");
+    drop(storage);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "postgres")]
+const PG_SCHEMA: &str = "lynx_destination_report_test";
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_destination_report() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let admin = sqlx::PgPool::connect(&db_url).await.unwrap();
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {PG_SCHEMA} CASCADE"))
+        .execute(&admin)
+        .await
+        .unwrap();
+    let postgres = PostgresStorage::new_in_schema(&db_url, 2, Default::default(), Some(PG_SCHEMA))
+        .await
+        .unwrap();
+    postgres.ensure_schema().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(postgres);
+    let seeded = seed(&storage).await;
+
+    let summary = storage
+        .destination_host_summary(&[], 2, 10, 0)
+        .await
+        .unwrap();
+    let hosts: Vec<(&str, i64, i64)> = summary
+        .iter()
+        .map(|host| (host.dest_host.as_str(), host.links, host.owners))
+        .collect();
+    assert_eq!(hosts, [("a.com", 2, 1), ("example.com", 2, 2)]);
+    assert_eq!(
+        summary[0].clicks,
+        clicks(&seeded, "user1-0") + clicks(&seeded, "user1-1")
+    );
+
+    let links = storage
+        .links_by_destination_host(&["b.com".to_string()], 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].short_code, "user2-0");
+    assert_eq!(links[0].owner_email.as_deref(), Some("user2@example.com"));
+
+    let duplicates = storage
+        .links_by_destination("https://B.com/?utm_source=x", 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(duplicates, links);
+
+    // Rows from before the columns are filled when they are added
+    for column in ["dest_host", "normalized_url"] {
+        sqlx::query(&format!(
+            "ALTER TABLE {PG_SCHEMA}.urls DROP COLUMN {column}"
+        ))
+        .execute(&admin)
+        .await
+        .unwrap();
+    }
+    storage.ensure_schema().await.unwrap();
+    let refilled = storage
+        .destination_host_summary(&[], 2, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(refilled, summary);
+    let duplicates = storage
+        .links_by_destination("https://b.com", 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(duplicates, links);
+    admin.close().await;
+}
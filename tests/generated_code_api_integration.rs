@@ -0,0 +1,205 @@
+//! Integration tests for conflicts on short codes: generated codes retry past
+//! taken ones, custom codes report them, and hash codes return the link an
+//! identical request made or fall back to a random code
+#![cfg(feature = "sqlite")]
+
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Extension, Json};
+use lynx::api::{
+    code_hash::hash_code,
+    code_rng::CodeRng,
+    handlers::{create_url, AppState, ShortenedUrlResponse},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::system_clock;
+use lynx::config::{CodeGenerationConfig, CodeStrategy, Config};
+use lynx::models::CreateUrlRequest;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::json;
+use std::sync::Arc;
+
+mod common;
+
+const SEED: u64 = 42;
+
+async fn create_test_state() -> Arc<AppState> {
+    create_state(Config {
+        short_code_max_length: 3,
+        ..common::test_config()
+    })
+    .await
+}
+
+/// Hash codes by default, salted with `salt`
+async fn create_hash_state() -> Arc<AppState> {
+    create_state(Config {
+        code_generation: CodeGenerationConfig {
+            strategy: CodeStrategy::Hash,
+            hash_salt: Some("salt".to_string()),
+            hash_length: 6,
+        },
+        ..common::test_config()
+    })
+    .await
+}
+
+async fn create_state(config: Config) -> Arc<AppState> {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let config = Arc::new(config);
+    Arc::new(AppState {
+        storage: Arc::new(storage),
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::seeded(SEED),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    })
+}
+
+async fn create(state: &Arc<AppState>, custom_code: Option<&str>) -> Result<String, StatusCode> {
+    let (status, response) = create_request(
+        state,
+        CreateUrlRequest {
+            url: "https://example.com/generated".to_string(),
+            custom_code: custom_code.map(str::to_string),
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at: None,
+            max_clicks: None,
+        },
+    )
+    .await?;
+    assert_eq!(status, StatusCode::CREATED);
+    Ok(response.inner.short_code.clone())
+}
+
+async fn create_request(
+    state: &Arc<AppState>,
+    request: CreateUrlRequest,
+) -> Result<(StatusCode, ShortenedUrlResponse), StatusCode> {
+    create_url(
+        State(Arc::clone(state)),
+        PublicBaseUrl(state.config.redirect_base_url.clone()),
+        Extension(Some(AuthClaims(Arc::new(json!({ "sub": "alice" }))))),
+        HeaderMap::new(),
+        Json(request),
+    )
+    .await
+    .map(|(status, Json(response))| (status, response))
+    .map_err(|error| error.status_code())
+}
+
+fn hash_request(url: &str, code_strategy: Option<CodeStrategy>) -> CreateUrlRequest {
+    CreateUrlRequest {
+        url: url.to_string(),
+        custom_code: None,
+        created_by_override: None,
+        hide_stats: None,
+        interstitial: None,
+        code_strategy,
+        campaign_id: None,
+        expires_at: None,
+        max_clicks: None,
+    }
+}
+
+#[tokio::test]
+async fn test_generated_code_collisions_are_retried() {
+    let state = create_test_state().await;
+
+    // The same seed yields the codes the handler will try first
+    let predicted = CodeRng::seeded(SEED);
+    let taken: Vec<String> = (0..3).map(|_| predicted.code(3)).collect();
+    for code in &taken {
+        state
+            .storage
+            .create_with_code(code, "https://example.com/taken", Some("bob"))
+            .await
+            .unwrap();
+    }
+
+    let code = create(&state, None).await.unwrap();
+    assert_eq!(code.len(), 3);
+    assert!(!taken.contains(&code));
+    let stored = state.storage.get(&code).await.unwrap().unwrap();
+    assert_eq!(stored.original_url, "https://example.com/generated");
+    for code in &taken {
+        let untouched = state.storage.get(code).await.unwrap().unwrap();
+        assert_eq!(untouched.original_url, "https://example.com/taken");
+    }
+}
+
+#[tokio::test]
+async fn test_custom_code_collision_is_a_conflict() {
+    let state = create_test_state().await;
+    create(&state, Some("ab1")).await.unwrap();
+    assert_eq!(create(&state, Some("ab1")).await, Err(StatusCode::CONFLICT));
+}
+
+#[tokio::test]
+async fn test_repeated_hash_requests_return_the_same_link() {
+    let state = create_hash_state().await;
+
+    let (status, first) = create_request(&state, hash_request("https://example.com/docs", None))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        first.inner.short_code,
+        hash_code("salt", Some("alice"), "https://example.com/docs", 6)
+    );
+
+    // An equivalent destination converges on the same link
+    let (status, again) = create_request(
+        &state,
+        hash_request("https://example.com/docs/#intro", None),
+    )
+    .await
+    .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again.inner.short_code, first.inner.short_code);
+    assert!(again.warnings.is_empty());
+
+    // The request can still ask for a random code
+    let (status, random) = create_request(
+        &state,
+        hash_request("https://example.com/docs", Some(CodeStrategy::Random)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(status, StatusCode::CREATED);
+    assert_ne!(random.inner.short_code, first.inner.short_code);
+}
+
+#[tokio::test]
+async fn test_hash_code_collisions_fall_back_to_a_random_code() {
+    let state = create_hash_state().await;
+    let code = hash_code("salt", Some("alice"), "https://example.com/docs", 6);
+    state
+        .storage
+        .create_with_code(&code, "https://example.com/other", Some("alice"))
+        .await
+        .unwrap();
+
+    let (status, created) = create_request(&state, hash_request("https://example.com/docs", None))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::CREATED);
+    assert_ne!(created.inner.short_code, code);
+    let warnings = serde_json::to_value(&created.warnings).unwrap();
+    assert_eq!(warnings[0]["code"], json!("hash_code_collision"));
+    assert_eq!(warnings[0]["details"], json!({ "hash_code": code }));
+}
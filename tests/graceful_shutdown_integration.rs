@@ -0,0 +1,98 @@
+//! Integration tests for graceful shutdown of the servers
+//!
+//! Both the API and redirect servers run through `lynx::shutdown::serve`;
+//! these tests bind a real listener, trigger shutdown while a slow request is
+//! in flight and check that the request still completes.
+
+use axum::{routing::get, Router};
+use lynx::shutdown;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+
+/// A router whose only route signals `started` and then answers after `delay`
+fn slow_router(started: Arc<Notify>, delay: Duration) -> Router {
+    Router::new().route(
+        "/slow",
+        get(move || {
+            let started = Arc::clone(&started);
+            async move {
+                started.notify_one();
+                tokio::time::sleep(delay).await;
+                "done"
+            }
+        }),
+    )
+}
+
+#[tokio::test]
+async fn test_in_flight_request_completes_during_shutdown() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let started = Arc::new(Notify::new());
+    let (trigger, signal) = shutdown::channel();
+    let server = tokio::spawn(shutdown::serve(
+        listener,
+        slow_router(Arc::clone(&started), Duration::from_millis(300)),
+        signal,
+    ));
+
+    let client = reqwest::Client::new();
+    let request = tokio::spawn({
+        let client = client.clone();
+        async move { client.get(format!("http://{addr}/slow")).send().await }
+    });
+    started.notified().await;
+    trigger.trigger();
+
+    let response = request.await.unwrap().expect("in-flight request dropped");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "done");
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server did not stop after draining")
+        .unwrap()
+        .unwrap();
+
+    // The listener is closed once the server returns
+    assert!(reqwest::Client::new()
+        .get(format!("http://{addr}/slow"))
+        .send()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_one_trigger_stops_every_server() {
+    let (trigger, signal) = shutdown::channel();
+    let mut servers = Vec::new();
+    for _ in 0..2 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let router = slow_router(Arc::new(Notify::new()), Duration::ZERO);
+        servers.push(tokio::spawn(shutdown::serve(
+            listener,
+            router,
+            signal.clone(),
+        )));
+    }
+
+    trigger.trigger();
+    for server in servers {
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server ignored the shared shutdown")
+            .unwrap()
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_dropped_trigger_counts_as_shutdown() {
+    let (trigger, signal) = shutdown::channel();
+    drop(trigger);
+    tokio::time::timeout(Duration::from_secs(1), signal.wait())
+        .await
+        .expect("signal kept waiting after its trigger was dropped");
+}
@@ -0,0 +1,199 @@
+//! Integration tests for permanently deleting links through
+//! `DELETE /api/admin/links/{code}?confirm=CODE`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, DatabaseConfig};
+use lynx::redirect;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+fn create_test_config(allow_hard_delete: bool) -> Arc<Config> {
+    let config = common::test_config();
+    Arc::new(Config {
+        database: DatabaseConfig {
+            allow_hard_delete,
+            ..config.database
+        },
+        ..config
+    })
+}
+
+async fn create_test_storage(allow_hard_delete: bool) -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_hard_delete(allow_hard_delete);
+    inner.init().await.unwrap();
+    let storage = Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10));
+    storage
+        .create_with_code("private", "https://example.com/private", Some("alice"))
+        .await
+        .unwrap();
+    storage
+        .add_alias("private", "other", Some("alice"))
+        .await
+        .unwrap()
+        .unwrap();
+    storage
+}
+
+async fn create_test_app(storage: &Arc<CachedStorage>, allow_hard_delete: bool) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(allow_hard_delete),
+        None,
+    )
+}
+
+async fn hard_delete(app: &Router, code: &str, confirm: Option<&str>) -> (StatusCode, Value) {
+    let mut uri = format!("/api/admin/links/{}", URL_SAFE_NO_PAD.encode(code));
+    if let Some(confirm) = confirm {
+        uri.push_str(&format!("?confirm={confirm}"));
+    }
+    let request = Request::builder()
+        .method("DELETE")
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn redirect_status(redirects: &Router, code: &str) -> StatusCode {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    redirects.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn test_hard_delete_is_refused_unless_enabled() {
+    let storage = create_test_storage(false).await;
+    let app = create_test_app(&storage, false).await;
+
+    let (status, _) = hard_delete(&app, "private", Some("private")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(storage
+        .get_authoritative("private")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_hard_delete_needs_the_code_confirmed() {
+    let storage = create_test_storage(true).await;
+    let app = create_test_app(&storage, true).await;
+
+    for confirm in [None, Some("other"), Some("")] {
+        let (status, _) = hard_delete(&app, "private", confirm).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{confirm:?}");
+    }
+    assert!(storage
+        .get_authoritative("private")
+        .await
+        .unwrap()
+        .is_some());
+
+    let (status, _) = hard_delete(&app, "missing", Some("missing")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_hard_delete_removes_cached_links_and_is_audited() {
+    let storage = create_test_storage(true).await;
+    let app = create_test_app(&storage, true).await;
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        None,
+        false,
+        StatusCode::FOUND,
+    );
+    for code in ["private", "other"] {
+        assert_eq!(redirect_status(&redirects, code).await, StatusCode::FOUND);
+    }
+    storage.flush().await.unwrap();
+
+    let (status, json) = hard_delete(&app, "private", Some("private")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["codes"], serde_json::json!(["private", "other"]));
+    assert_eq!(json["click_history_rows"], 1);
+
+    // Both codes were cached; neither redirects any more
+    for code in ["private", "other"] {
+        assert_eq!(
+            redirect_status(&redirects, code).await,
+            StatusCode::NOT_FOUND
+        );
+        assert!(storage.get_authoritative(code).await.unwrap().is_none());
+    }
+    let audit = storage.get_audit_log("private").await.unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].action, "hard_delete");
+    assert_eq!(audit[0].effective_user, "alice");
+
+    let (status, _) = hard_delete(&app, "private", Some("private")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_buffered_clicks_do_not_reach_a_recreated_code() {
+    let storage = create_test_storage(true).await;
+    let app = create_test_app(&storage, true).await;
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        None,
+        false,
+        StatusCode::FOUND,
+    );
+    for _ in 0..3 {
+        assert_eq!(
+            redirect_status(&redirects, "private").await,
+            StatusCode::FOUND
+        );
+    }
+
+    // The clicks are still buffered when the link is deleted
+    let (status, _) = hard_delete(&app, "private", Some("private")).await;
+    assert_eq!(status, StatusCode::OK);
+    storage
+        .create_with_code("private", "https://example.com/new", Some("bob"))
+        .await
+        .unwrap();
+    storage.flush().await.unwrap();
+
+    let link = storage.get_authoritative("private").await.unwrap().unwrap();
+    assert_eq!(link.clicks, 0);
+}
@@ -0,0 +1,100 @@
+//! Integration tests for `FRONTEND_ENABLED=false`
+//!
+//! A headless API server mounts `/api` only: `/` answers with an API index
+//! and every other path, UI routes and assets included, is a 404.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, FrontendConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+async fn create_test_api(frontend_enabled: bool) -> Router {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let config = Arc::new(Config {
+        frontend: FrontendConfig {
+            enabled: frontend_enabled,
+            static_dir: None,
+        },
+        ..common::test_config()
+    });
+    api::routes::create_api_router(Arc::new(storage), auth_service, config, None)
+}
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_root_answers_with_api_index() {
+    let app = create_test_api(false).await;
+
+    let (status, json) = get(&app, "/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json,
+        json!({
+            "name": "lynx",
+            "version": env!("CARGO_PKG_VERSION"),
+            "api": "/api",
+            "health": "/api/health",
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_frontend_routes_and_assets_are_not_mounted() {
+    let app = create_test_api(false).await;
+
+    for uri in ["/index.html", "/login", "/links/abc", "/assets/index.js"] {
+        let (status, _) = get(&app, uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_api_is_unaffected() {
+    let app = create_test_api(false).await;
+
+    let (status, json) = get(&app, "/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["message"], "OK");
+}
+
+#[tokio::test]
+async fn test_enabled_frontend_keeps_serving_the_ui_at_root() {
+    let app = create_test_api(true).await;
+
+    // Whatever the UI answers, it is not the API index
+    let (_, json) = get(&app, "/").await;
+    assert_eq!(json, Value::Null);
+}
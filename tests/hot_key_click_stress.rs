@@ -0,0 +1,253 @@
+//! Stress test for one very hot short code
+//!
+//! Many tasks redirect through the same code for several flush intervals,
+//! through the redirect router, `CachedStorage`, the click actor and its
+//! flushes to storage. Afterwards the stored click count must equal the
+//! redirects served exactly, storage must never fall more than two flush
+//! intervals behind, memory must stay flat and every flush must finish
+//! within one interval.
+//!
+//! Ignored by default; it runs for about ten seconds:
+//! `cargo test --release --test hot_key_click_stress -- --ignored --nocapture`
+//! With `DATABASE_BACKEND=postgres` and `DATABASE_URL` it runs on Postgres.
+#![cfg(feature = "sqlite")]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use lynx::redirect::create_redirect_router;
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use tokio::task::JoinSet;
+use tower::ServiceExt;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+/// Concurrent clients redirecting through the hot code
+const TASKS: usize = 64;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Flush intervals each scenario runs for
+const INTERVALS: u32 = 4;
+const SAMPLE_EVERY: Duration = Duration::from_millis(100);
+/// What one instance must absorb for a single code (TV-spot peak)
+const REQUIRED_RATE: f64 = 2_000.0;
+/// Resident memory the run may add once warmed up
+const MAX_RSS_GROWTH: u64 = 64 * 1024 * 1024;
+
+/// `duration_ms` and `entries` of each `clicks` flush.
+type Flushes = Arc<Mutex<Vec<(u64, u64)>>>;
+
+struct ClickFlushes(Flushes);
+
+struct Fields(HashMap<String, String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for ClickFlushes {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields(HashMap::new());
+        event.record(&mut fields);
+        let field = |name: &str| fields.0.get(name).map(String::as_str);
+        if field("message") == Some("flush completed") && field("stage") == Some("clicks") {
+            let number = |name: &str| field(name).and_then(|value| value.parse().ok());
+            if let (Some(duration_ms), Some(entries)) = (number("duration_ms"), number("entries")) {
+                self.0.lock().unwrap().push((duration_ms, entries));
+            }
+        }
+    }
+}
+
+async fn create_backend_storage() -> Arc<dyn Storage> {
+    #[cfg(feature = "postgres")]
+    if std::env::var("DATABASE_BACKEND").as_deref() == Ok("postgres") {
+        let url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set for PostgreSQL integration tests");
+        let storage = PostgresStorage::new(&url, 5).await.unwrap();
+        storage.init().await.unwrap();
+        return Arc::new(storage);
+    }
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    Arc::new(storage)
+}
+
+/// Resident set size of this process, where `/proc` has it.
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+struct Outcome {
+    served: u64,
+    rate: f64,
+    /// Most redirects served but not yet in storage at any sample
+    max_backlog: u64,
+    rss_growth: Option<u64>,
+}
+
+/// Redirect `TASKS` clients through one code for `INTERVALS` flush intervals
+/// with an actor queue of `actor_buffer_size` clicks.
+async fn run_scenario(actor_buffer_size: usize, flushes: &Flushes) -> Outcome {
+    let inner = create_backend_storage().await;
+    let code = format!("hot_{}_{}", actor_buffer_size, std::process::id());
+    inner
+        .create_with_code(&code, "https://example.com/tv-spot", None)
+        .await
+        .unwrap();
+    let cached = Arc::new(CachedStorage::new(
+        Arc::clone(&inner),
+        10_000,
+        FLUSH_INTERVAL.as_secs(),
+        actor_buffer_size,
+        100,
+    ));
+    let app: Router = create_redirect_router(Arc::clone(&cached), None, false, StatusCode::FOUND);
+
+    let served = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut clients = JoinSet::new();
+    for _ in 0..TASKS {
+        let (app, served, stop, uri) = (
+            app.clone(),
+            Arc::clone(&served),
+            Arc::clone(&stop),
+            format!("/{code}"),
+        );
+        clients.spawn(async move {
+            while !stop.load(Ordering::Relaxed) {
+                let request = Request::builder().uri(&uri).body(Body::empty()).unwrap();
+                let response = app.clone().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::FOUND);
+                served.fetch_add(1, Ordering::Relaxed);
+                // A cached redirect never waits, so yield as a connection
+                // would between requests rather than starve the workers
+                tokio::task::yield_now().await;
+            }
+        });
+    }
+
+    // Sample what was served against what reached storage
+    let started = Instant::now();
+    let run_for = FLUSH_INTERVAL * INTERVALS;
+    let mut samples: Vec<(Duration, u64)> = Vec::new();
+    let mut max_backlog = 0;
+    let mut rss_after_warmup = None;
+    let mut rss_growth = None;
+    while started.elapsed() < run_for {
+        tokio::time::sleep(SAMPLE_EVERY).await;
+        let at = started.elapsed();
+        let persisted = inner.get(&code).await.unwrap().unwrap().clicks as u64;
+        let now_served = served.load(Ordering::Relaxed);
+        samples.push((at, now_served));
+        max_backlog = max_backlog.max(now_served.saturating_sub(persisted));
+
+        // Everything served two intervals ago must be stored by now
+        if let Some(deadline) = at.checked_sub(FLUSH_INTERVAL * 2 + SAMPLE_EVERY) {
+            let due = samples
+                .iter()
+                .take_while(|(sampled_at, _)| *sampled_at <= deadline)
+                .last()
+                .map_or(0, |(_, served)| *served);
+            assert!(
+                persisted >= due,
+                "storage fell behind: {persisted} stored at {at:?}, {due} served by {deadline:?}"
+            );
+        }
+
+        if at >= FLUSH_INTERVAL {
+            let rss = rss_bytes();
+            let baseline = *rss_after_warmup.get_or_insert(rss);
+            if let (Some(baseline), Some(rss)) = (baseline, rss) {
+                rss_growth = Some(rss.saturating_sub(baseline).max(rss_growth.unwrap_or(0)));
+            }
+        }
+    }
+    stop.store(true, Ordering::Relaxed);
+    while let Some(client) = clients.join_next().await {
+        client.unwrap();
+    }
+    let elapsed = started.elapsed();
+    let served = served.load(Ordering::Relaxed);
+
+    cached.shutdown().await;
+    let stored = inner.get(&code).await.unwrap().unwrap().clicks as u64;
+    assert_eq!(stored, served, "every redirect is counted exactly once");
+    assert!(!flushes.lock().unwrap().is_empty(), "clicks were flushed");
+
+    Outcome {
+        served,
+        rate: served as f64 / elapsed.as_secs_f64(),
+        max_backlog,
+        rss_growth,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[ignore = "stress test; run with --ignored --nocapture, preferably with --release"]
+async fn test_hot_key_clicks_are_counted_exactly() {
+    let flushes = Flushes::default();
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::registry().with(ClickFlushes(Arc::clone(&flushes))),
+    )
+    .unwrap();
+
+    // The default queue, and one so small that nearly every click takes the
+    // overflow path into the shared flush layer
+    for (scenario, actor_buffer_size) in [("queue", 100_000), ("overflow", 16)] {
+        flushes.lock().unwrap().clear();
+        let outcome = run_scenario(actor_buffer_size, &flushes).await;
+        let flushes = std::mem::take(&mut *flushes.lock().unwrap());
+        let slowest_ms = flushes.iter().map(|(duration, _)| *duration).max().unwrap();
+
+        println!(
+            "{scenario}: {} redirects, {:.0}/s, {} flushes (slowest {slowest_ms} ms), \
+             max backlog {} clicks, RSS growth {}",
+            outcome.served,
+            outcome.rate,
+            flushes.len(),
+            outcome.max_backlog,
+            outcome
+                .rss_growth
+                .map_or("unknown".to_string(), |bytes| format!(
+                    "{} KiB",
+                    bytes / 1024
+                )),
+        );
+        assert!(
+            outcome.rate >= REQUIRED_RATE,
+            "{scenario}: only {:.0} redirects/s",
+            outcome.rate
+        );
+        assert!(
+            flushes.iter().all(|(_, entries)| *entries == 1),
+            "{scenario}: one code is one flushed row: {flushes:?}"
+        );
+        assert!(
+            u128::from(slowest_ms) < FLUSH_INTERVAL.as_millis(),
+            "{scenario}: a flush took {slowest_ms} ms"
+        );
+        if let Some(growth) = outcome.rss_growth {
+            assert!(
+                growth < MAX_RSS_GROWTH,
+                "{scenario}: RSS grew by {growth} bytes"
+            );
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Integration tests for daily instance statistics: recording a day on both
+//! backends and serving the history to admins.
+//!
+//! Like `storage_integration_test`, `DATABASE_BACKEND` picks the backend and
+//! the Postgres variant needs `DATABASE_URL`. The Postgres database may hold
+//! other tests' links, so counts are compared before and after.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    extract::{Query, State},
+    Extension,
+};
+use lynx::analytics::daily::DAY_SECS;
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::AppState,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+    stats::{get_stats_history, StatsHistoryQuery},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::{Clock, FakeClock};
+use lynx::models::InstanceStatsDay;
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// 2023-03-10T00:00:00Z
+const DAY: i64 = 1_678_406_400;
+
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(selected) => selected == backend,
+        Err(_) => true,
+    }
+}
+
+fn delta(before: InstanceStatsDay, after: InstanceStatsDay) -> InstanceStatsDay {
+    InstanceStatsDay {
+        day: after.day,
+        total_links: after.total_links - before.total_links,
+        active_links: after.active_links - before.active_links,
+        links_created: after.links_created - before.links_created,
+        clicks: after.clicks - before.clicks,
+        active_users: after.active_users - before.active_users,
+    }
+}
+
+async fn assert_day_is_recorded(storage: &dyn Storage, clock: &FakeClock, prefix: &str) {
+    let before = storage.record_instance_stats(DAY).await.unwrap();
+
+    clock.set_epoch_ms((DAY + 3600) * 1000);
+    for (code, user) in [("a", "alice"), ("b", "alice"), ("c", "bob")] {
+        storage
+            .create_with_code(
+                &format!("{prefix}_{code}"),
+                "https://example.com/stats",
+                Some(&format!("{prefix}_{user}")),
+            )
+            .await
+            .unwrap();
+    }
+    storage
+        .add_alias(&format!("{prefix}_a"), &format!("{prefix}_alias"), None)
+        .await
+        .unwrap();
+    storage
+        .increment_clicks(&format!("{prefix}_a"), 5)
+        .await
+        .unwrap();
+    storage.deactivate(&format!("{prefix}_c")).await.unwrap();
+
+    // The next day's links are not part of this one
+    clock.advance(Duration::from_secs(DAY_SECS as u64));
+    storage
+        .create_with_code(
+            &format!("{prefix}_next"),
+            "https://example.com/stats",
+            Some(&format!("{prefix}_carol")),
+        )
+        .await
+        .unwrap();
+
+    // Halfway through a day, the middle of the day counts as its start
+    let recorded = storage.record_instance_stats(DAY + 600).await.unwrap();
+    assert_eq!(
+        delta(before, recorded),
+        InstanceStatsDay {
+            day: DAY,
+            total_links: 3,
+            active_links: 2,
+            links_created: 3,
+            clicks: 5,
+            active_users: 2,
+        }
+    );
+
+    // Recording the day again replaces its row
+    assert_eq!(storage.record_instance_stats(DAY).await.unwrap(), recorded);
+    let history = storage.instance_stats_history(DAY).await.unwrap();
+    assert_eq!(history.iter().filter(|stats| stats.day == DAY).count(), 1);
+    assert_eq!(history[0], recorded);
+    assert!(storage
+        .instance_stats_history(DAY + DAY_SECS)
+        .await
+        .unwrap()
+        .iter()
+        .all(|stats| stats.day > DAY));
+}
+
+#[tokio::test]
+async fn test_instance_stats_are_recorded_once_per_day_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY * 1000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    assert!(storage.instance_stats_history(0).await.unwrap().is_empty());
+    assert_day_is_recorded(&storage, &clock, "lite").await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_instance_stats_are_recorded_once_per_day_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY * 1000));
+    let storage = PostgresStorage::new(&db_url, 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+
+    let prefix = format!("pg_stats_{}", std::process::id());
+    assert_day_is_recorded(&storage, &clock, &prefix).await;
+}
+
+async fn history(
+    state: &Arc<AppState>,
+    is_admin: bool,
+    days: Option<i64>,
+) -> Result<serde_json::Value, axum::http::StatusCode> {
+    get_stats_history(
+        State(Arc::clone(state)),
+        Extension(Some(AuthClaims(Arc::new(
+            json!({ "sub": "root", "is_admin": is_admin }),
+        )))),
+        Query(StatsHistoryQuery { days }),
+    )
+    .await
+    .map(|response| serde_json::to_value(response.0).unwrap())
+    .map_err(|error| error.status_code())
+}
+
+#[tokio::test]
+async fn test_stats_history_is_admin_only_and_keeps_gaps() {
+    // Noon, three days after DAY
+    let clock = Arc::new(FakeClock::at_epoch_ms((DAY + 3 * DAY_SECS + 43_200) * 1000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    let config = Arc::new(common::test_config());
+    let state = Arc::new(AppState {
+        storage: Arc::new(storage),
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: clock.clone(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+
+    assert_eq!(
+        history(&state, false, None).await,
+        Err(axum::http::StatusCode::FORBIDDEN)
+    );
+
+    let now_ms = clock.now_epoch_ms();
+    clock.set_epoch_ms((DAY + 2 * DAY_SECS + 60) * 1000);
+    state
+        .storage
+        .create_with_code("stats", "https://example.com/stats", Some("alice"))
+        .await
+        .unwrap();
+    clock.set_epoch_ms(now_ms);
+    // DAY + 1 was missed; today is still running
+    for day in [DAY, DAY + 2 * DAY_SECS, DAY + 3 * DAY_SECS] {
+        state.storage.record_instance_stats(day).await.unwrap();
+    }
+
+    let response = history(&state, true, Some(3)).await.unwrap();
+    assert_eq!(response["days"], 3);
+    let points = response["history"].as_array().unwrap();
+    let days: Vec<i64> = points.iter().map(|p| p["day"].as_i64().unwrap()).collect();
+    assert_eq!(days, [DAY, DAY + DAY_SECS, DAY + 2 * DAY_SECS]);
+    assert_eq!(points[0]["total_links"], 0);
+    assert!(points[1]["total_links"].is_null());
+    assert!(points[1]["clicks"].is_null());
+    assert_eq!(points[2]["total_links"], 1);
+    assert_eq!(points[2]["links_created"], 1);
+
+    let response = history(&state, true, None).await.unwrap();
+    assert_eq!(response["days"], 90);
+    assert_eq!(response["history"].as_array().unwrap().len(), 90);
+    let response = history(&state, true, Some(100_000)).await.unwrap();
+    assert_eq!(response["days"], 366);
+}
@@ -0,0 +1,310 @@
+//! Integration tests for the IPv4/IPv6 split: redirects record the visitor's
+//! IP version (IPv4-mapped IPv6 peers count as IPv4), and
+//! `group_by=ip_version` and `GET /api/admin/stats/ip-versions` report it
+//!
+//! Like `storage_integration_test`, `DATABASE_BACKEND` picks the backend for
+//! the storage tests and the Postgres variant needs `DATABASE_URL`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::analytics::{AnalyticsAggregator, AnalyticsGroupBy, AnalyticsRollup, IpVersion};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AnalyticsConfig, AuthConfig, AuthMode};
+use lynx::redirect::{self, RedirectAnalytics};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// 2024-01-01T00:00:00Z, long enough ago for pruning to merge it
+const OLD_HOUR: i64 = 1_704_067_200;
+
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(selected) => selected == backend,
+        Err(_) => true,
+    }
+}
+
+fn rollup(
+    short_code: &str,
+    time_bucket: i64,
+    ip_version: IpVersion,
+    visits: i64,
+) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket,
+        country_code: Some("US".to_string()),
+        region: None,
+        city: None,
+        asn: None,
+        ip_version,
+        visit_count: visits,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+fn split(aggregates: Vec<lynx::analytics::AnalyticsAggregate>) -> Vec<(String, i64)> {
+    let mut split: Vec<(String, i64)> = aggregates
+        .into_iter()
+        .map(|aggregate| (aggregate.dimension, aggregate.visit_count))
+        .collect();
+    split.sort();
+    split
+}
+
+async fn assert_versions_are_labelled(storage: &dyn Storage, prefix: &str) {
+    let code = format!("{prefix}_ipv");
+    storage
+        .create_with_code(&code, "https://example.com/ipv", None)
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup(&code, OLD_HOUR, IpVersion::V4, 3),
+            rollup(&code, OLD_HOUR, IpVersion::V6, 2),
+            rollup(&code, OLD_HOUR + 3600, IpVersion::V6, 4),
+        ])
+        .await
+        .unwrap();
+
+    let by_version = storage
+        .get_analytics_aggregate(&code, None, None, AnalyticsGroupBy::IpVersion, 10)
+        .await
+        .unwrap();
+    assert_eq!(by_version[0].dimension, "IPv6");
+    assert_eq!(
+        split(by_version),
+        vec![("IPv4".to_string(), 3), ("IPv6".to_string(), 6)]
+    );
+
+    // The instance-wide split counts this link's hour, along with whatever
+    // else a shared database holds for it
+    let instance = storage
+        .get_instance_ip_version_split(Some(OLD_HOUR), Some(OLD_HOUR))
+        .await
+        .unwrap();
+    let visits = |label: &str| {
+        instance
+            .iter()
+            .find(|aggregate| aggregate.dimension == label)
+            .map_or(0, |aggregate| aggregate.visit_count)
+    };
+    assert!(visits("IPv4") >= 3);
+    assert!(visits("IPv6") >= 2);
+}
+
+#[tokio::test]
+async fn test_ip_versions_are_labelled_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    assert_versions_are_labelled(&storage, "lite").await;
+
+    // Only this database's rows are in range, so the split is exact
+    let instance = storage
+        .get_instance_ip_version_split(Some(OLD_HOUR), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        split(instance),
+        vec![("IPv4".to_string(), 3), ("IPv6".to_string(), 6)]
+    );
+    assert!(storage
+        .get_instance_ip_version_split(None, Some(OLD_HOUR - 1))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_ip_versions_are_labelled_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let storage = PostgresStorage::new(&db_url, 5).await.unwrap();
+    storage.init().await.unwrap();
+
+    let prefix = format!("pg_ipv_{}", std::process::id());
+    assert_versions_are_labelled(&storage, &prefix).await;
+}
+
+async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn visit(redirects: &Router, code: &str, peer: &str) {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    let peer: IpAddr = peer.parse().unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::new(peer, 40_000)));
+    let status = redirects.clone().oneshot(request).await.unwrap().status();
+    assert_eq!(status, StatusCode::FOUND);
+}
+
+fn versions(body: &Value) -> Vec<(String, i64, bool)> {
+    let mut versions: Vec<(String, i64, bool)> = body["aggregates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|aggregate| {
+            (
+                aggregate["dimension"].as_str().unwrap().to_string(),
+                aggregate["visit_count"].as_i64().unwrap(),
+                aggregate["aggregated"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    versions.sort();
+    versions
+}
+
+#[tokio::test]
+async fn test_redirects_feed_the_ip_version_split() {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let storage = Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10));
+    for code in ["docs", "blog"] {
+        storage
+            .create_with_code(code, "https://example.com/", None)
+            .await
+            .unwrap();
+    }
+
+    // Without a GeoIP database, as in the default deployment
+    let aggregator = Arc::new(AnalyticsAggregator::new());
+    let flush_storage = Arc::clone(&storage);
+    let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
+        let storage = Arc::clone(&flush_storage);
+        Box::pin(async move {
+            let records = entries
+                .into_iter()
+                .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                .collect();
+            storage.upsert_known_analytics_batch(records).await?;
+            Ok(())
+        })
+    });
+    let analytics = RedirectAnalytics::from_enabled(
+        AnalyticsConfig {
+            enabled: true,
+            ..AnalyticsConfig::default()
+        },
+        Arc::clone(&aggregator),
+    )
+    .unwrap();
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        Some(analytics),
+        false,
+        StatusCode::FOUND,
+    );
+
+    // A dual-stack listener reports IPv4 peers as IPv4-mapped IPv6
+    for peer in ["::ffff:192.0.2.1", "::ffff:192.0.2.1", "192.0.2.2"] {
+        visit(&redirects, "docs", peer).await;
+    }
+    visit(&redirects, "docs", "2001:db8::1").await;
+    visit(&redirects, "blog", "2001:db8::2").await;
+
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+
+    // Old visits whose version is dropped by pruning become unknown
+    storage
+        .upsert_analytics_batch(vec![rollup("docs", OLD_HOUR, IpVersion::V6, 5)])
+        .await
+        .unwrap();
+    storage
+        .prune_analytics(1, &["ip_version".to_string()])
+        .await
+        .unwrap();
+
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let app = api::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        Arc::new(common::test_config()),
+        None,
+    );
+
+    let (status, body) = get_json(
+        &app,
+        &format!(
+            "/api/analytics/{}/aggregate?group_by=ip_version",
+            URL_SAFE_NO_PAD.encode("docs")
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        versions(&body),
+        vec![
+            ("IPv4".to_string(), 3, false),
+            ("IPv6".to_string(), 1, false),
+            ("unknown".to_string(), 5, true),
+        ]
+    );
+
+    let (status, body) = get_json(&app, "/api/admin/stats/ip-versions").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_visits"], json!(10));
+    assert_eq!(
+        versions(&body),
+        vec![
+            ("IPv4".to_string(), 3, false),
+            ("IPv6".to_string(), 2, false),
+            ("unknown".to_string(), 5, true),
+        ]
+    );
+
+    // Pruning dated the merged visits a day back; the redirects are recent
+    let an_hour_ago = chrono::Utc::now().timestamp() - 3600;
+    let (_, body) = get_json(
+        &app,
+        &format!("/api/admin/stats/ip-versions?end_time={an_hour_ago}"),
+    )
+    .await;
+    assert_eq!(body["total_visits"], json!(5));
+}
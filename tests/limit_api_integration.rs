@@ -0,0 +1,161 @@
+//! Integration tests for `limit` handling on list-style API endpoints
+//!
+//! Every endpoint that accepts a `limit` query parameter must apply the same
+//! rules: missing values use the endpoint default, zero and negative values
+//! are raised to 1, oversized values are capped at the configured maximum, and
+//! the effective limit is echoed back in the response body.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, PaginationConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use lynx::testing::Fixture;
+use serde_json::Value;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Number of URLs (and analytics rows) seeded for every test.
+const SEEDED_ROWS: usize = 8;
+
+/// Helper to create test config with the given pagination limits
+fn create_test_config(pagination: PaginationConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        pagination,
+        ..common::test_config()
+    })
+}
+
+/// Small maxima so that clamping is observable with a handful of rows.
+fn small_limits() -> PaginationConfig {
+    PaginationConfig {
+        list_max_limit: 3,
+        search_max_limit: 4,
+        analytics_max_limit: 5,
+        ..PaginationConfig::default()
+    }
+}
+
+/// Build an API router over storage seeded with URLs and analytics rows.
+async fn create_test_app(pagination: PaginationConfig) -> Router {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+
+    // One analytics row per day, each from a different country
+    Fixture::new(storage.clone())
+        .links_per_user(SEEDED_ROWS)
+        .with_analytics_days(SEEDED_ROWS as u32)
+        .seed()
+        .await
+        .unwrap();
+
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::routes::create_api_router(storage, auth_service, create_test_config(pagination), None)
+}
+
+/// Endpoints under test, paired with the JSON key holding their rows.
+fn endpoints() -> [(String, &'static str); 4] {
+    let code = URL_SAFE_NO_PAD.encode("user1-0");
+    [
+        ("/api/urls?".to_string(), "urls"),
+        ("/api/urls/search?q=user1&".to_string(), "items"),
+        (format!("/api/analytics/{code}?"), "entries"),
+        (format!("/api/analytics/{code}/aggregate?"), "aggregates"),
+    ]
+}
+
+async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Assert the echoed limit and the number of rows returned for `query`.
+async fn assert_limit(app: &Router, query: &str, expected: [i64; 4]) {
+    for ((prefix, key), expected) in endpoints().into_iter().zip(expected) {
+        let uri = format!("{prefix}{query}");
+        let (status, json) = get_json(app, &uri).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        assert_eq!(json["limit"], expected, "{uri}");
+        let rows = json[key].as_array().unwrap().len();
+        assert_eq!(rows, SEEDED_ROWS.min(expected as usize), "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_missing_limit_uses_endpoint_defaults() {
+    let app = create_test_app(PaginationConfig::default()).await;
+    assert_limit(&app, "", [50, 50, 100, 100]).await;
+}
+
+#[tokio::test]
+async fn test_missing_limit_default_is_capped_by_configured_max() {
+    let app = create_test_app(small_limits()).await;
+    assert_limit(&app, "", [3, 4, 5, 5]).await;
+}
+
+#[tokio::test]
+async fn test_zero_limit_returns_one_row() {
+    let app = create_test_app(small_limits()).await;
+    assert_limit(&app, "limit=0", [1, 1, 1, 1]).await;
+}
+
+#[tokio::test]
+async fn test_negative_limit_returns_one_row() {
+    let app = create_test_app(small_limits()).await;
+    assert_limit(&app, "limit=-10", [1, 1, 1, 1]).await;
+}
+
+#[tokio::test]
+async fn test_oversized_limit_is_clamped_to_configured_max() {
+    let app = create_test_app(small_limits()).await;
+    assert_limit(&app, "limit=1000000", [3, 4, 5, 5]).await;
+}
+
+#[tokio::test]
+async fn test_oversized_limit_is_clamped_to_default_max() {
+    let app = create_test_app(PaginationConfig::default()).await;
+    assert_limit(&app, "limit=1000000", [200, 200, 1000, 1000]).await;
+}
+
+#[tokio::test]
+async fn test_in_range_limit_is_honored() {
+    let app = create_test_app(small_limits()).await;
+    assert_limit(&app, "limit=2", [2, 2, 2, 2]).await;
+}
+
+#[tokio::test]
+async fn test_non_numeric_limit_is_rejected() {
+    let app = create_test_app(small_limits()).await;
+    for (prefix, _) in endpoints() {
+        let uri = format!("{prefix}limit=lots");
+        let (status, _) = get_json(&app, &uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
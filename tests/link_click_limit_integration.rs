@@ -0,0 +1,286 @@
+//! Integration tests for per-link click limits: a link created with
+//! `max_clicks` redirects that many times, even under concurrent load with
+//! clicks still buffered, and then answers like a deactivated link.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header::LOCATION, HeaderMap, Request, StatusCode},
+    response::Response,
+    Extension, Json, Router,
+};
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::{create_url, AppState},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::system_clock;
+use lynx::config::CodeStrategy;
+use lynx::models::{CreateUrlRequest, LinkOptions};
+use lynx::redirect;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use lynx::testing::Fixture;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// The link each redirect test seeds
+const LINK: &str = "user1-0";
+
+/// Options limiting a link to `max_clicks` clicks
+fn limited_to(max_clicks: i64) -> LinkOptions {
+    LinkOptions {
+        max_clicks: Some(max_clicks),
+        ..LinkOptions::default()
+    }
+}
+
+async fn create_test_storage() -> (Arc<CachedStorage>, Arc<dyn Storage>) {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let inner: Arc<dyn Storage> = Arc::new(inner);
+    // Flush clicks to the database only when asked, so every click made
+    // while hammering is still buffered.
+    let storage = Arc::new(CachedStorage::new(
+        Arc::clone(&inner),
+        1_000,
+        3_600,
+        10_000,
+        10,
+    ));
+    (storage, inner)
+}
+
+fn router(storage: Arc<CachedStorage>) -> Router {
+    redirect::create_redirect_router(storage, None, false, StatusCode::FOUND)
+}
+
+async fn get(app: &Router, code: &str) -> Response {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    app.clone().oneshot(request).await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_clicks_stop_at_the_limit() {
+    let (storage, inner) = create_test_storage().await;
+    Fixture::new(inner)
+        .link_options(LINK, limited_to(10))
+        .seed()
+        .await
+        .unwrap();
+    let app = router(Arc::clone(&storage));
+
+    let requests: Vec<_> = (0..200)
+        .map(|_| {
+            let app = app.clone();
+            tokio::spawn(async move { get(&app, LINK).await.status() })
+        })
+        .collect();
+    let mut redirected = 0;
+    for request in requests {
+        match request.await.unwrap() {
+            StatusCode::FOUND => redirected += 1,
+            status => assert_eq!(status, StatusCode::GONE),
+        }
+    }
+    assert_eq!(redirected, 10);
+    assert_eq!(get(&app, LINK).await.status(), StatusCode::GONE);
+
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative(LINK).await.unwrap().unwrap();
+    assert_eq!(url.clicks, 10);
+    assert_eq!(url.options.max_clicks, Some(10));
+}
+
+#[tokio::test]
+async fn test_limit_counts_clicks_made_before_it_was_cached() {
+    let (storage, inner) = create_test_storage().await;
+    // Clicks recorded in the database before the link is first looked up
+    Fixture::new(inner)
+        .link_options(LINK, limited_to(3))
+        .with_clicks(2..=2)
+        .seed()
+        .await
+        .unwrap();
+    let app = router(Arc::clone(&storage));
+
+    let response = get(&app, LINK).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[LOCATION], "https://example.com/user1/0");
+    assert_eq!(get(&app, LINK).await.status(), StatusCode::GONE);
+
+    // Raising the limit frees clicks again.
+    assert!(storage
+        .set_link_options(LINK, &limited_to(4))
+        .await
+        .unwrap());
+    assert_eq!(get(&app, LINK).await.status(), StatusCode::FOUND);
+    assert_eq!(get(&app, LINK).await.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_links_without_a_limit_keep_redirecting() {
+    let (storage, inner) = create_test_storage().await;
+    Fixture::new(inner).seed().await.unwrap();
+    let app = router(storage);
+
+    for _ in 0..20 {
+        assert_eq!(get(&app, LINK).await.status(), StatusCode::FOUND);
+    }
+}
+
+fn create_test_state(storage: Arc<dyn Storage>) -> Arc<AppState> {
+    let config = Arc::new(common::test_config());
+    Arc::new(AppState {
+        storage,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    })
+}
+
+/// Create a link limited to `max_clicks`, returning the status and body
+async fn create(state: &Arc<AppState>, code: &str, max_clicks: Option<i64>) -> (StatusCode, Value) {
+    request(
+        state,
+        CreateUrlRequest {
+            url: format!("https://example.com/{code}"),
+            custom_code: Some(code.to_string()),
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at: None,
+            max_clicks,
+        },
+    )
+    .await
+}
+
+/// Create a hash code link to `url` limited to `max_clicks`
+async fn create_hashed(
+    state: &Arc<AppState>,
+    url: &str,
+    max_clicks: Option<i64>,
+) -> (StatusCode, Value) {
+    request(
+        state,
+        CreateUrlRequest {
+            url: url.to_string(),
+            custom_code: None,
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: Some(CodeStrategy::Hash),
+            campaign_id: None,
+            expires_at: None,
+            max_clicks,
+        },
+    )
+    .await
+}
+
+async fn request(state: &Arc<AppState>, request: CreateUrlRequest) -> (StatusCode, Value) {
+    let result = create_url(
+        State(Arc::clone(state)),
+        PublicBaseUrl(state.config.redirect_base_url.clone()),
+        Extension(Some(AuthClaims(Arc::new(json!({ "sub": "alice" }))))),
+        HeaderMap::new(),
+        Json(request),
+    )
+    .await;
+    match result {
+        Ok((status, Json(response))) => (status, serde_json::to_value(response).unwrap()),
+        Err(error) => (error.status_code(), Value::Null),
+    }
+}
+
+#[tokio::test]
+async fn test_create_stores_the_limit_and_rejects_non_positive_ones() {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let state = create_test_state(Arc::clone(&storage) as Arc<dyn Storage>);
+
+    let (status, json) = create(&state, "once", Some(1)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["max_clicks"], 1);
+    assert_eq!(
+        storage
+            .get("once")
+            .await
+            .unwrap()
+            .unwrap()
+            .options
+            .max_clicks,
+        Some(1)
+    );
+
+    let (status, json) = create(&state, "open", None).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["max_clicks"], Value::Null);
+
+    for max_clicks in [0, -1] {
+        let (status, _) = create(&state, "never", Some(max_clicks)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{max_clicks}");
+    }
+    assert!(storage.get("never").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_repeated_hash_creates_never_change_the_limit() {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let state = create_test_state(Arc::clone(&storage) as Arc<dyn Storage>);
+    let url = "https://example.com/hashed";
+
+    let (status, json) = create_hashed(&state, url, Some(3)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let code = json["short_code"].as_str().unwrap().to_string();
+
+    // The same request, or one leaving the limit out, gets the link back
+    for max_clicks in [Some(3), None] {
+        let (status, json) = create_hashed(&state, url, max_clicks).await;
+        assert_eq!(status, StatusCode::OK, "{max_clicks:?}");
+        assert_eq!(json["short_code"], code.as_str());
+        assert_eq!(json["max_clicks"], 3);
+    }
+
+    // Another limit would change the existing link
+    for max_clicks in [1, 100] {
+        let (status, _) = create_hashed(&state, url, Some(max_clicks)).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{max_clicks}");
+    }
+    assert_eq!(
+        storage
+            .get(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .options
+            .max_clicks,
+        Some(3)
+    );
+}
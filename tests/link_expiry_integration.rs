@@ -0,0 +1,290 @@
+//! Integration tests for link expiry: creating a link with `expires_at`,
+//! and redirects that stop, cached or not, once it has passed.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header::LOCATION, HeaderMap, Request, StatusCode},
+    response::Response,
+    Extension, Json, Router,
+};
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::{create_url, AppState},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::{Clock, FakeClock};
+use lynx::config::{CodeStrategy, RedirectLandingConfig};
+use lynx::models::{CreateUrlRequest, LinkOptions};
+use lynx::redirect::{self, RootLanding};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use lynx::testing::Fixture;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+mod common;
+
+const NOW_MS: i64 = 1_800_000_000_000;
+
+/// The seeded link that expires a minute after `NOW_MS`
+const SOON: &str = "user1-0";
+
+async fn create_test_storage(clock: Arc<FakeClock>) -> Arc<CachedStorage> {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage = CachedStorage::new(inner, 1_000, 5, 1_000, 10).with_clock(clock as _);
+    let storage = Arc::new(storage);
+    // Without the clock, so seeding leaves it at `NOW_MS`
+    Fixture::new(Arc::clone(&storage) as Arc<dyn Storage>)
+        .link_options(
+            SOON,
+            LinkOptions {
+                expires_at: Some(NOW_MS + 60_000),
+                ..LinkOptions::default()
+            },
+        )
+        .seed()
+        .await
+        .unwrap();
+    storage
+}
+
+fn router(storage: Arc<CachedStorage>, expired_page: Option<&str>) -> Router {
+    redirect::create_redirect_router_with_landing(
+        storage,
+        None,
+        false,
+        StatusCode::FOUND,
+        None,
+        None,
+        redirect::RedirectLookup::default(),
+        RootLanding::from_config(&RedirectLandingConfig {
+            expired_page: expired_page.map(str::to_string),
+            ..RedirectLandingConfig::default()
+        }),
+    )
+}
+
+async fn get(app: &Router, code: &str) -> Response {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn body_text(response: Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_cached_links_stop_redirecting_when_they_expire() {
+    let clock = Arc::new(FakeClock::at_epoch_ms(NOW_MS));
+    let storage = create_test_storage(Arc::clone(&clock)).await;
+    let app = router(Arc::clone(&storage), None);
+
+    let response = get(&app, SOON).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[LOCATION], "https://example.com/user1/0");
+
+    // The link is cached by now; expiry is judged on every hit regardless,
+    // to the millisecond.
+    clock.advance(Duration::from_millis(59_999));
+    assert_eq!(get(&app, SOON).await.status(), StatusCode::FOUND);
+    clock.advance(Duration::from_millis(1));
+    let response = get(&app, SOON).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(body_text(response).await, "This link has expired");
+    assert_eq!(get(&app, SOON).await.status(), StatusCode::NOT_FOUND);
+
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative(SOON).await.unwrap().unwrap();
+    assert_eq!(url.clicks, 2, "expired hits must not count");
+    assert_eq!(url.options.expires_at, Some(NOW_MS + 60_000));
+}
+
+#[tokio::test]
+async fn test_expired_links_go_to_the_configured_page() {
+    let clock = Arc::new(FakeClock::at_epoch_ms(NOW_MS));
+    let storage = create_test_storage(Arc::clone(&clock)).await;
+    let app = router(storage, Some("https://example.com/expired"));
+
+    clock.advance(Duration::from_secs(3_600));
+    let response = get(&app, SOON).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[LOCATION], "https://example.com/expired");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+}
+
+#[tokio::test]
+async fn test_extending_expiry_reaches_cached_links() {
+    let clock = Arc::new(FakeClock::at_epoch_ms(NOW_MS));
+    let storage = create_test_storage(Arc::clone(&clock)).await;
+    let app = router(Arc::clone(&storage), None);
+
+    clock.advance(Duration::from_secs(120));
+    assert_eq!(get(&app, SOON).await.status(), StatusCode::NOT_FOUND);
+
+    assert!(storage
+        .set_link_options(SOON, &LinkOptions::default())
+        .await
+        .unwrap());
+    assert_eq!(get(&app, SOON).await.status(), StatusCode::FOUND);
+}
+
+fn create_test_state(storage: Arc<dyn Storage>, clock: Arc<FakeClock>) -> Arc<AppState> {
+    let config = Arc::new(common::test_config());
+    Arc::new(AppState {
+        storage,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: clock as Arc<dyn Clock>,
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    })
+}
+
+/// Create a link expiring at `expires_at`, returning the status and body
+async fn create(state: &Arc<AppState>, code: &str, expires_at: Option<i64>) -> (StatusCode, Value) {
+    request(
+        state,
+        CreateUrlRequest {
+            url: format!("https://example.com/{code}"),
+            custom_code: Some(code.to_string()),
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at,
+            max_clicks: None,
+        },
+    )
+    .await
+}
+
+/// Create a hash code link to `url` expiring at `expires_at`
+async fn create_hashed(
+    state: &Arc<AppState>,
+    url: &str,
+    expires_at: Option<i64>,
+) -> (StatusCode, Value) {
+    request(
+        state,
+        CreateUrlRequest {
+            url: url.to_string(),
+            custom_code: None,
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: Some(CodeStrategy::Hash),
+            campaign_id: None,
+            expires_at,
+            max_clicks: None,
+        },
+    )
+    .await
+}
+
+async fn request(state: &Arc<AppState>, request: CreateUrlRequest) -> (StatusCode, Value) {
+    let result = create_url(
+        State(Arc::clone(state)),
+        PublicBaseUrl(state.config.redirect_base_url.clone()),
+        Extension(Some(AuthClaims(Arc::new(json!({ "sub": "alice" }))))),
+        HeaderMap::new(),
+        Json(request),
+    )
+    .await;
+    match result {
+        Ok((status, Json(response))) => (status, serde_json::to_value(response).unwrap()),
+        Err(error) => (error.status_code(), Value::Null),
+    }
+}
+
+#[tokio::test]
+async fn test_create_stores_future_expiry_and_rejects_past_ones() {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let clock = Arc::new(FakeClock::at_epoch_ms(NOW_MS));
+    let state = create_test_state(Arc::clone(&storage) as Arc<dyn Storage>, clock);
+
+    let (status, json) = create(&state, "later", Some(NOW_MS + 3_600_000)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["expires_at"], NOW_MS + 3_600_000);
+    assert_eq!(
+        storage
+            .get("later")
+            .await
+            .unwrap()
+            .unwrap()
+            .options
+            .expires_at,
+        Some(NOW_MS + 3_600_000)
+    );
+
+    let (status, json) = create(&state, "forever", None).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["expires_at"], Value::Null);
+
+    // The same moment in seconds is long past
+    for expires_at in [NOW_MS, NOW_MS - 1, NOW_MS / 1000 + 3_600] {
+        let (status, _) = create(&state, "past", Some(expires_at)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{expires_at}");
+    }
+    assert!(storage.get("past").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_repeated_hash_creates_never_change_the_expiry() {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let clock = Arc::new(FakeClock::at_epoch_ms(NOW_MS));
+    let state = create_test_state(Arc::clone(&storage) as Arc<dyn Storage>, clock);
+    let url = "https://example.com/hashed";
+
+    let (status, json) = create_hashed(&state, url, Some(NOW_MS + 3_600_000)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let code = json["short_code"].as_str().unwrap().to_string();
+
+    // The same request, or one leaving the expiry out, gets the link back
+    for expires_at in [Some(NOW_MS + 3_600_000), None] {
+        let (status, json) = create_hashed(&state, url, expires_at).await;
+        assert_eq!(status, StatusCode::OK, "{expires_at:?}");
+        assert_eq!(json["short_code"], code.as_str());
+        assert_eq!(json["expires_at"], NOW_MS + 3_600_000);
+    }
+
+    // Another expiry would change the existing link
+    let (status, _) = create_hashed(&state, url, Some(NOW_MS + 60_000)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        storage
+            .get(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .options
+            .expires_at,
+        Some(NOW_MS + 3_600_000)
+    );
+}
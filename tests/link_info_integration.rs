@@ -0,0 +1,156 @@
+//! Integration tests for `GET /{code}/info.json` on the redirect server: the
+//! gate, the answer, and that the route never shadows a short code
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    response::Response,
+    Router,
+};
+use lynx::config::LinkInfoConfig;
+use lynx::redirect::{self, link_info::TOKEN_HEADER, LinkInfoGate, RootLanding};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const INTERNAL_PEER: &str = "10.0.0.7";
+const OUTSIDE_PEER: &str = "192.0.2.1";
+const TOKEN: &str = "gateway-token";
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner, 1_000, 5, 1_000, 10).into();
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    storage
+        .create_with_code("info.json", "https://example.com/named-info", None)
+        .await
+        .unwrap();
+    storage
+        .create_with_code("old", "https://example.com/old", None)
+        .await
+        .unwrap();
+    storage.deactivate("old").await.unwrap();
+    storage
+}
+
+fn router(storage: Arc<CachedStorage>, enabled: bool) -> Router {
+    let gate = LinkInfoGate::from_config(&LinkInfoConfig {
+        enabled,
+        allowed_ips: vec!["10.0.0.0/8".to_string()],
+        token: Some(TOKEN.to_string()),
+    })
+    .unwrap();
+    redirect::create_redirect_router_with_link_info(
+        storage,
+        None,
+        false,
+        StatusCode::FOUND,
+        None,
+        None,
+        redirect::RedirectLookup::default(),
+        RootLanding::default(),
+        gate,
+    )
+}
+
+async fn get(app: &Router, uri: &str, peer: &str, token: Option<&str>) -> Response {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header(TOKEN_HEADER, token);
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    let peer: SocketAddr = format!("{peer}:40000").parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_allowlisted_peer_or_token_reads_link_info() {
+    let storage = create_test_storage().await;
+    let app = router(Arc::clone(&storage), true);
+
+    let response = get(&app, "/docs/info.json", INTERNAL_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let created_at = storage.get("docs").await.unwrap().unwrap().created_at;
+    assert_eq!(
+        json_body(response).await,
+        json!({
+            "short_code": "docs",
+            "destination": "https://example.com/docs",
+            "is_active": true,
+            "created_at": created_at,
+        })
+    );
+
+    let response = get(&app, "/old/info.json", OUTSIDE_PEER, Some(TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json_body(response).await["is_active"], false);
+
+    let response = get(&app, "/missing/info.json", INTERNAL_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Reading info counts no click
+    storage.flush().await.unwrap();
+    assert_eq!(storage.get("docs").await.unwrap().unwrap().clicks, 0);
+}
+
+#[tokio::test]
+async fn test_other_callers_are_refused() {
+    let app = router(create_test_storage().await, true);
+
+    for token in [None, Some("wrong-token")] {
+        let response = get(&app, "/docs/info.json", OUTSIDE_PEER, token).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    // Refused before the lookup, so unknown codes look the same
+    let response = get(&app, "/missing/info.json", OUTSIDE_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_info_path_never_shadows_a_short_code() {
+    let app = router(create_test_storage().await, true);
+
+    // A link named like the suffix still redirects
+    let response = get(&app, "/info.json", OUTSIDE_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response.headers()["location"],
+        "https://example.com/named-info"
+    );
+    // Codes redirect as before
+    let response = get(&app, "/docs", OUTSIDE_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    // Deeper paths are neither codes nor info requests
+    let response = get(&app, "/a/docs/info.json", INTERNAL_PEER, None).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_disabled_endpoint_has_no_route() {
+    let app = router(create_test_storage().await, false);
+
+    // Even an allowlisted peer with the token gets the redirect server's miss
+    let response = get(&app, "/docs/info.json", INTERNAL_PEER, Some(TOKEN)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"URL not found");
+}
@@ -0,0 +1,147 @@
+//! Integration tests for the per-user link quota and the warnings it adds
+//! to create responses
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Extension, Json,
+};
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::{create_url, AppState},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::system_clock;
+use lynx::config::Config;
+use lynx::models::CreateUrlRequest;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+
+/// Helper to create test config with a quota of `max_links_per_user`
+fn create_test_config(max_links_per_user: u64) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        link_quota: LinkQuotaConfig {
+            max_links_per_user: Some(max_links_per_user),
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_state(max_links_per_user: u64) -> (Arc<AppState>, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let config = create_test_config(max_links_per_user);
+    let state = Arc::new(AppState {
+        storage: Arc::clone(&storage) as Arc<dyn Storage>,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+    (state, storage)
+}
+
+/// Create a link as `claims`, returning the status and response body
+async fn create(state: &Arc<AppState>, claims: Value, url: &str) -> (StatusCode, Value) {
+    let result = create_url(
+        State(Arc::clone(state)),
+        PublicBaseUrl(state.config.redirect_base_url.clone()),
+        Extension(Some(AuthClaims(Arc::new(claims)))),
+        HeaderMap::new(),
+        Json(CreateUrlRequest {
+            url: url.to_string(),
+            custom_code: None,
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at: None,
+            max_clicks: None,
+        }),
+    )
+    .await;
+    match result {
+        Ok((status, Json(response))) => (status, serde_json::to_value(response).unwrap()),
+        Err(error) => (error.status_code(), Value::Null),
+    }
+}
+
+#[tokio::test]
+async fn test_quota_warns_near_the_limit_and_then_refuses() {
+    let (state, _storage) = create_test_state(10).await;
+    let alice = json!({ "sub": "alice" });
+
+    for n in 1..=8 {
+        let (status, json) =
+            create(&state, alice.clone(), &format!("https://example.com/{n}")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(json.get("warnings").is_none(), "link {n}: {json}");
+    }
+    for n in 9..=10 {
+        let (status, json) =
+            create(&state, alice.clone(), &format!("https://example.com/{n}")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(json["warnings"][0]["code"], "quota_nearly_reached");
+        assert_eq!(
+            json["warnings"][0]["details"],
+            json!({ "used": n, "limit": 10 })
+        );
+    }
+
+    let (status, _) = create(&state, alice, "https://example.com/11").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Quotas are per user.
+    let (status, json) = create(&state, json!({ "sub": "bob" }), "https://example.com/bob").await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(json.get("warnings").is_none());
+}
+
+#[tokio::test]
+async fn test_deactivated_links_free_quota_and_admins_are_exempt() {
+    let (state, storage) = create_test_state(1).await;
+    let alice = json!({ "sub": "alice" });
+
+    let (status, json) = create(&state, alice.clone(), "https://example.com/a").await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["warnings"][0]["code"], "quota_nearly_reached");
+    let (status, _) = create(&state, alice.clone(), "https://example.com/b").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    storage
+        .deactivate(json["short_code"].as_str().unwrap())
+        .await
+        .unwrap();
+    let (status, _) = create(&state, alice, "https://example.com/b").await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let admin = json!({ "sub": "root", "is_admin": true });
+    for n in 0..3 {
+        let (status, json) = create(
+            &state,
+            admin.clone(),
+            &format!("https://example.com/admin/{n}"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(json.get("warnings").is_none());
+    }
+}
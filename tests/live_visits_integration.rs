@@ -0,0 +1,213 @@
+//! Integration tests for the live visit stream
+//! `GET /api/links/{code}/analytics/live`
+//!
+//! The API and redirect routers share one `LiveVisits` feed; these tests open
+//! a stream through the API router, drive redirects through the redirect
+//! router and read the resulting server-sent events.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::{Body, BodyDataStream},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, LiveVisitsConfig};
+use lynx::redirect::{self, LiveVisits};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tower::ServiceExt;
+
+mod common;
+
+const IPHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) Mobile/15E148";
+const DESKTOP: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/120.0 Safari/537.36";
+
+/// Helper to create test config with the given live visit settings
+fn create_test_config(live_visits: LiveVisitsConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        live_visits,
+        ..common::test_config()
+    })
+}
+
+/// Build API and redirect routers sharing the feed built from `live_config`.
+async fn create_test_apps(live_config: LiveVisitsConfig) -> (Router, Router) {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let inner: Arc<dyn Storage> = Arc::new(inner);
+    let cached = Arc::new(CachedStorage::new(Arc::clone(&inner), 1_000, 5, 1_000, 10));
+    for code in ["launch", "other"] {
+        cached
+            .create_with_code(code, "https://example.com/launch", None)
+            .await
+            .unwrap();
+    }
+
+    let config = create_test_config(live_config);
+    let live = LiveVisits::from_config(&config.live_visits).map(Arc::new);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+
+    let api_app = api::create_api_router_with_live_visits(
+        inner,
+        auth_service,
+        config,
+        None,
+        None,
+        live.clone(),
+    );
+    let redirect_app = redirect::create_redirect_router_with_live_visits(
+        cached,
+        None,
+        false,
+        StatusCode::PERMANENT_REDIRECT,
+        None,
+        live,
+    );
+    (api_app, redirect_app)
+}
+
+fn enabled(max_connections_per_user: usize) -> LiveVisitsConfig {
+    LiveVisitsConfig {
+        enabled: true,
+        max_connections_per_user,
+    }
+}
+
+/// Open the live stream for `code`, returning the status and the body stream.
+async fn subscribe(app: &Router, code: &str) -> (StatusCode, BodyDataStream) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/links/{}/analytics/live",
+                    URL_SAFE_NO_PAD.encode(code)
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    (response.status(), response.into_body().into_data_stream())
+}
+
+async fn visit(app: &Router, code: &str, user_agent: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{code}"))
+                .header(header::USER_AGENT, user_agent)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Read the next `visit` event from `stream` and return its JSON payload.
+async fn next_visit(stream: &mut BodyDataStream) -> Value {
+    let mut buffer = String::new();
+    loop {
+        if let Some(end) = buffer.find("\n\n") {
+            let event = &buffer[..end];
+            if event.lines().any(|line| line == "event: visit") {
+                let data = event
+                    .lines()
+                    .find_map(|line| line.strip_prefix("data: "))
+                    .expect("visit event without data");
+                return serde_json::from_str(data).unwrap();
+            }
+            buffer.drain(..end + 2);
+            continue;
+        }
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for a live visit")
+            .expect("live stream ended")
+            .unwrap();
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_live_stream_receives_visits_for_its_code() {
+    let (api_app, redirect_app) = create_test_apps(enabled(3)).await;
+
+    let (status, mut stream) = subscribe(&api_app, "launch").await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(
+        visit(&redirect_app, "other", DESKTOP).await,
+        StatusCode::PERMANENT_REDIRECT
+    );
+    assert_eq!(
+        visit(&redirect_app, "missing", DESKTOP).await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        visit(&redirect_app, "launch", IPHONE).await,
+        StatusCode::PERMANENT_REDIRECT
+    );
+    assert_eq!(
+        visit(&redirect_app, "launch", DESKTOP).await,
+        StatusCode::PERMANENT_REDIRECT
+    );
+
+    let first = next_visit(&mut stream).await;
+    assert_eq!(first["device"], "mobile");
+    assert_eq!(first["country"], "pending");
+    assert!(first["timestamp"].as_i64().unwrap() > 0);
+
+    let second = next_visit(&mut stream).await;
+    assert_eq!(second["device"], "desktop");
+}
+
+#[tokio::test]
+async fn test_live_stream_connections_are_limited_per_user() {
+    let (api_app, _) = create_test_apps(enabled(1)).await;
+
+    let (status, first) = subscribe(&api_app, "launch").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = subscribe(&api_app, "other").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Closing the first stream frees the slot.
+    drop(first);
+    let (status, _) = subscribe(&api_app, "other").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_live_stream_for_unknown_code_is_not_found() {
+    let (api_app, _) = create_test_apps(enabled(3)).await;
+    let (status, _) = subscribe(&api_app, "missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_live_stream_is_not_found_when_disabled() {
+    let (api_app, redirect_app) = create_test_apps(LiveVisitsConfig::default()).await;
+    let (status, _) = subscribe(&api_app, "launch").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        visit(&redirect_app, "launch", DESKTOP).await,
+        StatusCode::PERMANENT_REDIRECT
+    );
+}
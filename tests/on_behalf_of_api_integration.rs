@@ -0,0 +1,249 @@
+//! Integration tests for admins creating and updating links on behalf of
+//! another user
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api::{
+    self,
+    code_rng::CodeRng,
+    handlers::{create_url, AppState, ACT_AS_USER_HEADER},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::{AuthClaims, AuthService};
+use lynx::clock::system_clock;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::models::CreateUrlRequest;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10))
+}
+
+async fn create_test_app(storage: &Arc<CachedStorage>) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    )
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn json_request(method: &str, uri: &str) -> axum::http::request::Builder {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+}
+
+async fn create_user(storage: &Arc<CachedStorage>, user_id: &str) {
+    storage
+        .upsert_user(user_id, Some(&format!("{user_id}@example.com")), "oauth")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_creates_link_for_user_and_is_audited() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    create_user(&storage, "bob").await;
+
+    let body = json!({
+        "url": "https://example.com/bob",
+        "custom_code": "for-bob",
+        "created_by_override": "bob",
+    });
+    let (status, json) = send(
+        &app,
+        json_request("POST", "/api/urls")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["created_by"], "bob");
+
+    let audit = storage.get_audit_log("for-bob").await.unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].action, "create");
+    assert_eq!(audit[0].effective_user, "bob");
+    assert_ne!(audit[0].actor.as_deref(), Some("bob"));
+
+    // Links created without acting for anyone leave no audit entry.
+    let body = json!({ "url": "https://example.com/mine", "custom_code": "mine" });
+    let (status, _) = send(
+        &app,
+        json_request("POST", "/api/urls")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(storage.get_audit_log("mine").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_admin_update_with_header_hands_link_to_user() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    create_user(&storage, "carol").await;
+    storage
+        .create_with_code("handover", "https://example.com/v1", Some("support"))
+        .await
+        .unwrap();
+
+    let body = json!({ "url": "https://example.com/v2" });
+    let (status, json) = send(
+        &app,
+        json_request(
+            "PATCH",
+            &format!("/api/urls/{}", URL_SAFE_NO_PAD.encode("handover")),
+        )
+        .header(ACT_AS_USER_HEADER, "carol")
+        .body(Body::from(body.to_string()))
+        .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["created_by"], "carol");
+    assert_eq!(json["original_url"], "https://example.com/v2");
+
+    let stored = storage
+        .get_authoritative("handover")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.created_by.as_deref(), Some("carol"));
+    let audit = storage.get_audit_log("handover").await.unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(
+        (audit[0].action.as_str(), audit[0].effective_user.as_str()),
+        ("update", "carol")
+    );
+}
+
+#[tokio::test]
+async fn test_acting_for_unknown_or_conflicting_users_is_rejected() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    create_user(&storage, "bob").await;
+
+    let body = json!({
+        "url": "https://example.com/ghost",
+        "custom_code": "ghost",
+        "created_by_override": "nobody",
+    });
+    let (status, _) = send(
+        &app,
+        json_request("POST", "/api/urls")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body = json!({
+        "url": "https://example.com/ghost",
+        "custom_code": "ghost",
+        "created_by_override": "bob",
+    });
+    let (status, _) = send(
+        &app,
+        json_request("POST", "/api/urls")
+            .header(ACT_AS_USER_HEADER, "someone-else")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(storage.get_authoritative("ghost").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_non_admins_cannot_act_for_another_user() {
+    let storage = create_test_storage().await;
+    create_user(&storage, "bob").await;
+    let config = create_test_config();
+    let state = Arc::new(AppState {
+        storage: Arc::clone(&storage) as Arc<dyn Storage>,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+    let claims = AuthClaims(Arc::new(json!({ "sub": "alice" })));
+    let mut headers = HeaderMap::new();
+    headers.insert(ACT_AS_USER_HEADER, HeaderValue::from_static("bob"));
+
+    let public_base = PublicBaseUrl(state.config.redirect_base_url.clone());
+    let result = create_url(
+        State(state),
+        public_base,
+        Extension(Some(claims)),
+        headers,
+        Json(CreateUrlRequest {
+            url: "https://example.com/sneaky".to_string(),
+            custom_code: Some("sneaky".to_string()),
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at: None,
+            max_clicks: None,
+        }),
+    )
+    .await;
+
+    let Err(error) = result else {
+        panic!("non-admin acted on behalf of another user");
+    };
+    assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+    assert!(storage.get_authoritative("sneaky").await.unwrap().is_none());
+}
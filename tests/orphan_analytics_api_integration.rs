@@ -0,0 +1,144 @@
+//! Integration tests for orphaned analytics reporting and cleanup
+//!
+//! Analytics and click history rows are keyed by short code without a foreign
+//! key, so rows for codes that are not in `urls` can pile up. Admins can count
+//! them through `GET /api/stats/orphans` and remove them through
+//! `POST /api/stats/orphans/cleanup`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::analytics::{AnalyticsRollup, IpVersion};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        database: DatabaseConfig {
+            backend: DatabaseBackend::Sqlite,
+            url: "sqlite::memory:".to_string(),
+            max_connections: 1,
+            acquire_timeout_secs: 30,
+            slow_acquire_threshold_ms: 500,
+            schema: None,
+            allow_hard_delete: false,
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_app() -> (Router, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 1).await.unwrap());
+    storage.init().await.unwrap();
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let app = api::routes::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    );
+    (app, storage)
+}
+
+fn rollup(short_code: &str, visit_count: i64) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket: 3600,
+        country_code: Some("US".to_string()),
+        region: None,
+        city: None,
+        asn: None,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+async fn send(app: &Router, method: &str, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_orphan_stats_count_and_cleanup_remove_only_orphans() {
+    let (app, storage) = create_test_app().await;
+    storage
+        .create_with_code("kept", "https://example.com", None)
+        .await
+        .unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            rollup("kept", 3),
+            rollup("gone", 7),
+            rollup("never", 1),
+        ])
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO click_history (short_code, hour, clicks) VALUES ('gone', 3600, 4)")
+        .execute(storage.pool.as_ref())
+        .await
+        .unwrap();
+
+    let (status, json) = send(&app, "GET", "/api/stats/orphans").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["analytics"], 2);
+    assert_eq!(json["click_history"], 1);
+
+    let (status, json) = send(&app, "POST", "/api/stats/orphans/cleanup").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["deleted"]["analytics"], 2);
+    assert_eq!(json["deleted"]["click_history"], 1);
+
+    let (_, json) = send(&app, "GET", "/api/stats/orphans").await;
+    assert_eq!(json["analytics"], 0);
+    assert_eq!(json["click_history"], 0);
+    let kept = storage.get_analytics("kept", None, None, 10).await.unwrap();
+    assert_eq!(kept.len(), 1);
+    assert!(storage.get_authoritative("kept").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_orphan_cleanup_with_nothing_to_delete() {
+    let (app, _storage) = create_test_app().await;
+
+    let (status, json) = send(&app, "POST", "/api/stats/orphans/cleanup").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["deleted"]["analytics"], 0);
+    assert_eq!(json["deleted"]["click_history"], 0);
+}
@@ -0,0 +1,114 @@
+//! Integration tests for `OUTBOUND_PROXY_URL`: with the shared outbound
+//! client pointed at a local proxy stub, JWKS and Cloudflare certs fetches
+//! reach their hosts only through it.
+
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, CloudflareConfig, OAuthConfig, OutboundHttpConfig};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Request lines the proxy stub received
+type Seen = Arc<Mutex<Vec<String>>>;
+
+/// Start one proxy stub for the whole test binary, since the shared client
+/// is built once per process, and point the outbound client at it. The
+/// stub answers every request with a key set holding one HMAC key.
+fn proxy() -> &'static Seen {
+    static PROXY: OnceLock<Seen> = OnceLock::new();
+    PROXY.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let seen = Seen::default();
+        let recorded = Arc::clone(&seen);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(request_line.trim_end().to_string());
+                let body = r#"{"keys":[{"kid":"proxied","kty":"oct","alg":"HS256","k":"c2VjcmV0"}]}"#;
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        lynx::http::init(&OutboundHttpConfig {
+            proxy_url: Some(format!("http://{address}")),
+            ..OutboundHttpConfig::default()
+        })
+        .unwrap();
+        seen
+    })
+}
+
+fn requests_seen(line: &str) -> usize {
+    proxy()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|seen| *seen == line)
+        .count()
+}
+
+#[tokio::test]
+async fn test_jwks_is_fetched_through_the_proxy() {
+    proxy();
+    AuthService::new(AuthConfig {
+        mode: AuthMode::Oauth,
+        oauth: Some(OAuthConfig {
+            issuer_url: "http://idp.example.invalid".to_string(),
+            audience: "lynx".to_string(),
+            client_id: "lynx".to_string(),
+            scopes: "openid".to_string(),
+            redirect_uri: "http://lynx.example.invalid/callback".to_string(),
+            jwks_url: Some("http://idp.example.invalid/jwks".to_string()),
+            jwks_cache_ttl_secs: 300,
+            clock_skew_secs: 0,
+            allow_anonymous_subject: false,
+        }),
+        cloudflare: None,
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        requests_seen("GET http://idp.example.invalid/jwks HTTP/1.1"),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_cloudflare_certs_are_fetched_through_the_proxy() {
+    proxy();
+    AuthService::new(AuthConfig {
+        mode: AuthMode::Cloudflare,
+        oauth: None,
+        cloudflare: Some(CloudflareConfig {
+            team_domain: "http://team.example.invalid".to_string(),
+            audience: "lynx".to_string(),
+            certs_cache_ttl_secs: 3_600,
+            clock_skew_secs: 0,
+            allow_anonymous_subject: false,
+        }),
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        requests_seen("GET http://team.example.invalid/cdn-cgi/access/certs HTTP/1.1"),
+        1
+    );
+}
@@ -5,6 +5,7 @@
 //! `cargo test --profile profiling --features profiling --test performance_harness -- --ignored --nocapture`
 #![cfg(feature = "profiling")]
 
+mod common;
 #[path = "performance_harness/profiling.rs"]
 mod profiling;
 
@@ -20,8 +21,8 @@ use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use lynx::api::create_api_router;
 use lynx::auth::AuthService;
 use lynx::config::{
-    AnalyticsConfig, AuthConfig, AuthMode, CacheConfig, Config, DatabaseBackend, DatabaseConfig,
-    FrontendConfig, PaginationConfig, RedirectMode, ServerConfig,
+    CacheConfig, CacheEvictionPolicy, Config, DatabaseBackend, DatabaseConfig, RedirectMode,
+    ServerConfig,
 };
 use lynx::redirect::create_redirect_router;
 use lynx::storage::{CachedStorage, PostgresStorage, Storage};
@@ -31,7 +32,6 @@ use tokio::net::TcpListener;
 use tokio::task::{JoinHandle, JoinSet};
 
 use profiling::{FlamegraphConfig, ProfileMetric, ProfileScenario, ProfileSession};
-
 const SEED_URL_COUNT: usize = 20;
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
@@ -278,6 +278,10 @@ fn harness_config(database_url: String) -> Config {
             backend: DatabaseBackend::Postgres,
             url: database_url,
             max_connections: 50,
+            acquire_timeout_secs: 5,
+            slow_acquire_threshold_ms: 500,
+            schema: None,
+            allow_hard_delete: false,
         },
         api_server: ServerConfig {
             host: "127.0.0.1".into(),
@@ -288,24 +292,21 @@ fn harness_config(database_url: String) -> Config {
             port: 0,
         },
         redirect_base_url: "http://127.0.0.1".into(),
-        auth: AuthConfig {
-            mode: AuthMode::None,
-            oauth: None,
-            cloudflare: None,
-        },
-        frontend: FrontendConfig { static_dir: None },
+        redirect_path_prefix: None,
         cache: CacheConfig {
             max_entries: 500_000,
+            max_bytes: None,
             flush_interval_secs: 5,
             actor_buffer_size: 1_000_000,
             actor_flush_interval_ms: 100,
+            negative_max_entries: None,
+            eviction_policy: CacheEvictionPolicy::default(),
+            stale_max_age_secs: None,
+            entry_ttl_secs: None,
+            lookup_timeout_ms: 5_000,
         },
-        pagination: PaginationConfig {
-            cursor_hmac_secret: None,
-        },
-        short_code_max_length: 50,
-        analytics: AnalyticsConfig::default(),
         redirect_status: RedirectMode::Permanent,
+        ..common::test_config()
     }
 }
 
@@ -0,0 +1,129 @@
+//! Integration tests for short URLs behind a reverse proxy
+//!
+//! With `PUBLIC_URL_FROM_FORWARDED_HEADERS`, `X-Forwarded-Proto` and
+//! `X-Forwarded-Host` from a trusted proxy replace the scheme and host of
+//! `REDIRECT_BASE_URL` in API responses and the quick-create page. Other
+//! peers get the configured base URL whatever they send.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::Config;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+const PROXY: ([u8; 4], u16) = ([10, 0, 0, 5], 40000);
+const STRANGER: ([u8; 4], u16) = ([203, 0, 113, 9], 40000);
+
+/// Helper to create test config trusting proxies in 10.0.0.0/8
+fn create_test_config(from_forwarded_headers: bool) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        analytics: AnalyticsConfig {
+            trusted_proxy_mode: TrustedProxyMode::Standard,
+            trusted_proxies: vec!["10.0.0.0/8".to_string()],
+            ..AnalyticsConfig::default()
+        },
+        public_url: PublicUrlConfig {
+            from_forwarded_headers,
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_app(from_forwarded_headers: bool) -> Router {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let config = create_test_config(from_forwarded_headers);
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    api::create_api_router(
+        Arc::new(storage) as Arc<dyn Storage>,
+        auth_service,
+        config,
+        None,
+    )
+}
+
+/// Send `request` from `peer` through a proxy that forwards the public name.
+async fn send_forwarded(
+    app: &Router,
+    request: axum::http::request::Builder,
+    body: Body,
+    peer: ([u8; 4], u16),
+) -> (StatusCode, String) {
+    let request = request
+        .header("x-forwarded-proto", "https")
+        .header("x-forwarded-host", "go.example.com")
+        .extension(ConnectInfo(SocketAddr::from(peer)))
+        .body(body)
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn create(app: &Router, code: &str, peer: ([u8; 4], u16)) -> Value {
+    let (status, body) = send_forwarded(
+        app,
+        Request::builder()
+            .method("POST")
+            .uri("/api/urls")
+            .header(header::CONTENT_TYPE, "application/json"),
+        Body::from(json!({ "url": "https://example.com", "custom_code": code }).to_string()),
+        peer,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_trusted_proxy_sets_the_public_base_url() {
+    let app = create_test_app(true).await;
+
+    let created = create(&app, "docs", PROXY).await;
+    assert_eq!(created["redirect_base_url"], "https://go.example.com");
+    assert_eq!(created["short_url"], "https://go.example.com/docs");
+
+    let (status, page) = send_forwarded(
+        &app,
+        Request::builder().uri("/api/quick?url=https://example.com/page"),
+        Body::empty(),
+        PROXY,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(page.contains("href=\"https://go.example.com/"));
+}
+
+#[tokio::test]
+async fn test_untrusted_peers_get_the_configured_base_url() {
+    let app = create_test_app(true).await;
+
+    let created = create(&app, "docs", STRANGER).await;
+    assert_eq!(created["redirect_base_url"], "http://localhost:3000");
+    assert_eq!(created["short_url"], "http://localhost:3000/docs");
+}
+
+#[tokio::test]
+async fn test_forwarded_headers_are_ignored_unless_enabled() {
+    let app = create_test_app(false).await;
+
+    let created = create(&app, "docs", PROXY).await;
+    assert_eq!(created["short_url"], "http://localhost:3000/docs");
+}
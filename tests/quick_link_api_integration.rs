@@ -0,0 +1,205 @@
+//! Integration tests for the bookmarklet endpoint `GET /api/quick`
+//!
+//! The endpoint renders HTML for browsers and JSON when asked for it, returns
+//! the caller's existing link for a destination instead of creating another
+//! one, and applies a per-user rate limit.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, QuickLinkConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config with the given quick link limits
+fn create_test_config(quick_link: QuickLinkConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        quick_link,
+        ..common::test_config()
+    })
+}
+
+async fn create_test_app(quick_link: QuickLinkConfig) -> Router {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::routes::create_api_router(
+        Arc::new(storage),
+        auth_service,
+        create_test_config(quick_link),
+        None,
+    )
+}
+
+/// GET `uri`, optionally asking for JSON; returns status, headers and body text.
+async fn quick(app: &Router, uri: &str, json: bool) -> (StatusCode, header::HeaderMap, String) {
+    let mut request = Request::builder().uri(uri);
+    if json {
+        request = request.header(header::ACCEPT, "application/json");
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, headers, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_quick_link_renders_html_page_with_short_url() {
+    let app = create_test_app(QuickLinkConfig::default()).await;
+
+    let (status, headers, body) = quick(
+        &app,
+        "/api/quick?url=https%3A%2F%2Fexample.com%2Fpage%3Fa%3D1%26b%3D2",
+        false,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(headers[header::CONTENT_TYPE], "text/html; charset=utf-8");
+    assert!(headers[header::CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap()
+        .starts_with("default-src 'none'; script-src 'nonce-"));
+    assert!(body.contains("Short link created"));
+    assert!(body.contains("href=\"http://localhost:3000/"));
+    assert!(body.contains("https://example.com/page?a=1&amp;b=2"));
+    assert!(body.contains("id=\"copy\""));
+    assert!(body.contains("encodeURIComponent(location.href)"));
+}
+
+#[tokio::test]
+async fn test_quick_link_returns_json_and_reuses_existing_link() {
+    let app = create_test_app(QuickLinkConfig::default()).await;
+    let uri = "/api/quick?url=https://example.com/shared";
+
+    let (status, _, body) = quick(&app, uri, true).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(created["original_url"], "https://example.com/shared");
+    assert_eq!(created["created_via"], "bookmarklet");
+    assert_eq!(created["redirect_base_url"], "http://localhost:3000");
+    assert_eq!(
+        created["short_url"],
+        format!(
+            "http://localhost:3000/{}",
+            created["short_code"].as_str().unwrap()
+        )
+    );
+
+    let (status, _, body) = quick(&app, uri, true).await;
+    assert_eq!(status, StatusCode::OK);
+    let repeated: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(repeated["short_code"], created["short_code"]);
+
+    let (status, _, body) = quick(&app, uri, false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("You already shortened this page"));
+}
+
+#[tokio::test]
+async fn test_quick_link_rejects_invalid_destinations() {
+    let app = create_test_app(QuickLinkConfig::default()).await;
+
+    let (status, _, body) = quick(&app, "/api/quick?url=javascript:alert(1)", true).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let json: Value = serde_json::from_str(&body).unwrap();
+    assert!(json["error"].as_str().unwrap().contains("javascript"));
+
+    let (status, headers, body) = quick(&app, "/api/quick", false).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        headers[header::CONTENT_SECURITY_POLICY],
+        "default-src 'none'"
+    );
+    assert!(body.contains("URL cannot be empty"));
+}
+
+#[tokio::test]
+async fn test_quick_link_is_rate_limited_per_user() {
+    let app = create_test_app(QuickLinkConfig {
+        rate_limit_per_minute: 2,
+    })
+    .await;
+
+    for i in 0..2 {
+        let (status, _, _) = quick(
+            &app,
+            &format!("/api/quick?url=https://example.com/{i}"),
+            true,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    let (status, _, body) = quick(&app, "/api/quick?url=https://example.com/2", true).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert!(body.contains("per minute"));
+}
+
+#[tokio::test]
+async fn test_search_filters_links_by_creation_source() {
+    let app = create_test_app(QuickLinkConfig::default()).await;
+
+    let (status, _, _) = quick(&app, "/api/quick?url=https://example.com/report", true).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"{"url": "https://example.com/report-draft", "custom_code": "draft"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let (status, _, body) = quick(
+        &app,
+        "/api/urls/search?q=report&created_via=bookmarklet",
+        true,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let found: Value = serde_json::from_str(&body).unwrap();
+    let items = found["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["original_url"], "https://example.com/report");
+
+    let (status, _, body) = quick(&app, "/api/urls/search?q=report&created_via=api", true).await;
+    assert_eq!(status, StatusCode::OK);
+    let found: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(found["items"][0]["short_code"], "draft");
+    assert_eq!(found["items"][0]["created_via"], "api");
+
+    let (status, _, _) = quick(&app, "/api/urls/search?q=report&created_via=fax", true).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -2,16 +2,20 @@
 //!
 //! These tests verify that the redirect functionality works correctly,
 //! including concurrent redirects and proper handling of active/inactive URLs.
+#![cfg(feature = "sqlite")]
 
 use axum::{
     body::Body,
     extract::ConnectInfo,
     http::{Request, StatusCode},
 };
-use lynx::analytics::AnalyticsAggregator;
-use lynx::config::AnalyticsConfig;
-use lynx::redirect::{self, RedirectAnalytics};
+use lynx::analytics::{AnalyticsAggregator, AnalyticsRollup};
+use lynx::config::{AnalyticsConfig, RedirectLandingConfig};
+use lynx::redirect::{
+    self, CodeNormalizer, RedirectAnalytics, RedirectStats, RootLanding, SelfRedirectGuard,
+};
 use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use lynx::testing::Fixture;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tower::ServiceExt;
@@ -107,6 +111,43 @@ async fn test_timing_route_adds_observability_headers() {
     }
 }
 
+#[tokio::test]
+async fn test_create_then_redirect_is_served_from_cache() {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner.clone(), 1_000, 5, 1_000, 10).into();
+    storage
+        .create_with_code("fresh", "https://example.com/created", None)
+        .await
+        .unwrap();
+    // Rewrite the destination directly in the database; a redirect that
+    // consulted the database would observe the new value.
+    inner
+        .update_url("fresh", "https://example.com/database", None)
+        .await
+        .unwrap();
+    let app =
+        redirect::routes::create_redirect_router(storage, None, true, DEFAULT_REDIRECT_STATUS);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/fresh")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://example.com/created"
+    );
+    assert_eq!(response.headers().get("x-lynx-cache-hit").unwrap(), "true");
+    assert_eq!(response.headers().get("x-lynx-timing-db-ms").unwrap(), "0");
+}
+
 #[tokio::test]
 async fn test_analytics_route_records_without_geoip() {
     let storage = create_test_storage().await;
@@ -182,6 +223,431 @@ async fn test_redirect_inactive_url() {
     );
 }
 
+#[tokio::test]
+async fn unresolved_redirects_leave_no_analytics_or_clicks() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("resolved", "https://example.com/resolved", None)
+        .await
+        .unwrap();
+    storage
+        .create_with_code("retired", "https://example.com/retired", None)
+        .await
+        .unwrap();
+    storage.deactivate("retired").await.unwrap();
+    storage
+        .reserve_codes(&["held".to_string()], None, None, i64::MAX)
+        .await
+        .unwrap();
+
+    // Flush through the raw upsert so only the handlers can keep rows out.
+    let aggregator = Arc::new(AnalyticsAggregator::new());
+    let flush_storage = Arc::clone(&storage);
+    let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
+        let storage = Arc::clone(&flush_storage);
+        Box::pin(async move {
+            let records = entries
+                .into_iter()
+                .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                .collect();
+            storage.upsert_analytics_batch(records).await
+        })
+    });
+    let analytics = RedirectAnalytics::from_enabled(
+        AnalyticsConfig {
+            enabled: true,
+            ..AnalyticsConfig::default()
+        },
+        Arc::clone(&aggregator),
+    )
+    .unwrap();
+
+    for timing in [false, true] {
+        let app = redirect::routes::create_redirect_router(
+            storage.clone(),
+            Some(analytics.clone()),
+            timing,
+            DEFAULT_REDIRECT_STATUS,
+        );
+        for (code, expected) in [
+            ("resolved", DEFAULT_REDIRECT_STATUS),
+            ("missing", StatusCode::NOT_FOUND),
+            ("held", StatusCode::NOT_FOUND),
+            ("retired", StatusCode::GONE),
+        ] {
+            let mut request = Request::builder()
+                .uri(format!("/{code}"))
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), expected, "unexpected status for {code}");
+        }
+    }
+
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+    storage.flush().await.unwrap();
+
+    let visits = |entries: Vec<lynx::analytics::AnalyticsEntry>| {
+        entries.iter().map(|entry| entry.visit_count).sum::<i64>()
+    };
+    assert_eq!(
+        visits(
+            storage
+                .get_analytics("resolved", None, None, 100)
+                .await
+                .unwrap()
+        ),
+        2
+    );
+    for code in ["missing", "held", "retired"] {
+        let rows = storage.get_analytics(code, None, None, 100).await.unwrap();
+        assert!(rows.is_empty(), "{code} should have no analytics rows");
+    }
+
+    let resolved = storage
+        .get_authoritative("resolved")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(resolved.clicks, 2);
+    for code in ["held", "retired"] {
+        let url = storage.get_authoritative(code).await.unwrap().unwrap();
+        assert_eq!(url.clicks, 0, "{code} should not count clicks");
+    }
+    assert!(storage
+        .get_authoritative("missing")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn deactivated_link_stops_counting_clicks_until_reactivated() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("paused", "https://example.com/paused", None)
+        .await
+        .unwrap();
+    let app = redirect::routes::create_redirect_router(
+        storage.clone(),
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+    );
+    let hit = |expected: StatusCode| {
+        let app = app.clone();
+        async move {
+            let request = Request::builder()
+                .uri("/paused")
+                .body(Body::empty())
+                .unwrap();
+            assert_eq!(app.oneshot(request).await.unwrap().status(), expected);
+        }
+    };
+
+    hit(DEFAULT_REDIRECT_STATUS).await;
+    storage.flush().await.unwrap();
+    let clicks = storage
+        .get_authoritative("paused")
+        .await
+        .unwrap()
+        .unwrap()
+        .clicks;
+    assert_eq!(clicks, 1);
+
+    storage.deactivate("paused").await.unwrap();
+    for _ in 0..50 {
+        hit(StatusCode::GONE).await;
+    }
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative("paused").await.unwrap().unwrap();
+    assert_eq!(url.clicks, clicks, "inactive link must not count clicks");
+
+    storage.reactivate("paused").await.unwrap();
+    for _ in 0..3 {
+        hit(DEFAULT_REDIRECT_STATUS).await;
+    }
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative("paused").await.unwrap().unwrap();
+    assert_eq!(url.clicks, clicks + 3);
+}
+
+#[tokio::test]
+async fn test_redirect_outcomes_are_counted_when_stats_enabled() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("counted", "https://example.com", None)
+        .await
+        .unwrap();
+    storage
+        .create_with_code("retired", "https://example.com", None)
+        .await
+        .unwrap();
+    storage.deactivate("retired").await.unwrap();
+    let stats = Arc::new(RedirectStats::new(8, 20));
+    let app = redirect::routes::create_redirect_router_with_stats(
+        storage,
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+        Some(Arc::clone(&stats)),
+    );
+
+    for (uri, expected) in [
+        ("/counted", DEFAULT_REDIRECT_STATUS),
+        ("/counted?utm_source=mail", DEFAULT_REDIRECT_STATUS),
+        ("/retired", StatusCode::GONE),
+        ("/countd", StatusCode::NOT_FOUND),
+        ("/countd", StatusCode::NOT_FOUND),
+        ("/cuonted", StatusCode::NOT_FOUND),
+        (
+            "/wp-admin/install.php/with/a/long/path",
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), expected, "{uri}");
+    }
+
+    let snapshot = stats.snapshot(10);
+    assert_eq!(snapshot.found, 2);
+    assert_eq!(snapshot.inactive, 1);
+    assert_eq!(snapshot.not_found, 4);
+    assert_eq!(snapshot.expired, 0);
+    let top: Vec<(&str, u64)> = snapshot
+        .top_missing
+        .iter()
+        .map(|entry| (entry.code.as_str(), entry.count))
+        .collect();
+    // Only the code segment is kept, and paths longer than any valid code are
+    // counted without being tracked.
+    assert_eq!(top, vec![("countd", 2), ("cuonted", 1)]);
+}
+
+#[tokio::test]
+async fn test_mangled_codes_are_rescued_by_normalization() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("abc123", "https://example.com", None)
+        .await
+        .unwrap();
+    let clicks = storage
+        .get_authoritative("abc123")
+        .await
+        .unwrap()
+        .unwrap()
+        .clicks;
+    let stats = Arc::new(RedirectStats::new(8, 20));
+
+    for enable_timing_headers in [false, true] {
+        let app = redirect::routes::create_redirect_router_with_normalization(
+            Arc::clone(&storage),
+            None,
+            enable_timing_headers,
+            DEFAULT_REDIRECT_STATUS,
+            Some(Arc::clone(&stats)),
+            None,
+            Some(CodeNormalizer::new(".,);]")),
+        );
+        for uri in [
+            "/abc123.",
+            "/abc123,",
+            "/abc123)",
+            "/abc123;",
+            "/abc123]",
+            "/abc123%20",
+            "/abc123%2520",
+            "/%20abc123",
+            "/abc123.)",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS, "{uri}");
+            assert_eq!(response.headers()["location"], "https://example.com");
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/abc124.")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    let snapshot = stats.snapshot(10);
+    assert_eq!(snapshot.found, 18);
+    assert_eq!(snapshot.normalized, 18);
+    assert_eq!(snapshot.not_found, 2);
+    // Misses are reported as requested, not in their normalized form.
+    assert_eq!(snapshot.top_missing[0].code, "abc124.");
+
+    // Rescued clicks are credited to the link itself.
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative("abc123").await.unwrap().unwrap();
+    assert_eq!(url.clicks, clicks + 18);
+}
+
+#[tokio::test]
+async fn test_well_formed_and_unnormalized_codes_skip_normalization() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("abc123", "https://example.com", None)
+        .await
+        .unwrap();
+    let stats = Arc::new(RedirectStats::new(0, 20));
+    let app = redirect::routes::create_redirect_router_with_normalization(
+        Arc::clone(&storage),
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+        Some(Arc::clone(&stats)),
+        None,
+        Some(CodeNormalizer::new(".,);]")),
+    );
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/abc123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(stats.snapshot(0).normalized, 0);
+
+    // Without a normalizer a mangled code is just a missing one.
+    let app =
+        redirect::routes::create_redirect_router(storage, None, false, DEFAULT_REDIRECT_STATUS);
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/abc123.")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+fn self_redirect_router(storage: Arc<CachedStorage>) -> axum::Router {
+    redirect::routes::create_redirect_router_with_lookup(
+        storage,
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+        None,
+        None,
+        redirect::RedirectLookup {
+            self_redirects: Some(SelfRedirectGuard::new(vec!["short.example".to_string()], 3)),
+            ..redirect::RedirectLookup::default()
+        },
+    )
+}
+
+async fn get(app: &axum::Router, uri: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_self_redirect_loops_return_508() {
+    let storage = create_test_storage().await;
+    for (code, url) in [
+        ("mirror", "https://short.example/mirror"),
+        ("ping", "https://short.example/ping-alias"),
+        ("pong", "https://short.example/ping"),
+        ("l1", "https://short.example/l2"),
+        ("l2", "https://short.example/l3"),
+        ("l3", "https://short.example/l4"),
+        ("l4", "https://short.example/l5"),
+        ("l5", "https://example.com/too-far"),
+    ] {
+        storage.create_with_code(code, url, None).await.unwrap();
+    }
+    // The two-hop loop closes through an alias of the starting link.
+    storage.add_alias("ping", "ping-alias", None).await.unwrap();
+    let app = self_redirect_router(Arc::clone(&storage));
+
+    // Direct self-reference, a two-hop loop, and a chain past the hop limit.
+    for uri in ["/mirror", "/ping", "/pong", "/l1"] {
+        let response = get(&app, uri).await;
+        assert_eq!(response.status(), StatusCode::LOOP_DETECTED, "{uri}");
+    }
+
+    // Loops are never counted as clicks.
+    storage.flush().await.unwrap();
+    let url = storage.get_authoritative("mirror").await.unwrap().unwrap();
+    assert_eq!(url.clicks, 0);
+}
+
+#[tokio::test]
+async fn test_self_redirect_chains_serve_the_final_destination() {
+    let storage = create_test_storage().await;
+    for (code, url) in [
+        ("campaign", "https://short.example/landing?utm_source=mail"),
+        ("landing", "https://example.com/spring-sale"),
+        ("retired-hop", "https://short.example/retired"),
+        ("retired", "https://example.com/gone"),
+    ] {
+        storage.create_with_code(code, url, None).await.unwrap();
+    }
+    storage.deactivate("retired").await.unwrap();
+    let app = self_redirect_router(Arc::clone(&storage));
+
+    let response = get(&app, "/campaign").await;
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(
+        response.headers()["location"],
+        "https://example.com/spring-sale"
+    );
+
+    // A hop that cannot be followed is left for the client to request.
+    let response = get(&app, "/retired-hop").await;
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(
+        response.headers()["location"],
+        "https://short.example/retired"
+    );
+
+    // The requested link gets the click, not the links it chains through.
+    storage.flush().await.unwrap();
+    let campaign = storage
+        .get_authoritative("campaign")
+        .await
+        .unwrap()
+        .unwrap();
+    let landing = storage.get_authoritative("landing").await.unwrap().unwrap();
+    assert_eq!((campaign.clicks, landing.clicks), (1, 0));
+
+    // Without a guard the self link is redirected to as stored.
+    let app =
+        redirect::routes::create_redirect_router(storage, None, false, DEFAULT_REDIRECT_STATUS);
+    let response = get(&app, "/campaign").await;
+    assert_eq!(
+        response.headers()["location"],
+        "https://short.example/landing?utm_source=mail"
+    );
+}
+
 #[tokio::test]
 async fn bulk_deactivation_invalidates_warmed_redirect_cache() {
     let storage = create_test_storage().await;
@@ -250,6 +716,149 @@ async fn test_redirect_nonexistent_url() {
     );
 }
 
+#[tokio::test]
+async fn test_lookup_with_database_down_returns_503() {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner.clone(), 1_000, 5, 1_000, 10).into();
+    inner.pool.close().await;
+    inner.read_pool.close().await;
+
+    let app = redirect::routes::create_redirect_router(
+        storage.clone(),
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+    );
+    let request = Request::builder()
+        .uri("/unreachable")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+fn landing_router(storage: Arc<CachedStorage>, landing: RootLanding) -> axum::Router {
+    redirect::create_redirect_router_with_landing(
+        storage,
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+        None,
+        None,
+        redirect::RedirectLookup::default(),
+        landing,
+    )
+}
+
+#[tokio::test]
+async fn test_root_path_serves_the_landing_response() {
+    let storage = create_test_storage().await;
+
+    let response = get(
+        &landing_router(storage.clone(), RootLanding::default()),
+        "/",
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+
+    let landing = RootLanding::from_config(&RedirectLandingConfig {
+        homepage: Some("https://example.com/".to_string()),
+        ..RedirectLandingConfig::default()
+    });
+    let response = get(&landing_router(storage, landing), "/").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()["location"], "https://example.com/");
+}
+
+#[tokio::test]
+async fn test_trailing_slash_is_stripped_before_lookup() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("abc", "https://example.com/abc", None)
+        .await
+        .unwrap();
+    let app = landing_router(storage, RootLanding::default());
+
+    let response = get(&app, "/abc/").await;
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(response.headers()["location"], "https://example.com/abc");
+}
+
+#[tokio::test]
+async fn test_multi_segment_paths_are_not_looked_up() {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner.clone(), 1_000, 5, 1_000, 10).into();
+    let stats = Arc::new(RedirectStats::new(8, 20));
+    let app = redirect::create_redirect_router_with_stats(
+        storage,
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+        Some(Arc::clone(&stats)),
+    );
+    // Any lookup would now answer 503.
+    inner.pool.close().await;
+    inner.read_pool.close().await;
+
+    for uri in ["/abc/def", "//abc", "/abc//"] {
+        assert_eq!(
+            get(&app, uri).await.status(),
+            StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+    assert_eq!(
+        get(&app, "/abc/").await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(stats.snapshot(10).not_found, 3);
+}
+
+#[tokio::test]
+async fn test_non_web_destination_is_served_through_interstitial() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("mail", "mailto:team@example.com?subject=<hi>", None)
+        .await
+        .unwrap();
+
+    let app = redirect::routes::create_redirect_router(
+        storage.clone(),
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+    );
+    let response = app
+        .oneshot(Request::builder().uri("/mail").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("location").is_none());
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get("content-security-policy").unwrap(),
+        "default-src 'none'"
+    );
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains("href=\"mailto:team@example.com?subject=&lt;hi&gt;\""));
+}
+
 #[tokio::test]
 async fn test_concurrent_redirects() {
     // Test that concurrent redirects to the same URL work correctly
@@ -378,17 +987,11 @@ async fn test_redirect_multiple_different_urls() {
     // Test concurrent redirects to different URLs
     let storage = create_test_storage().await;
 
-    // Create multiple test URLs
-    for i in 0..10 {
-        storage
-            .create_with_code(
-                &format!("url_{}", i),
-                &format!("https://example.com/{}", i),
-                None,
-            )
-            .await
-            .unwrap();
-    }
+    let seeded = Fixture::new(storage.clone())
+        .links_per_user(10)
+        .seed()
+        .await
+        .unwrap();
 
     let app = redirect::routes::create_redirect_router(
         storage.clone(),
@@ -400,10 +1003,10 @@ async fn test_redirect_multiple_different_urls() {
     // Spawn concurrent redirects to different URLs
     let mut handles = vec![];
 
-    for i in 0..10 {
+    for link in seeded.links() {
         for _ in 0..5 {
             let app_clone = app.clone();
-            let url_path = format!("/url_{}", i);
+            let url_path = format!("/{}", link.short_code());
             let handle = tokio::spawn(async move {
                 let request = Request::builder()
                     .uri(&url_path)
@@ -476,3 +1079,91 @@ async fn test_configurable_redirect_status_codes() {
         );
     }
 }
+
+/// Legacy rows bypass ingest validation, so insert them straight into storage.
+async fn create_poisoned_storage() -> Arc<CachedStorage> {
+    let storage = create_test_storage().await;
+    for (code, url) in [
+        ("crlf", "https://example.com/a\r\nSet-Cookie: evil=1"),
+        ("octets", "https://example.com/caf\u{e9} menu\t"),
+        ("blank", "\r\n"),
+    ] {
+        storage.create_with_code(code, url, None).await.unwrap();
+    }
+    storage
+}
+
+#[tokio::test]
+async fn test_poisoned_destination_cannot_inject_headers() {
+    let storage = create_poisoned_storage().await;
+    for timing in [false, true] {
+        let app = redirect::routes::create_redirect_router(
+            storage.clone(),
+            None,
+            timing,
+            DEFAULT_REDIRECT_STATUS,
+        );
+        let response = app
+            .oneshot(Request::builder().uri("/crlf").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "https://example.com/aSet-Cookie:%20evil=1"
+        );
+        assert!(response.headers().get("set-cookie").is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_poisoned_destination_octets_are_percent_encoded() {
+    let storage = create_poisoned_storage().await;
+    let app = redirect::routes::create_redirect_router(
+        storage.clone(),
+        None,
+        false,
+        DEFAULT_REDIRECT_STATUS,
+    );
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/octets")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), DEFAULT_REDIRECT_STATUS);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "https://example.com/caf%C3%A9%20menu%09"
+    );
+}
+
+#[tokio::test]
+async fn test_unusable_poisoned_destination_returns_500() {
+    let storage = create_poisoned_storage().await;
+    for timing in [false, true] {
+        let app = redirect::routes::create_redirect_router(
+            storage.clone(),
+            None,
+            timing,
+            DEFAULT_REDIRECT_STATUS,
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/blank")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().get("location").is_none());
+    }
+}
@@ -0,0 +1,188 @@
+//! Integration tests for the countdown page: links flagged `interstitial`
+//! and destinations on a covered domain get the page instead of a redirect,
+//! other links redirect as before, and either way the visit is counted once.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header::LOCATION, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use lynx::analytics::{AnalyticsAggregator, AnalyticsRollup};
+use lynx::config::AnalyticsConfig;
+use lynx::models::LinkOptions;
+use lynx::redirect::{self, CountdownPage, RedirectAnalytics, RootLanding};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner, 1_000, 5, 1_000, 10).into();
+    for (code, destination) in [
+        ("plain", "https://example.com/plain"),
+        ("flagged", "https://example.com/flagged"),
+        ("partner", "https://shop.partner.example/offer?a=1&b=2"),
+    ] {
+        storage
+            .create_with_code(code, destination, None)
+            .await
+            .unwrap();
+    }
+    storage
+        .set_link_options(
+            "flagged",
+            &LinkOptions {
+                interstitial: Some(true),
+                ..LinkOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    storage
+}
+
+fn router(
+    storage: Arc<CachedStorage>,
+    analytics: Option<RedirectAnalytics>,
+    timing: bool,
+) -> Router {
+    redirect::create_redirect_router_with_interstitial(
+        storage,
+        analytics,
+        timing,
+        StatusCode::FOUND,
+        None,
+        None,
+        redirect::RedirectLookup::default(),
+        RootLanding::default(),
+        None,
+        CountdownPage::new(vec!["partner.example".to_string()], 3),
+    )
+}
+
+async fn get(app: &Router, code: &str) -> Response {
+    let mut request = Request::builder()
+        .uri(format!("/{code}"))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 12345))));
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn body_text(response: Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_flagged_links_show_the_countdown_page() {
+    let app = router(create_test_storage().await, None, false);
+
+    let response = get(&app, "flagged").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(LOCATION).is_none());
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let html = body_text(response).await;
+    assert!(html
+        .contains("<meta http-equiv=\"refresh\" content=\"3; url=https://example.com/flagged\">"));
+    assert!(html.contains("<a href=\"https://example.com/flagged\""));
+}
+
+#[tokio::test]
+async fn test_unflagged_links_redirect_at_once() {
+    let app = router(create_test_storage().await, None, false);
+
+    let response = get(&app, "plain").await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(response.headers()[LOCATION], "https://example.com/plain");
+}
+
+#[tokio::test]
+async fn test_covered_domains_show_the_countdown_page() {
+    let app = router(create_test_storage().await, None, true);
+
+    let response = get(&app, "partner").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html = body_text(response).await;
+    assert!(html.contains("url=https://shop.partner.example/offer?a=1&amp;b=2"));
+}
+
+#[tokio::test]
+async fn test_flag_changes_reach_cached_links() {
+    let storage = create_test_storage().await;
+    let app = router(Arc::clone(&storage), None, false);
+    assert_eq!(get(&app, "plain").await.status(), StatusCode::FOUND);
+
+    storage
+        .set_link_options(
+            "plain",
+            &LinkOptions {
+                interstitial: Some(true),
+                ..LinkOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(get(&app, "plain").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_countdown_visits_count_once() {
+    let storage = create_test_storage().await;
+    let aggregator = Arc::new(AnalyticsAggregator::new());
+    let flush_storage = Arc::clone(&storage);
+    let flush_handle = aggregator.start_flush_task_with_storage(3_600, move |entries| {
+        let storage = Arc::clone(&flush_storage);
+        Box::pin(async move {
+            let records = entries
+                .into_iter()
+                .map(|(key, value)| AnalyticsRollup::from_aggregate(key, value))
+                .collect();
+            storage.upsert_analytics_batch(records).await
+        })
+    });
+    let analytics = RedirectAnalytics::from_enabled(
+        AnalyticsConfig {
+            enabled: true,
+            ..AnalyticsConfig::default()
+        },
+        Arc::clone(&aggregator),
+    );
+
+    for timing in [false, true] {
+        let app = router(Arc::clone(&storage), analytics.clone(), timing);
+        for (code, expected) in [
+            ("flagged", StatusCode::OK),
+            ("partner", StatusCode::OK),
+            ("plain", StatusCode::FOUND),
+        ] {
+            assert_eq!(get(&app, code).await.status(), expected, "{code}");
+        }
+    }
+
+    aggregator.shutdown().await;
+    flush_handle.await.unwrap();
+    storage.flush().await.unwrap();
+
+    for code in ["flagged", "partner", "plain"] {
+        let url = storage.get_authoritative(code).await.unwrap().unwrap();
+        assert_eq!(url.clicks, 2, "{code}");
+        let visits: i64 = storage
+            .get_analytics(code, None, None, 100)
+            .await
+            .unwrap()
+            .iter()
+            .map(|entry| entry.visit_count)
+            .sum();
+        assert_eq!(visits, 2, "{code}");
+    }
+}
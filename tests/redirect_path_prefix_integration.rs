@@ -0,0 +1,158 @@
+//! Integration tests for `REDIRECT_PATH_PREFIX`
+//!
+//! With a prefix the redirect server answers only under it (codes, link info
+//! and the landing response), and the API builds short URLs with it.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{Config, LinkInfoConfig};
+use lynx::redirect::{
+    self, link_info::TOKEN_HEADER, mount_under_prefix, LinkInfoGate, RootLanding,
+};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+const TOKEN: &str = "gateway-token";
+
+async fn redirect_app(prefix: Option<&str>) -> Router {
+    let inner = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    inner.init().await.unwrap();
+    let storage: Arc<CachedStorage> = CachedStorage::new(inner, 1_000, 5, 1_000, 10).into();
+    storage
+        .create_with_code("docs", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    let gate = LinkInfoGate::from_config(&LinkInfoConfig {
+        enabled: true,
+        allowed_ips: Vec::new(),
+        token: Some(TOKEN.to_string()),
+    })
+    .unwrap();
+    let router = redirect::create_redirect_router_with_link_info(
+        storage,
+        None,
+        false,
+        StatusCode::FOUND,
+        None,
+        None,
+        redirect::RedirectLookup::default(),
+        RootLanding::default(),
+        gate,
+    );
+    mount_under_prefix(router, prefix)
+}
+
+async fn get(app: &Router, uri: &str) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .header(TOKEN_HEADER, TOKEN)
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+async fn json_body(response: Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_codes_resolve_under_the_prefix_only() {
+    let app = redirect_app(Some("/s")).await;
+
+    for uri in ["/s/docs", "/s/docs/"] {
+        let response = get(&app, uri).await;
+        assert_eq!(response.status(), StatusCode::FOUND, "{uri}");
+        assert_eq!(response.headers()["location"], "https://example.com/docs");
+    }
+    for uri in ["/docs", "/t/docs", "/sdocs", "/s/s/docs"] {
+        assert_eq!(
+            get(&app, uri).await.status(),
+            StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+    assert_eq!(get(&app, "/s").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_link_info_is_served_under_the_prefix() {
+    let app = redirect_app(Some("/go/links")).await;
+
+    let response = get(&app, "/go/links/docs/info.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await["destination"],
+        "https://example.com/docs"
+    );
+    let response = get(&app, "/docs/info.json").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_without_a_prefix_codes_resolve_at_the_root() {
+    let app = redirect_app(None).await;
+
+    assert_eq!(get(&app, "/docs").await.status(), StatusCode::FOUND);
+    assert_eq!(get(&app, "/docs/info.json").await.status(), StatusCode::OK);
+    assert_eq!(get(&app, "/s/docs").await.status(), StatusCode::NOT_FOUND);
+}
+
+async fn created_short_url(prefix: Option<&str>) -> Value {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let config = Arc::new(Config {
+        redirect_path_prefix: prefix.map(str::to_string),
+        ..common::test_config()
+    });
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    let app = api::create_api_router(
+        Arc::new(storage) as Arc<dyn Storage>,
+        auth_service,
+        config,
+        None,
+    );
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/urls")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "url": "https://example.com", "custom_code": "abc" }).to_string(),
+        ))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let json = json_body(response).await;
+    json!({ "short_url": json["short_url"], "redirect_base_url": json["redirect_base_url"] })
+}
+
+#[tokio::test]
+async fn test_short_urls_include_the_prefix() {
+    assert_eq!(
+        created_short_url(Some("/s")).await,
+        json!({
+            "short_url": "http://localhost:3000/s/abc",
+            "redirect_base_url": "http://localhost:3000/s",
+        })
+    );
+    assert_eq!(
+        created_short_url(None).await,
+        json!({
+            "short_url": "http://localhost:3000/abc",
+            "redirect_base_url": "http://localhost:3000",
+        })
+    );
+}
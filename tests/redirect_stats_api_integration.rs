@@ -0,0 +1,162 @@
+//! Integration tests for the admin redirect statistics endpoint
+//!
+//! The redirect server and the API server share one set of counters; these
+//! tests drive redirects through the redirect router and read the results back
+//! through `GET /api/stats/redirects`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, RedirectStatsConfig};
+use lynx::redirect::{self, RedirectStats};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config with the given redirect stats settings
+fn create_test_config(redirect_stats: RedirectStatsConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        redirect_stats,
+        ..common::test_config()
+    })
+}
+
+/// Build API and redirect routers sharing the stats built from `stats_config`.
+async fn create_test_apps(stats_config: RedirectStatsConfig) -> (Router, Router) {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let inner: Arc<dyn Storage> = Arc::new(inner);
+    let cached = Arc::new(CachedStorage::new(Arc::clone(&inner), 1_000, 5, 1_000, 10));
+    cached
+        .create_with_code("spring-sale", "https://example.com/sale", None)
+        .await
+        .unwrap();
+
+    let config = create_test_config(stats_config);
+    let stats = RedirectStats::from_config(&config.redirect_stats, config.short_code_max_length)
+        .map(Arc::new);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+
+    let api_app = api::create_api_router_with_redirect_stats(
+        inner,
+        auth_service,
+        config,
+        None,
+        stats.clone(),
+    );
+    let redirect_app = redirect::create_redirect_router_with_stats(
+        cached,
+        None,
+        false,
+        StatusCode::PERMANENT_REDIRECT,
+        stats,
+    );
+    (api_app, redirect_app)
+}
+
+async fn request(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_redirect_stats_report_outcomes_and_top_missing_codes() {
+    let (api_app, redirect_app) = create_test_apps(RedirectStatsConfig {
+        enabled: true,
+        top_missing_capacity: 16,
+    })
+    .await;
+
+    for uri in [
+        "/spring-sale",
+        "/spring-sale",
+        "/sprng-sale",
+        "/sprng-sale",
+        "/sprng-sale",
+        "/spring-sael",
+    ] {
+        request(&redirect_app, uri).await;
+    }
+
+    let (status, json) = request(&api_app, "/api/stats/redirects?limit=1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["found"], 2);
+    assert_eq!(json["inactive"], 0);
+    assert_eq!(json["not_found"], 4);
+    assert_eq!(json["expired"], 0);
+    assert_eq!(json["limit"], 1);
+    assert_eq!(json["top_missing_capacity"], 16);
+    assert_eq!(
+        json["top_missing"],
+        json!([{ "code": "sprng-sale", "count": 3 }])
+    );
+
+    // Creating the link cached it, so both visits are cache hits
+    let latency = &json["latency"];
+    assert_eq!(latency["window_secs"], 300);
+    assert_eq!(latency["cache_hit"]["count"], 2);
+    assert_eq!(latency["db_hit"]["count"], 0);
+    assert_eq!(latency["db_hit"]["p50_ms"], Value::Null);
+    assert_eq!(latency["miss"]["count"], 4);
+    for outcome in ["cache_hit", "miss"] {
+        for percentile in ["p50_ms", "p95_ms", "p99_ms"] {
+            assert!(
+                latency[outcome][percentile].is_number(),
+                "{outcome} {percentile}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_stats_without_tracker_still_count_outcomes() {
+    let (api_app, redirect_app) = create_test_apps(RedirectStatsConfig {
+        enabled: true,
+        top_missing_capacity: 0,
+    })
+    .await;
+    request(&redirect_app, "/missing").await;
+
+    let (status, json) = request(&api_app, "/api/stats/redirects").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["not_found"], 1);
+    assert_eq!(json["top_missing"], json!([]));
+    assert_eq!(json["latency"]["miss"]["count"], 1);
+    assert_eq!(json["latency"]["cache_hit"]["p50_ms"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_redirect_stats_disabled_returns_not_found() {
+    let (api_app, redirect_app) = create_test_apps(RedirectStatsConfig::default()).await;
+    let (status, _) = request(&redirect_app, "/spring-sale").await;
+    assert_eq!(status, StatusCode::PERMANENT_REDIRECT);
+
+    let (status, _) = request(&api_app, "/api/stats/redirects").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
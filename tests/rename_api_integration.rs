@@ -0,0 +1,192 @@
+//! Integration tests for renaming links through `POST /api/links/{code}/rename`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::redirect;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10))
+}
+
+async fn create_test_app(storage: &Arc<CachedStorage>) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    )
+}
+
+async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    let body = match body {
+        Some(body) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn rename(app: &Router, code: &str, new_code: &str) -> (StatusCode, Value) {
+    call(
+        app,
+        "POST",
+        &format!("/api/links/{}/rename", URL_SAFE_NO_PAD.encode(code)),
+        Some(json!({ "new_code": new_code })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_renamed_code_redirects_and_counts_clicks_on_the_new_code() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    let redirects = redirect::routes::create_redirect_router(
+        Arc::clone(&storage),
+        None,
+        false,
+        StatusCode::FOUND,
+    );
+    storage
+        .create_with_code("old", "https://example.com/docs", None)
+        .await
+        .unwrap();
+
+    // Warm the cache for the old code before it becomes an alias.
+    let hit = |code: &'static str| {
+        let redirects = redirects.clone();
+        async move {
+            let request = Request::builder()
+                .uri(format!("/{code}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = redirects.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FOUND);
+            response.headers()[header::LOCATION]
+                .to_str()
+                .unwrap()
+                .to_owned()
+        }
+    };
+    assert_eq!(hit("old").await, "https://example.com/docs");
+    storage.flush().await.unwrap();
+
+    let (status, json) = rename(&app, "old", "new").await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["short_code"], "new");
+    assert_eq!(json["short_url"], "http://localhost:3000/new");
+
+    let (status, _) = call(
+        &app,
+        "PATCH",
+        &format!("/api/urls/{}", URL_SAFE_NO_PAD.encode("new")),
+        Some(json!({ "url": "https://example.com/moved" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(hit("old").await, "https://example.com/moved");
+    assert_eq!(hit("new").await, "https://example.com/moved");
+    storage.flush().await.unwrap();
+
+    let new = storage.get_authoritative("new").await.unwrap().unwrap();
+    let old = storage.get_authoritative("old").await.unwrap().unwrap();
+    assert_eq!(new.clicks, 2);
+    assert_eq!(
+        old.clicks, 1,
+        "only clicks from before the rename stay on the alias"
+    );
+}
+
+#[tokio::test]
+async fn test_listing_nests_aliases_under_their_canonical_link() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    storage
+        .create_with_code("first", "https://example.com/docs", None)
+        .await
+        .unwrap();
+    assert_eq!(rename(&app, "first", "second").await.0, StatusCode::CREATED);
+    assert_eq!(rename(&app, "second", "third").await.0, StatusCode::CREATED);
+
+    let (status, json) = call(&app, "GET", "/api/urls", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let urls = json["urls"].as_array().unwrap();
+    assert_eq!(urls.len(), 1);
+    assert_eq!(urls[0]["short_code"], "third");
+    let mut aliases: Vec<&str> = urls[0]["aliases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|alias| alias["short_code"].as_str().unwrap())
+        .collect();
+    aliases.sort();
+    assert_eq!(aliases, vec!["first", "second"]);
+}
+
+#[tokio::test]
+async fn test_rename_rejects_chains_cycles_and_taken_codes() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    for code in ["alpha", "taken"] {
+        storage
+            .create_with_code(code, &format!("https://example.com/{code}"), None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(rename(&app, "alpha", "beta").await.0, StatusCode::CREATED);
+
+    assert_eq!(
+        rename(&app, "alpha", "gamma").await.0,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(rename(&app, "beta", "alpha").await.0, StatusCode::CONFLICT);
+    assert_eq!(rename(&app, "beta", "taken").await.0, StatusCode::CONFLICT);
+    assert_eq!(rename(&app, "beta", "").await.0, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        rename(&app, "missing", "delta").await.0,
+        StatusCode::NOT_FOUND
+    );
+    assert!(storage.get_authoritative("gamma").await.unwrap().is_none());
+}
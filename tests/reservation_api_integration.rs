@@ -0,0 +1,216 @@
+//! Integration tests for code reservation `POST /api/links/reserve`
+//!
+//! Reserved codes are created through the API router, looked up through the
+//! redirect router and activated through `PATCH /api/urls/{code}`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::redirect;
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+/// Build API and redirect routers over one cached storage.
+async fn create_test_apps() -> (Router, Router, Arc<CachedStorage>) {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    let storage = Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10));
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let api_app = api::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    );
+    let redirect_app = redirect::create_redirect_router(
+        Arc::clone(&storage),
+        None,
+        false,
+        StatusCode::PERMANENT_REDIRECT,
+    );
+    (api_app, redirect_app, storage)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    let request = match body {
+        Some(body) => request.body(Body::from(body.to_string())).unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn visit(app: &Router, code: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/{code}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_reserved_block_is_listed_and_not_redirected() {
+    let (api_app, redirect_app, _) = create_test_apps().await;
+
+    let (status, json) = send(
+        &api_app,
+        "POST",
+        "/api/links/reserve",
+        Some(json!({"block": {"prefix": "spring24-", "from": "a", "to": "z"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let reserved = json["reserved"].as_array().unwrap();
+    assert_eq!(reserved.len(), 26);
+    assert_eq!(reserved[0]["short_code"], "spring24-a");
+    assert_eq!(reserved[0]["original_url"], "");
+    assert_eq!(reserved[0]["short_url"], "http://localhost:3000/spring24-a");
+    assert_eq!(reserved[0]["reserved_until"], json["reserved_until"]);
+    assert!(json["reserved_until"].as_i64().unwrap() > chrono::Utc::now().timestamp_millis());
+
+    let (status, json) = send(&api_app, "GET", "/api/urls?limit=100", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let urls = json["urls"].as_array().unwrap();
+    assert_eq!(urls.len(), 26);
+    assert!(urls.iter().all(|url| url["reserved_until"].is_i64()));
+
+    assert_eq!(
+        visit(&redirect_app, "spring24-c").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_setting_a_destination_activates_a_reserved_code() {
+    let (api_app, redirect_app, _) = create_test_apps().await;
+
+    let (status, _) = send(
+        &api_app,
+        "POST",
+        "/api/links/reserve",
+        Some(json!({"codes": ["launch"]})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    // Looking the code up caches the reservation on the redirect side.
+    assert_eq!(visit(&redirect_app, "launch").await, StatusCode::NOT_FOUND);
+
+    let (status, json) = send(
+        &api_app,
+        "PATCH",
+        &format!("/api/urls/{}", URL_SAFE_NO_PAD.encode("launch")),
+        Some(json!({"url": "https://example.com/launch"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["reserved_until"], Value::Null);
+    assert_eq!(json["original_url"], "https://example.com/launch");
+
+    assert_eq!(
+        visit(&redirect_app, "launch").await,
+        StatusCode::PERMANENT_REDIRECT
+    );
+}
+
+#[tokio::test]
+async fn test_reservation_conflicts_reserve_nothing() {
+    let (api_app, _, storage) = create_test_apps().await;
+    storage
+        .create_with_code("promo-b", "https://example.com/b", None)
+        .await
+        .unwrap();
+
+    let (status, _) = send(
+        &api_app,
+        "POST",
+        "/api/links/reserve",
+        Some(json!({"block": {"prefix": "promo-", "from": "a", "to": "c"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(storage
+        .get_authoritative("promo-a")
+        .await
+        .unwrap()
+        .is_none());
+
+    let (status, _) = send(
+        &api_app,
+        "POST",
+        "/api/links/reserve",
+        Some(json!({"block": {"prefix": "promo-", "from": "c", "to": "a"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(&api_app, "POST", "/api/links/reserve", Some(json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_expired_reservations_are_deactivated() {
+    let (api_app, redirect_app, storage) = create_test_apps().await;
+
+    let (_, json) = send(
+        &api_app,
+        "POST",
+        "/api/links/reserve",
+        Some(json!({"codes": ["later"]})),
+    )
+    .await;
+    // Sent in milliseconds, swept by the second
+    let reserved_until = json["reserved_until"].as_i64().unwrap() / 1000;
+
+    assert_eq!(
+        storage
+            .expire_reservations(reserved_until - 1)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        storage.expire_reservations(reserved_until).await.unwrap(),
+        1
+    );
+
+    let url = storage.get_authoritative("later").await.unwrap().unwrap();
+    assert!(!url.is_active);
+    assert_eq!(visit(&redirect_app, "later").await, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,160 @@
+//! Integration tests for the batch lookup `POST /api/links/resolve`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request, StatusCode},
+    Extension, Json, Router,
+};
+use lynx::api::{
+    self,
+    code_rng::CodeRng,
+    handlers::AppState,
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    resolve::{resolve_links, ResolveLinksRequest},
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::{AuthClaims, AuthService};
+use lynx::clock::system_clock;
+use lynx::config::{AuthConfig, AuthMode, Config};
+use lynx::storage::{CachedStorage, SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config
+fn create_test_config() -> Arc<Config> {
+    Arc::new(common::test_config())
+}
+
+async fn create_test_storage() -> Arc<CachedStorage> {
+    let inner = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    inner.init().await.unwrap();
+    Arc::new(CachedStorage::new(Arc::new(inner), 1_000, 5, 1_000, 10))
+}
+
+async fn create_test_app(storage: &Arc<CachedStorage>) -> Router {
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::create_api_router(
+        Arc::clone(storage) as Arc<dyn Storage>,
+        auth_service,
+        create_test_config(),
+        None,
+    )
+}
+
+async fn resolve(app: &Router, codes: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/links/resolve")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "codes": codes }).to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_resolve_maps_every_code_to_a_link_or_null() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+    for code in ["one", "two"] {
+        storage
+            .create_with_code(code, &format!("https://example.com/{code}"), None)
+            .await
+            .unwrap();
+    }
+    storage.deactivate("two").await.unwrap();
+
+    // The second call is served from the cache warmed by the first.
+    for _ in 0..2 {
+        let (status, json) = resolve(&app, json!(["one", "two", "missing", "one"])).await;
+        assert_eq!(status, StatusCode::OK);
+        let links = json["links"].as_object().unwrap();
+        assert_eq!(links.len(), 3);
+        assert_eq!(links["one"]["original_url"], "https://example.com/one");
+        assert_eq!(links["one"]["short_url"], "http://localhost:3000/one");
+        assert_eq!(links["two"]["is_active"], false);
+        assert!(links["missing"].is_null());
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_rejects_too_many_codes() {
+    let storage = create_test_storage().await;
+    let app = create_test_app(&storage).await;
+
+    let codes: Vec<String> = (0..=api::resolve::MAX_RESOLVE_CODES)
+        .map(|i| format!("code{i}"))
+        .collect();
+    let (status, _) = resolve(&app, json!(codes)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, json) = resolve(&app, json!([])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["links"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_resolve_hides_other_users_links_from_non_admins() {
+    let storage = create_test_storage().await;
+    storage
+        .create_with_code("mine", "https://example.com/mine", Some("alice"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("theirs", "https://example.com/theirs", Some("bob"))
+        .await
+        .unwrap();
+    let config = create_test_config();
+    let state = Arc::new(AppState {
+        storage: Arc::clone(&storage) as Arc<dyn Storage>,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+    let claims = AuthClaims(Arc::new(json!({ "sub": "alice" })));
+
+    let public_base = PublicBaseUrl(state.config.redirect_base_url.clone());
+    let Json(response) = resolve_links(
+        State(state),
+        public_base,
+        Extension(Some(claims)),
+        Json(ResolveLinksRequest {
+            codes: vec!["mine".to_string(), "theirs".to_string()],
+        }),
+    )
+    .await
+    .unwrap_or_else(|error| panic!("resolve failed: {}", error.message()));
+
+    assert_eq!(
+        response.links["mine"].as_ref().unwrap().inner.short_code,
+        "mine"
+    );
+    assert!(response.links["theirs"].is_none());
+}
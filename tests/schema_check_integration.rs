@@ -0,0 +1,169 @@
+//! Integration tests for the read-only schema check CLI commands run
+//!
+//! `verify_schema` passes on a database the server (or `lynx db migrate`)
+//! has set up, fails with a hint on a missing or outdated schema, and needs
+//! no DDL privileges: on Postgres the CLI commands' storage calls are run as
+//! a role that may read and write rows but not create anything.
+#![cfg(feature = "sqlite")]
+
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{SqliteStorage, Storage};
+#[cfg(feature = "postgres")]
+use std::sync::Arc;
+
+const HINT: &str = "run the server or `lynx db migrate` first";
+
+/// Get the database backend to test from environment variable
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(val) => val.eq_ignore_ascii_case(backend),
+        Err(_) => true,
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_check_needs_a_migrated_database() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+    let storage = SqliteStorage::new("sqlite::memory:", 1).await.unwrap();
+
+    let error = storage.verify_schema().await.unwrap_err().to_string();
+    assert!(error.contains("table urls"), "{error}");
+    assert!(error.ends_with(HINT), "{error}");
+
+    storage.ensure_schema().await.unwrap();
+    storage.verify_schema().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_check_rejects_a_database_missing_a_migration() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("lynx-schema-check-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("lynx.db").display());
+
+    let storage = SqliteStorage::new(&url, 1).await.unwrap();
+    storage.ensure_schema().await.unwrap();
+    // Undo the newest users migration, as if the database predated it
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    sqlx::query("ALTER TABLE users DROP COLUMN last_seen_at")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    let error = storage.verify_schema().await.unwrap_err().to_string();
+    assert!(error.contains("(column users.last_seen_at)"), "{error}");
+
+    storage.ensure_schema().await.unwrap();
+    storage.verify_schema().await.unwrap();
+    drop(storage);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "postgres")]
+const SCHEMA: &str = "lynx_cli_role_test";
+#[cfg(feature = "postgres")]
+const ROLE: &str = "lynx_cli_no_ddl";
+#[cfg(feature = "postgres")]
+const ROLE_PASSWORD: &str = "lynx-cli-no-ddl";
+
+#[cfg(feature = "postgres")]
+/// Set up `SCHEMA` as the server would, then grant `ROLE` row access to it
+/// and nothing else.
+async fn grant_row_access_only(db_url: &str) {
+    let owner = PostgresStorage::new_in_schema(db_url, 1, Default::default(), Some(SCHEMA))
+        .await
+        .unwrap();
+    owner.ensure_schema().await.unwrap();
+
+    let admin = sqlx::PgPool::connect(db_url).await.unwrap();
+    sqlx::query(&format!(
+        "DO $$ BEGIN
+            IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = '{ROLE}') THEN
+                CREATE ROLE {ROLE} LOGIN PASSWORD '{ROLE_PASSWORD}';
+            END IF;
+        END $$"
+    ))
+    .execute(&admin)
+    .await
+    .unwrap();
+    for statement in [
+        format!("REVOKE CREATE ON SCHEMA public FROM {ROLE}"),
+        format!("REVOKE CREATE ON SCHEMA {SCHEMA} FROM {ROLE}"),
+        format!("GRANT USAGE ON SCHEMA {SCHEMA} TO {ROLE}"),
+        format!("GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA {SCHEMA} TO {ROLE}"),
+        format!("GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA {SCHEMA} TO {ROLE}"),
+    ] {
+        sqlx::query(&statement).execute(&admin).await.unwrap();
+    }
+    admin.close().await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_cli_commands_work_without_ddl_privileges() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    grant_row_access_only(&db_url).await;
+
+    let mut role_url = url::Url::parse(&db_url).unwrap();
+    role_url.set_username(ROLE).unwrap();
+    role_url.set_password(Some(ROLE_PASSWORD)).unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(
+        PostgresStorage::new_in_schema(role_url.as_str(), 1, Default::default(), Some(SCHEMA))
+            .await
+            .unwrap(),
+    );
+
+    // The role really cannot migrate
+    assert!(storage.ensure_schema().await.is_err());
+
+    // What `lynx admin`, `lynx user` and `lynx patch` do after the check
+    storage.verify_schema().await.unwrap();
+    let code = format!("cli_role_{}", std::process::id());
+    storage
+        .create_with_code(&code, "https://example.com", None)
+        .await
+        .unwrap();
+    assert!(storage
+        .patch_created_by(&code, "cli-user", Some("oauth"))
+        .await
+        .unwrap());
+    storage
+        .upsert_user("cli-user", Some("cli@example.com"), "oauth")
+        .await
+        .unwrap();
+    storage.promote_to_admin("cli-user", "oauth").await.unwrap();
+    assert!(storage
+        .list_manual_admins()
+        .await
+        .unwrap()
+        .iter()
+        .any(|(user_id, _, _)| user_id == "cli-user"));
+    assert!(!storage
+        .list_all_users(50, None, None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(
+        storage
+            .bulk_deactivate_user_links("cli-user")
+            .await
+            .unwrap()
+            >= 1
+    );
+    assert!(storage
+        .demote_from_admin("cli-user", "oauth")
+        .await
+        .unwrap());
+}
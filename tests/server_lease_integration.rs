@@ -0,0 +1,109 @@
+//! Integration tests for the leases running servers hold in the database,
+//! which `lynx link hard-delete` checks before deleting anything.
+//!
+//! Like `storage_integration_test`, `DATABASE_BACKEND` picks the backend and
+//! the Postgres variant needs `DATABASE_URL`. The Postgres database may hold
+//! other servers' leases, so only this test's lease is looked at.
+#![cfg(feature = "sqlite")]
+
+use lynx::clock::{Clock, FakeClock};
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{ServerLease, SqliteStorage, Storage, LEASE_TTL};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 2023-03-10T00:00:00Z
+const DAY_MS: i64 = 1_678_406_400_000;
+
+fn should_test_backend(backend: &str) -> bool {
+    match std::env::var("DATABASE_BACKEND") {
+        Ok(selected) => selected == backend,
+        Err(_) => true,
+    }
+}
+
+async fn held(storage: &dyn Storage, lease: &ServerLease) -> Option<ServerLease> {
+    storage
+        .active_server_leases()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|active| active.instance_id == lease.instance_id)
+}
+
+async fn assert_leases_lapse_and_release(storage: &dyn Storage, clock: &FakeClock) {
+    let ttl = i64::try_from(LEASE_TTL.as_millis()).unwrap();
+    let mut lease = ServerLease::starting(clock);
+    assert_eq!(held(storage, &lease).await, None, "not renewed yet");
+
+    lease.expires_at = clock.now_epoch_ms() + ttl;
+    storage.renew_server_lease(&lease).await.unwrap();
+    assert_eq!(held(storage, &lease).await, Some(lease.clone()));
+
+    // Renewing moves only the expiry
+    clock.advance(Duration::from_secs(30));
+    let renewed = ServerLease {
+        host: "elsewhere".to_string(),
+        started_at: 0,
+        expires_at: clock.now_epoch_ms() + ttl,
+        ..lease.clone()
+    };
+    storage.renew_server_lease(&renewed).await.unwrap();
+    lease.expires_at = renewed.expires_at;
+    assert_eq!(held(storage, &lease).await, Some(lease.clone()));
+
+    // A server that stops renewing loses its lease
+    clock.set_epoch_ms(lease.expires_at);
+    assert_eq!(held(storage, &lease).await, None, "lapsed");
+
+    clock.advance(Duration::from_secs(1));
+    lease.expires_at = clock.now_epoch_ms() + ttl;
+    storage.renew_server_lease(&lease).await.unwrap();
+    assert!(held(storage, &lease).await.is_some());
+    storage
+        .release_server_lease(&lease.instance_id)
+        .await
+        .unwrap();
+    assert_eq!(held(storage, &lease).await, None, "released");
+    storage
+        .release_server_lease(&lease.instance_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_server_leases_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY_MS));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    assert!(storage.active_server_leases().await.unwrap().is_empty());
+    assert_leases_lapse_and_release(&storage, &clock).await;
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_server_leases_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let clock = Arc::new(FakeClock::at_epoch_ms(DAY_MS));
+    let storage = PostgresStorage::new(&db_url, 5)
+        .await
+        .unwrap()
+        .with_clock(Arc::clone(&clock) as _);
+    storage.init().await.unwrap();
+    assert_leases_lapse_and_release(&storage, &clock).await;
+}
@@ -0,0 +1,249 @@
+//! Integration tests for the Slack slash command endpoint
+//!
+//! Requests are signed exactly like Slack signs them. The recorded payload is
+//! the example from Slack's request verification guide, with `text` filled in
+//! where a command needs a URL.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use hmac::{Hmac, Mac};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, SlackConfig};
+use lynx::models::CreatedVia;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+const SIGNING_SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+
+/// Slash command payload recorded from Slack, with `text` left as a placeholder.
+const RECORDED_PAYLOAD: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fshorten&text={text}&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+
+/// The unmodified example request and signature from Slack's guide.
+const RECORDED_TIMESTAMP: &str = "1531420618";
+const RECORDED_SIGNATURE: &str =
+    "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+/// Helper to create test config with the given Slack settings
+fn create_test_config(slack: Option<SlackConfig>) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        slack,
+        ..common::test_config()
+    })
+}
+
+fn slack_config() -> SlackConfig {
+    SlackConfig {
+        signing_secret: SIGNING_SECRET.to_string(),
+        user_map: [("U2CERLKJA".to_string(), "roadrunner-sub".to_string())].into(),
+        service_user: None,
+    }
+}
+
+async fn create_test_app(slack: Option<SlackConfig>) -> (Router, Arc<dyn Storage>) {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let app = api::routes::create_api_router(
+        Arc::clone(&storage),
+        auth_service,
+        create_test_config(slack),
+        None,
+    );
+    (app, storage)
+}
+
+fn payload(text: &str, user_id: &str) -> String {
+    RECORDED_PAYLOAD
+        .replace("{text}", text)
+        .replace("user_id=U2CERLKJA", &format!("user_id={user_id}"))
+}
+
+fn sign(timestamp: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SIGNING_SECRET.as_bytes()).unwrap();
+    mac.update(format!("v0:{timestamp}:{body}").as_bytes());
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("v0={digest}")
+}
+
+async fn post_command(
+    app: &Router,
+    timestamp: &str,
+    signature: &str,
+    body: String,
+) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/integrations/slack")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .header("X-Slack-Request-Timestamp", timestamp)
+                .header("X-Slack-Signature", signature)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Sign `body` with the current time and post it.
+async fn post_signed(app: &Router, body: String) -> (StatusCode, Value) {
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let signature = sign(&timestamp, &body);
+    post_command(app, &timestamp, &signature, body).await
+}
+
+#[tokio::test]
+async fn test_slash_command_creates_link_for_mapped_user() {
+    let (app, storage) = create_test_app(Some(slack_config())).await;
+
+    let (status, json) = post_signed(
+        &app,
+        payload("https%3A%2F%2Fexample.com%2Fteam%2Fdocs", "U2CERLKJA"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["response_type"], "ephemeral");
+    let short_url = json["text"].as_str().unwrap();
+    assert!(short_url.starts_with("http://localhost:3000/"), "{json}");
+
+    let links = storage
+        .list_user_links("roadrunner-sub", 10, None)
+        .await
+        .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].original_url, "https://example.com/team/docs");
+    assert_eq!(links[0].created_via, CreatedVia::Integration);
+    assert!(short_url.ends_with(&links[0].short_code));
+}
+
+#[tokio::test]
+async fn test_slash_command_uses_service_user_for_unmapped_users() {
+    let (app, storage) = create_test_app(Some(SlackConfig {
+        service_user: Some("slack-bot".to_string()),
+        ..slack_config()
+    }))
+    .await;
+
+    let (status, _) = post_signed(&app, payload("https%3A%2F%2Fexample.com", "U999")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        storage
+            .list_user_links("slack-bot", 10, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_slash_command_answers_user_errors_ephemerally() {
+    let (app, storage) = create_test_app(Some(slack_config())).await;
+
+    let (status, json) = post_signed(&app, payload("https%3A%2F%2Fexample.com", "U999")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        json["text"].as_str().unwrap().contains("not linked"),
+        "{json}"
+    );
+
+    let (status, json) = post_signed(&app, payload("javascript%3Aalert(1)", "U2CERLKJA")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        json["text"].as_str().unwrap().contains("not allowed"),
+        "{json}"
+    );
+
+    let (_, json) = post_signed(&app, payload("", "U2CERLKJA")).await;
+    assert_eq!(
+        json,
+        json!({
+            "response_type": "ephemeral",
+            "text": "Usage: `/shorten https://example.com/long/link`",
+        })
+    );
+
+    assert!(storage
+        .list_user_links("roadrunner-sub", 10, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_replayed_recorded_request_is_rejected() {
+    let (app, _) = create_test_app(Some(slack_config())).await;
+    let recorded = payload("", "U2CERLKJA").replace("%2Fshorten", "%2Fwebhook-collect");
+
+    // Valid signature, but the timestamp is years old.
+    let (status, _) = post_command(&app, RECORDED_TIMESTAMP, RECORDED_SIGNATURE, recorded).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_invalid_signature_is_rejected() {
+    let (app, storage) = create_test_app(Some(slack_config())).await;
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let signature = sign(
+        &timestamp,
+        &payload("https%3A%2F%2Fexample.com", "U2CERLKJA"),
+    );
+
+    let tampered = payload("https%3A%2F%2Fevil.example", "U2CERLKJA");
+    let (status, _) = post_command(&app, &timestamp, &signature, tampered).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = post_command(
+        &app,
+        &timestamp,
+        "v0=00",
+        payload("https%3A%2F%2Fexample.com", "U2CERLKJA"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(storage
+        .list_user_links("roadrunner-sub", 10, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_slack_endpoint_is_not_found_when_not_configured() {
+    let (app, _) = create_test_app(None).await;
+    let (status, _) = post_signed(&app, payload("https%3A%2F%2Fexample.com", "U2CERLKJA")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,231 @@
+//! Integration tests for click-count privacy: who sees the clicks of a link
+//! with `hide_stats`, or of any link under `HIDE_STATS_BY_DEFAULT`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api::{
+    analytics::{get_analytics, AnalyticsQueryParams, AnalyticsState},
+    code_rng::CodeRng,
+    handlers::{create_url, get_url, update_url, AppState},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::system_clock;
+use lynx::config::{Config, StatsPrivacyConfig};
+use lynx::models::{CreateUrlRequest, UpdateUrlRequest};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+
+async fn create_test_state(hide_by_default: bool) -> Arc<AppState> {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let config = Arc::new(Config {
+        stats_privacy: StatsPrivacyConfig { hide_by_default },
+        ..common::test_config()
+    });
+    Arc::new(AppState {
+        storage: Arc::new(storage),
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    })
+}
+
+fn claims(value: Value) -> Extension<Option<AuthClaims>> {
+    Extension(Some(AuthClaims(Arc::new(value))))
+}
+
+fn base(state: &AppState) -> PublicBaseUrl {
+    PublicBaseUrl(state.config.redirect_base_url.clone())
+}
+
+fn owner() -> Value {
+    json!({ "sub": "alice" })
+}
+
+fn stranger() -> Value {
+    json!({ "sub": "mallory" })
+}
+
+fn admin() -> Value {
+    json!({ "sub": "root", "is_admin": true })
+}
+
+/// Create `code` as alice with `hide_stats`, then give it 5 clicks
+async fn create(state: &Arc<AppState>, code: &str, hide_stats: Option<bool>) -> Value {
+    let (_, Json(response)) = create_url(
+        State(Arc::clone(state)),
+        base(state),
+        claims(owner()),
+        HeaderMap::new(),
+        Json(CreateUrlRequest {
+            url: "https://example.com/private".to_string(),
+            custom_code: Some(code.to_string()),
+            created_by_override: None,
+            hide_stats,
+            interstitial: None,
+            code_strategy: None,
+            campaign_id: None,
+            expires_at: None,
+            max_clicks: None,
+        }),
+    )
+    .await
+    .unwrap();
+    state.storage.increment_clicks(code, 5).await.unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+/// The link as `viewer` gets it from `GET /api/urls/{code}`
+async fn view(state: &Arc<AppState>, code: &str, viewer: Value) -> Value {
+    let Json(response) = get_url(
+        State(Arc::clone(state)),
+        base(state),
+        claims(viewer),
+        Path(URL_SAFE_NO_PAD.encode(code)),
+    )
+    .await
+    .unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+/// The status `viewer` gets from `GET /api/analytics/{code}`
+async fn analytics_status(state: &Arc<AppState>, code: &str, viewer: Value) -> StatusCode {
+    let analytics = Arc::new(AnalyticsState {
+        storage: Arc::clone(&state.storage),
+        aggregator: None,
+        max_limit: 100,
+        hide_stats_by_default: state.config.stats_privacy.hide_by_default,
+    });
+    get_analytics(
+        State(analytics),
+        claims(viewer),
+        Path(URL_SAFE_NO_PAD.encode(code)),
+        Query(AnalyticsQueryParams {
+            start_time: None,
+            end_time: None,
+            group_by: None,
+            limit: None,
+            tz: None,
+        }),
+    )
+    .await
+    .into_response()
+    .status()
+}
+
+#[tokio::test]
+async fn test_hidden_clicks_are_visible_to_owner_and_admin_only() {
+    let state = create_test_state(false).await;
+    let created = create(&state, "secret", Some(true)).await;
+    assert_eq!(created["hide_stats"], true);
+
+    for viewer in [owner(), admin()] {
+        let link = view(&state, "secret", viewer.clone()).await;
+        assert_eq!(link["clicks"], 5);
+        assert!(link.get("stats_hidden").is_none());
+        assert_eq!(
+            analytics_status(&state, "secret", viewer).await,
+            StatusCode::OK
+        );
+    }
+
+    let link = view(&state, "secret", stranger()).await;
+    assert_eq!(link["clicks"], 0);
+    assert_eq!(link["stats_hidden"], true);
+    assert_eq!(link["original_url"], "https://example.com/private");
+    assert_eq!(
+        analytics_status(&state, "secret", stranger()).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_links_follow_the_default_unless_they_choose() {
+    let state = create_test_state(true).await;
+    create(&state, "default", None).await;
+    create(&state, "public", Some(false)).await;
+
+    assert_eq!(
+        view(&state, "default", stranger()).await["stats_hidden"],
+        true
+    );
+    assert_eq!(view(&state, "public", stranger()).await["clicks"], 5);
+    assert_eq!(
+        analytics_status(&state, "public", stranger()).await,
+        StatusCode::OK
+    );
+
+    // Without the default, unflagged links show their clicks to everyone
+    let state = create_test_state(false).await;
+    create(&state, "open", None).await;
+    assert_eq!(view(&state, "open", stranger()).await["clicks"], 5);
+}
+
+#[tokio::test]
+async fn test_owner_can_change_visibility_on_update() {
+    let state = create_test_state(false).await;
+    create(&state, "toggle", None).await;
+
+    let Json(updated) = update_url(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(owner()),
+        Path(URL_SAFE_NO_PAD.encode("toggle")),
+        HeaderMap::new(),
+        Json(UpdateUrlRequest {
+            url: "https://example.com/moved".to_string(),
+            created_by_override: None,
+            hide_stats: Some(true),
+            interstitial: None,
+            campaign_id: None,
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(serde_json::to_value(updated).unwrap()["hide_stats"], true);
+    assert_eq!(view(&state, "toggle", stranger()).await["clicks"], 0);
+
+    // Leaving it out keeps the setting
+    let Json(updated) = update_url(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(owner()),
+        Path(URL_SAFE_NO_PAD.encode("toggle")),
+        HeaderMap::new(),
+        Json(UpdateUrlRequest {
+            url: "https://example.com/again".to_string(),
+            created_by_override: None,
+            hide_stats: None,
+            interstitial: None,
+            campaign_id: None,
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(serde_json::to_value(updated).unwrap()["hide_stats"], true);
+    assert_eq!(
+        view(&state, "toggle", stranger()).await["stats_hidden"],
+        true
+    );
+}
@@ -7,10 +7,22 @@
 //! - `DATABASE_BACKEND=sqlite cargo test` - Run only SQLite tests
 //! - `DATABASE_BACKEND=postgres cargo test` - Run only PostgreSQL tests
 //! - By default, both backends are tested
-
-use lynx::storage::{CachedStorage, ClickIncrement, PostgresStorage, SqliteStorage, Storage};
+#![cfg(feature = "sqlite")]
+
+use lynx::analytics::{AnalyticsRollup, IpVersion};
+use lynx::clock::FakeClock;
+use lynx::models::LinkOptions;
+use lynx::storage::relevance::search_score;
+#[cfg(feature = "postgres")]
+use lynx::storage::PostgresStorage;
+use lynx::storage::{
+    BulkPreview, CachedStorage, ClickIncrement, SearchParams, SearchResult, SearchSort,
+    SqliteStorage, Storage, StorageError,
+};
+use lynx::testing::Fixture;
 use std::num::NonZeroU64;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::OnceCell;
 
 /// Mutex to serialize all Postgres tests since they share the same database instance.
@@ -34,6 +46,7 @@ async fn create_sqlite_storage() -> Arc<dyn Storage> {
 }
 
 /// Helper to create PostgreSQL test storage
+#[cfg(feature = "postgres")]
 async fn create_postgres_storage() -> Option<Arc<dyn Storage>> {
     let db_url = std::env::var("DATABASE_URL").ok()?;
     let storage = PostgresStorage::new(&db_url, 5).await.ok()?;
@@ -41,6 +54,13 @@ async fn create_postgres_storage() -> Option<Arc<dyn Storage>> {
     Some(Arc::new(storage))
 }
 
+/// Without the `postgres` feature the PostgreSQL tests skip as if no
+/// database were configured
+#[cfg(not(feature = "postgres"))]
+async fn create_postgres_storage() -> Option<Arc<dyn Storage>> {
+    None
+}
+
 #[tokio::test]
 async fn test_concurrent_url_creation_sqlite() {
     if !should_test_backend("sqlite") {
@@ -104,10 +124,10 @@ async fn test_user_management_lifecycle_sqlite() {
         .unwrap();
 
     // List users
-    let users = storage.list_all_users(10, 0).await.unwrap();
+    let users = storage.list_all_users(10, None, None).await.unwrap();
     assert_eq!(users.len(), 1);
-    assert_eq!(users[0].0, "user123");
-    assert_eq!(users[0].2, "user@example.com");
+    assert_eq!(users[0].user_id, "user123");
+    assert_eq!(users[0].email.as_deref(), Some("user@example.com"));
 
     // Update user email
     storage
@@ -115,8 +135,8 @@ async fn test_user_management_lifecycle_sqlite() {
         .await
         .unwrap();
 
-    let users = storage.list_all_users(10, 0).await.unwrap();
-    assert_eq!(users[0].2, "newemail@example.com");
+    let users = storage.list_all_users(10, None, None).await.unwrap();
+    assert_eq!(users[0].email.as_deref(), Some("newemail@example.com"));
 
     // Promote to admin
     assert!(!storage.is_manual_admin("user123", "oauth").await.unwrap());
@@ -204,21 +224,447 @@ async fn test_bulk_link_operations() {
     }
 }
 
+/// Preview every bulk command against links named after `prefix` and check
+/// that the previews are right and that nothing was written.
+async fn assert_bulk_dry_runs_write_nothing(storage: Arc<dyn Storage>, prefix: &str) {
+    let owner = format!("{prefix}_owner");
+    let codes: Vec<String> = ["a", "b", "c"]
+        .iter()
+        .map(|suffix| format!("{prefix}_{suffix}"))
+        .collect();
+    for code in &codes {
+        storage
+            .create_with_code(code, "https://example.com", Some(&owner))
+            .await
+            .unwrap();
+    }
+    assert!(storage.deactivate(&codes[2]).await.unwrap());
+    let anonymous = format!("{prefix}_anon");
+    storage
+        .create_with_code(&anonymous, "https://example.com", None)
+        .await
+        .unwrap();
+    let malformed_before = storage.count_malformed_created_by().await.unwrap();
+
+    let deactivation = BulkPreview::user_links(storage.as_ref(), &owner, true)
+        .await
+        .unwrap();
+    assert_eq!(deactivation.affected, 2);
+    assert_eq!(
+        deactivation.sample,
+        vec![codes[1].clone(), codes[0].clone()]
+    );
+
+    let reactivation = BulkPreview::user_links(storage.as_ref(), &owner, false)
+        .await
+        .unwrap();
+    assert_eq!(reactivation.affected, 1);
+    assert_eq!(reactivation.sample, vec![codes[2].clone()]);
+
+    let patch = BulkPreview::malformed_created_by(storage.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(patch.affected, malformed_before);
+    assert!(patch.sample.contains(&anonymous));
+
+    // Every link reads back exactly as it was before the dry runs.
+    let mut states = Vec::new();
+    for code in &codes {
+        let url = storage.get_authoritative(code).await.unwrap().unwrap();
+        states.push(url.is_active);
+    }
+    assert_eq!(states, vec![true, true, false]);
+    let anonymous = storage
+        .get_authoritative(&anonymous)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(anonymous.created_by, None);
+    assert_eq!(
+        storage.count_malformed_created_by().await.unwrap(),
+        malformed_before
+    );
+    assert_eq!(
+        storage
+            .count_user_links_by_state(&owner, true)
+            .await
+            .unwrap(),
+        2
+    );
+}
+
 #[tokio::test]
-async fn test_cursor_pagination() {
-    // Test cursor-based pagination for listing URLs
+async fn test_bulk_dry_runs_write_nothing_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
     let storage = create_sqlite_storage().await;
+    assert_bulk_dry_runs_write_nothing(storage, "dry").await;
+}
 
-    // Create 10 links with known timestamps
-    for i in 0..10 {
+#[tokio::test]
+async fn test_bulk_dry_runs_write_nothing_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    // Acquire lock to serialize all Postgres tests (shared database)
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    // Use unique codes to avoid collisions in the shared database.
+    let prefix = format!("pg_dry_{}", std::process::id());
+    assert_bulk_dry_runs_write_nothing(storage, &prefix).await;
+}
+
+async fn assert_search_pins_the_exact_code(storage: Arc<dyn Storage>, prefix: &str) {
+    let owner = format!("{prefix}_owner");
+    let q = format!("{prefix}abc");
+    // The exact code is the oldest, so by recency it would come last
+    let codes: Vec<String> = ["", "123", "d"]
+        .iter()
+        .map(|suffix| format!("{q}{suffix}"))
+        .chain([format!("x{q}")])
+        .collect();
+    for code in &codes {
+        storage
+            .create_with_code(code, "https://example.com/docs", Some(&owner))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    let mut params = SearchParams {
+        q: q.clone(),
+        created_by: Some(owner.clone()),
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 2,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+    let found = |result: &SearchResult| -> Vec<String> {
+        result
+            .items
+            .iter()
+            .map(|url| url.short_code.clone())
+            .collect()
+    };
+
+    let first = storage.search(&params, true, None).await.unwrap();
+    assert!(first.exact_match);
+    assert_eq!(found(&first), vec![q.clone(), codes[3].clone()]);
+    assert!(first.has_more);
+
+    params.cursor = first.next_cursor;
+    let second = storage.search(&params, true, None).await.unwrap();
+    assert!(!second.exact_match);
+    assert_eq!(found(&second), vec![codes[2].clone(), codes[1].clone()]);
+    assert!(!second.has_more);
+
+    // A page of one still reaches every other match, each once
+    params.limit = 1;
+    params.cursor = None;
+    let mut seen = Vec::new();
+    loop {
+        let page = storage.search(&params, true, None).await.unwrap();
+        seen.extend(found(&page));
+        match page.next_cursor {
+            Some(cursor) => params.cursor = Some(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(
+        seen,
+        vec![
+            q.clone(),
+            codes[3].clone(),
+            codes[2].clone(),
+            codes[1].clone()
+        ]
+    );
+
+    // The search filters apply to the exact match too
+    assert!(storage.deactivate(&q).await.unwrap());
+    params.limit = 10;
+    params.cursor = None;
+    params.is_active = Some(true);
+    let active = storage.search(&params, true, None).await.unwrap();
+    assert!(!active.exact_match);
+    assert_eq!(active.items.len(), 3);
+
+    params.is_active = None;
+    let stranger = format!("{prefix}_stranger");
+    let other = storage
+        .search(&params, false, Some(&stranger))
+        .await
+        .unwrap();
+    assert!(other.items.is_empty());
+    assert!(!other.exact_match);
+}
+
+#[tokio::test]
+async fn test_search_pins_the_exact_code_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_search_pins_the_exact_code(storage, "exact").await;
+}
+
+#[tokio::test]
+async fn test_search_pins_the_exact_code_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    // Use unique codes to avoid collisions in the shared database.
+    let prefix = format!("pg_exact_{}", std::process::id());
+    assert_search_pins_the_exact_code(storage, &prefix).await;
+}
+
+async fn assert_search_ranks_by_relevance(storage: Arc<dyn Storage>, prefix: &str) {
+    let owner = format!("{prefix}_owner");
+    let q = format!("{prefix}guide");
+    // Created best match first, so by recency it would come last
+    let links = [
+        (format!("{q}s"), format!("https://example.com/{q}")),
+        (
+            format!("{prefix}misc"),
+            format!("https://example.com/archive/2019/{q}-old-notes-and-more"),
+        ),
+        (
+            format!("zz{q}zz1234"),
+            "https://example.com/other".to_string(),
+        ),
+    ];
+    for (code, destination) in &links {
         storage
-            .create_with_code(&format!("page{}", i), "https://example.com", Some("user1"))
+            .create_with_code(code, destination, Some(&owner))
             .await
             .unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    let mut params = SearchParams {
+        q: q.clone(),
+        created_by: Some(owner.clone()),
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 10,
+        cursor: None,
+        sort: SearchSort::Relevance,
+        offset: None,
+    };
+
+    let ranked = storage.search(&params, true, None).await.unwrap();
+    assert!(!ranked.has_more);
+    let codes: Vec<&str> = ranked
+        .items
+        .iter()
+        .map(|url| url.short_code.as_str())
+        .collect();
+    assert_eq!(codes[0], links[0].0);
+    assert_eq!(codes.len(), links.len());
+    let scores: Vec<f32> = ranked
+        .items
+        .iter()
+        .map(|url| search_score(url, &q))
+        .collect();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    // Pages of one still reach every match, each once
+    params.limit = 1;
+    let mut seen = Vec::new();
+    loop {
+        let page = storage.search(&params, true, None).await.unwrap();
+        seen.extend(page.items.iter().map(|url| url.short_code.clone()));
+        match (page.next_offset, page.next_cursor) {
+            (Some(offset), _) => params.offset = Some(offset),
+            (None, Some(cursor)) => params.cursor = Some(cursor),
+            (None, None) => break,
+        }
+    }
+    seen.sort();
+    let mut all: Vec<String> = links.iter().map(|(code, _)| code.clone()).collect();
+    all.sort();
+    assert_eq!(seen, all);
+}
+
+#[tokio::test]
+async fn test_search_ranks_by_relevance_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_search_ranks_by_relevance(storage, "rel").await;
+}
+
+#[tokio::test]
+async fn test_search_ranks_by_relevance_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_rel_{}", std::process::id());
+    assert_search_ranks_by_relevance(storage, &prefix).await;
+}
+
+/// Every change to what a link does stamps `updated_at` with the storage's
+/// clock; clicks and titles do not.
+async fn assert_updated_at_follows_clock(storage: Arc<dyn Storage>, clock: &FakeClock, code: &str) {
+    let updated_at = || async {
+        let url = storage.get_authoritative(code).await.unwrap().unwrap();
+        url.updated_at
+    };
+    let created = storage
+        .create_with_code(code, "https://example.com/a", None)
+        .await
+        .unwrap();
+    assert_eq!(created.updated_at, 1_700_000_000_000);
+
+    clock.set_epoch_ms(1_700_000_001_000);
+    storage.increment_clicks(code, 1).await.unwrap();
+    storage.set_title_if_missing(code, "Title").await.unwrap();
+    assert_eq!(updated_at().await, 1_700_000_000_000);
+
+    clock.set_epoch_ms(1_700_000_002_000);
+    let moved = storage
+        .update_url(code, "https://example.com/b", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(moved.updated_at, 1_700_000_002_000);
+
+    clock.set_epoch_ms(1_700_000_003_000);
+    assert!(storage.deactivate(code).await.unwrap());
+    assert_eq!(updated_at().await, 1_700_000_003_000);
+    clock.set_epoch_ms(1_700_000_004_000);
+    assert!(storage.deactivate(code).await.unwrap());
+    assert_eq!(updated_at().await, 1_700_000_003_000);
+    assert!(storage.reactivate(code).await.unwrap());
+    assert_eq!(updated_at().await, 1_700_000_004_000);
+
+    clock.set_epoch_ms(1_700_000_005_000);
+    assert!(storage
+        .patch_created_by(code, "new-owner", None)
+        .await
+        .unwrap());
+    assert_eq!(updated_at().await, 1_700_000_005_000);
+
+    clock.set_epoch_ms(1_700_000_006_000);
+    let options = LinkOptions {
+        max_clicks: Some(10),
+        ..LinkOptions::default()
+    };
+    assert!(storage.set_link_options(code, &options).await.unwrap());
+    assert_eq!(updated_at().await, 1_700_000_006_000);
+}
+
+#[tokio::test]
+async fn test_updated_at_follows_clock_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let clock = Arc::new(FakeClock::at_epoch_ms(1_700_000_000_000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    storage.init().await.unwrap();
+    assert_updated_at_follows_clock(Arc::new(storage), &clock, "touched").await;
+}
 
-        // Sleep briefly to ensure different created_at times
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_updated_at_follows_clock_postgres() {
+    if !should_test_backend("postgres") {
+        return;
     }
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let clock = Arc::new(FakeClock::at_epoch_ms(1_700_000_000_000));
+    let storage = PostgresStorage::new(&db_url, 5)
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    storage.init().await.unwrap();
+    let code = format!("pg_touched_{}", std::process::id());
+    assert_updated_at_follows_clock(Arc::new(storage), &clock, &code).await;
+}
+
+#[tokio::test]
+async fn test_cursor_pagination() {
+    // Test cursor-based pagination for listing URLs
+    let clock = Arc::new(FakeClock::at_epoch_ms(1_700_000_000_000));
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    storage.init().await.unwrap();
+    let storage = Arc::new(storage);
+
+    // Create 10 links one millisecond apart
+    let seeded = Fixture::new(storage.clone())
+        .links_per_user(10)
+        .clock(clock)
+        .seed()
+        .await
+        .unwrap();
 
     // Get first page (limit 3)
     let page1 = storage.list_with_cursor(3, None, true, None).await.unwrap();
@@ -261,6 +707,12 @@ async fn test_cursor_pagination() {
     }
 
     assert_eq!(all_codes.len(), 10, "Should paginate through all items");
+    let newest_first: Vec<String> = seeded
+        .links()
+        .rev()
+        .map(|link| link.short_code().to_string())
+        .collect();
+    assert_eq!(all_codes, newest_first);
 }
 
 #[tokio::test]
@@ -308,6 +760,35 @@ async fn test_user_link_isolation() {
     assert_eq!(admin_links.len(), 3);
 }
 
+/// Fire 100 concurrent single-click increments, split across every click
+/// entry point, so no wrapper can silently diverge from `increment_clicks`.
+async fn spawn_concurrent_click_increments(storage: Arc<dyn Storage>, code: &'static str) {
+    let mut handles = vec![];
+
+    for i in 0..100 {
+        let storage_clone = Arc::clone(&storage);
+        let handle = tokio::spawn(async move {
+            match i % 4 {
+                0 => storage_clone.increment_click(code).await,
+                1 => storage_clone.increment_clicks(code, 1).await,
+                2 => storage_clone
+                    .increment_click_owned(code.to_owned())
+                    .await
+                    .map_err(anyhow::Error::from),
+                _ => storage_clone
+                    .increment_clicks_owned(code.to_owned(), 1)
+                    .await
+                    .map_err(anyhow::Error::from),
+            }
+        });
+        handles.push(handle);
+    }
+
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+}
+
 #[tokio::test]
 async fn test_click_increment_consistency() {
     // Test that concurrent click increments are consistent
@@ -318,25 +799,59 @@ async fn test_click_increment_consistency() {
         .await
         .unwrap();
 
-    let mut handles = vec![];
-
-    // Increment clicks concurrently
-    for _ in 0..100 {
-        let storage_clone = Arc::clone(&storage);
-        let handle = tokio::spawn(async move { storage_clone.increment_click("popular").await });
-        handles.push(handle);
-    }
-
-    // Wait for all increments
-    for handle in handles {
-        handle.await.unwrap().unwrap();
-    }
+    spawn_concurrent_click_increments(Arc::clone(&storage), "popular").await;
 
     // Verify total click count
     let url = storage.get_authoritative("popular").await.unwrap().unwrap();
     assert_eq!(url.clicks, 100, "All 100 clicks should be counted");
 }
 
+#[tokio::test]
+async fn test_cached_click_increment_consistency() {
+    let inner = create_sqlite_storage().await;
+    inner
+        .create_with_code("popular_cached", "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    // Long intervals keep the background tickers out of the way, so only the
+    // explicit flush below can move clicks into the database.
+    let cached = Arc::new(CachedStorage::new(
+        Arc::clone(&inner),
+        10,
+        3_600,
+        8,
+        3_600_000,
+    ));
+
+    spawn_concurrent_click_increments(cached.clone(), "popular_cached").await;
+
+    cached.flush().await.unwrap();
+
+    let persisted = inner
+        .get_authoritative("popular_cached")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(persisted.clicks, 100, "All 100 clicks should be persisted");
+    let merged = cached
+        .get_authoritative("popular_cached")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        merged.clicks, 100,
+        "Flushed clicks must not be double counted"
+    );
+
+    cached.shutdown().await;
+    let after_shutdown = inner
+        .get_authoritative("popular_cached")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after_shutdown.clicks, 100);
+}
+
 #[tokio::test]
 async fn test_owned_click_batch_and_zero_amount() {
     let storage = create_sqlite_storage().await;
@@ -461,7 +976,7 @@ async fn test_patch_operations_isolation() {
 
     // Patch specific URL
     storage
-        .patch_created_by("malformed1", "fixed_user")
+        .patch_created_by("malformed1", "fixed_user", None)
         .await
         .unwrap();
 
@@ -550,37 +1065,43 @@ async fn test_patch_operations_isolation() {
     );
 }
 
-#[tokio::test]
-async fn test_list_user_links_pagination() {
-    // Test pagination for user-specific link listing
-    let storage = create_sqlite_storage().await;
-
-    // Create 15 links for user1
-    for i in 0..15 {
-        storage
-            .create_with_code(
-                &format!("user1_link{}", i),
-                "https://example.com",
-                Some("user1"),
-            )
-            .await
-            .unwrap();
-    }
+async fn assert_list_user_links_pagination(storage: Arc<dyn Storage>, prefix: &str) {
+    // Test pagination for user-specific link listing: 15 links for the user,
+    // several within the same millisecond
+    let seeded = Fixture::new(storage.clone())
+        .links_per_user(15)
+        .prefix(format!("{prefix}_"))
+        .seed()
+        .await
+        .unwrap();
+    let owner = &seeded.users[0].user_id;
 
     // Get first page
-    let page1 = storage.list_user_links("user1", 5, 0).await.unwrap();
+    let page1 = storage.list_user_links(owner, 5, None).await.unwrap();
     assert_eq!(page1.len(), 5);
+    let after = |page: &[Arc<lynx::models::ShortenedUrl>]| {
+        page.last().map(|last| (last.created_at, last.id))
+    };
 
     // Get second page
-    let page2 = storage.list_user_links("user1", 5, 5).await.unwrap();
+    let page2 = storage
+        .list_user_links(owner, 5, after(&page1))
+        .await
+        .unwrap();
     assert_eq!(page2.len(), 5);
 
     // Get third page
-    let page3 = storage.list_user_links("user1", 5, 10).await.unwrap();
+    let page3 = storage
+        .list_user_links(owner, 5, after(&page2))
+        .await
+        .unwrap();
     assert_eq!(page3.len(), 5);
 
     // Get fourth page (should be empty)
-    let page4 = storage.list_user_links("user1", 5, 15).await.unwrap();
+    let page4 = storage
+        .list_user_links(owner, 5, after(&page3))
+        .await
+        .unwrap();
     assert_eq!(page4.len(), 0);
 
     // Verify pages don't overlap
@@ -598,15 +1119,335 @@ async fn test_list_user_links_pagination() {
 }
 
 #[tokio::test]
-async fn test_sqlite_delete_protection() {
+async fn test_list_user_links_pagination_sqlite() {
     if !should_test_backend("sqlite") {
         return;
     }
 
-    // Test that DELETE operations on urls table are blocked by trigger
     let storage = create_sqlite_storage().await;
+    assert_list_user_links_pagination(storage, "user1").await;
+}
 
-    // Create a test URL
+#[tokio::test]
+async fn test_list_user_links_pagination_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_pages_{}", std::process::id());
+    assert_list_user_links_pagination(storage, &prefix).await;
+}
+
+async fn assert_owner_emails_match_auth_method(storage: Arc<dyn Storage>, prefix: &str) {
+    let owner = format!("{prefix}_owner");
+    let code = format!("{prefix}_owned");
+    let created = storage
+        .create_with_code_via(
+            &code,
+            "https://example.com",
+            Some(&owner),
+            Some("oauth"),
+            lynx::models::CreatedVia::Api,
+            &LinkOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(created.created_by_auth_method.as_deref(), Some("oauth"));
+
+    storage
+        .upsert_user(&owner, Some("owner@oauth.example"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user(&owner, Some("owner@cf.example"), "cloudflare")
+        .await
+        .unwrap();
+
+    let emails = storage
+        .user_emails(&[
+            (owner.clone(), "oauth".to_string()),
+            (format!("{prefix}_nobody"), "oauth".to_string()),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        emails,
+        vec![(
+            owner.clone(),
+            "oauth".to_string(),
+            "owner@oauth.example".to_string()
+        )]
+    );
+
+    storage
+        .patch_created_by(&code, &owner, Some("cloudflare"))
+        .await
+        .unwrap();
+    let patched = storage.get(&code).await.unwrap().unwrap();
+    assert_eq!(
+        patched.created_by_auth_method.as_deref(),
+        Some("cloudflare")
+    );
+}
+
+#[tokio::test]
+async fn test_owner_emails_match_auth_method_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_owner_emails_match_auth_method(storage, "owner").await;
+}
+
+#[tokio::test]
+async fn test_owner_emails_match_auth_method_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_owner_{}", std::process::id());
+    assert_owner_emails_match_auth_method(storage, &prefix).await;
+}
+
+async fn assert_user_profile_counts(storage: Arc<dyn Storage>, prefix: &str) {
+    let owner = format!("{prefix}_profile");
+    for suffix in ["a", "b", "off"] {
+        storage
+            .create_with_code(
+                &format!("{prefix}_profile_{suffix}"),
+                "https://example.com",
+                Some(&owner),
+            )
+            .await
+            .unwrap();
+    }
+    storage
+        .increment_clicks(&format!("{prefix}_profile_a"), 5)
+        .await
+        .unwrap();
+    storage
+        .deactivate(&format!("{prefix}_profile_off"))
+        .await
+        .unwrap();
+    storage
+        .reserve_codes(
+            &[format!("{prefix}_profile_later")],
+            Some(&owner),
+            None,
+            chrono::Utc::now().timestamp() + 3600,
+        )
+        .await
+        .unwrap();
+    storage
+        .add_alias(
+            &format!("{prefix}_profile_a"),
+            &format!("{prefix}_profile_alias"),
+            Some(&owner),
+        )
+        .await
+        .unwrap();
+
+    let counts = storage.user_link_counts(&owner).await.unwrap();
+    assert_eq!(
+        counts,
+        lynx::models::UserLinkCounts {
+            active: 2,
+            inactive: 1,
+            reserved: 1,
+            aliases: 1,
+            total_clicks: 5,
+        }
+    );
+    assert_eq!(
+        storage
+            .user_link_counts(&format!("{prefix}_nobody"))
+            .await
+            .unwrap(),
+        lynx::models::UserLinkCounts::default()
+    );
+
+    storage
+        .upsert_user(&owner, Some("profile@example.com"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user(&owner, None, "cloudflare")
+        .await
+        .unwrap();
+    storage.promote_to_admin(&owner, "oauth").await.unwrap();
+    let accounts = storage.user_accounts(&owner).await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    for account in &accounts {
+        assert_eq!(account.is_manual_admin, account.auth_method == "oauth");
+    }
+}
+
+#[tokio::test]
+async fn test_user_profile_counts_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_user_profile_counts(storage, "profile").await;
+}
+
+#[tokio::test]
+async fn test_user_profile_counts_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_profile_{}", std::process::id());
+    assert_user_profile_counts(storage, &prefix).await;
+}
+
+async fn assert_link_options_round_trip(storage: Arc<dyn Storage>, prefix: &str) {
+    let code = format!("{prefix}_quiet");
+    let created = storage
+        .create_with_code(&code, "https://example.com", None)
+        .await
+        .unwrap();
+    assert_eq!(created.options, LinkOptions::default());
+
+    let hidden = LinkOptions {
+        hide_stats: Some(true),
+        interstitial: Some(true),
+        expires_at: Some(1_900_000_000_000),
+        max_clicks: Some(10),
+    };
+    assert!(storage.set_link_options(&code, &hidden).await.unwrap());
+    assert_eq!(storage.get(&code).await.unwrap().unwrap().options, hidden);
+    assert!(!storage
+        .set_link_options(&format!("{prefix}_missing"), &hidden)
+        .await
+        .unwrap());
+
+    let renamed = storage
+        .rename_code(&code, &format!("{prefix}_quieter"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(renamed.options, hidden);
+
+    // Created with options, the link has them from the first read on
+    let limited = format!("{prefix}_limited");
+    let created = storage
+        .create_with_code_via(
+            &limited,
+            "https://example.com",
+            None,
+            None,
+            lynx::models::CreatedVia::Api,
+            &hidden,
+        )
+        .await
+        .unwrap();
+    assert_eq!(created.options, hidden);
+    assert_eq!(
+        storage.get(&limited).await.unwrap().unwrap().options,
+        hidden
+    );
+
+    // Options are replaced whole, so leaving one out clears it
+    assert!(storage
+        .set_link_options(&renamed.short_code, &LinkOptions::default())
+        .await
+        .unwrap());
+    assert_eq!(
+        storage
+            .get(&renamed.short_code)
+            .await
+            .unwrap()
+            .unwrap()
+            .options,
+        LinkOptions::default()
+    );
+}
+
+#[tokio::test]
+async fn test_link_options_round_trip_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_link_options_round_trip(storage, "hide").await;
+}
+
+#[tokio::test]
+async fn test_link_options_round_trip_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_hide_{}", std::process::id());
+    assert_link_options_round_trip(storage, &prefix).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_delete_protection() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    // Test that DELETE operations on urls table are blocked by trigger
+    let storage = create_sqlite_storage().await;
+
+    // Create a test URL
     storage
         .create_with_code("test_delete", "https://example.com", Some("user1"))
         .await
@@ -649,6 +1490,7 @@ async fn test_sqlite_delete_protection() {
     assert!(url.is_some(), "URL should still exist after failed DELETE");
 }
 
+#[cfg(feature = "postgres")]
 #[tokio::test]
 async fn test_postgres_delete_protection() {
     if !should_test_backend("postgres") {
@@ -715,6 +1557,7 @@ async fn test_postgres_delete_protection() {
         .await;
 }
 
+#[cfg(feature = "postgres")]
 #[tokio::test]
 async fn test_postgres_truncate_protection() {
     if !should_test_backend("postgres") {
@@ -788,6 +1631,282 @@ async fn test_postgres_truncate_protection() {
         .await;
 }
 
+/// Hard delete takes the link's aliases, analytics and history with it and
+/// leaves other links and the audit log alone; the code is free afterwards.
+async fn assert_hard_delete_removes_the_link(storage: Arc<dyn Storage>, prefix: &str) {
+    let code = format!("{prefix}_gone");
+    let alias = format!("{prefix}_gone_alias");
+    let kept = format!("{prefix}_kept");
+    let private = format!("https://example.com/{prefix}/private");
+    storage
+        .create_with_code(&code, &format!("{private}/jane"), Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .add_alias(&code, &alias, Some("user1"))
+        .await
+        .unwrap()
+        .unwrap();
+    storage
+        .create_with_code(&kept, "https://example.com/kept", Some("user1"))
+        .await
+        .unwrap();
+    storage
+        .update_url(&code, &format!("{private}/john"), Some("user1"))
+        .await
+        .unwrap()
+        .unwrap();
+    storage.increment_clicks(&code, 2).await.unwrap();
+    storage.increment_clicks(&kept, 1).await.unwrap();
+    storage
+        .upsert_analytics_batch(vec![
+            AnalyticsRollup {
+                alias_used: Some(alias.clone()),
+                estimated_visits: 1,
+                ..null_dimension_visits(&code, 2)
+            },
+            null_dimension_visits(&kept, 1),
+        ])
+        .await
+        .unwrap();
+    storage
+        .record_audit_entry("update", &code, Some("admin"), "user1")
+        .await
+        .unwrap();
+
+    let params = SearchParams {
+        q: format!("{prefix}/private"),
+        created_by: None,
+        created_from: None,
+        created_to: None,
+        is_active: None,
+        created_via: None,
+        unused_since: None,
+        campaign_id: None,
+        limit: 10,
+        cursor: None,
+        sort: SearchSort::CreatedAt,
+        offset: None,
+    };
+    let found = storage.search(&params, true, None).await.unwrap();
+    assert!(!found.items.is_empty());
+
+    let report = storage
+        .hard_delete(&code, Some("operator"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(report.codes, vec![code.clone(), alias.clone()]);
+    assert_eq!(report.analytics_rows, 3, "{report:?}");
+    assert_eq!(report.click_history_rows, 1);
+    assert_eq!(report.url_history_rows, 1);
+
+    for gone in [&code, &alias] {
+        assert!(storage.get_authoritative(gone).await.unwrap().is_none());
+    }
+    assert!(analytics_rows(&storage, &code).await.is_empty());
+    assert!(storage.get_url_history(&code).await.unwrap().is_empty());
+    let audit = storage.get_audit_log(&code).await.unwrap();
+    let actions: Vec<&str> = audit.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(actions.len(), 2, "{actions:?}");
+    assert!(actions.contains(&"update"), "{actions:?}");
+    let deleted = audit
+        .iter()
+        .find(|entry| entry.action == "hard_delete")
+        .unwrap();
+    assert_eq!(deleted.actor.as_deref(), Some("operator"));
+    assert_eq!(deleted.effective_user, "user1");
+    assert_eq!(analytics_rows(&storage, &kept).await, vec![1]);
+    assert_eq!(
+        storage
+            .get_authoritative(&kept)
+            .await
+            .unwrap()
+            .unwrap()
+            .clicks,
+        1
+    );
+    let found = storage.search(&params, true, None).await.unwrap();
+    assert!(found.items.is_empty(), "{:?}", found.items);
+
+    assert!(storage.hard_delete(&code, None).await.unwrap().is_none());
+    storage
+        .create_with_code(&code, "https://example.com/again", None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_hard_delete_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let disabled = create_sqlite_storage().await;
+    disabled
+        .create_with_code("kept", "https://example.com", None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        disabled.hard_delete("kept", None).await,
+        Err(StorageError::PermissionDenied)
+    ));
+    assert!(disabled.get_authoritative("kept").await.unwrap().is_some());
+
+    let storage = SqliteStorage::new("sqlite::memory:", 5)
+        .await
+        .unwrap()
+        .with_hard_delete(true);
+    storage.init().await.unwrap();
+    let storage = Arc::new(storage);
+    assert_hard_delete_removes_the_link(Arc::clone(&storage) as Arc<dyn Storage>, "hd").await;
+
+    // The protection is back for every other delete
+    let result = sqlx::query("DELETE FROM urls WHERE short_code = ?")
+        .bind("hd_kept")
+        .execute(storage.pool.as_ref())
+        .await;
+    assert!(result.is_err(), "DELETE should be blocked by trigger");
+    storage.verify_schema().await.unwrap();
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_hard_delete_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let db_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_hd_{}", std::process::id());
+    let disabled = PostgresStorage::new(&db_url, 5).await.unwrap();
+    disabled.init().await.unwrap();
+    assert!(matches!(
+        disabled
+            .hard_delete(&format!("{prefix}_missing"), None)
+            .await,
+        Err(StorageError::PermissionDenied)
+    ));
+
+    let storage = Arc::new(
+        PostgresStorage::new(&db_url, 5)
+            .await
+            .unwrap()
+            .with_hard_delete(true),
+    );
+    assert_hard_delete_removes_the_link(Arc::clone(&storage) as Arc<dyn Storage>, &prefix).await;
+
+    // The trigger is enabled again for every other delete
+    let result = sqlx::query("DELETE FROM urls WHERE short_code = $1")
+        .bind(format!("{prefix}_kept"))
+        .execute(storage.pool.as_ref())
+        .await;
+    let err_msg = result.unwrap_err().to_string();
+    assert!(
+        err_msg.contains("DELETE operations are not allowed"),
+        "Error should mention DELETE not allowed, got: {}",
+        err_msg
+    );
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_schema_isolation() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let db_url = match std::env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    // The default schema is initialized first, so the triggers below can only
+    // exist if init looked for them on the other schema's own urls table.
+    let public = PostgresStorage::new(&db_url, 5).await.unwrap();
+    public.init().await.unwrap();
+
+    let schema = "lynx_schema_isolation_test";
+    let isolated = PostgresStorage::new_in_schema(&db_url, 5, Default::default(), Some(schema))
+        .await
+        .unwrap();
+    isolated.init().await.unwrap();
+    isolated
+        .init()
+        .await
+        .expect("init is idempotent in a schema");
+
+    let (current,): (String,) = sqlx::query_as("SELECT current_schema()::TEXT")
+        .fetch_one(isolated.pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(current, schema);
+
+    let code = format!(
+        "pg_schema_{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    isolated
+        .create_with_code(&code, "https://example.com", Some("user1"))
+        .await
+        .unwrap();
+    assert!(isolated.get_authoritative(&code).await.unwrap().is_some());
+    assert!(
+        public.get_authoritative(&code).await.unwrap().is_none(),
+        "links created in {schema} must not be visible in the default schema"
+    );
+
+    let (functions,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+         WHERE n.nspname = $1 AND p.proname IN ('prevent_urls_delete', 'prevent_urls_truncate')",
+    )
+    .bind(schema)
+    .fetch_one(isolated.pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(functions, 2, "trigger functions live in {schema}");
+
+    let result = sqlx::query("DELETE FROM urls WHERE short_code = $1")
+        .bind(&code)
+        .execute(isolated.pool.as_ref())
+        .await;
+    assert!(result.is_err(), "DELETE should be blocked in {schema}");
+    let result = sqlx::query("TRUNCATE urls")
+        .execute(isolated.pool.as_ref())
+        .await;
+    assert!(result.is_err(), "TRUNCATE should be blocked in {schema}");
+
+    assert!(
+        PostgresStorage::new_in_schema(&db_url, 5, Default::default(), Some("bad; schema"))
+            .await
+            .is_err()
+    );
+}
+
+#[cfg(feature = "postgres")]
 #[tokio::test]
 async fn test_postgres_concurrent_init() {
     if !should_test_backend("postgres") {
@@ -845,7 +1964,7 @@ async fn test_postgres_concurrent_init() {
 
     // Check DELETE trigger exists
     let delete_trigger: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM pg_trigger WHERE tgname = 'prevent_urls_delete_trigger'",
+        "SELECT COUNT(*) FROM pg_trigger WHERE tgname = 'prevent_urls_delete_trigger' AND tgrelid = 'urls'::regclass",
     )
     .fetch_one(storage.pool.as_ref())
     .await
@@ -858,7 +1977,7 @@ async fn test_postgres_concurrent_init() {
 
     // Check TRUNCATE trigger exists
     let truncate_trigger: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM pg_trigger WHERE tgname = 'prevent_urls_truncate_trigger'",
+        "SELECT COUNT(*) FROM pg_trigger WHERE tgname = 'prevent_urls_truncate_trigger' AND tgrelid = 'urls'::regclass",
     )
     .fetch_one(storage.pool.as_ref())
     .await
@@ -1018,3 +2137,325 @@ async fn test_url_update_history_and_restore_postgres() {
 
     assert!(storage.restore_url(&code, 999_999_999, None).await.is_err());
 }
+
+/// SQLite reads use their own WAL-backed pool, so a large analytics flush
+/// holding the single write connection must not stall lookups behind it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sqlite_reads_stay_fast_during_large_analytics_flush() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "lynx-read-write-split-{}-{nanos}.db",
+        std::process::id()
+    ));
+    let storage = Arc::new(
+        SqliteStorage::new(&format!("sqlite://{}", path.display()), 4)
+            .await
+            .unwrap(),
+    );
+    storage.init().await.unwrap();
+    storage
+        .create_with_code("hot", "https://example.com/hot", None)
+        .await
+        .unwrap();
+
+    let rollups: Vec<AnalyticsRollup> = (0..20_000)
+        .map(|i| AnalyticsRollup {
+            short_code: "hot".to_string(),
+            time_bucket: 1_698_768_000 + i64::from(i) * 3600,
+            country_code: Some("US".to_string()),
+            region: None,
+            city: None,
+            asn: Some(i64::from(i)),
+            ip_version: IpVersion::V4,
+            visit_count: 1,
+            estimated_visits: 0,
+            alias_used: None,
+        })
+        .collect();
+
+    let flush = {
+        let storage = Arc::clone(&storage);
+        tokio::spawn(async move { storage.upsert_analytics_batch(rollups).await })
+    };
+
+    let mut reads = 0;
+    let mut slowest = Duration::ZERO;
+    loop {
+        let started = Instant::now();
+        let url = storage.get_authoritative("hot").await.unwrap();
+        slowest = slowest.max(started.elapsed());
+        assert!(url.is_some());
+        reads += 1;
+        if flush.is_finished() {
+            break;
+        }
+    }
+    flush.await.unwrap().unwrap();
+
+    assert!(
+        slowest < Duration::from_millis(250),
+        "slowest read took {slowest:?} during the flush ({reads} reads)"
+    );
+
+    drop(storage);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+fn null_dimension_visits(short_code: &str, visit_count: i64) -> AnalyticsRollup {
+    AnalyticsRollup {
+        short_code: short_code.to_string(),
+        time_bucket: 1_698_768_000,
+        country_code: Some("US".to_string()),
+        region: None,
+        city: None,
+        asn: None,
+        ip_version: IpVersion::V4,
+        visit_count,
+        estimated_visits: 0,
+        alias_used: None,
+    }
+}
+
+/// Visit counts for `short_code`, one per analytics row.
+async fn analytics_rows(storage: &Arc<dyn Storage>, short_code: &str) -> Vec<i64> {
+    storage
+        .get_analytics(short_code, None, None, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|entry| entry.visit_count)
+        .collect()
+}
+
+async fn assert_null_dimensions_share_a_row(storage: Arc<dyn Storage>, prefix: &str) {
+    let code = format!("{prefix}_nulls");
+    storage
+        .create_with_code(&code, "https://example.com/nulls", None)
+        .await
+        .unwrap();
+
+    for visit_count in [2, 3] {
+        storage
+            .upsert_analytics_batch(vec![null_dimension_visits(&code, visit_count)])
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        storage
+            .upsert_known_analytics_batch(vec![null_dimension_visits(&code, 4)])
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(analytics_rows(&storage, &code).await, [9]);
+
+    // A set dimension is still a row of its own
+    storage
+        .upsert_analytics_batch(vec![AnalyticsRollup {
+            asn: Some(64_500),
+            ..null_dimension_visits(&code, 1)
+        }])
+        .await
+        .unwrap();
+    let mut rows = analytics_rows(&storage, &code).await;
+    rows.sort();
+    assert_eq!(rows, [1, 9]);
+}
+
+#[tokio::test]
+async fn test_null_dimensions_share_a_row_sqlite() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+
+    let storage = create_sqlite_storage().await;
+    assert_null_dimensions_share_a_row(storage, "nulls").await;
+}
+
+#[tokio::test]
+async fn test_null_dimensions_share_a_row_postgres() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+
+    let lock = POSTGRES_TABLE_LOCK
+        .get_or_init(|| async { Arc::new(tokio::sync::Mutex::new(())) })
+        .await;
+    let _guard = lock.lock().await;
+
+    let storage = match create_postgres_storage().await {
+        Some(storage) => storage,
+        None => {
+            println!("SKIPPED: DATABASE_URL not set");
+            return;
+        }
+    };
+
+    let prefix = format!("pg_nulls_{}", std::process::id());
+    assert_null_dimensions_share_a_row(storage, &prefix).await;
+}
+
+/// Rows as a database from before `idx_analytics_key` holds them: the same
+/// NULL-dimension key three times, and one row with every dimension set.
+const DUPLICATE_ANALYTICS_ROWS: &str = "INSERT INTO {table} (short_code, time_bucket, country_code, region, city, asn, ip_version, visit_count, created_at, updated_at) VALUES \
+    ('old', 1698768000, 'US', NULL, NULL, NULL, 4, 1, 10, 10), \
+    ('old', 1698768000, 'US', NULL, NULL, NULL, 4, 2, 20, 20), \
+    ('old', 1698768000, 'US', NULL, NULL, NULL, 4, 3, 30, 30), \
+    ('old', 1698768000, 'US', 'CA', 'Oakland', 64500, 4, 5, 10, 10)";
+
+async fn assert_duplicates_merged(storage: Arc<dyn Storage>) {
+    let mut rows = analytics_rows(&storage, "old").await;
+    rows.sort();
+    assert_eq!(rows, [5, 6]);
+
+    // Later upserts of the key land on the merged row
+    storage
+        .upsert_analytics_batch(vec![null_dimension_visits("old", 1)])
+        .await
+        .unwrap();
+    let mut rows = analytics_rows(&storage, "old").await;
+    rows.sort();
+    assert_eq!(rows, [5, 7]);
+}
+
+#[tokio::test]
+async fn test_sqlite_duplicate_analytics_rows_are_merged() {
+    if !should_test_backend("sqlite") {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("lynx-analytics-key-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("lynx.db").display());
+
+    let storage = SqliteStorage::new(&url, 1).await.unwrap();
+    storage.ensure_schema().await.unwrap();
+    storage
+        .create_with_code("old", "https://example.com/old", None)
+        .await
+        .unwrap();
+    // Undo the migration and write the duplicates it would have prevented
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    for statement in [
+        "DROP INDEX idx_analytics_key",
+        "DELETE FROM schema_migrations WHERE version = 1",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+    sqlx::query(&DUPLICATE_ANALYTICS_ROWS.replace("{table}", "analytics"))
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    storage.ensure_schema().await.unwrap();
+    storage.verify_schema().await.unwrap();
+    assert_duplicates_merged(Arc::new(storage)).await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_duplicate_analytics_rows_are_merged() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let schema = "lynx_analytics_key_test";
+    let admin = sqlx::PgPool::connect(&db_url).await.unwrap();
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {schema} CASCADE"))
+        .execute(&admin)
+        .await
+        .unwrap();
+    let storage = PostgresStorage::new_in_schema(&db_url, 2, Default::default(), Some(schema))
+        .await
+        .unwrap();
+    storage.ensure_schema().await.unwrap();
+    storage
+        .create_with_code("old", "https://example.com/old", None)
+        .await
+        .unwrap();
+    // Back to the old table constraint, under which the duplicates were
+    // written
+    for statement in [
+        format!("DROP INDEX {schema}.idx_analytics_key"),
+        format!("DELETE FROM {schema}.schema_migrations WHERE version = 1"),
+        format!("ALTER TABLE {schema}.analytics ADD CONSTRAINT analytics_old_key UNIQUE (short_code, time_bucket, country_code, region, city, asn, ip_version)"),
+        DUPLICATE_ANALYTICS_ROWS.replace("{table}", &format!("{schema}.analytics")),
+    ] {
+        sqlx::query(&statement).execute(&admin).await.unwrap();
+    }
+
+    storage.ensure_schema().await.unwrap();
+    let constraints: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM pg_constraint WHERE conrelid = '{schema}.analytics'::regclass AND contype = 'u'"
+    ))
+    .fetch_one(&admin)
+    .await
+    .unwrap();
+    assert_eq!(constraints, 0);
+    storage.verify_schema().await.unwrap();
+    assert_duplicates_merged(Arc::new(storage)).await;
+
+    sqlx::query(&format!("DROP SCHEMA {schema} CASCADE"))
+        .execute(&admin)
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "postgres")]
+#[tokio::test]
+async fn test_postgres_created_at_migrates_to_milliseconds_once() {
+    if !should_test_backend("postgres") {
+        return;
+    }
+    let Ok(db_url) = std::env::var("DATABASE_URL") else {
+        println!("SKIPPED: DATABASE_URL not set");
+        return;
+    };
+    let schema = "lynx_created_at_millis_test";
+    let admin = sqlx::PgPool::connect(&db_url).await.unwrap();
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {schema} CASCADE"))
+        .execute(&admin)
+        .await
+        .unwrap();
+    let storage = PostgresStorage::new_in_schema(&db_url, 2, Default::default(), Some(schema))
+        .await
+        .unwrap();
+    storage.ensure_schema().await.unwrap();
+    storage
+        .create_with_code("early", "https://example.com", None)
+        .await
+        .unwrap();
+    // Back to the schema from before millisecond timestamps
+    for statement in [
+        format!("ALTER TABLE {schema}.urls DROP COLUMN updated_at"),
+        format!("UPDATE {schema}.urls SET created_at = 1700000000"),
+        format!("DELETE FROM {schema}.schema_migrations WHERE version = 2"),
+    ] {
+        sqlx::query(&statement).execute(&admin).await.unwrap();
+    }
+
+    storage.ensure_schema().await.unwrap();
+    storage.ensure_schema().await.unwrap();
+    storage.verify_schema().await.unwrap();
+    let early = storage.get("early").await.unwrap().unwrap();
+    assert_eq!(early.created_at, 1_700_000_000_000);
+    assert_eq!(early.updated_at, early.created_at);
+
+    sqlx::query(&format!("DROP SCHEMA {schema} CASCADE"))
+        .execute(&admin)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,191 @@
+//! Integration tests for rejecting tokens without a usable subject
+//!
+//! A token that passes signature and time checks but has no `sub` would create
+//! links with a NULL `created_by`. The API answers such tokens with 401 unless
+//! `AUTH_ALLOW_ANONYMOUS_SUBJECT` is set.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use base64::prelude::*;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{Config, OAuthConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tower::ServiceExt;
+
+mod common;
+
+const SECRET: &[u8] = b"subject-claim-test-secret";
+const KID: &str = "test-key";
+const ISSUER: &str = "https://issuer.example.com";
+const AUDIENCE: &str = "lynx";
+
+/// Serve a JWKS document holding the HMAC test key; returns its URL.
+async fn spawn_jwks_server() -> String {
+    let jwks = json!({
+        "keys": [{ "kid": KID, "kty": "oct", "alg": "HS256", "k": BASE64_STANDARD.encode(SECRET) }]
+    });
+    let app = Router::new().route("/jwks", get(move || async move { Json(jwks) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/jwks", addr)
+}
+
+fn oauth_config(jwks_url: String, allow_anonymous_subject: bool) -> OAuthConfig {
+    OAuthConfig {
+        issuer_url: ISSUER.to_string(),
+        audience: AUDIENCE.to_string(),
+        client_id: AUDIENCE.to_string(),
+        scopes: "openid".to_string(),
+        redirect_uri: "http://localhost:8080/auth/callback".to_string(),
+        jwks_url: Some(jwks_url),
+        jwks_cache_ttl_secs: 300,
+        clock_skew_secs: 30,
+        allow_anonymous_subject,
+    }
+}
+
+/// Helper to create test config
+fn create_test_config(oauth: OAuthConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        auth: AuthConfig {
+            mode: AuthMode::Oauth,
+            oauth: Some(oauth),
+            cloudflare: None,
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_api(allow_anonymous_subject: bool) -> (Router, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let config = create_test_config(oauth_config(
+        spawn_jwks_server().await,
+        allow_anonymous_subject,
+    ));
+    let auth_service = Arc::new(AuthService::new(config.auth.clone()).await.unwrap());
+    let app = api::create_api_router(
+        Arc::clone(&storage) as Arc<dyn Storage>,
+        auth_service,
+        config,
+        None,
+    );
+    (app, storage)
+}
+
+/// A correctly signed, unexpired token carrying `claims` plus the standard ones.
+fn token(claims: Value) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut body = json!({ "iss": ISSUER, "aud": AUDIENCE, "iat": now, "exp": now + 300 });
+    body.as_object_mut()
+        .unwrap()
+        .extend(claims.as_object().unwrap().clone());
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(KID.to_string());
+    encode(&header, &body, &EncodingKey::from_secret(SECRET)).unwrap()
+}
+
+async fn create_link(app: &Router, token: &str, code: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/urls")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    json!({ "url": "https://example.com", "custom_code": code }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn quick_link(app: &Router, token: &str) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/quick?url=https%3A%2F%2Fexample.com%2Fquick")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::ACCEPT, "application/json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn ownerless_links(storage: &SqliteStorage) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM urls WHERE created_by IS NULL")
+        .fetch_one(storage.pool.as_ref())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_tokens_without_subject_cannot_create_links() {
+    let (app, storage) = create_test_api(false).await;
+
+    for (claims, code) in [
+        (json!({ "email": "nobody@example.com" }), "no-sub"),
+        (json!({ "sub": "" }), "empty-sub"),
+        (json!({ "sub": 42 }), "numeric-sub"),
+    ] {
+        let token = token(claims);
+        assert_eq!(
+            create_link(&app, &token, code).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(quick_link(&app, &token).await, StatusCode::UNAUTHORIZED);
+        assert!(storage.get_authoritative(code).await.unwrap().is_none());
+    }
+
+    // A token naming a user still works and attributes the link to them.
+    assert_eq!(
+        create_link(&app, &token(json!({ "sub": "alice" })), "with-sub").await,
+        StatusCode::CREATED
+    );
+    let link = storage
+        .get_authoritative("with-sub")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(link.created_by.as_deref(), Some("alice"));
+
+    assert_eq!(
+        ownerless_links(&storage).await,
+        0,
+        "the API must not create links without an owner"
+    );
+}
+
+#[tokio::test]
+async fn test_anonymous_subjects_are_accepted_only_when_allowed() {
+    let (app, _storage) = create_test_api(true).await;
+
+    assert_eq!(
+        create_link(&app, &token(json!({})), "anonymous").await,
+        StatusCode::CREATED
+    );
+}
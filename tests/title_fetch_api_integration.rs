@@ -0,0 +1,159 @@
+//! Integration tests for fetching page titles of new links
+//!
+//! Links created through the API get the `<title>` of their destination in
+//! the background when `TITLE_FETCH_ENABLED` is set. A local page server
+//! stands in for the destination; because it listens on loopback, fetching
+//! from it also needs `TITLE_FETCH_ALLOW_PRIVATE_ADDRESSES`.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use lynx::api;
+use lynx::auth::AuthService;
+use lynx::config::{AuthConfig, AuthMode, Config, TitleFetchConfig};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+mod common;
+
+/// Helper to create test config with the given title fetch settings
+fn create_test_config(title_fetch: TitleFetchConfig) -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        title_fetch,
+        ..common::test_config()
+    })
+}
+
+async fn create_test_app(title_fetch: TitleFetchConfig) -> Router {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    api::routes::create_api_router(
+        Arc::new(storage),
+        auth_service,
+        create_test_config(title_fetch),
+        None,
+    )
+}
+
+/// Serve a page titled "Quarterly report" on loopback, counting requests.
+async fn start_page_server() -> (String, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&hits);
+    let app = Router::new().route(
+        "/report",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                (
+                    [(header::CONTENT_TYPE, "text/html")],
+                    "<html><head><title>Quarterly report</title></head></html>",
+                )
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/report"), hits)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// The stored title of `code`, waiting up to two seconds for it to appear.
+async fn wait_for_title(app: &Router, code: &str) -> Value {
+    let uri = format!("/api/urls/{}", URL_SAFE_NO_PAD.encode(code));
+    for _ in 0..40 {
+        let (status, body) = send(app, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        if !body["title"].is_null() {
+            return body["title"].clone();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Value::Null
+}
+
+#[tokio::test]
+async fn test_created_link_gets_its_page_title() {
+    let (page, hits) = start_page_server().await;
+    let app = create_test_app(TitleFetchConfig {
+        enabled: true,
+        allow_private_addresses: true,
+    })
+    .await;
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/urls",
+        Some(json!({ "url": page, "custom_code": "report" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    // The response does not wait for the fetch.
+    assert!(body["title"].is_null());
+
+    assert_eq!(wait_for_title(&app, "report").await, "Quarterly report");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    let (_, listing) = send(&app, "GET", "/api/urls", None).await;
+    assert_eq!(listing["urls"][0]["title"], "Quarterly report");
+}
+
+#[tokio::test]
+async fn test_private_destinations_and_disabled_fetching_leave_no_title() {
+    let (page, hits) = start_page_server().await;
+
+    for title_fetch in [
+        TitleFetchConfig::default(),
+        TitleFetchConfig {
+            enabled: true,
+            allow_private_addresses: false,
+        },
+    ] {
+        let app = create_test_app(title_fetch).await;
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": page, "custom_code": "report" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(wait_for_title(&app, "report").await.is_null());
+    }
+
+    // The loopback page was never requested.
+    assert_eq!(hits.load(Ordering::SeqCst), 0);
+}
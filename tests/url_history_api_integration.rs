@@ -7,6 +7,7 @@
 //! Tests run with `AUTH_MODE=none`, so the caller is always treated as an
 //! admin. Fine-grained owner/admin authorization is covered by unit tests in
 //! `src/api/handlers.rs`.
+#![cfg(feature = "sqlite")]
 
 use axum::{
     body::{to_bytes, Body},
@@ -22,6 +23,8 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 use tower::ServiceExt;
 
+mod common;
+
 async fn create_test_storage() -> Arc<dyn Storage> {
     let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
     storage.init().await.unwrap();
@@ -30,36 +33,6 @@ async fn create_test_storage() -> Arc<dyn Storage> {
 
 fn create_test_config() -> Arc<Config> {
     Arc::new(Config {
-        database: DatabaseConfig {
-            backend: DatabaseBackend::Sqlite,
-            url: "sqlite::memory:".to_string(),
-            max_connections: 5,
-        },
-        api_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 8080,
-        },
-        redirect_server: ServerConfig {
-            host: "127.0.0.1".to_string(),
-            port: 3000,
-        },
-        redirect_base_url: "http://localhost:3000".to_string(),
-        auth: AuthConfig {
-            mode: AuthMode::None,
-            oauth: None,
-            cloudflare: None,
-        },
-        frontend: FrontendConfig { static_dir: None },
-        cache: CacheConfig {
-            max_entries: 10000,
-            flush_interval_secs: 5,
-            actor_buffer_size: 100000,
-            actor_flush_interval_ms: 100,
-        },
-        pagination: PaginationConfig {
-            cursor_hmac_secret: None,
-        },
-        short_code_max_length: 50,
         analytics: AnalyticsConfig {
             enabled: false,
             geoip_city_db_path: None,
@@ -69,8 +42,10 @@ fn create_test_config() -> Arc<Config> {
             trusted_proxies: vec![],
             num_trusted_proxies: None,
             flush_interval_secs: 30,
+            daily_rollup_after_days: 30,
+            sampling: Default::default(),
         },
-        redirect_status: RedirectMode::default(),
+        ..common::test_config()
     })
 }
 
@@ -134,7 +109,7 @@ async fn create_url(app: &Router, code: &str, url: &str) {
 #[tokio::test]
 async fn test_update_records_history_and_changes_destination() {
     let app = build_app().await;
-    create_url(&app, "hist", "https://v1.example.com").await;
+    create_url(&app, "hist", "https://v1.example.com/").await;
 
     let encoded = encode_short_code("hist");
 
@@ -148,32 +123,32 @@ async fn test_update_records_history_and_changes_destination() {
         &app,
         "PATCH",
         &format!("/api/urls/{encoded}"),
-        Some(json!({ "url": "https://v2.example.com" })),
+        Some(json!({ "url": "https://v2.example.com/" })),
     )
     .await;
     assert_eq!(status, StatusCode::OK);
-    assert_eq!(body["original_url"], "https://v2.example.com");
+    assert_eq!(body["original_url"], "https://v2.example.com/");
 
     // The read path now serves the new destination.
     let (status, body) = send(&app, "GET", &format!("/api/urls/{encoded}"), None).await;
     assert_eq!(status, StatusCode::OK);
-    assert_eq!(body["original_url"], "https://v2.example.com");
+    assert_eq!(body["original_url"], "https://v2.example.com/");
 
     // History captured the previous destination.
     let (status, body) = send(&app, "GET", &format!("/api/urls/{encoded}/history"), None).await;
     assert_eq!(status, StatusCode::OK);
     let entries = body.as_array().unwrap();
     assert_eq!(entries.len(), 1);
-    assert_eq!(entries[0]["historic_url"], "https://v1.example.com");
+    assert_eq!(entries[0]["historic_url"], "https://v1.example.com/");
 }
 
 #[tokio::test]
 async fn test_history_is_ordered_newest_first() {
     let app = build_app().await;
-    create_url(&app, "multi", "https://v1.example.com").await;
+    create_url(&app, "multi", "https://v1.example.com/").await;
     let encoded = encode_short_code("multi");
 
-    for next in ["https://v2.example.com", "https://v3.example.com"] {
+    for next in ["https://v2.example.com/", "https://v3.example.com/"] {
         let (status, _) = send(
             &app,
             "PATCH",
@@ -188,17 +163,17 @@ async fn test_history_is_ordered_newest_first() {
     assert_eq!(status, StatusCode::OK);
     let entries = body.as_array().unwrap();
     assert_eq!(entries.len(), 2);
-    assert_eq!(entries[0]["historic_url"], "https://v2.example.com");
-    assert_eq!(entries[1]["historic_url"], "https://v1.example.com");
+    assert_eq!(entries[0]["historic_url"], "https://v2.example.com/");
+    assert_eq!(entries[1]["historic_url"], "https://v1.example.com/");
 }
 
 #[tokio::test]
 async fn test_restore_reverts_destination() {
     let app = build_app().await;
-    create_url(&app, "restore", "https://v1.example.com").await;
+    create_url(&app, "restore", "https://v1.example.com/").await;
     let encoded = encode_short_code("restore");
 
-    for next in ["https://v2.example.com", "https://v3.example.com"] {
+    for next in ["https://v2.example.com/", "https://v3.example.com/"] {
         send(
             &app,
             "PATCH",
@@ -214,7 +189,7 @@ async fn test_restore_reverts_destination() {
     let original_id = entries.last().unwrap()["id"].as_i64().unwrap();
     assert_eq!(
         entries.last().unwrap()["historic_url"],
-        "https://v1.example.com"
+        "https://v1.example.com/"
     );
 
     // Restore to the original destination.
@@ -226,13 +201,13 @@ async fn test_restore_reverts_destination() {
     )
     .await;
     assert_eq!(status, StatusCode::OK);
-    assert_eq!(body["original_url"], "https://v1.example.com");
+    assert_eq!(body["original_url"], "https://v1.example.com/");
 
     // The currently-active destination (v3) was preserved in history.
     let (_, body) = send(&app, "GET", &format!("/api/urls/{encoded}/history"), None).await;
     let entries = body.as_array().unwrap();
     assert_eq!(entries.len(), 3);
-    assert_eq!(entries[0]["historic_url"], "https://v3.example.com");
+    assert_eq!(entries[0]["historic_url"], "https://v3.example.com/");
 }
 
 #[tokio::test]
@@ -252,7 +227,7 @@ async fn test_update_missing_code_returns_404() {
 #[tokio::test]
 async fn test_update_empty_url_returns_400() {
     let app = build_app().await;
-    create_url(&app, "empty", "https://v1.example.com").await;
+    create_url(&app, "empty", "https://v1.example.com/").await;
     let encoded = encode_short_code("empty");
     let (status, _) = send(
         &app,
@@ -267,7 +242,7 @@ async fn test_update_empty_url_returns_400() {
 #[tokio::test]
 async fn test_restore_unknown_history_returns_404() {
     let app = build_app().await;
-    create_url(&app, "badrestore", "https://v1.example.com").await;
+    create_url(&app, "badrestore", "https://v1.example.com/").await;
     let encoded = encode_short_code("badrestore");
     let (status, _) = send(
         &app,
@@ -278,3 +253,150 @@ async fn test_restore_unknown_history_returns_404() {
     .await;
     assert_eq!(status, StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_create_rejects_crlf_header_injection_with_422() {
+    let app = build_app().await;
+    for url in [
+        "https://example.com/\r\nSet-Cookie: session=attacker",
+        "https://example.com/\nLocation: https://evil.example",
+        "https://example.com/\r",
+    ] {
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": url, "custom_code": "inject" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{url:?}");
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("control characters"),
+            "{body}"
+        );
+    }
+
+    // Nothing was stored under the code.
+    let encoded = encode_short_code("inject");
+    let (status, _) = send(&app, "GET", &format!("/api/urls/{encoded}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_update_rejects_crlf_header_injection_and_keeps_destination() {
+    let app = build_app().await;
+    create_url(&app, "safe", "https://v1.example.com/").await;
+    let encoded = encode_short_code("safe");
+
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        &format!("/api/urls/{encoded}"),
+        Some(json!({ "url": "https://v2.example.com/\r\nX-Injected: 1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, body) = send(&app, "GET", &format!("/api/urls/{encoded}"), None).await;
+    assert_eq!(body["original_url"], "https://v1.example.com/");
+    let (_, history) = send(&app, "GET", &format!("/api/urls/{encoded}/history"), None).await;
+    assert_eq!(history.as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_create_reports_each_destination_violation() {
+    let app = build_app().await;
+    let too_long = format!("https://example.com/{}", "a".repeat(2048));
+    for (url, expected) in [
+        ("javascript:alert(1)", "scheme 'javascript' is not allowed"),
+        ("data:text/html,hello", "scheme 'data' is not allowed"),
+        ("mailto:team@example.com", "scheme 'mailto' is not allowed"),
+        ("https://example.com/a b", "whitespace"),
+        ("example.com/no-scheme", "not valid"),
+        (too_long.as_str(), "maximum length of 2048"),
+    ] {
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": url, "custom_code": "violation" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{url}");
+        assert!(
+            body["error"].as_str().unwrap().contains(expected),
+            "{url}: {body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_destinations_on_own_redirect_domain_need_the_allow_flag() {
+    let build = |allow_self_redirects: bool| async move {
+        let mut config = (*create_test_config()).clone();
+        config.destination = DestinationConfig {
+            self_hosts: vec!["localhost".to_string(), "go.example".to_string()],
+            allow_self_redirects,
+            ..DestinationConfig::default()
+        };
+        api::routes::create_api_router(
+            create_test_storage().await,
+            create_test_auth_service().await,
+            Arc::new(config),
+            None,
+        )
+    };
+
+    let app = build(false).await;
+    for url in ["http://localhost:3000/other", "https://GO.example/promo"] {
+        let (status, body) = send(
+            &app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": url, "custom_code": "loop" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{url}");
+        assert!(
+            body["error"].as_str().unwrap().contains("redirect loop"),
+            "{url}: {body}"
+        );
+    }
+    create_url(&app, "elsewhere", "https://example.com/").await;
+    let (status, _) = send(
+        &app,
+        "PATCH",
+        &format!("/api/urls/{}", encode_short_code("elsewhere")),
+        Some(json!({ "url": "https://go.example/elsewhere" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let app = build(true).await;
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/urls",
+        Some(json!({ "url": "https://go.example/promo", "custom_code": "chained" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["original_url"], "https://go.example/promo");
+}
+
+#[tokio::test]
+async fn test_create_stores_normalized_destination() {
+    let app = build_app().await;
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/api/urls",
+        Some(json!({ "url": "  HTTPS://Example.COM/Path?q=1  ", "custom_code": "normal" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["original_url"], "https://example.com/Path?q=1");
+}
@@ -0,0 +1,260 @@
+//! Integration tests for the admin user profile and list, and `GET /api/me`
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension,
+};
+use lynx::api::{
+    code_rng::CodeRng,
+    handlers::AppState,
+    profile::{get_my_profile, get_user_profile, list_users, UserListQuery},
+    public_url::PublicBaseUrl,
+    quick::QuickRateLimiter,
+    server_info::{RuntimeFacts, ServerInfo},
+};
+use lynx::auth::AuthClaims;
+use lynx::clock::system_clock;
+use lynx::config::Config;
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+mod common;
+
+fn create_test_config() -> Arc<Config> {
+    use lynx::config::*;
+
+    Arc::new(Config {
+        link_quota: LinkQuotaConfig {
+            max_links_per_user: Some(20),
+        },
+        ..common::test_config()
+    })
+}
+
+async fn create_test_state() -> (Arc<AppState>, Arc<SqliteStorage>) {
+    let storage = Arc::new(SqliteStorage::new("sqlite::memory:", 5).await.unwrap());
+    storage.init().await.unwrap();
+    let config = create_test_config();
+    let state = Arc::new(AppState {
+        storage: Arc::clone(&storage) as Arc<dyn Storage>,
+        quick_limiter: QuickRateLimiter::new(config.quick_link.rate_limit_per_minute),
+        anonymous_limiter: QuickRateLimiter::new(config.anonymous_create.rate_limit_per_minute),
+        server_info: Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        creation_challenge: None,
+        code_rng: CodeRng::from_entropy(),
+        clock: system_clock(),
+        analytics: None,
+        usage: None,
+        config,
+        redirect_stats: None,
+        live_visits: None,
+        title_fetcher: None,
+    });
+    (state, storage)
+}
+
+fn claims(value: Value) -> Extension<Option<AuthClaims>> {
+    Extension(Some(AuthClaims(Arc::new(value))))
+}
+
+fn base(state: &AppState) -> PublicBaseUrl {
+    PublicBaseUrl(state.config.redirect_base_url.clone())
+}
+
+/// Give alice two active links, one deactivated, one pending reservation and
+/// one alias, signed in with two auth methods of which one is an admin.
+async fn seed_alice(storage: &SqliteStorage) {
+    for code in ["alice-a", "alice-b", "alice-off"] {
+        storage
+            .create_with_code(code, "https://example.com/alice", Some("alice"))
+            .await
+            .unwrap();
+    }
+    storage.increment_clicks("alice-a", 3).await.unwrap();
+    storage.increment_clicks("alice-b", 4).await.unwrap();
+    storage.deactivate("alice-off").await.unwrap();
+    storage
+        .reserve_codes(
+            &["alice-later".to_string()],
+            Some("alice"),
+            None,
+            chrono::Utc::now().timestamp() + 3600,
+        )
+        .await
+        .unwrap();
+    storage
+        .add_alias("alice-a", "alice-alias", Some("alice"))
+        .await
+        .unwrap();
+    storage
+        .create_with_code("bob-a", "https://example.com/bob", Some("bob"))
+        .await
+        .unwrap();
+
+    storage
+        .upsert_user("alice", Some("alice@example.com"), "oauth")
+        .await
+        .unwrap();
+    storage
+        .upsert_user("alice", None, "cloudflare")
+        .await
+        .unwrap();
+    storage.promote_to_admin("alice", "oauth").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_profile_counts_links_by_state() {
+    let (state, storage) = create_test_state().await;
+    seed_alice(&storage).await;
+
+    let profile = get_user_profile(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(json!({ "sub": "root", "is_admin": true })),
+        Path("alice".to_string()),
+    )
+    .await
+    .unwrap();
+    let profile = serde_json::to_value(profile.0).unwrap();
+
+    assert_eq!(profile["user_id"], "alice");
+    assert_eq!(
+        profile["links"],
+        json!({ "active": 2, "inactive": 1, "reserved": 1, "aliases": 1, "total_clicks": 7 })
+    );
+    // Active links, the reservation and the alias all count toward the quota
+    assert_eq!(profile["quota"], json!({ "used": 4, "limit": 20 }));
+    assert_eq!(profile["is_manual_admin"], true);
+
+    let accounts = profile["accounts"].as_array().unwrap();
+    assert_eq!(accounts.len(), 2);
+    let oauth = accounts
+        .iter()
+        .find(|account| account["auth_method"] == "oauth")
+        .unwrap();
+    assert_eq!(oauth["email"], "alice@example.com");
+    assert_eq!(oauth["is_manual_admin"], true);
+
+    let recent: Vec<&str> = profile["recent_links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|link| link["short_code"].as_str().unwrap())
+        .collect();
+    assert_eq!(recent.len(), 5);
+    assert!(!recent.contains(&"bob-a"));
+    assert!(profile["recent_links"][0]["short_url"].is_string());
+}
+
+#[tokio::test]
+async fn test_admin_profile_is_admin_only_and_404s_for_unknown_users() {
+    let (state, storage) = create_test_state().await;
+    seed_alice(&storage).await;
+
+    let forbidden = get_user_profile(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(json!({ "sub": "bob" })),
+        Path("alice".to_string()),
+    )
+    .await;
+    assert_eq!(
+        forbidden.err().unwrap().status_code(),
+        StatusCode::FORBIDDEN
+    );
+
+    let missing = get_user_profile(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(json!({ "sub": "root", "is_admin": true })),
+        Path("nobody".to_string()),
+    )
+    .await;
+    assert_eq!(missing.err().unwrap().status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_me_returns_the_callers_profile() {
+    let (state, storage) = create_test_state().await;
+    seed_alice(&storage).await;
+
+    let profile = get_my_profile(
+        State(Arc::clone(&state)),
+        base(&state),
+        claims(json!({ "sub": "bob" })),
+    )
+    .await
+    .unwrap();
+    let profile = serde_json::to_value(profile.0).unwrap();
+    assert_eq!(profile["user_id"], "bob");
+    assert_eq!(profile["links"]["active"], 1);
+    assert_eq!(profile["accounts"], json!([]));
+    assert_eq!(profile["is_manual_admin"], false);
+
+    let anonymous = get_my_profile(State(Arc::clone(&state)), base(&state), Extension(None)).await;
+    assert_eq!(
+        anonymous.err().unwrap().status_code(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+async fn list(
+    state: &Arc<AppState>,
+    caller: Value,
+    inactive_days: Option<i64>,
+) -> Result<Value, StatusCode> {
+    list_users(
+        State(Arc::clone(state)),
+        claims(caller),
+        Query(UserListQuery {
+            limit: None,
+            page: 1,
+            inactive_days,
+        }),
+    )
+    .await
+    .map(|response| serde_json::to_value(response.0).unwrap())
+    .map_err(|error| error.status_code())
+}
+
+#[tokio::test]
+async fn test_admin_user_list_shows_last_seen_and_filters_inactive_users() {
+    let (state, storage) = create_test_state().await;
+    seed_alice(&storage).await;
+    let admin = json!({ "sub": "root", "is_admin": true });
+
+    assert_eq!(
+        list(&state, json!({ "sub": "bob" }), None).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+
+    let all = list(&state, admin.clone(), None).await.unwrap();
+    let users = all["users"].as_array().unwrap();
+    assert_eq!(users.len(), 2);
+    let now = chrono::Utc::now().timestamp();
+    for user in users {
+        assert!(user["last_seen_at"].as_i64().unwrap() > now - 60);
+    }
+
+    // Alice's Cloudflare sign-in has not been used in a year
+    sqlx::query(
+        "UPDATE users SET last_seen_at = last_seen_at - 365 * 86400 WHERE auth_method = 'cloudflare'",
+    )
+    .execute(storage.pool.as_ref())
+    .await
+    .unwrap();
+    let inactive = list(&state, admin.clone(), Some(180)).await.unwrap();
+    assert_eq!(inactive["inactive_days"], 180);
+    let users = inactive["users"].as_array().unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["user_id"], "alice");
+    assert_eq!(users[0]["auth_method"], "cloudflare");
+
+    assert_eq!(
+        list(&state, admin, Some(-1)).await,
+        Err(StatusCode::BAD_REQUEST)
+    );
+}
@@ -0,0 +1,181 @@
+//! Integration tests for per-user API request accounting: the auth
+//! middleware counts requests by endpoint class, `GET
+//! /api/admin/users/{user_id}/usage` reports them per day before and after
+//! a flush, and the admin user list sums them per user.
+#![cfg(feature = "sqlite")]
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use lynx::api::{self, server_info::RuntimeFacts, server_info::ServerInfo};
+use lynx::auth::usage::ApiUsageTracker;
+use lynx::auth::AuthService;
+use lynx::clock::system_clock;
+use lynx::config::{AuthConfig, AuthMode};
+use lynx::storage::{SqliteStorage, Storage};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+
+/// The user every request is made as when authentication is off
+const LEGACY_USER: &str = "00000000-0000-0000-0000-000000000000";
+
+async fn create_app() -> (Router, Arc<ApiUsageTracker>, Arc<dyn Storage>) {
+    let storage = SqliteStorage::new("sqlite::memory:", 5).await.unwrap();
+    storage.init().await.unwrap();
+    let storage: Arc<dyn Storage> = Arc::new(storage);
+    let auth_service = Arc::new(
+        AuthService::new(AuthConfig {
+            mode: AuthMode::None,
+            oauth: None,
+            cloudflare: None,
+        })
+        .await
+        .unwrap(),
+    );
+    let config = Arc::new(common::test_config());
+    let usage = Arc::new(ApiUsageTracker::new(Arc::clone(&storage), system_clock()));
+    let app = api::create_api_router_with_usage(
+        Arc::clone(&storage),
+        auth_service,
+        Arc::clone(&config),
+        None,
+        None,
+        None,
+        Arc::new(ServerInfo::new(&config, &RuntimeFacts::default())),
+        None,
+        Some(Arc::clone(&usage)),
+    );
+    (app, usage, storage)
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Two creates, two reads, a search and two analytics reads. Codes in
+/// paths are base64url-encoded (`ZG9jcw` is `docs`).
+async fn make_traffic(app: &Router) {
+    for code in ["docs", "blog"] {
+        let (status, _) = send(
+            app,
+            "POST",
+            "/api/urls",
+            Some(json!({ "url": "https://example.com/", "custom_code": code })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    for uri in [
+        "/api/urls",
+        "/api/urls/ZG9jcw",
+        "/api/urls/search?q=example",
+        "/api/analytics/ZG9jcw",
+        "/api/links/ZG9jcw/clicks/history",
+    ] {
+        let (status, _) = send(app, "GET", uri, None).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn test_usage_is_counted_per_class_before_and_after_a_flush() {
+    let (app, usage, storage) = create_app().await;
+    make_traffic(&app).await;
+
+    let (status, report) = send(
+        &app,
+        "GET",
+        &format!("/api/admin/users/{LEGACY_USER}/usage"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["days"], json!(30));
+    // The report request itself is a read
+    let expected_total = json!({
+        "create_requests": 2,
+        "read_requests": 3,
+        "search_requests": 1,
+        "analytics_requests": 2,
+    });
+    assert_eq!(report["total"], expected_total);
+    assert_eq!(report["total_requests"], json!(8));
+    let daily = report["daily"].as_array().unwrap();
+    assert_eq!(daily.len(), 1);
+    assert_eq!(daily[0]["total"], json!(8));
+
+    // Flushed counts read back the same, plus the second report request
+    assert_eq!(usage.flush().await, 1);
+    assert_eq!(
+        storage.user_usage(LEGACY_USER, 0).await.unwrap()[0]
+            .counts
+            .total(),
+        8
+    );
+    let (_, report) = send(
+        &app,
+        "GET",
+        &format!("/api/admin/users/{LEGACY_USER}/usage?days=1"),
+        None,
+    )
+    .await;
+    assert_eq!(report["total"]["read_requests"], json!(4));
+    assert_eq!(report["total_requests"], json!(9));
+    assert_eq!(report["daily"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_user_list_sums_usage_per_user() {
+    let (app, usage, _) = create_app().await;
+    make_traffic(&app).await;
+    usage.flush().await;
+    send(&app, "GET", "/api/urls", None).await;
+
+    let (status, list) = send(&app, "GET", "/api/admin/users", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list["usage_days"], json!(30));
+    let users = list["users"].as_array().unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["user_id"], json!(LEGACY_USER));
+    // Seven flushed, one pending and the list request itself
+    assert_eq!(
+        users[0]["api_requests"],
+        json!({
+            "create_requests": 2,
+            "read_requests": 4,
+            "search_requests": 1,
+            "analytics_requests": 2,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_usage_days_are_bounded() {
+    let (app, _, _) = create_app().await;
+    for days in [0, 367] {
+        let (status, _) = send(
+            &app,
+            "GET",
+            &format!("/api/admin/users/{LEGACY_USER}/usage?days={days}"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "days={days}");
+    }
+}